@@ -17,6 +17,7 @@
 # Rust: starlark benchmark.py
 
 REPEAT_100M = 100000000
+REPEAT_10M = 10000000
 
 
 def benchmark_loop():
@@ -47,6 +48,14 @@ def benchmark_call_native_1pos():
     return y
 
 
+def benchmark_call_method_1pos():
+    y = 0
+    xs = {}
+    for x in range(REPEAT_100M):
+        y = xs.get(x)
+    return y
+
+
 def op4(_x):
     pass
 
@@ -91,4 +100,29 @@ def benchmark_call_def_8name():
     return y
 
 
+# Dict literal with constant keys, as in the attributes of generated BUILD rules.
+def benchmark_dict_const_keys():
+    y = None
+    for x in range(REPEAT_10M):
+        y = {
+            "name": "target",
+            "srcs": x,
+            "deps": x,
+            "visibility": x,
+            "testonly": False,
+            "licenses": x,
+            "tags": x,
+            "copts": x,
+            "linkopts": x,
+            "defines": x,
+            "includes": x,
+            "hdrs": x,
+            "data": x,
+            "features": x,
+            "compatible_with": x,
+            "target_compatible_with": x,
+        }
+    return y
+
+
 print(benchmark_call_def_1name())
@@ -19,7 +19,17 @@
 
 use std::collections::HashSet;
 
+pub use cache::AnalysisCache;
+pub use driver::AnalysisDriver;
+pub use fix::apply_lint_fixes;
 pub use lint_message::LintMessage;
+pub use scope::AstModuleScopeAnalysis;
+pub use scope::ScopeAnalysis;
+pub use scope::ScopeBinding;
+pub use scope::ScopeBindingId;
+pub use scope::ScopeReference;
+pub use scope::ScopeReferenceTarget;
+pub use slice::AstModuleSlice;
 pub use types::EvalMessage;
 pub use types::EvalSeverity;
 pub use types::Lint;
@@ -28,13 +38,20 @@ pub use unused_loads::remove::remove_unused_loads;
 use crate::analysis::types::LintT;
 use crate::syntax::AstModule;
 
+mod cache;
+mod constant;
+mod driver;
 mod dubious;
 pub mod find_call_name;
+mod fix;
 mod flow;
+mod idiom;
 mod incompatible;
 mod lint_message;
 mod names;
 mod performance;
+mod scope;
+mod slice;
 mod types;
 mod underscore;
 mod unused_loads;
@@ -53,9 +70,11 @@ impl AstModuleLint for AstModule {
         res.extend(flow::lint(self).into_iter().map(LintT::erase));
         res.extend(incompatible::lint(self).into_iter().map(LintT::erase));
         res.extend(dubious::lint(self).into_iter().map(LintT::erase));
+        res.extend(constant::lint(self).into_iter().map(LintT::erase));
         res.extend(names::lint(self, globals).into_iter().map(LintT::erase));
         res.extend(underscore::lint(self).into_iter().map(LintT::erase));
         res.extend(performance::lint(self).into_iter().map(LintT::erase));
+        res.extend(idiom::lint(self).into_iter().map(LintT::erase));
         res.retain(|issue| !self.is_suppressed(&issue.short_name, issue.location.span));
         res
     }
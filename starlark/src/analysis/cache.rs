@@ -0,0 +1,137 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! On-disk cache of per-file analysis results.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::syntax::Dialect;
+
+/// Stores results of analyzing a file in a directory, keyed by a SHA-256 digest
+/// of the file path, the file content and a configuration string.
+/// The file content itself is not stored.
+///
+/// The configuration string must change whenever the analysis may produce a different
+/// result for the same file content, for example when the tool version, the dialect
+/// or the set of globals changes. Entries which do not match exactly are ignored,
+/// so stale or corrupt entries never produce wrong results, they are just recomputed.
+///
+/// Use with [`AnalysisDriver::analyze_paths_cached`](crate::analysis::AnalysisDriver::analyze_paths_cached).
+#[derive(Debug, Clone)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+    config: String,
+}
+
+/// Entry stores its key, so an entry renamed or copied to another name is a cache miss.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry<S, T> {
+    key: S,
+    result: T,
+}
+
+impl AnalysisCache {
+    /// Cache in directory `dir`, which is created if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>, config: impl Into<String>) -> AnalysisCache {
+        AnalysisCache {
+            dir: dir.into(),
+            config: config.into(),
+        }
+    }
+
+    /// Cache with the same directory, and the dialect added to the configuration.
+    pub(crate) fn for_dialect(&self, dialect: &Dialect) -> AnalysisCache {
+        AnalysisCache {
+            dir: self.dir.clone(),
+            config: format!("{}\n{:?}", self.config, dialect),
+        }
+    }
+
+    /// Hex SHA-256 of the path, the configuration and the content,
+    /// each prefixed with its length so the parts cannot run into each other.
+    fn key(&self, path: &str, content: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [path, self.config.as_str(), content] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        let mut key = String::with_capacity(64);
+        for b in hasher.finalize() {
+            write!(key, "{:02x}", b).unwrap();
+        }
+        key
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Cached result of analyzing `content` of file `path`, if any.
+    pub(crate) fn get<T: DeserializeOwned>(&self, path: &str, content: &str) -> Option<T> {
+        let key = self.key(path, content);
+        let data = fs::read(self.entry_path(&key)).ok()?;
+        let entry: CacheEntry<String, T> = serde_json::from_slice(&data).ok()?;
+        if entry.key == key {
+            Some(entry.result)
+        } else {
+            None
+        }
+    }
+
+    /// Store the result of analyzing `content` of file `path`.
+    ///
+    /// Failure to write the cache is not an error, the result is just not cached.
+    pub(crate) fn put<T: Serialize>(&self, path: &str, content: &str, result: &T) {
+        let key = self.key(path, content);
+        let entry = CacheEntry {
+            key: key.as_str(),
+            result,
+        };
+        let Ok(data) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        let _ignore = fs::create_dir_all(&self.dir);
+        // Write to a unique temporary file and rename, so concurrent readers
+        // (including other processes) never observe a partially written entry.
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let target = self.entry_path(&key);
+        let tmp = target.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        if fs::write(&tmp, data).is_err() || fs::rename(&tmp, &target).is_err() {
+            let _ignore = fs::remove_file(&tmp);
+        }
+    }
+
+    /// The cache directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
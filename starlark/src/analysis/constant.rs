@@ -0,0 +1,656 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Flag conditions whose value is known statically, given literals and module constants.
+//!
+//! A module constant is a top-level variable assigned exactly once, directly at the top level,
+//! from an expression which is itself constant. Any other binding of the same name anywhere
+//! in the module (e.g. a parameter, a loop variable or a `load`) makes it non-constant.
+//! A variable holding a list is also non-constant if it is used anywhere other than
+//! in a condition or comparison (e.g. `FLAGS.append(x)`, `f(FLAGS)` or `y = FLAGS`),
+//! since the list may be mutated.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+
+use starlark_syntax::syntax::ast::AssignTarget;
+use starlark_syntax::syntax::ast::AstAssignIdent;
+use starlark_syntax::syntax::ast::AstExpr;
+use starlark_syntax::syntax::ast::AstLiteral;
+use starlark_syntax::syntax::ast::AstParameter;
+use starlark_syntax::syntax::ast::AstStmt;
+use starlark_syntax::syntax::ast::BinOp;
+use starlark_syntax::syntax::ast::Clause;
+use starlark_syntax::syntax::ast::Expr;
+use starlark_syntax::syntax::ast::Parameter;
+use starlark_syntax::syntax::ast::Stmt;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::uniplate::Visit;
+use thiserror::Error;
+
+use crate::analysis::types::LintT;
+use crate::analysis::types::LintWarning;
+use crate::analysis::EvalSeverity;
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::syntax::AstModule;
+use crate::values::string::repr::string_repr;
+
+#[derive(Error, Debug)]
+pub(crate) enum ConstantCondition {
+    #[error("Condition `{0}` is always {1}{}", Reasons(.2))]
+    AlwaysSame(String, bool, Vec<Reason>),
+}
+
+impl LintWarning for ConstantCondition {
+    fn severity(&self) -> EvalSeverity {
+        EvalSeverity::Warning
+    }
+
+    fn short_name(&self) -> &'static str {
+        match self {
+            ConstantCondition::AlwaysSame(..) => "constant-condition",
+        }
+    }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            ConstantCondition::AlwaysSame(x, b, reasons) => {
+                vec![x.clone(), b.to_string(), Reasons(reasons).to_string()]
+            }
+        }
+    }
+}
+
+/// One step of the reasoning: a constant and where it was assigned.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Reason {
+    name: String,
+    value: Const,
+    location: FileSpan,
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is `{}` (assigned at {})",
+            self.name, self.value, self.location
+        )
+    }
+}
+
+struct Reasons<'a>(&'a [Reason]);
+
+impl Display for Reasons<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, reason) in self.0.iter().enumerate() {
+            f.write_str(if i == 0 { ", because " } else { ", and " })?;
+            Display::fmt(reason, f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Const {
+    None,
+    Bool(bool),
+    Int(i32),
+    String(String),
+    List(Vec<Const>),
+    Tuple(Vec<Const>),
+}
+
+impl Display for Const {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn items(f: &mut fmt::Formatter<'_>, xs: &[Const]) -> fmt::Result {
+            for (i, x) in xs.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(", ")?;
+                }
+                Display::fmt(x, f)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Const::None => f.write_str("None"),
+            Const::Bool(true) => f.write_str("True"),
+            Const::Bool(false) => f.write_str("False"),
+            Const::Int(x) => write!(f, "{}", x),
+            Const::String(x) => {
+                let mut s = String::new();
+                string_repr(x, &mut s);
+                f.write_str(&s)
+            }
+            Const::List(xs) => {
+                f.write_str("[")?;
+                items(f, xs)?;
+                f.write_str("]")
+            }
+            Const::Tuple(xs) => {
+                f.write_str("(")?;
+                items(f, xs)?;
+                if xs.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl Const {
+    fn to_bool(&self) -> bool {
+        match self {
+            Const::None => false,
+            Const::Bool(x) => *x,
+            Const::Int(x) => *x != 0,
+            Const::String(x) => !x.is_empty(),
+            Const::List(xs) | Const::Tuple(xs) => !xs.is_empty(),
+        }
+    }
+
+    fn contains(&self, x: &Const) -> Option<bool> {
+        match (self, x) {
+            (Const::List(xs) | Const::Tuple(xs), x) => Some(xs.contains(x)),
+            (Const::String(s), Const::String(x)) => Some(s.contains(x.as_str())),
+            _ => None,
+        }
+    }
+
+    /// Whether the value contains a list, which may be mutated.
+    fn is_mutable(&self) -> bool {
+        match self {
+            Const::List(_) => true,
+            Const::Tuple(xs) => xs.iter().any(|x| x.is_mutable()),
+            Const::None | Const::Bool(_) | Const::Int(_) | Const::String(_) => false,
+        }
+    }
+
+    fn compare(&self, x: &Const) -> Option<std::cmp::Ordering> {
+        match (self, x) {
+            (Const::Int(a), Const::Int(b)) => Some(a.cmp(b)),
+            (Const::String(a), Const::String(b)) => Some(a.cmp(b)),
+            (Const::Bool(a), Const::Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+/// A constant value, together with the constants it was derived from.
+struct Derived {
+    value: Const,
+    reasons: Vec<Reason>,
+}
+
+impl Derived {
+    fn literal(value: Const) -> Derived {
+        Derived {
+            value,
+            reasons: Vec::new(),
+        }
+    }
+
+    fn with(mut self, reasons: &[Reason]) -> Derived {
+        for r in reasons {
+            if !self.reasons.contains(r) {
+                self.reasons.push(r.clone());
+            }
+        }
+        self
+    }
+
+    /// The value of `next`, having first reasoned about `self`.
+    fn then(self, next: Derived) -> Derived {
+        Derived {
+            value: next.value,
+            reasons: self.reasons,
+        }
+        .with(&next.reasons)
+    }
+}
+
+struct Constants<'a> {
+    /// How many times each name is bound anywhere in the module.
+    bindings: HashMap<&'a str, usize>,
+    /// Names used other than in a condition or comparison,
+    /// so their value may be mutated or aliased.
+    escaping: HashSet<&'a str>,
+    /// Known constants, with the reasoning that determined their value.
+    values: HashMap<&'a str, Vec<Reason>>,
+}
+
+impl<'a> Constants<'a> {
+    fn new(module: &'a AstModule) -> Constants<'a> {
+        let mut res = Constants {
+            bindings: HashMap::new(),
+            escaping: HashSet::new(),
+            values: HashMap::new(),
+        };
+        res.collect_bindings_stmt(module.statement());
+        res.collect_escaping_stmt(module.statement());
+
+        let codemap = module.codemap();
+        let top_level: &[AstStmt] = match &**module.statement() {
+            Stmt::Statements(xs) => xs,
+            _ => std::slice::from_ref(module.statement()),
+        };
+        for stmt in top_level {
+            if let Stmt::Assign(assign) = &**stmt {
+                if let AssignTarget::Identifier(name) = &*assign.lhs {
+                    if res.bindings.get(name.ident.as_str()) == Some(&1) {
+                        if let Some(v) = res.eval(&assign.rhs) {
+                            if v.value.is_mutable()
+                                && res.escaping.contains(name.ident.as_str())
+                            {
+                                continue;
+                            }
+                            let mut reasons = vec![Reason {
+                                name: name.ident.clone(),
+                                value: v.value,
+                                location: codemap.file_span(stmt.span),
+                            }];
+                            reasons.extend(v.reasons);
+                            res.values.insert(&name.ident, reasons);
+                        }
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    fn bind(&mut self, x: &'a AstAssignIdent) {
+        *self.bindings.entry(&x.ident).or_default() += 1;
+    }
+
+    fn bind_params(&mut self, params: &'a [AstParameter]) {
+        for p in params {
+            match &**p {
+                Parameter::Normal(x, _)
+                | Parameter::WithDefaultValue(x, _, _)
+                | Parameter::Args(x, _)
+                | Parameter::KwArgs(x, _) => self.bind(x),
+                Parameter::NoArgs => {}
+            }
+        }
+    }
+
+    fn collect_bindings_stmt(&mut self, x: &'a AstStmt) {
+        match &**x {
+            Stmt::Assign(x) => x.lhs.visit_lvalue(|x| self.bind(x)),
+            Stmt::AssignModify(lhs, _, _) => lhs.visit_lvalue(|x| self.bind(x)),
+            Stmt::For(x) => x.var.visit_lvalue(|x| self.bind(x)),
+            Stmt::Def(x) => {
+                self.bind(&x.name);
+                self.bind_params(&x.params);
+            }
+            Stmt::Load(x) => {
+                for arg in &x.args {
+                    self.bind(&arg.local);
+                }
+            }
+            _ => {}
+        }
+        x.visit_stmt(|x| self.collect_bindings_stmt(x));
+        x.visit_expr(|x| self.collect_bindings_expr(x));
+    }
+
+    fn collect_bindings_expr(&mut self, x: &'a AstExpr) {
+        match &**x {
+            Expr::Lambda(x) => self.bind_params(&x.params),
+            Expr::ListComprehension(_, for_, clauses)
+            | Expr::DictComprehension(_, for_, clauses) => {
+                for_.var.visit_lvalue(|x| self.bind(x));
+                for clause in clauses {
+                    if let Clause::For(x) = clause {
+                        x.var.visit_lvalue(|x| self.bind(x));
+                    }
+                }
+            }
+            _ => {}
+        }
+        x.visit_expr(|x| self.collect_bindings_expr(x));
+    }
+
+    fn collect_escaping_stmt(&mut self, x: &'a AstStmt) {
+        match &**x {
+            Stmt::If(cond, _) | Stmt::IfElse(cond, _) => self.collect_escaping_expr(cond, true),
+            Stmt::Expression(e) => self.collect_escaping_expr(e, true),
+            _ => x.visit_children(|x| match x {
+                Visit::Expr(x) => self.collect_escaping_expr(x, false),
+                Visit::Stmt(_) => {}
+            }),
+        }
+        x.visit_stmt(|x| self.collect_escaping_stmt(x));
+    }
+
+    /// `tested` is true if the value of `x` is only tested for truth or compared.
+    fn collect_escaping_expr(&mut self, x: &'a AstExpr, tested: bool) {
+        match &**x {
+            Expr::Identifier(x) => {
+                if !tested {
+                    self.escaping.insert(&x.ident);
+                }
+            }
+            Expr::Not(x) => self.collect_escaping_expr(x, true),
+            Expr::Op(lhs, BinOp::And | BinOp::Or, rhs) => {
+                self.collect_escaping_expr(lhs, tested);
+                self.collect_escaping_expr(rhs, tested);
+            }
+            Expr::Op(
+                lhs,
+                BinOp::Equal
+                | BinOp::NotEqual
+                | BinOp::Less
+                | BinOp::Greater
+                | BinOp::LessOrEqual
+                | BinOp::GreaterOrEqual
+                | BinOp::In
+                | BinOp::NotIn,
+                rhs,
+            ) => {
+                self.collect_escaping_expr(lhs, true);
+                self.collect_escaping_expr(rhs, true);
+            }
+            Expr::If(x) => {
+                let (cond, then, els) = &**x;
+                self.collect_escaping_expr(cond, true);
+                self.collect_escaping_expr(then, tested);
+                self.collect_escaping_expr(els, tested);
+            }
+            _ => x.visit_expr(|x| self.collect_escaping_expr(x, false)),
+        }
+    }
+
+    fn eval_ident(&self, name: &str) -> Option<Derived> {
+        if let Some(reasons) = self.values.get(name) {
+            return Some(Derived {
+                value: reasons[0].value.clone(),
+                reasons: reasons.clone(),
+            });
+        }
+        if self.bindings.contains_key(name) {
+            return None;
+        }
+        match name {
+            "None" => Some(Derived::literal(Const::None)),
+            "True" => Some(Derived::literal(Const::Bool(true))),
+            "False" => Some(Derived::literal(Const::Bool(false))),
+            _ => None,
+        }
+    }
+
+    fn eval_list(&self, xs: &[AstExpr]) -> Option<(Vec<Const>, Vec<Reason>)> {
+        let mut values = Vec::with_capacity(xs.len());
+        let mut reasons = Vec::new();
+        for x in xs {
+            let x = self.eval(x)?;
+            values.push(x.value);
+            for r in x.reasons {
+                if !reasons.contains(&r) {
+                    reasons.push(r);
+                }
+            }
+        }
+        Some((values, reasons))
+    }
+
+    fn eval(&self, x: &AstExpr) -> Option<Derived> {
+        match &**x {
+            Expr::Literal(AstLiteral::Int(x)) => match x.node {
+                starlark_syntax::lexer::TokenInt::I32(i) => Some(Derived::literal(Const::Int(i))),
+                _ => None,
+            },
+            Expr::Literal(AstLiteral::String(x)) => {
+                Some(Derived::literal(Const::String(x.node.clone())))
+            }
+            Expr::Literal(_) => None,
+            Expr::Identifier(x) => self.eval_ident(&x.ident),
+            Expr::List(xs) => {
+                let (values, reasons) = self.eval_list(xs)?;
+                Some(Derived::literal(Const::List(values)).with(&reasons))
+            }
+            Expr::Tuple(xs) => {
+                let (values, reasons) = self.eval_list(xs)?;
+                Some(Derived::literal(Const::Tuple(values)).with(&reasons))
+            }
+            Expr::Not(x) => {
+                let x = self.eval(x)?;
+                Some(Derived::literal(Const::Bool(!x.value.to_bool())).with(&x.reasons))
+            }
+            Expr::Minus(x) => {
+                let x = self.eval(x)?;
+                match x.value {
+                    Const::Int(i) => {
+                        Some(Derived::literal(Const::Int(i.checked_neg()?)).with(&x.reasons))
+                    }
+                    _ => None,
+                }
+            }
+            Expr::Op(lhs, BinOp::And, rhs) => {
+                let lhs = self.eval(lhs)?;
+                if !lhs.value.to_bool() {
+                    Some(lhs)
+                } else {
+                    Some(lhs.then(self.eval(rhs)?))
+                }
+            }
+            Expr::Op(lhs, BinOp::Or, rhs) => {
+                let lhs = self.eval(lhs)?;
+                if lhs.value.to_bool() {
+                    Some(lhs)
+                } else {
+                    Some(lhs.then(self.eval(rhs)?))
+                }
+            }
+            Expr::Op(lhs, op, rhs) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                let res = match op {
+                    BinOp::Equal => lhs.value == rhs.value,
+                    BinOp::NotEqual => lhs.value != rhs.value,
+                    BinOp::Less => lhs.value.compare(&rhs.value)?.is_lt(),
+                    BinOp::Greater => lhs.value.compare(&rhs.value)?.is_gt(),
+                    BinOp::LessOrEqual => lhs.value.compare(&rhs.value)?.is_le(),
+                    BinOp::GreaterOrEqual => lhs.value.compare(&rhs.value)?.is_ge(),
+                    BinOp::In => rhs.value.contains(&lhs.value)?,
+                    BinOp::NotIn => !rhs.value.contains(&lhs.value)?,
+                    _ => return None,
+                };
+                Some(
+                    Derived::literal(Const::Bool(res))
+                        .with(&lhs.reasons)
+                        .with(&rhs.reasons),
+                )
+            }
+            Expr::If(x) => {
+                let (cond, then, els) = &**x;
+                let cond = self.eval(cond)?;
+                let branch = if cond.value.to_bool() { then } else { els };
+                Some(cond.then(self.eval(branch)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Conditions written as a bare literal (e.g. `if False:`) are deliberate, so don't flag them.
+fn is_deliberate(x: &AstExpr) -> bool {
+    match &**x {
+        Expr::Literal(_) => true,
+        Expr::Identifier(x) => matches!(x.ident.as_str(), "True" | "False" | "None"),
+        _ => false,
+    }
+}
+
+fn check(
+    constants: &Constants,
+    cond: &AstExpr,
+    codemap: &CodeMap,
+    res: &mut Vec<LintT<ConstantCondition>>,
+) {
+    if is_deliberate(cond) {
+        return;
+    }
+    if let Some(v) = constants.eval(cond) {
+        res.push(LintT::new(
+            codemap,
+            cond.span,
+            ConstantCondition::AlwaysSame(
+                codemap.source_span(cond.span).to_owned(),
+                v.value.to_bool(),
+                v.reasons,
+            ),
+        ));
+    }
+}
+
+fn stmt(
+    constants: &Constants,
+    x: &AstStmt,
+    codemap: &CodeMap,
+    res: &mut Vec<LintT<ConstantCondition>>,
+) {
+    match &**x {
+        Stmt::If(cond, _) | Stmt::IfElse(cond, _) => check(constants, cond, codemap, res),
+        _ => {}
+    }
+    x.visit_stmt(|x| stmt(constants, x, codemap, res));
+}
+
+fn expr(
+    constants: &Constants,
+    x: &AstExpr,
+    codemap: &CodeMap,
+    res: &mut Vec<LintT<ConstantCondition>>,
+) {
+    match &**x {
+        Expr::If(x) => check(constants, &x.0, codemap, res),
+        Expr::ListComprehension(_, _, clauses) | Expr::DictComprehension(_, _, clauses) => {
+            for clause in clauses {
+                if let Clause::If(cond) = clause {
+                    check(constants, cond, codemap, res);
+                }
+            }
+        }
+        _ => {}
+    }
+    x.visit_expr(|x| expr(constants, x, codemap, res));
+}
+
+pub(crate) fn lint(module: &AstModule) -> Vec<LintT<ConstantCondition>> {
+    let constants = Constants::new(module);
+    let mut res = Vec::new();
+    stmt(&constants, module.statement(), module.codemap(), &mut res);
+    module
+        .statement()
+        .visit_expr(|x| expr(&constants, x, module.codemap(), &mut res));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark_syntax::slice_vec_ext::SliceExt;
+
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn lint_messages(x: &str) -> Vec<String> {
+        let m = AstModule::parse("X", x.to_owned(), &Dialect::Extended).unwrap();
+        lint(&m).map(|x| x.problem.to_string())
+    }
+
+    #[test]
+    fn test_constant_condition() {
+        let res = lint_messages(
+            r#"
+DEBUG = False
+MODE = "opt"
+PROD = not DEBUG and MODE == "opt"
+def f():
+    if DEBUG:
+        pass
+    if PROD or g():
+        pass
+    if MODE in ["dbg", "dev"]:
+        pass
+    return 1 if MODE != "opt" else 2
+"#,
+        );
+        assert_eq!(
+            res,
+            &[
+                "Condition `DEBUG` is always false, because `DEBUG` is `False` (assigned at X:2:1-14)",
+                "Condition `PROD or g()` is always true, because `PROD` is `True` (assigned at X:4:1-35), and `DEBUG` is `False` (assigned at X:2:1-14), and `MODE` is `\"opt\"` (assigned at X:3:1-13)",
+                "Condition `MODE in [\"dbg\", \"dev\"]` is always false, because `MODE` is `\"opt\"` (assigned at X:3:1-13)",
+                "Condition `MODE != \"opt\"` is always false, because `MODE` is `\"opt\"` (assigned at X:3:1-13)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_condition_not_constant() {
+        let res = lint_messages(
+            r#"
+REASSIGNED = False
+REASSIGNED = True
+AUGMENTED = 1
+AUGMENTED += 1
+SHADOWED = False
+CALLED = f()
+load("x.bzl", LOADED = "y")
+if True:
+    pass
+if REASSIGNED or AUGMENTED or CALLED or LOADED:
+    pass
+def g(SHADOWED):
+    if SHADOWED:
+        pass
+[x for x in [] if False]
+"#,
+        );
+        assert_eq!(res, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_constant_condition_mutated() {
+        let res = lint_messages(
+            r#"
+FLAGS = []
+FLAGS.append("-O2")
+PASSED = []
+register(PASSED)
+ALIASED = []
+alias = ALIASED
+TESTED = []
+if FLAGS or PASSED or ALIASED:
+    pass
+if TESTED:
+    pass
+"#,
+        );
+        assert_eq!(
+            res,
+            &["Condition `TESTED` is always false, because `TESTED` is `[]` (assigned at X:8:1-12)"]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Analyze many files in parallel.
+
+use std::collections::HashSet;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::analysis::AnalysisCache;
+use crate::analysis::AstModuleLint;
+use crate::analysis::Lint;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+/// Parse and analyze many files on a pool of threads.
+///
+/// Results are returned in the order of the inputs regardless of the number of threads,
+/// so the output is deterministic. Data shared between files (for example the set of
+/// global names, or [`Globals`](crate::environment::Globals) for typechecking)
+/// is captured by reference by the analysis function.
+///
+/// ```
+/// use starlark::analysis::AnalysisDriver;
+/// use starlark::syntax::Dialect;
+///
+/// let driver = AnalysisDriver::new(Dialect::Extended);
+/// let sources = vec![
+///     ("a.star".to_owned(), "x = 1".to_owned()),
+///     ("b.star".to_owned(), "def f(:".to_owned()),
+/// ];
+/// let results = driver.analyze_sources(sources, |module| module.stmt_locations().len());
+/// assert_eq!(results[0].as_ref().ok(), Some(&1));
+/// assert!(results[1].is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnalysisDriver {
+    dialect: Dialect,
+    threads: NonZeroUsize,
+}
+
+impl AnalysisDriver {
+    /// Create a driver which parses files with the given dialect,
+    /// using as many threads as there are available CPUs.
+    pub fn new(dialect: Dialect) -> AnalysisDriver {
+        AnalysisDriver {
+            dialect,
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+
+    /// Set the maximum number of threads used for analysis.
+    pub fn with_threads(mut self, threads: NonZeroUsize) -> AnalysisDriver {
+        self.threads = threads;
+        self
+    }
+
+    /// Parse the files at `paths` and run `analyze` on each successfully parsed module.
+    pub fn analyze_paths<T: Send>(
+        &self,
+        paths: &[PathBuf],
+        analyze: impl Fn(&AstModule) -> T + Sync,
+    ) -> Vec<crate::Result<T>> {
+        self.run(paths.len(), |i| {
+            Ok(analyze(&AstModule::parse_file(&paths[i], &self.dialect)?))
+        })
+    }
+
+    /// Parse the `(filename, content)` pairs and run `analyze` on each successfully parsed module.
+    pub fn analyze_sources<T: Send>(
+        &self,
+        sources: Vec<(String, String)>,
+        analyze: impl Fn(&AstModule) -> T + Sync,
+    ) -> Vec<crate::Result<T>> {
+        // Each source is taken exactly once by the worker which claims its index.
+        let sources: Vec<_> = sources.into_iter().map(|x| Mutex::new(Some(x))).collect();
+        self.run(sources.len(), |i| {
+            let (filename, content) = sources[i].lock().unwrap().take().unwrap();
+            Ok(analyze(&AstModule::parse(
+                &filename,
+                content,
+                &self.dialect,
+            )?))
+        })
+    }
+
+    /// Like [`analyze_paths`](Self::analyze_paths), but reuse results stored in `cache`
+    /// for files whose content has not changed, and store new results in `cache`.
+    ///
+    /// Files which fail to parse are not cached.
+    pub fn analyze_paths_cached<T: Serialize + DeserializeOwned + Send>(
+        &self,
+        paths: &[PathBuf],
+        cache: &AnalysisCache,
+        analyze: impl Fn(&AstModule) -> T + Sync,
+    ) -> Vec<crate::Result<T>> {
+        let cache = cache.for_dialect(&self.dialect);
+        self.run(paths.len(), |i| {
+            let path = &paths[i];
+            let content = fs::read_to_string(path).map_err(anyhow::Error::new)?;
+            let filename = path.to_string_lossy();
+            if let Some(result) = cache.get(&filename, &content) {
+                return Ok(result);
+            }
+            let module = AstModule::parse(&filename, content.clone(), &self.dialect)?;
+            let result = analyze(&module);
+            cache.put(&filename, &content, &result);
+            Ok(result)
+        })
+    }
+
+    /// Lint the files at `paths`, see [`AstModuleLint::lint`].
+    pub fn lint_paths(
+        &self,
+        paths: &[PathBuf],
+        globals: Option<&HashSet<String>>,
+    ) -> Vec<crate::Result<Vec<Lint>>> {
+        self.analyze_paths(paths, |module| module.lint(globals))
+    }
+
+    /// Run `job` for indices `0..count` on the thread pool, collecting results in order.
+    fn run<T: Send>(
+        &self,
+        count: usize,
+        job: impl Fn(usize) -> crate::Result<T> + Sync,
+    ) -> Vec<crate::Result<T>> {
+        let threads = self.threads.get().min(count);
+        if threads <= 1 {
+            return (0..count).map(job).collect();
+        }
+
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<(usize, crate::Result<T>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= count {
+                                return done;
+                            }
+                            done.push((i, job(i)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().unwrap())
+                .collect()
+        });
+        results.sort_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, r)| r).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use crate::analysis::AnalysisCache;
+    use crate::analysis::AnalysisDriver;
+    use crate::analysis::AstModuleLint;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn sources(n: usize) -> Vec<(String, String)> {
+        (0..n)
+            .map(|i| {
+                let content = if i % 7 == 3 {
+                    "def broken(:".to_owned()
+                } else {
+                    format!("def f{i}():\n    x = {i}\n    return 1\n")
+                };
+                (format!("f{i}.star"), content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ordered_output() {
+        for threads in [1, 2, 8] {
+            let driver = AnalysisDriver::new(Dialect::Extended)
+                .with_threads(NonZeroUsize::new(threads).unwrap());
+            let results = driver
+                .analyze_sources(sources(50), |m| m.stmt_locations()[0].filename().to_owned());
+            assert_eq!(50, results.len());
+            for (i, r) in results.iter().enumerate() {
+                match r {
+                    Ok(name) => assert_eq!(&format!("f{i}.star"), name),
+                    Err(e) => {
+                        assert_eq!(3, i % 7);
+                        assert!(e.to_string().contains(&format!("f{i}.star")));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lint() {
+        let driver = AnalysisDriver::new(Dialect::Extended);
+        let lints = driver.analyze_sources(sources(10), |m| {
+            m.lint(None)
+                .into_iter()
+                .map(|l| l.short_name)
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            &vec!["unused-assign".to_owned()],
+            lints[0].as_ref().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_missing_path() {
+        let driver = AnalysisDriver::new(Dialect::Extended);
+        let results = driver.lint_paths(&[PathBuf::from("/definitely/not/here.star")], None);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn test_cached() {
+        let dir = std::env::temp_dir().join(format!(
+            "starlark-analysis-cache-test-{}",
+            std::process::id()
+        ));
+        let file = dir.join("src").join("a.star");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "x = 1\ny = 2\n").unwrap();
+
+        let calls = AtomicUsize::new(0);
+        let analyze = |m: &AstModule| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            m.stmt_locations().len()
+        };
+        let driver = AnalysisDriver::new(Dialect::Extended);
+        let run = |config: &str| {
+            let cache = AnalysisCache::new(dir.join("cache"), config);
+            let results = driver.analyze_paths_cached(std::slice::from_ref(&file), &cache, analyze);
+            *results[0].as_ref().unwrap()
+        };
+
+        assert_eq!(2, run("v1"));
+        assert_eq!(2, run("v1"));
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+
+        // Entries do not contain the source.
+        for entry in fs::read_dir(dir.join("cache")).unwrap() {
+            let data = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!data.contains("y = 2"), "{}", data);
+        }
+
+        // Content change invalidates.
+        fs::write(&file, "x = 1\n").unwrap();
+        assert_eq!(1, run("v1"));
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+
+        // Config change invalidates.
+        assert_eq!(1, run("v2"));
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+
+        // Corrupt entries are ignored.
+        for entry in fs::read_dir(dir.join("cache")).unwrap() {
+            fs::write(entry.unwrap().path(), "not json").unwrap();
+        }
+        assert_eq!(1, run("v1"));
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -54,6 +54,13 @@ impl LintWarning for Dubious {
             Dubious::IdentifierAsStatement(..) => "ident-as-statement",
         }
     }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Dubious::DuplicateKey(x, span) => vec![x.clone(), span.to_string()],
+            Dubious::IdentifierAsStatement(x) => vec![x.clone()],
+        }
+    }
 }
 
 // Go implementation of Starlark disallows duplicate top-level assignments,
@@ -184,9 +191,7 @@ mod tests {
         duplicate_dictionary_key(&m, &mut res);
         assert_eq!(
             res.map(|x| x.problem.about()),
-            &[
-                "\"no1\"", "42", "\"no2\"", "123", "0.25", "no3", "no3", "no4"
-            ]
+            &["\"no1\"", "42", "\"no2\"", "123", "0.25", "no3", "no3", "no4"]
         );
     }
 
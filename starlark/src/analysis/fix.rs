@@ -0,0 +1,113 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Automatic fixes of lints.
+
+use crate::analysis::Lint;
+
+/// Apply the [`replacement`](Lint::replacement) of each lint to `source`,
+/// which must be the source of the module the lints were produced for.
+///
+/// Lints without a replacement are ignored. A replacement overlapping
+/// another one which starts earlier is skipped, run the linter again to fix it.
+pub fn apply_lint_fixes(source: &str, lints: &[Lint]) -> String {
+    let mut fixes: Vec<(usize, usize, &str)> = lints
+        .iter()
+        .filter_map(|lint| {
+            let replacement = lint.replacement()?;
+            let span = lint.location.span;
+            Some((
+                span.begin().get() as usize,
+                span.end().get() as usize,
+                replacement,
+            ))
+        })
+        .collect();
+    fixes.sort_by_key(|(begin, end, _)| (*begin, *end));
+
+    let mut res = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (begin, end, replacement) in fixes {
+        if begin < pos {
+            continue;
+        }
+        res.push_str(&source[pos..begin]);
+        res.push_str(replacement);
+        pos = end;
+    }
+    res.push_str(&source[pos..]);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::AstModuleLint;
+    use crate::analysis::apply_lint_fixes;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn fix(code: &str) -> String {
+        let module = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+        apply_lint_fixes(code, &module.lint(None))
+    }
+
+    #[test]
+    fn test_apply_lint_fixes() {
+        assert_eq!(
+            r#"
+def f(x, k):
+    a = x.get('k')
+    b = dict(x)
+    c = type(x)!=type("")
+    d = x[k] if k in x else []
+    return (a, b, c, d)
+"#,
+            fix(r#"
+def f(x, k):
+    a = x.get('k') if x.get('k') != None else None
+    b = dict(**x  )
+    c = type(x)!=str
+    d = x[k] if k in x else []
+    return (a, b, c, d)
+"#)
+        );
+    }
+
+    #[test]
+    fn test_apply_lint_fixes_nested() {
+        // Inner expression is fixed on the second run.
+        let code = r#"
+def f(x):
+    return dict(**dict(**x))
+"#;
+        let once = fix(code);
+        assert_eq!(
+            r#"
+def f(x):
+    return dict(dict(**x))
+"#,
+            once
+        );
+        assert_eq!(
+            r#"
+def f(x):
+    return dict(dict(x))
+"#,
+            fix(&once)
+        );
+    }
+}
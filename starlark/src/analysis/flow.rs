@@ -73,6 +73,20 @@ impl LintWarning for FlowIssue {
             FlowIssue::NoEffect => "no-effect",
         }
     }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            FlowIssue::MissingReturnExpression(x, def, ret) => {
+                vec![x.clone(), def.to_string(), ret.to_string()]
+            }
+            FlowIssue::MissingReturn(x, ret) => vec![x.clone(), ret.to_string()],
+            FlowIssue::Unreachable(x) => vec![x.clone()],
+            FlowIssue::RedundantReturn
+            | FlowIssue::RedundantContinue
+            | FlowIssue::MisplacedLoad
+            | FlowIssue::NoEffect => Vec::new(),
+        }
+    }
 }
 
 fn returns(x: &AstStmt) -> Vec<(Span, Option<&AstExpr>)> {
@@ -0,0 +1,336 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Lints suggesting shorter idioms.
+//!
+//! Automatic rewrites are only offered when they provably keep the meaning of the program
+//! from the syntax alone, otherwise the lint is advice.
+
+use starlark_syntax::syntax::ast::Argument;
+use starlark_syntax::syntax::ast::AstExpr;
+use starlark_syntax::syntax::ast::BinOp;
+use starlark_syntax::syntax::ast::Expr;
+use starlark_syntax::syntax::module::AstModuleFields;
+use thiserror::Error;
+
+use crate::analysis::EvalSeverity;
+use crate::analysis::types::LintT;
+use crate::analysis::types::LintWarning;
+use crate::codemap::CodeMap;
+use crate::syntax::AstModule;
+
+#[derive(Error, Debug)]
+pub(crate) enum Idiom {
+    #[error("`{0}` can be written `{1}`, unless `{2}` may store `None`")]
+    NoneCoalescingGet(String, String, String),
+    #[error("`{0}` can be written `{1}` if `{2}` is a dict")]
+    InCheckIndex(String, String, String),
+}
+
+impl LintWarning for Idiom {
+    fn severity(&self) -> EvalSeverity {
+        EvalSeverity::Advice
+    }
+
+    fn short_name(&self) -> &'static str {
+        match self {
+            Idiom::NoneCoalescingGet(..) => "none-coalescing-get",
+            Idiom::InCheckIndex(..) => "in-check-index",
+        }
+    }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Idiom::NoneCoalescingGet(x, y, z) | Idiom::InCheckIndex(x, y, z) => {
+                vec![x.clone(), y.clone(), z.clone()]
+            }
+        }
+    }
+}
+
+fn is_none(x: &AstExpr) -> bool {
+    matches!(&**x, Expr::Identifier(x) if x.node.ident == "None")
+}
+
+/// If the expression is `x.get(k)`, return `x` and `k`.
+fn unpack_get_call(x: &AstExpr) -> Option<(&AstExpr, &AstExpr)> {
+    match &**x {
+        Expr::Call(fun, args) if args.len() == 1 => match (&***fun, &*args[0]) {
+            (Expr::Dot(obj, name), Argument::Positional(key)) if name.node == "get" => {
+                Some((obj, key))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// If the expression is `a == None` or `None == a` with given operator, return `a`.
+fn unpack_none_check(x: &AstExpr, op: BinOp) -> Option<&AstExpr> {
+    match &**x {
+        Expr::Op(lhs, x_op, rhs) if *x_op == op => {
+            if is_none(rhs) {
+                Some(lhs)
+            } else if is_none(lhs) {
+                Some(rhs)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// If the expression is `k in x` with given operator, return `k` and `x`.
+fn unpack_in_check(x: &AstExpr, op: BinOp) -> Option<(&AstExpr, &AstExpr)> {
+    match &**x {
+        Expr::Op(key, x_op, obj) if *x_op == op => Some((key, obj)),
+        _ => None,
+    }
+}
+
+/// If the expression is `x[k]`, return `x` and `k`.
+fn unpack_index(x: &AstExpr) -> Option<(&AstExpr, &AstExpr)> {
+    match &**x {
+        Expr::Index(obj_key) => Some((&obj_key.0, &obj_key.1)),
+        _ => None,
+    }
+}
+
+fn same(a: &AstExpr, b: &AstExpr) -> bool {
+    a.node.to_string() == b.node.to_string()
+}
+
+/// Is the expression free of side effects and failures, so evaluating it
+/// a different number of times does not change the program.
+fn is_pure(x: &AstExpr) -> bool {
+    match &**x {
+        Expr::Identifier(_) | Expr::Literal(_) => true,
+        Expr::Tuple(xs) | Expr::List(xs) => xs.iter().all(is_pure),
+        _ => false,
+    }
+}
+
+/// Is the expression a dict display with pure keys and values,
+/// so `k in x` and `x[k]` are dict operations.
+fn is_pure_dict(x: &AstExpr) -> bool {
+    match &**x {
+        Expr::Dict(xs) => xs.iter().all(|(k, v)| is_pure(k) && is_pure(v)),
+        _ => false,
+    }
+}
+
+/// Source of `obj.get(key, default)`, keeping the formatting of the operands.
+fn get_with_default(codemap: &CodeMap, obj: &AstExpr, key: &AstExpr, default: &AstExpr) -> String {
+    let obj_source = codemap.source_span(obj.span);
+    let obj_source = match &**obj {
+        Expr::Identifier(_)
+        | Expr::Dot(..)
+        | Expr::Call(..)
+        | Expr::Index(..)
+        | Expr::List(..)
+        | Expr::Dict(..) => obj_source.to_owned(),
+        _ => format!("({})", obj_source),
+    };
+    format!(
+        "{}.get({}, {})",
+        obj_source,
+        codemap.source_span(key.span),
+        codemap.source_span(default.span)
+    )
+}
+
+fn match_none_coalescing_get(codemap: &CodeMap, x: &AstExpr, res: &mut Vec<LintT<Idiom>>) {
+    let Expr::If(cond_then_else) = &**x else {
+        return;
+    };
+    let (cond, then_expr, else_expr) = &**cond_then_else;
+    // `x.get(k) if x.get(k) != None else d` or `d if x.get(k) == None else x.get(k)`
+    let (checked, value, default) = if let Some(checked) = unpack_none_check(cond, BinOp::NotEqual)
+    {
+        (checked, then_expr, else_expr)
+    } else if let Some(checked) = unpack_none_check(cond, BinOp::Equal) {
+        (checked, else_expr, then_expr)
+    } else {
+        return;
+    };
+    let Some((obj, key)) = unpack_get_call(value) else {
+        return;
+    };
+    if same(checked, value) {
+        let lint = LintT::new(
+            codemap,
+            x.span,
+            Idiom::NoneCoalescingGet(
+                x.to_string(),
+                get_with_default(codemap, obj, key, default),
+                obj.to_string(),
+            ),
+        );
+        // A stored `None` is replaced by the default, so only a `None` default is the same
+        // as `x.get(k)`, and `x.get(k)` is called once instead of twice.
+        let lint = if is_none(default) && is_pure(obj) && is_pure(key) {
+            lint.with_replacement(codemap.source_span(value.span).to_owned())
+        } else {
+            lint
+        };
+        res.push(lint);
+    }
+}
+
+fn match_in_check_index(codemap: &CodeMap, x: &AstExpr, res: &mut Vec<LintT<Idiom>>) {
+    let Expr::If(cond_then_else) = &**x else {
+        return;
+    };
+    let (cond, then_expr, else_expr) = &**cond_then_else;
+    // `x[k] if k in x else d` or `d if k not in x else x[k]`
+    let ((key, obj), value, default) = if let Some(check) = unpack_in_check(cond, BinOp::In) {
+        (check, then_expr, else_expr)
+    } else if let Some(check) = unpack_in_check(cond, BinOp::NotIn) {
+        (check, else_expr, then_expr)
+    } else {
+        return;
+    };
+    let Some((index_obj, index_key)) = unpack_index(value) else {
+        return;
+    };
+    if same(obj, index_obj) && same(key, index_key) {
+        let replacement = get_with_default(codemap, obj, key, default);
+        let lint = LintT::new(
+            codemap,
+            x.span,
+            Idiom::InCheckIndex(x.to_string(), replacement.clone(), obj.to_string()),
+        );
+        // Lists and strings have no `.get`, and the default is now always evaluated.
+        let lint = if is_pure_dict(obj) && is_pure(key) && is_pure(default) {
+            lint.with_replacement(replacement)
+        } else {
+            lint
+        };
+        res.push(lint);
+    }
+}
+
+fn check_if_expr(module: &AstModule, res: &mut Vec<LintT<Idiom>>) {
+    fn check(codemap: &CodeMap, x: &AstExpr, res: &mut Vec<LintT<Idiom>>) {
+        match_none_coalescing_get(codemap, x, res);
+        match_in_check_index(codemap, x, res);
+        x.visit_expr(|x| check(codemap, x, res));
+    }
+    module
+        .statement()
+        .visit_expr(|x| check(module.codemap(), x, res));
+}
+
+pub(crate) fn lint(module: &AstModule) -> Vec<LintT<Idiom>> {
+    let mut res = Vec::new();
+    check_if_expr(module, &mut res);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark_syntax::slice_vec_ext::SliceExt;
+
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("bad.bzl", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_lint_none_coalescing_get() {
+        let res = lint(&module(
+            r#"
+def foo(x, d):
+    a = x.get("k") if x.get("k") != None else d
+    b = d if x.get("k") == None else x.get("k")
+    c = x.get("k") if None != x.get("k") else 1
+    e = x.get("k") if x.get("j") != None else d
+    return (a, b, c, e)
+"#,
+        ));
+        assert_eq!(
+            res.map(|x| x.to_string()),
+            &[
+                "bad.bzl:3:9-48: `(x.get(\"k\") if (x.get(\"k\") != None) else d)` can be written `x.get(\"k\", d)`, unless `x` may store `None`",
+                "bad.bzl:4:9-48: `(d if (x.get(\"k\") == None) else x.get(\"k\"))` can be written `x.get(\"k\", d)`, unless `x` may store `None`",
+                "bad.bzl:5:9-48: `(x.get(\"k\") if (None != x.get(\"k\")) else 1)` can be written `x.get(\"k\", 1)`, unless `x` may store `None`",
+            ]
+        );
+        // A stored `None` would be replaced by the default.
+        assert!(res.iter().all(|x| x.replacement.is_none()));
+    }
+
+    #[test]
+    fn test_lint_none_coalescing_get_replacement() {
+        let res = lint(&module(
+            r#"
+def foo(x, k):
+    a = x.get( 'k' ) if x.get('k') != None else None
+    b = f().get(k) if f().get(k) != None else None
+    return (a, b)
+"#,
+        ));
+        assert_eq!(
+            res.map(|x| x.replacement.as_deref()),
+            &[Some("x.get( 'k' )"), None]
+        );
+    }
+
+    #[test]
+    fn test_lint_in_check_index() {
+        let res = lint(&module(
+            r#"
+def foo(x, k):
+    a = x[k] if k in x else []
+    b = None if k not in x else x[k]
+    c = x[k] if k in y else []
+    d = (x + y)[k] if k in x + y else 0
+    return (a, b, c, d)
+"#,
+        ));
+        assert_eq!(
+            res.map(|x| x.to_string()),
+            &[
+                "bad.bzl:3:9-31: `(x[k] if (k in x) else [])` can be written `x.get(k, [])` if `x` is a dict",
+                "bad.bzl:4:9-37: `(None if (k not in x) else x[k])` can be written `x.get(k, None)` if `x` is a dict",
+                "bad.bzl:6:9-40: `((x + y)[k] if (k in (x + y)) else 0)` can be written `(x + y).get(k, 0)` if `(x + y)` is a dict",
+            ]
+        );
+        // `x` may be a list or a string, which have no `.get`.
+        assert!(res.iter().all(|x| x.replacement.is_none()));
+    }
+
+    #[test]
+    fn test_lint_in_check_index_replacement() {
+        let res = lint(&module(
+            r#"
+def foo(k, d):
+    a = {'a': 1}[k] if k in {'a': 1} else d
+    b = {'a': 1}[k] if k in {'a': 1} else fail("missing")
+    c = {'a': f()}[k] if k in {'a': f()} else d
+    return (a, b, c)
+"#,
+        ));
+        assert_eq!(
+            res.map(|x| x.replacement.as_deref()),
+            &[Some("{'a': 1}.get(k, d)"), None, None]
+        );
+    }
+}
@@ -59,6 +59,13 @@ impl LintWarning for Incompatibility {
             Incompatibility::DuplicateTopLevelAssign(..) => "duplicate-top-level-assign",
         }
     }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Incompatibility::IncompatibleTypeCheck(x, y) => vec![x.clone(), y.clone()],
+            Incompatibility::DuplicateTopLevelAssign(x, span) => vec![x.clone(), span.to_string()],
+        }
+    }
 }
 
 static TYPES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
@@ -101,14 +108,24 @@ fn match_bad_type_equality(
             if (*op == BinOp::Equal || *op == BinOp::NotEqual) && is_type_call(lhs) =>
         {
             if let Some(replacement) = lookup_type(rhs, types) {
-                res.push(LintT::new(
-                    codemap,
-                    x.span,
-                    Incompatibility::IncompatibleTypeCheck(
-                        x.to_string(),
-                        format!("{}{}type({})", lhs.node, op, replacement),
-                    ),
-                ))
+                // Only `y` is rewritten, the rest keeps its original source.
+                let fixed = format!(
+                    "{}type({}){}",
+                    codemap.source_span(Span::new(x.span.begin(), rhs.span.begin())),
+                    replacement,
+                    codemap.source_span(Span::new(rhs.span.end(), x.span.end())),
+                );
+                res.push(
+                    LintT::new(
+                        codemap,
+                        x.span,
+                        Incompatibility::IncompatibleTypeCheck(
+                            x.to_string(),
+                            format!("{}{}type({})", lhs.node, op, replacement),
+                        ),
+                    )
+                    .with_replacement(fixed),
+                )
             }
         }
         _ => {}
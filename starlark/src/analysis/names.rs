@@ -88,6 +88,17 @@ impl LintWarning for NameWarning {
             Self::UsingMaybeUndefined(..) => "using-maybe-undefined",
         }
     }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Self::UnusedLoad(x)
+            | Self::UnusedAssign(x)
+            | Self::UnusedArgument(x)
+            | Self::UsingUnassigned(x)
+            | Self::UsingUndefined(x)
+            | Self::UsingMaybeUndefined(x) => vec![x.clone()],
+        }
+    }
 }
 
 #[derive(Debug, Copy, Dupe, Clone, PartialEq, Eq)]
@@ -53,6 +53,14 @@ impl LintWarning for Performance {
             Performance::InefficientBoolCheck(..) => "inefficient-bool-check",
         }
     }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            Performance::DictWithoutStarStar(x, y) => vec![x.clone(), y.clone()],
+            Performance::EagerAndInefficientBoolCheck(x) => vec![x.clone()],
+            Performance::InefficientBoolCheck(x, y) => vec![x.clone(), y.clone()],
+        }
+    }
 }
 
 fn match_dict_copy(codemap: &CodeMap, x: &AstExpr, res: &mut Vec<LintT<Performance>>) {
@@ -60,11 +68,12 @@ fn match_dict_copy(codemap: &CodeMap, x: &AstExpr, res: &mut Vec<LintT<Performan
     match &**x {
         Expr::Call(fun, args) if args.len() == 1 => match (&***fun, &*args[0]) {
             (Expr::Identifier(f), Argument::KwArgs(arg)) if f.node.ident == "dict" => {
-                res.push(LintT::new(
+                let lint = LintT::new(
                     codemap,
                     x.span,
                     Performance::DictWithoutStarStar(x.to_string(), format!("dict({})", arg.node)),
-                ))
+                );
+                res.push(lint.with_replacement(format!("dict({})", codemap.source_span(arg.span))));
             }
             _ => {}
         },
@@ -0,0 +1,387 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Name resolution results, as computed by the evaluator.
+
+use std::collections::HashMap;
+
+use dupe::Dupe;
+use starlark_syntax::syntax::ast::ClauseP;
+use starlark_syntax::syntax::ast::DefP;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::ForP;
+use starlark_syntax::syntax::ast::LambdaP;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::uniplate::Visit;
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::environment::names::MutableNames;
+use crate::eval::compiler::scope::payload::CstAssignIdent;
+use crate::eval::compiler::scope::payload::CstAssignTarget;
+use crate::eval::compiler::scope::payload::CstExpr;
+use crate::eval::compiler::scope::payload::CstParameter;
+use crate::eval::compiler::scope::payload::CstStmt;
+use crate::eval::compiler::scope::scope_resolver_globals::ScopeResolverGlobals;
+use crate::eval::compiler::scope::BindingId;
+use crate::eval::compiler::scope::Captured;
+use crate::eval::compiler::scope::ModuleScopeData;
+use crate::eval::compiler::scope::ModuleScopes;
+use crate::eval::compiler::scope::ResolvedIdent;
+use crate::eval::compiler::scope::Slot;
+use crate::syntax::AstBindingKind;
+use crate::syntax::AstModule;
+use crate::values::FrozenHeap;
+
+/// Index of a binding in [`ScopeAnalysis::bindings`].
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeBindingId(pub usize);
+
+/// A place for a variable. Several assignments of the same name in the same scope
+/// share one binding, e.g. in `x = 1; x = 2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeBinding {
+    /// Name of the variable.
+    pub name: String,
+    /// How the variable is bound.
+    pub kind: AstBindingKind,
+    /// Every place the variable is assigned, in source order.
+    pub assignments: Vec<FileSpan>,
+    /// Whether the variable is referenced from a nested `def` or `lambda`.
+    pub captured: bool,
+}
+
+/// What an identifier refers to.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum ScopeReferenceTarget {
+    /// A binding in this module.
+    Binding(ScopeBindingId),
+    /// A name not bound in this module, so a builtin or a global provided by the host.
+    Global,
+}
+
+/// An identifier read in the module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeReference {
+    /// The identifier.
+    pub name: String,
+    /// Location of the identifier.
+    pub span: FileSpan,
+    /// What it refers to.
+    pub target: ScopeReferenceTarget,
+}
+
+/// Results of name resolution, as performed by the evaluator before executing a module.
+#[derive(Debug)]
+pub struct ScopeAnalysis {
+    /// All bindings in the module, ordered by their first assignment.
+    pub bindings: Vec<ScopeBinding>,
+    /// All identifiers read in the module, in source order.
+    pub references: Vec<ScopeReference>,
+    /// Resolution errors, e.g. a type expression referring to a local variable.
+    pub errors: Vec<crate::Error>,
+}
+
+impl ScopeAnalysis {
+    /// Get a binding by id.
+    pub fn binding(&self, id: ScopeBindingId) -> &ScopeBinding {
+        &self.bindings[id.0]
+    }
+
+    /// References which resolve to the given binding.
+    pub fn references_to(&self, id: ScopeBindingId) -> impl Iterator<Item = &ScopeReference> {
+        self.references
+            .iter()
+            .filter(move |r| r.target == ScopeReferenceTarget::Binding(id))
+    }
+}
+
+/// Resolve names in a module.
+pub trait AstModuleScopeAnalysis {
+    /// Resolve every identifier in the module to the binding it refers to, using the
+    /// same rules as the evaluator. Names not bound in the module are reported as
+    /// [`ScopeReferenceTarget::Global`], without checking they exist.
+    fn scope_analysis(self) -> ScopeAnalysis;
+}
+
+impl AstModuleScopeAnalysis for AstModule {
+    fn scope_analysis(self) -> ScopeAnalysis {
+        let (codemap, statement, dialect, _) = self.into_parts();
+        let names = MutableNames::new();
+        let frozen_heap = FrozenHeap::new();
+        let (
+            errors,
+            ModuleScopes {
+                cst, scope_data, ..
+            },
+        ) = ModuleScopes::check_module(
+            &names,
+            &frozen_heap,
+            &HashMap::new(),
+            statement,
+            ScopeResolverGlobals::unknown(),
+            frozen_heap.alloc_any(codemap.dupe()),
+            &dialect,
+        );
+        let mut collect = Collect {
+            codemap: &codemap,
+            scope_data: &scope_data,
+            ids: HashMap::new(),
+            bindings: Vec::new(),
+            references: Vec::new(),
+        };
+        collect.stmt(&cst);
+        collect.finish(errors.into_iter().map(|e| e.into_error()).collect())
+    }
+}
+
+struct Collect<'a, 'f> {
+    codemap: &'a CodeMap,
+    scope_data: &'a ModuleScopeData<'f>,
+    ids: HashMap<BindingId, usize>,
+    bindings: Vec<ScopeBinding>,
+    references: Vec<(ScopeReference, Option<BindingId>)>,
+}
+
+impl Collect<'_, '_> {
+    fn assign(&mut self, x: &CstAssignIdent, kind: Option<AstBindingKind>) {
+        let Some(binding_id) = x.payload else {
+            return;
+        };
+        let span = self.codemap.file_span(x.span);
+        let index = match self.ids.get(&binding_id) {
+            Some(index) => *index,
+            None => {
+                let binding = self.scope_data.get_binding(binding_id);
+                let kind = kind.unwrap_or(match binding.resolved_slot(self.codemap) {
+                    Ok(Slot::Local(_)) => AstBindingKind::Local,
+                    _ => AstBindingKind::Module,
+                });
+                self.bindings.push(ScopeBinding {
+                    name: x.ident.clone(),
+                    kind,
+                    assignments: Vec::new(),
+                    captured: binding.captured == Captured::Yes,
+                });
+                self.ids.insert(binding_id, self.bindings.len() - 1);
+                self.bindings.len() - 1
+            }
+        };
+        self.bindings[index].assignments.push(span);
+    }
+
+    fn assign_target(&mut self, x: &CstAssignTarget, kind: Option<AstBindingKind>) {
+        x.visit_lvalue(|x| self.assign(x, kind));
+    }
+
+    fn params(&mut self, params: &[CstParameter]) {
+        for p in params {
+            if let Some(x) = p.ident() {
+                self.assign(x, Some(AstBindingKind::Parameter));
+            }
+        }
+    }
+
+    fn stmt(&mut self, x: &CstStmt) {
+        match &**x {
+            StmtP::Assign(assign) => self.assign_target(&assign.lhs, None),
+            StmtP::AssignModify(lhs, _, _) => self.assign_target(lhs, None),
+            StmtP::For(ForP { var, .. }) => self.assign_target(var, None),
+            StmtP::Def(DefP { name, params, .. }) => {
+                self.assign(name, None);
+                self.params(params);
+            }
+            StmtP::Load(load) => {
+                for arg in &load.args {
+                    self.assign(&arg.local, Some(AstBindingKind::Load));
+                }
+            }
+            _ => {}
+        }
+        x.visit_children(|x| match x {
+            Visit::Stmt(x) => self.stmt(x),
+            Visit::Expr(x) => self.expr(x),
+        });
+    }
+
+    fn expr(&mut self, x: &CstExpr) {
+        match &**x {
+            ExprP::Identifier(ident) => {
+                if let Some(resolved) = ident.node.payload {
+                    let binding_id = match resolved {
+                        ResolvedIdent::Slot(_, binding_id) => Some(binding_id),
+                        ResolvedIdent::Global(_) => None,
+                    };
+                    self.references.push((
+                        ScopeReference {
+                            name: ident.node.ident.clone(),
+                            span: self.codemap.file_span(x.span),
+                            target: ScopeReferenceTarget::Global,
+                        },
+                        binding_id,
+                    ));
+                }
+            }
+            ExprP::Lambda(LambdaP { params, .. }) => self.params(params),
+            ExprP::ListComprehension(_, for_, clauses)
+            | ExprP::DictComprehension(_, for_, clauses) => {
+                self.assign_target(&for_.var, Some(AstBindingKind::Comprehension));
+                for clause in clauses {
+                    if let ClauseP::For(x) = clause {
+                        self.assign_target(&x.var, Some(AstBindingKind::Comprehension));
+                    }
+                }
+            }
+            _ => {}
+        }
+        x.visit_expr(|x| self.expr(x));
+    }
+
+    fn finish(mut self, errors: Vec<crate::Error>) -> ScopeAnalysis {
+        // Traversal order differs from source order (e.g. in comprehensions),
+        // so sort bindings by first assignment, and references by position.
+        for b in &mut self.bindings {
+            b.assignments.sort_by_key(|s| s.span.begin());
+        }
+        let mut order: Vec<usize> = (0..self.bindings.len()).collect();
+        order.sort_by_key(|i| self.bindings[*i].assignments[0].span.begin());
+        let mut renumber = vec![0; order.len()];
+        for (new, old) in order.iter().enumerate() {
+            renumber[*old] = new;
+        }
+        let mut bindings: Vec<Option<ScopeBinding>> = self.bindings.into_iter().map(Some).collect();
+        let bindings = order.iter().map(|i| bindings[*i].take().unwrap()).collect();
+
+        let mut references: Vec<ScopeReference> = self
+            .references
+            .into_iter()
+            .map(|(mut r, binding_id)| {
+                if let Some(index) = binding_id.and_then(|b| self.ids.get(&b)) {
+                    r.target = ScopeReferenceTarget::Binding(ScopeBindingId(renumber[*index]));
+                }
+                r
+            })
+            .collect();
+        references.sort_by_key(|r| r.span.span.begin());
+
+        ScopeAnalysis {
+            bindings,
+            references,
+            errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn analysis(program: &str) -> ScopeAnalysis {
+        AstModule::parse("a.star", program.to_owned(), &Dialect::Extended)
+            .unwrap()
+            .scope_analysis()
+    }
+
+    fn describe(a: &ScopeAnalysis) -> Vec<String> {
+        a.references
+            .iter()
+            .map(|r| match r.target {
+                ScopeReferenceTarget::Global => format!("{}: global", r.name),
+                ScopeReferenceTarget::Binding(id) => {
+                    let b = a.binding(id);
+                    format!(
+                        "{}: {:?} at {}",
+                        r.name,
+                        b.kind,
+                        b.assignments[0].resolve_span()
+                    )
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scope_analysis() {
+        let a = analysis(
+            r#"
+load("m.star", "l")
+x = 1
+def f(p):
+    y = p + x
+    return [y for y in l if y]
+def g():
+    return lambda q: q + x + len(f)
+x = 2
+"#,
+        );
+        assert!(a.errors.is_empty());
+        assert_eq!(
+            describe(&a),
+            &[
+                "p: Parameter at 4:7-8",
+                "x: Module at 3:1-2",
+                "y: Comprehension at 6:19-20",
+                "l: Load at 2:16-19",
+                "y: Comprehension at 6:19-20",
+                "q: Parameter at 8:19-20",
+                "x: Module at 3:1-2",
+                "len: global",
+                "f: Module at 4:5-6",
+            ]
+        );
+        let x = a.bindings.iter().position(|b| b.name == "x").unwrap();
+        assert_eq!(2, a.binding(ScopeBindingId(x)).assignments.len());
+        assert_eq!(2, a.references_to(ScopeBindingId(x)).count());
+        let y = a
+            .bindings
+            .iter()
+            .find(|b| b.name == "y" && b.kind == AstBindingKind::Local)
+            .unwrap();
+        assert_eq!(1, y.assignments.len());
+    }
+
+    #[test]
+    fn test_scope_analysis_captured() {
+        let a = analysis(
+            r#"
+def f():
+    a = 1
+    b = 2
+    def g():
+        return a
+    return b + g()
+"#,
+        );
+        let captured: Vec<(&str, bool)> = a
+            .bindings
+            .iter()
+            .map(|b| (b.name.as_str(), b.captured))
+            .collect();
+        assert_eq!(
+            captured,
+            &[("f", false), ("a", true), ("b", false), ("g", false)]
+        );
+    }
+
+    #[test]
+    fn test_scope_analysis_errors() {
+        let a = analysis("def f(x):\n    y: x = 1\n");
+        assert_eq!(1, a.errors.len());
+    }
+}
@@ -0,0 +1,347 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Slice a module down to the top-level statements needed for some of its names.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::AstStmt;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::ForP;
+use starlark_syntax::syntax::ast::Stmt;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+use starlark_syntax::syntax::uniplate::Visit;
+use thiserror::Error;
+
+use crate::codemap::Spanned;
+use crate::syntax::AstModule;
+
+#[derive(Error, Debug)]
+enum SliceError {
+    #[error("Module does not define `{0}`")]
+    UnknownSymbol(String),
+}
+
+/// Names bound and read by a single top-level statement.
+#[derive(Default)]
+struct TopLevelNames<'a> {
+    binds: Vec<&'a str>,
+    reads: HashSet<&'a str>,
+    /// Names whose values may be mutated: receivers of method calls, call arguments
+    /// and assignment targets like `x[k]` or `x.f`.
+    mutates: HashSet<&'a str>,
+    /// Does the statement call anything, which may mutate whatever the callee can reach.
+    calls: bool,
+}
+
+impl<'a> TopLevelNames<'a> {
+    fn collect(stmt: &'a AstStmt) -> Self {
+        let mut names = TopLevelNames::default();
+        names.binds(stmt);
+        names.reads(Visit::Stmt(stmt));
+        names
+    }
+
+    /// Module-level names bound by the statement. Bodies of `def` are not entered,
+    /// but `if` and `for` at the top level bind module-level names.
+    fn binds(&mut self, stmt: &'a AstStmt) {
+        match &**stmt {
+            StmtP::Statements(xs) => xs.iter().for_each(|x| self.binds(x)),
+            StmtP::If(_, then_block) => self.binds(then_block),
+            StmtP::IfElse(_, then_block_else_block) => {
+                self.binds(&then_block_else_block.0);
+                self.binds(&then_block_else_block.1);
+            }
+            StmtP::For(ForP { var, body, .. }) => {
+                var.visit_lvalue(|x| self.binds.push(&x.ident));
+                self.binds(body);
+            }
+            StmtP::Def(def) => self.binds.push(&def.name.ident),
+            StmtP::Assign(assign) => assign.lhs.visit_lvalue(|x| self.binds.push(&x.ident)),
+            StmtP::AssignModify(lhs, _, _) => lhs.visit_lvalue(|x| self.binds.push(&x.ident)),
+            StmtP::Load(load) => load
+                .args
+                .iter()
+                .for_each(|x| self.binds.push(&x.local.ident)),
+            StmtP::Break
+            | StmtP::Continue
+            | StmtP::Pass
+            | StmtP::Return(_)
+            | StmtP::Expression(_) => {}
+        }
+    }
+
+    /// Every identifier read anywhere in the statement, including function bodies.
+    /// Locals shadowing module names are counted too, which only makes the slice larger.
+    fn reads(&mut self, x: Visit<'a, AstNoPayload>) {
+        match &x {
+            Visit::Expr(e) => match &e.node {
+                ExprP::Identifier(ident) => {
+                    self.reads.insert(&ident.ident);
+                }
+                ExprP::Call(f, args) => {
+                    self.calls = true;
+                    if !matches!(f.node, ExprP::Identifier(_)) {
+                        identifiers(Visit::Expr(f), &mut self.mutates);
+                    }
+                    for arg in args {
+                        identifiers(Visit::Expr(arg.expr()), &mut self.mutates);
+                    }
+                }
+                _ => {}
+            },
+            Visit::Stmt(stmt) => match &stmt.node {
+                StmtP::Assign(assign) => {
+                    assign
+                        .lhs
+                        .visit_expr(|x| identifiers(Visit::Expr(x), &mut self.mutates));
+                }
+                StmtP::AssignModify(lhs, _, _) | StmtP::For(ForP { var: lhs, .. }) => {
+                    lhs.visit_expr(|x| identifiers(Visit::Expr(x), &mut self.mutates));
+                }
+                _ => {}
+            },
+        }
+        x.visit_children(|x| self.reads(x));
+    }
+}
+
+fn identifiers<'a>(x: Visit<'a, AstNoPayload>, res: &mut HashSet<&'a str>) {
+    if let Visit::Expr(e) = &x {
+        if let ExprP::Identifier(ident) = &e.node {
+            res.insert(&ident.ident);
+        }
+    }
+    x.visit_children(|x| identifiers(x, res));
+}
+
+/// Keep only the parts of a module needed to compute some of its names.
+pub trait AstModuleSlice: Sized {
+    /// Drop the top-level statements which do not contribute to the given module-level names,
+    /// so evaluating the result is cheaper than evaluating the whole module,
+    /// e.g. when an IDE needs one constant from a large generated file.
+    ///
+    /// A statement is kept if it binds a needed name, or if it may mutate a needed name,
+    /// whether or not it binds anything (e.g. `x.append(1)` or `y = x.pop()`).
+    /// Calls are treated conservatively: a statement calling anything is assumed to run
+    /// every function reachable from the names it reads, e.g. `f()` is kept
+    /// if `f` may mutate a needed name.
+    /// Needed names are then extended with everything the kept statements read, until a fixed point.
+    /// Other top-level statements (e.g. `print("hello")` or `y = [x]`)
+    /// are dropped, so their side effects are not observed.
+    ///
+    /// The kept statements keep their order and their locations in the original file.
+    fn slice_for_symbols(self, symbols: &[&str]) -> anyhow::Result<AstModule>;
+}
+
+impl AstModuleSlice for AstModule {
+    fn slice_for_symbols(self, symbols: &[&str]) -> anyhow::Result<AstModule> {
+        let keep = {
+            let stmts = top_level_stmts(self.statement());
+            let names: Vec<TopLevelNames> =
+                stmts.iter().map(|x| TopLevelNames::collect(x)).collect();
+
+            let mut binders: HashMap<&str, Vec<usize>> = HashMap::new();
+            let mut readers: HashMap<&str, Vec<usize>> = HashMap::new();
+            let mut mutators: HashMap<&str, Vec<usize>> = HashMap::new();
+            for (i, n) in names.iter().enumerate() {
+                for name in &n.binds {
+                    binders.entry(name).or_default().push(i);
+                }
+                for name in &n.reads {
+                    readers.entry(name).or_default().push(i);
+                }
+                for name in &n.mutates {
+                    mutators.entry(name).or_default().push(i);
+                }
+            }
+
+            // Statements which may mutate `name`: the ones mutating it directly,
+            // and the ones calling anything which can reach those through the names they read.
+            let mutators_of = |name: &str| -> Vec<usize> {
+                let mut visited = vec![false; stmts.len()];
+                let mut queue: Vec<usize> = mutators.get(name).cloned().unwrap_or_default();
+                let mut res = Vec::new();
+                while let Some(i) = queue.pop() {
+                    if visited[i] {
+                        continue;
+                    }
+                    visited[i] = true;
+                    if names[i].calls || names[i].mutates.contains(name) {
+                        res.push(i);
+                    }
+                    for bind in &names[i].binds {
+                        queue.extend(readers.get(bind).into_iter().flatten());
+                    }
+                }
+                res
+            };
+
+            let mut keep = vec![false; stmts.len()];
+            let mut seen: HashSet<&str> = HashSet::new();
+            let mut queue: Vec<&str> = Vec::new();
+            for symbol in symbols {
+                let Some((symbol, _)) = binders.get_key_value(symbol) else {
+                    return Err(SliceError::UnknownSymbol((*symbol).to_owned()).into());
+                };
+                if seen.insert(symbol) {
+                    queue.push(symbol);
+                }
+            }
+            while let Some(name) = queue.pop() {
+                let stmts = binders.get(name).into_iter().flatten().copied();
+                for i in stmts.chain(mutators_of(name)) {
+                    if !keep[i] {
+                        keep[i] = true;
+                        for read in &names[i].reads {
+                            if seen.insert(read) {
+                                queue.push(read);
+                            }
+                        }
+                    }
+                }
+            }
+            keep
+        };
+
+        Ok(self.map_statement(|statement| {
+            fn retain(stmt: AstStmt, keep: &[bool], index: &mut usize, res: &mut Vec<AstStmt>) {
+                match stmt.node {
+                    StmtP::Statements(xs) => {
+                        for x in xs {
+                            retain(x, keep, index, res);
+                        }
+                    }
+                    _ => {
+                        if keep[*index] {
+                            res.push(stmt);
+                        }
+                        *index += 1;
+                    }
+                }
+            }
+
+            let span = statement.span;
+            let mut res = Vec::new();
+            retain(statement, &keep, &mut 0, &mut res);
+            Spanned {
+                node: Stmt::Statements(res),
+                span,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::AstModuleSlice;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn eval_sliced(code: &str, symbols: &[&str]) -> Module {
+        let ast = AstModule::parse("a.star", code.to_owned(), &Dialect::Extended).unwrap();
+        let ast = ast.slice_for_symbols(symbols).unwrap();
+        let module = Module::new();
+        Evaluator::new(&module)
+            .eval_module(ast, &Globals::standard())
+            .unwrap();
+        module
+    }
+
+    const CODE: &str = r#"
+A = 1
+def f(x):
+    return x + A
+B = f(1)
+C = fail("expensive")
+D = []
+D.append(B)
+print("side effect")
+E = [x for x in D if x > C]
+"#;
+
+    #[test]
+    fn test_slice_keeps_dependencies() {
+        let module = eval_sliced(CODE, &["B"]);
+        assert_eq!(Some(2), module.get("B").and_then(|v| v.unpack_i32()));
+        assert!(module.get("C").is_none());
+        assert!(module.get("E").is_none());
+    }
+
+    #[test]
+    fn test_slice_keeps_mutations() {
+        let module = eval_sliced(CODE, &["D"]);
+        assert_eq!("[2]", module.get("D").unwrap().to_repr());
+        assert!(module.get("C").is_none());
+        assert!(module.get("E").is_none());
+    }
+
+    #[test]
+    fn test_slice_drops_unrelated() {
+        let module = eval_sliced(CODE, &["A"]);
+        assert_eq!(Some(1), module.get("A").and_then(|v| v.unpack_i32()));
+        assert!(module.get("B").is_none());
+        assert!(module.get("D").is_none());
+    }
+
+    #[test]
+    fn test_slice_keeps_mutations_which_bind() {
+        let code = r#"
+D = [1, 2]
+X = D.pop()
+Y = [D[0]]
+Y[0] = fail("unused")
+"#;
+        let module = eval_sliced(code, &["D"]);
+        assert_eq!("[1]", module.get("D").unwrap().to_repr());
+        assert!(module.get("Y").is_none());
+    }
+
+    #[test]
+    fn test_slice_keeps_mutations_in_calls() {
+        let code = r#"
+def f():
+    D.append(1)
+def add(xs, x):
+    xs.append(x)
+def pure(x):
+    return x + 1
+D = []
+f()
+g = f
+g()
+add(D, 2)
+pure(fail("unused"))
+"#;
+        let module = eval_sliced(code, &["D"]);
+        assert_eq!("[1, 1, 2]", module.get("D").unwrap().to_repr());
+        assert!(module.get("pure").is_none());
+    }
+
+    #[test]
+    fn test_slice_unknown_symbol() {
+        let ast = AstModule::parse("a.star", CODE.to_owned(), &Dialect::Extended).unwrap();
+        let err = ast.slice_for_symbols(&["Z"]).unwrap_err();
+        assert_eq!("Module does not define `Z`", err.to_string());
+    }
+}
@@ -26,10 +26,14 @@ use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
 use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
+use crate::message::MessageCatalog;
+use crate::message::MessageTemplate;
 
 pub(crate) trait LintWarning: Display {
     fn severity(&self) -> EvalSeverity;
     fn short_name(&self) -> &'static str;
+    /// Arguments of the message, for the `{0}`, `{1}`, ... placeholders of its template.
+    fn args(&self) -> Vec<String>;
 }
 
 /// A private version of lint without the inner trait erased, useful so we can test
@@ -39,6 +43,8 @@ pub(crate) struct LintT<T> {
     pub location: FileSpan,
     pub original: String,
     pub problem: T,
+    /// Source code to replace `original` with, if the lint can be fixed automatically.
+    pub replacement: Option<String>,
 }
 
 /// A lint produced by `AstModule::lint`.
@@ -55,6 +61,32 @@ pub struct Lint {
     pub problem: String,
     /// The source code at [`location`](Lint::location).
     pub original: String,
+    /// Use [`replacement`](Lint::replacement) to read it.
+    replacement: Option<String>,
+    /// Use [`message_template`](Lint::message_template) to read it.
+    message_template: MessageTemplate,
+}
+
+impl Lint {
+    /// The [`problem`](Lint::problem) as a template for translation,
+    /// its code is the [`short_name`](Lint::short_name).
+    pub fn message_template(&self) -> &MessageTemplate {
+        &self.message_template
+    }
+
+    /// The [`problem`](Lint::problem) translated by `catalog`,
+    /// or the English text if the catalog has no template for it.
+    pub fn localized_problem(&self, catalog: &dyn MessageCatalog) -> String {
+        self.message_template
+            .localize(catalog)
+            .unwrap_or_else(|| self.problem.clone())
+    }
+
+    /// Source code to replace [`original`](Lint::original) with,
+    /// if the lint can be fixed automatically, see [`apply_lint_fixes`](crate::analysis::apply_lint_fixes).
+    pub fn replacement(&self) -> Option<&str> {
+        self.replacement.as_deref()
+    }
 }
 
 impl Display for Lint {
@@ -76,9 +108,17 @@ impl<T: LintWarning> LintT<T> {
             original: location.file.source_span(span).to_owned(),
             location,
             problem,
+            replacement: None,
         }
     }
 
+    /// Fix the lint by replacing the whole linted span with `replacement`,
+    /// which must be built from the original source to keep its formatting.
+    pub(crate) fn with_replacement(mut self, replacement: String) -> Self {
+        self.replacement = Some(replacement);
+        self
+    }
+
     pub(crate) fn erase(self) -> Lint {
         Lint {
             location: self.location,
@@ -86,6 +126,8 @@ impl<T: LintWarning> LintT<T> {
             severity: self.problem.severity(),
             problem: self.problem.to_string(),
             original: self.original,
+            replacement: self.replacement,
+            message_template: MessageTemplate::new(self.problem.short_name(), self.problem.args()),
         }
     }
 }
@@ -132,6 +174,9 @@ pub struct EvalMessage {
     pub full_error_with_span: Option<String>,
     /// The text referred to by `.span`
     pub original: Option<String>,
+    /// [`content_hash`](crate::codemap::CodeMap::content_hash) of the file `.span` refers to,
+    /// used to detect the message refers to an outdated revision of the file.
+    pub content_hash: Option<u64>,
 }
 
 impl Display for EvalMessage {
@@ -148,9 +193,24 @@ impl EvalMessage {
     /// Produce an `EvalMessage` from a `starlark::Error`
     pub fn from_error(file: &Path, err: &crate::Error) -> Self {
         if let Some(span) = err.span() {
-            return Self::from_diagnostic(span, err.without_diagnostic(), err);
+            Self::from_diagnostic(span, err.without_diagnostic(), err)
+        } else {
+            Self::from_any_error(file, err)
+        }
+    }
+
+    /// Produce an `EvalMessage` from a `starlark::Error`, with the message translated by `catalog`.
+    pub fn from_error_localized(
+        file: &Path,
+        err: &crate::Error,
+        catalog: &dyn MessageCatalog,
+    ) -> Self {
+        let message = err.localized_message(catalog);
+        if let Some(span) = err.span() {
+            Self::from_diagnostic(span, message, err.localized(catalog))
+        } else {
+            Self::from_any_error(file, &message)
         }
-        Self::from_any_error(file, err)
     }
 
     /// Create an `EvalMessage` from any kind of error
@@ -165,6 +225,7 @@ impl EvalMessage {
             description: format!("{:#}", x),
             full_error_with_span: None,
             original: None,
+            content_hash: None,
         }
     }
 
@@ -183,6 +244,7 @@ impl EvalMessage {
             description: format!("{:#}", message),
             full_error_with_span: Some(full_error.to_string()),
             original: Some(original),
+            content_hash: Some(span.content_hash()),
         }
     }
 }
@@ -197,6 +259,7 @@ impl From<Lint> for EvalMessage {
             description: x.problem,
             full_error_with_span: None,
             original: Some(x.original),
+            content_hash: Some(x.location.content_hash()),
         }
     }
 }
@@ -52,6 +52,14 @@ impl LintWarning for UnderscoreWarning {
             UnderscoreWarning::UsingIgnored(..) => "using-ignored",
         }
     }
+
+    fn args(&self) -> Vec<String> {
+        match self {
+            UnderscoreWarning::UnderscoreDefinition(x) | UnderscoreWarning::UsingIgnored(x) => {
+                vec![x.clone()]
+            }
+        }
+    }
 }
 
 pub(crate) fn lint(module: &AstModule) -> Vec<LintT<UnderscoreWarning>> {
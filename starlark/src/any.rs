@@ -333,4 +333,71 @@ mod tests {
         #[allow(dead_code)] // field `0` is never read
         struct FooBar<'x, P: My<'x>>(&'x P);
     }
+
+    #[test]
+    fn test_provides_static_type_multiple_lifetimes() {
+        fn test<'a, A: AnyLifetime<'a>>(expected: TypeId) {
+            assert_eq!(expected, A::static_type_id());
+        }
+
+        #[derive(ProvidesStaticType)]
+        #[allow(dead_code)] // fields are never read
+        struct TwoLifetimes<'a, 'b, T>(&'a str, &'b T);
+        test::<TwoLifetimes<String>>(TypeId::of::<TwoLifetimes<'static, 'static, String>>());
+
+        fn convert_any<'p, 'a>(
+            x: &'p dyn AnyLifetime<'a>,
+        ) -> Option<&'p TwoLifetimes<'a, 'a, String>> {
+            x.downcast_ref()
+        }
+        let s = String::new();
+        let v = TwoLifetimes("x", &s);
+        assert!(convert_any(&v).is_some());
+    }
+
+    #[test]
+    fn test_provides_static_type_generic_bounds() {
+        fn test<'a, A: AnyLifetime<'a>>(expected: TypeId) {
+            assert_eq!(expected, A::static_type_id());
+        }
+
+        trait My<'a, X> {}
+        impl<'a, X> My<'a, X> for String {}
+        impl<'a, X> My<'a, X> for &'a str {}
+
+        // Type arguments in bounds, bounds in where clause.
+        #[derive(ProvidesStaticType)]
+        #[allow(dead_code)] // field `0` is never read
+        struct WithBounds<'a, P, Q: My<'a, P>>(&'a P, Q)
+        where
+            P: My<'a, Vec<Q>> + Display;
+        test::<WithBounds<String, &str>>(TypeId::of::<WithBounds<'static, String, &'static str>>());
+
+        #[derive(ProvidesStaticType)]
+        #[allow(dead_code)] // field `0` is never read
+        struct Unsized<'a, T: ?Sized>(&'a T);
+        test::<Unsized<str>>(TypeId::of::<Unsized<'static, str>>());
+    }
+
+    #[test]
+    fn test_provides_static_type_starlark_value_bound() {
+        use std::marker::PhantomData;
+
+        use crate::values::StarlarkValue;
+        use crate::values::Value;
+        use crate::values::ValueLike;
+        use crate::values::list::value::List;
+
+        fn test<'a, A: AnyLifetime<'a>>(expected: TypeId) {
+            assert_eq!(expected, A::static_type_id());
+        }
+
+        #[derive(ProvidesStaticType)]
+        #[allow(dead_code)] // field `0` is never read
+        struct Holder<'v, T, V>(T, V, PhantomData<&'v ()>)
+        where
+            T: StarlarkValue<'v>,
+            V: ValueLike<'v>;
+        test::<Holder<List, Value>>(TypeId::of::<Holder<'static, List<'static>, Value<'static>>>());
+    }
 }
@@ -36,6 +36,7 @@ use crate::environment::Globals;
 use crate::environment::GlobalsBuilder;
 use crate::environment::Module;
 use crate::eval::Evaluator;
+use crate::eval::FileLoader;
 use crate::eval::ReturnFileLoader;
 use crate::stdlib::PrintHandler;
 use crate::syntax::AstModule;
@@ -48,6 +49,8 @@ use crate::values::AllocValue;
 use crate::values::Heap;
 use crate::values::OwnedFrozenValue;
 use crate::values::Value;
+use crate::values::ValueDiff;
+use crate::values::ValueDiffOptions;
 
 fn mk_environment() -> GlobalsBuilder {
     GlobalsBuilder::extended().with(test_functions)
@@ -70,11 +73,30 @@ static ASSERTS_STAR: Lazy<FrozenModule> = Lazy::new(|| {
     m.freeze().unwrap()
 });
 
-fn assert_equals<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
-    if !a.equals(b)? {
-        Err(anyhow::anyhow!("assert_eq: expected {}, got {}", a, b).into())
-    } else {
-        Ok(NoneType)
+/// Options used to render `assert_eq` failures, which [`Assert`] passes as
+/// [`extra`](Evaluator::extra) unless `setup_eval` sets it to something else.
+fn diff_options(eval: &Evaluator) -> ValueDiffOptions {
+    eval.extra
+        .and_then(|extra| extra.downcast_ref::<ValueDiffOptions>())
+        .copied()
+        .unwrap_or_default()
+}
+
+fn assert_equals<'v>(
+    a: Value<'v>,
+    b: Value<'v>,
+    options: &ValueDiffOptions,
+) -> starlark::Result<NoneType> {
+    if a.equals(b)? {
+        return Ok(NoneType);
+    }
+    let diff = ValueDiff::new(a, b, options)?;
+    match diff.differences() {
+        // Scalars or values of different types: both values are short enough, or diff is useless.
+        [(path, _)] if path.is_empty() => {
+            Err(anyhow::anyhow!("assert_eq: expected {}, got {}", a, b).into())
+        }
+        _ => Err(anyhow::anyhow!("assert_eq: values differ:\n{}", diff).into()),
     }
 }
 
@@ -107,8 +129,12 @@ enum GcStrategy {
 #[starlark_module]
 // Deliberately qualify the GlobalsBuild type to test that we can
 fn asserts_star(builder: &mut crate::environment::GlobalsBuilder) {
-    fn eq<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
-        assert_equals(a, b)
+    fn eq<'v>(
+        a: Value<'v>,
+        b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<NoneType> {
+        assert_equals(a, b, &diff_options(eval))
     }
 
     fn ne<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
@@ -128,7 +154,11 @@ fn asserts_star(builder: &mut crate::environment::GlobalsBuilder) {
     }
 
     fn r#true(x: Value) -> starlark::Result<NoneType> {
-        assert_equals(Value::new_bool(x.to_bool()), Value::new_bool(true))
+        assert_equals(
+            Value::new_bool(x.to_bool()),
+            Value::new_bool(true),
+            &ValueDiffOptions::default(),
+        )
     }
 
     // We don't allow this at runtime - just to be compatible with the Go Starlark test suite
@@ -159,13 +189,12 @@ pub(crate) fn test_functions(builder: &mut GlobalsBuilder) {
         Ok(AllocStruct::EMPTY)
     }
 
-    // Approximate version of a method used by the Go test suite
-    fn set<'v>(xs: Value<'v>) -> anyhow::Result<Value<'v>> {
-        Ok(xs)
-    }
-
-    fn assert_eq<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
-        assert_equals(a, b)
+    fn assert_eq<'v>(
+        a: Value<'v>,
+        b: Value<'v>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<NoneType> {
+        assert_equals(a, b, &diff_options(eval))
     }
 
     fn assert_ne<'v>(a: Value<'v>, b: Value<'v>) -> starlark::Result<NoneType> {
@@ -220,12 +249,30 @@ pub struct Assert<'a> {
     dialect: Dialect,
     modules: HashMap<String, FrozenModule>,
     globals: Globals,
+    /// Loader for modules not added with `module_add`.
+    loader: Option<&'a (dyn FileLoader + 'a)>,
     gc_strategy: Option<GcStrategy>,
     setup_eval: Box<dyn Fn(&mut Evaluator)>,
     // Ideally `print_handler` should be set up in `setup_eval`
     // but if you know how to do it, show me how.
     print_handler: Option<&'a (dyn PrintHandler + 'a)>,
     static_typechecking: bool,
+    diff_options: ValueDiffOptions,
+}
+
+/// Modules added to the [`Assert`] environment, then the user supplied loader.
+struct AssertLoader<'a> {
+    modules: ReturnFileLoader<'a>,
+    fallback: Option<&'a (dyn FileLoader + 'a)>,
+}
+
+impl<'a> FileLoader for AssertLoader<'a> {
+    fn load(&self, path: &str) -> anyhow::Result<FrozenModule> {
+        match self.fallback {
+            Some(fallback) if !self.modules.modules.contains_key(path) => fallback.load(path),
+            _ => self.modules.load(path),
+        }
+    }
 }
 
 /// Construction and state management.
@@ -240,10 +287,12 @@ impl<'a> Assert<'a> {
             dialect: Dialect::Extended,
             modules: hashmap!["asserts.star".to_owned() => Lazy::force(&ASSERTS_STAR).dupe()],
             globals: Lazy::force(&GLOBALS).dupe(),
+            loader: None,
             gc_strategy: None,
             setup_eval: Box::new(|_| ()),
             print_handler: None,
             static_typechecking: true,
+            diff_options: ValueDiffOptions::default(),
         }
     }
 
@@ -268,6 +317,27 @@ impl<'a> Assert<'a> {
         self.static_typechecking = false;
     }
 
+    /// Configure how differences are rendered when `assert_eq` or `asserts.eq` fails.
+    /// The options are passed as the evaluator [`extra`](Evaluator::extra),
+    /// so the defaults are used if [`setup_eval`](Assert::setup_eval) sets `extra`.
+    ///
+    /// ```
+    /// # use starlark::assert::Assert;
+    /// # use starlark::values::ValueDiffOptions;
+    /// let mut a = Assert::new();
+    /// a.diff_options(ValueDiffOptions {
+    ///     max_differences: 1,
+    ///     ..ValueDiffOptions::default()
+    /// });
+    /// a.fail(
+    ///     "assert_eq([1, 2, 3], [1, 4, 5])",
+    ///     "[1]: expected 2, got 4\n  ... and 1 more differences",
+    /// );
+    /// ```
+    pub fn diff_options(&mut self, options: ValueDiffOptions) {
+        self.diff_options = options;
+    }
+
     fn with_gc<A>(&self, f: impl Fn(GcStrategy) -> A) -> A {
         match self.gc_strategy {
             None => {
@@ -292,7 +362,10 @@ impl<'a> Assert<'a> {
         for (k, v) in &self.modules {
             modules.insert(k.as_str(), v);
         }
-        let loader = ReturnFileLoader { modules: &modules };
+        let loader = AssertLoader {
+            modules: ReturnFileLoader { modules: &modules },
+            fallback: self.loader,
+        };
         let ast = AstModule::parse(path, program.to_owned(), &self.dialect)?;
         let gc_always = |_span: FileSpanRef, eval: &mut Evaluator| {
             eval.trigger_gc();
@@ -300,6 +373,9 @@ impl<'a> Assert<'a> {
         let mut eval = Evaluator::new(module);
         eval.enable_static_typechecking(self.static_typechecking);
         (self.setup_eval)(&mut eval);
+        if eval.extra.is_none() {
+            eval.extra = Some(&self.diff_options);
+        }
         if let Some(print_handler) = self.print_handler {
             eval.set_print_handler(print_handler);
         }
@@ -310,7 +386,7 @@ impl<'a> Assert<'a> {
             GcStrategy::Always => eval.before_stmt_fn(&gc_always),
         }
         eval.set_loader(&loader);
-        eval.eval_module(ast, &self.globals).map_err(Into::into)
+        eval.eval_module(ast, &self.globals)
     }
 
     fn execute_fail<'v>(
@@ -421,6 +497,22 @@ impl<'a> Assert<'a> {
         module
     }
 
+    /// Configure a [`FileLoader`] for `load` statements of modules which were not
+    /// added with [`module_add`](Assert::module_add) or [`module`](Assert::module).
+    ///
+    /// ```
+    /// # use starlark::assert::Assert;
+    /// # use starlark::eval::MockLoader;
+    /// let loader = MockLoader::new().module("lib.star", "def double(x): return x * 2");
+    /// let mut a = Assert::new();
+    /// a.loader(&loader);
+    /// a.eq("42", "load('lib.star', 'double'); double(21)");
+    /// a.fail("load('missing.star', 'x')", "missing.star");
+    /// ```
+    pub fn loader(&mut self, loader: &'a (dyn FileLoader + 'a)) {
+        self.loader = Some(loader);
+    }
+
     /// Set the [`Globals`] that future tests have access to.
     pub fn globals(&mut self, x: Globals) {
         self.globals = x;
@@ -428,11 +520,30 @@ impl<'a> Assert<'a> {
 
     /// Modify the [`Globals`] that future tests have access to.
     /// Note that this method will start from the default environment for [`Assert`],
-    /// ignoring any previous [`globals`](Assert::globals) or [`globals_add`](Assert::globals_add) calls.
+    /// ignoring any previous [`globals`](Assert::globals), [`globals_add`](Assert::globals_add)
+    /// or [`globals_extend`](Assert::globals_extend) calls.
     pub fn globals_add(&mut self, f: impl FnOnce(&mut GlobalsBuilder)) {
         self.globals(mk_environment().with(f).build())
     }
 
+    /// Add to the [`Globals`] that future tests have access to.
+    /// Unlike [`globals_add`](Assert::globals_add), this keeps everything already set up
+    /// with [`globals`](Assert::globals), [`globals_add`](Assert::globals_add)
+    /// or previous calls to this method.
+    ///
+    /// ```
+    /// # use starlark::assert::Assert;
+    /// let answer = 42;
+    /// let mut a = Assert::new();
+    /// a.globals_extend(|g| g.set("answer", answer));
+    /// a.globals_extend(|g| g.set("question", "?"));
+    /// a.eq("answer", "42");
+    /// a.eq("question", "'?'");
+    /// ```
+    pub fn globals_extend(&mut self, f: impl FnOnce(&mut GlobalsBuilder)) {
+        self.globals = GlobalsBuilder::from_globals(&self.globals).with(f).build();
+    }
+
     fn fails_with_name(&self, func: &str, program: &str, msgs: &[&str]) -> crate::Error {
         self.with_gc(|gc| {
             let module_env = Module::new();
@@ -0,0 +1,513 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Evaluate many modules in parallel.
+//!
+//! [`evaluate_all`] evaluates a set of modules and everything they `load()`,
+//! sharing one globals object and evaluating every dependency once,
+//! no matter how many modules load it.
+//!
+//! Each module is frozen into its own [`FrozenHeap`](crate::values::FrozenHeap),
+//! which is kept alive by the modules loading it. Frozen heaps are not pooled
+//! between the modules of a batch.
+//!
+//! ```
+//! use starlark::batch::evaluate_all;
+//! use starlark::batch::BatchLoader;
+//! use starlark::batch::BatchOptions;
+//! use starlark::environment::Globals;
+//!
+//! struct Sources;
+//!
+//! impl BatchLoader for Sources {
+//!     fn source(&self, id: &str) -> anyhow::Result<String> {
+//!         match id {
+//!             "lib.star" => Ok("def double(x): return x * 2".to_owned()),
+//!             "a.star" => Ok("load('lib.star', 'double')\na = double(1)".to_owned()),
+//!             "b.star" => Ok("load('lib.star', 'double')\nb = double(2)".to_owned()),
+//!             _ => Err(anyhow::anyhow!("unknown module `{}`", id)),
+//!         }
+//!     }
+//! }
+//!
+//! let results = evaluate_all(
+//!     &["a.star", "b.star"],
+//!     &Sources,
+//!     &Globals::standard(),
+//!     &BatchOptions::default(),
+//! );
+//! let b = results[1].result.as_ref().unwrap();
+//! assert_eq!(4, b.get("b").unwrap().value().unpack_i32().unwrap());
+//! ```
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::mem;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+
+use dupe::Dupe;
+
+use crate::environment::FrozenModule;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::eval::ReturnFileLoader;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+/// Provides module sources to [`evaluate_all`].
+pub trait BatchLoader: Sync {
+    /// Source code of the module with the given id.
+    fn source(&self, id: &str) -> anyhow::Result<String>;
+
+    /// Id of the module loaded with `load(path, ...)` from the module `from`.
+    ///
+    /// Default implementation uses `path` as the id.
+    fn resolve(&self, path: &str, from: &str) -> anyhow::Result<String> {
+        let _ = from;
+        Ok(path.to_owned())
+    }
+}
+
+/// Options for [`evaluate_all`].
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Dialect used to parse all the modules.
+    pub dialect: Dialect,
+    /// Number of threads evaluating modules. Zero means available parallelism.
+    pub threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            dialect: Dialect::Extended,
+            threads: 0,
+        }
+    }
+}
+
+/// Outcome of evaluating one of the requested modules.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Module id as passed to [`evaluate_all`].
+    pub id: String,
+    /// Frozen module, or the error which prevented evaluating it.
+    pub result: crate::Result<FrozenModule>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum BatchError {
+    #[error("Module `{0}` loaded from `{1}` failed to evaluate")]
+    DependencyFailed(String, String),
+    #[error("Module `{0}` cannot be evaluated because of a load cycle")]
+    Cycle(String),
+}
+
+/// Dependency of a module: `load()` path and the resolved module id.
+struct Dep {
+    path: String,
+    id: String,
+}
+
+enum Node {
+    /// Queued, being parsed or being evaluated.
+    Busy,
+    /// Parsed, waiting for `remaining` dependencies to be evaluated.
+    Waiting {
+        ast: Box<AstModule>,
+        deps: Vec<Dep>,
+        remaining: usize,
+    },
+    Done(crate::Result<FrozenModule>),
+}
+
+enum Task {
+    Parse(String),
+    Eval(String, Box<AstModule>, Vec<Dep>),
+}
+
+enum Outcome {
+    Parsed(String, Box<AstModule>, Vec<Dep>),
+    Done(String, crate::Result<FrozenModule>),
+}
+
+struct State {
+    nodes: HashMap<String, Node>,
+    /// Modules waiting for the given module.
+    dependents: HashMap<String, Vec<String>>,
+    queue: VecDeque<Task>,
+    /// Tasks taken from the queue but not finished.
+    in_flight: usize,
+    /// Payload of the first task which panicked. Workers stop once it is set,
+    /// and the panic is resumed on the calling thread.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl State {
+    fn schedule(&mut self, id: &str) {
+        if !self.nodes.contains_key(id) {
+            self.nodes.insert(id.to_owned(), Node::Busy);
+            self.queue.push_back(Task::Parse(id.to_owned()));
+        }
+    }
+
+    fn parsed(&mut self, id: String, ast: Box<AstModule>, deps: Vec<Dep>) {
+        let mut remaining = 0;
+        for dep in &deps {
+            self.schedule(&dep.id);
+            if !matches!(self.nodes.get(&dep.id), Some(Node::Done(_))) {
+                remaining += 1;
+                self.dependents
+                    .entry(dep.id.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+        }
+        if remaining == 0 {
+            self.queue.push_back(Task::Eval(id, ast, deps));
+        } else {
+            self.nodes.insert(
+                id,
+                Node::Waiting {
+                    ast,
+                    deps,
+                    remaining,
+                },
+            );
+        }
+    }
+
+    fn done(&mut self, id: String, result: crate::Result<FrozenModule>) {
+        for dependent in self.dependents.remove(&id).unwrap_or_default() {
+            let node = self.nodes.get_mut(&dependent).unwrap();
+            if let Node::Waiting { remaining, .. } = node {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    if let Node::Waiting { ast, deps, .. } = mem::replace(node, Node::Busy) {
+                        self.queue.push_back(Task::Eval(dependent, ast, deps));
+                    }
+                }
+            }
+        }
+        self.nodes.insert(id, Node::Done(result));
+    }
+
+    /// Frozen dependencies of a module, all of which must be evaluated.
+    fn deps(&self, id: &str, deps: &[Dep]) -> crate::Result<Vec<(String, FrozenModule)>> {
+        deps.iter()
+            .map(|dep| match self.nodes.get(&dep.id) {
+                Some(Node::Done(Ok(module))) => Ok((dep.path.clone(), module.dupe())),
+                _ => Err(crate::Error::new_other(BatchError::DependencyFailed(
+                    dep.id.clone(),
+                    id.to_owned(),
+                ))),
+            })
+            .collect()
+    }
+}
+
+fn parse(
+    id: &str,
+    loader: &dyn BatchLoader,
+    opts: &BatchOptions,
+) -> crate::Result<(Box<AstModule>, Vec<Dep>)> {
+    let source = loader.source(id)?;
+    let ast = AstModule::parse(id, source, &opts.dialect)?;
+    let mut deps: Vec<Dep> = Vec::new();
+    for load in ast.loads() {
+        if deps.iter().any(|d| d.path == load.module_id) {
+            continue;
+        }
+        let dep_id = loader.resolve(load.module_id, id).map_err(|e| {
+            let mut e = crate::Error::from(e);
+            e.set_span(load.span.span, &load.span.file);
+            e
+        })?;
+        deps.push(Dep {
+            path: load.module_id.to_owned(),
+            id: dep_id,
+        });
+    }
+    Ok((Box::new(ast), deps))
+}
+
+fn eval(
+    ast: Box<AstModule>,
+    deps: Vec<(String, FrozenModule)>,
+    globals: &Globals,
+) -> crate::Result<FrozenModule> {
+    let modules: HashMap<&str, &FrozenModule> =
+        deps.iter().map(|(path, m)| (path.as_str(), m)).collect();
+    let loader = ReturnFileLoader { modules: &modules };
+    let module = Module::new();
+    {
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        eval.eval_module(*ast, globals)?;
+    }
+    Ok(module.freeze()?)
+}
+
+fn run_task(
+    task: Task,
+    state: &Mutex<State>,
+    loader: &dyn BatchLoader,
+    globals: &Globals,
+    opts: &BatchOptions,
+) -> Outcome {
+    match task {
+        Task::Parse(id) => match parse(&id, loader, opts) {
+            Ok((ast, deps)) => Outcome::Parsed(id, ast, deps),
+            Err(e) => Outcome::Done(id, Err(e)),
+        },
+        Task::Eval(id, ast, deps) => {
+            let deps = state.lock().unwrap().deps(&id, &deps);
+            let result = deps.and_then(|deps| eval(ast, deps, globals));
+            Outcome::Done(id, result)
+        }
+    }
+}
+
+fn worker(
+    state: &Mutex<State>,
+    wake: &Condvar,
+    loader: &dyn BatchLoader,
+    globals: &Globals,
+    opts: &BatchOptions,
+) {
+    let mut guard = state.lock().unwrap();
+    loop {
+        if guard.panic.is_some() {
+            return;
+        } else if let Some(task) = guard.queue.pop_front() {
+            guard.in_flight += 1;
+            drop(guard);
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                run_task(task, state, loader, globals, opts)
+            }));
+            guard = state.lock().unwrap();
+            match outcome {
+                Ok(Outcome::Parsed(id, ast, deps)) => guard.parsed(id, ast, deps),
+                Ok(Outcome::Done(id, result)) => guard.done(id, result),
+                Err(payload) => {
+                    guard.panic.get_or_insert(payload);
+                }
+            }
+            guard.in_flight -= 1;
+            wake.notify_all();
+        } else if guard.in_flight == 0 {
+            return;
+        } else {
+            guard = wake.wait(guard).unwrap();
+        }
+    }
+}
+
+/// Evaluate modules with the given ids and all the modules they load.
+///
+/// Modules are parsed and evaluated on `opts.threads` threads as soon as their
+/// dependencies are available, and each module is evaluated at most once.
+/// Returns one result per distinct id in `ids`, in order of first appearance.
+/// If a dependency fails, the modules loading it fail too.
+///
+/// If the loader or an evaluation panics, the remaining work is abandoned
+/// and the panic is resumed on the calling thread.
+pub fn evaluate_all(
+    ids: &[&str],
+    loader: &dyn BatchLoader,
+    globals: &Globals,
+    opts: &BatchOptions,
+) -> Vec<BatchResult> {
+    let mut state = State {
+        nodes: HashMap::new(),
+        dependents: HashMap::new(),
+        queue: VecDeque::new(),
+        in_flight: 0,
+        panic: None,
+    };
+    let mut requested: Vec<&str> = Vec::new();
+    for id in ids {
+        if !requested.contains(id) {
+            requested.push(id);
+            state.schedule(id);
+        }
+    }
+
+    let threads = match opts.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let state = Mutex::new(state);
+    let wake = Condvar::new();
+    if threads == 1 {
+        worker(&state, &wake, loader, globals, opts);
+    } else {
+        thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| worker(&state, &wake, loader, globals, opts));
+            }
+        });
+    }
+
+    let state = state.into_inner().unwrap();
+    if let Some(payload) = state.panic {
+        panic::resume_unwind(payload);
+    }
+    let mut nodes = state.nodes;
+    requested
+        .into_iter()
+        .map(|id| {
+            let result = match nodes.remove(id) {
+                Some(Node::Done(result)) => result,
+                // Nothing left to run, so the module waits for itself.
+                _ => Err(crate::Error::new_other(BatchError::Cycle(id.to_owned()))),
+            };
+            BatchResult {
+                id: id.to_owned(),
+                result,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::panic;
+    use std::sync::Mutex;
+
+    use crate::batch::evaluate_all;
+    use crate::batch::BatchLoader;
+    use crate::batch::BatchOptions;
+    use crate::environment::Globals;
+
+    struct Sources {
+        sources: HashMap<&'static str, &'static str>,
+        requests: Mutex<Vec<String>>,
+    }
+
+    impl Sources {
+        fn new(sources: &[(&'static str, &'static str)]) -> Sources {
+            Sources {
+                sources: sources.iter().copied().collect(),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl BatchLoader for Sources {
+        fn source(&self, id: &str) -> anyhow::Result<String> {
+            self.requests.lock().unwrap().push(id.to_owned());
+            match self.sources.get(id) {
+                Some(s) => Ok((*s).to_owned()),
+                None => Err(anyhow::anyhow!("Unknown module `{}`", id)),
+            }
+        }
+
+        fn resolve(&self, path: &str, _from: &str) -> anyhow::Result<String> {
+            Ok(path.trim_start_matches("//").to_owned())
+        }
+    }
+
+    fn run(sources: &Sources, ids: &[&str], threads: usize) -> Vec<Result<i32, String>> {
+        let opts = BatchOptions {
+            threads,
+            ..BatchOptions::default()
+        };
+        evaluate_all(ids, sources, &Globals::standard(), &opts)
+            .into_iter()
+            .map(|r| match r.result {
+                Ok(m) => Ok(m.get("x").unwrap().value().unpack_i32().unwrap()),
+                Err(e) => Err(e.to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate_all_shared_deps() {
+        for threads in [1, 4] {
+            let sources = Sources::new(&[
+                ("lib", "x = 10"),
+                ("mid", "load('//lib', y = 'x')\nx = y + 1"),
+                ("a", "load('lib', 'x')\nload('mid', m = 'x')\nx = x + m"),
+                ("b", "load('//mid', 'x')"),
+            ]);
+            assert_eq!(
+                vec![Ok(21), Ok(11)],
+                run(&sources, &["a", "b", "a"], threads)
+            );
+            let mut requests = sources.requests.into_inner().unwrap();
+            requests.sort();
+            assert_eq!(vec!["a", "b", "lib", "mid"], requests);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_all_errors() {
+        let sources = Sources::new(&[
+            ("bad", "x = 1 +"),
+            ("uses_bad", "load('bad', 'x')"),
+            ("fails", "x = 1 // 0"),
+            ("ok", "x = 1"),
+            ("cycle1", "load('cycle2', 'x')"),
+            ("cycle2", "load('cycle1', 'x')"),
+        ]);
+        let res = run(
+            &sources,
+            &["uses_bad", "fails", "ok", "missing", "cycle1"],
+            2,
+        );
+        let err = |i: usize| res[i].as_ref().unwrap_err().as_str();
+        assert!(err(0).contains("Module `bad` loaded from `uses_bad` failed"));
+        assert!(err(1).contains("Floor division by zero"));
+        assert_eq!(Ok(1), res[2]);
+        assert!(err(3).contains("Unknown module `missing`"));
+        assert!(err(4).contains("`cycle1` cannot be evaluated because of a load cycle"));
+    }
+
+    #[test]
+    fn test_evaluate_all_panic() {
+        struct Panics;
+
+        impl BatchLoader for Panics {
+            fn source(&self, id: &str) -> anyhow::Result<String> {
+                match id {
+                    "boom" => panic!("loader panicked"),
+                    _ => Ok("load('boom', 'x')".to_owned()),
+                }
+            }
+        }
+
+        for threads in [1, 4] {
+            let opts = BatchOptions {
+                threads,
+                ..BatchOptions::default()
+            };
+            let payload = panic::catch_unwind(|| {
+                evaluate_all(&["a", "b", "c"], &Panics, &Globals::standard(), &opts)
+            })
+            .unwrap_err();
+            assert_eq!(Some(&"loader panicked"), payload.downcast_ref::<&str>());
+        }
+    }
+}
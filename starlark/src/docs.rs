@@ -21,13 +21,18 @@
 #![allow(missing_docs)]
 
 pub mod code;
+pub mod diff;
 pub mod markdown;
 mod parse;
+pub mod site;
+pub mod stub;
 
 use std::collections::HashMap;
 
 use allocative::Allocative;
 pub use code::render_docs_as_code;
+pub use diff::ApiChange;
+pub use diff::ApiParamKind;
 pub use parse::DocStringKind;
 pub use starlark_derive::StarlarkDocs;
 use starlark_map::small_map::SmallMap;
@@ -265,6 +270,16 @@ impl DocParam {
     pub fn get_doc_summary(&self) -> Option<&str> {
         self.get_doc_string().map(|ds| ds.summary.as_str())
     }
+
+    /// Name of the parameter, `None` for `*` and `/` markers.
+    pub(crate) fn name(&self) -> Option<&str> {
+        match self {
+            DocParam::Arg { name, .. }
+            | DocParam::Args { name, .. }
+            | DocParam::Kwargs { name, .. } => Some(name),
+            DocParam::OnlyNamedAfter | DocParam::OnlyPosBefore => None,
+        }
+    }
 }
 
 /// The main structure that represents the documentation for a given symbol / module.
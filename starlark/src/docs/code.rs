@@ -54,7 +54,7 @@ impl DocString {
 
     /// Render the docstring as in `render_as_code`, but surround it in triple quotes,
     /// a common convention in starlark docstrings.
-    fn render_as_quoted_code(&self) -> String {
+    pub(crate) fn render_as_quoted_code(&self) -> String {
         format!("\"\"\"\n{}\n\"\"\"", self.render_as_code())
     }
 }
@@ -81,7 +81,7 @@ impl DocModule {
 }
 
 impl DocFunction {
-    fn starlark_docstring(&self) -> Option<String> {
+    pub(crate) fn starlark_docstring(&self) -> Option<String> {
         let mut docs = String::new();
         if let Some(main_docs) = self.docs.as_ref().map(DocString::render_as_code) {
             docs.push_str(&main_docs);
@@ -124,6 +124,33 @@ impl DocFunction {
         }
     }
 
+    /// Render the signature on a single line, e.g. `f(x: int, y = 1) -> str`,
+    /// also returning the character range of the parameter `highlight` if there is one.
+    pub(crate) fn render_signature_line(
+        &self,
+        name: &str,
+        highlight: Option<&str>,
+    ) -> (String, Option<(usize, usize)>) {
+        let mut res = format!("{}(", name);
+        let mut range = None;
+        for (i, p) in self.params.iter().enumerate() {
+            if i != 0 {
+                res.push_str(", ");
+            }
+            let rendered = p.render_as_code();
+            if highlight.is_some() && highlight == p.name().map(|n| n.trim_start_matches('*')) {
+                let start = res.chars().count();
+                range = Some((start, start + rendered.chars().count()));
+            }
+            res.push_str(&rendered);
+        }
+        res.push(')');
+        if !self.ret.typ.is_any() {
+            res.push_str(&format!(" -> {}", self.ret.typ));
+        }
+        (res, range)
+    }
+
     pub fn render_as_code(&self, name: &str) -> String {
         let params: Vec<_> = self.params.iter().map(DocParam::render_as_code).collect();
         let spacer_len = if params.is_empty() {
@@ -0,0 +1,544 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Compare the public API of two versions of a module.
+
+use std::fmt;
+use std::fmt::Display;
+
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocModule;
+use crate::docs::DocParam;
+use crate::typing::Ty;
+
+/// How a parameter can be passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiParamKind {
+    /// Positional-only, at the given position.
+    PositionalOnly(usize),
+    /// Positional or named, at the given position.
+    Positional(usize),
+    /// Named-only.
+    NamedOnly,
+    /// `*args`.
+    Args,
+    /// `**kwargs`.
+    Kwargs,
+}
+
+impl ApiParamKind {
+    /// Whether every way of passing a parameter of kind `old` still works with this kind.
+    fn accepts(&self, old: &ApiParamKind) -> bool {
+        match (old, self) {
+            (old, new) if old == new => true,
+            // Callers passing it by position still can, and nobody could pass it by name.
+            (ApiParamKind::PositionalOnly(i), ApiParamKind::Positional(j)) => i == j,
+            // Callers passing it by name still can, and nobody could pass it by position.
+            (ApiParamKind::NamedOnly, ApiParamKind::Positional(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for ApiParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiParamKind::PositionalOnly(i) => write!(f, "positional-only at position {}", i),
+            ApiParamKind::Positional(i) => write!(f, "positional at position {}", i),
+            ApiParamKind::NamedOnly => write!(f, "named-only"),
+            ApiParamKind::Args => write!(f, "*args"),
+            ApiParamKind::Kwargs => write!(f, "**kwargs"),
+        }
+    }
+}
+
+/// A difference in the public API of a module, as found by [`DocModule::api_diff`].
+///
+/// Names of members of nested namespaces are joined with `.`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiChange {
+    /// A symbol was added.
+    Added { name: String },
+    /// A symbol was removed.
+    Removed { name: String },
+    /// A symbol changed between a function, a value, a type or a namespace.
+    KindChanged {
+        name: String,
+        old: &'static str,
+        new: &'static str,
+    },
+    /// The type of a value or of a type symbol changed.
+    TypeChanged { name: String, old: Ty, new: Ty },
+    /// A function gained a parameter.
+    ParamAdded {
+        function: String,
+        param: String,
+        /// Whether callers must now pass it.
+        required: bool,
+    },
+    /// A function lost a parameter.
+    ParamRemoved { function: String, param: String },
+    /// A parameter moved, or changed how it can be passed.
+    ParamKindChanged {
+        function: String,
+        param: String,
+        old: ApiParamKind,
+        new: ApiParamKind,
+    },
+    /// The type of a parameter changed.
+    ParamTypeChanged {
+        function: String,
+        param: String,
+        old: Ty,
+        new: Ty,
+    },
+    /// A default value was added, removed or changed. Values are the `repr()` of the default.
+    DefaultChanged {
+        function: String,
+        param: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// The return type of a function changed.
+    ReturnTypeChanged { function: String, old: Ty, new: Ty },
+}
+
+impl ApiChange {
+    /// Whether existing users of the module may stop working.
+    ///
+    /// Type changes are conservatively considered breaking. A changed default value
+    /// is not, although it may change behavior. A parameter kind change is breaking
+    /// unless it only allows more ways to pass the parameter, for example named-only
+    /// to positional or named is not breaking, but the reverse is.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            ApiChange::Added { .. } => false,
+            ApiChange::Removed { .. } => true,
+            ApiChange::KindChanged { .. } => true,
+            ApiChange::TypeChanged { .. } => true,
+            ApiChange::ParamAdded { required, .. } => *required,
+            ApiChange::ParamRemoved { .. } => true,
+            ApiChange::ParamKindChanged { old, new, .. } => !new.accepts(old),
+            ApiChange::ParamTypeChanged { .. } => true,
+            ApiChange::DefaultChanged { new, .. } => new.is_none(),
+            ApiChange::ReturnTypeChanged { .. } => true,
+        }
+    }
+}
+
+impl Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn default(x: &Option<String>) -> &str {
+            x.as_deref().unwrap_or("no default")
+        }
+
+        match self {
+            ApiChange::Added { name } => write!(f, "added `{}`", name),
+            ApiChange::Removed { name } => write!(f, "removed `{}`", name),
+            ApiChange::KindChanged { name, old, new } => {
+                write!(f, "`{}` changed from {} to {}", name, old, new)
+            }
+            ApiChange::TypeChanged { name, old, new } => {
+                write!(f, "type of `{}` changed from `{}` to `{}`", name, old, new)
+            }
+            ApiChange::ParamAdded {
+                function,
+                param,
+                required,
+            } => write!(
+                f,
+                "added {} parameter `{}` to `{}`",
+                if *required { "required" } else { "optional" },
+                param,
+                function
+            ),
+            ApiChange::ParamRemoved { function, param } => {
+                write!(f, "removed parameter `{}` from `{}`", param, function)
+            }
+            ApiChange::ParamKindChanged {
+                function,
+                param,
+                old,
+                new,
+            } => write!(
+                f,
+                "parameter `{}` of `{}` changed from {} to {}",
+                param, function, old, new
+            ),
+            ApiChange::ParamTypeChanged {
+                function,
+                param,
+                old,
+                new,
+            } => write!(
+                f,
+                "type of parameter `{}` of `{}` changed from `{}` to `{}`",
+                param, function, old, new
+            ),
+            ApiChange::DefaultChanged {
+                function,
+                param,
+                old,
+                new,
+            } => write!(
+                f,
+                "default of parameter `{}` of `{}` changed from {} to {}",
+                param,
+                function,
+                default(old),
+                default(new)
+            ),
+            ApiChange::ReturnTypeChanged { function, old, new } => write!(
+                f,
+                "return type of `{}` changed from `{}` to `{}`",
+                function, old, new
+            ),
+        }
+    }
+}
+
+struct Param<'a> {
+    name: &'a str,
+    kind: ApiParamKind,
+    ty: &'a Ty,
+    default: Option<&'a String>,
+}
+
+fn params(f: &DocFunction) -> Vec<Param<'_>> {
+    let mut named_only = false;
+    let mut pos = 0;
+    let mut res = Vec::new();
+    for p in &f.params {
+        match p {
+            DocParam::Arg {
+                name,
+                typ,
+                default_value,
+                ..
+            } => {
+                let kind = if named_only {
+                    ApiParamKind::NamedOnly
+                } else {
+                    pos += 1;
+                    ApiParamKind::Positional(pos - 1)
+                };
+                res.push(Param {
+                    name,
+                    kind,
+                    ty: typ,
+                    default: default_value.as_ref(),
+                });
+            }
+            DocParam::OnlyPosBefore => {
+                // Everything before `/` can't be passed by name.
+                for p in &mut res {
+                    if let ApiParamKind::Positional(i) = p.kind {
+                        p.kind = ApiParamKind::PositionalOnly(i);
+                    }
+                }
+            }
+            DocParam::OnlyNamedAfter => named_only = true,
+            DocParam::Args {
+                name,
+                tuple_elem_ty,
+                ..
+            } => {
+                named_only = true;
+                res.push(Param {
+                    name,
+                    kind: ApiParamKind::Args,
+                    ty: tuple_elem_ty,
+                    default: None,
+                });
+            }
+            DocParam::Kwargs {
+                name,
+                dict_value_ty,
+                ..
+            } => res.push(Param {
+                name,
+                kind: ApiParamKind::Kwargs,
+                ty: dict_value_ty,
+                default: None,
+            }),
+        }
+    }
+    res
+}
+
+fn diff_function(name: &str, old: &DocFunction, new: &DocFunction, res: &mut Vec<ApiChange>) {
+    let old_params = params(old);
+    let new_params = params(new);
+    for o in &old_params {
+        match new_params.iter().find(|n| n.name == o.name) {
+            None => res.push(ApiChange::ParamRemoved {
+                function: name.to_owned(),
+                param: o.name.to_owned(),
+            }),
+            Some(n) => {
+                if o.kind != n.kind {
+                    res.push(ApiChange::ParamKindChanged {
+                        function: name.to_owned(),
+                        param: o.name.to_owned(),
+                        old: o.kind.clone(),
+                        new: n.kind.clone(),
+                    });
+                }
+                if o.ty != n.ty {
+                    res.push(ApiChange::ParamTypeChanged {
+                        function: name.to_owned(),
+                        param: o.name.to_owned(),
+                        old: o.ty.clone(),
+                        new: n.ty.clone(),
+                    });
+                }
+                if o.default != n.default {
+                    res.push(ApiChange::DefaultChanged {
+                        function: name.to_owned(),
+                        param: o.name.to_owned(),
+                        old: o.default.cloned(),
+                        new: n.default.cloned(),
+                    });
+                }
+            }
+        }
+    }
+    for n in &new_params {
+        if !old_params.iter().any(|o| o.name == n.name) {
+            res.push(ApiChange::ParamAdded {
+                function: name.to_owned(),
+                param: n.name.to_owned(),
+                required: n.default.is_none()
+                    && !matches!(n.kind, ApiParamKind::Args | ApiParamKind::Kwargs),
+            });
+        }
+    }
+    if old.ret.typ != new.ret.typ {
+        res.push(ApiChange::ReturnTypeChanged {
+            function: name.to_owned(),
+            old: old.ret.typ.clone(),
+            new: new.ret.typ.clone(),
+        });
+    }
+}
+
+fn kind(x: &DocItem) -> &'static str {
+    match x {
+        DocItem::Module(_) => "namespace",
+        DocItem::Type(_) => "type",
+        DocItem::Member(DocMember::Function(_)) => "function",
+        DocItem::Member(DocMember::Property(_)) => "value",
+    }
+}
+
+fn diff_item(name: &str, old: &DocItem, new: &DocItem, res: &mut Vec<ApiChange>) {
+    match (old, new) {
+        (DocItem::Module(old), DocItem::Module(new)) => diff_module(Some(name), old, new, res),
+        (DocItem::Type(old), DocItem::Type(new)) => {
+            if old.ty != new.ty {
+                res.push(ApiChange::TypeChanged {
+                    name: name.to_owned(),
+                    old: old.ty.clone(),
+                    new: new.ty.clone(),
+                });
+            }
+        }
+        (DocItem::Member(DocMember::Function(old)), DocItem::Member(DocMember::Function(new))) => {
+            diff_function(name, old, new, res)
+        }
+        (DocItem::Member(DocMember::Property(old)), DocItem::Member(DocMember::Property(new))) => {
+            if old.typ != new.typ {
+                res.push(ApiChange::TypeChanged {
+                    name: name.to_owned(),
+                    old: old.typ.clone(),
+                    new: new.typ.clone(),
+                });
+            }
+        }
+        _ => res.push(ApiChange::KindChanged {
+            name: name.to_owned(),
+            old: kind(old),
+            new: kind(new),
+        }),
+    }
+}
+
+fn diff_module(prefix: Option<&str>, old: &DocModule, new: &DocModule, res: &mut Vec<ApiChange>) {
+    let full_name = |name: &str| match prefix {
+        Some(prefix) => format!("{}.{}", prefix, name),
+        None => name.to_owned(),
+    };
+    for (name, o) in &old.members {
+        match new.members.get(name) {
+            None => res.push(ApiChange::Removed {
+                name: full_name(name),
+            }),
+            Some(n) => diff_item(&full_name(name), o, n, res),
+        }
+    }
+    for name in new.members.keys() {
+        if !old.members.contains_key(name) {
+            res.push(ApiChange::Added {
+                name: full_name(name),
+            });
+        }
+    }
+}
+
+impl DocModule {
+    /// Compare the public API of this (old) version of a module with a `new` version.
+    ///
+    /// Documentation of a module is obtained with
+    /// [`FrozenModule::documentation`](crate::environment::FrozenModule::documentation).
+    /// Changes to docstrings are ignored.
+    pub fn api_diff(&self, new: &DocModule) -> Vec<ApiChange> {
+        let mut res = Vec::new();
+        diff_module(None, self, new, &mut res);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::docs::diff::ApiChange;
+    use crate::docs::DocFunction;
+    use crate::docs::DocItem;
+    use crate::docs::DocMember;
+    use crate::docs::DocModule;
+    use crate::docs::DocParam;
+    use crate::typing::Ty;
+
+    fn api_diff(old: &str, new: &str) -> Vec<ApiChange> {
+        let a = Assert::new();
+        let old = a.pass_module(old).documentation();
+        let new = a.pass_module(new).documentation();
+        old.api_diff(&new)
+    }
+
+    fn describe(changes: &[ApiChange]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|c| format!("{}{}", if c.is_breaking() { "!" } else { "" }, c))
+            .collect()
+    }
+
+    #[test]
+    fn test_api_diff() {
+        let changes = api_diff(
+            r#"
+def f(a, b = 1, *, c = "x"):
+    pass
+def g(a):
+    pass
+def _private():
+    pass
+removed = 1
+changed = 1
+"#,
+            r#"
+def f(b, a = 2, *, c = "y", d = None, e):
+    pass
+g = 1
+def _private(x):
+    pass
+changed = "x"
+added = 1
+"#,
+        );
+        assert_eq!(
+            describe(&changes),
+            &[
+                "!parameter `a` of `f` changed from positional at position 0 to positional at position 1",
+                "default of parameter `a` of `f` changed from no default to 2",
+                "!parameter `b` of `f` changed from positional at position 1 to positional at position 0",
+                "!default of parameter `b` of `f` changed from 1 to no default",
+                "default of parameter `c` of `f` changed from \"x\" to \"y\"",
+                "added optional parameter `d` to `f`",
+                "!added required parameter `e` to `f`",
+                "!`g` changed from function to value",
+                "!removed `removed`",
+                "!type of `changed` changed from `int` to `str`",
+                "added `added`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_api_diff_same() {
+        let program = "def f(x: int, *args, **kwargs) -> str:\n    return ''\ny = [1]\n";
+        assert_eq!(api_diff(program, program), Vec::new());
+    }
+
+    #[test]
+    fn test_api_diff_positional_only() {
+        fn module(params: Vec<DocParam>) -> DocModule {
+            DocModule {
+                docs: None,
+                members: [(
+                    "f".to_owned(),
+                    DocItem::Member(DocMember::Function(DocFunction {
+                        params,
+                        ..DocFunction::default()
+                    })),
+                )]
+                .into_iter()
+                .collect(),
+            }
+        }
+
+        fn arg(name: &str) -> DocParam {
+            DocParam::Arg {
+                name: name.to_owned(),
+                docs: None,
+                typ: Ty::any(),
+                default_value: None,
+            }
+        }
+
+        let old = module(vec![arg("a"), arg("b"), DocParam::OnlyPosBefore, arg("c")]);
+        let new = module(vec![arg("a"), DocParam::OnlyPosBefore, arg("b"), arg("c")]);
+        assert_eq!(
+            describe(&old.api_diff(&new)),
+            &[
+                "parameter `b` of `f` changed from positional-only at position 1 to positional at position 1"
+            ]
+        );
+        assert_eq!(
+            describe(&new.api_diff(&old)),
+            &[
+                "!parameter `b` of `f` changed from positional at position 1 to positional-only at position 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_api_diff_named_only() {
+        let named_only = "def f(a, *, b):\n    pass\n";
+        let positional = "def f(a, b):\n    pass\n";
+        assert_eq!(
+            describe(&api_diff(named_only, positional)),
+            &["parameter `b` of `f` changed from named-only to positional at position 1"]
+        );
+        assert_eq!(
+            describe(&api_diff(positional, named_only)),
+            &["!parameter `b` of `f` changed from positional at position 1 to named-only"]
+        );
+    }
+}
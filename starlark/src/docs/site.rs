@@ -0,0 +1,419 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Documentation site for a workspace of Starlark modules.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Captures;
+use regex::Regex;
+use starlark_map::small_map::SmallMap;
+
+use crate::docs::markdown::render_doc_item;
+use crate::docs::DocFunction;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::docs::DocModule;
+use crate::docs::DocParam;
+use crate::docs::DocString;
+
+/// Name of the index page.
+pub const INDEX_PAGE: &str = "index.md";
+
+/// Documented module of a workspace.
+struct SiteModule {
+    docs: DocModule,
+    /// Symbols bound by `load()`: local name to module path and name in that module.
+    loads: SmallMap<String, (String, String)>,
+}
+
+/// Documentation of a set of modules, rendered as markdown pages
+/// with an index page, one page per module, and links between them.
+///
+/// Symbols a module re-exports from another module of the site with `load()`
+/// are listed as links to the module which defines them.
+/// Symbol names in docstrings written as `` `name` `` are linked to the documentation
+/// of the symbol, if `name` is defined or loaded in the module of the docstring.
+///
+/// ```
+/// use starlark::docs::site::DocSite;
+/// use starlark::docs::DocModule;
+///
+/// let mut site = DocSite::new();
+/// site.add_module("lib.star", DocModule::default(), []);
+/// site.add_module("main.star", DocModule::default(), [("f", "lib.star", "g")]);
+/// let pages = site.render();
+/// assert_eq!(3, pages.len());
+/// ```
+#[derive(Default)]
+pub struct DocSite {
+    modules: SmallMap<String, SiteModule>,
+}
+
+impl DocSite {
+    /// Create an empty site.
+    pub fn new() -> DocSite {
+        DocSite::default()
+    }
+
+    /// Add a module with its documentation, for example from
+    /// [`FrozenModule::documentation`](crate::environment::FrozenModule::documentation).
+    ///
+    /// `path` is the path other modules use to `load()` this module.
+    /// `loads` are the symbols bound by `load()` statements of the module
+    /// (for example obtained with [`AstModule::loads`](crate::syntax::AstModule::loads)),
+    /// as `(local name, module path, name in that module)`.
+    pub fn add_module<'a>(
+        &mut self,
+        path: &str,
+        docs: DocModule,
+        loads: impl IntoIterator<Item = (&'a str, &'a str, &'a str)>,
+    ) {
+        let loads = loads
+            .into_iter()
+            .map(|(local, module, their)| (local.to_owned(), (module.to_owned(), their.to_owned())))
+            .collect();
+        self.modules
+            .insert(path.to_owned(), SiteModule { docs, loads });
+    }
+
+    /// Name of the page documenting the module with given path.
+    pub fn page_name(path: &str) -> String {
+        let name: String = path
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}.md", name.trim_start_matches('_'))
+    }
+
+    /// Module and name where the symbol visible as `name` in `module` is defined.
+    /// `None` if the symbol is not a public symbol of a module of this site.
+    fn resolve<'s>(&'s self, module: &'s str, name: &'s str) -> Option<(&'s str, &'s str)> {
+        let (mut module, mut name) = (module, name);
+        // Bound the number of steps, `load()` cycles are errors, but docs can be stale.
+        for _ in 0..=self.modules.len() {
+            let m = self.modules.get(module)?;
+            match m.loads.get(name) {
+                Some((from, their)) => {
+                    module = from;
+                    name = their;
+                }
+                None => return m.docs.members.contains_key(name).then_some((module, name)),
+            }
+        }
+        None
+    }
+
+    /// Markdown link target for a symbol, relative to the page of `from`.
+    fn link(&self, from: &str, module: &str, name: &str) -> String {
+        let anchor = name.to_lowercase();
+        if from == module {
+            format!("#{}", anchor)
+        } else {
+            format!("{}#{}", Self::page_name(module), anchor)
+        }
+    }
+
+    /// Replace `` `name` `` in the text with links, except in code blocks.
+    fn link_text(&self, module: &str, text: &str) -> String {
+        static SYMBOL_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"`([A-Za-z_][A-Za-z0-9_]*)`").unwrap());
+
+        let mut in_code_block = false;
+        text.lines()
+            .map(|line| {
+                if line.trim_start().starts_with("```") {
+                    in_code_block = !in_code_block;
+                }
+                if in_code_block {
+                    return line.to_owned();
+                }
+                SYMBOL_RE
+                    .replace_all(line, |c: &Captures| match self.resolve(module, &c[1]) {
+                        Some((m, n)) => format!("[`{}`]({})", &c[1], self.link(module, m, n)),
+                        None => c[0].to_owned(),
+                    })
+                    .into_owned()
+            })
+            .join("\n")
+    }
+
+    fn link_doc_string(&self, module: &str, docs: &mut Option<DocString>) {
+        if let Some(docs) = docs {
+            docs.summary = self.link_text(module, &docs.summary);
+            if let Some(details) = &mut docs.details {
+                *details = self.link_text(module, details);
+            }
+        }
+    }
+
+    fn link_function(&self, module: &str, function: &mut DocFunction) {
+        self.link_doc_string(module, &mut function.docs);
+        self.link_doc_string(module, &mut function.ret.docs);
+        for param in &mut function.params {
+            match param {
+                DocParam::Arg { docs, .. }
+                | DocParam::Args { docs, .. }
+                | DocParam::Kwargs { docs, .. } => self.link_doc_string(module, docs),
+                DocParam::OnlyNamedAfter | DocParam::OnlyPosBefore => {}
+            }
+        }
+    }
+
+    fn link_member(&self, module: &str, member: &mut DocMember) {
+        match member {
+            DocMember::Function(f) => self.link_function(module, f),
+            DocMember::Property(p) => self.link_doc_string(module, &mut p.docs),
+        }
+    }
+
+    fn link_item(&self, module: &str, item: &mut DocItem) {
+        match item {
+            DocItem::Module(m) => {
+                self.link_doc_string(module, &mut m.docs);
+                for member in m.members.values_mut() {
+                    self.link_item(module, member);
+                }
+            }
+            DocItem::Type(t) => {
+                self.link_doc_string(module, &mut t.docs);
+                for member in t.members.values_mut() {
+                    self.link_member(module, member);
+                }
+            }
+            DocItem::Member(m) => self.link_member(module, m),
+        }
+    }
+
+    fn render_module(&self, path: &str, module: &SiteModule) -> String {
+        let mut local = DocModule {
+            docs: module.docs.docs.clone(),
+            members: SmallMap::new(),
+        };
+        let mut reexports = Vec::new();
+        for (name, item) in &module.docs.members {
+            match self.resolve(path, name) {
+                Some((m, n)) if m != path => reexports.push((name, m, n)),
+                _ => {
+                    let mut item = item.clone();
+                    self.link_item(path, &mut item);
+                    local.members.insert(name.clone(), item);
+                }
+            }
+        }
+        self.link_doc_string(path, &mut local.docs);
+
+        let mut page = render_doc_item(path, &DocItem::Module(local));
+        if !reexports.is_empty() {
+            page.push_str("\n\n## Re-exports\n\n");
+            for (name, m, n) in reexports.into_iter().sorted() {
+                let _ = writeln!(
+                    page,
+                    "* `{}`: [`{}`]({}) from `{}`",
+                    name,
+                    n,
+                    self.link(path, m, n),
+                    m
+                );
+            }
+        }
+        page
+    }
+
+    fn render_index(&self) -> String {
+        let mut page = "# Index\n\n## Modules\n\n".to_owned();
+        for (path, module) in self.modules.iter().sorted_by_key(|(p, _)| *p) {
+            let _ = write!(page, "* [`{}`]({})", path, Self::page_name(path));
+            if let Some(docs) = &module.docs.docs {
+                let _ = write!(page, ": {}", docs.summary);
+            }
+            page.push('\n');
+        }
+
+        let symbols: Vec<(&str, &str)> = self
+            .modules
+            .iter()
+            .flat_map(|(path, module)| {
+                module
+                    .docs
+                    .members
+                    .keys()
+                    .filter(|name| !module.loads.contains_key(*name))
+                    .map(move |name| (name.as_str(), path.as_str()))
+            })
+            .sorted()
+            .collect();
+        if !symbols.is_empty() {
+            page.push_str("\n## Symbols\n\n");
+            for (name, path) in symbols {
+                let _ = writeln!(
+                    page,
+                    "* [`{}`]({}) in `{}`",
+                    name,
+                    self.link("", path, name),
+                    path
+                );
+            }
+        }
+        page
+    }
+
+    /// Render the site: pairs of page name and markdown content.
+    /// The first page is the [`INDEX_PAGE`], followed by module pages in the order of adding.
+    pub fn render(&self) -> Vec<(String, String)> {
+        let mut pages = vec![(INDEX_PAGE.to_owned(), self.render_index())];
+        for (path, module) in &self.modules {
+            pages.push((Self::page_name(path), self.render_module(path, module)));
+        }
+        pages
+    }
+
+    /// Render the site and write the pages into the directory, creating it if needed.
+    pub fn write(&self, dir: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (name, content) in self.render() {
+            fs::write(dir.join(name), content)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::docs::site::DocSite;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    const LIB: &str = r#"
+"""Helpers."""
+
+def helper(x):
+    """Returns `x`, see also `other`.
+
+    Example:
+
+    ```
+    `helper` is not linked here
+    ```
+    """
+    return x
+
+def other():
+    """Calls `helper`."""
+    pass
+"#;
+
+    const MAIN: &str = r#"
+"""Entry points, uses `helper`."""
+
+load("lib", "helper", o = "other")
+
+def main():
+    """Calls `o` and `unknown`."""
+    return o()
+
+helper = helper
+"#;
+
+    fn site() -> DocSite {
+        let mut a = Assert::new();
+        let lib = a.module("lib", LIB).documentation();
+        let main = a.module("main", MAIN).documentation();
+        let ast = AstModule::parse("main", MAIN.to_owned(), &Dialect::Extended).unwrap();
+        let loads: Vec<(&str, &str, &str)> = ast
+            .loads()
+            .iter()
+            .flat_map(|l| {
+                l.symbols
+                    .iter()
+                    .map(|(local, their)| (*local, l.module_id, *their))
+            })
+            .collect();
+
+        let mut site = DocSite::new();
+        site.add_module("lib", lib, []);
+        site.add_module("main", main, loads);
+        site
+    }
+
+    #[test]
+    fn test_pages() {
+        let pages = site().render();
+        let names: Vec<&str> = pages.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(vec!["index.md", "lib.md", "main.md"], names);
+    }
+
+    #[test]
+    fn test_index() {
+        let index = &site().render()[0].1;
+        assert!(index.contains("* [`lib`](lib.md): Helpers.\n"), "{}", index);
+        assert!(
+            index.contains("* [`helper`](lib.md#helper) in `lib`\n"),
+            "{}",
+            index
+        );
+        assert!(
+            index.contains("* [`main`](main.md#main) in `main`\n"),
+            "{}",
+            index
+        );
+        assert!(!index.contains("in `main`\n* [`o`]"), "{}", index);
+    }
+
+    #[test]
+    fn test_cross_links() {
+        let pages = site().render();
+        let lib = &pages[1].1;
+        assert!(
+            lib.contains("Returns `x`, see also [`other`](#other)."),
+            "{}",
+            lib
+        );
+        assert!(lib.contains("Calls [`helper`](#helper)."), "{}", lib);
+        assert!(lib.contains("\n`helper` is not linked here\n"), "{}", lib);
+
+        let main = &pages[2].1;
+        assert!(main.contains("uses [`helper`](lib.md#helper)."), "{}", main);
+        assert!(
+            main.contains("Calls [`o`](lib.md#other) and `unknown`."),
+            "{}",
+            main
+        );
+    }
+
+    #[test]
+    fn test_reexports() {
+        let main = &site().render()[2].1;
+        assert!(
+            main.contains("## Re-exports\n\n* `helper`: [`helper`](lib.md#helper) from `lib`\n"),
+            "{}",
+            main
+        );
+        assert!(!main.contains("## helper"), "{}", main);
+    }
+}
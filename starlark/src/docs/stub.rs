@@ -0,0 +1,202 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Interface stubs (`.star-api` files) for analyzed modules.
+//!
+//! A stub contains only the public signatures, docstrings and constant types of
+//! a module. Function bodies and default values are replaced with `...`, so the
+//! stub can be parsed with [`Dialect::Extended`](crate::syntax::Dialect::Extended)
+//! and typechecked to obtain the module [`Interface`](crate::typing::Interface)
+//! without evaluating the original source.
+//!
+//! The `starlark --lsp` server resolves a `load` of a missing `foo.star` to
+//! `foo.star-api` if it exists, so loads of modules shipped only as stubs get
+//! completions, hover docs and go-to-definition.
+
+use itertools::Itertools;
+
+use crate::docs::DocFunction;
+use crate::docs::DocMember;
+use crate::docs::DocModule;
+use crate::docs::DocParam;
+use crate::docs::DocProperty;
+use crate::docs::DocString;
+
+/// Conventional file extension for interface stubs.
+pub const STUB_EXTENSION: &str = "star-api";
+
+impl DocModule {
+    /// Render the module as an interface stub.
+    ///
+    /// Functions are rendered as `def` statements with `...` bodies and `...`
+    /// for default values. Constants are rendered as `NAME: type = ...`.
+    /// Members which cannot be represented as a function or a constant are skipped.
+    pub fn render_as_stub(&self) -> String {
+        let header = self
+            .docs
+            .as_ref()
+            .map(DocString::render_as_quoted_code)
+            .into_iter();
+        let members = self.members.iter().filter_map(|(name, item)| {
+            match item.try_as_member_with_collapsed_object().ok()? {
+                DocMember::Function(f) => Some(f.render_as_stub(name)),
+                DocMember::Property(p) => Some(p.render_as_stub(name)),
+            }
+        });
+        let mut res = header.chain(members).join("\n\n");
+        res.push('\n');
+        res
+    }
+}
+
+impl DocFunction {
+    fn render_as_stub(&self, name: &str) -> String {
+        let params = self.params.iter().map(DocParam::render_as_stub).join(", ");
+        let ret = match &self.ret.typ {
+            t if t.is_any() => String::new(),
+            t => format!(" -> {}", t),
+        };
+        let docstring = self
+            .starlark_docstring()
+            .map(|ds| format!("{}\n", ds))
+            .unwrap_or_default();
+        format!("def {}({}){}:\n{}    ...", name, params, ret, docstring)
+    }
+}
+
+impl DocParam {
+    fn render_as_stub(&self) -> String {
+        match self {
+            DocParam::Arg {
+                name,
+                typ,
+                default_value,
+                ..
+            } => {
+                let default = if default_value.is_some() {
+                    " = ..."
+                } else {
+                    ""
+                };
+                if typ.is_any() {
+                    format!("{}{}", name, default)
+                } else {
+                    format!("{}: {}{}", name, typ, default)
+                }
+            }
+            DocParam::OnlyNamedAfter => "*".to_owned(),
+            DocParam::OnlyPosBefore => "/".to_owned(),
+            DocParam::Args {
+                name,
+                tuple_elem_ty: typ,
+                ..
+            }
+            | DocParam::Kwargs {
+                name,
+                dict_value_ty: typ,
+                ..
+            } => {
+                if typ.is_any() {
+                    name.clone()
+                } else {
+                    format!("{}: {}", name, typ)
+                }
+            }
+        }
+    }
+}
+
+impl DocProperty {
+    fn render_as_stub(&self, name: &str) -> String {
+        // Without an annotation the constant would be inferred as `ellipsis`.
+        let typ = match &self.typ {
+            t if t.is_any() => "typing.Any".to_owned(),
+            t => t.to_string(),
+        };
+        match &self.docs {
+            Some(ds) => format!("{}\n{}: {} = ...", ds.render_as_quoted_code(), name, typ),
+            None => format!("{}: {} = ...", name, typ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+    use crate::typing::AstModuleTypecheck;
+    use crate::typing::Ty;
+
+    #[test]
+    fn test_render_as_stub() {
+        let src = r#"
+"""Module docs."""
+
+LIMIT = 10
+_PRIVATE = 1
+
+def greet(name: str, times: int = 2, *args, sep = ", ", **kwargs) -> str:
+    """Greet someone.
+
+    Args:
+        name: Who to greet.
+    """
+    return sep.join([name] * times)
+
+def _hidden():
+    pass
+"#;
+        let globals = Globals::standard();
+        let module = Module::new();
+        {
+            let ast = AstModule::parse("a.star", src.to_owned(), &Dialect::Extended).unwrap();
+            let mut eval = Evaluator::new(&module);
+            eval.eval_module(ast, &globals).unwrap();
+        }
+        let module = module.freeze().unwrap();
+        let stub = module.documentation().render_as_stub();
+
+        let expected = r#""""
+Module docs.
+"""
+
+LIMIT: int = ...
+
+def greet(name: str, times: int = ..., *args, sep = ..., **kwargs) -> str:
+    """
+    Greet someone.
+
+    Args:
+        name:     Who to greet.
+    """
+    ...
+"#;
+        assert_eq!(expected, stub);
+
+        let ast = AstModule::parse("a.star-api", stub, &Dialect::Extended).unwrap();
+        let (errors, _, interface, _) = ast.typecheck(&globals, &HashMap::new());
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(Some(&Ty::int()), interface.get("LIMIT"));
+        assert!(interface.get("greet").is_some());
+        assert!(interface.get("_hidden").is_none());
+    }
+}
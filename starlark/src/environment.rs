@@ -22,15 +22,20 @@
 //! User executions store their values in a [`Module`], which have to be converted to a
 //! [`FrozenModule`] using [`freeze`](Module::freeze) before they can be `load()`'d as a dependency.
 
+pub(crate) mod disabled;
 mod globals;
-mod methods;
+pub(crate) mod methods;
 mod module_dump;
+mod module_serialize;
+mod module_visit;
 mod modules;
 pub(crate) mod names;
 pub(crate) mod slots;
 
 pub use globals::*;
 pub use methods::*;
+pub use module_visit::FrozenScalar;
+pub use module_visit::FrozenValueEvent;
 pub use modules::*;
 use thiserror::Error;
 
@@ -0,0 +1,95 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Placeholders for globals of disabled features.
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::starlark_simple_value;
+use crate::typing::Ty;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum DisabledGlobalError {
+    #[error("`{0}` is not available because feature `{1}` is disabled")]
+    Disabled(String, String),
+}
+
+/// Value of a global registered by [`GlobalsBuilder::feature`](crate::environment::GlobalsBuilder::feature)
+/// when the feature is disabled, so code guarded by `has_feature` compiles.
+/// Calling it fails, and so does calling its attributes, e.g. `json.encode(x)`.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub(crate) struct DisabledGlobal {
+    /// Name of the global, or path to the attribute, e.g. `json.encode`.
+    name: String,
+    feature: String,
+}
+
+impl DisabledGlobal {
+    pub(crate) fn new(name: &str, feature: &str) -> DisabledGlobal {
+        DisabledGlobal {
+            name: name.to_owned(),
+            feature: feature.to_owned(),
+        }
+    }
+}
+
+impl Display for DisabledGlobal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<disabled {}>", self.name)
+    }
+}
+
+starlark_simple_value!(DisabledGlobal);
+
+#[starlark_value(type = "disabled_global")]
+impl<'v> StarlarkValue<'v> for DisabledGlobal {
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        _args: &Arguments<'v, '_>,
+        _eval: &mut Evaluator<'v, '_, '_>,
+    ) -> crate::Result<Value<'v>> {
+        Err(crate::Error::new_other(DisabledGlobalError::Disabled(
+            self.name.clone(),
+            self.feature.clone(),
+        )))
+    }
+
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        Some(heap.alloc(DisabledGlobal {
+            name: format!("{}.{}", self.name, attribute),
+            feature: self.feature.clone(),
+        }))
+    }
+
+    fn typechecker_ty(&self) -> Option<Ty> {
+        // Uses are checked at runtime, where they fail.
+        Some(Ty::any())
+    }
+}
@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+use std::fmt;
+use std::fmt::Debug;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -25,11 +27,13 @@ use once_cell::sync::OnceCell;
 
 use crate::collections::symbol::map::SymbolMap;
 use crate::collections::SmallMap;
+use crate::collections::SmallSet;
 use crate::docs::DocItem;
 use crate::docs::DocMember;
 use crate::docs::DocModule;
 use crate::docs::DocString;
 use crate::docs::DocStringKind;
+use crate::environment::disabled::DisabledGlobal;
 use crate::stdlib;
 pub use crate::stdlib::LibraryExtension;
 use crate::typing::Ty;
@@ -43,6 +47,7 @@ use crate::values::FrozenHeap;
 use crate::values::FrozenHeapRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
+use crate::values::Heap;
 use crate::values::Value;
 
 /// The global values available during execution.
@@ -55,6 +60,10 @@ struct GlobalsData {
     variables: SymbolMap<FrozenValue>,
     variable_names: Vec<FrozenStringValue>,
     docstring: Option<String>,
+    /// Sorted.
+    features: Vec<String>,
+    #[allocative(skip)]
+    coercions: Coercions,
 }
 
 /// Used to build a [`Globals`] value.
@@ -71,6 +80,23 @@ pub struct GlobalsBuilder {
     /// FIXME(JakobDegen): This should probably be removed. Having a docstring on a `GlobalsBuilder`
     /// doesn't really make sense, because there's no way good way to combine multiple docstrings.
     docstring: Option<String>,
+    /// Coercions of native function arguments.
+    coercions: Coercions,
+    /// Features declared with [`add_feature`](GlobalsBuilder::add_feature).
+    features: SmallSet<String>,
+}
+
+/// Conversion of a value passed to a native function parameter,
+/// see [`GlobalsBuilder::coercion`].
+type Coercion = dyn for<'v> Fn(Value<'v>, &'v Heap) -> Option<Value<'v>> + Send + Sync;
+
+#[derive(Clone, Default)]
+struct Coercions(Vec<Arc<Coercion>>);
+
+impl Debug for Coercions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Coercions").field(&self.0.len()).finish()
+    }
 }
 
 impl Globals {
@@ -130,6 +156,62 @@ impl Globals {
         self.0.variables.iter().map(|(n, v)| (n.as_str(), *v))
     }
 
+    /// Create a [`Globals`] with only the variables of this one for which `keep` returns `true`.
+    ///
+    /// Struct members are not filtered, a kept struct keeps all its members.
+    pub fn filter(&self, keep: impl Fn(&str) -> bool) -> Globals {
+        let mut variables = SymbolMap::new();
+        for (name, value) in self.0.variables.iter() {
+            if keep(name.as_str()) {
+                variables.insert(name.as_str(), *value);
+            }
+        }
+        Globals(Arc::new(GlobalsData {
+            heap: self.0.heap.dupe(),
+            variables,
+            variable_names: self
+                .0
+                .variable_names
+                .iter()
+                .copied()
+                .filter(|name| keep(name.as_str()))
+                .collect(),
+            docstring: self.0.docstring.clone(),
+            features: self.0.features.clone(),
+            coercions: self.0.coercions.clone(),
+        }))
+    }
+
+    /// Features declared with [`GlobalsBuilder::add_feature`], sorted.
+    pub fn features(&self) -> impl Iterator<Item = &str> {
+        self.0.features.iter().map(|f| f.as_str())
+    }
+
+    /// Feature declared with [`GlobalsBuilder::add_feature`].
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.0
+            .features
+            .binary_search_by(|f| f.as_str().cmp(feature))
+            .is_ok()
+    }
+
+    /// Values produced by coercions registered with [`GlobalsBuilder::coercion`]
+    /// which accept given value.
+    pub(crate) fn coerce<'v, 'a>(
+        &'a self,
+        value: Value<'v>,
+        heap: &'v Heap,
+    ) -> impl Iterator<Item = Value<'v>> + 'a
+    where
+        'v: 'a,
+    {
+        self.0
+            .coercions
+            .0
+            .iter()
+            .filter_map(move |c| c(value, heap))
+    }
+
     pub(crate) fn heap(&self) -> &FrozenHeapRef {
         &self.0.heap
     }
@@ -172,6 +254,23 @@ impl GlobalsBuilder {
             variables: SymbolMap::new(),
             struct_fields: Vec::new(),
             docstring: None,
+            coercions: Coercions::default(),
+            features: SmallSet::new(),
+        }
+    }
+
+    /// Create a [`GlobalsBuilder`] starting with everything in `globals`,
+    /// so more values can be added to an existing environment.
+    pub(crate) fn from_globals(globals: &Globals) -> Self {
+        let heap = FrozenHeap::new();
+        heap.add_reference(globals.heap());
+        Self {
+            heap,
+            variables: globals.0.variables.clone(),
+            struct_fields: Vec::new(),
+            docstring: globals.0.docstring.clone(),
+            coercions: globals.0.coercions.clone(),
+            features: globals.0.features.iter().cloned().collect(),
         }
     }
 
@@ -226,14 +325,80 @@ impl GlobalsBuilder {
             .map(|x| self.heap.alloc_str_intern(x.as_str()))
             .collect();
         variable_names.sort();
+        let mut features: Vec<String> = self.features.into_iter().collect();
+        features.sort();
         Globals(Arc::new(GlobalsData {
             heap: self.heap.into_ref(),
             variables: self.variables,
             variable_names,
             docstring: self.docstring,
+            features,
+            coercions: self.coercions,
         }))
     }
 
+    /// Declare a feature of the host, which scripts can query with `has_feature`
+    /// and `features` from [`LibraryExtension::Features`].
+    pub fn add_feature(&mut self, feature: &str) {
+        self.features.insert(feature.to_owned());
+    }
+
+    /// Register the globals added by `f` and declare `feature` only if `enabled`,
+    /// so scripts can check for optional globals with `has_feature`.
+    ///
+    /// If not `enabled`, the names `f` would add are defined as placeholders,
+    /// so code guarded by `has_feature` still compiles,
+    /// and calling them or their attributes fails when evaluated.
+    /// Names already defined are kept.
+    ///
+    /// ```
+    /// # use starlark::assert::Assert;
+    /// # use starlark::environment::GlobalsBuilder;
+    /// # use starlark::environment::LibraryExtension;
+    /// let json_enabled = false;
+    /// let mut a = Assert::new();
+    /// a.globals(
+    ///     GlobalsBuilder::extended_by(&[LibraryExtension::Features])
+    ///         .with_feature("json", json_enabled, |g| LibraryExtension::Json.add(g))
+    ///         .build(),
+    /// );
+    /// a.is_true(r#"
+    /// def encode(x):
+    ///     if has_feature("json"):
+    ///         return json.encode(x)
+    ///     return repr(x)
+    /// encode([1]) == "[1]"
+    /// "#);
+    /// a.fail("json.encode(1)", "feature `json` is disabled");
+    /// ```
+    pub fn feature(&mut self, feature: &str, enabled: bool, f: impl FnOnce(&mut GlobalsBuilder)) {
+        if enabled {
+            f(self);
+            self.add_feature(feature);
+        } else {
+            let mut disabled = GlobalsBuilder::new();
+            f(&mut disabled);
+            for name in disabled.variables.keys() {
+                let name = name.as_str();
+                if self.struct_fields.is_empty() && self.variables.get_str(name).is_some() {
+                    continue;
+                }
+                self.set(name, DisabledGlobal::new(name, feature));
+            }
+        }
+    }
+
+    /// A fluent API for [`feature`](GlobalsBuilder::feature).
+    pub fn with_feature(
+        mut self,
+        feature: &str,
+        enabled: bool,
+        f: impl FnOnce(&mut GlobalsBuilder),
+    ) -> Self {
+        self.feature(feature, enabled, f);
+        self
+    }
+
     /// Set a value in the [`GlobalsBuilder`].
     pub fn set<'v, V: AllocFrozenValue>(&'v mut self, name: &str, value: V) {
         let value = value.alloc_frozen_value(&self.heap);
@@ -291,6 +456,24 @@ impl GlobalsBuilder {
         value.alloc_frozen_value(&self.heap)
     }
 
+    /// Register a coercion of arguments of native functions.
+    ///
+    /// When an argument cannot be unpacked into the type of a native function parameter,
+    /// coercions are tried in the order of registration:
+    /// a coercion returns `None` if it does not accept the value,
+    /// and the first value produced by a coercion which unpacks into the parameter type is used.
+    /// For example, this allows natives to take a `Label` parameter while
+    /// scripts keep passing strings.
+    ///
+    /// Coercions are used when evaluating modules with the built [`Globals`],
+    /// including calls of functions from other modules during that evaluation.
+    pub fn coercion(
+        &mut self,
+        coercion: impl for<'v> Fn(Value<'v>, &'v Heap) -> Option<Value<'v>> + Send + Sync + 'static,
+    ) {
+        self.coercions.0.push(Arc::new(coercion));
+    }
+
     /// Set per module docstring.
     ///
     /// This function is called by the `starlark_derive` generated code
@@ -366,4 +549,36 @@ mod tests {
         Globals: Send + Sync,
     {
     }
+
+    #[test]
+    fn test_from_globals() {
+        let globals = {
+            let base = GlobalsBuilder::new()
+                .with(|g| {
+                    g.set("a", "x");
+                    g.add_feature("f");
+                })
+                .build();
+            GlobalsBuilder::from_globals(&base)
+                .with(|g| g.set("b", 2))
+                .build()
+        };
+        assert_eq!(
+            Some("x"),
+            globals
+                .get_frozen("a")
+                .and_then(|v| v.to_value().unpack_str())
+        );
+        assert_eq!(
+            Some(2),
+            globals
+                .get_frozen("b")
+                .and_then(|v| v.to_value().unpack_i32())
+        );
+        assert_eq!(
+            vec!["a", "b"],
+            globals.names().map(|n| n.as_str()).collect::<Vec<_>>()
+        );
+        assert!(globals.has_feature("f"));
+    }
 }
@@ -113,6 +113,13 @@ impl Methods {
     }
 }
 
+/// Methods of a type deriving `StarlarkValue`, generated by `#[starlark_methods]`.
+#[doc(hidden)]
+pub trait DerivedMethods {
+    /// Add the methods to the builder.
+    fn methods(builder: &mut MethodsBuilder);
+}
+
 impl Methods {
     /// Create an empty [`Methods`], with no functions in scope.
     pub fn new() -> Self {
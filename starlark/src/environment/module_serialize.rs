@@ -0,0 +1,652 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Binary serialization of frozen modules containing only data.
+
+use std::io::Read;
+use std::io::Write;
+use std::str::FromStr;
+
+use starlark_map::small_map::SmallMap;
+use starlark_syntax::syntax::ast::Visibility;
+
+use crate::environment::FrozenModule;
+use crate::environment::Module;
+use crate::values::dict::AllocDict;
+use crate::values::dict::FrozenDictRef;
+use crate::values::list::AllocList;
+use crate::values::list::FrozenListRef;
+use crate::values::num::value::NumRef;
+use crate::values::structs::AllocStruct;
+use crate::values::structs::FrozenStructRef;
+use crate::values::tuple::FrozenTupleRef;
+use crate::values::types::float::StarlarkFloat;
+use crate::values::types::int_or_big::StarlarkInt;
+use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Value;
+use crate::values::ValueIdentity;
+use crate::values::ValueLike;
+
+const MAGIC: &[u8; 8] = b"STARMOD\0";
+const VERSION: u32 = 1;
+/// Containers nested deeper than this are not written or read,
+/// so reading crafted input cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, thiserror::Error)]
+enum ModuleSerializeError {
+    #[error("Not a serialized starlark module")]
+    BadMagic,
+    #[error("Unsupported serialized module version {0}, expected {VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("Cannot serialize `{0}`: values of type `{1}` are not supported")]
+    UnsupportedType(String, &'static str),
+    #[error("Cannot serialize `{0}`: value is cyclic")]
+    Cyclic(String),
+    #[error("Cannot serialize `{0}`: containers are nested deeper than {MAX_DEPTH}")]
+    TooDeep(String),
+    #[error("Serialized module is corrupted")]
+    Corrupted,
+}
+
+mod tag {
+    pub(super) const NONE: u8 = 0;
+    pub(super) const FALSE: u8 = 1;
+    pub(super) const TRUE: u8 = 2;
+    pub(super) const INT: u8 = 3;
+    pub(super) const BIG_INT: u8 = 4;
+    pub(super) const FLOAT: u8 = 5;
+    pub(super) const STRING: u8 = 6;
+    pub(super) const LIST: u8 = 7;
+    pub(super) const TUPLE: u8 = 8;
+    pub(super) const DICT: u8 = 9;
+    pub(super) const STRUCT: u8 = 10;
+    /// Container serialized earlier, by index.
+    pub(super) const REF: u8 = 11;
+}
+
+impl FrozenModule {
+    /// Write the module in a binary format, which can be read back with
+    /// [`deserialize`](FrozenModule::deserialize) without evaluating the module again.
+    ///
+    /// Only data is supported: `None`, booleans, numbers, strings,
+    /// and lists, tuples, dicts and structs of them. Values shared between
+    /// several symbols stay shared after deserialization.
+    /// Modules with functions or other values fail to serialize,
+    /// and so do containers nested more than 128 levels deep.
+    ///
+    /// The docstring, load visibility and extra value are preserved,
+    /// recorded calls and heap profile are not.
+    pub fn serialize(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        let mut w = Writer {
+            w,
+            ids: SmallMap::new(),
+            in_progress: Vec::new(),
+        };
+        w.w.write_all(MAGIC)?;
+        w.u32(VERSION)?;
+        w.option_str(self.docstring())?;
+        match self.load_visibility() {
+            None => w.u8(0)?,
+            Some(patterns) => {
+                w.u8(1)?;
+                w.len(patterns.len())?;
+                for pattern in patterns {
+                    w.str(pattern)?;
+                }
+            }
+        }
+        let items: Vec<_> = self.all_items().collect();
+        w.len(items.len())?;
+        for (name, value) in items {
+            let (_, vis) = self.get_any_visibility(&name)?;
+            w.str(&name)?;
+            w.u8(match vis {
+                Visibility::Private => 0,
+                Visibility::Public => 1,
+            })?;
+            w.value(&name, value)?;
+        }
+        match self.extra_value() {
+            None => w.u8(0)?,
+            Some(value) => {
+                w.u8(1)?;
+                w.value("<extra value>", value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a module written by [`serialize`](FrozenModule::serialize).
+    ///
+    /// Malformed input is an error, it never panics.
+    pub fn deserialize(r: &mut impl Read) -> anyhow::Result<FrozenModule> {
+        let module = Module::new();
+        let mut r = Reader {
+            r,
+            heap: module.frozen_heap(),
+            values: Vec::new(),
+            depth: 0,
+        };
+        let mut magic = [0; MAGIC.len()];
+        r.r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(ModuleSerializeError::BadMagic.into());
+        }
+        let version = r.u32()?;
+        if version != VERSION {
+            return Err(ModuleSerializeError::UnsupportedVersion(version).into());
+        }
+        if let Some(docstring) = r.option_string()? {
+            module.set_docstring(docstring);
+        }
+        if r.bool()? {
+            let len = r.len()?;
+            let patterns = (0..len)
+                .map(|_| r.string())
+                .collect::<anyhow::Result<_>>()?;
+            module.set_load_visibility(patterns)?;
+        }
+        for _ in 0..r.len()? {
+            let name = r.string()?;
+            let public = r.bool()?;
+            let value = Value::new_frozen(r.value()?);
+            if public {
+                module.set(&name, value);
+            } else {
+                module.set_private(module.frozen_heap().alloc_str_intern(&name), value);
+            }
+        }
+        if r.bool()? {
+            module.set_extra_value(Value::new_frozen(r.value()?));
+        }
+        module.freeze()
+    }
+}
+
+struct Writer<'a, W: Write> {
+    w: &'a mut W,
+    /// Containers already written, with their index.
+    ids: SmallMap<ValueIdentity<'static>, u32>,
+    /// Containers being written, to detect cycles.
+    in_progress: Vec<ValueIdentity<'static>>,
+}
+
+impl<'a, W: Write> Writer<'a, W> {
+    fn u8(&mut self, x: u8) -> anyhow::Result<()> {
+        Ok(self.w.write_all(&[x])?)
+    }
+
+    fn u32(&mut self, x: u32) -> anyhow::Result<()> {
+        Ok(self.w.write_all(&x.to_le_bytes())?)
+    }
+
+    fn len(&mut self, len: usize) -> anyhow::Result<()> {
+        self.u32(len.try_into()?)
+    }
+
+    fn str(&mut self, s: &str) -> anyhow::Result<()> {
+        self.len(s.len())?;
+        Ok(self.w.write_all(s.as_bytes())?)
+    }
+
+    fn option_str(&mut self, s: Option<&str>) -> anyhow::Result<()> {
+        match s {
+            None => self.u8(0),
+            Some(s) => {
+                self.u8(1)?;
+                self.str(s)
+            }
+        }
+    }
+
+    fn value(&mut self, name: &str, value: FrozenValue) -> anyhow::Result<()> {
+        if value.is_none() {
+            return self.u8(tag::NONE);
+        }
+        if let Some(b) = value.unpack_bool() {
+            return self.u8(if b { tag::TRUE } else { tag::FALSE });
+        }
+        if let Some(num) = value.to_value().unpack_num() {
+            return match num {
+                NumRef::Int(StarlarkIntRef::Small(i)) => {
+                    self.u8(tag::INT)?;
+                    self.u32(i.to_i32() as u32)
+                }
+                NumRef::Int(StarlarkIntRef::Big(i)) => {
+                    self.u8(tag::BIG_INT)?;
+                    self.str(&i.to_string())
+                }
+                NumRef::Float(StarlarkFloat(f)) => {
+                    self.u8(tag::FLOAT)?;
+                    Ok(self.w.write_all(&f.to_bits().to_le_bytes())?)
+                }
+            };
+        }
+        if let Some(s) = value.to_value().unpack_str() {
+            self.u8(tag::STRING)?;
+            return self.str(s);
+        }
+
+        let identity = value.to_value().identity();
+        if let Some(id) = self.ids.get(&identity) {
+            let id = *id;
+            self.u8(tag::REF)?;
+            return self.u32(id);
+        }
+        if self.in_progress.contains(&identity) {
+            return Err(ModuleSerializeError::Cyclic(name.to_owned()).into());
+        }
+        if self.in_progress.len() == MAX_DEPTH {
+            return Err(ModuleSerializeError::TooDeep(name.to_owned()).into());
+        }
+        self.in_progress.push(identity);
+        if let Some(list) = FrozenListRef::from_frozen_value(value) {
+            self.u8(tag::LIST)?;
+            self.values(name, list)?;
+        } else if let Some(tuple) = FrozenTupleRef::from_frozen_value(value) {
+            self.u8(tag::TUPLE)?;
+            self.values(name, tuple.content())?;
+        } else if let Some(dict) = FrozenDictRef::from_frozen_value(value) {
+            self.u8(tag::DICT)?;
+            self.len(dict.iter().len())?;
+            for (k, v) in dict.iter() {
+                self.value(name, k)?;
+                self.value(name, v)?;
+            }
+        } else if let Some(s) = FrozenStructRef::from_value(value) {
+            self.u8(tag::STRUCT)?;
+            self.len(s.iter().count())?;
+            for (k, v) in s.iter() {
+                self.str(k.as_str())?;
+                self.value(name, v)?;
+            }
+        } else {
+            return Err(ModuleSerializeError::UnsupportedType(
+                name.to_owned(),
+                value.to_value().get_type(),
+            )
+            .into());
+        }
+        self.in_progress.pop();
+        let id = self.ids.len().try_into()?;
+        self.ids.insert(identity, id);
+        Ok(())
+    }
+
+    fn values(&mut self, name: &str, values: &[FrozenValue]) -> anyhow::Result<()> {
+        self.len(values.len())?;
+        for v in values {
+            self.value(name, *v)?;
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a, R: Read> {
+    r: &'a mut R,
+    heap: &'a FrozenHeap,
+    /// Containers read so far, indexed by [`tag::REF`].
+    values: Vec<FrozenValue>,
+    /// Number of containers being read.
+    depth: usize,
+}
+
+impl<'a, R: Read> Reader<'a, R> {
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        let mut buf = [0; 1];
+        self.r.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let mut buf = [0; 4];
+        self.r.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn bool(&mut self) -> anyhow::Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ModuleSerializeError::Corrupted.into()),
+        }
+    }
+
+    fn len(&mut self) -> anyhow::Result<usize> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let len = self.len()?;
+        let mut buf = Vec::new();
+        self.r.by_ref().take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(ModuleSerializeError::Corrupted.into());
+        }
+        Ok(String::from_utf8(buf).map_err(|_| ModuleSerializeError::Corrupted)?)
+    }
+
+    fn option_string(&mut self) -> anyhow::Result<Option<String>> {
+        if self.bool()? {
+            Ok(Some(self.string()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<FrozenValue> {
+        let value = match self.u8()? {
+            tag::NONE => return Ok(FrozenValue::new_none()),
+            tag::FALSE => return Ok(FrozenValue::new_bool(false)),
+            tag::TRUE => return Ok(FrozenValue::new_bool(true)),
+            tag::INT => return Ok(self.heap.alloc(self.u32()? as i32)),
+            tag::BIG_INT => {
+                let i = StarlarkInt::from_str(&self.string()?)
+                    .map_err(|_| ModuleSerializeError::Corrupted)?;
+                return Ok(self.heap.alloc(i));
+            }
+            tag::FLOAT => {
+                let mut buf = [0; 8];
+                self.r.read_exact(&mut buf)?;
+                return Ok(self.heap.alloc(f64::from_bits(u64::from_le_bytes(buf))));
+            }
+            tag::STRING => return Ok(self.heap.alloc_str(&self.string()?).to_frozen_value()),
+            tag::REF => {
+                let id = self.len()?;
+                return Ok(*self.values.get(id).ok_or(ModuleSerializeError::Corrupted)?);
+            }
+            tag @ (tag::LIST | tag::TUPLE | tag::DICT | tag::STRUCT) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(ModuleSerializeError::Corrupted.into());
+                }
+                self.depth += 1;
+                let value = self.container(tag);
+                self.depth -= 1;
+                value?
+            }
+            _ => return Err(ModuleSerializeError::Corrupted.into()),
+        };
+        self.values.push(value);
+        Ok(value)
+    }
+
+    fn container(&mut self, tag: u8) -> anyhow::Result<FrozenValue> {
+        match tag {
+            tag::LIST => {
+                let values = self.values()?;
+                Ok(self.heap.alloc(AllocList(values)))
+            }
+            tag::TUPLE => {
+                let values = self.values()?;
+                Ok(self.heap.alloc_tuple(&values))
+            }
+            tag::DICT => {
+                // Keys are checked here, because allocating a dict
+                // panics on unhashable keys.
+                let len = self.len()?;
+                let mut entries = SmallMap::new();
+                for _ in 0..len {
+                    let key = self
+                        .value()?
+                        .get_hashed()
+                        .map_err(|_| ModuleSerializeError::Corrupted)?;
+                    let value = self.value()?;
+                    if entries.insert_hashed(key, value).is_some() {
+                        return Err(ModuleSerializeError::Corrupted.into());
+                    }
+                }
+                Ok(self.heap.alloc(AllocDict(entries)))
+            }
+            tag::STRUCT => {
+                // Allocating a struct panics on duplicate fields.
+                let len = self.len()?;
+                let mut fields = SmallMap::new();
+                for _ in 0..len {
+                    let name = self.string()?;
+                    let value = self.value()?;
+                    if fields.insert(name, value).is_some() {
+                        return Err(ModuleSerializeError::Corrupted.into());
+                    }
+                }
+                Ok(self.heap.alloc(AllocStruct(fields)))
+            }
+            _ => unreachable!("not a container tag: {}", tag),
+        }
+    }
+
+    fn values(&mut self) -> anyhow::Result<Vec<FrozenValue>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.value()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tag;
+    use super::MAGIC;
+    use super::MAX_DEPTH;
+    use super::VERSION;
+    use crate::environment::FrozenModule;
+    use crate::environment::Globals;
+    use crate::environment::LibraryExtension;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn eval(program: &str) -> FrozenModule {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::extended_by(&[LibraryExtension::StructType]))
+                .unwrap();
+        }
+        module.freeze().unwrap()
+    }
+
+    fn round_trip(module: &FrozenModule) -> FrozenModule {
+        let mut buf = Vec::new();
+        module.serialize(&mut buf).unwrap();
+        FrozenModule::deserialize(&mut buf.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let module = eval(
+            r#"
+"""Prelude."""
+x = [None, True, 1, -2, 1 << 100, 1.5, "s", (1, "t")]
+_private = {"a": struct(b = [1], c = {})}
+y = x
+"#,
+        );
+        let module = round_trip(&module);
+        assert_eq!(
+            r#"[None, True, 1, -2, 1267650600228229401496703205376, 1.5, "s", (1, "t")]"#,
+            module.get("x").unwrap().value().to_repr()
+        );
+        assert_eq!(
+            r#"{"a": struct(b=[1], c={})}"#,
+            module
+                .get_any_visibility("_private")
+                .unwrap()
+                .0
+                .value()
+                .to_repr()
+        );
+        assert!(module.get("_private").is_err());
+        // Sharing is preserved.
+        assert!(
+            module
+                .get("x")
+                .unwrap()
+                .value()
+                .ptr_eq(module.get("y").unwrap().value())
+        );
+        assert_eq!(Some("Prelude."), module.docstring());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let module = eval("def f(): pass\nx = [f]");
+        let err = module.serialize(&mut Vec::new()).unwrap_err();
+        assert!(
+            err.to_string().contains("values of type `function`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_cyclic() {
+        let module = eval("x = []\nx.append(x)");
+        let err = module.serialize(&mut Vec::new()).unwrap_err();
+        assert_eq!("Cannot serialize `x`: value is cyclic", err.to_string());
+    }
+
+    #[test]
+    fn test_corrupted() {
+        assert!(FrozenModule::deserialize(&mut b"garbage!".as_slice()).is_err());
+        let mut buf = Vec::new();
+        eval("x = [1]").serialize(&mut buf).unwrap();
+        buf.truncate(buf.len() - 2);
+        assert!(FrozenModule::deserialize(&mut buf.as_slice()).is_err());
+    }
+
+    /// Serialized module with one symbol `x` with serialized value `value`.
+    fn module_with_value(value: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&VERSION.to_le_bytes());
+        // No docstring, no load visibility, one symbol.
+        buf.extend_from_slice(&[0, 0]);
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(b"x");
+        buf.push(1);
+        buf.extend_from_slice(value);
+        // No extra value.
+        buf.push(0);
+        buf
+    }
+
+    fn assert_corrupted(value: &[u8]) {
+        let err = FrozenModule::deserialize(&mut module_with_value(value).as_slice()).unwrap_err();
+        assert_eq!("Serialized module is corrupted", err.to_string());
+    }
+
+    #[test]
+    fn test_module_with_value() {
+        let module = FrozenModule::deserialize(
+            &mut module_with_value(&[tag::LIST, 1, 0, 0, 0, tag::TRUE]).as_slice(),
+        )
+        .unwrap();
+        assert_eq!("[True]", module.get("x").unwrap().value().to_repr());
+    }
+
+    #[test]
+    fn test_unhashable_dict_key() {
+        // `{[]: None}`
+        assert_corrupted(&[tag::DICT, 1, 0, 0, 0, tag::LIST, 0, 0, 0, 0, tag::NONE]);
+    }
+
+    #[test]
+    fn test_duplicate_dict_key() {
+        // `{True: None, True: None}`
+        assert_corrupted(&[
+            tag::DICT,
+            2,
+            0,
+            0,
+            0,
+            tag::TRUE,
+            tag::NONE,
+            tag::TRUE,
+            tag::NONE,
+        ]);
+    }
+
+    #[test]
+    fn test_duplicate_struct_field() {
+        // `struct(a = None, a = None)`
+        assert_corrupted(&[
+            tag::STRUCT,
+            2,
+            0,
+            0,
+            0,
+            1,
+            0,
+            0,
+            0,
+            b'a',
+            tag::NONE,
+            1,
+            0,
+            0,
+            0,
+            b'a',
+            tag::NONE,
+        ]);
+    }
+
+    #[test]
+    fn test_too_deep() {
+        // `[[[...]]]` nested a million times, which would overflow the stack.
+        let mut value = Vec::new();
+        for _ in 0..1_000_000 {
+            value.extend_from_slice(&[tag::LIST, 1, 0, 0, 0]);
+        }
+        value.push(tag::NONE);
+        assert_corrupted(&value);
+    }
+
+    #[test]
+    fn test_too_deep_write() {
+        let nested = |depth| eval(&format!("x = None\nfor _ in range({depth}):\n  x = [x]"));
+        let module = round_trip(&nested(MAX_DEPTH));
+        let repr = module.get("x").unwrap().value().to_repr();
+        assert_eq!(MAX_DEPTH, repr.matches('[').count());
+
+        let err = nested(MAX_DEPTH + 1)
+            .serialize(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(
+            "Cannot serialize `x`: containers are nested deeper than 128",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_mutated() {
+        let mut buf = Vec::new();
+        eval(r#"x = [1, "s", {"a": (1.5, 1 << 100)}, struct(b = {})]"#)
+            .serialize(&mut buf)
+            .unwrap();
+        // Every corruption of a single byte is an error or a valid module, never a panic.
+        for i in 0..buf.len() {
+            for b in [0, 1, 2, 7, 9, 10, 11, 0x7f, 0xff] {
+                let mut buf = buf.clone();
+                buf[i] = b;
+                let _ignore = FrozenModule::deserialize(&mut buf.as_slice());
+            }
+        }
+    }
+}
@@ -0,0 +1,325 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Streaming visitor over exported values of frozen modules.
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use starlark_syntax::syntax::ast::Visibility;
+
+use crate::environment::FrozenModule;
+use crate::values::dict::FrozenDictRef;
+use crate::values::list::FrozenListRef;
+use crate::values::num::value::NumRef;
+use crate::values::structs::FrozenStructRef;
+use crate::values::tuple::FrozenTupleRef;
+use crate::values::types::float::StarlarkFloat;
+use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::FrozenValue;
+use crate::values::ValueIdentity;
+
+#[derive(Debug, thiserror::Error)]
+enum ModuleVisitError {
+    #[error("Cannot visit `{0}`: value is cyclic")]
+    Cyclic(String),
+    #[error("Cannot visit `{0}`: dict key of type `{1}` is not a scalar")]
+    NonScalarKey(String, &'static str),
+}
+
+/// Leaf value reported by [`FrozenModule::visit_exports`].
+#[derive(Debug, Clone, Copy)]
+pub enum FrozenScalar<'a> {
+    /// `None`.
+    None,
+    /// `True` or `False`.
+    Bool(bool),
+    /// Integer which fits in `i64`.
+    Int(i64),
+    /// Integer which does not fit in `i64`.
+    BigInt(&'a BigInt),
+    /// Float.
+    Float(f64),
+    /// String.
+    String(&'a str),
+    /// Value which is not data, like a function.
+    /// It is up to the visitor to skip it, fail or convert it, for example with `to_repr`.
+    Other(FrozenValue),
+}
+
+/// Event reported by [`FrozenModule::visit_exports`].
+///
+/// Containers are reported as a begin event, the events of their elements,
+/// and a matching [`End`](FrozenValueEvent::End).
+#[derive(Debug, Clone, Copy)]
+pub enum FrozenValueEvent<'a> {
+    /// Start of an exported symbol, followed by the events of its value.
+    Export(&'a str),
+    /// Leaf value.
+    Scalar(FrozenScalar<'a>),
+    /// Start of a list with the given number of elements.
+    BeginList(usize),
+    /// Start of a tuple with the given number of elements.
+    BeginTuple(usize),
+    /// Start of a dict with the given number of entries.
+    /// Each entry is reported as a [`Key`](FrozenValueEvent::Key) followed by the events of the value.
+    BeginDict(usize),
+    /// Start of a struct with the given number of fields.
+    /// Each field is reported as a [`Key`](FrozenValueEvent::Key) with a string,
+    /// followed by the events of the value.
+    BeginStruct(usize),
+    /// Dict key or struct field name.
+    Key(FrozenScalar<'a>),
+    /// End of the innermost list, tuple, dict or struct.
+    End,
+}
+
+impl FrozenModule {
+    /// Walk the public symbols of the module in definition order,
+    /// reporting their values to `visitor` as a stream of events,
+    /// like a serde serializer does.
+    ///
+    /// This can be used to export evaluated configuration directly into a database
+    /// without building an intermediate tree (like JSON) in memory.
+    ///
+    /// Lists, tuples, dicts and structs are walked, values shared between several
+    /// places are reported each time they are reached.
+    /// Other values are reported as [`FrozenScalar::Other`].
+    /// Fails if a value is cyclic, if a dict key is not a scalar, or if `visitor` fails.
+    pub fn visit_exports<'a>(
+        &'a self,
+        mut visitor: impl FnMut(FrozenValueEvent<'a>) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        for (name, value) in self.all_items() {
+            if let (_, Visibility::Private) = self.get_any_visibility(&name)? {
+                continue;
+            }
+            visitor(FrozenValueEvent::Export(name.as_str()))?;
+            Visitor {
+                name: name.as_str(),
+                visitor: &mut visitor,
+                in_progress: Vec::new(),
+            }
+            .value(value)?;
+        }
+        Ok(())
+    }
+}
+
+struct Visitor<'a, 'f, F: FnMut(FrozenValueEvent<'a>) -> anyhow::Result<()>> {
+    /// Exported symbol being visited, for errors.
+    name: &'a str,
+    visitor: &'f mut F,
+    /// Containers being visited, to detect cycles.
+    in_progress: Vec<ValueIdentity<'static>>,
+}
+
+impl<'a, 'f, F: FnMut(FrozenValueEvent<'a>) -> anyhow::Result<()>> Visitor<'a, 'f, F> {
+    fn scalar(value: FrozenValue) -> Option<FrozenScalar<'a>> {
+        if value.is_none() {
+            return Some(FrozenScalar::None);
+        }
+        if let Some(b) = value.unpack_bool() {
+            return Some(FrozenScalar::Bool(b));
+        }
+        if let Some(num) = value.to_value().unpack_num() {
+            return Some(match num {
+                NumRef::Int(StarlarkIntRef::Small(i)) => FrozenScalar::Int(i.to_i32().into()),
+                NumRef::Int(StarlarkIntRef::Big(i)) => match i.get().to_i64() {
+                    Some(i) => FrozenScalar::Int(i),
+                    None => FrozenScalar::BigInt(i.get()),
+                },
+                NumRef::Float(StarlarkFloat(f)) => FrozenScalar::Float(f),
+            });
+        }
+        if let Some(s) = value.to_value().unpack_str() {
+            return Some(FrozenScalar::String(s));
+        }
+        None
+    }
+
+    fn value(&mut self, value: FrozenValue) -> anyhow::Result<()> {
+        if let Some(scalar) = Self::scalar(value) {
+            return (self.visitor)(FrozenValueEvent::Scalar(scalar));
+        }
+
+        let identity = value.to_value().identity();
+        if self.in_progress.contains(&identity) {
+            return Err(ModuleVisitError::Cyclic(self.name.to_owned()).into());
+        }
+        self.in_progress.push(identity);
+        if let Some(list) = FrozenListRef::from_frozen_value(value) {
+            (self.visitor)(FrozenValueEvent::BeginList(list.len()))?;
+            self.values(list)?;
+        } else if let Some(tuple) = FrozenTupleRef::from_frozen_value(value) {
+            (self.visitor)(FrozenValueEvent::BeginTuple(tuple.len()))?;
+            self.values(tuple.content())?;
+        } else if let Some(dict) = FrozenDictRef::from_frozen_value(value) {
+            (self.visitor)(FrozenValueEvent::BeginDict(dict.iter().len()))?;
+            for (k, v) in dict.iter() {
+                let key = Self::scalar(k).ok_or_else(|| {
+                    ModuleVisitError::NonScalarKey(self.name.to_owned(), k.to_value().get_type())
+                })?;
+                (self.visitor)(FrozenValueEvent::Key(key))?;
+                self.value(v)?;
+            }
+            (self.visitor)(FrozenValueEvent::End)?;
+        } else if let Some(s) = FrozenStructRef::from_value(value) {
+            (self.visitor)(FrozenValueEvent::BeginStruct(s.iter().len()))?;
+            for (k, v) in s.iter() {
+                (self.visitor)(FrozenValueEvent::Key(FrozenScalar::String(k.as_str())))?;
+                self.value(v)?;
+            }
+            (self.visitor)(FrozenValueEvent::End)?;
+        } else {
+            (self.visitor)(FrozenValueEvent::Scalar(FrozenScalar::Other(value)))?;
+        }
+        self.in_progress.pop();
+        Ok(())
+    }
+
+    fn values(&mut self, values: &[FrozenValue]) -> anyhow::Result<()> {
+        for v in values {
+            self.value(*v)?;
+        }
+        (self.visitor)(FrozenValueEvent::End)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use crate::environment::FrozenModule;
+    use crate::environment::FrozenScalar;
+    use crate::environment::FrozenValueEvent;
+    use crate::environment::Globals;
+    use crate::environment::LibraryExtension;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn eval(program: &str) -> FrozenModule {
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::extended_by(&[LibraryExtension::StructType]))
+                .unwrap();
+        }
+        module.freeze().unwrap()
+    }
+
+    /// Events, one per line, indented by nesting.
+    fn events(module: &FrozenModule) -> anyhow::Result<String> {
+        let mut w = String::new();
+        let mut depth = 0;
+        module.visit_exports(|event| {
+            if let FrozenValueEvent::End = event {
+                depth -= 1;
+            }
+            let event = match event {
+                FrozenValueEvent::Scalar(FrozenScalar::Other(v)) => {
+                    format!("Other({})", v.to_value().get_type())
+                }
+                event => format!("{event:?}"),
+            };
+            writeln!(w, "{}{}", "  ".repeat(depth), event).unwrap();
+            if event.starts_with("Begin") {
+                depth += 1;
+            }
+            Ok(())
+        })?;
+        Ok(w)
+    }
+
+    #[test]
+    fn test_visit_exports() {
+        let module = eval(
+            r#"
+x = [None, True, 1, 1 << 40, 1 << 100, 1.5, "s"]
+_private = 1
+y = {"a": struct(b = (1,), c = {})}
+def f(): pass
+"#,
+        );
+        assert_eq!(
+            r#"Export("x")
+BeginList(7)
+  Scalar(None)
+  Scalar(Bool(true))
+  Scalar(Int(1))
+  Scalar(Int(1099511627776))
+  Scalar(BigInt(1267650600228229401496703205376))
+  Scalar(Float(1.5))
+  Scalar(String("s"))
+End
+Export("y")
+BeginDict(1)
+  Key(String("a"))
+  BeginStruct(2)
+    Key(String("b"))
+    BeginTuple(1)
+      Scalar(Int(1))
+    End
+    Key(String("c"))
+    BeginDict(0)
+    End
+  End
+End
+Export("f")
+Other(function)
+"#,
+            events(&module).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cyclic() {
+        let module = eval("x = []\nx.append(x)");
+        let err = events(&module).unwrap_err();
+        assert_eq!("Cannot visit `x`: value is cyclic", err.to_string());
+    }
+
+    #[test]
+    fn test_non_scalar_key() {
+        let module = eval("x = {(1, 2): 3}");
+        let err = events(&module).unwrap_err();
+        assert_eq!(
+            "Cannot visit `x`: dict key of type `tuple` is not a scalar",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_visitor_error() {
+        let module = eval("x = [1, 2]");
+        let mut count = 0;
+        let err = module
+            .visit_exports(|_| {
+                count += 1;
+                if count == 3 {
+                    Err(anyhow::anyhow!("stop"))
+                } else {
+                    Ok(())
+                }
+            })
+            .unwrap_err();
+        assert_eq!("stop", err.to_string());
+        assert_eq!(3, count);
+    }
+}
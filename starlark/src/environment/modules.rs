@@ -32,7 +32,9 @@ use itertools::Itertools;
 use starlark_syntax::syntax::ast::Visibility;
 
 use crate::cast::transmute;
+use crate::codemap::FileSpan;
 use crate::collections::Hashed;
+use crate::collections::SmallMap;
 use crate::docs::DocItem;
 use crate::docs::DocMember;
 use crate::docs::DocModule;
@@ -48,10 +50,13 @@ use crate::environment::Globals;
 use crate::errors::did_you_mean::did_you_mean;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
 use crate::eval::ProfileData;
+use crate::eval::RecordedCall;
 use crate::values::layout::heap::heap_type::HeapKind;
+use crate::values::layout::heap::heap_type::OnFrozenHooks;
 use crate::values::layout::heap::profile::aggregated::AggregateHeapProfileInfo;
 use crate::values::layout::heap::profile::aggregated::RetainedHeapProfile;
 use crate::values::Freeze;
+use crate::values::FreezeStats;
 use crate::values::Freezer;
 use crate::values::FrozenHeap;
 use crate::values::FrozenHeapRef;
@@ -63,6 +68,7 @@ use crate::values::OwnedFrozenValue;
 use crate::values::Trace;
 use crate::values::Tracer;
 use crate::values::Value;
+use crate::values::ValueIdentity;
 
 #[derive(Debug, thiserror::Error)]
 enum ModuleError {
@@ -70,6 +76,14 @@ enum ModuleError {
     RetainedMemoryProfileNotEnabled,
     #[error("Extra value already set to a value of type `{}`", .0)]
     ExtraValueAlreadySet(&'static str),
+    #[error("Load visibility can only be set once per module")]
+    LoadVisibilityAlreadySet,
+    #[error("Module `{module}` cannot be loaded from `{from}`, its load visibility is [{}]", .visibility.iter().map(|v| format!("\"{}\"", v)).join(", "))]
+    LoadNotVisible {
+        module: String,
+        from: String,
+        visibility: Vec<String>,
+    },
 }
 
 /// The result of freezing a [`Module`], making it and its contained values immutable.
@@ -99,8 +113,16 @@ pub(crate) struct FrozenModuleData {
     pub(crate) names: FrozenNames,
     pub(crate) slots: FrozenSlots,
     docstring: Option<String>,
+    /// Set with [`Module::set_load_visibility`].
+    load_visibility: Option<Vec<String>>,
     /// When heap profile enabled, this field stores retained memory info.
     heap_profile: Option<RetainedHeapProfile>,
+    /// Calls recorded with [`Evaluator::enable_call_recording`](crate::eval::Evaluator::enable_call_recording).
+    recorded_calls: Vec<RecordedCall<FrozenValue>>,
+    /// Where the symbols were originally defined, following `load`s.
+    definitions: SmallMap<FrozenStringValue, FileSpan>,
+    /// Set when [`Module::enable_freeze_stats`] was called.
+    freeze_stats: Option<FreezeStats>,
 }
 
 /// A container for user values, used during execution.
@@ -120,6 +142,8 @@ pub struct Module {
     // exported.
     slots: MutableSlots<'static>,
     docstring: RefCell<Option<String>>,
+    /// Which modules are allowed to `load` this module, `None` if not restricted.
+    load_visibility: RefCell<Option<Vec<String>>>,
     /// Module evaluation duration:
     /// * evaluation of the top-level statements
     /// * optimizations during that evaluation
@@ -130,6 +154,14 @@ pub struct Module {
     extra_value: Cell<Option<Value<'static>>>,
     /// When `Some`, heap profile is collected on freeze.
     heap_profile_on_freeze: Cell<Option<RetainedHeapProfileMode>>,
+    /// When true, freeze statistics are collected on freeze.
+    freeze_stats: Cell<bool>,
+    /// Top-level calls recorded by the evaluator, values are allocated from heap.
+    recorded_calls: RefCell<Vec<RecordedCall<Value<'static>>>>,
+    /// Where the symbols were originally defined, following `load`s.
+    definitions: RefCell<SmallMap<FrozenStringValue, FileSpan>>,
+    /// Passed to the freezer when the module is frozen.
+    on_frozen: OnFrozenHooks,
 }
 
 impl FrozenModule {
@@ -250,6 +282,10 @@ impl FrozenModule {
         }
     }
 
+    pub(crate) fn docstring(&self) -> Option<&str> {
+        self.module.docstring.as_deref()
+    }
+
     /// Retained memory info, or error if not enabled.
     pub fn heap_profile(&self) -> anyhow::Result<ProfileData> {
         match &self.module.heap_profile {
@@ -268,6 +304,65 @@ impl FrozenModule {
         self.extra_value
             .map(|v| unsafe { OwnedFrozenValue::new(self.heap.dupe(), v) })
     }
+
+    /// Patterns of modules allowed to `load` this module,
+    /// set with [`Module::set_load_visibility`]. `None` if not restricted.
+    pub fn load_visibility(&self) -> Option<&[String]> {
+        self.module.load_visibility.as_deref()
+    }
+
+    /// Top-level calls recorded while evaluating this module, in call order.
+    /// Empty unless [`Evaluator::enable_call_recording`](crate::eval::Evaluator::enable_call_recording)
+    /// was used.
+    pub fn recorded_calls(&self) -> &[RecordedCall<FrozenValue>] {
+        &self.module.recorded_calls
+    }
+
+    /// Statistics of the values copied to the frozen heap when the module was frozen,
+    /// `None` unless [`Module::enable_freeze_stats`] was called.
+    pub fn freeze_stats(&self) -> Option<&FreezeStats> {
+        self.module.freeze_stats.as_ref()
+    }
+
+    /// Where the symbol `name` was originally defined.
+    ///
+    /// For symbols imported with `load`, this is the location in the module
+    /// which defined the symbol, following chains of re-exports,
+    /// or the `load` statement if the symbol comes from a module without
+    /// source locations, like one created with [`from_globals`](FrozenModule::from_globals).
+    /// For symbols assigned several times, this is the first assignment.
+    pub fn definition_location(&self, name: &str) -> Option<FileSpan> {
+        self.module.definitions.get(name).cloned()
+    }
+
+    /// Check this module, loaded as `module`, may be loaded from the module named `from`.
+    ///
+    /// A pattern `"public"` allows any module, a pattern ending with `...`
+    /// allows modules starting with the rest of the pattern,
+    /// and any other pattern allows the module with exactly that name.
+    pub fn check_load_visibility(&self, module: &str, from: &str) -> anyhow::Result<()> {
+        let visibility = match self.load_visibility() {
+            None => return Ok(()),
+            Some(visibility) => visibility,
+        };
+        let allowed = visibility.iter().any(|pattern| {
+            pattern == "public"
+                || match pattern.strip_suffix("...") {
+                    Some(prefix) => from.starts_with(prefix),
+                    None => pattern == from,
+                }
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(ModuleError::LoadNotVisible {
+                module: module.to_owned(),
+                from: from.to_owned(),
+                visibility: visibility.to_vec(),
+            }
+            .into())
+        }
+    }
 }
 
 impl FrozenModuleData {
@@ -330,16 +425,42 @@ impl Module {
             names: MutableNames::new(),
             slots: MutableSlots::new(),
             docstring: RefCell::new(None),
+            load_visibility: RefCell::new(None),
             eval_duration: Cell::new(Duration::ZERO),
             extra_value: Cell::new(None),
             heap_profile_on_freeze: Cell::new(None),
+            freeze_stats: Cell::new(false),
+            recorded_calls: RefCell::new(Vec::new()),
+            definitions: RefCell::new(SmallMap::new()),
+            on_frozen: OnFrozenHooks::default(),
         }
     }
 
+    /// Remove all the variables and values from this module, making it equivalent
+    /// to [`Module::new`], but keep the memory of the [`heap`](Module::heap)
+    /// for reuse by the next evaluation.
+    ///
+    /// Useful when evaluating many small independent programs in a row.
+    pub fn reset(&mut self) {
+        // Values are dropped while the frozen heap they may point to is still alive.
+        self.heap.reset();
+        let heap = mem::take(&mut self.heap);
+        *self = Module::new();
+        self.heap = heap;
+    }
+
     pub(crate) fn enable_retained_heap_profile(&self, mode: RetainedHeapProfileMode) {
         self.heap_profile_on_freeze.set(Some(mode));
     }
 
+    /// Collect statistics of the values copied to the frozen heap when the module is frozen,
+    /// available with [`FrozenModule::freeze_stats`].
+    ///
+    /// Off by default, because it records every frozen value.
+    pub fn enable_freeze_stats(&self) {
+        self.freeze_stats.set(true);
+    }
+
     /// Get the heap on which values are allocated by this module.
     pub fn heap(&self) -> &Heap {
         &self.heap
@@ -405,7 +526,24 @@ impl Module {
             })
     }
 
+    /// Register a callback invoked by [`freeze`](Module::freeze) for each value
+    /// allocated on the mutable heap, with the identity the value had before freezing
+    /// and its frozen copy.
+    ///
+    /// Identities recorded during evaluation can be matched against it
+    /// after [`ValueIdentity::erase_lifetime`], to map side tables keyed
+    /// by mutable values to the frozen values. See [`Freezer::on_frozen`].
+    pub fn on_frozen(&self, hook: impl Fn(ValueIdentity<'static>, FrozenValue) + 'static) {
+        self.on_frozen.add(Box::new(hook));
+    }
+
     /// Freeze the environment, all its value will become immutable afterwards.
+    ///
+    /// Freezing is single-threaded: each value is replaced in place with a forward
+    /// to its frozen copy, and the copies are allocated in one frozen heap,
+    /// neither of which can be shared between threads.
+    /// To freeze faster, split the code into several modules
+    /// which are evaluated and frozen independently.
     pub fn freeze(self) -> anyhow::Result<FrozenModule> {
         let Module {
             names,
@@ -413,18 +551,28 @@ impl Module {
             frozen_heap,
             heap,
             docstring,
+            load_visibility,
             eval_duration,
             extra_value,
             heap_profile_on_freeze,
+            freeze_stats,
+            recorded_calls,
+            definitions,
+            on_frozen,
         } = self;
         let start = Instant::now();
         // This is when we do the GC/freeze, using the module slots as roots
         // Note that we even freeze anonymous slots, since they are accessed by
         // slot-index in the code, and we don't walk into them, so don't know if
         // they are used.
-        let freezer = Freezer::new(frozen_heap);
+        let mut freezer = Freezer::new(frozen_heap);
+        freezer.on_frozen = on_frozen;
+        if freeze_stats.get() {
+            freezer.enable_stats();
+        }
         let slots = slots.freeze(&freezer)?;
         let extra_value = extra_value.into_inner().freeze(&freezer)?;
+        let recorded_calls = recorded_calls.into_inner().freeze(&freezer)?;
         let stacks = if let Some(mode) = heap_profile_on_freeze.get() {
             // TODO(nga): retained heap profile does not store information about data
             //   allocated in frozen heap before freeze starts.
@@ -440,7 +588,11 @@ impl Module {
             names: names.freeze(),
             slots,
             docstring: docstring.into_inner(),
+            load_visibility: load_visibility.into_inner(),
             heap_profile: stacks,
+            recorded_calls,
+            definitions: definitions.into_inner(),
+            freeze_stats: freezer.stats(),
         };
         let frozen_module_ref = freezer.heap.alloc_any(rest);
         for frozen_def in freezer.frozen_defs.borrow().as_slice() {
@@ -491,12 +643,23 @@ impl Module {
         for (k, slot) in module.module.names.symbols() {
             if Self::default_visibility(&k) == Visibility::Public {
                 if let Some(value) = module.module.slots.get_slot(slot) {
-                    self.set_private(k, Value::new_frozen(value))
+                    self.set_private(k, Value::new_frozen(value));
+                    if let Some(location) = module.definition_location(&k) {
+                        self.record_definition(&k, location);
+                    }
                 }
             }
         }
     }
 
+    /// Record where the symbol `name` is defined, unless it is already recorded.
+    pub(crate) fn record_definition(&self, name: &str, location: FileSpan) {
+        let mut definitions = self.definitions.borrow_mut();
+        if !definitions.contains_key(name) {
+            definitions.insert(self.frozen_heap.alloc_str_intern(name), location);
+        }
+    }
+
     pub(crate) fn load_symbol<'v>(
         &'v self,
         module: &FrozenModule,
@@ -517,6 +680,20 @@ impl Module {
         self.docstring.replace(Some(docstring));
     }
 
+    /// Restrict which modules can `load` this module once it is frozen,
+    /// see [`FrozenModule::check_load_visibility`] for the pattern syntax.
+    /// An empty list means the module cannot be loaded at all.
+    ///
+    /// Can only be set once.
+    pub fn set_load_visibility(&self, visibility: Vec<String>) -> anyhow::Result<()> {
+        let mut load_visibility = self.load_visibility.borrow_mut();
+        if load_visibility.is_some() {
+            return Err(ModuleError::LoadVisibilityAlreadySet.into());
+        }
+        *load_visibility = Some(visibility);
+        Ok(())
+    }
+
     pub(crate) fn add_eval_duration(&self, duration: Duration) {
         self.eval_duration.set(self.eval_duration.get() + duration);
     }
@@ -530,6 +707,8 @@ impl Module {
             self.set_extra_value(extra_value);
         }
 
+        self.recorded_calls_cell().borrow_mut().trace(tracer);
+
         self.heap().trace_interner(tracer);
     }
 
@@ -554,6 +733,28 @@ impl Module {
         // Cast lifetime.
         unsafe { transmute!(Option<Value>, Option<Value>, self.extra_value.get()) }
     }
+
+    fn recorded_calls_cell<'v>(&'v self) -> &'v RefCell<Vec<RecordedCall<Value<'v>>>> {
+        // Cast lifetime.
+        unsafe {
+            transmute!(
+                &'v RefCell<Vec<RecordedCall<Value<'static>>>>,
+                &'v RefCell<Vec<RecordedCall<Value<'v>>>>,
+                &self.recorded_calls
+            )
+        }
+    }
+
+    pub(crate) fn record_call<'v>(&'v self, call: RecordedCall<Value<'v>>) {
+        self.recorded_calls_cell().borrow_mut().push(call);
+    }
+
+    /// Top-level calls recorded so far while evaluating this module, in call order.
+    /// Empty unless [`Evaluator::enable_call_recording`](crate::eval::Evaluator::enable_call_recording)
+    /// is used.
+    pub fn recorded_calls<'v>(&'v self) -> Vec<RecordedCall<Value<'v>>> {
+        self.recorded_calls_cell().borrow().clone()
+    }
 }
 
 #[test]
@@ -565,18 +766,29 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use dupe::Dupe;
     use starlark_derive::starlark_module;
 
     use crate as starlark;
+    use crate::assert::Assert;
     use crate::environment::FrozenModule;
     use crate::environment::Globals;
     use crate::environment::GlobalsBuilder;
+    use crate::environment::LibraryExtension;
     use crate::environment::Module;
     use crate::eval::runtime::profile::mode::ProfileMode;
     use crate::eval::Evaluator;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
     use crate::values::list::ListRef;
+    use crate::values::none::NoneType;
+    use crate::values::structs::StructRef;
+    use crate::values::FrozenValue;
+    use crate::values::Value;
+    use crate::values::ValueIdentityMap;
 
     #[test]
     fn test_gen_heap_summary_profile() {
@@ -632,4 +844,238 @@ x = f(1)
                 .len()
         );
     }
+
+    #[test]
+    fn test_recorded_calls() {
+        #[starlark_module]
+        fn rules(globals: &mut GlobalsBuilder) {
+            fn cc_library(
+                #[starlark(require = named)] name: &str,
+                #[starlark(kwargs)] kwargs: Value,
+            ) -> anyhow::Result<NoneType> {
+                let _ = (name, kwargs);
+                Ok(NoneType)
+            }
+        }
+
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.enable_call_recording();
+            eval.eval_module(
+                AstModule::parse(
+                    "BUILD",
+                    r#"
+def my_macro(name, *args):
+    cc_library(name = name + "_lib", srcs = list(args))
+
+cc_library(name = "a", srcs = ["a.c"], **{"deps": [":b"]})
+my_macro("b", "b.c")
+"#
+                    .to_owned(),
+                    &Dialect::Extended,
+                )
+                .unwrap(),
+                &GlobalsBuilder::standard().with(rules).build(),
+            )
+            .unwrap();
+            assert_eq!(2, module.recorded_calls().len());
+        }
+        let module = module.freeze().unwrap();
+        let calls = module.recorded_calls();
+        assert_eq!(
+            vec!["cc_library", "my_macro"],
+            calls
+                .iter()
+                .map(|c| c.function.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["name", "srcs", "deps"],
+            calls[0]
+                .named
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "[\":b\"]",
+            calls[0].named_arg("deps").unwrap().to_value().to_repr()
+        );
+        assert_eq!(
+            vec!["\"b\"", "\"b.c\""],
+            calls[1]
+                .positional
+                .iter()
+                .map(|v| v.to_value().to_repr())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("BUILD:6:1-21", calls[1].location.to_string());
+    }
+
+    #[test]
+    fn test_module_reset() {
+        let mut module = Module::new();
+        for i in 0..3 {
+            {
+                let mut eval = Evaluator::new(&module);
+                let program = format!("x = [{}] * 1000\ny = str(x)", i);
+                let ast = AstModule::parse("x.star", program, &Dialect::Extended).unwrap();
+                eval.eval_module(ast, &Globals::standard()).unwrap();
+            }
+            assert_eq!(
+                1000,
+                ListRef::from_value(module.get("x").unwrap()).unwrap().len()
+            );
+            module.reset();
+            assert!(module.get("x").is_none());
+            assert_eq!(0, module.names().count());
+        }
+    }
+
+    #[test]
+    fn test_module_reset_after_allocation_limit() {
+        let mut module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.set_max_heap_bytes(10000).unwrap();
+            let ast = AstModule::parse("x.star", "x = [1] * 100000".to_owned(), &Dialect::Extended)
+                .unwrap();
+            let err = eval
+                .eval_module(ast, &Globals::standard())
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("allocation limit"), "{err}");
+        }
+        module.reset();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.fail_allocations_after(0).unwrap();
+        }
+        module.reset();
+        let mut eval = Evaluator::new(&module);
+        let ast =
+            AstModule::parse("x.star", "x = [1] * 100000".to_owned(), &Dialect::Extended).unwrap();
+        eval.eval_module(ast, &Globals::standard()).unwrap();
+        assert_eq!(
+            100000,
+            ListRef::from_value(module.get("x").unwrap()).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_on_frozen() {
+        let module = Module::new();
+        let frozen: Rc<RefCell<ValueIdentityMap<'static, FrozenValue>>> = Rc::default();
+        let list = {
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("x.star", "x = [[1], 'a']".to_owned(), &Dialect::Extended)
+                .unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            module.get("x").unwrap().identity().erase_lifetime()
+        };
+        module.on_frozen({
+            let frozen = frozen.dupe();
+            move |identity, value| {
+                frozen.borrow_mut().insert(identity, value);
+            }
+        });
+        let module = module.freeze().unwrap();
+        let frozen = frozen.borrow();
+        // Outer list and inner list, the string is static.
+        assert_eq!(2, frozen.len());
+        assert!(
+            frozen
+                .get(&list)
+                .unwrap()
+                .to_value()
+                .ptr_eq(module.get("x").unwrap().value())
+        );
+    }
+
+    #[test]
+    fn test_definition_location() {
+        let mut a = Assert::new();
+        a.module(
+            "a",
+            r#"
+x = 1
+def f(): pass
+"#,
+        );
+        a.module("b", "load('a', y = 'x', 'f')\nz = y");
+        let c = a.module(
+            "c",
+            r#"
+load('b', 'y', 'f', 'z')
+for (i, j) in [(1, 2)]:
+    if True:
+        k = i
+"#,
+        );
+        let location = |name| c.definition_location(name).unwrap().to_string();
+        assert_eq!("a.bzl:2:1-2", location("y"));
+        assert_eq!("a.bzl:3:5-6", location("f"));
+        assert_eq!("b.bzl:2:1-2", location("z"));
+        assert_eq!("c.bzl:3:9-10", location("j"));
+        assert_eq!("c.bzl:5:9-10", location("k"));
+        assert!(c.definition_location("x").is_none());
+    }
+
+    #[test]
+    fn test_recorded_calls_snapshot_arguments() {
+        #[starlark_module]
+        fn rules(globals: &mut GlobalsBuilder) {
+            fn rule(#[starlark(kwargs)] kwargs: Value) -> anyhow::Result<NoneType> {
+                let _ = kwargs;
+                Ok(NoneType)
+            }
+        }
+
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.enable_call_recording();
+            eval.eval_module(
+                AstModule::parse(
+                    "BUILD",
+                    r#"
+srcs = ["a.c"]
+info = struct(srcs = srcs)
+rule(srcs = srcs, info = info)
+srcs.append("b.c")
+native = struct(rule = rule)
+native.rule(srcs = srcs)
+"#
+                    .to_owned(),
+                    &Dialect::Extended,
+                )
+                .unwrap(),
+                &GlobalsBuilder::extended_by(&[LibraryExtension::StructType])
+                    .with(rules)
+                    .build(),
+            )
+            .unwrap();
+        }
+        let module = module.freeze().unwrap();
+        let calls = module.recorded_calls();
+        assert_eq!(
+            vec!["struct", "rule", "append", "struct", "rule"],
+            calls
+                .iter()
+                .map(|c| c.function.as_str())
+                .collect::<Vec<_>>()
+        );
+        // Arguments are recorded as they were at the time of the call.
+        let srcs = calls[1].named_arg("srcs").unwrap().to_value();
+        assert_eq!("[\"a.c\"]", srcs.to_repr());
+        // Shared values are copied once.
+        let info = calls[1].named_arg("info").unwrap().to_value();
+        let (_, info_srcs) = StructRef::from_value(info).unwrap().iter().next().unwrap();
+        assert!(info_srcs.ptr_eq(srcs));
+        assert_eq!(
+            "[\"a.c\", \"b.c\"]",
+            calls[4].named_arg("srcs").unwrap().to_value().to_repr()
+        );
+    }
 }
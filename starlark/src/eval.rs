@@ -19,25 +19,41 @@
 //! [`eval_module`](Evaluator::eval_module).
 
 pub(crate) mod bc;
+pub(crate) mod compiled_expr;
 pub(crate) mod compiler;
 pub(crate) mod runtime;
+pub(crate) mod sandboxed_expr;
 pub(crate) mod soft_error;
 
 use std::collections::HashMap;
 use std::mem;
 use std::time::Instant;
 
+pub use compiled_expr::CompiledExpr;
 use dupe::Dupe;
+pub use compiler::opt_level::OptLevel;
 pub use runtime::arguments::Arguments;
+pub use runtime::async_executor::AsyncExecutor;
 pub use runtime::before_stmt::BeforeStmtFuncDyn;
 pub use runtime::evaluator::Evaluator;
 pub use runtime::file_loader::FileLoader;
+pub use runtime::file_loader::MockLoader;
 pub use runtime::file_loader::ReturnFileLoader;
+pub use runtime::mutation_audit::ModuleMutation;
+pub use runtime::native_call_interceptor::NativeCallInterceptor;
 pub use runtime::params::parser::ParametersParser;
+pub use runtime::params::spec::ParameterInfo;
+pub use runtime::params::spec::ParameterStyle;
 pub use runtime::params::spec::ParametersSpec;
 pub use runtime::params::spec::ParametersSpecBuilder;
 pub use runtime::profile::data::ProfileData;
 pub use runtime::profile::mode::ProfileMode;
+pub use runtime::progress::EvalProgress;
+pub use runtime::progress::ProgressHandler;
+pub use runtime::recorded_call::RecordedCall;
+pub use runtime::runtime_lints::RuntimeLints;
+pub use sandboxed_expr::SandboxLimits;
+pub use sandboxed_expr::SandboxedExpr;
 pub use soft_error::SoftErrorHandler;
 pub use starlark_syntax::call_stack::CallStack;
 use starlark_syntax::slice_vec_ext::SliceExt;
@@ -55,6 +71,9 @@ use crate::eval::compiler::Compiler;
 use crate::eval::runtime::arguments::ArgNames;
 use crate::eval::runtime::arguments::ArgumentsFull;
 use crate::eval::runtime::evaluator;
+pub use crate::stdlib::artifacts::Artifact;
+pub use crate::stdlib::artifacts::ArtifactRegistry;
+pub use crate::stdlib::artifacts::DeclaredArtifact;
 use crate::syntax::DialectTypes;
 use crate::values::Value;
 
@@ -124,6 +143,8 @@ impl<'v, 'a, 'e> Evaluator<'v, 'a, 'e> {
             codemap,
             eval: self,
             check_types: dialect.enable_types == DialectTypes::Enable,
+            to_kwargs: dialect.enable_to_kwargs,
+            duplicate_dict_keys: dialect.enable_duplicate_dict_keys,
             top_level_stmt_count,
             typecheck,
         };
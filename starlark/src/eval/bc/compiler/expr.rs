@@ -17,11 +17,8 @@
 
 //! Compile expressions.
 
-use std::collections::HashSet;
-
 use starlark_syntax::slice_vec_ext::SliceExt;
 
-use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::eval::bc::compiler::if_compiler::write_if_else;
 use crate::eval::bc::instr_impl::*;
@@ -211,19 +208,17 @@ impl IrSpanned<ExprCompiled> {
 
     fn try_dict_const_keys(
         xs: &[(IrSpanned<ExprCompiled>, IrSpanned<ExprCompiled>)],
-    ) -> Option<Box<[Hashed<FrozenValue>]>> {
-        let mut keys = Vec::new();
-        let mut keys_unique = HashSet::new();
+    ) -> Option<DictConstKeysTemplate> {
+        let mut keys = SmallMap::with_capacity(xs.len());
         for (k, _) in xs {
             let k = k.as_value()?.get_hashed().ok()?;
-            keys.push(k);
-            let inserted = keys_unique.insert(k);
-            if !inserted {
+            let prev = keys.insert_hashed(k, FrozenValue::new_none());
+            if prev.is_some() {
                 // Otherwise fail at runtime
                 return None;
             }
         }
-        Some(keys.into_boxed_slice())
+        Some(DictConstKeysTemplate(keys))
     }
 
     fn write_dict(
@@ -237,9 +232,9 @@ impl IrSpanned<ExprCompiled> {
         } else if let Some(d) = Self::try_dict_of_consts(xs) {
             bc.write_instr::<InstrDictOfConsts>(span, (d, target));
         } else if let Some(keys) = Self::try_dict_const_keys(xs) {
-            assert_eq!(keys.len(), xs.len());
+            assert_eq!(keys.0.len(), xs.len());
             write_exprs(xs.iter().map(|(_, v)| v), bc, |values, bc| {
-                assert_eq!(values.len() as usize, keys.len());
+                assert_eq!(values.len() as usize, keys.0.len());
                 bc.write_instr::<InstrDictConstKeys>(span, (keys, values.to_range_from(), target));
             });
         } else {
@@ -291,7 +286,11 @@ impl IrSpanned<ExprCompiled> {
         target: BcSlotOut,
         bc: &mut BcWriter,
     ) {
-        if let Some(a) = a.as_value() {
+        if bc.runtime_lints.equality {
+            write_n_exprs([a, b], bc, |[a, b], bc| {
+                bc.write_instr::<InstrEqChecked>(span, (a, b, target));
+            });
+        } else if let Some(a) = a.as_value() {
             Self::write_equals_const(span, b, a, target, bc);
         } else if let Some(b) = b.as_value() {
             Self::write_equals_const(span, a, b, target, bc);
@@ -368,6 +367,7 @@ impl IrSpanned<ExprCompiled> {
                         Builtin1::Dot(field) => {
                             bc.write_instr::<InstrObjectField>(span, (expr, field.clone(), target))
                         }
+                        Builtin1::ToKwargs => bc.write_instr::<InstrToKwargs>(span, arg),
                     }
                 });
             }
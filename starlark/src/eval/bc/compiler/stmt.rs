@@ -16,6 +16,7 @@
  */
 
 use crate::eval::bc::bytecode::Bc;
+use crate::eval::bc::compiler::expr::write_exprs;
 use crate::eval::bc::compiler::if_compiler::write_if_else;
 use crate::eval::bc::compiler::if_compiler::write_if_then;
 use crate::eval::bc::instr_impl::InstrCheckType;
@@ -24,6 +25,7 @@ use crate::eval::bc::instr_impl::InstrReturn;
 use crate::eval::bc::instr_impl::InstrReturnCheckType;
 use crate::eval::bc::instr_impl::InstrReturnConst;
 use crate::eval::bc::stack_ptr::BcSlotIn;
+use crate::eval::bc::stack_ptr::BcSlotOut;
 use crate::eval::bc::writer::BcWriter;
 use crate::eval::compiler::expr::ExprCompiled;
 use crate::eval::compiler::expr::MaybeNot;
@@ -48,29 +50,58 @@ pub(crate) fn write_for(
 ) {
     let definitely_assigned = bc.save_definitely_assigned();
 
-    over.write_bc_cb(bc, |over, bc| {
-        if let Some(var) = var.as_local_non_captured() {
-            // Typical case: `for x in ...: ...`,
-            // compile loop assignment directly to a local variable.
-            bc.write_for(over, var.to_bc_slot().to_out(), span, |bc| {
+    if let Some(range_args) = over.as_range() {
+        // `for i in range(...): ...`, count without allocating the range.
+        write_exprs(range_args, bc, |range_args, bc| {
+            write_for_var(var, bc, Box::new(body), |var, bc, body| {
+                bc.write_for_range(range_args, over.span, var, span, body)
+            })
+        });
+    } else {
+        over.write_bc_cb(bc, |over, bc| {
+            write_for_var(var, bc, Box::new(body), |var, bc, body| {
+                bc.write_for(over, var, span, body)
+            })
+        });
+    }
+
+    bc.restore_definitely_assigned(definitely_assigned);
+}
+
+/// Write the loop with `write_loop`, assigning the loop variable in the loop body.
+fn write_for_var<'a>(
+    var: &IrSpanned<AssignCompiledValue>,
+    bc: &mut BcWriter,
+    body: Box<dyn FnOnce(&mut BcWriter) + 'a>,
+    write_loop: impl FnOnce(BcSlotOut, &mut BcWriter, Box<dyn FnOnce(&mut BcWriter) + '_>),
+) {
+    if let Some(var) = var.as_local_non_captured() {
+        // Typical case: `for x in ...: ...`,
+        // compile loop assignment directly to a local variable.
+        write_loop(
+            var.to_bc_slot().to_out(),
+            bc,
+            Box::new(|bc| {
                 bc.mark_definitely_assigned(var);
                 body(bc);
-            })
-        } else {
-            // General case, e. g. `for (x, y[0]) in ...: ...`,
-            // compile loop assignment to a temporary variable,
-            // and reassign it in the loop body.
-            bc.alloc_slot(|var_slot, bc| {
-                bc.write_for(over, var_slot.to_out(), span, |bc| {
+            }),
+        )
+    } else {
+        // General case, e. g. `for (x, y[0]) in ...: ...`,
+        // compile loop assignment to a temporary variable,
+        // and reassign it in the loop body.
+        bc.alloc_slot(|var_slot, bc| {
+            write_loop(
+                var_slot.to_out(),
+                bc,
+                Box::new(|bc| {
                     var.write_bc(var_slot.to_in(), bc);
                     var.mark_definitely_assigned_after(bc);
                     body(bc);
-                })
-            })
-        }
-    });
-
-    bc.restore_definitely_assigned(definitely_assigned);
+                }),
+            )
+        })
+    }
 }
 
 impl StmtsCompiled {
@@ -263,6 +294,8 @@ impl StmtsCompiled {
         heap: &FrozenHeap,
     ) -> Bc {
         let mut bc = BcWriter::new(local_names, param_count, heap);
+        bc.runtime_lints = compiler.runtime_lints;
+        bc.optimizations = compiler.optimizations;
         self.write_bc(compiler, &mut bc);
 
         // Small optimization: if the last statement is return,
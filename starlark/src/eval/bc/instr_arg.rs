@@ -25,7 +25,6 @@ use std::fmt::Write;
 use itertools::Itertools;
 
 use crate::collections::symbol::symbol::Symbol;
-use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::environment::slots::ModuleSlotId;
 use crate::eval::bc::addr::BcAddr;
@@ -36,6 +35,7 @@ use crate::eval::bc::call::BcCallArgsFull;
 use crate::eval::bc::call::BcCallArgsPos;
 use crate::eval::bc::for_loop::LoopDepth;
 use crate::eval::bc::instr::BcInstr;
+use crate::eval::bc::instr_impl::DictConstKeysTemplate;
 use crate::eval::bc::instr_impl::InstrDefData;
 use crate::eval::bc::native_function::BcNativeFunction;
 use crate::eval::bc::opcode::BcOpcode;
@@ -625,7 +625,7 @@ impl BcInstrArg for Box<[FrozenValue]> {
     fn visit_jump_addr(_param: &Self, _ip: BcAddr, _consumer: &mut dyn FnMut(BcAddr)) {}
 }
 
-impl BcInstrArg for Box<[Hashed<FrozenValue>]> {
+impl BcInstrArg for DictConstKeysTemplate {
     fn fmt_append(
         param: &Self,
         _ip: BcAddr,
@@ -633,11 +633,11 @@ impl BcInstrArg for Box<[Hashed<FrozenValue>]> {
         f: &mut dyn Write,
     ) -> fmt::Result {
         write!(f, " [")?;
-        for (i, v) in param.iter().enumerate() {
+        for (i, k) in param.0.keys().enumerate() {
             if i != 0 {
                 write!(f, ", ")?;
             }
-            write!(f, "{}", TruncateValueRepr(*v.key()))?;
+            write!(f, "{}", TruncateValueRepr(*k))?;
         }
         write!(f, "]")?;
         Ok(())
@@ -25,7 +25,6 @@ use starlark_syntax::eval_exception::EvalException;
 
 use crate::coerce::coerce;
 use crate::collections::symbol::symbol::Symbol;
-use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::const_frozen_string;
 use crate::environment::slots::ModuleSlotId;
@@ -49,6 +48,7 @@ use crate::eval::bc::stack_ptr::BcSlotInRange;
 use crate::eval::bc::stack_ptr::BcSlotInRangeFrom;
 use crate::eval::bc::stack_ptr::BcSlotOut;
 use crate::eval::compiler::add_span_to_expr_error;
+use crate::eval::compiler::constants::Constants;
 use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::FrozenDef;
 use crate::eval::compiler::def::ParameterCompiled;
@@ -61,9 +61,11 @@ use crate::eval::compiler::stmt::add_assign;
 use crate::eval::compiler::stmt::bit_or_assign;
 use crate::eval::compiler::stmt::possible_gc;
 use crate::eval::compiler::stmt::AssignError;
+use crate::eval::runtime::arguments::ArgumentsFull;
 use crate::eval::runtime::arguments::ResolvedArgName;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::profile::instant::ProfilerInstant;
+use crate::eval::runtime::runtime_lints::RuntimeLintError;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
 use crate::eval::Arguments;
@@ -73,6 +75,7 @@ use crate::eval::ParametersSpec;
 use crate::values::dict::Dict;
 use crate::values::int::PointerI32;
 use crate::values::layout::value_not_special::FrozenValueNotSpecial;
+use crate::values::range::Range;
 use crate::values::string::dot_format::format_one;
 use crate::values::string::interpolation::percent_s_one;
 use crate::values::types::known_methods::KnownMethod;
@@ -87,6 +90,7 @@ use crate::values::StarlarkValue;
 use crate::values::StringValue;
 use crate::values::StringValueLike;
 use crate::values::Value;
+use crate::values::ValueLike;
 
 /// Instructions which either fail or proceed to the following instruction,
 /// and it returns error with span.
@@ -540,6 +544,44 @@ impl InstrNoFlowImpl for InstrEqStrImpl {
     }
 }
 
+/// `==` with [`RuntimeLints::equality`](crate::eval::RuntimeLints::equality) check.
+pub(crate) struct InstrEqCheckedImpl;
+pub(crate) type InstrEqChecked = InstrNoFlow<InstrEqCheckedImpl>;
+
+impl InstrNoFlowImpl for InstrEqCheckedImpl {
+    type Arg = (BcSlotIn, BcSlotIn, BcSlotOut);
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr,
+        (a, b, target): &(BcSlotIn, BcSlotIn, BcSlotOut),
+    ) -> crate::Result<()> {
+        let a = frame.get_bc_slot(*a);
+        let b = frame.get_bc_slot(*b);
+        let r = a.equals(b)?;
+        if let Some(e) = RuntimeLintError::check_equality(a, b, r) {
+            report_runtime_lint(eval, ip, RuntimeLintError::EQUALITY, e)?;
+        }
+        frame.set_bc_slot(*target, Value::new_bool(r));
+        Ok(())
+    }
+}
+
+/// Report runtime lint violation to the soft error handler.
+#[cold]
+#[inline(never)]
+fn report_runtime_lint(
+    eval: &mut Evaluator,
+    ip: BcPtrAddr,
+    category: &str,
+    e: RuntimeLintError,
+) -> crate::Result<()> {
+    let e = Bc::wrap_error_for_instr_ptr(ip, crate::Error::new_other(e), eval).into_error();
+    eval.soft_error_handler.soft_error(category, e)
+}
+
 pub(crate) struct InstrNotImpl;
 pub(crate) struct InstrMinusImpl;
 pub(crate) struct InstrPlusImpl;
@@ -904,6 +946,16 @@ impl InstrUnOpImpl for InstrLenImpl {
     }
 }
 
+pub(crate) struct InstrToKwargsImpl;
+pub(crate) type InstrToKwargs = InstrUnOp<InstrToKwargsImpl>;
+
+impl InstrUnOpImpl for InstrToKwargsImpl {
+    #[inline(always)]
+    fn eval<'v>(v: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        v.to_kwargs(heap)
+    }
+}
+
 pub(crate) struct InstrTupleNPopImpl;
 pub(crate) struct InstrListNPopImpl;
 pub(crate) struct InstrListOfConstsImpl;
@@ -1024,22 +1076,29 @@ impl InstrNoFlowImpl for InstrDictNPopImpl {
     }
 }
 
+/// Dict literal with constant unique keys: the keys with placeholder values.
+///
+/// At runtime the map is cloned, which copies the keys, their hashes and the index
+/// in a single allocation instead of inserting the keys one by one,
+/// and then the values are filled in.
+#[derive(Debug)]
+pub(crate) struct DictConstKeysTemplate(pub(crate) SmallMap<FrozenValue, FrozenValue>);
+
 impl InstrNoFlowImpl for InstrDictConstKeysImpl {
-    type Arg = (Box<[Hashed<FrozenValue>]>, BcSlotInRangeFrom, BcSlotOut);
+    type Arg = (DictConstKeysTemplate, BcSlotInRangeFrom, BcSlotOut);
 
     fn run_with_args<'v>(
         eval: &mut Evaluator<'v, '_, '_>,
         frame: BcFramePtr<'v>,
         _: BcPtrAddr,
-        (keys, values, target): &(Box<[Hashed<FrozenValue>]>, BcSlotInRangeFrom, BcSlotOut),
+        (keys, values, target): &(DictConstKeysTemplate, BcSlotInRangeFrom, BcSlotOut),
     ) -> crate::Result<()> {
-        let values = frame.get_bc_slot_range(values.to_range(keys.len() as u32));
-        let mut dict = SmallMap::with_capacity(keys.len());
-        for (k, v) in keys.iter().zip(values) {
-            let prev = dict.insert_hashed(*k, *v);
-            debug_assert!(prev.is_none());
+        let values = frame.get_bc_slot_range(values.to_range(keys.0.len() as u32));
+        let mut dict: SmallMap<Value<'v>, Value<'v>> = (*coerce(&keys.0)).clone();
+        for (slot, v) in dict.values_mut().zip(values) {
+            *slot = *v;
         }
-        let dict = eval.heap().alloc(Dict::new(coerce(dict)));
+        let dict = eval.heap().alloc(Dict::new(dict));
         frame.set_bc_slot(*target, dict);
         Ok(())
     }
@@ -1125,6 +1184,28 @@ impl BcInstr for InstrComprDictInsert {
     }
 }
 
+/// Condition check for [`RuntimeLints::truthiness`](crate::eval::RuntimeLints::truthiness).
+pub(crate) struct InstrCheckTruthinessImpl;
+pub(crate) type InstrCheckTruthiness = InstrNoFlow<InstrCheckTruthinessImpl>;
+
+impl InstrNoFlowImpl for InstrCheckTruthinessImpl {
+    type Arg = BcSlotIn;
+
+    #[inline(always)]
+    fn run_with_args<'v>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr,
+        cond: &BcSlotIn,
+    ) -> crate::Result<()> {
+        let cond = frame.get_bc_slot(*cond);
+        match RuntimeLintError::check_truthiness(cond) {
+            None => Ok(()),
+            Some(e) => report_runtime_lint(eval, ip, RuntimeLintError::TRUTHINESS, e),
+        }
+    }
+}
+
 pub(crate) struct InstrCheckTypeImpl;
 pub(crate) type InstrCheckType = InstrNoFlow<InstrCheckTypeImpl>;
 
@@ -1254,6 +1335,146 @@ impl BcInstr for InstrIter {
     }
 }
 
+/// Setup `for` loop over `range(...)` without allocating the range object.
+pub(crate) struct InstrIterRange;
+/// `continue` statement in a loop over `range(...)`.
+pub(crate) struct InstrContinueRange;
+
+/// Start, stop and step of `range(...)` call with given arguments,
+/// or `None` if the arguments are not integers or step is zero.
+#[inline(always)]
+fn range_loop_args(args: &[Value]) -> Option<(i32, i32, i32)> {
+    let (start, stop, step) = match args {
+        [stop] => (0, stop.unpack_i32()?, 1),
+        [start, stop] => (start.unpack_i32()?, stop.unpack_i32()?, 1),
+        [start, stop, step] => (start.unpack_i32()?, stop.unpack_i32()?, step.unpack_i32()?),
+        _ => return None,
+    };
+    if step == 0 {
+        return None;
+    }
+    Some((start, stop, step))
+}
+
+/// The value of counted loop iteration and the next value to try,
+/// or `None` if the loop is finished.
+#[inline(always)]
+fn range_loop_next(next: i32, stop: i32, step: i32) -> Option<(i32, i32)> {
+    if (step > 0 && next < stop) || (step < 0 && next > stop) {
+        // If addition overflows, the next value is past `stop` anyway.
+        Some((next, next.checked_add(step).unwrap_or(stop)))
+    } else {
+        None
+    }
+}
+
+impl BcInstr for InstrIterRange {
+    type Arg = (
+        BcSlotInRange,
+        FrozenRef<'static, FrameSpan>,
+        [BcSlotOut; 3],
+        BcSlotOut,
+        BcAddrOffset,
+    );
+
+    #[inline(always)]
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (args, span, [next_slot, stop_slot, step_slot], var, end): &(
+            BcSlotInRange,
+            FrozenRef<'static, FrameSpan>,
+            [BcSlotOut; 3],
+            BcSlotOut,
+            BcAddrOffset,
+        ),
+    ) -> InstrControl<'v, 'b> {
+        let args = frame.get_bc_slot_range(*args);
+        let (start, stop, step) = match range_loop_args(args) {
+            Some(start_stop_step) => start_stop_step,
+            None => {
+                // Let `range` produce the error.
+                let arguments = Arguments(ArgumentsFull {
+                    pos: args,
+                    ..ArgumentsFull::default()
+                });
+                let range = match Constants::get().fn_range.0.to_value().invoke_with_loc(
+                    Some(*span),
+                    &arguments,
+                    eval,
+                ) {
+                    Ok(range) => range,
+                    Err(e) => return InstrControl::Err(e),
+                };
+                match range.downcast_ref::<Range>() {
+                    Some(range) => range.start_stop_step(),
+                    None => {
+                        return InstrControl::Err(crate::Error::new_other(anyhow::anyhow!(
+                            "`range` returned `{}`",
+                            range.get_type()
+                        )));
+                    }
+                }
+            }
+        };
+        match range_loop_next(start, stop, step) {
+            Some((value, next)) => {
+                let heap = eval.heap();
+                frame.set_bc_slot(*next_slot, heap.alloc(next));
+                frame.set_bc_slot(*stop_slot, heap.alloc(stop));
+                frame.set_bc_slot(*step_slot, heap.alloc(step));
+                frame.set_bc_slot(*var, heap.alloc(value));
+                InstrControl::Next(ip.add_instr::<Self>())
+            }
+            None => InstrControl::Next(ip.add_rel(*end)),
+        }
+    }
+}
+
+impl BcInstr for InstrContinueRange {
+    type Arg = (
+        BcSlotInRange,
+        BcSlotOut,
+        BcSlotOut,
+        BcAddrOffsetNeg,
+        BcAddrOffset,
+    );
+
+    #[inline(always)]
+    fn run<'v, 'b>(
+        eval: &mut Evaluator<'v, '_, '_>,
+        frame: BcFramePtr<'v>,
+        ip: BcPtrAddr<'b>,
+        (state, next_slot, var, begin, end): &(
+            BcSlotInRange,
+            BcSlotOut,
+            BcSlotOut,
+            BcAddrOffsetNeg,
+            BcAddrOffset,
+        ),
+    ) -> InstrControl<'v, 'b> {
+        let [next, stop, step] = frame.get_bc_slot_range(*state) else {
+            unreachable!("counted loop state is three slots")
+        };
+        // Slots are written only by `InstrIterRange` and this instruction.
+        let (next, stop, step) = (
+            next.unpack_i32().unwrap(),
+            stop.unpack_i32().unwrap(),
+            step.unpack_i32().unwrap(),
+        );
+        match range_loop_next(next, stop, step) {
+            Some((value, next)) => {
+                let heap = eval.heap();
+                frame.set_bc_slot(*next_slot, heap.alloc(next));
+                frame.set_bc_slot(*var, heap.alloc(value));
+                InstrControl::Next(ip.add_rel_neg(*begin))
+            }
+            None => InstrControl::Next(ip.add_rel(*end)),
+        }
+    }
+}
+
 impl BcInstr for InstrContinue {
     type Arg = (
         BcSlotIn,
@@ -1462,6 +1683,9 @@ pub(crate) trait BcFrozenCallable: BcInstrArg + Copy {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>>;
+
+    /// The called function.
+    fn function<'v>(self) -> Value<'v>;
 }
 
 impl BcFrozenCallable for FrozenValue {
@@ -1474,6 +1698,11 @@ impl BcFrozenCallable for FrozenValue {
     ) -> crate::Result<Value<'v>> {
         self.to_value().invoke_with_loc(Some(location), args, eval)
     }
+
+    #[inline(always)]
+    fn function<'v>(self) -> Value<'v> {
+        self.to_value()
+    }
 }
 
 impl BcFrozenCallable for FrozenValueTyped<'static, FrozenDef> {
@@ -1488,6 +1717,11 @@ impl BcFrozenCallable for FrozenValueTyped<'static, FrozenDef> {
             self.as_ref().invoke(self.to_value(), args, eval)
         })
     }
+
+    #[inline(always)]
+    fn function<'v>(self) -> Value<'v> {
+        self.to_value()
+    }
 }
 
 impl BcFrozenCallable for BcNativeFunction {
@@ -1502,6 +1736,11 @@ impl BcFrozenCallable for BcNativeFunction {
             self.invoke(args, eval)
         })
     }
+
+    #[inline(always)]
+    fn function<'v>(self) -> Value<'v> {
+        self.to_value()
+    }
 }
 
 pub(crate) struct InstrCallImpl<A: BcCallArgs<Symbol>>(marker::PhantomData<fn(A)>);
@@ -1544,7 +1783,9 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallImpl<A> {
     ) -> crate::Result<()> {
         let f = frame.get_bc_slot(*this);
         let arguments = Arguments(args.pop_from_stack(frame));
+        let recorded = eval.snapshot_call(|| f.name_for_call_stack(), *span, &arguments.0)?;
         let r = f.invoke_with_loc(Some(*span), &arguments, eval)?;
+        eval.record_call(recorded);
         frame.set_bc_slot(*target, r);
         Ok(())
     }
@@ -1563,7 +1804,11 @@ impl<F: BcFrozenCallable, A: BcCallArgs<Symbol>> InstrNoFlowImpl
         (fun, args, span, target): &(F, A, FrozenRef<'static, FrameSpan>, BcSlotOut),
     ) -> crate::Result<()> {
         let arguments = Arguments(args.pop_from_stack(frame));
+        eval.check_call_args(&arguments.0)?;
+        let recorded =
+            eval.snapshot_call(|| fun.function().name_for_call_stack(), *span, &arguments.0)?;
         let r = fun.bc_invoke(*span, &arguments, eval)?;
+        eval.record_call(recorded);
         frame.set_bc_slot(*target, r);
         Ok(())
     }
@@ -1590,10 +1835,14 @@ impl<A: BcCallArgsForDef> InstrNoFlowImpl for InstrCallFrozenDefImpl<A> {
         ),
     ) -> crate::Result<()> {
         let arguments = args.pop_from_stack(frame);
+        eval.check_call_args(&arguments)?;
+        let recorded =
+            eval.snapshot_call(|| fun.to_value().name_for_call_stack(), *span, &arguments)?;
         let r = eval.with_call_stack(fun.to_value(), Some(*span), |eval| {
             fun.as_ref()
                 .invoke_with_args(fun.to_value(), &arguments, eval)
         })?;
+        eval.record_call(recorded);
         frame.set_bc_slot(*target, r);
         Ok(())
     }
@@ -1612,7 +1861,9 @@ fn call_method_common<'v>(
 ) -> crate::Result<()> {
     // TODO: wrong span: should be span of `object.method`, not of the whole expression
     let method = get_attr_hashed_raw(this, symbol, eval.heap())?;
+    let recorded = eval.snapshot_call(|| symbol.as_str().to_owned(), span, &arguments.0)?;
     let r = method.invoke(this, span, arguments, eval)?;
+    eval.record_call(recorded);
     frame.set_bc_slot(target, r);
     Ok(())
 }
@@ -1634,9 +1885,11 @@ fn call_maybe_known_method_common<'v>(
         // If pointers are equal, getattr would return the same method
         // we already have.
         if ptr::eq(methods, known_method.type_methods) {
+            let recorded = eval.snapshot_call(|| symbol.as_str().to_owned(), span, &arguments.0)?;
             let r = eval.with_call_stack(known_method.to_value(), Some(span), |eval| {
                 known_method.invoke_method(this, arguments, eval)
             })?;
+            eval.record_call(recorded);
             frame.set_bc_slot(target, r);
             return Ok(());
         }
@@ -1669,6 +1922,7 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMethodImpl<A> {
     ) -> crate::Result<()> {
         let this = frame.get_bc_slot(*this);
         let arguments = Arguments(args.pop_from_stack(frame));
+        eval.check_call_args(&arguments.0)?;
         call_method_common(eval, frame, this, symbol, &arguments, *span, *target)
     }
 }
@@ -1699,6 +1953,7 @@ impl<A: BcCallArgs<Symbol>> InstrNoFlowImpl for InstrCallMaybeKnownMethodImpl<A>
     ) -> crate::Result<()> {
         let this = frame.get_bc_slot(*this);
         let arguments = Arguments(args.pop_from_stack(frame));
+        eval.check_call_args(&arguments.0)?;
         call_maybe_known_method_common(
             eval,
             frame,
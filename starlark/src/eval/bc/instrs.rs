@@ -33,8 +33,12 @@ use crate::eval::bc::addr::BcAddr;
 use crate::eval::bc::addr::BcAddrOffset;
 use crate::eval::bc::addr::BcPtrAddr;
 use crate::eval::bc::instr::BcInstr;
+use crate::eval::bc::instr_impl::InstrBr;
 use crate::eval::bc::instr_impl::InstrEnd;
+use crate::eval::bc::instr_impl::InstrIfBr;
+use crate::eval::bc::instr_impl::InstrIfNotBr;
 use crate::eval::bc::instr_impl::InstrIter;
+use crate::eval::bc::instr_impl::InstrIterRange;
 use crate::eval::bc::opcode::BcOpcode;
 use crate::eval::bc::opcode::BcOpcodeHandler;
 use crate::eval::bc::repr::BcInstrHeader;
@@ -258,6 +262,9 @@ impl BcInstrs {
             if opcode == BcOpcode::Iter {
                 let for_loop = ptr.get_instr::<InstrIter>();
                 loop_ends.push(ip.offset(for_loop.arg.4));
+            } else if opcode == BcOpcode::IterRange {
+                let for_loop = ptr.get_instr::<InstrIterRange>();
+                loop_ends.push(ip.offset(for_loop.arg.4));
             }
         }
         Ok(())
@@ -336,6 +343,46 @@ impl BcInstrsWriter {
         }
     }
 
+    /// Retarget branches which jump to an unconditional branch
+    /// to the final target of the branch chain.
+    ///
+    /// Branches to the first instruction of a statement are not changed
+    /// to keep the statement visible to `before_stmt` instrumentation.
+    pub(crate) fn thread_jumps(&mut self, is_stmt_start: impl Fn(BcAddr) -> bool) {
+        let end = self.ip();
+        let start = BcPtrAddr::for_slice_start(&self.instrs);
+        // Branches only jump forward, so following a chain of branches terminates.
+        let final_target = |mut target: BcAddr| {
+            while target < end && !is_stmt_start(target) {
+                match start.offset(target).get_instr_checked::<InstrBr>() {
+                    Some(br) => target = target.offset(br.arg),
+                    None => break,
+                }
+            }
+            target
+        };
+        let mut ip = BcAddr(0);
+        while ip != end {
+            let ptr = start.offset(ip);
+            let opcode = ptr.get_opcode();
+            let offset: Option<*mut BcAddrOffset> = match opcode {
+                BcOpcode::Br => Some(unsafe { &mut (*ptr.get_instr_mut::<InstrBr>()).arg }),
+                BcOpcode::IfBr => Some(unsafe { &mut (*ptr.get_instr_mut::<InstrIfBr>()).arg.1 }),
+                BcOpcode::IfNotBr => {
+                    Some(unsafe { &mut (*ptr.get_instr_mut::<InstrIfNotBr>()).arg.1 })
+                }
+                _ => None,
+            };
+            if let Some(offset) = offset {
+                unsafe {
+                    let target = final_target(ip.offset(*offset));
+                    *offset = target.offset_from(ip);
+                }
+            }
+            ip += opcode.size_of_repr() as u32;
+        }
+    }
+
     pub(crate) fn finish(
         mut self,
         slow_args: Vec<(BcAddr, BcInstrSlowArg)>,
@@ -56,6 +56,13 @@ impl BcNativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
-        self.imp.invoke(eval, args)
+        let imp = self.imp;
+        eval.invoke_native(
+            &self.fun.as_ref().name,
+            None,
+            args,
+            self.fun.as_ref().raw_docs.as_ref(),
+            |eval| imp.invoke(eval, args),
+        )
     }
 }
@@ -52,6 +52,7 @@ pub(crate) enum BcOpcode {
     EqPtr,
     EqStr,
     EqInt,
+    EqChecked,
     Not,
     Minus,
     Plus,
@@ -77,6 +78,7 @@ pub(crate) enum BcOpcode {
     LeftShift,
     RightShift,
     Len,
+    ToKwargs,
     Type,
     TypeIs,
     IsInstance,
@@ -91,11 +93,14 @@ pub(crate) enum BcOpcode {
     ComprListAppend,
     ComprDictInsert,
     CheckType,
+    CheckTruthiness,
     Br,
     IfBr,
     IfNotBr,
     Iter,
     Continue,
+    IterRange,
+    ContinueRange,
     Break,
     IterStop,
     Return,
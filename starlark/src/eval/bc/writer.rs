@@ -28,11 +28,14 @@ use crate::eval::bc::for_loop::LoopDepth;
 use crate::eval::bc::instr::BcInstr;
 use crate::eval::bc::instr_impl::InstrBr;
 use crate::eval::bc::instr_impl::InstrBreak;
+use crate::eval::bc::instr_impl::InstrCheckTruthiness;
 use crate::eval::bc::instr_impl::InstrConst;
 use crate::eval::bc::instr_impl::InstrContinue;
+use crate::eval::bc::instr_impl::InstrContinueRange;
 use crate::eval::bc::instr_impl::InstrIfBr;
 use crate::eval::bc::instr_impl::InstrIfNotBr;
 use crate::eval::bc::instr_impl::InstrIter;
+use crate::eval::bc::instr_impl::InstrIterRange;
 use crate::eval::bc::instr_impl::InstrIterStop;
 use crate::eval::bc::instr_impl::InstrLoadLocal;
 use crate::eval::bc::instr_impl::InstrLoadLocalCaptured;
@@ -49,7 +52,9 @@ use crate::eval::bc::stack_ptr::BcSlotOut;
 use crate::eval::bc::stack_ptr::BcSlotRange;
 use crate::eval::bc::stack_ptr::BcSlotsN;
 use crate::eval::compiler::expr::MaybeNot;
+use crate::eval::compiler::opt_level::Optimizations;
 use crate::eval::runtime::frame_span::FrameSpan;
+use crate::eval::runtime::runtime_lints::RuntimeLints;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
 use crate::values::FrozenHeap;
@@ -106,9 +111,18 @@ impl BcStatementLocations {
 }
 
 /// For loop during bytecode write.
-struct BcWriterForLoop {
+/// State of the loop stored in slots.
+#[derive(Copy, Clone)]
+enum BcWriterForLoopState {
     /// Iterator variable.
-    iter: BcSlotIn,
+    Iter(BcSlotIn),
+    /// Next value, stop and step of a loop over `range(...)`.
+    Range(BcSlotRange),
+}
+
+struct BcWriterForLoop {
+    /// Iteration state.
+    state: BcWriterForLoopState,
     /// Variable to store the next value in.
     var: BcSlotOut,
     /// Address of the first instruction in the loop body.
@@ -140,6 +154,10 @@ pub(crate) struct BcWriter<'f> {
 
     /// Allocate various objects here.
     pub(crate) heap: &'f FrozenHeap,
+    /// Emit instructions for runtime strict checks.
+    pub(crate) runtime_lints: RuntimeLints,
+    /// Enabled peephole optimizations.
+    pub(crate) optimizations: Optimizations,
 }
 
 impl<'f> BcWriter<'f> {
@@ -166,6 +184,8 @@ impl<'f> BcWriter<'f> {
             heap,
             for_loops: Vec::new(),
             max_loop_depth: LoopDepth(0),
+            runtime_lints: RuntimeLints::default(),
+            optimizations: Optimizations::default(),
         }
     }
 
@@ -173,7 +193,7 @@ impl<'f> BcWriter<'f> {
     #[allow(let_underscore_drop)]
     pub(crate) fn finish(self) -> Bc {
         let BcWriter {
-            instrs,
+            mut instrs,
             slow_args: spans,
             stmt_locs,
             stack_size,
@@ -183,7 +203,12 @@ impl<'f> BcWriter<'f> {
             heap,
             for_loops,
             max_loop_depth,
+            runtime_lints: _,
+            optimizations,
         } = self;
+        if optimizations.thread_jumps {
+            instrs.thread_jumps(|addr| stmt_locs.stmt_at(addr).is_some());
+        }
         let _ = heap;
         let _ = definitely_assigned;
         assert_eq!(stack_size, 0);
@@ -310,7 +335,7 @@ impl<'f> BcWriter<'f> {
         // Do not emit no-op `Mov`.
         // It can occur when compiling code like `x = x`.
         // Currently we do not erase these no-op assignments at IR.
-        if source.get() == target.get() {
+        if source.get() == target.get() && self.optimizations.skip_self_moves {
             return;
         }
 
@@ -345,8 +370,15 @@ impl<'f> BcWriter<'f> {
         self.instrs.addr_to_patch(addr, arg)
     }
 
+    fn write_check_truthiness(&mut self, cond: BcSlotIn, span: FrameSpan) {
+        if self.runtime_lints.truthiness {
+            self.write_instr::<InstrCheckTruthiness>(span, cond);
+        }
+    }
+
     /// Write conditional branch.
     pub(crate) fn write_if_not_br(&mut self, cond: BcSlotIn, span: FrameSpan) -> PatchAddr {
+        self.write_check_truthiness(cond, span);
         let (addr, arg) =
             self.write_instr_ret_arg::<InstrIfNotBr>(span, (cond, BcAddrOffset::FORWARD));
         self.instrs.addr_to_patch(addr, unsafe { &(*arg).1 })
@@ -354,6 +386,7 @@ impl<'f> BcWriter<'f> {
 
     /// Write conditional branch.
     pub(crate) fn write_if_br(&mut self, cond: BcSlotIn, span: FrameSpan) -> PatchAddr {
+        self.write_check_truthiness(cond, span);
         let (addr, arg) =
             self.write_instr_ret_arg::<InstrIfBr>(span, (cond, BcAddrOffset::FORWARD));
         self.instrs.addr_to_patch(addr, unsafe { &(*arg).1 })
@@ -401,30 +434,71 @@ impl<'f> BcWriter<'f> {
         let for_loop = self.for_loops.last().unwrap();
         let jump_back = self.ip().offset_from(for_loop.inner_addr).neg();
         let var = for_loop.var;
-        let (addr, arg) = self.write_instr_ret_arg::<InstrContinue>(
-            span,
-            (
-                for_loop.iter,
-                loop_depth,
-                var,
-                jump_back,
-                BcAddrOffset::FORWARD,
-            ),
-        );
-        let end_patch = self.instrs.addr_to_patch(addr, unsafe { &(*arg).4 });
+        let end_patch = match for_loop.state {
+            BcWriterForLoopState::Iter(iter) => {
+                let (addr, arg) = self.write_instr_ret_arg::<InstrContinue>(
+                    span,
+                    (iter, loop_depth, var, jump_back, BcAddrOffset::FORWARD),
+                );
+                self.instrs.addr_to_patch(addr, unsafe { &(*arg).4 })
+            }
+            BcWriterForLoopState::Range(state) => {
+                let (addr, arg) = self.write_instr_ret_arg::<InstrContinueRange>(
+                    span,
+                    (
+                        state.to_in(),
+                        state.start.to_out(),
+                        var,
+                        jump_back,
+                        BcAddrOffset::FORWARD,
+                    ),
+                );
+                self.instrs.addr_to_patch(addr, unsafe { &(*arg).4 })
+            }
+        };
         let for_loop = self.for_loops.last_mut().unwrap();
         for_loop.end_addrs_to_patch.push(end_patch);
     }
 
     pub(crate) fn write_break(&mut self, span: FrameSpan) {
         let for_loop = self.for_loops.last().unwrap();
-        let (addr, arg) =
-            self.write_instr_ret_arg::<InstrBreak>(span, (for_loop.iter, BcAddrOffset::FORWARD));
-        let end_patch = self.instrs.addr_to_patch(addr, unsafe { &(*arg).1 });
+        let end_patch = match for_loop.state {
+            BcWriterForLoopState::Iter(iter) => {
+                let (addr, arg) =
+                    self.write_instr_ret_arg::<InstrBreak>(span, (iter, BcAddrOffset::FORWARD));
+                self.instrs.addr_to_patch(addr, unsafe { &(*arg).1 })
+            }
+            // Nothing to stop in a loop over `range(...)`.
+            BcWriterForLoopState::Range(_) => self.write_br(span),
+        };
         let for_loop = self.for_loops.last_mut().unwrap();
         for_loop.end_addrs_to_patch.push(end_patch);
     }
 
+    /// Write loop body and the loop end after the loop setup instruction.
+    fn write_for_body(
+        &mut self,
+        state: BcWriterForLoopState,
+        var: BcSlotOut,
+        end_patch: PatchAddr,
+        span: FrameSpan,
+        body: impl FnOnce(&mut BcWriter),
+    ) {
+        self.for_loops.push(BcWriterForLoop {
+            inner_addr: self.ip(),
+            end_addrs_to_patch: vec![end_patch],
+            var,
+            state,
+        });
+        self.max_loop_depth = cmp::max(self.max_loop_depth, LoopDepth(self.for_loops.len() as u32));
+        body(self);
+        self.write_continue(span);
+        let for_loop = self.for_loops.pop().unwrap();
+        for addr_to_patch in for_loop.end_addrs_to_patch {
+            self.patch_addr(addr_to_patch);
+        }
+    }
+
     /// Write for loop.
     pub(crate) fn write_for(
         &mut self,
@@ -445,19 +519,54 @@ impl<'f> BcWriter<'f> {
                 (over, loop_depth, iter.to_out(), var, BcAddrOffset::FORWARD),
             );
             let end_patch = bc.instrs.addr_to_patch(addr, unsafe { &(*arg).4 });
-            bc.for_loops.push(BcWriterForLoop {
-                inner_addr: bc.ip(),
-                end_addrs_to_patch: vec![end_patch],
+            bc.write_for_body(
+                BcWriterForLoopState::Iter(iter.to_in()),
                 var,
-                iter: iter.to_in(),
-            });
-            bc.max_loop_depth = cmp::max(bc.max_loop_depth, LoopDepth(bc.for_loops.len() as u32));
-            body(bc);
-            bc.write_continue(span);
-            let for_loop = bc.for_loops.pop().unwrap();
-            for addr_to_patch in for_loop.end_addrs_to_patch {
-                bc.patch_addr(addr_to_patch);
-            }
+                end_patch,
+                span,
+                body,
+            );
+
+            bc.restore_definitely_assigned(definitely_assigned);
+        })
+    }
+
+    /// Write for loop over `range(...)` called with given arguments.
+    /// The loop counts without allocating the range object.
+    pub(crate) fn write_for_range(
+        &mut self,
+        range_args: BcSlotInRange,
+        range_span: FrameSpan,
+        var: BcSlotOut,
+        span: FrameSpan,
+        body: impl FnOnce(&mut BcWriter),
+    ) {
+        // Allocate slots to store the next value, stop and step.
+        self.alloc_slots(3, |state, bc| {
+            let definitely_assigned = bc.save_definitely_assigned();
+
+            let (addr, arg) = bc.write_instr_ret_arg::<InstrIterRange>(
+                range_span,
+                (
+                    range_args,
+                    bc.alloc_file_span(range_span),
+                    [
+                        state.start.to_out(),
+                        (state.start + 1).to_out(),
+                        (state.start + 2).to_out(),
+                    ],
+                    var,
+                    BcAddrOffset::FORWARD,
+                ),
+            );
+            let end_patch = bc.instrs.addr_to_patch(addr, unsafe { &(*arg).4 });
+            bc.write_for_body(
+                BcWriterForLoopState::Range(state),
+                var,
+                end_patch,
+                span,
+                body,
+            );
 
             bc.restore_definitely_assigned(definitely_assigned);
         })
@@ -468,8 +577,9 @@ impl<'f> BcWriter<'f> {
     pub(crate) fn write_iter_stop(&mut self, span: FrameSpan) {
         // We can stop iteration in any order, but let's for consistency stop them in reverse order.
         for depth in (0..self.for_loops.len()).rev() {
-            let iter = self.for_loops[depth].iter;
-            self.write_instr::<InstrIterStop>(span, iter);
+            if let BcWriterForLoopState::Iter(iter) = self.for_loops[depth].state {
+                self.write_instr::<InstrIterStop>(span, iter);
+            }
         }
     }
 
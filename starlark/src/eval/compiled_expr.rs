@@ -0,0 +1,253 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Evaluation of a single expression against fixed globals.
+
+use starlark_syntax::codemap::Spanned;
+use starlark_syntax::syntax::ast::AssignIdentP;
+use starlark_syntax::syntax::ast::AstStmt;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::LambdaP;
+use starlark_syntax::syntax::ast::ParameterP;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+
+use crate::collections::SmallMap;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::OwnedFrozenValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum CompiledExprError {
+    #[error("Expected a single expression")]
+    NotExpression,
+}
+
+/// An expression compiled once against fixed [`Globals`],
+/// to be evaluated many times with different variable bindings.
+///
+/// Created with [`CompiledExpr::new`] and evaluated with
+/// [`Evaluator::eval_compiled_expr_typed`]. Evaluation is a plain function call:
+/// no module is created, parsed or compiled.
+///
+/// ```
+/// use starlark::collections::SmallMap;
+/// use starlark::environment::Globals;
+/// use starlark::environment::Module;
+/// use starlark::eval::CompiledExpr;
+/// use starlark::eval::Evaluator;
+///
+/// let expr = CompiledExpr::new("x * 2 + len(y)", &["x", "y"], &Globals::standard()).unwrap();
+/// let module = Module::new();
+/// let mut eval = Evaluator::new(&module);
+/// for x in 0..3 {
+///     let mut bindings = SmallMap::new();
+///     bindings.insert("x", module.heap().alloc(x));
+///     bindings.insert("y", module.heap().alloc("abc"));
+///     let res: i32 = eval.eval_compiled_expr_typed(&expr, &bindings).unwrap();
+///     assert_eq!(x * 2 + 3, res);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledExpr {
+    /// Lambda taking the variables as keyword-only parameters.
+    function: OwnedFrozenValue,
+}
+
+impl CompiledExpr {
+    /// Name of the lambda in the module used to compile the expression.
+    const FUNCTION: &'static str = "expr";
+
+    /// Compile an expression, which can refer to the given `variables` and to `globals`.
+    ///
+    /// Referencing any other name is a compile error.
+    pub fn new(expr: &str, variables: &[&str], globals: &Globals) -> crate::Result<CompiledExpr> {
+        Self::with_dialect(expr, variables, globals, &Dialect::Extended)
+    }
+
+    /// Like [`new`](CompiledExpr::new), but parse the expression with the given [`Dialect`].
+    pub fn with_dialect(
+        expr: &str,
+        variables: &[&str],
+        globals: &Globals,
+        dialect: &Dialect,
+    ) -> crate::Result<CompiledExpr> {
+        let ast = AstModule::parse("<expr>", expr.to_owned(), dialect)?;
+        let span = ast.statement().span;
+        if Self::as_expression(ast.statement()).is_none() {
+            return Err(crate::Error::new_spanned(
+                crate::ErrorKind::Other(CompiledExprError::NotExpression.into()),
+                span,
+                ast.codemap(),
+            ));
+        }
+        let ast = ast.map_statement(|stmt| {
+            let body = match stmt.node {
+                StmtP::Statements(mut stmts) => stmts.pop().unwrap(),
+                _ => stmt,
+            };
+            let StmtP::Expression(body) = body.node else {
+                unreachable!("checked above")
+            };
+            let params = Some(ParameterP::NoArgs)
+                .filter(|_| !variables.is_empty())
+                .into_iter()
+                .chain(variables.iter().map(|v| {
+                    ParameterP::Normal(
+                        Spanned {
+                            node: AssignIdentP {
+                                ident: (*v).to_owned(),
+                                payload: (),
+                            },
+                            span,
+                        },
+                        None,
+                    )
+                }))
+                .map(|node| Spanned { node, span })
+                .collect();
+            Spanned {
+                node: StmtP::Expression(Spanned {
+                    node: ExprP::Lambda(LambdaP {
+                        params,
+                        body: Box::new(body),
+                        payload: (),
+                    }),
+                    span,
+                }),
+                span,
+            }
+        });
+
+        let module = Module::new();
+        let function = Evaluator::new(&module).eval_module(ast, globals)?;
+        module.set(Self::FUNCTION, function);
+        let module = module.freeze()?;
+        Ok(CompiledExpr {
+            function: module.get(Self::FUNCTION)?,
+        })
+    }
+
+    fn as_expression(stmt: &AstStmt) -> Option<&AstStmt> {
+        match &stmt.node {
+            StmtP::Statements(stmts) if stmts.len() == 1 => Self::as_expression(&stmts[0]),
+            StmtP::Expression(_) => Some(stmt),
+            _ => None,
+        }
+    }
+}
+
+impl<'v> Evaluator<'v, '_, '_> {
+    /// Evaluate a precompiled expression, binding each of its variables
+    /// to the value of the same name in `bindings`.
+    ///
+    /// Every variable the expression was compiled with must be bound,
+    /// and no other names may be given.
+    pub fn eval_compiled_expr_typed<T: UnpackValue<'v>>(
+        &mut self,
+        expr: &CompiledExpr,
+        bindings: &SmallMap<&str, Value<'v>>,
+    ) -> crate::Result<T> {
+        let function = expr.function.owned_value(self.frozen_heap());
+        let named: Vec<(&str, Value<'v>)> = bindings.iter().map(|(k, v)| (*k, *v)).collect();
+        let res = self.eval_function(function, &[], &named)?;
+        Ok(T::unpack_value_err(res)?)
+    }
+
+    /// Evaluate a single expression against `globals`, with the variables in `bindings`.
+    ///
+    /// This compiles the expression on every call, use [`CompiledExpr`]
+    /// to evaluate the same expression repeatedly.
+    pub fn eval_expression_typed<T: UnpackValue<'v>>(
+        &mut self,
+        expr: &str,
+        globals: &Globals,
+        bindings: &SmallMap<&str, Value<'v>>,
+    ) -> crate::Result<T> {
+        let variables: Vec<&str> = bindings.keys().copied().collect();
+        let expr = CompiledExpr::new(expr, &variables, globals)?;
+        self.eval_compiled_expr_typed(&expr, bindings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collections::SmallMap;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::CompiledExpr;
+    use crate::eval::Evaluator;
+    use crate::values::Value;
+
+    #[test]
+    fn test_eval_expression_typed() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let mut bindings = SmallMap::new();
+        bindings.insert("a", module.heap().alloc(3));
+        bindings.insert("b", module.heap().alloc("x"));
+        let res: String = eval
+            .eval_expression_typed("b * a + str([a])", &Globals::standard(), &bindings)
+            .unwrap();
+        assert_eq!("xxx[3]", res);
+        let res: i32 = eval
+            .eval_expression_typed("max([1, 5, 2])", &Globals::standard(), &SmallMap::new())
+            .unwrap();
+        assert_eq!(5, res);
+    }
+
+    #[test]
+    fn test_compiled_expr_errors() {
+        let globals = Globals::standard();
+        for (expr, err) in [
+            ("x = 1", "Expected a single expression"),
+            ("1\n2", "Expected a single expression"),
+            ("def f(): pass", "Expected a single expression"),
+            ("x + y", "Variable `y` not found"),
+        ] {
+            let e = CompiledExpr::new(expr, &["x"], &globals).unwrap_err();
+            assert!(e.to_string().contains(err), "{expr}: {e}");
+        }
+
+        let expr = CompiledExpr::new("x + 1", &["x"], &globals).unwrap();
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let e = eval
+            .eval_compiled_expr_typed::<Value>(&expr, &SmallMap::new())
+            .unwrap_err();
+        assert!(e.to_string().contains("Missing parameter `x`"), "{e}");
+        let mut bindings = SmallMap::new();
+        bindings.insert("x", module.heap().alloc("s"));
+        let e = eval
+            .eval_compiled_expr_typed::<Value>(&expr, &bindings)
+            .unwrap_err();
+        assert!(e.to_string().contains("`+` not supported"), "{e}");
+        bindings.insert("x", module.heap().alloc(1));
+        let e = eval
+            .eval_compiled_expr_typed::<String>(&expr, &bindings)
+            .unwrap_err();
+        assert!(
+            e.to_string().contains("Expected `str`, but got `int"),
+            "{e}"
+        );
+    }
+}
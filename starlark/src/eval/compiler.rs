@@ -26,6 +26,7 @@ pub(crate) mod expr_bool;
 pub(crate) mod known;
 pub(crate) mod module;
 pub(crate) mod opt_ctx;
+pub(crate) mod opt_level;
 pub(crate) mod scope;
 pub(crate) mod small_vec_1;
 pub(crate) mod span;
@@ -88,6 +89,10 @@ pub(crate) struct Compiler<'v, 'a, 'e, 'x> {
     pub(crate) globals: FrozenRef<'static, Globals>,
     pub(crate) codemap: FrozenRef<'static, CodeMap>,
     pub(crate) check_types: bool,
+    /// Set with [`Dialect::enable_to_kwargs`](crate::syntax::Dialect::enable_to_kwargs).
+    pub(crate) to_kwargs: bool,
+    /// Set with [`Dialect::enable_duplicate_dict_keys`](crate::syntax::Dialect::enable_duplicate_dict_keys).
+    pub(crate) duplicate_dict_keys: bool,
     pub(crate) top_level_stmt_count: usize,
     /// Set with `@starlark-rust: typecheck`.
     pub(crate) typecheck: bool,
@@ -21,6 +21,7 @@ use starlark_syntax::syntax::ast::ArgumentP;
 
 use crate::coerce::coerce;
 use crate::collections::symbol::symbol::Symbol;
+use crate::eval::compiler::expr::Builtin1;
 use crate::eval::compiler::expr::ExprCompiled;
 use crate::eval::compiler::opt_ctx::OptCtx;
 use crate::eval::compiler::scope::payload::CstArgument;
@@ -179,7 +180,17 @@ impl Compiler<'_, '_, '_, '_> {
                     res.pos_named.push(self.expr(value));
                 }
                 ArgumentP::Args(x) => res.args = Some(self.expr(x)),
-                ArgumentP::KwArgs(x) => res.kwargs = Some(self.expr(x)),
+                ArgumentP::KwArgs(x) => {
+                    let kwargs = self.expr(x);
+                    res.kwargs = Some(if self.to_kwargs {
+                        IrSpanned {
+                            span: kwargs.span,
+                            node: ExprCompiled::Builtin1(Builtin1::ToKwargs, Box::new(kwargs)),
+                        }
+                    } else {
+                        kwargs
+                    });
+                }
             }
         }
         res
@@ -94,6 +94,19 @@ impl CallCompiled {
         self.args.one_pos()
     }
 
+    /// If this call expression is `range(...)` with positional arguments, return the arguments.
+    pub(crate) fn as_range(&self) -> Option<&[IrSpanned<ExprCompiled>]> {
+        if !self.fun.is_fn_range() {
+            return None;
+        }
+        let args = self.args.pos_only()?;
+        if (1..=3).contains(&args.len()) {
+            Some(args)
+        } else {
+            None
+        }
+    }
+
     /// If this call expression is `isinstance(x, t)`, return `(x, t)`.
     pub(crate) fn as_isinstance(&self) -> Option<(&IrSpanned<ExprCompiled>, FrozenValue)> {
         if !self.fun.is_fn_isinstance() {
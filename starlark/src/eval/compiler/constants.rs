@@ -47,6 +47,7 @@ pub(crate) struct Constants {
     pub(crate) fn_dict: BuiltinFn,
     pub(crate) fn_tuple: BuiltinFn,
     pub(crate) fn_isinstance: BuiltinFn,
+    pub(crate) fn_range: BuiltinFn,
     // Technically, this is not a function.
     pub(crate) typing_callable: BuiltinFn,
 }
@@ -62,6 +63,7 @@ impl Constants {
                 fn_dict: BuiltinFn(g.get_frozen("dict").unwrap()),
                 fn_tuple: BuiltinFn(g.get_frozen("tuple").unwrap()),
                 fn_isinstance: BuiltinFn(g.get_frozen("isinstance").unwrap()),
+                fn_range: BuiltinFn(g.get_frozen("range").unwrap()),
                 typing_callable: {
                     let typing = g.get_frozen("typing").unwrap();
                     let typing = FrozenStructRef::from_value(typing).unwrap();
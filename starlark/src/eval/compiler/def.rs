@@ -58,6 +58,7 @@ use crate::eval::compiler::def_inline::inline_def_body;
 use crate::eval::compiler::def_inline::InlineDefBody;
 use crate::eval::compiler::expr::ExprCompiled;
 use crate::eval::compiler::opt_ctx::OptCtx;
+use crate::eval::compiler::opt_level::Optimizations;
 use crate::eval::compiler::scope::payload::CstAssignIdent;
 use crate::eval::compiler::scope::payload::CstParameter;
 use crate::eval::compiler::scope::payload::CstPayload;
@@ -469,6 +470,8 @@ impl Compiler<'_, '_, '_, '_> {
         let inline_def_body = if has_types {
             // It is harder to inline if a function declares parameter types or return type.
             None
+        } else if !Optimizations::new(self.eval.opt_level).inline {
+            None
         } else {
             inline_def_body(&params, &body)
         };
@@ -829,23 +832,26 @@ impl FrozenDef {
 
         // Now perform the optimization of function body with fully frozen module:
         // all module variables are frozen, so we can inline more aggressively.
-        let body_optimized = self
-            .def_info
-            .body_stmts
-            .optimize(&mut OptCtx::new(
+        let optimized;
+        let body = if self.def_info.stmt_compile_context.optimizations.inline {
+            optimized = self.def_info.body_stmts.optimize(&mut OptCtx::new(
                 &mut OptimizeOnFreezeContext {
                     module: def_module.as_ref(),
                     heap,
                     frozen_heap,
                 },
                 self.parameters.len().try_into().unwrap(),
-            ))
-            .as_bc(
-                &self.def_info.stmt_compile_context,
-                self.def_info.used,
-                self.parameters.len() as u32,
-                frozen_heap,
-            );
+            ));
+            &optimized
+        } else {
+            &self.def_info.body_stmts
+        };
+        let body_optimized = body.as_bc(
+            &self.def_info.stmt_compile_context,
+            self.def_info.used,
+            self.parameters.len() as u32,
+            frozen_heap,
+        );
 
         // Store the optimized body.
         // This is (relatively) safe because we know that during freeze
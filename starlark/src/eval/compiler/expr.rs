@@ -22,6 +22,7 @@ use std::cmp::Ordering;
 
 use dupe::Dupe;
 use starlark_derive::VisitSpanMut;
+use starlark_map::small_set::SmallSet;
 use starlark_syntax::slice_vec_ext::SliceExt;
 use starlark_syntax::syntax::ast::AstExprP;
 use starlark_syntax::syntax::ast::AstLiteral;
@@ -36,7 +37,6 @@ use thiserror::Error;
 use crate::codemap::Spanned;
 use crate::collections::symbol::symbol::Symbol;
 use crate::environment::slots::ModuleSlotId;
-use crate::errors::did_you_mean::did_you_mean;
 use crate::eval::compiler::args::ArgsCompiledValue;
 use crate::eval::compiler::call::CallCompiled;
 use crate::eval::compiler::compr::ComprCompiled;
@@ -142,6 +142,8 @@ pub(crate) enum Builtin1 {
     FormatOne(FrozenStringValue, FrozenStringValue),
     /// `x.field`.
     Dot(Symbol),
+    /// `f(**x)` argument converted to a dictionary.
+    ToKwargs,
 }
 
 impl Builtin1 {
@@ -163,6 +165,7 @@ impl Builtin1 {
             Builtin1::Dot(field) => {
                 Some(ExprCompiled::compile_time_getattr(v, field, ctx)?.to_value())
             }
+            Builtin1::ToKwargs => v.to_value().to_kwargs(ctx.heap()).ok(),
         }
     }
 }
@@ -327,6 +330,23 @@ impl ExprCompiled {
         }
     }
 
+    /// Expression is builtin `range` function.
+    pub(crate) fn is_fn_range(&self) -> bool {
+        match self.as_value() {
+            Some(value) => value == Constants::get().fn_range,
+            None => false,
+        }
+    }
+
+    /// If expression is `range(...)` with one to three positional arguments,
+    /// return the arguments.
+    pub(crate) fn as_range(&self) -> Option<&[IrSpanned<ExprCompiled>]> {
+        match self {
+            Self::Call(c) => c.as_range(),
+            _ => None,
+        }
+    }
+
     /// If expression is `type(x)`, return `x`.
     pub(crate) fn as_type(&self) -> Option<&IrSpanned<ExprCompiled>> {
         match self {
@@ -906,11 +926,19 @@ impl ExprCompiled {
         step: Option<IrSpanned<ExprCompiled>>,
         ctx: &mut OptCtx,
     ) -> ExprCompiled {
+        // Omitted index is constant, present index must be a constant value.
+        fn const_index(e: &Option<IrSpanned<ExprCompiled>>) -> Option<Option<FrozenValue>> {
+            match e {
+                None => Some(None),
+                Some(e) => e.as_value().map(Some),
+            }
+        }
+
         if let (Some(array), Some(start), Some(stop), Some(step)) = (
             array.as_builtin_value(),
-            start.as_ref().map(|e| e.as_value()),
-            stop.as_ref().map(|e| e.as_value()),
-            step.as_ref().map(|e| e.as_value()),
+            const_index(&start),
+            const_index(&stop),
+            const_index(&step),
         ) {
             if let Ok(v) = array.to_value().slice(
                 start.map(|v| v.to_value()),
@@ -1016,6 +1044,49 @@ impl ExprCompiled {
             },
         }))
     }
+
+    /// Keys of a dict literal are not distinct constants, so they may be equal at runtime.
+    fn dict_keys_may_collide(xs: &[(IrSpanned<ExprCompiled>, IrSpanned<ExprCompiled>)]) -> bool {
+        let mut keys = SmallSet::with_capacity(xs.len());
+        for (k, _) in xs {
+            match k.as_value().and_then(|k| k.get_hashed().ok()) {
+                Some(k) if keys.insert_hashed(k) => {}
+                _ => return true,
+            }
+        }
+        false
+    }
+
+    /// Compile a dict literal which may have duplicate keys as `dict([(k, v), ...])`.
+    pub(crate) fn dict_last_wins(
+        span: FrameSpan,
+        xs: Vec<(IrSpanned<ExprCompiled>, IrSpanned<ExprCompiled>)>,
+        heap: &FrozenHeap,
+    ) -> ExprCompiled {
+        let entries = xs
+            .into_iter()
+            .map(|(k, v)| IrSpanned {
+                span: k.span.merge(&v.span),
+                node: ExprCompiled::tuple(vec![k, v], heap),
+            })
+            .collect();
+        ExprCompiled::Call(Box::new(IrSpanned {
+            span,
+            node: CallCompiled {
+                fun: IrSpanned {
+                    span,
+                    node: ExprCompiled::Value(Constants::get().fn_dict.0),
+                },
+                args: ArgsCompiledValue {
+                    pos_named: vec![IrSpanned {
+                        span,
+                        node: ExprCompiled::List(entries),
+                    }],
+                    ..ArgsCompiledValue::default()
+                },
+            },
+        }))
+    }
 }
 
 #[derive(Debug, Clone, Error)]
@@ -1041,7 +1112,7 @@ fn try_eval_type_is(
     }
 }
 
-trait AstLiteralCompile {
+pub(crate) trait AstLiteralCompile {
     fn compile(&self, heap: &FrozenHeap) -> FrozenValue;
 }
 
@@ -1105,20 +1176,6 @@ impl<P: AstPayload> CompilerExprUtil<P> for ExprP<P> {
     }
 }
 
-#[cold]
-#[inline(never)]
-fn get_attr_no_attr_error<'v>(x: Value<'v>, attribute: &Symbol) -> crate::Error {
-    match did_you_mean(attribute.as_str(), x.dir_attr().iter().map(|s| s.as_str())) {
-        None => ValueError::NoAttr(x.get_type().to_owned(), attribute.as_str().to_owned()).into(),
-        Some(better) => ValueError::NoAttrDidYouMean(
-            x.get_type().to_owned(),
-            attribute.as_str().to_owned(),
-            better.to_owned(),
-        )
-        .into(),
-    }
-}
-
 pub(crate) enum MemberOrValue<'v, 'a> {
     Member(&'a UnboundValue),
     Value(Value<'v>),
@@ -1153,7 +1210,7 @@ pub(crate) fn get_attr_hashed_raw<'v>(
         }
     }
     match aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
-        None => Err(get_attr_no_attr_error(x, attribute)),
+        None => Err(ValueError::no_attr(x, attribute.as_str())),
         Some(x) => Ok(MemberOrValue::Value(x)),
     }
 }
@@ -1170,7 +1227,7 @@ pub(crate) fn get_attr_hashed_bind<'v>(
         }
     }
     match aref.get_attr_hashed(attribute.as_str_hashed(), heap) {
-        None => Err(get_attr_no_attr_error(x, attribute)),
+        None => Err(ValueError::no_attr(x, attribute.as_str())),
         Some(x) => {
             // Only `get_methods` is allowed to return unbound methods or attributes.
             // Both types are crate private, so we assume `get_attr` never returns them.
@@ -1253,7 +1310,15 @@ impl<'v, 'a, 'e> Compiler<'v, 'a, 'e, '_> {
             }
             ExprP::Dict(exprs) => {
                 let xs = exprs.map(|(k, v)| (self.expr(k), self.expr(v)));
-                ExprCompiled::Dict(xs)
+                if self.duplicate_dict_keys
+                    && xs.len() > 1
+                    && ExprCompiled::dict_keys_may_collide(&xs)
+                {
+                    // Last value wins, like in `dict([(k, v), ...])`.
+                    ExprCompiled::dict_last_wins(span, xs, self.eval.module_env.frozen_heap())
+                } else {
+                    ExprCompiled::Dict(xs)
+                }
             }
             ExprP::If(cond_then_expr_else_expr) => {
                 let (cond, then_expr, else_expr) = &**cond_then_expr_else_expr;
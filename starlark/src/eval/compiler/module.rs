@@ -18,6 +18,9 @@
 //! Compile and evaluate module top-level statements.
 
 use starlark_syntax::eval_exception::EvalException;
+use starlark_syntax::syntax::ast::AssignP;
+use starlark_syntax::syntax::ast::DefP;
+use starlark_syntax::syntax::ast::ForP;
 use starlark_syntax::syntax::ast::LoadP;
 use starlark_syntax::syntax::ast::StmtP;
 use starlark_syntax::syntax::top_level_stmts::top_level_stmts_mut;
@@ -27,6 +30,7 @@ use crate::const_frozen_string;
 use crate::eval::bc::frame::alloca_frame;
 use crate::eval::compiler::add_span_to_expr_error;
 use crate::eval::compiler::expr_throw;
+use crate::eval::compiler::scope::payload::CstAssignIdent;
 use crate::eval::compiler::scope::payload::CstPayload;
 use crate::eval::compiler::scope::payload::CstStmt;
 use crate::eval::compiler::scope::ScopeId;
@@ -71,6 +75,11 @@ impl<'v> Compiler<'v, '_, '_, '_> {
             }
             Some(loader) => expr_throw(loader.load(name), span, self.eval)?,
         };
+        expr_throw(
+            loadenv.check_load_visibility(name, self.codemap.filename()),
+            span,
+            self.eval,
+        )?;
 
         for load_arg in &load.node.args {
             let (slot, _captured) = self
@@ -87,12 +96,37 @@ impl<'v> Compiler<'v, '_, '_, '_> {
                 FrameSpan::new(FrozenFileSpan::new(self.codemap, load_arg.span())),
                 self.eval,
             )?;
-            self.eval.set_slot_module(slot, value)
+            self.eval.set_slot_module(slot, value);
+            let location = loadenv
+                .definition_location(&load_arg.their.node)
+                .unwrap_or_else(|| self.codemap.file_span(load_arg.their.span));
+            self.eval
+                .module_env
+                .record_definition(&load_arg.local.node.ident, location);
         }
 
         Ok(())
     }
 
+    /// Record where the module variables assigned by the statement are defined.
+    fn record_definitions(&self, stmt: &CstStmt) {
+        let record = |ident: &CstAssignIdent| {
+            self.eval
+                .module_env
+                .record_definition(&ident.node.ident, self.codemap.file_span(ident.span))
+        };
+        match &stmt.node {
+            StmtP::Assign(AssignP { lhs, .. }) | StmtP::AssignModify(lhs, _, _) => {
+                lhs.node.visit_lvalue(record)
+            }
+            StmtP::For(ForP { var, .. }) => var.node.visit_lvalue(record),
+            // Def body assigns local variables.
+            StmtP::Def(DefP { name, .. }) => return record(name),
+            _ => {}
+        }
+        stmt.node.visit_stmt(|stmt| self.record_definitions(stmt));
+    }
+
     /// Compile and evaluate regular statement.
     /// Regular statement is a statement which is not `load` or a sequence of statements.
     fn eval_regular_top_level_stmt(
@@ -156,7 +190,10 @@ impl<'v> Compiler<'v, '_, '_, '_> {
                     })?;
                     last = Value::new_none();
                 }
-                _ => last = self.eval_regular_top_level_stmt(stmt, local_names)?,
+                _ => {
+                    self.record_definitions(stmt);
+                    last = self.eval_regular_top_level_stmt(stmt, local_names)?
+                }
             }
         }
 
@@ -0,0 +1,77 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dupe::Dupe;
+
+/// How much the compiler optimizes the code, set with
+/// [`Evaluator::set_opt_level`](crate::eval::Evaluator::set_opt_level).
+///
+/// Lower levels compile faster and produce bytecode which maps to
+/// the source code more directly, which is useful when debugging.
+/// Constant folding, including comparisons of constants, is part of compilation,
+/// so it is done at every level.
+///
+/// When no level is set, small functions are inlined and function bodies are
+/// re-optimized as with [`Aggressive`](OptLevel::Aggressive),
+/// and moves of a slot to itself are removed, but branches are not threaded.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// Emit bytecode statement by statement without further optimization.
+    None = 0,
+    /// Peephole optimizations of the bytecode:
+    /// moves of a slot to itself are removed,
+    /// and branches to unconditional branches jump directly to the final target.
+    Peephole = 1,
+    /// Peephole optimizations, plus inlining of small functions,
+    /// and re-optimization of function bodies when the module is frozen,
+    /// once module variables are known.
+    Aggressive = 2,
+}
+
+/// Optimizations enabled by an [`OptLevel`], or by default when no level is set.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub(crate) struct Optimizations {
+    /// Do not emit moves of a slot to itself.
+    pub(crate) skip_self_moves: bool,
+    /// Retarget branches to unconditional branches to the final target.
+    pub(crate) thread_jumps: bool,
+    /// Inline small functions, and re-optimize function bodies on freeze.
+    pub(crate) inline: bool,
+}
+
+impl Optimizations {
+    pub(crate) fn new(opt_level: Option<OptLevel>) -> Optimizations {
+        match opt_level {
+            None => Optimizations {
+                skip_self_moves: true,
+                thread_jumps: false,
+                inline: true,
+            },
+            Some(opt_level) => Optimizations {
+                skip_self_moves: opt_level >= OptLevel::Peephole,
+                thread_jumps: opt_level >= OptLevel::Peephole,
+                inline: opt_level >= OptLevel::Aggressive,
+            },
+        }
+    }
+}
+
+impl Default for Optimizations {
+    fn default() -> Optimizations {
+        Optimizations::new(None)
+    }
+}
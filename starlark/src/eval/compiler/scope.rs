@@ -20,6 +20,7 @@ pub(crate) mod scope_resolver_globals;
 mod tests;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::iter;
 use std::marker::PhantomData;
 use std::mem;
@@ -48,12 +49,14 @@ use starlark_syntax::syntax::top_level_stmts::top_level_stmts_mut;
 use starlark_syntax::syntax::uniplate::VisitMut;
 
 use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
 use crate::codemap::Span;
 use crate::environment::names::MutableNames;
 use crate::environment::slots::ModuleSlotId;
 use crate::environment::Module;
 use crate::errors::did_you_mean::did_you_mean;
 use crate::eval::compiler::def::CopySlotFromParent;
+use crate::eval::compiler::expr::AstLiteralCompile;
 use crate::eval::compiler::scope::payload::CstAssignIdent;
 use crate::eval::compiler::scope::payload::CstAssignTarget;
 use crate::eval::compiler::scope::payload::CstExpr;
@@ -65,6 +68,7 @@ use crate::eval::compiler::scope::payload::CstStmtFromAst;
 use crate::eval::compiler::scope::payload::CstTypeExpr;
 use crate::eval::compiler::scope::scope_resolver_globals::ScopeResolverGlobals;
 use crate::eval::runtime::slots::LocalSlotIdCapturedOrNot;
+use crate::message::MessageTemplate;
 use crate::syntax::Dialect;
 use crate::typing::error::InternalError;
 use crate::typing::Interface;
@@ -72,6 +76,7 @@ use crate::values::FrozenHeap;
 use crate::values::FrozenRef;
 use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
+use crate::values::ValueLike;
 
 #[derive(Debug, thiserror::Error)]
 enum ScopeError {
@@ -81,11 +86,40 @@ enum ScopeError {
     VariableNotFoundDidYouMean(String, String),
     #[error("Identifiers in type expressions can only refer globals or builtins: `{0}`")]
     TypeExpressionGlobalOrBuiltin(String),
+    #[error("Variable `{0}` shadows a builtin in strict mode")]
+    ShadowsBuiltin(String),
+    #[error("Private top-level variable `{0}` is never used in strict mode")]
+    UnusedPrivateTopLevel(String),
+    #[error("Dictionary key repeated for `{0}`, first used at {1}")]
+    DuplicateDictKey(String, FileSpan),
+}
+
+impl ScopeError {
+    fn message_template(&self) -> MessageTemplate {
+        let (code, args) = match self {
+            ScopeError::VariableNotFound(x) => ("variable-not-found", vec![x.clone()]),
+            ScopeError::VariableNotFoundDidYouMean(x, better) => (
+                "variable-not-found-did-you-mean",
+                vec![x.clone(), better.clone()],
+            ),
+            ScopeError::TypeExpressionGlobalOrBuiltin(x) => {
+                ("type-expression-global-or-builtin", vec![x.clone()])
+            }
+            ScopeError::ShadowsBuiltin(x) => ("shadows-builtin", vec![x.clone()]),
+            ScopeError::UnusedPrivateTopLevel(x) => ("unused-private-top-level", vec![x.clone()]),
+            ScopeError::DuplicateDictKey(x, first) => {
+                ("duplicate-dict-key", vec![x.clone(), first.to_string()])
+            }
+        };
+        MessageTemplate::new(code, args)
+    }
 }
 
 impl From<ScopeError> for crate::Error {
     fn from(e: ScopeError) -> Self {
+        let message_template = e.message_template();
         crate::Error::new(crate::ErrorKind::Scope(anyhow::Error::new(e)))
+            .with_message_template(message_template)
     }
 }
 
@@ -103,6 +137,10 @@ struct ModuleScopeBuilder<'a> {
     globals: ScopeResolverGlobals,
     errors: Vec<EvalException>,
     top_level_stmt_count: usize,
+    /// Bindings referenced by identifiers, for [`Dialect::strict`] checks.
+    used_bindings: HashSet<BindingId>,
+    /// Set with [`Dialect::enable_duplicate_dict_keys`].
+    duplicate_dict_keys: bool,
 }
 
 pub(crate) struct ModuleScopes<'f> {
@@ -341,10 +379,15 @@ impl<'f> ModuleScopeBuilder<'f> {
             globals,
             errors: Vec::new(),
             top_level_stmt_count: top_level_stmts.len(),
+            used_bindings: HashSet::new(),
+            duplicate_dict_keys: dialect.enable_duplicate_dict_keys,
         };
         for stmt in top_level_stmts.iter_mut() {
             scope.resolve_idents(stmt);
         }
+        if dialect.strict {
+            scope.check_strict();
+        }
         (cst, scope)
     }
 }
@@ -627,10 +670,50 @@ impl<'f> ModuleScopeBuilder<'f> {
                 let (k, v) = &mut **k_v;
                 self.resolve_idents_in_compr(&mut [k, v], first_for, clauses)
             }
+            ExprP::Dict(xs) => {
+                self.check_dict_keys(xs);
+                for (k, v) in xs {
+                    self.resolve_idents_in_expr_impl(scope, k);
+                    self.resolve_idents_in_expr_impl(scope, v);
+                }
+            }
             _ => expr.visit_expr_mut(|expr| self.resolve_idents_in_expr_impl(scope, expr)),
         }
     }
 
+    /// Duplicate literal keys in a dict literal are a static error,
+    /// unless allowed by [`Dialect::enable_duplicate_dict_keys`].
+    /// Other duplicate keys are detected at runtime.
+    fn check_dict_keys(&mut self, xs: &[(CstExpr, CstExpr)]) {
+        if self.duplicate_dict_keys {
+            return;
+        }
+        let mut keys: SmallMap<FrozenValue, Span> = SmallMap::new();
+        for (k, _) in xs {
+            let ExprP::Literal(x) = &k.node else {
+                continue;
+            };
+            // Compare keys like at runtime, e.g. `1` and `1.0` are the same key.
+            let Ok(key) = x.compile(self.frozen_heap).get_hashed() else {
+                continue;
+            };
+            match keys.get_hashed(key.as_ref()) {
+                Some(first) => self.errors.push(EvalException::new(
+                    ScopeError::DuplicateDictKey(
+                        key.key().to_value().to_repr(),
+                        self.codemap.file_span(*first),
+                    )
+                    .into(),
+                    k.span,
+                    &self.codemap,
+                )),
+                None => {
+                    keys.insert_hashed(key, k.span);
+                }
+            }
+        }
+    }
+
     fn resolve_idents_in_expr(&mut self, expr: &mut CstExpr) {
         self.resolve_idents_in_expr_impl(ResolveIdentScope::Any, expr);
     }
@@ -690,7 +773,10 @@ impl<'f> ModuleScopeBuilder<'f> {
                     Some(v) => ResolvedIdent::Global(v),
                 }
             }
-            Some((slot, binding_id)) => ResolvedIdent::Slot(slot, binding_id),
+            Some((slot, binding_id)) => {
+                self.used_bindings.insert(binding_id);
+                ResolvedIdent::Slot(slot, binding_id)
+            }
         };
         match scope {
             ResolveIdentScope::Any => {}
@@ -710,6 +796,35 @@ impl<'f> ModuleScopeBuilder<'f> {
         ident.node.payload = Some(resolved);
     }
 
+    /// Checks for [`Dialect::strict`], performed after all identifiers are resolved.
+    fn check_strict(&mut self) {
+        if let Some(globals) = self.globals.globals {
+            for binding in &self.scope_data.bindings {
+                if let BindingSource::Source(span) = binding.source {
+                    if globals.get_frozen(binding.name.as_str()).is_some() {
+                        self.errors.push(EvalException::new(
+                            ScopeError::ShadowsBuiltin(binding.name.as_str().to_owned()).into(),
+                            span,
+                            &self.codemap,
+                        ));
+                    }
+                }
+            }
+        }
+        for binding_id in self.module_bindings.values() {
+            let binding = self.scope_data.get_binding(*binding_id);
+            if let BindingSource::Source(span) = binding.source {
+                if binding.vis == Visibility::Private && !self.used_bindings.contains(binding_id) {
+                    self.errors.push(EvalException::new(
+                        ScopeError::UnusedPrivateTopLevel(binding.name.as_str().to_owned()).into(),
+                        span,
+                        &self.codemap,
+                    ));
+                }
+            }
+        }
+    }
+
     fn resolve_idents_in_compr(
         &mut self,
         exprs: &mut [&mut CstExpr],
@@ -45,6 +45,7 @@ use crate::eval::compiler::expr::ExprLogicalBinOp;
 use crate::eval::compiler::expr_bool::ExprCompiledBool;
 use crate::eval::compiler::known::list_to_tuple;
 use crate::eval::compiler::opt_ctx::OptCtx;
+use crate::eval::compiler::opt_level::Optimizations;
 use crate::eval::compiler::scope::payload::CstAssignTarget;
 use crate::eval::compiler::scope::payload::CstExpr;
 use crate::eval::compiler::scope::payload::CstStmt;
@@ -57,6 +58,7 @@ use crate::eval::runtime::evaluator::Evaluator;
 use crate::eval::runtime::evaluator::GC_THRESHOLD;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::frozen_file_span::FrozenFileSpan;
+use crate::eval::runtime::runtime_lints::RuntimeLints;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
 use crate::values::dict::Dict;
@@ -106,6 +108,10 @@ pub(crate) enum StmtCompiled {
 pub(crate) struct StmtCompileContext {
     /// Current function has return type.
     pub(crate) has_return_type: bool,
+    /// Emit instructions for [`Evaluator::set_runtime_lints`](crate::eval::Evaluator::set_runtime_lints).
+    pub(crate) runtime_lints: RuntimeLints,
+    /// Set with [`Evaluator::set_opt_level`](crate::eval::Evaluator::set_opt_level).
+    pub(crate) optimizations: Optimizations,
 }
 
 pub(crate) struct OptimizeOnFreezeContext<'v, 'a> {
@@ -631,7 +637,11 @@ pub(crate) fn add_assign<'v>(
 
 impl Compiler<'_, '_, '_, '_> {
     pub(crate) fn compile_context(&self, has_return_type: bool) -> StmtCompileContext {
-        StmtCompileContext { has_return_type }
+        StmtCompileContext {
+            has_return_type,
+            runtime_lints: self.eval.runtime_lints,
+            optimizations: Optimizations::new(self.eval.opt_level),
+        }
     }
 
     pub(crate) fn stmt(&mut self, stmt: &CstStmt, allow_gc: bool) -> StmtsCompiled {
@@ -16,6 +16,7 @@
  */
 
 pub(crate) mod arguments;
+pub(crate) mod async_executor;
 pub(crate) mod before_stmt;
 pub(crate) mod cheap_call_stack;
 pub(crate) mod evaluator;
@@ -23,8 +24,13 @@ pub(crate) mod file_loader;
 pub(crate) mod frame_span;
 pub(crate) mod frozen_file_span;
 pub(crate) mod inlined_frame;
+pub(crate) mod mutation_audit;
+pub(crate) mod native_call_interceptor;
 pub(crate) mod params;
 pub(crate) mod profile;
+pub(crate) mod progress;
+pub(crate) mod recorded_call;
+pub(crate) mod runtime_lints;
 pub(crate) mod rust_loc;
 pub(crate) mod slots;
 pub(crate) mod small_duration;
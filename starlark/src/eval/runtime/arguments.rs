@@ -31,8 +31,10 @@ use crate::collections::symbol::symbol::Symbol;
 use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::collections::StarlarkHashValue;
+use crate::eval::Evaluator;
 use crate::eval::ParametersSpec;
 use crate::hint::unlikely;
+use crate::message::MessageTemplate;
 use crate::values::dict::Dict;
 use crate::values::dict::DictRef;
 use crate::values::iter::StarlarkIterator;
@@ -47,13 +49,20 @@ use crate::values::ValueLike;
 pub(crate) enum FunctionError {
     #[error("Missing parameter `{name}` for call to {function}")]
     MissingParameter { name: String, function: String },
+    #[error(
+        "Missing parameter `{name}` for call to {function}, \
+        it is named-only and must be passed as `{name}=...`"
+    )]
+    MissingNamedOnlyParameter { name: String, function: String },
     #[error("Found {count} extra positional argument(s) for call to {function}")]
     ExtraPositionalArg { count: usize, function: String },
-    #[error("Found `{}` extra named parameter(s) for call to {function}", .names.join("` `"))]
+    #[error("Found {} extra named parameter(s) for call to {function}", format_extra_names(.names))]
     ExtraNamedArg {
         names: Vec<String>,
         function: String,
     },
+    #[error("Positional-only parameter `{name}` passed by name for call to {function}")]
+    PositionalOnlyPassedByName { name: String, function: String },
     #[error("Argument `{name}` occurs more than once")]
     RepeatedArg { name: String },
     #[error("The argument provided for *args is not an identifier")]
@@ -65,11 +74,78 @@ pub(crate) enum FunctionError {
     #[error("Wrong number of positional arguments, expected {}, got {got}",
         if min == max {min.to_string()} else {format!("between {} and {}", min, max)})]
     WrongNumberOfArgs { min: usize, max: usize, got: usize },
+    #[error("Too many positional arguments: {count}, the limit is {max}")]
+    TooManyPositionalArgs { count: usize, max: usize },
+    #[error("Too many named arguments: {count}, the limit is {max}")]
+    TooManyNamedArgs { count: usize, max: usize },
+}
+
+/// Join names of extra named arguments, listing only the first few,
+/// so generated code passing thousands of them does not produce enormous errors.
+fn format_extra_names(names: &[String]) -> String {
+    const MAX_LISTED: usize = 10;
+    let listed = names
+        .iter()
+        .take(MAX_LISTED)
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if names.len() <= MAX_LISTED {
+        listed
+    } else {
+        format!("{} and {} more", listed, names.len() - MAX_LISTED)
+    }
+}
+
+impl FunctionError {
+    /// The message with the fields as arguments, in the order they are declared.
+    fn message_template(&self) -> MessageTemplate {
+        let (code, args) = match self {
+            FunctionError::MissingParameter { name, function } => {
+                ("missing-parameter", vec![name.clone(), function.clone()])
+            }
+            FunctionError::MissingNamedOnlyParameter { name, function } => (
+                "missing-named-only-parameter",
+                vec![name.clone(), function.clone()],
+            ),
+            FunctionError::ExtraPositionalArg { count, function } => (
+                "extra-positional-arg",
+                vec![count.to_string(), function.clone()],
+            ),
+            FunctionError::ExtraNamedArg { names, function } => (
+                "extra-named-arg",
+                vec![format_extra_names(names), function.clone()],
+            ),
+            FunctionError::PositionalOnlyPassedByName { name, function } => (
+                "positional-only-passed-by-name",
+                vec![name.clone(), function.clone()],
+            ),
+            FunctionError::RepeatedArg { name } => ("repeated-arg", vec![name.clone()]),
+            FunctionError::ArgsValueIsNotString => ("args-value-is-not-string", Vec::new()),
+            FunctionError::ArgsArrayIsNotIterable => ("args-array-is-not-iterable", Vec::new()),
+            FunctionError::KwArgsIsNotDict => ("kwargs-is-not-dict", Vec::new()),
+            FunctionError::WrongNumberOfArgs { min, max, got } => (
+                "wrong-number-of-args",
+                vec![min.to_string(), max.to_string(), got.to_string()],
+            ),
+            FunctionError::TooManyPositionalArgs { count, max } => (
+                "too-many-positional-args",
+                vec![count.to_string(), max.to_string()],
+            ),
+            FunctionError::TooManyNamedArgs { count, max } => (
+                "too-many-named-args",
+                vec![count.to_string(), max.to_string()],
+            ),
+        };
+        MessageTemplate::new(code, args)
+    }
 }
 
 impl From<FunctionError> for crate::Error {
     fn from(e: FunctionError) -> Self {
+        let message_template = e.message_template();
         crate::Error::new(crate::ErrorKind::Function(anyhow::Error::new(e)))
+            .with_message_template(message_template)
     }
 }
 
@@ -167,6 +243,62 @@ pub(crate) trait ArgumentsImpl<'v, 'a>: Debug {
     fn names(&self) -> ArgNames<'a, 'v, Self::ArgSymbol>;
     fn args(&self) -> Option<Value<'v>>;
     fn kwargs(&self) -> Option<Value<'v>>;
+
+    /// Check the number of arguments, including those passed with `*args` and `**kwargs`,
+    /// does not exceed the given limits.
+    ///
+    /// Malformed `*args` or `**kwargs` are not counted here: they are reported when the
+    /// arguments are collected.
+    fn check_limits(&self, max_pos: Option<usize>, max_named: Option<usize>) -> crate::Result<()> {
+        if let Some(max) = max_pos {
+            let star = match self.args() {
+                Some(args) => args.length().map_or(0, |n| n as usize),
+                None => 0,
+            };
+            let count = self.pos().len() + star;
+            if count > max {
+                return Err(FunctionError::TooManyPositionalArgs { count, max }.into());
+            }
+        }
+        if let Some(max) = max_named {
+            let star = match self.kwargs() {
+                Some(kwargs) => DictRef::from_value(kwargs).map_or(0, |d| d.len()),
+                None => 0,
+            };
+            let count = self.named().len() + star;
+            if count > max {
+                return Err(FunctionError::TooManyNamedArgs { count, max }.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// All positional and named arguments, with `*args` and `**kwargs` expanded.
+    fn flatten(&self, heap: &'v Heap) -> crate::Result<(Vec<Value<'v>>, Vec<(String, Value<'v>)>)>
+    where
+        'v: 'a,
+    {
+        let mut pos = self.pos().to_vec();
+        if let Some(args) = self.args() {
+            pos.extend(args.iterate(heap)?);
+        }
+        let mut named: Vec<(String, Value<'v>)> = self
+            .names()
+            .iter()
+            .zip(self.named())
+            .map(|((_, name), value)| (name.as_str().to_owned(), *value))
+            .collect();
+        if let Some(kwargs) = self.kwargs() {
+            let kwargs = match DictRef::from_value(kwargs) {
+                Some(kwargs) => kwargs,
+                None => return Err(FunctionError::KwArgsIsNotDict.into()),
+            };
+            for (k, v) in kwargs.iter() {
+                named.push((Arguments::unpack_kwargs_key(k)?.to_owned(), v));
+            }
+        }
+        Ok((pos, named))
+    }
 }
 
 /// Arguments object is passed from the starlark interpreter to function implementation
@@ -396,6 +528,34 @@ impl<'v, 'a> Arguments<'v, 'a> {
         Ok(())
     }
 
+    /// Like [`no_named_args`](Arguments::no_named_args), for a function
+    /// with given positional-only parameters, to report which parameter was passed by name.
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn no_named_args_for(&self, function: &str, params: &[&str]) -> crate::Result<()> {
+        #[cold]
+        #[inline(never)]
+        fn bad(x: &Arguments, function: &str, params: &[&str]) -> crate::Result<()> {
+            for name in x.0.names.iter().map(|x| x.0.as_str()) {
+                if params.contains(&name) {
+                    return Err(crate::Error::from(
+                        FunctionError::PositionalOnlyPassedByName {
+                            name: name.to_owned(),
+                            function: function.to_owned(),
+                        },
+                    ));
+                }
+            }
+            x.no_named_args()
+        }
+
+        if self.0.named.is_empty() && self.0.kwargs.is_none() {
+            Ok(())
+        } else {
+            bad(self, function, params)
+        }
+    }
+
     /// Produce [`Err`] if there are any named (i.e. non-positional) arguments.
     #[inline(always)]
     pub fn no_named_args(&self) -> crate::Result<()> {
@@ -564,6 +724,55 @@ impl Arguments<'_, '_> {
             Some(x) => Ok(Some(T::unpack_named_param(x, name)?)),
         }
     }
+
+    /// Like [`check_required`](Arguments::check_required), but if the value cannot be unpacked,
+    /// try coercions registered with
+    /// [`GlobalsBuilder::coercion`](crate::environment::GlobalsBuilder::coercion).
+    pub fn check_required_coerce<'v, T: UnpackValue<'v>>(
+        name: &str,
+        x: Option<Value<'v>>,
+        eval: &Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<T> {
+        let x = x.ok_or_else(|| ValueError::MissingRequired(name.to_owned()))?;
+        Self::unpack_named_param_coerce(x, name, eval)
+    }
+
+    /// Like [`check_optional`](Arguments::check_optional), but if the value cannot be unpacked,
+    /// try coercions registered with
+    /// [`GlobalsBuilder::coercion`](crate::environment::GlobalsBuilder::coercion).
+    pub fn check_optional_coerce<'v, T: UnpackValue<'v>>(
+        name: &str,
+        x: Option<Value<'v>>,
+        eval: &Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<Option<T>> {
+        match x {
+            None => Ok(None),
+            Some(x) => Ok(Some(Self::unpack_named_param_coerce(x, name, eval)?)),
+        }
+    }
+
+    #[inline]
+    fn unpack_named_param_coerce<'v, T: UnpackValue<'v>>(
+        x: Value<'v>,
+        name: &str,
+        eval: &Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<T> {
+        match T::unpack_value(x) {
+            Ok(Some(v)) => return Ok(v),
+            Ok(None) => {}
+            // Unpack again to produce the error with parameter name.
+            Err(_) => return T::unpack_named_param(x, name),
+        }
+        for coerced in eval.module_def_info.globals.coerce(x, eval.heap()) {
+            match T::unpack_value(coerced) {
+                Ok(Some(v)) => return Ok(v),
+                Ok(None) => {}
+                Err(_) => return T::unpack_named_param(coerced, name),
+            }
+        }
+        // Report the error for the original value.
+        T::unpack_named_param(x, name)
+    }
 }
 
 impl<'a> Arguments<'static, 'a> {
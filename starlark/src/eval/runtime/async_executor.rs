@@ -0,0 +1,82 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Running futures of `async` native functions.
+
+use std::future::Future;
+use std::pin::pin;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Wake;
+use std::task::Waker;
+use std::thread;
+use std::thread::Thread;
+
+/// Runs the futures returned by `async fn` native functions
+/// defined with [`#[starlark_module]`](macro@crate::starlark_module),
+/// installed with [`Evaluator::set_async_executor`](crate::eval::Evaluator::set_async_executor).
+///
+/// Evaluation is synchronous: when an `async` native function is called,
+/// the evaluator is suspended until the executor has driven the future to completion.
+/// Embedders whose native functions do I/O with an async runtime
+/// should install an executor which runs the future on that runtime.
+///
+/// If no executor is installed, the future is polled on the current thread,
+/// which is parked while the future is pending.
+/// That is enough for futures which are woken by other threads,
+/// but not for futures which need a runtime on the current thread.
+pub trait AsyncExecutor {
+    /// Poll `future` until it completes.
+    fn block_on(&self, future: Pin<&mut (dyn Future<Output = ()> + '_)>);
+}
+
+impl<'a> dyn AsyncExecutor + 'a {
+    /// Run a future to completion with this executor, and return its output.
+    pub fn run<R>(&self, future: impl Future<Output = R>) -> R {
+        let mut output = None;
+        {
+            let future = pin!(async {
+                output = Some(future.await);
+            });
+            self.block_on(future);
+        }
+        output.expect("async executor returned before the future completed")
+    }
+}
+
+/// Executor used when none is installed: poll on the current thread,
+/// and park the thread until the future is woken.
+pub(crate) struct ThreadParkAsyncExecutor;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+impl AsyncExecutor for ThreadParkAsyncExecutor {
+    fn block_on(&self, mut future: Pin<&mut (dyn Future<Output = ()> + '_)>) {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        while future.as_mut().poll(&mut context).is_pending() {
+            thread::park();
+        }
+    }
+}
@@ -36,7 +36,7 @@ pub(crate) struct BeforeStmt<'a, 'e: 'a> {
 #[doc(hidden)]
 pub enum BeforeStmtFunc<'a, 'e: 'a> {
     Fn(&'a dyn for<'v1> Fn(FileSpanRef, &mut Evaluator<'v1, 'a, 'e>)),
-    Dyn(Box<dyn BeforeStmtFuncDyn<'a, 'e>>),
+    Dyn(Box<dyn BeforeStmtFuncDyn<'a, 'e> + 'a>),
 }
 
 impl<'a, 'e: 'a> BeforeStmtFunc<'a, 'e> {
@@ -83,8 +83,8 @@ impl<'a, 'e: 'a> From<&'a dyn for<'v1> Fn(FileSpanRef, &mut Evaluator<'v1, 'a, '
     }
 }
 
-impl<'a, 'e: 'a> From<Box<dyn BeforeStmtFuncDyn<'a, 'e>>> for BeforeStmtFunc<'a, 'e> {
-    fn from(value: Box<dyn BeforeStmtFuncDyn<'a, 'e>>) -> Self {
+impl<'a, 'e: 'a> From<Box<dyn BeforeStmtFuncDyn<'a, 'e> + 'a>> for BeforeStmtFunc<'a, 'e> {
+    fn from(value: Box<dyn BeforeStmtFuncDyn<'a, 'e> + 'a>) -> Self {
         Self::Dyn(value)
     }
 }
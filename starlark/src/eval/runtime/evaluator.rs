@@ -19,11 +19,13 @@ use std::collections::HashSet;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::path::Path;
+use std::time::Instant;
 
 use dupe::Dupe;
 use starlark_syntax::eval_exception::EvalException;
 use starlark_syntax::frame::Frame;
 use starlark_syntax::internal_error;
+use starlark_syntax::slice_vec_ext::VecExt;
 use thiserror::Error;
 
 use crate::any::AnyLifetime;
@@ -36,6 +38,7 @@ use crate::collections::string_pool::StringPool;
 use crate::const_frozen_string;
 use crate::environment::slots::ModuleSlotId;
 use crate::environment::FrozenModuleData;
+use crate::environment::Globals;
 use crate::environment::Module;
 use crate::eval::bc::addr::BcPtrAddr;
 use crate::eval::bc::bytecode::Bc;
@@ -46,11 +49,19 @@ use crate::eval::compiler::def::CopySlotFromParent;
 use crate::eval::compiler::def::Def;
 use crate::eval::compiler::def::DefInfo;
 use crate::eval::compiler::def::FrozenDef;
+use crate::eval::compiler::opt_level::OptLevel;
+use crate::eval::runtime::arguments::Arguments;
+use crate::eval::runtime::arguments::ArgumentsImpl;
+use crate::eval::runtime::async_executor::AsyncExecutor;
+use crate::eval::runtime::async_executor::ThreadParkAsyncExecutor;
 use crate::eval::runtime::before_stmt::BeforeStmt;
 use crate::eval::runtime::before_stmt::BeforeStmtFunc;
 use crate::eval::runtime::cheap_call_stack::CheapCallStack;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrames;
+use crate::eval::runtime::mutation_audit::ModuleMutation;
+use crate::eval::runtime::mutation_audit::MutationAudit;
+use crate::eval::runtime::native_call_interceptor::NativeCallInterceptor;
 use crate::eval::runtime::profile::bc::BcProfile;
 use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::profile::heap::HeapProfile;
@@ -61,6 +72,11 @@ use crate::eval::runtime::profile::or_instrumentation::ProfileOrInstrumentationM
 use crate::eval::runtime::profile::stmt::StmtProfile;
 use crate::eval::runtime::profile::time_flame::TimeFlameProfile;
 use crate::eval::runtime::profile::typecheck::TypecheckProfile;
+use crate::eval::runtime::progress::ProgressHandler;
+use crate::eval::runtime::progress::ProgressReporter;
+use crate::eval::runtime::recorded_call::ArgsSnapshot;
+use crate::eval::runtime::recorded_call::RecordedCall;
+use crate::eval::runtime::runtime_lints::RuntimeLints;
 use crate::eval::runtime::rust_loc::rust_loc;
 use crate::eval::runtime::slots::LocalCapturedSlotId;
 use crate::eval::runtime::slots::LocalSlotId;
@@ -68,10 +84,13 @@ use crate::eval::soft_error::HardErrorSoftErrorHandler;
 use crate::eval::CallStack;
 use crate::eval::FileLoader;
 use crate::eval::SoftErrorHandler;
+use crate::stdlib::artifacts::ArtifactRegistry;
 use crate::stdlib::breakpoint::BreakpointConsole;
 use crate::stdlib::breakpoint::RealBreakpointConsole;
 use crate::stdlib::extra::PrintHandler;
 use crate::stdlib::extra::StderrPrintHandler;
+use crate::values::function::add_native_signature;
+use crate::values::function::NativeCallableRawDocs;
 use crate::values::function::NativeFunction;
 use crate::values::layout::value_captured::value_captured_get;
 use crate::values::layout::value_captured::FrozenValueCaptured;
@@ -104,6 +123,16 @@ enum EvaluatorError {
     CallstackSizeAlreadySet,
     #[error("Max callstack size cannot be zero")]
     ZeroCallstackSize,
+    #[error("Mutation audit cannot be combined with bytecode profiling")]
+    MutationAuditWithBytecodeProfile,
+    #[error("Mutation audit not enabled")]
+    MutationAuditNotEnabled,
+    #[error("Progress handler cannot be combined with bytecode profiling")]
+    ProgressWithBytecodeProfile,
+    #[error("Evaluation exceeded the limit of {0} steps")]
+    TooManySteps(u64),
+    #[error("Evaluation limits cannot be combined with heap or flame profiling")]
+    LimitsWithProfile,
 }
 
 /// Number of bytes to allocate between GC's.
@@ -140,6 +169,12 @@ pub struct Evaluator<'v, 'a, 'e> {
     pub(crate) profile_or_instrumentation_mode: ProfileOrInstrumentationMode,
     // Used for line profiling
     stmt_profile: StmtProfile,
+    // Records mutations of module-level collections.
+    mutation_audit: MutationAudit,
+    // Runtime checks compiled into the bytecode.
+    pub(crate) runtime_lints: RuntimeLints,
+    // Optimizations done by the compiler.
+    pub(crate) opt_level: Option<OptLevel>,
     // Holds things that require hooking into evaluation.
     eval_instrumentation: EvaluationInstrumentation<'a, 'e>,
     // Total time spent in runtime typechecking.
@@ -157,10 +192,22 @@ pub struct Evaluator<'v, 'a, 'e> {
         Option<Box<dyn Fn() -> anyhow::Result<Box<dyn BreakpointConsole>>>>,
     /// Use in implementation of `print` function.
     pub(crate) print_handler: &'a (dyn PrintHandler + 'a),
+    /// Outputs declared with `declare_output`.
+    pub(crate) artifact_registry: Option<&'a ArtifactRegistry>,
     /// Deprecation handler.
     pub(crate) soft_error_handler: &'a (dyn SoftErrorHandler + 'a),
+    /// Hooks around native calls.
+    native_call_interceptor: Option<&'a (dyn NativeCallInterceptor + 'a)>,
+    /// Runs futures of `async` native functions.
+    async_executor: &'a (dyn AsyncExecutor + 'a),
     /// Max size of starlark stack
     pub(crate) max_callstack_size: Option<usize>,
+    /// Max number of positional arguments to a call, including `*args`.
+    max_call_args: Option<usize>,
+    /// Max number of named arguments to a call, including `**kwargs`.
+    max_call_kwargs: Option<usize>,
+    /// Record calls made from the module top level.
+    record_calls: bool,
     // The Starlark-level call-stack of functions.
     // Must go last because it's quite a big structure
     pub(crate) call_stack: CheapCallStack<'v>,
@@ -173,16 +220,54 @@ struct EvaluationInstrumentation<'a, 'e: 'a> {
     // Extra functions to run on each statement, usually empty
     before_stmt: BeforeStmt<'a, 'e>,
     heap_or_flame_profile: bool,
+    // Limits checked before every instruction, usually disabled.
+    limits: InstrLimits,
     // Whether we need to instrument evaluation or not, should be set if before_stmt or bc_profile are enabled.
     enabled: bool,
 }
 
+/// Limits set with [`Evaluator::set_max_steps`] and [`Evaluator::set_max_heap_bytes`].
+#[derive(Default)]
+struct InstrLimits {
+    /// Maximum number of instructions to execute.
+    max_steps: Option<u64>,
+    /// Number of instructions executed.
+    steps: u64,
+    /// Whether the heap allocation limits are checked.
+    heap: bool,
+}
+
+impl InstrLimits {
+    fn enabled(&self) -> bool {
+        self.max_steps.is_some() || self.heap
+    }
+
+    #[inline(always)]
+    fn before_instr(&mut self, heap: &Heap) -> crate::Result<()> {
+        if let Some(max_steps) = self.max_steps {
+            self.steps += 1;
+            if self.steps > max_steps {
+                return Err(crate::Error::new_other(EvaluatorError::TooManySteps(
+                    max_steps,
+                )));
+            }
+        }
+        if self.heap {
+            if let Some(e) = heap.allocation_error() {
+                return Err(crate::Error::new_other(e));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<'a, 'e: 'a> EvaluationInstrumentation<'a, 'e> {
     fn new() -> EvaluationInstrumentation<'a, 'e> {
         Self {
             bc_profile: BcProfile::new(),
             before_stmt: BeforeStmt::default(),
             heap_or_flame_profile: false,
+            limits: InstrLimits::default(),
             enabled: false,
         }
     }
@@ -193,8 +278,10 @@ impl<'a, 'e: 'a> EvaluationInstrumentation<'a, 'e> {
 
     fn change<F: FnOnce(&mut EvaluationInstrumentation<'a, 'e>) -> R, R>(&mut self, f: F) -> R {
         let r = f(self);
-        self.enabled =
-            self.bc_profile.enabled() || self.before_stmt.enabled() || self.heap_or_flame_profile;
+        self.enabled = self.bc_profile.enabled()
+            || self.before_stmt.enabled()
+            || self.heap_or_flame_profile
+            || self.limits.enabled();
         r
     }
 }
@@ -223,6 +310,9 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             profile_or_instrumentation_mode: ProfileOrInstrumentationMode::None,
             heap_profile: HeapProfile::new(),
             stmt_profile: StmtProfile::new(),
+            mutation_audit: MutationAudit::default(),
+            runtime_lints: RuntimeLints::default(),
+            opt_level: None,
             typecheck_profile: TypecheckProfile::default(),
             time_flame_profile: TimeFlameProfile::new(),
             eval_instrumentation: EvaluationInstrumentation::new(),
@@ -230,10 +320,16 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             string_pool: StringPool::default(),
             breakpoint_handler: None,
             print_handler: &StderrPrintHandler,
+            artifact_registry: None,
             soft_error_handler: &HardErrorSoftErrorHandler,
+            native_call_interceptor: None,
+            async_executor: &ThreadParkAsyncExecutor,
             verbose_gc: false,
             static_typechecking: false,
             max_callstack_size: None,
+            max_call_args: None,
+            max_call_kwargs: None,
+            record_calls: false,
         }
     }
 
@@ -260,6 +356,20 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.static_typechecking = enable;
     }
 
+    /// Enable runtime checks for likely bugs, see [`RuntimeLints`].
+    ///
+    /// Must be called before evaluation starts.
+    pub fn set_runtime_lints(&mut self, checks: RuntimeLints) {
+        self.runtime_lints = checks;
+    }
+
+    /// Set how much the compiler optimizes the code, see [`OptLevel`].
+    ///
+    /// Must be called before evaluation starts.
+    pub fn set_opt_level(&mut self, level: OptLevel) {
+        self.opt_level = Some(level);
+    }
+
     /// Set the [`FileLoader`] used to resolve `load()` statements.
     /// A list of all load statements can be obtained through
     /// [`AstModule::loads`](crate::syntax::AstModule::loads).
@@ -275,6 +385,19 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             return Err(EvaluatorError::ProfileOrInstrumentationAlreadyEnabled.into());
         }
 
+        if self.eval_instrumentation.limits.enabled()
+            && matches!(
+                mode,
+                ProfileMode::HeapSummaryAllocated
+                    | ProfileMode::HeapFlameAllocated
+                    | ProfileMode::HeapSummaryRetained
+                    | ProfileMode::HeapFlameRetained
+                    | ProfileMode::TimeFlame
+            )
+        {
+            return Err(EvaluatorError::LimitsWithProfile.into());
+        }
+
         self.profile_or_instrumentation_mode = ProfileOrInstrumentationMode::Profile(mode.dupe());
 
         match mode {
@@ -367,6 +490,42 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         }
     }
 
+    /// Record which statements mutate collections (lists, dicts, sets, and collections nested
+    /// in them or in tuples, structs and records) stored in module variables.
+    ///
+    /// Must be called before evaluation starts. Mutations are detected by comparing
+    /// collection contents before each statement, and are attributed to the statement
+    /// which started executing last, so a mutation made by a caller after a callee
+    /// returns is attributed to the last statement of the callee.
+    /// Collections nested more than 8 levels deep, and values of other types
+    /// (for example, custom mutable values), are not inspected.
+    pub fn enable_mutation_audit(&mut self) -> crate::Result<()> {
+        if self.eval_instrumentation.bc_profile.enabled() {
+            return Err(crate::Error::new_other(
+                EvaluatorError::MutationAuditWithBytecodeProfile,
+            ));
+        }
+        if !self.mutation_audit.enabled() {
+            self.mutation_audit.enable();
+            self.before_stmt_fn(&|span, eval| {
+                eval.mutation_audit.before_stmt(span, eval.module_env)
+            });
+        }
+        Ok(())
+    }
+
+    /// Mutations of module-level collections recorded so far, in execution order.
+    ///
+    /// Works if [`enable_mutation_audit`](Evaluator::enable_mutation_audit) was called.
+    pub fn module_mutations(&mut self) -> crate::Result<Vec<ModuleMutation>> {
+        match self.mutation_audit.mutations(self.module_env) {
+            Some(mutations) => Ok(mutations),
+            None => Err(crate::Error::new_other(
+                EvaluatorError::MutationAuditNotEnabled,
+            )),
+        }
+    }
+
     /// Get code coverage.
     ///
     /// Works if statement profile is enabled.
@@ -383,6 +542,74 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         }
     }
 
+    /// Call `handler` every `every` executed statements with the current location
+    /// and counters, for example to render progress or to detect stuck scripts.
+    /// Errors returned by the handler stop the evaluation.
+    ///
+    /// Must be called before evaluation starts. Statements are counted
+    /// across all evaluations done with this evaluator. Zero `every` is treated as one.
+    pub fn set_progress_handler(
+        &mut self,
+        handler: &'a (dyn ProgressHandler + 'a),
+        every: u64,
+    ) -> crate::Result<()> {
+        if self.eval_instrumentation.bc_profile.enabled() {
+            return Err(crate::Error::new_other(
+                EvaluatorError::ProgressWithBytecodeProfile,
+            ));
+        }
+        self.before_stmt(BeforeStmtFunc::Dyn(Box::new(ProgressReporter::new(
+            handler, every,
+        ))));
+        Ok(())
+    }
+
+    /// Fail the evaluation once more than `allocations` values have been allocated
+    /// on the heap after this call, to test how the caller handles running out of memory.
+    ///
+    /// Allocations are counted by the heap, and the error is returned before the next
+    /// instruction is executed, or earlier by operations like `[0] * n`. For the same code
+    /// and the same `allocations`, the evaluation always fails at the same point.
+    /// Cannot be combined with heap or flame profiling.
+    pub fn fail_allocations_after(&mut self, allocations: usize) -> crate::Result<()> {
+        if self.eval_instrumentation.heap_or_flame_profile {
+            return Err(crate::Error::new_other(EvaluatorError::LimitsWithProfile));
+        }
+        self.module_env.heap().fail_allocations_after(allocations);
+        self.eval_instrumentation.change(|v| v.limits.heap = true);
+        Ok(())
+    }
+
+    /// Fail the evaluation after executing more than `steps` bytecode instructions,
+    /// including instructions of functions called from native code.
+    ///
+    /// Steps are counted across all evaluations done with this evaluator.
+    /// Cannot be combined with heap or flame profiling.
+    pub fn set_max_steps(&mut self, steps: u64) -> crate::Result<()> {
+        if self.eval_instrumentation.heap_or_flame_profile {
+            return Err(crate::Error::new_other(EvaluatorError::LimitsWithProfile));
+        }
+        self.eval_instrumentation
+            .change(|v| v.limits.max_steps = Some(steps));
+        Ok(())
+    }
+
+    /// Fail the evaluation once more than `bytes` are allocated on the heap of the module.
+    ///
+    /// The limit is checked on every allocation and before every instruction,
+    /// and operations like `[0] * n` fail before allocating more than the limit.
+    /// Memory allocated outside of the heap (for example, by native functions
+    /// for temporary values) is not counted.
+    /// Cannot be combined with heap or flame profiling.
+    pub fn set_max_heap_bytes(&mut self, bytes: usize) -> crate::Result<()> {
+        if self.eval_instrumentation.heap_or_flame_profile {
+            return Err(crate::Error::new_other(EvaluatorError::LimitsWithProfile));
+        }
+        self.module_env.heap().set_allocation_limit(Some(bytes));
+        self.eval_instrumentation.change(|v| v.limits.heap = true);
+        Ok(())
+    }
+
     /// Enable interactive `breakpoint()`. When enabled, `breakpoint()`
     /// reads commands from stdin and write to stdout.
     /// When disabled (default), `breakpoint()` function results in error.
@@ -437,6 +664,61 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.print_handler = handler;
     }
 
+    /// Enable `declare_output` and `write_output` functions,
+    /// which record the outputs in the given registry.
+    pub fn set_artifact_registry(&mut self, registry: &'a ArtifactRegistry) {
+        self.artifact_registry = Some(registry);
+    }
+
+    /// Set hooks to be called around every native function and method call.
+    pub fn set_native_call_interceptor(
+        &mut self,
+        interceptor: &'a (dyn NativeCallInterceptor + 'a),
+    ) {
+        self.native_call_interceptor = Some(interceptor);
+    }
+
+    /// Set the executor which runs the futures of `async` native functions.
+    /// If not set, futures are polled on the current thread.
+    pub fn set_async_executor(&mut self, executor: &'a (dyn AsyncExecutor + 'a)) {
+        self.async_executor = executor;
+    }
+
+    /// Executor which runs the futures of `async` native functions.
+    pub fn async_executor(&self) -> &'a (dyn AsyncExecutor + 'a) {
+        self.async_executor
+    }
+
+    /// Invoke a native function or method named `name`,
+    /// calling the native call interceptor if one is installed.
+    /// Errors in the arguments are reported with the signature from `raw_docs`.
+    #[inline(always)]
+    pub(crate) fn invoke_native(
+        &mut self,
+        name: &str,
+        this: Option<Value<'v>>,
+        args: &Arguments<'v, '_>,
+        raw_docs: Option<&NativeCallableRawDocs>,
+        invoke: impl FnOnce(&mut Self) -> crate::Result<Value<'v>>,
+    ) -> crate::Result<Value<'v>> {
+        let r = match self.native_call_interceptor {
+            None => invoke(self),
+            Some(interceptor) => {
+                if let Some(r) = interceptor
+                    .before_call(name, this, args)
+                    .map_err(crate::Error::new_other)?
+                {
+                    return Ok(r);
+                }
+                let start = Instant::now();
+                let r = invoke(self);
+                interceptor.after_call(name, start.elapsed(), r.as_ref().copied());
+                r
+            }
+        };
+        r.map_err(|e| add_native_signature(e, name, this, raw_docs))
+    }
+
     /// Set deprecation handler. If not set, deprecations are treated as hard errors.
     pub fn set_soft_error_handler(&mut self, handler: &'a (dyn SoftErrorHandler + 'a)) {
         self.soft_error_handler = handler;
@@ -661,6 +943,19 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         }
     }
 
+    /// Globals of the innermost Starlark function on the call stack,
+    /// or of the module if called from the top level.
+    pub(crate) fn caller_globals(&self) -> FrozenRef<'static, Globals> {
+        let mut n = 0;
+        while let Some(func) = self.call_stack.top_nth_function_opt(n) {
+            if let Ok(def_info) = self.func_to_def_info(func) {
+                return def_info.globals;
+            }
+            n += 1;
+        }
+        self.module_def_info.globals
+    }
+
     pub(crate) fn top_frame_def_info(&self) -> crate::Result<FrozenRef<DefInfo>> {
         let func = self.call_stack.top_nth_function(0)?;
         self.func_to_def_info(func)
@@ -728,8 +1023,12 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.time_flame_profile
             .record_call_enter(const_frozen_string!("GC").to_value());
 
+        self.mutation_audit.before_gc(self.module_env);
+
         self.heap().garbage_collect(|tracer| self.trace(tracer));
 
+        self.mutation_audit.after_gc(self.module_env);
+
         self.time_flame_profile.record_call_exit();
 
         if self.verbose_gc {
@@ -811,6 +1110,9 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
                                 "both before_stmt and bc_profile are enabled"
                             )));
                         }
+                        (false, false) if self.eval_instrumentation.limits.enabled() => {
+                            EvalCallbacksMode::LimitsOnly
+                        }
                         (false, false) => {
                             return Err(EvalException::new_unknown_span(internal_error!(
                                 "neither before_stmt nor bc_profile are enabled"
@@ -845,6 +1147,84 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.max_callstack_size = Some(stack_size);
         Ok(())
     }
+
+    /// Limit the number of positional arguments (including those passed with `*args`)
+    /// to a single call made from Starlark code. Calls exceeding the limit fail.
+    pub fn set_max_call_args(&mut self, max: usize) {
+        self.max_call_args = Some(max);
+    }
+
+    /// Limit the number of named arguments (including those passed with `**kwargs`)
+    /// to a single call made from Starlark code. Calls exceeding the limit fail.
+    pub fn set_max_call_kwargs(&mut self, max: usize) {
+        self.max_call_kwargs = Some(max);
+    }
+
+    /// Check the arguments of a call against the limits set with
+    /// [`set_max_call_args`](Evaluator::set_max_call_args) and
+    /// [`set_max_call_kwargs`](Evaluator::set_max_call_kwargs).
+    #[inline(always)]
+    pub(crate) fn check_call_args<'b>(&self, args: &impl ArgumentsImpl<'v, 'b>) -> crate::Result<()>
+    where
+        'v: 'b,
+    {
+        if self.max_call_args.is_none() && self.max_call_kwargs.is_none() {
+            return Ok(());
+        }
+        args.check_limits(self.max_call_args, self.max_call_kwargs)
+    }
+
+    /// Record every call made directly from the top level of the module:
+    /// the function name and the fully evaluated arguments.
+    ///
+    /// Calls made from inside functions are not recorded.
+    /// Recorded calls are available from [`Module::recorded_calls`],
+    /// and after freezing, as frozen values, from
+    /// [`FrozenModule::recorded_calls`](crate::environment::FrozenModule::recorded_calls).
+    pub fn enable_call_recording(&mut self) {
+        self.record_calls = true;
+    }
+
+    /// If call recording is enabled and the call is made from the module top level,
+    /// snapshot its arguments before the call is made, to be passed to
+    /// [`record_call`](Evaluator::record_call) once the call succeeds.
+    #[inline(always)]
+    pub(crate) fn snapshot_call<'b>(
+        &self,
+        function: impl FnOnce() -> String,
+        span: FrozenRef<'static, FrameSpan>,
+        args: &impl ArgumentsImpl<'v, 'b>,
+    ) -> crate::Result<Option<RecordedCall<Value<'v>>>>
+    where
+        'v: 'b,
+    {
+        // Module evaluation pushes an empty frame, so top level calls are made with one frame.
+        if !self.record_calls || self.call_stack.count() != 1 {
+            return Ok(None);
+        }
+        let (positional, named) = args.flatten(self.heap())?;
+        let mut snapshot = ArgsSnapshot::new(self.heap());
+        let positional = positional
+            .into_try_map(|x| snapshot.copy(x))
+            .map_err(crate::Error::new_other)?;
+        let named = named
+            .into_try_map(|(name, x)| anyhow::Ok((name, snapshot.copy(x)?)))
+            .map_err(crate::Error::new_other)?;
+        Ok(Some(RecordedCall {
+            function: function(),
+            location: span.span.to_file_span(),
+            positional,
+            named,
+        }))
+    }
+
+    /// Record a successful call snapshotted with [`snapshot_call`](Evaluator::snapshot_call).
+    #[inline(always)]
+    pub(crate) fn record_call(&self, call: Option<RecordedCall<Value<'v>>>) {
+        if let Some(call) = call {
+            self.module_env.record_call(call);
+        }
+    }
 }
 
 pub(crate) trait EvaluationCallbacks {
@@ -873,6 +1253,7 @@ impl EvaluationCallbacks for EvalCallbacksDisabled {
 pub(crate) enum EvalCallbacksMode {
     BcProfile,
     BeforeStmt,
+    LimitsOnly,
 }
 
 pub(crate) struct EvalCallbacksEnabled<'a> {
@@ -899,12 +1280,16 @@ impl<'a> EvaluationCallbacks for EvalCallbacksEnabled<'a> {
         ip: BcPtrAddr,
         opcode: BcOpcode,
     ) -> crate::Result<()> {
+        eval.eval_instrumentation
+            .limits
+            .before_instr(eval.module_env.heap())?;
         match self.mode {
             EvalCallbacksMode::BcProfile => {
                 eval.eval_instrumentation.bc_profile.before_instr(opcode);
                 Ok(())
             }
             EvalCallbacksMode::BeforeStmt => self.before_stmt(eval, ip),
+            EvalCallbacksMode::LimitsOnly => Ok(()),
         }
     }
 }
@@ -18,11 +18,25 @@
 //! Define variants of the evaluation function with different support
 //! for the `load(...)` statement.
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 use dupe::Dupe;
 
 use crate::environment::FrozenModule;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[derive(Debug, thiserror::Error)]
+enum MockLoaderError {
+    #[error("MockLoader does not know the module `{0}`")]
+    UnknownModule(String),
+    #[error("Cycle in `load()` of mock modules: {0}")]
+    Cycle(String),
+}
 
 /// A trait for turning a `path` given by a `load()` statement into a [`FrozenModule`].
 pub trait FileLoader {
@@ -52,6 +66,128 @@ impl<'a> FileLoader for ReturnFileLoader<'a> {
     }
 }
 
+/// [`FileLoader`] for tests which evaluates modules from source strings
+/// instead of reading them from the file system.
+///
+/// Modules are evaluated on first `load()` with the loader's globals,
+/// may themselves load other mock modules, and are cached after that.
+///
+/// ```
+/// use starlark::environment::Globals;
+/// use starlark::environment::Module;
+/// use starlark::eval::Evaluator;
+/// use starlark::eval::MockLoader;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+///
+/// let loader = MockLoader::new().module("lib.star", "def double(x): return x * 2");
+///
+/// let ast = AstModule::parse(
+///     "main.star",
+///     "load('lib.star', 'double')\ndouble(21)".to_owned(),
+///     &Dialect::Standard,
+/// )
+/// .unwrap();
+/// let module = Module::new();
+/// let mut eval = Evaluator::new(&module);
+/// eval.set_loader(&loader);
+/// let res = eval.eval_module(ast, &Globals::standard()).unwrap();
+/// assert_eq!(42, res.unpack_i32().unwrap());
+/// ```
+pub struct MockLoader {
+    globals: Globals,
+    dialect: Dialect,
+    /// Module source and the dialect overriding the default.
+    sources: HashMap<String, (String, Option<Dialect>)>,
+    loaded: RefCell<HashMap<String, FrozenModule>>,
+    /// Modules being evaluated, to report cycles.
+    loading: RefCell<Vec<String>>,
+}
+
+impl Default for MockLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockLoader {
+    /// Loader with standard globals, [`Dialect::Extended`] and no modules.
+    pub fn new() -> MockLoader {
+        MockLoader {
+            globals: Globals::standard(),
+            dialect: Dialect::Extended,
+            sources: HashMap::new(),
+            loaded: RefCell::new(HashMap::new()),
+            loading: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Globals available to the mock modules.
+    pub fn globals(mut self, globals: Globals) -> Self {
+        self.globals = globals;
+        self
+    }
+
+    /// Dialect used to parse modules without their own dialect.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Add a module which `load(name, ...)` evaluates from `source`.
+    pub fn module(mut self, name: &str, source: &str) -> Self {
+        self.sources
+            .insert(name.to_owned(), (source.to_owned(), None));
+        self
+    }
+
+    /// Add a module parsed with the given dialect instead of the default one.
+    pub fn module_with_dialect(mut self, name: &str, source: &str, dialect: Dialect) -> Self {
+        self.sources
+            .insert(name.to_owned(), (source.to_owned(), Some(dialect)));
+        self
+    }
+
+    fn eval(&self, path: &str, source: &str, dialect: &Dialect) -> anyhow::Result<FrozenModule> {
+        let ast =
+            AstModule::parse(path, source.to_owned(), dialect).map_err(|e| e.into_anyhow())?;
+        let module = Module::new();
+        {
+            let mut eval = Evaluator::new(&module);
+            eval.set_loader(self);
+            eval.eval_module(ast, &self.globals)
+                .map_err(|e| e.into_anyhow())?;
+        }
+        module.freeze()
+    }
+}
+
+impl FileLoader for MockLoader {
+    fn load(&self, path: &str) -> anyhow::Result<FrozenModule> {
+        if let Some(module) = self.loaded.borrow().get(path) {
+            return Ok(module.dupe());
+        }
+        let Some((source, dialect)) = self.sources.get(path) else {
+            return Err(MockLoaderError::UnknownModule(path.to_owned()).into());
+        };
+        if self.loading.borrow().iter().any(|p| p == path) {
+            let mut cycle = self.loading.borrow().clone();
+            cycle.push(path.to_owned());
+            return Err(MockLoaderError::Cycle(cycle.join(" -> ")).into());
+        }
+
+        self.loading.borrow_mut().push(path.to_owned());
+        let res = self.eval(path, source, dialect.as_ref().unwrap_or(&self.dialect));
+        self.loading.borrow_mut().pop();
+
+        let module = res?;
+        self.loaded
+            .borrow_mut()
+            .insert(path.to_owned(), module.dupe());
+        Ok(module)
+    }
+}
+
 /// Same as [`ReturnFileLoader`], but does not require fighting the borrow checker.
 #[cfg(test)]
 pub(crate) struct ReturnOwnedFileLoader {
@@ -70,3 +206,43 @@ impl FileLoader for ReturnOwnedFileLoader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::runtime::file_loader::FileLoader;
+    use crate::eval::MockLoader;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_mock_loader() {
+        let loader = MockLoader::new()
+            .module("a.star", "A = 1")
+            .module("b.star", "load('a.star', 'A')\nB = A + 1");
+        let b = loader.load("b.star").unwrap();
+        assert_eq!(2, b.get("B").unwrap().value().unpack_i32().unwrap());
+        // Cached.
+        assert!(loader.load("a.star").unwrap().get("A").is_ok());
+    }
+
+    #[test]
+    fn test_mock_loader_errors() {
+        let loader = MockLoader::new()
+            .module("x.star", "load('y.star', 'Y')\nX = 1")
+            .module("y.star", "load('x.star', 'X')\nY = 1");
+        let err = loader.load("x.star").unwrap_err().to_string();
+        assert!(err.contains("x.star -> y.star -> x.star"), "{err}");
+
+        let err = loader.load("z.star").unwrap_err().to_string();
+        assert!(err.contains("does not know the module `z.star`"), "{err}");
+    }
+
+    #[test]
+    fn test_mock_loader_dialect() {
+        let loader = MockLoader::new()
+            .dialect(Dialect::Standard)
+            .module("standard.star", "x: int = 1")
+            .module_with_dialect("extended.star", "x: int = 1", Dialect::Extended);
+        assert!(loader.load("standard.star").is_err());
+        assert!(loader.load("extended.star").is_ok());
+    }
+}
@@ -0,0 +1,262 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Recording of mutations of module-level collections.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use starlark_map::StarlarkHasher;
+
+use crate::codemap::FileSpan;
+use crate::codemap::FileSpanRef;
+use crate::environment::slots::ModuleSlotId;
+use crate::environment::Module;
+use crate::values::dict::DictRef;
+use crate::values::layout::pointer::RawPointer;
+use crate::values::list::ListRef;
+use crate::values::record::Record;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::set::SetRef;
+use crate::values::Value;
+
+/// Nested collections deeper than this are not inspected.
+const MAX_DEPTH: usize = 8;
+
+/// Number of enabled audits in the process, mutations are only counted when non-zero.
+static AUDITS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Number of list, dict and set mutations made on this thread while an audit is enabled.
+    static MUTATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Count a mutation of a list, a dict or a set, so the audit rescans module
+/// collections only after statements which mutated something.
+#[inline(always)]
+pub(crate) fn note_mutation() {
+    if AUDITS.load(Ordering::Relaxed) != 0 {
+        MUTATIONS.with(|m| m.set(m.get().wrapping_add(1)));
+    }
+}
+
+fn mutation_count() -> u64 {
+    MUTATIONS.with(|m| m.get())
+}
+
+/// A mutation of a collection stored in a module variable,
+/// recorded with [`Evaluator::enable_mutation_audit`](crate::eval::Evaluator::enable_mutation_audit).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleMutation {
+    /// Name of the module variable holding the mutated collection.
+    pub name: String,
+    /// The statement which mutated the collection.
+    pub location: FileSpan,
+}
+
+/// State of a collection bound to a module variable.
+struct Snapshot {
+    /// Identity of the collection, refreshed after garbage collection.
+    identity: RawPointer,
+    /// Hash of the collection contents.
+    fingerprint: u64,
+}
+
+/// State of mutation audit.
+#[derive(Default)]
+pub(crate) struct MutationAudit(
+    // Box because when audit is not enabled, we want this to be small and cheap
+    Option<Box<MutationAuditState>>,
+);
+
+struct MutationAuditState {
+    /// Snapshot per module variable, so rebinding a variable is not a mutation.
+    snapshots: HashMap<ModuleSlotId, Snapshot>,
+    /// [`mutation_count`] at the last check.
+    mutation_count: u64,
+    /// The statement which started executing last.
+    last: Option<FileSpan>,
+    mutations: Vec<ModuleMutation>,
+}
+
+impl MutationAuditState {
+    fn new() -> Self {
+        AUDITS.fetch_add(1, Ordering::Relaxed);
+        MutationAuditState {
+            snapshots: HashMap::new(),
+            mutation_count: mutation_count(),
+            last: None,
+            mutations: Vec::new(),
+        }
+    }
+}
+
+impl Drop for MutationAuditState {
+    fn drop(&mut self) {
+        AUDITS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Hash of collection contents which does not depend on value addresses,
+/// so it survives garbage collection.
+fn fingerprint(value: Value, depth: usize, hasher: &mut StarlarkHasher) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    if let Some(list) = ListRef::from_value(value) {
+        "list".hash(hasher);
+        list.len().hash(hasher);
+        for x in list.iter() {
+            fingerprint(x, depth + 1, hasher);
+        }
+    } else if let Some(dict) = DictRef::from_value(value) {
+        "dict".hash(hasher);
+        dict.len().hash(hasher);
+        for (k, v) in dict.iter() {
+            fingerprint(k, depth + 1, hasher);
+            fingerprint(v, depth + 1, hasher);
+        }
+    } else if let Some(tuple) = TupleRef::from_value(value) {
+        "tuple".hash(hasher);
+        for x in tuple.iter() {
+            fingerprint(x, depth + 1, hasher);
+        }
+    } else if let Some(set) = SetRef::from_value(value) {
+        "set".hash(hasher);
+        set.len().hash(hasher);
+        for x in set.iter() {
+            fingerprint(*x, depth + 1, hasher);
+        }
+    } else if let Some(s) = StructRef::from_value(value) {
+        "struct".hash(hasher);
+        for (k, v) in s.iter() {
+            k.as_str().hash(hasher);
+            fingerprint(v, depth + 1, hasher);
+        }
+    } else if let Some(record) = Record::from_value(value) {
+        "record".hash(hasher);
+        for (k, v) in record.iter() {
+            k.hash(hasher);
+            fingerprint(v, depth + 1, hasher);
+        }
+    } else {
+        value.get_type().hash(hasher);
+        if let Ok(hash) = value.get_hash() {
+            hash.hash(hasher);
+        }
+    }
+}
+
+/// Whether a value is a collection the audit watches, which is mutable or may contain
+/// mutable collections.
+fn is_watched(value: Value) -> bool {
+    value.unpack_frozen().is_none()
+        && (ListRef::from_value(value).is_some()
+            || DictRef::from_value(value).is_some()
+            || TupleRef::from_value(value).is_some()
+            || SetRef::from_value(value).is_some()
+            || StructRef::from_value(value).is_some()
+            || Record::from_value(value).is_some())
+}
+
+impl MutationAudit {
+    pub(crate) fn enable(&mut self) {
+        self.0 = Some(Box::new(MutationAuditState::new()));
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Compare module collections with the last snapshot, attributing changes
+    /// to the statement which started executing last.
+    ///
+    /// Contents are only fingerprinted for rebound variables,
+    /// or for all variables if anything was mutated since the last check.
+    fn check(state: &mut MutationAuditState, module: &Module) {
+        let mutation_count = mutation_count();
+        let mutated = mutation_count != state.mutation_count;
+        state.mutation_count = mutation_count;
+        for (slot, value) in module.values_by_slot_id() {
+            if !is_watched(value) {
+                state.snapshots.remove(&slot);
+                continue;
+            }
+            let identity = value.ptr_value();
+            let old = state.snapshots.get(&slot);
+            if !mutated && old.is_some_and(|old| old.identity == identity) {
+                continue;
+            }
+            let mut hasher = StarlarkHasher::new();
+            fingerprint(value, 0, &mut hasher);
+            let snapshot = Snapshot {
+                identity,
+                fingerprint: hasher.finish(),
+            };
+            if let Some(old) = old {
+                if old.identity == snapshot.identity && old.fingerprint != snapshot.fingerprint {
+                    if let (Some(location), Some(name)) =
+                        (&state.last, module.mutable_names().get_slot(slot))
+                    {
+                        state.mutations.push(ModuleMutation {
+                            name: name.as_str().to_owned(),
+                            location: location.clone(),
+                        });
+                    }
+                }
+            }
+            state.snapshots.insert(slot, snapshot);
+        }
+    }
+
+    pub(crate) fn before_stmt(&mut self, span: FileSpanRef, module: &Module) {
+        if let Some(state) = &mut self.0 {
+            Self::check(state, module);
+            state.last = Some(span.to_file_span());
+        }
+    }
+
+    /// Garbage collection moves values, so snapshots must be up to date before it,
+    /// otherwise a variable rebound since the last check would look mutated.
+    pub(crate) fn before_gc(&mut self, module: &Module) {
+        if let Some(state) = &mut self.0 {
+            Self::check(state, module);
+        }
+    }
+
+    /// Refresh identities of values moved by garbage collection.
+    pub(crate) fn after_gc(&mut self, module: &Module) {
+        if let Some(state) = &mut self.0 {
+            for (slot, value) in module.values_by_slot_id() {
+                if let Some(snapshot) = state.snapshots.get_mut(&slot) {
+                    snapshot.identity = value.ptr_value();
+                }
+            }
+        }
+    }
+
+    pub(crate) fn mutations(&mut self, module: &Module) -> Option<Vec<ModuleMutation>> {
+        let state = self.0.as_mut()?;
+        Self::check(state, module);
+        Some(state.mutations.clone())
+    }
+}
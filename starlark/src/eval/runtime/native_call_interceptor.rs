@@ -0,0 +1,60 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Hooks around native function and method calls.
+
+use std::time::Duration;
+
+use crate::eval::Arguments;
+use crate::values::Value;
+
+/// Hooks called around every call of a native function or method
+/// (defined with [`#[starlark_module]`](macro@crate::starlark_module) or otherwise),
+/// installed with [`Evaluator::set_native_call_interceptor`](crate::eval::Evaluator::set_native_call_interceptor).
+///
+/// Calls of functions defined in Starlark are not intercepted. Neither are calls which
+/// the compiler replaces with dedicated instructions, such as `len(x)` or `type(x)`.
+/// Calls of pure functions with constant arguments may be evaluated during compilation,
+/// and then intercepted at that time.
+pub trait NativeCallInterceptor {
+    /// Called before the native function `name` is invoked.
+    /// `this` is the receiver when a method is called.
+    ///
+    /// Returning `Some` skips the call, and the returned value is used as the result.
+    /// Returning an error fails the call with that error.
+    fn before_call<'v>(
+        &self,
+        name: &str,
+        this: Option<Value<'v>>,
+        args: &Arguments<'v, '_>,
+    ) -> anyhow::Result<Option<Value<'v>>> {
+        let _ = (name, this, args);
+        Ok(None)
+    }
+
+    /// Called after the native function `name` returns, with the time spent in the call
+    /// and the result of the call. Not called when
+    /// [`before_call`](NativeCallInterceptor::before_call) skipped the call or failed.
+    fn after_call<'v>(
+        &self,
+        name: &str,
+        duration: Duration,
+        result: Result<Value<'v>, &crate::Error>,
+    ) {
+        let _ = (name, duration, result);
+    }
+}
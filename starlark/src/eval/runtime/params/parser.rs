@@ -77,6 +77,7 @@ mod tests {
     use crate::docs::DocStringKind;
     use crate::eval::compiler::def::FrozenDef;
     use crate::eval::runtime::params::spec::ParameterKind;
+    use crate::eval::runtime::params::spec::ParameterStyle;
     use crate::eval::runtime::params::spec::ParametersSpec;
     use crate::typing::Ty;
     use crate::values::FrozenValue;
@@ -201,6 +202,60 @@ mod tests {
         test("a, **kwargs");
     }
 
+    #[test]
+    fn test_def_parameters() {
+        let a = Assert::new();
+        let module = a.pass_module(
+            r#"
+def f(a, b = [1], *args, c, d = "x", **kwargs):
+    "Callback docs."
+    pass
+g = lambda: None
+"#,
+        );
+        let f = module.get("f").unwrap();
+        assert_eq!(Some("Callback docs."), f.def_docstring());
+        let params: Vec<_> = f
+            .def_parameters()
+            .unwrap()
+            .parameters()
+            .map(|p| {
+                (
+                    p.name,
+                    p.style,
+                    p.required,
+                    p.default.map(|d| d.to_value().to_repr()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("a", ParameterStyle::PosOrNamed, true, None),
+                (
+                    "b",
+                    ParameterStyle::PosOrNamed,
+                    false,
+                    Some("[1]".to_owned())
+                ),
+                ("args", ParameterStyle::Args, false, None),
+                ("c", ParameterStyle::NamedOnly, true, None),
+                (
+                    "d",
+                    ParameterStyle::NamedOnly,
+                    false,
+                    Some("\"x\"".to_owned())
+                ),
+                ("kwargs", ParameterStyle::Kwargs, false, None),
+            ],
+            params
+        );
+
+        let g = module.get("g").unwrap();
+        assert_eq!(0, g.def_parameters().unwrap().len());
+        assert_eq!(None, g.def_docstring());
+        assert!(FrozenValue::new_none().def_parameters().is_none());
+    }
+
     #[test]
     fn test_can_fill_with_args() {
         fn test(sig: &str, pos: usize, names: &[&str], expected: bool) {
@@ -78,6 +78,34 @@ enum CurrentParameterStyle {
     NoMore,
 }
 
+/// How a parameter can be passed, see [`ParameterInfo`].
+#[derive(Debug, Copy, Clone, Dupe, PartialEq, Eq)]
+pub enum ParameterStyle {
+    /// Parameter can only be passed by position.
+    PosOnly,
+    /// Parameter can be passed by position or by name.
+    PosOrNamed,
+    /// Parameter can only be passed by name.
+    NamedOnly,
+    /// `*args`.
+    Args,
+    /// `**kwargs`.
+    Kwargs,
+}
+
+/// Description of a function parameter, as returned by [`ParametersSpec::parameters`].
+#[derive(Debug, Copy, Clone, Dupe)]
+pub struct ParameterInfo<'a, V> {
+    /// Parameter name, without `*` or `**` for `*args` and `**kwargs`.
+    pub name: &'a str,
+    /// How the parameter can be passed.
+    pub style: ParameterStyle,
+    /// The parameter must be passed by the caller.
+    pub required: bool,
+    /// The default value, if the parameter has one.
+    pub default: Option<V>,
+}
+
 /// Builder for [`ParametersSpec`]
 pub struct ParametersSpecBuilder<V> {
     function_name: String,
@@ -322,6 +350,33 @@ impl<V> ParametersSpec<V> {
         collector
     }
 
+    /// Parameters in the order they are declared, e.g. to check a callback
+    /// accepts the expected arguments before calling it.
+    pub fn parameters(&self) -> impl Iterator<Item = ParameterInfo<'_, V>>
+    where
+        V: Copy,
+    {
+        self.iter_params().enumerate().map(|(i, (name, kind))| {
+            let style = match kind {
+                ParameterKind::Args => ParameterStyle::Args,
+                ParameterKind::KWargs => ParameterStyle::Kwargs,
+                _ if i < self.positional_only as usize => ParameterStyle::PosOnly,
+                _ if i < self.positional as usize => ParameterStyle::PosOrNamed,
+                _ => ParameterStyle::NamedOnly,
+            };
+            let default = match kind {
+                ParameterKind::Defaulted(v) => Some(*v),
+                _ => None,
+            };
+            ParameterInfo {
+                name: name.trim_start_matches('*'),
+                style,
+                required: matches!(kind, ParameterKind::Required),
+                default,
+            }
+        })
+    }
+
     /// Iterate over the parameters
     ///
     /// Returns an iterator over (name, kind)
@@ -451,18 +506,28 @@ impl<'v> ParametersSpec<Value<'v>> {
         'v: 'a,
     {
         /// Lazily initialized `kwargs` object.
-        #[derive(Default)]
         struct LazyKwargs<'v> {
             kwargs: Option<SmallMap<StringValue<'v>, Value<'v>>>,
+            /// Capacity to allocate on first insertion.
+            capacity: usize,
         }
 
         impl<'v> LazyKwargs<'v> {
+            fn new(named: usize) -> LazyKwargs<'v> {
+                // Calls with many named arguments (e.g. from generated code) would otherwise
+                // repeatedly grow the map and its index.
+                LazyKwargs {
+                    kwargs: None,
+                    capacity: cmp::max(12, named),
+                }
+            }
+
             // Return true if the value is a duplicate
             #[inline(always)]
             fn insert(&mut self, key: Hashed<StringValue<'v>>, val: Value<'v>) -> bool {
                 match &mut self.kwargs {
                     None => {
-                        let mut mp = SmallMap::with_capacity(12);
+                        let mut mp = SmallMap::with_capacity(self.capacity);
                         mp.insert_hashed_unique_unchecked(key, val);
                         self.kwargs = Some(mp);
                         false
@@ -485,7 +550,7 @@ impl<'v> ParametersSpec<Value<'v>> {
         assert!(slots.len() >= len);
 
         let mut star_args = Vec::new();
-        let mut kwargs = LazyKwargs::default();
+        let mut kwargs = LazyKwargs::new(args.names().len());
         let mut next_position = 0;
 
         // First deal with positional parameters
@@ -600,9 +665,14 @@ impl<'v> ParametersSpec<Value<'v>> {
             }
             match def {
                 ParameterKind::Required => {
-                    return Err(FunctionError::MissingParameter {
-                        name: self.param_names[index].clone(),
-                        function: self.signature(),
+                    let name = self.param_names[index].clone();
+                    let function = self.signature();
+                    // Extra positional arguments were likely meant for this parameter.
+                    let named_only = index >= (self.positional as usize) && !star_args.is_empty();
+                    return Err(if named_only {
+                        FunctionError::MissingNamedOnlyParameter { name, function }
+                    } else {
+                        FunctionError::MissingParameter { name, function }
                     }
                     .into());
                 }
@@ -629,6 +699,16 @@ impl<'v> ParametersSpec<Value<'v>> {
         if let Some(kwargs_pos) = self.kwargs {
             slots[kwargs_pos as usize].set(Some(kwargs.alloc(heap)));
         } else if let Some(kwargs) = kwargs.kwargs {
+            if let Some(name) = self.param_names[..self.positional_only as usize]
+                .iter()
+                .find(|name| kwargs.keys().any(|k| k.as_str() == name.as_str()))
+            {
+                return Err(FunctionError::PositionalOnlyPassedByName {
+                    name: name.clone(),
+                    function: self.signature(),
+                }
+                .into());
+            }
             return Err(FunctionError::ExtraNamedArg {
                 names: kwargs.keys().map(|x| x.as_str().to_owned()).collect(),
                 function: self.signature(),
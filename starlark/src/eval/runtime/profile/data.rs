@@ -148,25 +148,38 @@ impl ProfileData {
                 ));
             }
         }
-        let profile = match &profile_mode {
-            ProfileMode::Bytecode => BcProfilerType::merge_profiles(&profiles)?.profile,
-            ProfileMode::BytecodePairs => BcPairsProfilerType::merge_profiles(&profiles)?.profile,
+        Self::merge_with_mode(&profile_mode, &profiles)
+    }
+
+    /// Profile without any data, e.g. for evaluations which failed before
+    /// producing a profile.
+    pub fn empty(profile_mode: &ProfileMode) -> crate::Result<ProfileData> {
+        Self::merge_with_mode(profile_mode, &[])
+    }
+
+    fn merge_with_mode(
+        profile_mode: &ProfileMode,
+        profiles: &[&ProfileData],
+    ) -> crate::Result<ProfileData> {
+        let profile = match profile_mode {
+            ProfileMode::Bytecode => BcProfilerType::merge_profiles(profiles)?.profile,
+            ProfileMode::BytecodePairs => BcPairsProfilerType::merge_profiles(profiles)?.profile,
             ProfileMode::HeapSummaryAllocated => {
-                HeapSummaryAllocatedProfilerType::merge_profiles(&profiles)?.profile
+                HeapSummaryAllocatedProfilerType::merge_profiles(profiles)?.profile
             }
             ProfileMode::HeapSummaryRetained => {
-                HeapSummaryRetainedProfilerType::merge_profiles(&profiles)?.profile
+                HeapSummaryRetainedProfilerType::merge_profiles(profiles)?.profile
             }
             ProfileMode::HeapFlameAllocated => {
-                HeapFlameAllocatedProfilerType::merge_profiles(&profiles)?.profile
+                HeapFlameAllocatedProfilerType::merge_profiles(profiles)?.profile
             }
             ProfileMode::HeapFlameRetained => {
-                HeapFlameRetainedProfilerType::merge_profiles(&profiles)?.profile
+                HeapFlameRetainedProfilerType::merge_profiles(profiles)?.profile
             }
-            ProfileMode::TimeFlame => TimeFlameProfilerType::merge_profiles(&profiles)?.profile,
-            ProfileMode::Typecheck => TypecheckProfilerType::merge_profiles(&profiles)?.profile,
-            ProfileMode::Statement => StmtProfilerType::merge_profiles(&profiles)?.profile,
-            ProfileMode::Coverage => CoverageProfileType::merge_profiles(&profiles)?.profile,
+            ProfileMode::TimeFlame => TimeFlameProfilerType::merge_profiles(profiles)?.profile,
+            ProfileMode::Typecheck => TypecheckProfilerType::merge_profiles(profiles)?.profile,
+            ProfileMode::Statement => StmtProfilerType::merge_profiles(profiles)?.profile,
+            ProfileMode::Coverage => CoverageProfileType::merge_profiles(profiles)?.profile,
         };
         Ok(ProfileData { profile })
     }
@@ -200,8 +200,8 @@ impl StmtProfileData {
             time: SmallDuration,
             count: usize,
         }
-        // There should be one EMPTY span entry
-        let mut items = Vec::with_capacity(self.stmts.len() - 1);
+        // There should be one EMPTY span entry, unless the profile is empty
+        let mut items = Vec::with_capacity(self.stmts.len().saturating_sub(1));
         let mut total_time = SmallDuration::default();
         let mut total_count = 0;
         for (file_span, &(count, time)) in &self.stmts {
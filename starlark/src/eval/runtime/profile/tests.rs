@@ -143,3 +143,34 @@ fn test_profile_golden_time_flame() {
 fn test_profile_golden_typecheck() {
     test_profile_golden_for_mode(ProfileMode::Typecheck);
 }
+
+#[test]
+fn test_profile_empty() {
+    for mode in ProfileMode::ALL {
+        let profile_data = ProfileData::empty(&mode).unwrap();
+        assert_eq!(mode, profile_data.profile_mode());
+        profile_data.gen().unwrap();
+        ProfileData::merge([&profile_data, &profile_data]).unwrap();
+    }
+}
+
+#[test]
+fn test_profile_failed_eval() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.enable_profile(&ProfileMode::Statement).unwrap();
+    eval.eval_module(
+        AstModule::parse(
+            "test.star",
+            "x = 1\nfail('oops')\n".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap(),
+        &GlobalsBuilder::extended().build(),
+    )
+    .unwrap_err();
+
+    let profile_data = eval.gen_profile().unwrap();
+    let csv = profile_data.gen().unwrap();
+    assert!(csv.contains("test.star"));
+}
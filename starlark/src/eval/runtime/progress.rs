@@ -0,0 +1,106 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Periodic progress reports during evaluation.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::codemap::FileSpan;
+use crate::codemap::FileSpanRef;
+use crate::eval::BeforeStmtFuncDyn;
+use crate::eval::Evaluator;
+
+/// Snapshot of an ongoing evaluation, passed to [`ProgressHandler`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct EvalProgress {
+    /// Number of statements executed so far.
+    pub steps: u64,
+    /// Statement about to be executed.
+    pub location: FileSpan,
+    /// Name of the function executing the statement.
+    pub function: String,
+    /// Number of frames on the call stack.
+    pub call_stack_count: usize,
+    /// Bytes allocated in the evaluator heap.
+    pub allocated_bytes: usize,
+    /// Time since the handler was installed.
+    pub elapsed: Duration,
+}
+
+/// Called periodically during evaluation,
+/// installed with [`Evaluator::set_progress_handler`](crate::eval::Evaluator::set_progress_handler).
+///
+/// Can be used to render progress in interactive hosts,
+/// or to detect scripts which are stuck before any limit is reached.
+pub trait ProgressHandler {
+    /// Called every configured number of executed statements.
+    ///
+    /// Returning an error stops the evaluation with that error.
+    fn progress(&self, progress: &EvalProgress) -> anyhow::Result<()>;
+}
+
+/// Counts statements and invokes the handler.
+pub(crate) struct ProgressReporter<'a> {
+    handler: &'a (dyn ProgressHandler + 'a),
+    every: u64,
+    steps: u64,
+    next_report: u64,
+    start: Instant,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub(crate) fn new(handler: &'a (dyn ProgressHandler + 'a), every: u64) -> Self {
+        let every = every.max(1);
+        ProgressReporter {
+            handler,
+            every,
+            steps: 0,
+            next_report: every,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<'a, 'e: 'a> BeforeStmtFuncDyn<'a, 'e> for ProgressReporter<'a> {
+    fn call<'v>(
+        &mut self,
+        span: FileSpanRef,
+        eval: &mut Evaluator<'v, 'a, 'e>,
+    ) -> crate::Result<()> {
+        self.steps += 1;
+        if self.steps < self.next_report {
+            return Ok(());
+        }
+        self.next_report += self.every;
+        let progress = EvalProgress {
+            steps: self.steps,
+            location: span.to_file_span(),
+            function: eval
+                .call_stack
+                .top_nth_function_opt(0)
+                .map_or_else(String::new, |f| f.name_for_call_stack()),
+            call_stack_count: eval.call_stack_count(),
+            allocated_bytes: eval.heap().allocated_bytes(),
+            elapsed: self.start.elapsed(),
+        };
+        self.handler
+            .progress(&progress)
+            .map_err(crate::Error::new_other)
+    }
+}
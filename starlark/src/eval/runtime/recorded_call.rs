@@ -0,0 +1,162 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use allocative::Allocative;
+use starlark_derive::Freeze;
+use starlark_derive::Trace;
+use starlark_map::small_map::SmallMap;
+use starlark_syntax::codemap::FileSpan;
+
+use crate as starlark;
+use crate::values::dict::Dict;
+use crate::values::dict::DictMut;
+use crate::values::dict::DictRef;
+use crate::values::layout::pointer::RawPointer;
+use crate::values::list::value::ListData;
+use crate::values::list::ListRef;
+use crate::values::structs::value::Struct;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::set::copy_mutable_set;
+use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::Value;
+
+/// A call made from the top level of a module, recorded when
+/// [`Evaluator::enable_call_recording`](crate::eval::Evaluator::enable_call_recording)
+/// is on.
+///
+/// While the module is evaluated, the recorded calls are available from
+/// [`Module::recorded_calls`](crate::environment::Module::recorded_calls) with `V = Value`,
+/// after freezing from
+/// [`FrozenModule::recorded_calls`](crate::environment::FrozenModule::recorded_calls)
+/// with `V = FrozenValue`.
+///
+/// Arguments are recorded as they were when the call was made:
+/// lists, dicts and sets passed to the call (also nested in tuples and structs)
+/// are copied, so mutating them later does not change the recorded call.
+/// Values of other mutable types are recorded as is.
+#[derive(Debug, Clone, Trace, Freeze, Allocative)]
+pub struct RecordedCall<V> {
+    /// Name of the called function.
+    pub function: String,
+    /// Location of the call expression.
+    #[trace(unsafe_ignore)]
+    #[freeze(identity)]
+    pub location: FileSpan,
+    /// Positional arguments, including those passed with `*args`.
+    pub positional: Vec<V>,
+    /// Named arguments in call order, including those passed with `**kwargs`.
+    pub named: Vec<(String, V)>,
+}
+
+impl<V: Copy> RecordedCall<V> {
+    /// Value of the named argument `name`.
+    pub fn named_arg(&self, name: &str) -> Option<V> {
+        self.named.iter().find(|(n, _)| n == name).map(|(_, v)| *v)
+    }
+}
+
+/// Copies mutable values of call arguments, so later mutations
+/// do not affect the recorded arguments.
+///
+/// Values reachable from several places (including cycles) are copied once,
+/// so the shape of the value graph is preserved.
+pub(crate) struct ArgsSnapshot<'v> {
+    heap: &'v Heap,
+    copied: HashMap<RawPointer, Value<'v>>,
+}
+
+impl<'v> ArgsSnapshot<'v> {
+    pub(crate) fn new(heap: &'v Heap) -> ArgsSnapshot<'v> {
+        ArgsSnapshot {
+            heap,
+            copied: HashMap::new(),
+        }
+    }
+
+    /// Copy of the value, or the value itself if it is immutable.
+    pub(crate) fn copy(&mut self, value: Value<'v>) -> anyhow::Result<Value<'v>> {
+        if value.unpack_frozen().is_some() {
+            return Ok(value);
+        }
+        if let Some(copy) = self.copied.get(&value.ptr_value()) {
+            return Ok(*copy);
+        }
+
+        let copy = if let Some(list) = ListRef::from_value(value) {
+            // Register the copy before copying the content, the list may contain itself.
+            let copy = self.heap.alloc_list(&[]);
+            self.copied.insert(value.ptr_value(), copy);
+            let content = list
+                .content()
+                .iter()
+                .map(|x| self.copy(*x))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            ListData::from_value_mut(copy)?.extend(content, self.heap);
+            copy
+        } else if let Some(dict) = DictRef::from_value(value) {
+            let items: Vec<_> = dict.iter_hashed().collect();
+            drop(dict);
+            let copy = self.heap.alloc(Dict::default());
+            self.copied.insert(value.ptr_value(), copy);
+            for (k, v) in items {
+                // Keys are hashable, so they are immutable, and do not need copying.
+                let v = self.copy(v)?;
+                DictMut::from_value(copy)?.aref.insert_hashed(k, v);
+            }
+            copy
+        } else if let Some(set) = copy_mutable_set(value, self.heap) {
+            // Set elements are hashable, so they are immutable.
+            set
+        } else if let Some(tuple) = TupleRef::from_value(value) {
+            let content = tuple
+                .content()
+                .iter()
+                .map(|x| self.copy(*x))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            if Self::same(tuple.content(), &content) {
+                value
+            } else {
+                self.heap.alloc_tuple(&content)
+            }
+        } else if let Some(s) = StructRef::from_value(value) {
+            let original: Vec<Value<'v>> = s.iter().map(|(_, v)| v).collect();
+            let fields: SmallMap<StringValue<'v>, Value<'v>> = s
+                .iter()
+                .map(|(k, v)| Ok((k, self.copy(v)?)))
+                .collect::<anyhow::Result<_>>()?;
+            let copied: Vec<Value<'v>> = fields.values().copied().collect();
+            if Self::same(&original, &copied) {
+                value
+            } else {
+                self.heap.alloc(Struct::new(fields))
+            }
+        } else {
+            value
+        };
+        self.copied.insert(value.ptr_value(), copy);
+        Ok(copy)
+    }
+
+    /// Copying did not change any value.
+    fn same(original: &[Value<'v>], copied: &[Value<'v>]) -> bool {
+        original.iter().zip(copied).all(|(o, c)| o.ptr_eq(*c))
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dupe::Dupe;
+
+use crate::values::Value;
+
+/// Runtime checks for code which is valid Starlark but most likely a bug.
+///
+/// Unlike [`Dialect::strict`](crate::syntax::Dialect::strict), which rejects code
+/// when it is parsed, these checks look at the values seen during evaluation.
+///
+/// All checks are disabled by default. Violations are reported to the
+/// [`SoftErrorHandler`](crate::eval::SoftErrorHandler) with categories
+/// `runtime_lint_truthiness` and `runtime_lint_equality`: the default handler turns
+/// them into errors, a custom handler can collect them as warnings instead.
+///
+/// Checks are compiled into the bytecode, so they must be set with
+/// [`Evaluator::set_runtime_lints`](crate::eval::Evaluator::set_runtime_lints)
+/// before the module is evaluated.
+#[derive(Debug, Clone, Copy, Dupe, Default, PartialEq, Eq)]
+pub struct RuntimeLints {
+    /// Report conditions which test a value whose length is zero but which is truthy.
+    /// This happens with native types which implement `len()` but not `bool()`.
+    pub truthiness: bool,
+    /// Report `==` and `!=` between values of unrelated types, which are always unequal.
+    /// Comparisons with `None` and between `int` and `float` are not reported,
+    /// neither are comparisons the optimizer evaluates at compile time.
+    pub equality: bool,
+}
+
+impl RuntimeLints {
+    /// Enable all the checks.
+    pub fn all() -> RuntimeLints {
+        RuntimeLints {
+            truthiness: true,
+            equality: true,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum RuntimeLintError {
+    #[error("Value of type `{0}` has zero length but is truthy, because the type does not implement `bool()`")]
+    TruthyEmpty(String),
+    #[error("Comparing `{0}` with `{1}` is always false, because the types are unrelated")]
+    UnrelatedEquality(String, String),
+}
+
+impl RuntimeLintError {
+    pub(crate) const TRUTHINESS: &'static str = "runtime_lint_truthiness";
+    pub(crate) const EQUALITY: &'static str = "runtime_lint_equality";
+
+    pub(crate) fn check_truthiness(v: Value) -> Option<RuntimeLintError> {
+        if v.to_bool() && matches!(v.length(), Ok(0)) {
+            Some(RuntimeLintError::TruthyEmpty(v.get_type().to_owned()))
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn check_equality(a: Value, b: Value, eq: bool) -> Option<RuntimeLintError> {
+        let (ta, tb) = (a.get_type(), b.get_type());
+        let numeric = |t| matches!(t, "int" | "float");
+        if eq || ta == tb || a.is_none() || b.is_none() || (numeric(ta) && numeric(tb)) {
+            None
+        } else {
+            Some(RuntimeLintError::UnrelatedEquality(
+                ta.to_owned(),
+                tb.to_owned(),
+            ))
+        }
+    }
+}
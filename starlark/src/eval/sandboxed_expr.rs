@@ -0,0 +1,439 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Hardened evaluation of user-supplied expressions.
+
+use std::cell::Cell;
+
+use once_cell::sync::Lazy;
+
+use crate::collections::SmallMap;
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Arguments;
+use crate::eval::CompiledExpr;
+use crate::eval::Evaluator;
+use crate::eval::NativeCallInterceptor;
+use crate::syntax::Dialect;
+use crate::values::Heap;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum SandboxError {
+    #[error("Expression is {0} bytes long, the limit is {1}")]
+    ExprTooLong(usize, usize),
+    #[error("Expression made more than {0} native calls")]
+    TooManyNativeCalls(usize),
+    #[error("Expression allocated more than {0} bytes")]
+    HeapLimitExceeded(usize),
+}
+
+/// Limits applied to a [`SandboxedExpr`].
+#[derive(Debug, Clone)]
+pub struct SandboxLimits {
+    /// Maximum length of the expression source in bytes.
+    pub max_expr_len: usize,
+    /// Maximum depth of the call stack.
+    pub max_callstack_size: usize,
+    /// Maximum number of positional arguments to a single call.
+    pub max_call_args: usize,
+    /// Maximum number of named arguments to a single call.
+    pub max_call_kwargs: usize,
+    /// Maximum number of native function and method calls in one evaluation.
+    pub max_native_calls: usize,
+    /// Maximum number of bytecode instructions executed in one evaluation.
+    pub max_steps: u64,
+    /// Maximum number of bytes allocated on the heap in one evaluation,
+    /// on top of what the heap already holds, checked on every allocation.
+    pub max_heap_bytes: usize,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        SandboxLimits {
+            max_expr_len: 4096,
+            max_callstack_size: 50,
+            max_call_args: 32,
+            max_call_kwargs: 32,
+            max_native_calls: 10_000,
+            max_steps: 100_000,
+            max_heap_bytes: 16 << 20,
+        }
+    }
+}
+
+/// Enforces [`SandboxLimits::max_native_calls`].
+struct SandboxGuard<'h> {
+    limits: &'h SandboxLimits,
+    native_calls: Cell<usize>,
+}
+
+impl NativeCallInterceptor for SandboxGuard<'_> {
+    fn before_call<'v>(
+        &self,
+        _name: &str,
+        _this: Option<Value<'v>>,
+        _args: &Arguments<'v, '_>,
+    ) -> anyhow::Result<Option<Value<'v>>> {
+        let calls = self.native_calls.get() + 1;
+        if calls > self.limits.max_native_calls {
+            return Err(SandboxError::TooManyNativeCalls(self.limits.max_native_calls).into());
+        }
+        self.native_calls.set(calls);
+        Ok(None)
+    }
+}
+
+/// Limits the heap to a budget on top of what it already holds,
+/// and restores the previous limit when the evaluation ends.
+struct HeapLimitGuard<'v> {
+    heap: &'v Heap,
+    previous: Option<usize>,
+    limit: usize,
+}
+
+impl<'v> HeapLimitGuard<'v> {
+    fn new(heap: &'v Heap, budget: usize) -> Self {
+        HeapLimitGuard {
+            heap,
+            previous: heap.allocation_limit(),
+            limit: heap.allocated_bytes().saturating_add(budget),
+        }
+    }
+
+    fn exceeded(&self) -> bool {
+        self.heap.allocation_limit_exceeded() || self.heap.allocated_bytes() > self.limit
+    }
+}
+
+impl Drop for HeapLimitGuard<'_> {
+    fn drop(&mut self) {
+        // Also forgets that the sandbox limit was exceeded.
+        self.heap.set_allocation_limit(self.previous);
+    }
+}
+
+/// An expression written by an untrusted user, such as a filter
+/// `label.endswith("_test") and size < 100`.
+///
+/// Compared to [`CompiledExpr`], a sandboxed expression:
+///
+/// * can't contain `def`, `lambda` or `load`,
+/// * only sees the globals listed in [`SandboxedExpr::STANDARD_GLOBALS`]
+///   (or the globals given to [`SandboxedExpr::with_globals`]),
+/// * is evaluated in a fresh evaluator with [`SandboxLimits`] applied.
+///
+/// The limits bound recursion, call sizes, the number of native calls,
+/// the number of executed instructions and the heap size.
+///
+/// ```
+/// use starlark::collections::SmallMap;
+/// use starlark::eval::SandboxedExpr;
+///
+/// let filter = SandboxedExpr::new(
+///     r#"label.endswith("_test") and size < 100"#,
+///     &["label", "size"],
+/// )
+/// .unwrap();
+/// let mut bindings = SmallMap::new();
+/// bindings.insert("label", serde_json::json!("foo_test"));
+/// bindings.insert("size", serde_json::json!(10));
+/// assert!(filter.eval_bool(&bindings).unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SandboxedExpr {
+    expr: CompiledExpr,
+    limits: SandboxLimits,
+}
+
+impl SandboxedExpr {
+    /// Standard globals available to sandboxed expressions by default.
+    pub const STANDARD_GLOBALS: &'static [&'static str] = &[
+        "False",
+        "None",
+        "True",
+        "abs",
+        "all",
+        "any",
+        "bool",
+        "dict",
+        "enumerate",
+        "float",
+        "getattr",
+        "hasattr",
+        "int",
+        "len",
+        "list",
+        "max",
+        "min",
+        "repr",
+        "reversed",
+        "sorted",
+        "str",
+        "tuple",
+        "type",
+        "zip",
+    ];
+
+    /// The dialect sandboxed expressions are parsed with.
+    fn dialect() -> Dialect {
+        Dialect {
+            enable_def: false,
+            enable_lambda: false,
+            enable_load: false,
+            ..Dialect::Standard
+        }
+    }
+
+    /// Compile an expression referring to `variables` and [`STANDARD_GLOBALS`](SandboxedExpr::STANDARD_GLOBALS),
+    /// with the default [`SandboxLimits`].
+    pub fn new(expr: &str, variables: &[&str]) -> crate::Result<SandboxedExpr> {
+        static GLOBALS: Lazy<Globals> = Lazy::new(|| {
+            Globals::standard().filter(|name| SandboxedExpr::STANDARD_GLOBALS.contains(&name))
+        });
+        Self::with_globals(expr, variables, &GLOBALS, SandboxLimits::default())
+    }
+
+    /// Compile an expression referring to `variables` and `globals`.
+    ///
+    /// All of `globals` are visible to the expression,
+    /// use [`Globals::filter`] to restrict them to a whitelist.
+    pub fn with_globals(
+        expr: &str,
+        variables: &[&str],
+        globals: &Globals,
+        limits: SandboxLimits,
+    ) -> crate::Result<SandboxedExpr> {
+        if expr.len() > limits.max_expr_len {
+            return Err(crate::Error::new_other(SandboxError::ExprTooLong(
+                expr.len(),
+                limits.max_expr_len,
+            )));
+        }
+        let expr = CompiledExpr::with_dialect(expr, variables, globals, &Self::dialect())?;
+        Ok(SandboxedExpr { expr, limits })
+    }
+
+    /// Evaluate the expression with `bindings` allocated on the heap of `module`.
+    pub fn eval_typed<'v, T: UnpackValue<'v>>(
+        &self,
+        module: &'v Module,
+        bindings: &SmallMap<&str, Value<'v>>,
+    ) -> crate::Result<T> {
+        let guard = SandboxGuard {
+            limits: &self.limits,
+            native_calls: Cell::new(0),
+        };
+        let heap_guard = HeapLimitGuard::new(module.heap(), self.limits.max_heap_bytes);
+        let mut eval = Evaluator::new(module);
+        eval.set_max_callstack_size(self.limits.max_callstack_size)?;
+        eval.set_max_call_args(self.limits.max_call_args);
+        eval.set_max_call_kwargs(self.limits.max_call_kwargs);
+        eval.set_max_steps(self.limits.max_steps)?;
+        eval.set_max_heap_bytes(heap_guard.limit)?;
+        eval.set_native_call_interceptor(&guard);
+        let res = eval.eval_compiled_expr_typed(&self.expr, bindings);
+        // The last instruction may have allocated past the limit.
+        if heap_guard.exceeded() {
+            return Err(crate::Error::new_other(SandboxError::HeapLimitExceeded(
+                self.limits.max_heap_bytes,
+            )));
+        }
+        res
+    }
+
+    /// Evaluate the expression with variables bound to JSON values,
+    /// the result must be a `bool`.
+    pub fn eval_bool(&self, bindings: &SmallMap<&str, serde_json::Value>) -> crate::Result<bool> {
+        let module = Module::new();
+        let bindings = bindings
+            .iter()
+            .map(|(name, value)| (*name, module.heap().alloc(value.clone())))
+            .collect();
+        self.eval_typed(&module, &bindings)
+    }
+
+    /// The limits this expression is evaluated with.
+    pub fn limits(&self) -> &SandboxLimits {
+        &self.limits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collections::SmallMap;
+    use crate::environment::Module;
+    use crate::eval::SandboxLimits;
+    use crate::eval::SandboxedExpr;
+
+    fn eval(expr: &str, limits: SandboxLimits) -> crate::Result<bool> {
+        let mut bindings = SmallMap::new();
+        bindings.insert("label", serde_json::json!("//foo:bar_test"));
+        bindings.insert("size", serde_json::json!(42));
+        bindings.insert("tags", serde_json::json!(["slow", "manual"]));
+        SandboxedExpr::with_globals(
+            expr,
+            &["label", "size", "tags"],
+            &crate::environment::Globals::standard()
+                .filter(|name| SandboxedExpr::STANDARD_GLOBALS.contains(&name)),
+            limits,
+        )?
+        .eval_bool(&bindings)
+    }
+
+    fn fails(expr: &str, limits: SandboxLimits, msg: &str) {
+        let e = eval(expr, limits).unwrap_err().to_string();
+        assert!(e.contains(msg), "{expr}: {e}");
+    }
+
+    #[test]
+    fn test_sandboxed_expr() {
+        assert!(eval(
+            r#"label.endswith("_test") and size < 100"#,
+            SandboxLimits::default()
+        )
+        .unwrap());
+        assert!(!eval(r#""manual" not in tags"#, SandboxLimits::default()).unwrap());
+        assert!(eval(
+            "any([t.startswith('s') for t in tags])",
+            SandboxLimits::default()
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_sandboxed_expr_rejected() {
+        let limits = SandboxLimits::default;
+        fails("(lambda: True)()", limits(), "lambda");
+        fails("x = 1", limits(), "Expected a single expression");
+        fails("len(range(10)) > 0", limits(), "Variable `range` not found");
+        fails(
+            "print(label) == None",
+            limits(),
+            "Variable `print` not found",
+        );
+        fails("size", limits(), "Expected `bool`");
+        fails(
+            "size > 0",
+            SandboxLimits {
+                max_expr_len: 4,
+                ..limits()
+            },
+            "Expression is 8 bytes long, the limit is 4",
+        );
+        fails(
+            "len([str(x) for x in tags * 10]) > 0",
+            SandboxLimits {
+                max_native_calls: 10,
+                ..limits()
+            },
+            "Expression made more than 10 native calls",
+        );
+        fails(
+            "len(label * 100000) > 0",
+            SandboxLimits {
+                max_heap_bytes: 1 << 16,
+                ..limits()
+            },
+            "Expression allocated more than 65536 bytes",
+        );
+    }
+
+    #[test]
+    fn test_sandboxed_expr_resource_limits() {
+        let limits = SandboxLimits::default;
+        // Does not even fit `int`.
+        fails(
+            "len([0] * 10000000000) > 0",
+            limits(),
+            "too big to fit in i32",
+        );
+        // Fails before allocating.
+        fails(
+            "len([0] * 1000000000) > 0",
+            limits(),
+            "Expression allocated more than 16777216 bytes",
+        );
+        fails(
+            "len(label * 1000000000) > 0",
+            limits(),
+            "Expression allocated more than 16777216 bytes",
+        );
+        // Nested comprehensions make no native calls.
+        fails(
+            "len([[t for t in tags * 1000] for u in tags * 1000]) > 0",
+            limits(),
+            "Evaluation exceeded the limit of 100000 steps",
+        );
+        fails(
+            "len([[t for t in tags * 1000] for u in tags * 1000]) > 0",
+            SandboxLimits {
+                max_steps: u64::MAX,
+                max_heap_bytes: 1 << 20,
+                ..limits()
+            },
+            "Expression allocated more than 1048576 bytes",
+        );
+    }
+
+    #[test]
+    fn test_sandboxed_expr_custom_values() {
+        let expr = SandboxedExpr::new("x in y", &["x", "y"]).unwrap();
+        let module = Module::new();
+        let mut bindings = SmallMap::new();
+        bindings.insert("x", module.heap().alloc(1));
+        bindings.insert("y", module.heap().alloc(vec![1, 2]));
+        assert!(expr.eval_typed::<bool>(&module, &bindings).unwrap());
+    }
+
+    #[test]
+    fn test_sandboxed_expr_heap_limit_is_per_evaluation() {
+        let limits = SandboxLimits {
+            max_heap_bytes: 1 << 16,
+            ..SandboxLimits::default()
+        };
+        let expr = SandboxedExpr::with_globals(
+            "len(x) > 0 and len('ab' * n) > 0",
+            &["x", "n"],
+            &crate::environment::Globals::standard(),
+            limits,
+        )
+        .unwrap();
+        let module = Module::new();
+        // Memory the module already holds does not count against the limit.
+        let x = module.heap().alloc("x".repeat(1 << 17));
+        let mut bindings = SmallMap::new();
+        bindings.insert("x", x);
+        bindings.insert("n", module.heap().alloc(10));
+        assert!(expr.eval_typed::<bool>(&module, &bindings).unwrap());
+        bindings.insert("n", module.heap().alloc(100000));
+        let e = expr.eval_typed::<bool>(&module, &bindings).unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("Expression allocated more than 65536 bytes"),
+            "{e}"
+        );
+        // Exceeding the limit does not break later allocations on the module.
+        assert_eq!(
+            1 << 18,
+            module.heap().alloc("y".repeat(1 << 18)).length().unwrap()
+        );
+        bindings.insert("n", module.heap().alloc(10));
+        assert!(expr.eval_typed::<bool>(&module, &bindings).unwrap());
+    }
+}
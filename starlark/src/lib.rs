@@ -428,9 +428,11 @@
 
 mod macros;
 
+pub use starlark_derive::starlark_methods;
 pub use starlark_derive::starlark_module;
 pub use starlark_derive::StarlarkDocs;
 pub use starlark_syntax::codemap;
+pub use starlark_syntax::message;
 pub use starlark_syntax::Error;
 pub use starlark_syntax::ErrorKind;
 pub use starlark_syntax::Result;
@@ -440,12 +442,14 @@ pub use stdlib::PrintHandler;
 pub mod analysis;
 pub mod any;
 pub mod assert;
+pub mod batch;
 pub mod collections;
 pub mod debug;
 pub mod docs;
 pub mod environment;
 pub mod errors;
 pub mod eval;
+pub mod plugin;
 mod private;
 pub mod read_line;
 mod sealed;
@@ -481,8 +485,15 @@ pub mod __macro_refs {
 pub mod __derive_refs {
     pub mod serde {
         pub use serde::ser::Error;
+        pub use serde::ser::SerializeStruct;
+        pub use serde::ser::SerializeStructVariant;
+        pub use serde::ser::SerializeTupleStruct;
+        pub use serde::ser::SerializeTupleVariant;
         pub use serde::Serialize;
         pub use serde::Serializer;
     }
     pub use inventory;
+
+    pub use crate::environment::methods::DerivedMethods;
+    pub use crate::values::structs::unpack::UnpackStructFields;
 }
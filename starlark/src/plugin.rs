@@ -0,0 +1,599 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Versioned C ABI for native extensions compiled into separate libraries.
+//!
+//! Rust types and trait objects have no stable layout, so a plugin built with a different
+//! compiler or a different starlark version cannot hand its functions to the host directly.
+//! Instead a plugin exports an `extern "C"` function named [`PLUGIN_REGISTER_SYMBOL`]
+//! of type [`PluginRegisterFn`] returning a [`PluginDescriptor`]. Arguments and results
+//! cross the boundary as JSON, so only `#[repr(C)]` types defined here are shared.
+//!
+//! The host loads the library (e.g. with `libloading`), looks up the symbol, and passes it
+//! to [`register_plugin`], which adds the plugin functions to the globals as a namespace
+//! named after the plugin.
+//!
+//! ```
+//! use starlark::environment::GlobalsBuilder;
+//! use starlark::environment::Module;
+//! use starlark::eval::Evaluator;
+//! use starlark::plugin::register_plugin;
+//! use starlark::plugin::PluginCallResult;
+//! use starlark::plugin::PluginDescriptor;
+//! use starlark::plugin::PluginFunction;
+//! use starlark::plugin::PluginStr;
+//! use starlark::plugin::PLUGIN_ABI_VERSION;
+//! use starlark::syntax::AstModule;
+//! use starlark::syntax::Dialect;
+//!
+//! // Plugin side.
+//! unsafe extern "C" fn greet(args: PluginStr) -> PluginCallResult {
+//!     let args: serde_json::Value = serde_json::from_str(unsafe { args.as_str() }).unwrap();
+//!     let name = args["args"][0].as_str().unwrap_or("world");
+//!     PluginCallResult::ok(PluginStr::from_string(format!("\"hello {}\"", name)))
+//! }
+//!
+//! unsafe extern "C" fn free(s: PluginStr) {
+//!     drop(unsafe { s.into_string() });
+//! }
+//!
+//! static FUNCTIONS: [PluginFunction; 1] = [PluginFunction {
+//!     name: PluginStr::from_static("greet"),
+//!     doc: PluginStr::from_static("Greet someone."),
+//!     call: Some(greet),
+//! }];
+//!
+//! static DESCRIPTOR: PluginDescriptor = PluginDescriptor {
+//!     abi_version: PLUGIN_ABI_VERSION,
+//!     name: PluginStr::from_static("hello"),
+//!     functions: FUNCTIONS.as_ptr(),
+//!     function_count: FUNCTIONS.len(),
+//!     free: Some(free),
+//! };
+//!
+//! extern "C" fn starlark_plugin_register() -> *const PluginDescriptor {
+//!     &DESCRIPTOR
+//! }
+//!
+//! // Host side.
+//! let mut builder = GlobalsBuilder::standard();
+//! unsafe { register_plugin(&mut builder, starlark_plugin_register) }.unwrap();
+//! let globals = builder.build();
+//!
+//! let module = Module::new();
+//! let mut eval = Evaluator::new(&module);
+//! let ast = AstModule::parse(
+//!     "x.star",
+//!     "hello.greet('bob')".to_owned(),
+//!     &Dialect::Standard,
+//! )
+//! .unwrap();
+//! let res = eval.eval_module(ast, &globals).unwrap();
+//! assert_eq!("hello bob", res.unpack_str().unwrap());
+//! ```
+
+use std::collections::HashSet;
+use std::slice;
+use std::str;
+
+use crate::environment::GlobalsBuilder;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::eval::ParametersSpec;
+use crate::typing::Ty;
+use crate::values::function::NativeCallableRawDocs;
+use crate::values::function::NativeFunction;
+use crate::values::Value;
+
+/// Version of the plugin ABI. Incremented on any change to the types in this module.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Name of the symbol of type [`PluginRegisterFn`] a plugin library exports.
+pub const PLUGIN_REGISTER_SYMBOL: &str = "starlark_plugin_register";
+
+/// Borrowed or plugin-owned UTF-8 string.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginStr {
+    /// Pointer to the first byte.
+    pub ptr: *const u8,
+    /// Length in bytes.
+    pub len: usize,
+}
+
+impl PluginStr {
+    /// Refer to a static string, e.g. in a static descriptor.
+    pub const fn from_static(s: &'static str) -> PluginStr {
+        PluginStr {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// Borrow a string for the duration of a call.
+    fn borrow(s: &str) -> PluginStr {
+        PluginStr {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// Transfer ownership of the string to the caller.
+    /// The string must be released with [`PluginStr::into_string`] by the same library.
+    pub fn from_string(s: String) -> PluginStr {
+        let s = Box::leak(s.into_boxed_str());
+        PluginStr {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// Take back a string created with [`PluginStr::from_string`].
+    ///
+    /// # Safety
+    ///
+    /// Must be called once, with a string created by `from_string` in the same library.
+    pub unsafe fn into_string(self) -> String {
+        let bytes = slice::from_raw_parts_mut(self.ptr as *mut u8, self.len);
+        String::from_utf8_unchecked(Box::from_raw(bytes).into_vec())
+    }
+
+    /// View the string.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be valid for `len` bytes of UTF-8 for the returned lifetime.
+    pub unsafe fn as_str<'a>(self) -> &'a str {
+        str::from_utf8_unchecked(slice::from_raw_parts(self.ptr, self.len))
+    }
+
+    /// View the string, checking it is UTF-8.
+    unsafe fn to_str<'a>(self, what: &str) -> anyhow::Result<&'a str> {
+        if self.ptr.is_null() {
+            return Err(PluginError::Null(what.to_owned()).into());
+        }
+        str::from_utf8(slice::from_raw_parts(self.ptr, self.len))
+            .map_err(|_| PluginError::InvalidUtf8(what.to_owned()).into())
+    }
+}
+
+/// Result of a [`PluginCallFn`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginCallResult {
+    /// Whether the call succeeded.
+    pub ok: bool,
+    /// On success the returned value as JSON, otherwise the error message.
+    /// Released by the host with [`PluginDescriptor::free`] unless the pointer is null.
+    pub value: PluginStr,
+}
+
+impl PluginCallResult {
+    /// Successful call returning the given JSON.
+    pub fn ok(json: PluginStr) -> PluginCallResult {
+        PluginCallResult {
+            ok: true,
+            value: json,
+        }
+    }
+
+    /// Failed call with the given message.
+    pub fn err(message: PluginStr) -> PluginCallResult {
+        PluginCallResult {
+            ok: false,
+            value: message,
+        }
+    }
+}
+
+/// Plugin function. Receives a JSON object `{"args": [...], "kwargs": {...}}`
+/// which is only valid during the call.
+pub type PluginCallFn = unsafe extern "C" fn(args: PluginStr) -> PluginCallResult;
+
+/// Release a string returned by the plugin.
+pub type PluginFreeFn = unsafe extern "C" fn(s: PluginStr);
+
+/// Registration function exported by a plugin as [`PLUGIN_REGISTER_SYMBOL`].
+pub type PluginRegisterFn = unsafe extern "C" fn() -> *const PluginDescriptor;
+
+/// Function provided by a plugin.
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginFunction {
+    /// Name of the function within the plugin namespace.
+    pub name: PluginStr,
+    /// Docstring, may be empty.
+    pub doc: PluginStr,
+    /// Implementation, must not be null.
+    pub call: Option<PluginCallFn>,
+}
+
+/// Everything a plugin provides. Must stay valid while the plugin is loaded.
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginDescriptor {
+    /// Must be [`PLUGIN_ABI_VERSION`] the plugin was built against.
+    pub abi_version: u32,
+    /// Name of the namespace with the plugin functions.
+    pub name: PluginStr,
+    /// Pointer to `function_count` functions.
+    pub functions: *const PluginFunction,
+    /// Number of functions.
+    pub function_count: usize,
+    /// Release strings returned from [`PluginFunction::call`].
+    /// Null if the plugin only returns strings which need no release, e.g. static strings.
+    pub free: Option<PluginFreeFn>,
+}
+
+// SAFETY: descriptors only refer to immutable data and thread-safe functions.
+unsafe impl Sync for PluginDescriptor {}
+unsafe impl Sync for PluginFunction {}
+
+#[derive(Debug, thiserror::Error)]
+enum PluginError {
+    #[error("Plugin registration returned null")]
+    NullDescriptor,
+    #[error("Plugin ABI version {0} is not supported, expected version {PLUGIN_ABI_VERSION}")]
+    AbiVersion(u32),
+    #[error("Plugin {0} is null")]
+    Null(String),
+    #[error("Plugin {0} is not valid UTF-8")]
+    InvalidUtf8(String),
+    #[error("Plugin `{0}` defines function `{1}` more than once")]
+    DuplicateFunction(String, String),
+    #[error("Plugin function `{0}` returned invalid JSON: {1}")]
+    InvalidResult(String, serde_json::Error),
+    #[error("{0}")]
+    Call(String),
+}
+
+struct PluginCall {
+    name: String,
+    call: PluginCallFn,
+    free: Option<PluginFreeFn>,
+}
+
+impl PluginCall {
+    fn invoke<'v>(
+        &self,
+        eval: &mut Evaluator<'v, '_, '_>,
+        args: &Arguments<'v, '_>,
+    ) -> crate::Result<Value<'v>> {
+        let heap = eval.heap();
+        let pos = args
+            .positions(heap)?
+            .map(|v| v.to_json_value())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut named = serde_json::Map::new();
+        for (k, v) in args.names_map()? {
+            named.insert(k.as_str().to_owned(), v.to_json_value()?);
+        }
+        let input = serde_json::json!({ "args": pos, "kwargs": named }).to_string();
+        // SAFETY: `register_plugin` caller guarantees the plugin is valid.
+        let (ok, output) = unsafe {
+            let res = (self.call)(PluginStr::borrow(&input));
+            let output = res.value.to_str("result").map(str::to_owned);
+            if let Some(free) = self.free {
+                if !res.value.ptr.is_null() {
+                    free(res.value);
+                }
+            }
+            (res.ok, output?)
+        };
+        if !ok {
+            return Err(crate::Error::new_other(PluginError::Call(output)));
+        }
+        let json: serde_json::Value = serde_json::from_str(&output)
+            .map_err(|e| PluginError::InvalidResult(self.name.clone(), e))
+            .map_err(crate::Error::new_other)?;
+        Ok(heap.alloc(json))
+    }
+}
+
+/// Register the functions of a plugin in `builder` as a namespace named after the plugin.
+///
+/// # Safety
+///
+/// `register` must follow the plugin ABI, and the library providing it must stay
+/// loaded while the globals are in use.
+pub unsafe fn register_plugin(
+    builder: &mut GlobalsBuilder,
+    register: PluginRegisterFn,
+) -> anyhow::Result<()> {
+    let descriptor = register();
+    let Some(descriptor) = descriptor.as_ref() else {
+        return Err(PluginError::NullDescriptor.into());
+    };
+    if descriptor.abi_version != PLUGIN_ABI_VERSION {
+        return Err(PluginError::AbiVersion(descriptor.abi_version).into());
+    }
+    let plugin_name = descriptor.name.to_str("name")?;
+    let functions: &'static [PluginFunction] = if descriptor.function_count == 0 {
+        &[]
+    } else if descriptor.functions.is_null() {
+        return Err(PluginError::Null("function list".to_owned()).into());
+    } else {
+        slice::from_raw_parts(descriptor.functions, descriptor.function_count)
+    };
+
+    let mut calls = Vec::new();
+    let mut names = HashSet::new();
+    for f in functions {
+        let name = f.name.to_str("function name")?;
+        if !names.insert(name) {
+            return Err(
+                PluginError::DuplicateFunction(plugin_name.to_owned(), name.to_owned()).into(),
+            );
+        }
+        let doc: &'static str = match f.doc.len {
+            0 => "",
+            _ => f.doc.to_str("docstring")?,
+        };
+        let Some(call) = f.call else {
+            return Err(PluginError::Null(format!("function `{}`", name)).into());
+        };
+        calls.push((
+            doc,
+            PluginCall {
+                name: format!("{}.{}", plugin_name, name),
+                call,
+                free: descriptor.free,
+            },
+        ));
+    }
+
+    builder.struct_(plugin_name, |builder| {
+        for (doc, call) in calls {
+            let name = call.name.clone();
+            let short_name = name[plugin_name.len() + 1..].to_owned();
+            let mut signature = ParametersSpec::with_capacity(name.clone(), 2);
+            signature.args();
+            signature.kwargs();
+            let mut function =
+                NativeFunction::new_direct(move |eval, args| call.invoke(eval, args), name);
+            function.raw_docs = Some(NativeCallableRawDocs {
+                rust_docstring: Some(doc).filter(|d| !d.is_empty()),
+                signature: signature.finish(),
+                parameter_types: vec![Ty::any(), Ty::any()],
+                parameter_docs: Vec::new(),
+                return_type: Ty::any(),
+                as_type: None,
+            });
+            builder.set(&short_name, function);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use crate::assert::Assert;
+    use crate::docs::DocItem;
+    use crate::docs::DocMember;
+    use crate::environment::GlobalsBuilder;
+    use crate::plugin::register_plugin;
+    use crate::plugin::PluginCallResult;
+    use crate::plugin::PluginDescriptor;
+    use crate::plugin::PluginFunction;
+    use crate::plugin::PluginStr;
+    use crate::plugin::PLUGIN_ABI_VERSION;
+    use crate::values::Heap;
+
+    unsafe extern "C" fn echo(args: PluginStr) -> PluginCallResult {
+        PluginCallResult::ok(PluginStr::from_string(args.as_str().to_owned()))
+    }
+
+    unsafe extern "C" fn fail(_args: PluginStr) -> PluginCallResult {
+        PluginCallResult::err(PluginStr::from_string("plugin failed".to_owned()))
+    }
+
+    unsafe extern "C" fn free(s: PluginStr) {
+        drop(s.into_string());
+    }
+
+    static FUNCTIONS: [PluginFunction; 2] = [
+        PluginFunction {
+            name: PluginStr::from_static("echo"),
+            doc: PluginStr::from_static("Return the arguments."),
+            call: Some(echo),
+        },
+        PluginFunction {
+            name: PluginStr::from_static("fail"),
+            doc: PluginStr::from_static(""),
+            call: Some(fail),
+        },
+    ];
+
+    static DESCRIPTOR: PluginDescriptor = PluginDescriptor {
+        abi_version: PLUGIN_ABI_VERSION,
+        name: PluginStr::from_static("test_plugin"),
+        functions: FUNCTIONS.as_ptr(),
+        function_count: FUNCTIONS.len(),
+        free: Some(free),
+    };
+
+    static FUTURE: PluginDescriptor = PluginDescriptor {
+        abi_version: PLUGIN_ABI_VERSION + 1,
+        name: PluginStr::from_static("future"),
+        functions: FUNCTIONS.as_ptr(),
+        function_count: FUNCTIONS.len(),
+        free: Some(free),
+    };
+
+    static DUPLICATE_FUNCTIONS: [PluginFunction; 2] = [
+        PluginFunction {
+            name: PluginStr::from_static("f"),
+            doc: PluginStr::from_static(""),
+            call: Some(echo),
+        },
+        PluginFunction {
+            name: PluginStr::from_static("f"),
+            doc: PluginStr::from_static(""),
+            call: Some(fail),
+        },
+    ];
+
+    static DUPLICATE: PluginDescriptor = PluginDescriptor {
+        abi_version: PLUGIN_ABI_VERSION,
+        name: PluginStr::from_static("dup"),
+        functions: DUPLICATE_FUNCTIONS.as_ptr(),
+        function_count: DUPLICATE_FUNCTIONS.len(),
+        free: Some(free),
+    };
+
+    unsafe extern "C" fn constant(_args: PluginStr) -> PluginCallResult {
+        PluginCallResult::ok(PluginStr::from_static("[1, 2]"))
+    }
+
+    unsafe extern "C" fn null(_args: PluginStr) -> PluginCallResult {
+        PluginCallResult::ok(PluginStr {
+            ptr: ptr::null(),
+            len: 0,
+        })
+    }
+
+    static STATIC_FUNCTIONS: [PluginFunction; 2] = [
+        PluginFunction {
+            name: PluginStr::from_static("constant"),
+            doc: PluginStr::from_static(""),
+            call: Some(constant),
+        },
+        PluginFunction {
+            name: PluginStr::from_static("null"),
+            doc: PluginStr::from_static(""),
+            call: Some(null),
+        },
+    ];
+
+    static STATIC: PluginDescriptor = PluginDescriptor {
+        abi_version: PLUGIN_ABI_VERSION,
+        name: PluginStr::from_static("static_plugin"),
+        functions: STATIC_FUNCTIONS.as_ptr(),
+        function_count: STATIC_FUNCTIONS.len(),
+        free: None,
+    };
+
+    static MISSING_CALL_FUNCTIONS: [PluginFunction; 1] = [PluginFunction {
+        name: PluginStr::from_static("f"),
+        doc: PluginStr::from_static(""),
+        call: None,
+    }];
+
+    static MISSING_CALL: PluginDescriptor = PluginDescriptor {
+        abi_version: PLUGIN_ABI_VERSION,
+        name: PluginStr::from_static("missing"),
+        functions: MISSING_CALL_FUNCTIONS.as_ptr(),
+        function_count: MISSING_CALL_FUNCTIONS.len(),
+        free: Some(free),
+    };
+
+    extern "C" fn register() -> *const PluginDescriptor {
+        &DESCRIPTOR
+    }
+
+    extern "C" fn register_static() -> *const PluginDescriptor {
+        &STATIC
+    }
+
+    extern "C" fn register_missing_call() -> *const PluginDescriptor {
+        &MISSING_CALL
+    }
+
+    extern "C" fn register_future() -> *const PluginDescriptor {
+        &FUTURE
+    }
+
+    extern "C" fn register_duplicate() -> *const PluginDescriptor {
+        &DUPLICATE
+    }
+
+    #[test]
+    fn test_plugin_call() {
+        let mut a = Assert::new();
+        a.globals_add(|builder| unsafe { register_plugin(builder, register) }.unwrap());
+        a.eq(
+            "{'args': [1, 'x'], 'kwargs': {'k': [True, None]}}",
+            "test_plugin.echo(1, 'x', k = [True, None])",
+        );
+        a.fail("test_plugin.fail()", "plugin failed");
+        a.fail(
+            "test_plugin.echo(lambda x: x)",
+            "not supported on type `function`",
+        );
+    }
+
+    #[test]
+    fn test_plugin_docs() {
+        let mut builder = GlobalsBuilder::new();
+        unsafe { register_plugin(&mut builder, register) }.unwrap();
+        let globals = builder.build();
+        let heap = Heap::new();
+        let echo = globals
+            .get("test_plugin")
+            .unwrap()
+            .get_attr("echo", &heap)
+            .unwrap()
+            .unwrap();
+        match echo.documentation() {
+            Some(DocItem::Member(DocMember::Function(f))) => {
+                assert_eq!("Return the arguments.", f.docs.unwrap().summary);
+            }
+            docs => panic!("Unexpected docs: {:?}", docs),
+        }
+    }
+
+    #[test]
+    fn test_plugin_abi_version() {
+        let mut builder = GlobalsBuilder::new();
+        let err = unsafe { register_plugin(&mut builder, register_future) }.unwrap_err();
+        assert_eq!(
+            format!(
+                "Plugin ABI version {} is not supported, expected version {}",
+                PLUGIN_ABI_VERSION + 1,
+                PLUGIN_ABI_VERSION
+            ),
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_plugin_duplicate_function() {
+        let mut builder = GlobalsBuilder::new();
+        let err = unsafe { register_plugin(&mut builder, register_duplicate) }.unwrap_err();
+        assert_eq!(
+            "Plugin `dup` defines function `f` more than once",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_plugin_without_free() {
+        let mut a = Assert::new();
+        a.globals_add(|builder| unsafe { register_plugin(builder, register_static) }.unwrap());
+        a.eq("[1, 2]", "static_plugin.constant()");
+        a.fail("static_plugin.null()", "Plugin result is null");
+    }
+
+    #[test]
+    fn test_plugin_null_function() {
+        let mut builder = GlobalsBuilder::new();
+        let err = unsafe { register_plugin(&mut builder, register_missing_call) }.unwrap_err();
+        assert_eq!("Plugin function `f` is null", err.to_string());
+    }
+}
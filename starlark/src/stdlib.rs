@@ -20,26 +20,39 @@
 
 use dupe::Dupe;
 
+#[cfg(not(feature = "url"))]
+use crate::environment::disabled::DisabledGlobal;
 use crate::environment::GlobalsBuilder;
 
+pub(crate) mod artifacts;
 pub(crate) mod breakpoint;
 pub(crate) mod call_stack;
 pub(crate) mod dict;
 pub(crate) mod extra;
 mod funcs;
+pub(crate) mod glob;
+pub(crate) mod graphs;
 pub(crate) mod internal;
 pub(crate) mod json;
 pub(crate) mod list;
+pub(crate) mod math;
 pub(crate) mod partial;
 pub(crate) mod string;
+pub(crate) mod string_helpers;
 pub(crate) mod structs;
+pub(crate) mod template;
+#[cfg(feature = "url")]
+pub(crate) mod url;
+pub(crate) mod version;
 
 pub use extra::PrintHandler;
 
 use crate::stdlib::funcs::globals::register_globals;
 use crate::stdlib::internal::register_internal;
 use crate::values::enumeration::globals::register_enum;
+use crate::values::label::register_label;
 use crate::values::record::globals::register_record;
+use crate::values::types::set::register_set;
 use crate::values::typing;
 
 /// Return the default global environment, it is not yet frozen so that a caller
@@ -60,8 +73,10 @@ pub enum LibraryExtension {
     RecordType,
     /// Definitions to support the `enum` type, the `enum()` constructor.
     EnumType,
+    /// Definitions to support the `set` type, the `set()` constructor.
+    /// Sets become `frozenset` when frozen.
+    SetType,
     /// A function `map(f, xs)` which applies `f` to each element of `xs` and returns the result.
-    // TODO(nga): add set: https://www.internalfb.com/tasks/?t=184017710
     Map,
     /// A function `filter(f, xs)` which applies `f` to each element of `xs` and returns those for which `f` returns `True`.
     /// As a special case, `filter(None, xs)` removes all `None` values.
@@ -94,6 +109,47 @@ pub enum LibraryExtension {
     /// Add a function `call_stack()` which returns a string representation of
     /// the current call stack.
     CallStack,
+    /// Add functions `float_hex(x)` and `float_fromhex(s)` which convert floats
+    /// to and from exact hexadecimal notation, like Python `float.hex()` and `float.fromhex()`.
+    FloatHex,
+    /// Add numeric functions `pow(x, y, z)`, `bin(x)`, `oct(x)`, `hex(x)` and `bit_length(x)`
+    /// which behave like their Python counterparts.
+    Numeric,
+    /// Add a `math` module with functions like `math.floor`, `math.sqrt`, `math.log`,
+    /// trigonometric functions and constants `math.pi` and `math.e`.
+    Math,
+    /// Add a `template` module with `template.substitute` for `$name` placeholders,
+    /// which reports invalid or missing placeholders with their location in the template.
+    Template,
+    /// Add a function `visibility(...)` which restricts which modules can `load`
+    /// the current module.
+    LoadVisibility,
+    /// Add string helpers: `natsort_key(s)` to sort strings in natural order,
+    /// `string_similarity(a, b)`, and a `versions` module with `versions.compare(a, b)`.
+    StringHelpers,
+    /// Add a function `version(s)` which parses a semantic version, supporting comparisons,
+    /// component access and constraint matching with `v.matches(">=1.2, <2")`.
+    Version,
+    /// Add a `url` module with `url.parse`, `url.join` and `url.encode_query`.
+    /// Without the `url` cargo feature, `url` is still defined,
+    /// but calling any of its functions fails.
+    Url,
+    /// Add a `Label` type for build-system target labels like `//pkg:name`,
+    /// with relative resolution and target pattern matching.
+    Label,
+    /// Add functions `glob_match(pattern, path)` and `filter_glob(include, paths)`
+    /// which match paths against Bazel-style `*`/`**` patterns without file system access.
+    Glob,
+    /// Add a `graphs` module with `toposort` and `find_cycle` for graphs
+    /// given as dicts of adjacency lists.
+    Graphs,
+    /// Add functions `has_feature(name)` and `features()` which query the features
+    /// declared with [`GlobalsBuilder::add_feature`].
+    Features,
+    /// Add functions `declare_output(path)` and `write_output(output, content)`
+    /// which declare output files materialized by the host,
+    /// see [`ArtifactRegistry`](crate::eval::ArtifactRegistry).
+    Artifacts,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -102,8 +158,10 @@ impl LibraryExtension {
     pub(crate) fn all() -> &'static [Self] {
         use LibraryExtension::*;
         &[
-            StructType, RecordType, EnumType, Map, Filter, Partial, Debug, Print, Pprint, Pstr,
-            Prepr, Breakpoint, Json, Typing, Internal, CallStack,
+            StructType, RecordType, EnumType, SetType, Map, Filter, Partial, Debug, Print, Pprint,
+            Pstr, Prepr, Breakpoint, Json, Typing, Internal, CallStack, FloatHex, Numeric, Math,
+            Template, LoadVisibility, StringHelpers, Version, Label, Glob, Graphs, Features,
+            Artifacts, Url,
         ]
     }
 
@@ -114,6 +172,7 @@ impl LibraryExtension {
             StructType => structs::global(builder),
             RecordType => register_record(builder),
             EnumType => register_enum(builder),
+            SetType => register_set(builder),
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => partial::partial(builder),
@@ -127,6 +186,22 @@ impl LibraryExtension {
             Typing => typing::globals::register_typing(builder),
             Internal => register_internal(builder),
             CallStack => call_stack::global(builder),
+            FloatHex => extra::float_hex(builder),
+            Numeric => extra::numeric(builder),
+            Math => math::math(builder),
+            Template => template::template(builder),
+            LoadVisibility => extra::load_visibility(builder),
+            StringHelpers => string_helpers::string_helpers(builder),
+            Version => version::version(builder),
+            #[cfg(feature = "url")]
+            Url => url::url(builder),
+            #[cfg(not(feature = "url"))]
+            Url => builder.set("url", DisabledGlobal::new("url", "url")),
+            Label => register_label(builder),
+            Glob => glob::glob(builder),
+            Graphs => graphs::graphs(builder),
+            Features => extra::features(builder),
+            Artifacts => artifacts::artifacts(builder),
         }
     }
 }
@@ -245,4 +320,17 @@ getattr(True2, "invert2")() == False2
 "#,
         );
     }
+
+    #[cfg(not(feature = "url"))]
+    #[test]
+    fn test_url_without_feature() {
+        use crate::stdlib::LibraryExtension;
+
+        let mut a = Assert::new();
+        a.globals(GlobalsBuilder::extended_by(&[LibraryExtension::Url]).build());
+        a.fail(
+            "url.parse('https://example.com')",
+            "`url.parse` is not available because feature `url` is disabled",
+        );
+    }
 }
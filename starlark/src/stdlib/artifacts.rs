@@ -0,0 +1,304 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `artifacts` extension: output files declared by scripts,
+//! with content written by scripts and materialized by the host after evaluation.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+use starlark_map::small_map::SmallMap;
+use starlark_syntax::codemap::FileSpan;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::eval::Evaluator;
+use crate::starlark_simple_value;
+use crate::values::none::NoneType;
+use crate::values::StarlarkValue;
+
+#[derive(Debug, thiserror::Error)]
+enum ArtifactError {
+    #[error("Artifacts are not enabled, the host did not set an artifact registry")]
+    NoRegistry,
+    #[error("Invalid artifact path `{0}`: must be relative and not contain `.` or `..`")]
+    InvalidPath(String),
+    #[error("Artifact `{0}` is already declared")]
+    AlreadyDeclared(String),
+    #[error("Artifact `{0}` is not declared in this evaluation")]
+    NotDeclared(String),
+    #[error("Artifact `{0}` is already written")]
+    AlreadyWritten(String),
+}
+
+/// Handle to an output declared with `declare_output`.
+///
+/// The handle only holds the path, so it can be frozen and returned from modules,
+/// the content is stored in the [`ArtifactRegistry`].
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct Artifact {
+    path: String,
+}
+
+starlark_simple_value!(Artifact);
+
+impl Artifact {
+    /// Path of the output, relative to a directory chosen by the host.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<artifact {}>", self.path)
+    }
+}
+
+#[starlark_value(type = "artifact")]
+impl<'v> StarlarkValue<'v> for Artifact {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(artifact_methods)
+    }
+}
+
+#[starlark_module]
+fn artifact_methods(builder: &mut MethodsBuilder) {
+    /// Path of the output, as given to `declare_output`.
+    #[starlark(attribute)]
+    fn path(this: &Artifact) -> anyhow::Result<String> {
+        Ok(this.path.clone())
+    }
+}
+
+/// Output declared by a script, collected with [`ArtifactRegistry::into_artifacts`].
+#[derive(Debug, Clone)]
+pub struct DeclaredArtifact {
+    path: String,
+    location: Option<FileSpan>,
+    content: Option<String>,
+}
+
+impl DeclaredArtifact {
+    /// Path of the output, relative to a directory chosen by the host.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Where `declare_output` was called.
+    pub fn location(&self) -> Option<&FileSpan> {
+        self.location.as_ref()
+    }
+
+    /// Content written with `write_output`, or `None` if the script did not write it.
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+}
+
+/// Outputs declared by scripts during evaluation.
+///
+/// Installed with [`Evaluator::set_artifact_registry`]; without a registry,
+/// `declare_output` and `write_output` fail, so scripts can only create outputs
+/// when the host opts in. The same registry can be used for several evaluations.
+///
+/// ```
+/// use starlark::environment::GlobalsBuilder;
+/// use starlark::environment::LibraryExtension;
+/// use starlark::environment::Module;
+/// use starlark::eval::ArtifactRegistry;
+/// use starlark::eval::Evaluator;
+/// use starlark::syntax::AstModule;
+/// use starlark::syntax::Dialect;
+///
+/// let program = r#"
+/// out = declare_output("gen/hello.txt")
+/// write_output(out, "Hello")
+/// "#;
+/// let globals = GlobalsBuilder::extended_by(&[LibraryExtension::Artifacts]).build();
+/// let registry = ArtifactRegistry::new();
+/// let module = Module::new();
+/// {
+///     let mut eval = Evaluator::new(&module);
+///     eval.set_artifact_registry(&registry);
+///     let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Standard).unwrap();
+///     eval.eval_module(ast, &globals).unwrap();
+/// }
+/// let artifacts = registry.into_artifacts();
+/// assert_eq!("gen/hello.txt", artifacts[0].path());
+/// assert_eq!(Some("Hello"), artifacts[0].content());
+/// ```
+#[derive(Debug, Default)]
+pub struct ArtifactRegistry {
+    artifacts: RefCell<SmallMap<String, DeclaredArtifact>>,
+}
+
+impl ArtifactRegistry {
+    /// Create an empty registry.
+    pub fn new() -> ArtifactRegistry {
+        ArtifactRegistry::default()
+    }
+
+    fn declare(&self, path: &str, location: Option<FileSpan>) -> anyhow::Result<Artifact> {
+        if path.starts_with('/')
+            || path
+                .split('/')
+                .any(|c| c.is_empty() || c == "." || c == "..")
+        {
+            return Err(ArtifactError::InvalidPath(path.to_owned()).into());
+        }
+        let mut artifacts = self.artifacts.borrow_mut();
+        if artifacts.contains_key(path) {
+            return Err(ArtifactError::AlreadyDeclared(path.to_owned()).into());
+        }
+        artifacts.insert(
+            path.to_owned(),
+            DeclaredArtifact {
+                path: path.to_owned(),
+                location,
+                content: None,
+            },
+        );
+        Ok(Artifact {
+            path: path.to_owned(),
+        })
+    }
+
+    fn write(&self, artifact: &Artifact, content: &str) -> anyhow::Result<()> {
+        let mut artifacts = self.artifacts.borrow_mut();
+        let declared = artifacts
+            .get_mut(artifact.path.as_str())
+            .ok_or_else(|| ArtifactError::NotDeclared(artifact.path.clone()))?;
+        if declared.content.is_some() {
+            return Err(ArtifactError::AlreadyWritten(artifact.path.clone()).into());
+        }
+        declared.content = Some(content.to_owned());
+        Ok(())
+    }
+
+    /// Declared outputs, in declaration order.
+    pub fn into_artifacts(self) -> Vec<DeclaredArtifact> {
+        self.artifacts.into_inner().into_values().collect()
+    }
+}
+
+#[starlark_module]
+pub(crate) fn artifacts(builder: &mut GlobalsBuilder) {
+    /// Declare an output file with a path relative to a directory chosen by the host,
+    /// returning a handle to write it with `write_output`.
+    ///
+    /// Fails if the host did not enable artifacts, or the path is already declared.
+    fn declare_output(
+        #[starlark(require = pos)] path: &str,
+        eval: &mut Evaluator,
+    ) -> anyhow::Result<Artifact> {
+        let location = eval.call_stack_top_location();
+        eval.artifact_registry
+            .ok_or(ArtifactError::NoRegistry)?
+            .declare(path, location)
+    }
+
+    /// Set the content of an output declared with `declare_output`.
+    /// Each output can be written once.
+    fn write_output(
+        #[starlark(require = pos)] output: &Artifact,
+        #[starlark(require = pos)] content: &str,
+        eval: &mut Evaluator,
+    ) -> anyhow::Result<NoneType> {
+        eval.artifact_registry
+            .ok_or(ArtifactError::NoRegistry)?
+            .write(output, content)?;
+        Ok(NoneType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::LibraryExtension;
+    use crate::environment::Module;
+    use crate::eval::ArtifactRegistry;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn eval(registry: &ArtifactRegistry, program: &str) -> crate::Result<()> {
+        let globals = GlobalsBuilder::extended_by(&[LibraryExtension::Artifacts]).build();
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.set_artifact_registry(registry);
+        let ast = AstModule::parse("x.star", program.to_owned(), &Dialect::Extended)?;
+        eval.eval_module(ast, &globals)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_artifacts() {
+        let registry = ArtifactRegistry::new();
+        eval(
+            &registry,
+            r#"
+def gen(name):
+    out = declare_output("gen/" + name)
+    write_output(out, name.upper())
+    return out
+a = gen("a")
+if a.path != "gen/a" or type(a) != "artifact":
+    fail(a)
+declare_output("b")
+"#,
+        )
+        .unwrap();
+        let artifacts = registry.into_artifacts();
+        assert_eq!(2, artifacts.len());
+        assert_eq!("gen/a", artifacts[0].path());
+        assert_eq!(Some("A"), artifacts[0].content());
+        assert_eq!(
+            "x.star:3:11-40",
+            artifacts[0].location().unwrap().to_string()
+        );
+        assert_eq!(None, artifacts[1].content());
+    }
+
+    #[test]
+    fn test_artifact_errors() {
+        let registry = ArtifactRegistry::new();
+        let err = |program| eval(&registry, program).unwrap_err().to_string();
+        assert!(err("declare_output('../x')").contains("Invalid artifact path"));
+        assert!(err("declare_output('/x')").contains("Invalid artifact path"));
+        assert!(err("declare_output('x')\ndeclare_output('x')").contains("already declared"));
+        assert!(
+            err("y = declare_output('y')\nwrite_output(y, '')\nwrite_output(y, '')")
+                .contains("already written")
+        );
+
+        let mut a = Assert::new();
+        a.globals_add(|g| LibraryExtension::Artifacts.add(g));
+        a.fail("declare_output('x')", "Artifacts are not enabled");
+    }
+}
@@ -368,6 +368,7 @@ pub(crate) fn dict_methods(registry: &mut MethodsBuilder) {
 #[cfg(test)]
 mod tests {
     use crate::assert;
+    use crate::assert::Assert;
 
     #[test]
     fn test_error_codes() {
@@ -385,7 +386,25 @@ mod tests {
         // In Starlark spec this is a runtime error. In Python it's fine.
         // We make it a runtime error, plus have a lint that checks for it statically.
         assert::fails("{40+2: 2, 6*7: 3}", &["key repeated", "42"]);
-        // Also check we fail if the entire dictionary is static (a different code path).
+        // Duplicate constant keys are a compile-time error.
         assert::fails("{42: 2, 42: 3}", &["key repeated", "42"]);
+        assert::fail(
+            "def f():\n    return {'a': 1, 'b': 2, 'a': 3}",
+            "Dictionary key repeated for `\"a\"`, first used at assert.bzl:2:13-16",
+        );
+        assert::fail("def f():\n    return {1: 1, 1.0: 2}", "key repeated");
+        assert::pass("{1: 1, 1.5: 2, '1': 3}");
+    }
+
+    #[test]
+    fn test_dict_with_duplicates_allowed() {
+        let mut a = Assert::new();
+        a.dialect_set(|d| d.enable_duplicate_dict_keys = true);
+        a.eq("{'a': 2, 'b': 3}", "{'a': 1, 'b': 3, 'a': 2}");
+        a.eq("[1, 1.5]", "list({1: 'x', 1.5: 'y', 1.0: 'z'})");
+        a.eq("{42: 3}", "{40+2: 2, 6*7: 3}");
+        a.eq("{1: 2}", "{1: 2}");
+        a.eq("{'a': 1, 'b': 2}", "{'a': 1, 'b': 2}");
+        a.pass("def f(): return 1\nassert_eq({f(): 1, f(): 2}, {1: 2})");
     }
 }
@@ -17,16 +17,24 @@
 
 use std::fmt;
 
+use either::Either;
 use itertools::Itertools;
 use starlark_derive::starlark_module;
 
 use crate as starlark;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
+use crate::values::float::StarlarkFloat;
 use crate::values::function::StarlarkFunction;
+use crate::values::list_or_tuple::UnpackListOrTuple;
 use crate::values::none::NoneOr;
 use crate::values::none::NoneType;
+use crate::values::num::value::Num;
+use crate::values::num::value::NumRef;
+use crate::values::string::repr::string_repr;
 use crate::values::tuple::UnpackTuple;
+use crate::values::types::float::float;
+use crate::values::types::int_or_big::StarlarkIntRef;
 use crate::values::typing::iter::StarlarkIter;
 use crate::values::StringValue;
 use crate::values::Value;
@@ -102,6 +110,130 @@ pub fn debug(builder: &mut GlobalsBuilder) {
     }
 }
 
+#[starlark_module]
+pub fn float_hex(builder: &mut GlobalsBuilder) {
+    /// Format a float in hexadecimal notation, like Python `float.hex()`.
+    ///
+    /// The result is exact, and can be converted back with `float_fromhex`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// float_hex(1.5) == "0x1.8000000000000p+0"
+    /// float_hex(-0.0) == "-0x0.0p+0"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn float_hex(#[starlark(require = pos)] x: StarlarkFloat) -> anyhow::Result<String> {
+        let mut res = String::new();
+        float::write_hex(&mut res, x.0)?;
+        Ok(res)
+    }
+
+    /// Parse a float in hexadecimal notation, like Python `float.fromhex()`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// float_fromhex("0x1.8p1") == 3.0
+    /// float_fromhex("-0x1p-2") == -0.25
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn float_fromhex(#[starlark(require = pos)] s: &str) -> anyhow::Result<f64> {
+        match float::parse_hex(s) {
+            Some(f) => Ok(f),
+            None => {
+                let mut repr = String::new();
+                string_repr(s, &mut repr);
+                Err(anyhow::anyhow!(
+                    "{} is not a valid hexadecimal floating-point number",
+                    repr
+                ))
+            }
+        }
+    }
+}
+
+#[starlark_module]
+pub fn numeric(builder: &mut GlobalsBuilder) {
+    /// Raise `x` to the power `y`, optionally modulo `z`, like Python `pow`.
+    ///
+    /// `pow(x, y)` is an integer if both arguments are integers and `y` is non-negative,
+    /// otherwise a float. `pow(x, y, z)` requires all arguments to be integers,
+    /// and is computed more efficiently than `pow(x, y) % z`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// pow(2, 10) == 1024
+    /// pow(2, -1) == 0.5
+    /// pow(4.0, 0.5) == 2.0
+    /// pow(3, 200, 7) == 2
+    /// pow(-2, 3, 5) == 2
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn pow(
+        #[starlark(require = pos)] x: NumRef,
+        #[starlark(require = pos)] y: NumRef,
+        #[starlark(require = pos)] z: Option<NumRef>,
+    ) -> anyhow::Result<Num> {
+        x.pow(y, z)
+    }
+
+    /// Format an integer in binary with a `0b` prefix, like Python `bin`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// bin(10) == "0b1010"
+    /// bin(-3) == "-0b11"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn bin(#[starlark(require = pos)] x: StarlarkIntRef) -> anyhow::Result<String> {
+        Ok(x.to_str_radix_prefixed(2, "0b"))
+    }
+
+    /// Format an integer in octal with a `0o` prefix, like Python `oct`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// oct(8) == "0o10"
+    /// oct(-8) == "-0o10"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn oct(#[starlark(require = pos)] x: StarlarkIntRef) -> anyhow::Result<String> {
+        Ok(x.to_str_radix_prefixed(8, "0o"))
+    }
+
+    /// Format an integer in lowercase hexadecimal with a `0x` prefix, like Python `hex`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// hex(255) == "0xff"
+    /// hex(-256) == "-0x100"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn hex(#[starlark(require = pos)] x: StarlarkIntRef) -> anyhow::Result<String> {
+        Ok(x.to_str_radix_prefixed(16, "0x"))
+    }
+
+    /// Number of bits required to represent an integer in binary,
+    /// excluding the sign and leading zeros, like Python `int.bit_length()`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// bit_length(0) == 0
+    /// bit_length(255) == 8
+    /// bit_length(-256) == 9
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn bit_length(#[starlark(require = pos)] x: StarlarkIntRef) -> anyhow::Result<u64> {
+        Ok(x.bit_length())
+    }
+}
+
 struct PrintWrapper<'a, 'b>(&'a Vec<Value<'b>>);
 impl fmt::Display for PrintWrapper<'_, '_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -197,6 +329,63 @@ pub fn prepr(builder: &mut GlobalsBuilder) {
     }
 }
 
+#[starlark_module]
+pub fn load_visibility(builder: &mut GlobalsBuilder) {
+    /// Restrict which modules can `load` the current module.
+    ///
+    /// Takes `"public"`, `"private"` or a list of patterns: a module name,
+    /// a prefix ending with `...` such as `"rules/..."`, or `"public"`.
+    /// A module loading the current module from outside these patterns
+    /// fails at its `load` statement. Can be called at most once per module.
+    fn visibility<'v>(
+        #[starlark(require = pos)] visibility: Either<&str, UnpackListOrTuple<String>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<NoneType> {
+        let visibility = match visibility {
+            Either::Left("public") => vec!["public".to_owned()],
+            Either::Left("private") => Vec::new(),
+            Either::Left(s) => {
+                let mut repr = String::new();
+                string_repr(s, &mut repr);
+                return Err(anyhow::anyhow!(
+                    "Expecting `\"public\"`, `\"private\"` or a list of patterns, got {}",
+                    repr
+                ));
+            }
+            Either::Right(patterns) => patterns.items,
+        };
+        eval.module().set_load_visibility(visibility)?;
+        Ok(NoneType)
+    }
+}
+
+#[starlark_module]
+pub fn features(builder: &mut GlobalsBuilder) {
+    /// Check whether the host declared the feature,
+    /// typically meaning that optional globals are available.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// not has_feature("no-such-feature")
+    /// # "#);
+    /// ```
+    fn has_feature<'v>(
+        #[starlark(require = pos)] name: &str,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<bool> {
+        Ok(eval.caller_globals().has_feature(name))
+    }
+
+    /// Features declared by the host, sorted.
+    fn features<'v>(eval: &mut Evaluator<'v, '_, '_>) -> anyhow::Result<Vec<String>> {
+        Ok(eval
+            .caller_globals()
+            .features()
+            .map(|f| f.to_owned())
+            .collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::cell::RefCell;
@@ -205,7 +394,10 @@ mod tests {
     use dupe::Dupe;
 
     use crate::assert;
+    use crate::assert::test_functions;
     use crate::assert::Assert;
+    use crate::environment::GlobalsBuilder;
+    use crate::environment::LibraryExtension;
     use crate::stdlib::PrintHandler;
 
     #[test]
@@ -309,4 +501,132 @@ assert_eq(prepr("abcd"), "\"abcd\"")
 "#,
         );
     }
+
+    #[test]
+    fn test_float_hex() {
+        assert::pass(
+            r#"
+assert_eq(float_hex(0.1), "0x1.999999999999ap-4")
+assert_eq(float_fromhex(float_hex(0.1)), 0.1)
+assert_eq(float_fromhex("0x1.999999999999ap-4"), 0.1)
+assert_eq(str(float_fromhex("inf")), "+inf")
+"#,
+        );
+        assert::fail(
+            "float_fromhex('0x1.8q')",
+            "is not a valid hexadecimal floating-point number",
+        );
+    }
+
+    #[test]
+    fn test_numeric() {
+        assert::pass(
+            r#"
+assert_eq(pow(0, 0), 1)
+assert_eq(pow(-1, 3), -1)
+assert_eq(pow(-1, 4), 1)
+assert_eq(pow(2, 100), 1267650600228229401496703205376)
+assert_eq(pow(2, 100, 1000000007), 976371285)
+assert_eq(pow(2, 3, -5), -2)
+assert_eq(pow(3, 2, -5), -1)
+assert_eq(pow(-3, 3, 5), 3)
+assert_eq(pow(-3, 3, -5), -2)
+assert_eq(pow(2.0, 3), 8.0)
+assert_eq(bin(0), "0b0")
+assert_eq(hex(1267650600228229401496703205376), "0x10000000000000000000000000")
+assert_eq(bit_length(1267650600228229401496703205376), 101)
+assert_eq(bit_length(-2147483648), 32)
+"#,
+        );
+        assert::fail("pow(2, 3, 0)", "Modulo by zero");
+        assert::fail("pow(2, -3, 5)", "Negative exponent with modulus");
+        assert::fail("pow(2.0, 3, 5)", "requires all arguments to be integers");
+        assert::fail("pow(0, -1)", "zero cannot be raised to a negative power");
+        assert::fail("pow(-8, 1.0 / 3)", "is not a real number");
+        assert::fail("pow(3, 10000000)", "Integer overflow computing pow");
+    }
+
+    #[test]
+    fn test_load_visibility() {
+        let mut a = Assert::new();
+        a.module("exact", "visibility(['assert.bzl'])\nx = 1");
+        a.module("prefix", "visibility(['other.bzl', 'ass...'])\nx = 2");
+        a.module("public", "visibility('public')\nx = 3");
+        a.module("private", "visibility('private')\nx = 4");
+        a.module("elsewhere", "visibility(['rules/...'])\nx = 5");
+        a.pass("load('exact', 'x')\nassert_eq(1, x)");
+        a.pass("load('prefix', 'x')\nassert_eq(2, x)");
+        a.pass("load('public', 'x')\nassert_eq(3, x)");
+        a.fail(
+            "load('private', 'x')",
+            "Module `private` cannot be loaded from `assert.bzl`, its load visibility is []",
+        );
+        a.fail(
+            "load('elsewhere', 'x')",
+            r#"Module `elsewhere` cannot be loaded from `assert.bzl`, its load visibility is ["rules/..."]"#,
+        );
+        a.fail("visibility('everyone')", "got \"everyone\"");
+        a.fail(
+            "visibility('public')\nvisibility('private')",
+            "Load visibility can only be set once per module",
+        );
+    }
+
+    #[test]
+    fn test_features() {
+        let mut a = Assert::new();
+        a.globals_add(|g| {
+            LibraryExtension::Features.add(g);
+            g.feature("question", true, |g| g.set("question", "?"));
+            g.feature("answer", false, |g| g.set("answer", 42));
+            g.add_feature("fast");
+        });
+        a.eq("['fast', 'question']", "features()");
+        a.pass(
+            r#"
+def check():
+    return has_feature("question") and not has_feature("answer")
+assert_true(check())
+"#,
+        );
+        a.fail(
+            "answer()",
+            "`answer` is not available because feature `answer` is disabled",
+        );
+    }
+
+    #[test]
+    fn test_disabled_feature() {
+        let mut a = Assert::new();
+        a.globals(
+            GlobalsBuilder::extended_by(&[LibraryExtension::Features])
+                .with(test_functions)
+                .with_feature("json", false, |g| LibraryExtension::Json.add(g))
+                .build(),
+        );
+        a.pass(
+            r#"
+def encode(x):
+    if has_feature("json"):
+        return json.encode(x)
+    return repr(x)
+assert_eq(encode([1]), "[1]")
+"#,
+        );
+        a.fail(
+            "json.encode([1])",
+            "`json.encode` is not available because feature `json` is disabled",
+        );
+        a.fail(
+            "json()",
+            "`json` is not available because feature `json` is disabled",
+        );
+    }
+
+    #[test]
+    fn test_disabled_feature_keeps_defined() {
+        let mut a = Assert::new();
+        a.globals_extend(|g| g.feature("json", false, |g| LibraryExtension::Json.add(g)));
+        a.eq("'[1]'", "json.encode([1])");
+    }
 }
@@ -28,6 +28,7 @@ use starlark_derive::starlark_module;
 use crate as starlark;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
+use crate::message::MessageTemplate;
 use crate::values::bool::StarlarkBool;
 use crate::values::float::StarlarkFloat;
 use crate::values::function::SpecialBuiltinFunction;
@@ -88,9 +89,14 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
                 None => x.collect_repr(&mut s),
             }
         }
-        Err(starlark::Error::new(starlark::ErrorKind::Fail(
-            anyhow::Error::msg(s),
-        )))
+        let message_template = MessageTemplate::new(
+            "fail",
+            vec![s.strip_prefix(' ').unwrap_or_default().to_owned()],
+        );
+        Err(
+            starlark::Error::new(starlark::ErrorKind::Fail(anyhow::Error::msg(s)))
+                .with_message_template(message_template),
+        )
     }
 
     /// Take the absolute value of an int.
@@ -366,7 +372,7 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
             Some(v) => Ok(v),
             None => match default {
                 Some(x) => Ok(x),
-                None => ValueError::unsupported_owned(a.get_type(), &format!(".{}", attr), None),
+                None => Err(ValueError::no_attr(a, attr)),
             },
         }
     }
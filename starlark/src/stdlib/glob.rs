@@ -0,0 +1,294 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `glob_match` and `filter_glob` functions.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::list::AllocList;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::StringValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum GlobError {
+    #[error("Invalid glob pattern `{0}`: {1}")]
+    InvalidPattern(String, &'static str),
+}
+
+/// Options controlling how patterns match.
+#[derive(Debug, Clone, Copy)]
+struct GlobOptions {
+    /// Compare letters case-sensitively.
+    case_sensitive: bool,
+    /// Let wildcards match path segments starting with `.`.
+    hidden: bool,
+}
+
+#[derive(Debug)]
+enum Segment {
+    /// `**`, any number of path segments.
+    Recursive,
+    /// A single path segment, possibly with `*` wildcards.
+    Pattern(String),
+}
+
+/// A compiled pattern like `src/**/*.rs`.
+#[derive(Debug)]
+struct Glob {
+    segments: Vec<Segment>,
+    options: GlobOptions,
+}
+
+/// Match a single path segment against a pattern where `*` matches any characters.
+fn match_segment(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let name = name.as_bytes();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern, and the name position it was tried at.
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, n));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == name[n] {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|b| *b == b'*')
+}
+
+impl Glob {
+    fn new(pattern: &str, options: GlobOptions) -> anyhow::Result<Glob> {
+        let err = |msg| GlobError::InvalidPattern(pattern.to_owned(), msg);
+        if pattern.is_empty() {
+            return Err(err("pattern is empty").into());
+        }
+        if pattern.starts_with('/') {
+            return Err(err("pattern must be relative").into());
+        }
+        let pattern = if options.case_sensitive {
+            pattern.to_owned()
+        } else {
+            pattern.to_lowercase()
+        };
+        let mut segments = Vec::new();
+        for segment in pattern.split('/') {
+            match segment {
+                "" => return Err(err("pattern contains an empty path segment").into()),
+                "." | ".." => return Err(err("pattern contains `.` or `..`").into()),
+                "**" => {
+                    // Consecutive `**` are equivalent to one.
+                    if !matches!(segments.last(), Some(Segment::Recursive)) {
+                        segments.push(Segment::Recursive);
+                    }
+                }
+                _ if segment.contains("**") => {
+                    return Err(err("`**` must be a whole path segment").into());
+                }
+                _ => segments.push(Segment::Pattern(segment.to_owned())),
+            }
+        }
+        Ok(Glob { segments, options })
+    }
+
+    fn matches_name(&self, pattern: &str, name: &str) -> bool {
+        if !self.options.hidden && name.starts_with('.') && !pattern.starts_with('.') {
+            return false;
+        }
+        match_segment(pattern, name)
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let lowercase;
+        let path = if self.options.case_sensitive {
+            path
+        } else {
+            lowercase = path.to_lowercase();
+            &lowercase
+        };
+        let names: Vec<&str> = path.split('/').collect();
+        let (mut s, mut n) = (0, 0);
+        // Position of the last `**` in the segments, and the name position it was tried at.
+        let mut recursive = None;
+        while n < names.len() {
+            match self.segments.get(s) {
+                Some(Segment::Recursive) => {
+                    recursive = Some((s, n));
+                    s += 1;
+                    continue;
+                }
+                Some(Segment::Pattern(pattern)) if self.matches_name(pattern, names[n]) => {
+                    s += 1;
+                    n += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            // Backtrack: let the last `**` consume one more name.
+            match recursive {
+                Some((rec_s, rec_n)) if self.options.hidden || !names[rec_n].starts_with('.') => {
+                    s = rec_s + 1;
+                    n = rec_n + 1;
+                    recursive = Some((rec_s, rec_n + 1));
+                }
+                _ => return false,
+            }
+        }
+        self.segments[s..]
+            .iter()
+            .all(|s| matches!(s, Segment::Recursive))
+    }
+}
+
+#[starlark_module]
+pub(crate) fn glob(builder: &mut GlobalsBuilder) {
+    /// Check whether a `/`-separated path matches a Bazel-style glob pattern,
+    /// without accessing the file system.
+    ///
+    /// `*` matches any characters within a path segment, and a `**` segment
+    /// matches any number of segments, including none.
+    /// With `case_sensitive = False` letters are compared ignoring case,
+    /// and with `hidden = False` wildcards don't match segments starting with `.`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// glob_match("src/**/*.rs", "src/a/b/lib.rs")
+    /// glob_match("src/**/*.rs", "src/lib.rs")
+    /// not glob_match("*.rs", "src/lib.rs")
+    /// not glob_match("**/*.py", "a/.hidden/x.py", hidden = False)
+    /// glob_match("*.TXT", "notes.txt", case_sensitive = False)
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn glob_match(
+        #[starlark(require = pos)] pattern: &str,
+        #[starlark(require = pos)] path: &str,
+        #[starlark(require = named, default = true)] case_sensitive: bool,
+        #[starlark(require = named, default = true)] hidden: bool,
+    ) -> anyhow::Result<bool> {
+        let options = GlobOptions {
+            case_sensitive,
+            hidden,
+        };
+        Ok(Glob::new(pattern, options)?.matches(path))
+    }
+
+    /// Return the paths in `paths` which match any of the `include` patterns
+    /// and none of the `exclude` patterns, in their original order.
+    ///
+    /// Patterns and options are the same as for `glob_match`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// filter_glob(["**/*.rs"], ["a.rs", "b.py", "t/c.rs"], exclude = ["t/**"]) == ["a.rs"]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn filter_glob<'v>(
+        #[starlark(require = pos)] include: UnpackListOrTuple<&str>,
+        #[starlark(require = pos)] paths: UnpackListOrTuple<StringValue<'v>>,
+        #[starlark(require = named, default = UnpackListOrTuple::default())]
+        exclude: UnpackListOrTuple<&str>,
+        #[starlark(require = named, default = true)] case_sensitive: bool,
+        #[starlark(require = named, default = true)] hidden: bool,
+    ) -> anyhow::Result<AllocList<Vec<Value<'v>>>> {
+        let options = GlobOptions {
+            case_sensitive,
+            hidden,
+        };
+        let compile = |patterns: Vec<&str>| {
+            patterns
+                .into_iter()
+                .map(|p| Glob::new(p, options))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        let include = compile(include.items)?;
+        let exclude = compile(exclude.items)?;
+        Ok(AllocList(
+            paths
+                .items
+                .into_iter()
+                .filter(|path| {
+                    include.iter().any(|g| g.matches(path.as_str()))
+                        && !exclude.iter().any(|g| g.matches(path.as_str()))
+                })
+                .map(|path| path.to_value())
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_glob_match() {
+        assert::pass(
+            r#"
+def m(p, s, **kwargs):
+    return glob_match(p, s, **kwargs)
+assert_true(m("a", "a"))
+assert_false(m("a", "a/b"))
+assert_true(m("*", "abc"))
+assert_true(m("*", ""))
+assert_false(m("*", "a/b"))
+assert_true(m("a*c*", "abxcy"))
+assert_false(m("a*c", "abxcy"))
+assert_true(m("**", "a/b/c"))
+assert_true(m("a/**", "a"))
+assert_true(m("**/b/**/d", "a/b/c/b/d"))
+assert_true(m("**/**/x", "x"))
+assert_false(m("**/b", "a/bb"))
+assert_true(m("*.java", ".x.java"))
+assert_false(m("*.java", ".x.java", hidden = False))
+assert_true(m(".*", ".x", hidden = False))
+assert_false(m("**/x", ".a/x", hidden = False))
+assert_false(m("Ab", "aB"))
+assert_true(m("Ab", "aB", case_sensitive = False))
+"#,
+        );
+        for pattern in ["", "/a", "a//b", "a/**b", "a/../b"] {
+            assert::fail(
+                &format!("glob_match({:?}, 'a')", pattern),
+                "Invalid glob pattern",
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_glob() {
+        assert::pass(
+            r#"
+files = ["BUILD", "a.cc", "a.h", "test/a_test.cc", "x/y.cc"]
+assert_eq(["a.cc", "test/a_test.cc", "x/y.cc"], filter_glob(["**/*.cc"], files))
+assert_eq(["a.cc", "a.h", "x/y.cc"], filter_glob(("**/*.cc", "*.h"), files, exclude = ["test/**"]))
+assert_eq([], filter_glob([], files))
+"#,
+        );
+    }
+}
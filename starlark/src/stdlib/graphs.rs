@@ -0,0 +1,247 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `graphs` extension: ordering and cycle detection
+//! on graphs given as dicts of adjacency lists.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use starlark_derive::starlark_module;
+use starlark_map::small_set::SmallSet;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::dict::DictRef;
+use crate::values::list::AllocList;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::none::NoneOr;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum GraphsError {
+    #[error("Graph contains a cycle: {0}")]
+    Cycle(String),
+}
+
+/// A graph with nodes numbered in order of first appearance,
+/// first as dict keys, then in the adjacency lists.
+struct Graph<'v> {
+    nodes: SmallSet<Value<'v>>,
+    edges: Vec<Vec<usize>>,
+}
+
+impl<'v> Graph<'v> {
+    fn new(graph: DictRef<'v>) -> crate::Result<Graph<'v>> {
+        let mut nodes = SmallSet::new();
+        for (node, _) in graph.iter_hashed() {
+            nodes.insert_hashed(node);
+        }
+        let mut edges = vec![Vec::new(); nodes.len()];
+        for (i, (_, successors)) in graph.iter_hashed().enumerate() {
+            for successor in UnpackListOrTuple::<Value>::unpack_value_err(successors)? {
+                let successor = successor.get_hashed()?;
+                nodes.insert_hashed(successor);
+                edges[i].push(nodes.get_index_of_hashed_by_value(successor).unwrap());
+            }
+        }
+        edges.resize(nodes.len(), Vec::new());
+        Ok(Graph { nodes, edges })
+    }
+
+    fn node(&self, i: usize) -> Value<'v> {
+        *self.nodes.get_index(i).unwrap()
+    }
+
+    /// Nodes such that each node comes after all the nodes it has edges to.
+    /// Among nodes which could come next, the one which appeared first wins.
+    /// Returns `None` if the graph has a cycle.
+    fn toposort(&self) -> Option<Vec<usize>> {
+        let mut pending: Vec<usize> = self.edges.iter().map(|e| e.len()).collect();
+        let mut dependents = vec![Vec::new(); self.edges.len()];
+        for (i, successors) in self.edges.iter().enumerate() {
+            for &j in successors {
+                dependents[j].push(i);
+            }
+        }
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..pending.len())
+            .filter(|&i| pending[i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(pending.len());
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(i);
+            for &d in &dependents[i] {
+                pending[d] -= 1;
+                if pending[d] == 0 {
+                    ready.push(Reverse(d));
+                }
+            }
+        }
+        (order.len() == pending.len()).then_some(order)
+    }
+
+    /// The first cycle found by a depth-first search, as a path which starts
+    /// and ends at the same node.
+    fn find_cycle(&self) -> Option<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Unvisited,
+            OnStack,
+            Done,
+        }
+
+        let mut state = vec![State::Unvisited; self.edges.len()];
+        for root in 0..self.edges.len() {
+            if state[root] != State::Unvisited {
+                continue;
+            }
+            // Each entry is a node and the index of the next edge to follow.
+            let mut stack = vec![(root, 0)];
+            state[root] = State::OnStack;
+            while let Some((node, edge)) = stack.last_mut() {
+                let node = *node;
+                match self.edges[node].get(*edge) {
+                    None => {
+                        state[node] = State::Done;
+                        stack.pop();
+                    }
+                    Some(&next) => {
+                        *edge += 1;
+                        match state[next] {
+                            State::Unvisited => {
+                                state[next] = State::OnStack;
+                                stack.push((next, 0));
+                            }
+                            State::OnStack => {
+                                let start = stack.iter().position(|(n, _)| *n == next).unwrap();
+                                let mut cycle: Vec<usize> =
+                                    stack[start..].iter().map(|(n, _)| *n).collect();
+                                cycle.push(next);
+                                return Some(cycle);
+                            }
+                            State::Done => {}
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn values(&self, indices: Vec<usize>) -> AllocList<Vec<Value<'v>>> {
+        AllocList(indices.into_iter().map(|i| self.node(i)).collect())
+    }
+}
+
+#[starlark_module]
+fn graphs_members(globals: &mut GlobalsBuilder) {
+    /// Sort the nodes of a graph so that every node comes after the nodes it
+    /// points to, e.g. after its dependencies.
+    ///
+    /// The graph is a dict from each node to a list of the nodes it points to.
+    /// Nodes which only appear in those lists are included too.
+    /// The result is deterministic: whenever several nodes could come next,
+    /// the one which appears first in the graph is chosen.
+    /// Fails if the graph contains a cycle.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// graphs.toposort({"app": ["lib", "util"], "lib": ["util"]}) == ["util", "lib", "app"]
+    /// graphs.toposort({"b": [], "a": []}) == ["b", "a"]
+    /// # "#);
+    /// ```
+    fn toposort<'v>(
+        #[starlark(require = pos)] graph: DictRef<'v>,
+    ) -> starlark::Result<AllocList<Vec<Value<'v>>>> {
+        let graph = Graph::new(graph)?;
+        match graph.toposort() {
+            Some(order) => Ok(graph.values(order)),
+            None => {
+                let cycle = graph
+                    .find_cycle()
+                    .expect("toposort failed, so there must be a cycle");
+                let path: Vec<String> =
+                    cycle.into_iter().map(|i| graph.node(i).to_repr()).collect();
+                Err(anyhow::Error::new(GraphsError::Cycle(path.join(" -> "))).into())
+            }
+        }
+    }
+
+    /// Find a cycle in a graph given as a dict from each node to a list of the
+    /// nodes it points to.
+    ///
+    /// Returns the cycle as a list of nodes starting and ending with the same
+    /// node, or `None` if the graph has no cycles.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// graphs.find_cycle({"a": ["b"], "b": ["c"], "c": ["b"]}) == ["b", "c", "b"]
+    /// graphs.find_cycle({"a": ["b"]}) == None
+    /// # "#);
+    /// ```
+    fn find_cycle<'v>(
+        #[starlark(require = pos)] graph: DictRef<'v>,
+    ) -> starlark::Result<NoneOr<AllocList<Vec<Value<'v>>>>> {
+        let graph = Graph::new(graph)?;
+        Ok(match graph.find_cycle() {
+            Some(cycle) => NoneOr::Other(graph.values(cycle)),
+            None => NoneOr::None,
+        })
+    }
+}
+
+pub(crate) fn graphs(globals: &mut GlobalsBuilder) {
+    globals.struct_("graphs", graphs_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_toposort() {
+        assert::pass(
+            r#"
+assert_eq([], graphs.toposort({}))
+assert_eq(["c", "b", "a"], graphs.toposort({"a": ["b"], "b": ["c"]}))
+assert_eq(["d", "b", "c", "a"], graphs.toposort({"a": ["b", "c"], "b": ["d"], "c": ["d"]}))
+assert_eq(["x", "y", "z"], graphs.toposort({"x": [], "y": (), "z": []}))
+assert_eq([2, 1, 3], graphs.toposort({1: [2, 2], 3: [2]}))
+"#,
+        );
+        assert::fail(
+            r#"graphs.toposort({"a": ["b"], "b": ["c"], "c": ["a"], "d": []})"#,
+            r#"Graph contains a cycle: "a" -> "b" -> "c" -> "a""#,
+        );
+        assert::fail(r#"graphs.toposort({"a": "b"})"#, "but got `string");
+        assert::fail(r#"graphs.toposort({"a": [[]]})"#, "not hashable");
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        assert::pass(
+            r#"
+assert_eq(None, graphs.find_cycle({}))
+assert_eq(None, graphs.find_cycle({"a": ["b", "c"], "b": ["c"]}))
+assert_eq(["a", "a"], graphs.find_cycle({"a": ["a"]}))
+assert_eq(["b", "c", "b"], graphs.find_cycle({"a": ["b"], "b": ["c"], "c": ["b"]}))
+"#,
+        );
+    }
+}
@@ -211,8 +211,19 @@ impl AllocFrozenValue for serde_json::Value {
 pub(crate) fn json(globals: &mut GlobalsBuilder) {
     #[starlark_module]
     fn json_members(globals: &mut GlobalsBuilder) {
-        fn encode(#[starlark(require = pos)] x: Value) -> anyhow::Result<String> {
-            x.to_json()
+        /// Encode a value as JSON.
+        ///
+        /// With `sort_keys = True` object keys are sorted, so the output
+        /// does not depend on dict insertion order.
+        fn encode(
+            #[starlark(require = pos)] x: Value,
+            #[starlark(require = named, default = false)] sort_keys: bool,
+        ) -> anyhow::Result<String> {
+            if sort_keys {
+                x.to_json_sorted()
+            } else {
+                x.to_json()
+            }
         }
 
         fn decode<'v>(
@@ -239,6 +250,27 @@ mod tests {
         a.eq("'[10]'", "json.encode([10])");
     }
 
+    #[test]
+    fn test_json_encode_sort_keys() {
+        let a = Assert::new();
+        a.eq(
+            r#"'{"b":1,"a":[{"y":2,"x":3}]}'"#,
+            r#"json.encode({"b": 1, "a": [{"y": 2, "x": 3}]})"#,
+        );
+        a.eq(
+            r#"'{"a":[{"x":3,"y":2}],"b":1}'"#,
+            r#"json.encode({"b": 1, "a": [{"y": 2, "x": 3}]}, sort_keys = True)"#,
+        );
+        a.eq(
+            "json.encode({'a': 1.5, 'b': None}, sort_keys = True)",
+            "json.encode({'b': None, 'a': 1.5}, sort_keys = True)",
+        );
+        a.eq(
+            "json.encode(123456789123456789123456789)",
+            "json.encode(123456789123456789123456789, sort_keys = True)",
+        );
+    }
+
     #[test]
     fn test_json_decode() {
         let a = Assert::new();
@@ -0,0 +1,367 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `math` extension module.
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::num::value::NumRef;
+use crate::values::types::int_or_big::StarlarkInt;
+
+fn round_to_int(x: NumRef, f: impl FnOnce(f64) -> f64) -> anyhow::Result<StarlarkInt> {
+    match x {
+        NumRef::Int(i) => Ok(i.to_owned()),
+        NumRef::Float(x) => StarlarkInt::from_f64_exact(f(x.0)),
+    }
+}
+
+/// IEEE 754 remainder: `x - n * y` where `n` is `x / y` rounded to the nearest integer,
+/// ties to even.
+///
+/// Same algorithm as CPython: reduce with the exact `fmod` first, so the result is exact
+/// even when `x / y` is too large to be represented as an integer.
+fn ieee_remainder(x: f64, y: f64) -> f64 {
+    if x.is_nan() {
+        return x;
+    }
+    if y.is_nan() {
+        return y;
+    }
+    if x.is_infinite() || y == 0.0 {
+        return f64::NAN;
+    }
+    if y.is_infinite() {
+        return x;
+    }
+    let absx = x.abs();
+    let absy = y.abs();
+    let m = absx % absy;
+    let c = absy - m;
+    let r = if m < c {
+        m
+    } else if m > c {
+        -c
+    } else {
+        // Half-way case: pick `m` or `-c` so that the quotient is even.
+        // `0.5 * (absx - m)` is exact, and its `fmod` by `absy` is either `0` or `m`.
+        m - 2.0 * ((0.5 * (absx - m)) % absy)
+    };
+    1.0f64.copysign(x) * r
+}
+
+pub(crate) fn math(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn math_members(globals: &mut GlobalsBuilder) {
+        /// The mathematical constant `e`, the base of natural logarithms.
+        const e: f64 = std::f64::consts::E;
+
+        /// The mathematical constant `pi`.
+        const pi: f64 = std::f64::consts::PI;
+
+        /// The smallest integer greater than or equal to `x`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// math.ceil(1.2) == 2
+        /// math.ceil(-1.2) == -1
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn ceil(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<StarlarkInt> {
+            round_to_int(x, f64::ceil)
+        }
+
+        /// The largest integer less than or equal to `x`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// math.floor(1.8) == 1
+        /// math.floor(-1.2) == -2
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn floor(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<StarlarkInt> {
+            round_to_int(x, f64::floor)
+        }
+
+        /// The nearest integer to `x`, rounding half away from zero, as a float.
+        #[starlark(speculative_exec_safe)]
+        fn round(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().round())
+        }
+
+        /// Absolute value of `x` as a float.
+        #[starlark(speculative_exec_safe)]
+        fn fabs(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().abs())
+        }
+
+        /// A float with the magnitude of `x` and the sign of `y`.
+        #[starlark(speculative_exec_safe)]
+        fn copysign(
+            #[starlark(require = pos)] x: NumRef,
+            #[starlark(require = pos)] y: NumRef,
+        ) -> anyhow::Result<f64> {
+            Ok(x.as_float().copysign(y.as_float()))
+        }
+
+        /// Floating-point remainder of `x / y`, with the sign of `x`.
+        ///
+        /// Unlike the `%` operator, the result has the sign of `x`, not `y`.
+        #[starlark(speculative_exec_safe)]
+        fn r#mod(
+            #[starlark(require = pos)] x: NumRef,
+            #[starlark(require = pos)] y: NumRef,
+        ) -> anyhow::Result<f64> {
+            Ok(x.as_float() % y.as_float())
+        }
+
+        /// IEEE 754 floating-point remainder of `x / y`.
+        #[starlark(speculative_exec_safe)]
+        fn remainder(
+            #[starlark(require = pos)] x: NumRef,
+            #[starlark(require = pos)] y: NumRef,
+        ) -> anyhow::Result<f64> {
+            Ok(ieee_remainder(x.as_float(), y.as_float()))
+        }
+
+        /// `x` raised to the power `y`, as a float.
+        #[starlark(speculative_exec_safe)]
+        fn pow(
+            #[starlark(require = pos)] x: NumRef,
+            #[starlark(require = pos)] y: NumRef,
+        ) -> anyhow::Result<f64> {
+            Ok(x.as_float().powf(y.as_float()))
+        }
+
+        /// Square root of `x`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// math.sqrt(16) == 4.0
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn sqrt(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().sqrt())
+        }
+
+        /// `e` raised to the power `x`.
+        #[starlark(speculative_exec_safe)]
+        fn exp(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().exp())
+        }
+
+        /// Logarithm of `x` in the given `base`, or natural logarithm if `base` is not given.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// math.log(math.e) == 1.0
+        /// math.log(1024, 2) == 10.0
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn log(
+            #[starlark(require = pos)] x: NumRef,
+            #[starlark(require = pos)] base: Option<NumRef>,
+        ) -> anyhow::Result<f64> {
+            let x = x.as_float();
+            Ok(match base {
+                None => x.ln(),
+                Some(base) => x.log(base.as_float()),
+            })
+        }
+
+        /// Convert angle `x` from radians to degrees.
+        #[starlark(speculative_exec_safe)]
+        fn degrees(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().to_degrees())
+        }
+
+        /// Convert angle `x` from degrees to radians.
+        #[starlark(speculative_exec_safe)]
+        fn radians(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().to_radians())
+        }
+
+        /// Euclidean norm `sqrt(x*x + y*y)`.
+        #[starlark(speculative_exec_safe)]
+        fn hypot(
+            #[starlark(require = pos)] x: NumRef,
+            #[starlark(require = pos)] y: NumRef,
+        ) -> anyhow::Result<f64> {
+            Ok(x.as_float().hypot(y.as_float()))
+        }
+
+        /// Sine of `x` radians.
+        #[starlark(speculative_exec_safe)]
+        fn sin(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().sin())
+        }
+
+        /// Cosine of `x` radians.
+        #[starlark(speculative_exec_safe)]
+        fn cos(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().cos())
+        }
+
+        /// Tangent of `x` radians.
+        #[starlark(speculative_exec_safe)]
+        fn tan(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().tan())
+        }
+
+        /// Arc sine of `x`, in radians.
+        #[starlark(speculative_exec_safe)]
+        fn asin(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().asin())
+        }
+
+        /// Arc cosine of `x`, in radians.
+        #[starlark(speculative_exec_safe)]
+        fn acos(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().acos())
+        }
+
+        /// Arc tangent of `x`, in radians.
+        #[starlark(speculative_exec_safe)]
+        fn atan(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().atan())
+        }
+
+        /// Arc tangent of `y / x`, in radians, using the signs of both arguments
+        /// to determine the quadrant.
+        #[starlark(speculative_exec_safe)]
+        fn atan2(
+            #[starlark(require = pos)] y: NumRef,
+            #[starlark(require = pos)] x: NumRef,
+        ) -> anyhow::Result<f64> {
+            Ok(y.as_float().atan2(x.as_float()))
+        }
+
+        /// Hyperbolic sine of `x`.
+        #[starlark(speculative_exec_safe)]
+        fn sinh(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().sinh())
+        }
+
+        /// Hyperbolic cosine of `x`.
+        #[starlark(speculative_exec_safe)]
+        fn cosh(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().cosh())
+        }
+
+        /// Hyperbolic tangent of `x`.
+        #[starlark(speculative_exec_safe)]
+        fn tanh(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().tanh())
+        }
+
+        /// Inverse hyperbolic sine of `x`.
+        #[starlark(speculative_exec_safe)]
+        fn asinh(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().asinh())
+        }
+
+        /// Inverse hyperbolic cosine of `x`.
+        #[starlark(speculative_exec_safe)]
+        fn acosh(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().acosh())
+        }
+
+        /// Inverse hyperbolic tangent of `x`.
+        #[starlark(speculative_exec_safe)]
+        fn atanh(#[starlark(require = pos)] x: NumRef) -> anyhow::Result<f64> {
+            Ok(x.as_float().atanh())
+        }
+    }
+
+    // Copying starlark-go's math module:
+    // https://github.com/google/starlark-go/blob/master/lib/math/math.go
+    globals.struct_("math", math_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_math_rounding() {
+        assert::pass(
+            r#"
+assert_eq(math.ceil(3), 3)
+assert_eq(math.ceil(2.0), 2)
+assert_eq(math.floor(-0.5), -1)
+assert_eq(math.floor(1e20), 100000000000000000000)
+assert_eq(math.round(2.5), 3.0)
+assert_eq(math.round(-2.5), -3.0)
+"#,
+        );
+        assert::fail(
+            "math.ceil(float('inf'))",
+            "cannot be represented as exact integer",
+        );
+        assert::fail(
+            "math.floor(float('nan'))",
+            "cannot be represented as exact integer",
+        );
+    }
+
+    #[test]
+    fn test_math_remainder() {
+        assert::pass(
+            r#"
+assert_eq(math.mod(-5, 3), -2.0)
+assert_eq(-5 % 3, 1)
+assert_eq(math.remainder(5, 2), 1.0)
+assert_eq(math.remainder(7, 2), -1.0)
+assert_eq(math.remainder(-5, 3), 1.0)
+assert_eq(math.remainder(1e300, 7.0), 1.0)
+assert_eq(math.remainder(-1e300, 7.0), -1.0)
+assert_eq(math.remainder(1e300, 3.0), 0.0)
+assert_eq(math.remainder(2.5, 1), 0.5)
+assert_eq(math.remainder(3.5, 1), -0.5)
+assert_eq(math.remainder(-2.5, 1), -0.5)
+assert_eq(math.remainder(-3.5, -1), 0.5)
+assert_eq(math.remainder(3, float('inf')), 3.0)
+"#,
+        );
+    }
+
+    #[test]
+    fn test_math_functions() {
+        assert::pass(
+            r#"
+assert_eq(math.pi, 3.141592653589793)
+assert_eq(math.degrees(math.pi), 180.0)
+assert_eq(math.radians(180), math.pi)
+assert_eq(math.hypot(3, 4), 5.0)
+assert_eq(math.copysign(2, -0.0), -2.0)
+assert_eq(math.fabs(-3), 3.0)
+assert_eq(math.pow(2, 0.5), math.sqrt(2))
+assert_eq(math.exp(0), 1.0)
+assert_eq(math.sin(0), 0.0)
+assert_eq(math.cos(0), 1.0)
+assert_eq(math.atan2(0, -1), math.pi)
+assert_eq(math.log(100, 10), 2.0)
+assert_eq(str(math.sqrt(-1)), "nan")
+"#,
+        );
+    }
+}
@@ -31,6 +31,7 @@ use crate::eval::Evaluator;
 use crate::values::list::AllocList;
 use crate::values::list::UnpackList;
 use crate::values::none::NoneOr;
+use crate::values::string::casefold::push_casefold;
 use crate::values::string::dot_format;
 use crate::values::tuple::UnpackTuple;
 use crate::values::type_repr::StarlarkTypeRepr;
@@ -153,6 +154,29 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         Ok(result)
     }
 
+    /// [string.casefold](
+    /// https://docs.python.org/3/library/stdtypes.html#str.casefold
+    /// ): convert a string for caseless comparison. _Not part of standard Starlark._
+    ///
+    /// Like `S.lower()`, but uses the full Unicode case folding, so also folds
+    /// characters which have no single lowercase equivalent, for example the
+    /// German `ß` becomes `ss` and the Greek final sigma `ς` becomes `σ`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// "Hello, World!".casefold() == "hello, world!"
+    /// "Straße".casefold() == "STRASSE".casefold()
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn casefold(this: &str) -> anyhow::Result<String> {
+        let mut result = String::with_capacity(this.len());
+        for c in this.chars() {
+            push_casefold(c, &mut result);
+        }
+        Ok(result)
+    }
+
     /// [string.codepoints](
     /// https://github.com/bazelbuild/starlark/blob/master/spec.md#string·codepoints
     /// ): returns an iterable of the unicode codepoint of a string.
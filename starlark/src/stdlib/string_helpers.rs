@@ -0,0 +1,274 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `StringHelpers` extension: natural sorting,
+//! version comparison and string similarity.
+
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::tuple::AllocTuple;
+use crate::values::types::int_or_big::StarlarkInt;
+use crate::values::Heap;
+use crate::values::Value;
+
+/// Split `s` into runs of non-digits, each followed by an optional run of ASCII digits.
+fn natural_parts(s: &str) -> Vec<(&str, Option<&str>)> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let text_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (text, after) = rest.split_at(text_len);
+        let digits_len = after
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after.len());
+        let (digits, after) = after.split_at(digits_len);
+        parts.push((text, Some(digits).filter(|d| !d.is_empty())));
+        rest = after;
+    }
+    parts
+}
+
+/// Compare two runs of ASCII digits by their numeric value.
+fn compare_digits(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// A component of a version string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VersionPart<'a> {
+    /// A run of non-digits, such as `rc` or `beta`, which marks a pre-release.
+    Text(&'a str),
+    /// A run of ASCII digits.
+    Number(&'a str),
+}
+
+impl PartialOrd for VersionPart<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VersionPart<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (VersionPart::Text(a), VersionPart::Text(b)) => a.cmp(b),
+            (VersionPart::Text(_), VersionPart::Number(_)) => Ordering::Less,
+            (VersionPart::Number(_), VersionPart::Text(_)) => Ordering::Greater,
+            (VersionPart::Number(a), VersionPart::Number(b)) => compare_digits(a, b),
+        }
+    }
+}
+
+/// Split a version string into runs of digits and runs of other alphanumeric
+/// characters, dropping separators such as `.`, `-` or `_`.
+fn version_parts(s: &str) -> Vec<VersionPart<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find(char::is_alphanumeric) {
+        rest = &rest[start..];
+        let is_digit = rest.starts_with(|c: char| c.is_ascii_digit());
+        let len = rest
+            .find(|c: char| !c.is_alphanumeric() || c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (part, after) = rest.split_at(len);
+        parts.push(if is_digit {
+            VersionPart::Number(part)
+        } else {
+            VersionPart::Text(part)
+        });
+        rest = after;
+    }
+    parts
+}
+
+/// Compare version strings part by part, missing parts are `0`.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let a = version_parts(a);
+    let b = version_parts(b);
+    let zero = VersionPart::Number("0");
+    for i in 0..a.len().max(b.len()) {
+        let a = a.get(i).unwrap_or(&zero);
+        let b = b.get(i).unwrap_or(&zero);
+        let ord = a.cmp(b);
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+pub(crate) fn string_helpers(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn string_helpers_globals(globals: &mut GlobalsBuilder) {
+        /// A key to sort strings in natural order, where runs of digits
+        /// are compared by their numeric value.
+        ///
+        /// The key is a tuple of `(text, number)` pairs, where `number` is `-1`
+        /// if the text is not followed by digits.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// sorted(["file10", "file9", "file1"], key = natsort_key) == ["file1", "file9", "file10"]
+        /// natsort_key("a10b") == (("a", 10), ("b", -1))
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn natsort_key<'v>(
+            #[starlark(require = pos)] s: &str,
+            heap: &'v Heap,
+        ) -> anyhow::Result<Value<'v>> {
+            let mut key = Vec::new();
+            for (text, digits) in natural_parts(s) {
+                let number = match digits {
+                    None => StarlarkInt::from(-1),
+                    Some(digits) => StarlarkInt::from_str(digits)?,
+                };
+                key.push(heap.alloc((text, number)));
+            }
+            Ok(heap.alloc(AllocTuple(key)))
+        }
+
+        /// Similarity of two strings, from `0.0` for completely different strings
+        /// to `1.0` for equal strings, based on the Levenshtein edit distance.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// string_similarity("kitten", "kitten") == 1.0
+        /// string_similarity("kitten", "sitting") > string_similarity("kitten", "dog")
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn string_similarity(
+            #[starlark(require = pos)] a: &str,
+            #[starlark(require = pos)] b: &str,
+        ) -> anyhow::Result<f64> {
+            Ok(strsim::normalized_levenshtein(a, b))
+        }
+    }
+
+    #[starlark_module]
+    fn versions_members(globals: &mut GlobalsBuilder) {
+        /// Compare two version strings, returning `-1`, `0` or `1` if `a` is
+        /// older than, the same as or newer than `b`.
+        ///
+        /// Versions are split into runs of digits, compared by their numeric value,
+        /// and runs of letters, compared as strings. Other characters such as `.` or `-`
+        /// only separate the parts, and missing parts count as `0`. Letters sort before
+        /// any number, so a pre-release such as `2.0-rc1` is older than `2.0` and `2.0.1`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// versions.compare("1.10", "1.9") == 1
+        /// versions.compare("1.0", "1") == 0
+        /// versions.compare("2.0-rc1", "2.0-rc2") == -1
+        /// versions.compare("2.0-rc1", "2.0") == -1
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn compare(
+            #[starlark(require = pos)] a: &str,
+            #[starlark(require = pos)] b: &str,
+        ) -> anyhow::Result<i32> {
+            Ok(compare_versions(a, b) as i32)
+        }
+    }
+
+    string_helpers_globals(globals);
+    globals.struct_("versions", versions_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_natsort_key() {
+        assert::pass(
+            r#"
+assert_eq((), natsort_key(""))
+assert_eq((("", 42),), natsort_key("42"))
+assert_eq((("v", 1), (".", 123456789012345678901234567890)), natsort_key("v1.123456789012345678901234567890"))
+xs = ["x10", "x9", "X1", "x", "x01a", "x1", "10", "9"]
+assert_eq(["10", "9", "X1", "x", "x01a", "x1", "x10", "x9"], sorted(xs))
+assert_eq(["9", "10", "X1", "x", "x1", "x01a", "x9", "x10"], sorted(xs, key = natsort_key))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_versions_compare() {
+        assert::pass(
+            r#"
+assert_eq(0, versions.compare("", ""))
+assert_eq(0, versions.compare("1.2.0", "1.2"))
+assert_eq(0, versions.compare("1.02", "1.2"))
+assert_eq(1, versions.compare("1.10", "1.9"))
+assert_eq(-1, versions.compare("1.9", "1.10"))
+assert_eq(1, versions.compare("1.2.1", "1.2"))
+assert_eq(-1, versions.compare("1.0a", "1.0b"))
+assert_eq(1, versions.compare("10.0.0", "9.99.99"))
+assert_eq(-1, versions.compare("2.0-rc1", "2.0"))
+assert_eq(-1, versions.compare("2.0rc1", "2.0"))
+assert_eq(-1, versions.compare("2.0-rc1", "2.0.1"))
+assert_eq(-1, versions.compare("2.0-rc9", "2.0-rc10"))
+assert_eq(-1, versions.compare("2.0-beta2", "2.0-rc1"))
+assert_eq(1, versions.compare("2.0", "2.0-rc10"))
+assert_eq(0, versions.compare("2.0-rc1", "2.0.rc.1"))
+assert_eq(-1, versions.compare("1.0.rc", "1.0.0"))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_casefold() {
+        assert::pass(
+            r#"
+assert_eq("hello", "HeLLo".casefold())
+assert_eq("strasse", "STRAẞE".casefold())
+assert_eq("ﬁ".casefold(), "FI".casefold())
+assert_eq("όσος".casefold(), "ΌΣΟΣ".casefold())
+assert_eq("όσοσ", "όσος".casefold())
+assert_eq("ss", "ß".casefold())
+assert_eq("μ", "µ".casefold())
+assert_eq("ἀι", "ᾈ".casefold())
+assert_eq("Ꭰ", "ꭰ".casefold())
+assert_eq("i\u0307", "İ".casefold())
+assert_eq("ǆ", "Ǆ".casefold())
+"#,
+        );
+    }
+
+    #[test]
+    fn test_string_similarity() {
+        assert::pass(
+            r#"
+assert_eq(1.0, string_similarity("", ""))
+assert_eq(0.0, string_similarity("abc", "xyz"))
+assert_eq(0.5, string_similarity("abcd", "abxy"))
+"#,
+        );
+    }
+}
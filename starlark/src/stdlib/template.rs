@@ -0,0 +1,259 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `template` extension module.
+
+use std::fmt;
+use std::fmt::Display;
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::dict::DictRef;
+
+/// Location of a placeholder in a template, rendered with the offending line.
+#[derive(Debug)]
+struct TemplateSpan {
+    line: usize,
+    column: usize,
+    text: String,
+    width: usize,
+}
+
+impl TemplateSpan {
+    fn new(template: &str, start: usize, end: usize) -> TemplateSpan {
+        let line_start = template[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = template[start..]
+            .find('\n')
+            .map_or(template.len(), |i| start + i);
+        TemplateSpan {
+            line: template[..start].matches('\n').count() + 1,
+            column: template[line_start..start].chars().count() + 1,
+            text: template[line_start..line_end].to_owned(),
+            width: template[start..end.min(line_end)].chars().count().max(1),
+        }
+    }
+}
+
+impl Display for TemplateSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "line {}, column {}:", self.line, self.column)?;
+        writeln!(f, "  {}", self.text)?;
+        write!(
+            f,
+            "  {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(self.width)
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TemplateError {
+    #[error("Invalid placeholder in template at {0}")]
+    InvalidPlaceholder(TemplateSpan),
+    #[error("Placeholder `{0}` not found in mapping, at {1}")]
+    MissingKey(String, TemplateSpan),
+}
+
+fn is_identifier_start(b: u8) -> bool {
+    b == b'_' || b.is_ascii_alphabetic()
+}
+
+fn is_identifier_continue(b: u8) -> bool {
+    b == b'_' || b.is_ascii_alphanumeric()
+}
+
+/// Length of the identifier at the start of `s`, zero if there is none.
+fn identifier_len(s: &[u8]) -> usize {
+    match s.first() {
+        Some(b) if is_identifier_start(*b) => {
+            1 + s[1..]
+                .iter()
+                .take_while(|b| is_identifier_continue(**b))
+                .count()
+        }
+        _ => 0,
+    }
+}
+
+/// Substitute `$name` and `${name}` placeholders in `template` using `lookup`.
+///
+/// `$$` is an escape for a single `$`. In lenient mode, invalid placeholders
+/// and placeholders missing from the mapping are copied to the output unchanged.
+fn substitute_template(
+    template: &str,
+    strict: bool,
+    mut lookup: impl FnMut(&str) -> Option<String>,
+) -> anyhow::Result<String> {
+    let bytes = template.as_bytes();
+    let mut res = String::with_capacity(template.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while let Some(dollar) = template[i..].find('$').map(|d| i + d) {
+        res.push_str(&template[copied..dollar]);
+        let rest = &bytes[dollar + 1..];
+        // Byte range of the placeholder, and the range of the name within it.
+        let (end, name) = match rest.first() {
+            Some(b'$') => {
+                res.push('$');
+                i = dollar + 2;
+                copied = i;
+                continue;
+            }
+            Some(b'{') => {
+                let len = identifier_len(&rest[1..]);
+                if len != 0 && rest.get(1 + len) == Some(&b'}') {
+                    (dollar + len + 3, Some(dollar + 2..dollar + 2 + len))
+                } else {
+                    let end = rest
+                        .iter()
+                        .position(|b| *b == b'}' || *b == b'\n')
+                        .filter(|p| rest[*p] == b'}')
+                        .map_or(dollar + 2, |p| dollar + p + 2);
+                    (end, None)
+                }
+            }
+            _ => {
+                let len = identifier_len(rest);
+                if len != 0 {
+                    (dollar + 1 + len, Some(dollar + 1..dollar + 1 + len))
+                } else {
+                    (dollar + 1, None)
+                }
+            }
+        };
+        match name {
+            Some(name) => match lookup(&template[name.clone()]) {
+                Some(value) => res.push_str(&value),
+                None if strict => {
+                    return Err(TemplateError::MissingKey(
+                        template[name].to_owned(),
+                        TemplateSpan::new(template, dollar, end),
+                    )
+                    .into());
+                }
+                None => res.push_str(&template[dollar..end]),
+            },
+            None if strict => {
+                return Err(TemplateError::InvalidPlaceholder(TemplateSpan::new(
+                    template, dollar, end,
+                ))
+                .into());
+            }
+            None => res.push_str(&template[dollar..end]),
+        }
+        i = end;
+        copied = end;
+    }
+    res.push_str(&template[copied..]);
+    Ok(res)
+}
+
+pub(crate) fn template(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn template_members(globals: &mut GlobalsBuilder) {
+        /// Substitute placeholders in a template string with values from a dictionary.
+        ///
+        /// Placeholders are written `$name` or `${name}`, where `name` is an identifier,
+        /// and `$$` produces a literal `$`. Values are converted to strings like `str()`.
+        /// Unlike `%` and `format`, braces have no special meaning outside placeholders.
+        ///
+        /// In strict mode (the default) an invalid placeholder or a name
+        /// missing from the mapping is an error which points at the location
+        /// in the template. With `strict = False` such placeholders are left unchanged.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// template.substitute("Hello ${name}!", {"name": "world"}) == "Hello world!"
+        /// template.substitute("$x + $x = ${y}0", {"x": 1, "y": 2}) == "1 + 1 = 20"
+        /// template.substitute("{} costs $$5", {}) == "{} costs $5"
+        /// template.substitute("$a $b ${", {"a": 1}, strict = False) == "1 $b ${"
+        /// # "#);
+        /// ```
+        ///
+        /// ```
+        /// # starlark::assert::fail(r#"
+        /// template.substitute("Hello ${nmae}", {"name": "world"}) # error: not found
+        /// # "#, "not found");
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn substitute<'v>(
+            #[starlark(require = pos)] template: &str,
+            #[starlark(require = pos)] mapping: DictRef<'v>,
+            #[starlark(require = named, default = true)] strict: bool,
+        ) -> anyhow::Result<String> {
+            substitute_template(template, strict, |name| {
+                mapping.get_str(name).map(|v| v.to_str())
+            })
+        }
+    }
+
+    globals.struct_("template", template_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_substitute() {
+        assert::pass(
+            r#"
+assert_eq("", template.substitute("", {}))
+assert_eq("no placeholders {}", template.substitute("no placeholders {}", {}))
+assert_eq("a1b", template.substitute("a${x}b", {"x": 1}))
+assert_eq("1b", template.substitute("$x${y}", {"x": 1, "y": "b"}))
+assert_eq("[1, 2].", template.substitute("$x_.", {"x_": [1, 2]}))
+assert_eq("$x", template.substitute("$$x", {"x": 1}))
+assert_eq("héllo wörld", template.substitute("héllo $w", {"w": "wörld"}))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_substitute_lenient() {
+        assert::pass(
+            r#"
+assert_eq("$ ${} ${a b} $1 ${x", template.substitute("$ ${} ${a b} $1 ${x", {}, strict = False))
+assert_eq("1 $y ${z}", template.substitute("$x $y ${z}", {"x": 1}, strict = False))
+assert_eq("$", template.substitute("$$", {}, strict = False))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_substitute_errors() {
+        assert::fail(
+            r#"template.substitute("Hello ${name}", {})"#,
+            "Placeholder `name` not found in mapping, at line 1, column 7:\n  Hello ${name}\n        ^^^^^^^",
+        );
+        assert::fail(
+            r#"template.substitute("a\nb ${x y} c", {})"#,
+            "Invalid placeholder in template at line 2, column 3:\n  b ${x y} c\n    ^^^^^^",
+        );
+        assert::fail(
+            r#"template.substitute("price: $5", {})"#,
+            "Invalid placeholder in template at line 1, column 8:\n  price: $5\n         ^",
+        );
+        assert::fail(
+            r#"template.substitute("${x", {"x": 1})"#,
+            "Invalid placeholder in template at line 1, column 1:\n  ${x\n  ^^",
+        );
+    }
+}
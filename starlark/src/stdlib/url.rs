@@ -0,0 +1,324 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `url` extension module.
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::values::dict::DictRef;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::StarlarkValue;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum UrlError {
+    #[error("Invalid URL `{0}`: {1}")]
+    Invalid(String, url::ParseError),
+    #[error("Cannot join `{0}` to URL `{1}`: {2}")]
+    Join(String, String, url::ParseError),
+    #[error("Query parameter `{0}` contains itself")]
+    QueryCycle(String),
+    #[error("Query parameter `{0}` is nested more than {1} lists deep")]
+    QueryTooDeep(String, usize),
+}
+
+/// The maximum nesting of lists and tuples in a query parameter value.
+const MAX_QUERY_VALUE_DEPTH: usize = 64;
+
+/// A parsed absolute URL, created with `url.parse`.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub(crate) struct StarlarkUrl(#[allocative(skip)] url::Url);
+
+starlark_simple_value!(StarlarkUrl);
+
+impl StarlarkUrl {
+    fn parse(s: &str) -> anyhow::Result<StarlarkUrl> {
+        match url::Url::parse(s) {
+            Ok(url) => Ok(StarlarkUrl(url)),
+            Err(e) => Err(UrlError::Invalid(s.to_owned(), e).into()),
+        }
+    }
+
+    fn join(&self, relative: &str) -> anyhow::Result<StarlarkUrl> {
+        match self.0.join(relative) {
+            Ok(url) => Ok(StarlarkUrl(url)),
+            Err(e) => Err(UrlError::Join(relative.to_owned(), self.0.to_string(), e).into()),
+        }
+    }
+}
+
+impl Display for StarlarkUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+#[starlark_value(type = "url")]
+impl<'v> StarlarkValue<'v> for StarlarkUrl {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(url_methods)
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match other.downcast_ref::<StarlarkUrl>() {
+            Some(other) => Ok(self.0 == other.0),
+            None => Ok(false),
+        }
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        std::hash::Hash::hash(&self.0, hasher);
+        Ok(())
+    }
+}
+
+#[starlark_module]
+fn url_methods(builder: &mut MethodsBuilder) {
+    /// The scheme, like `"https"`.
+    #[starlark(attribute)]
+    fn scheme(this: &StarlarkUrl) -> anyhow::Result<String> {
+        Ok(this.0.scheme().to_owned())
+    }
+
+    /// The user name, `""` if there is none.
+    #[starlark(attribute)]
+    fn username(this: &StarlarkUrl) -> anyhow::Result<String> {
+        Ok(this.0.username().to_owned())
+    }
+
+    /// The password, or `None`.
+    #[starlark(attribute)]
+    fn password(this: &StarlarkUrl) -> anyhow::Result<Option<String>> {
+        Ok(this.0.password().map(str::to_owned))
+    }
+
+    /// The host, or `None`. International domain names are in their ASCII form.
+    #[starlark(attribute)]
+    fn host(this: &StarlarkUrl) -> anyhow::Result<Option<String>> {
+        Ok(this.0.host_str().map(str::to_owned))
+    }
+
+    /// The port if given explicitly and not the default for the scheme, or `None`.
+    #[starlark(attribute)]
+    fn port(this: &StarlarkUrl) -> anyhow::Result<Option<i32>> {
+        Ok(this.0.port().map(i32::from))
+    }
+
+    /// The percent-encoded path, like `"/a/b%20c"`.
+    #[starlark(attribute)]
+    fn path(this: &StarlarkUrl) -> anyhow::Result<String> {
+        Ok(this.0.path().to_owned())
+    }
+
+    /// The percent-encoded query without the leading `?`, or `None`.
+    #[starlark(attribute)]
+    fn query(this: &StarlarkUrl) -> anyhow::Result<Option<String>> {
+        Ok(this.0.query().map(str::to_owned))
+    }
+
+    /// The fragment without the leading `#`, or `None`.
+    #[starlark(attribute)]
+    fn fragment(this: &StarlarkUrl) -> anyhow::Result<Option<String>> {
+        Ok(this.0.fragment().map(str::to_owned))
+    }
+
+    /// Resolve a relative reference against this URL, like a browser resolves links.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// str(url.parse("https://example.com/a/b").join("c")) == "https://example.com/a/c"
+    /// # "#);
+    /// ```
+    fn join(
+        this: &StarlarkUrl,
+        #[starlark(require = pos)] relative: &str,
+    ) -> anyhow::Result<StarlarkUrl> {
+        this.join(relative)
+    }
+}
+
+/// Append a query parameter, a list or tuple value appends one parameter per element
+/// and a `None` value appends nothing.
+///
+/// `parents` are the lists and tuples `value` is nested in, used to reject cycles
+/// and overly deep nesting.
+fn encode_query_value<'v>(
+    query: &mut url::form_urlencoded::Serializer<String>,
+    name: &str,
+    value: Value<'v>,
+    parents: &mut Vec<Value<'v>>,
+) -> anyhow::Result<()> {
+    if value.is_none() {
+        return Ok(());
+    }
+    if let Some(values) = UnpackListOrTuple::<Value>::unpack_value_opt(value) {
+        if parents.iter().any(|p| p.ptr_eq(value)) {
+            return Err(UrlError::QueryCycle(name.to_owned()).into());
+        }
+        if parents.len() == MAX_QUERY_VALUE_DEPTH {
+            return Err(UrlError::QueryTooDeep(name.to_owned(), MAX_QUERY_VALUE_DEPTH).into());
+        }
+        parents.push(value);
+        for value in values.items {
+            encode_query_value(query, name, value, parents)?;
+        }
+        parents.pop();
+    } else {
+        query.append_pair(name, &value.to_str());
+    }
+    Ok(())
+}
+
+pub(crate) fn url(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn url_members(globals: &mut GlobalsBuilder) {
+        /// Parse an absolute URL, normalizing it.
+        /// Components are available as attributes, and `str()` gives the normalized URL.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// url.parse("HTTPS://user@Example.com:8443/a b?x=1#top").host == "example.com"
+        /// url.parse("https://example.com:8443/a b?x=1#top").path == "/a%20b"
+        /// str(url.parse("HTTPS://Example.com:443/a?x=1#top")) == "https://example.com/a?x=1#top"
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn parse(#[starlark(require = pos)] s: &str) -> anyhow::Result<StarlarkUrl> {
+            StarlarkUrl::parse(s)
+        }
+
+        /// Resolve the relative reference `relative` against the absolute URL `base`,
+        /// returning the resulting URL as a string.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// url.join("https://example.com/repo/", "pkg/1.0/a.tar.gz") == "https://example.com/repo/pkg/1.0/a.tar.gz"
+        /// url.join("https://example.com/repo/x", "/root") == "https://example.com/root"
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn join(
+            #[starlark(require = pos)] base: &str,
+            #[starlark(require = pos)] relative: &str,
+        ) -> anyhow::Result<String> {
+            Ok(StarlarkUrl::parse(base)?.join(relative)?.to_string())
+        }
+
+        /// Encode a dictionary as an `application/x-www-form-urlencoded` query string.
+        ///
+        /// Values are converted with `str()`, a list or tuple value gives one parameter
+        /// per element, and `None` values are omitted.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// url.encode_query({"q": "a b&c", "n": 1}) == "q=a+b%26c&n=1"
+        /// url.encode_query({"tag": ["x", "y"], "skip": None}) == "tag=x&tag=y"
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn encode_query<'v>(
+            #[starlark(require = pos)] params: DictRef<'v>,
+        ) -> anyhow::Result<String> {
+            let mut query = url::form_urlencoded::Serializer::new(String::new());
+            for (name, value) in params.iter() {
+                encode_query_value(&mut query, &name.to_str(), value, &mut Vec::new())?;
+            }
+            Ok(query.finish())
+        }
+    }
+
+    globals.struct_("url", url_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_url_parse() {
+        assert::pass(
+            r#"
+u = url.parse("HTTPS://user@Example.com:8443/a b?x=1#top")
+assert_eq(("https", "user", "example.com", 8443), (u.scheme, u.username, u.host, u.port))
+assert_eq(("/a%20b", "x=1", "top"), (u.path, u.query, u.fragment))
+assert_eq("https://user@example.com:8443/a%20b?x=1#top", str(u))
+u = url.parse("http://[::1]/x?")
+assert_eq(("[::1]", None, "/x", ""), (u.host, u.port, u.path, u.query))
+u = url.parse("https://example.com:443")
+assert_eq((None, "/", None, None), (u.port, u.path, u.query, u.password))
+assert_eq(url.parse("https://EXAMPLE.com/"), url.parse("https://example.com"))
+assert_eq("url", type(u))
+d = {u: 1}
+d[url.parse("https://example.com/")] = 2
+assert_eq([2], d.values())
+"#,
+        );
+        assert::fail("url.parse('example.com/x')", "Invalid URL `example.com/x`");
+        assert::fail(
+            "url.join('mailto:a@b.c', 'x')",
+            "Cannot join `x` to URL `mailto:a@b.c`",
+        );
+    }
+
+    #[test]
+    fn test_url_encode_query() {
+        assert::pass(
+            r#"
+assert_eq("", url.encode_query({}))
+assert_eq("a=%C3%A9&b=True&c=1&c=2", url.encode_query({"a": "é", "b": True, "c": (1, 2)}))
+x = [1]
+assert_eq("a=1&a=1&a=2", url.encode_query({"a": [x, (x, [2])]}))
+"#,
+        );
+        assert::fail(
+            r#"
+x = [1]
+x.append(x)
+url.encode_query({"a": x})
+"#,
+            "Query parameter `a` contains itself",
+        );
+        assert::fail(
+            r#"
+def nest():
+    x = 1
+    for _ in range(100):
+        x = [x]
+    return x
+url.encode_query({"a": nest()})
+"#,
+            "Query parameter `a` is nested more than 64 lists deep",
+        );
+    }
+}
@@ -0,0 +1,496 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Implementation of the `version` extension: semantic version values.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use allocative::Allocative;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::values::types::int_or_big::StarlarkInt;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum VersionError {
+    #[error("Invalid version `{0}`, expected `MAJOR.MINOR.PATCH[-PRERELEASE][+BUILD]`")]
+    InvalidVersion(String),
+    #[error("Invalid version constraint `{0}`")]
+    InvalidConstraint(String),
+}
+
+/// A dot-separated pre-release identifier, numeric identifiers sort before alphanumeric ones.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Allocative)]
+enum PreRelease {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl Display for PreRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreRelease::Numeric(n) => write!(f, "{}", n),
+            PreRelease::Alphanumeric(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Parse a numeric version component, rejecting leading zeros like semver does.
+fn parse_number(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0'))
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// A semantic version, as described by <https://semver.org>.
+///
+/// Versions compare by precedence, build metadata is ignored
+/// for comparison, equality and hashing.
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub(crate) struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<PreRelease>,
+    build: Option<String>,
+}
+
+starlark_simple_value!(Version);
+
+impl Version {
+    fn new(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+            build: None,
+        }
+    }
+
+    /// The smallest pre-release of `major.minor.patch`, used as an exclusive upper bound
+    /// so that a range like `<2.0.0` does not contain `2.0.0-rc1`.
+    fn lowest(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            pre: vec![PreRelease::Numeric(0)],
+            ..Version::new(major, minor, patch)
+        }
+    }
+
+    fn parse(s: &str) -> anyhow::Result<Version> {
+        Self::parse_partial(s.strip_prefix('v').unwrap_or(s))
+            .filter(|(_, minor, patch)| minor.is_some() && patch.is_some())
+            .map(|(v, _, _)| v)
+            .ok_or_else(|| VersionError::InvalidVersion(s.to_owned()).into())
+    }
+
+    /// Parse a version where the minor and patch components may be missing,
+    /// returning which components were present. Missing components are zero.
+    fn parse_partial(s: &str) -> Option<(Version, Option<u64>, Option<u64>)> {
+        let (s, build) = match s.split_once('+') {
+            Some((s, build)) => {
+                if !build.split('.').all(is_identifier) {
+                    return None;
+                }
+                (s, Some(build.to_owned()))
+            }
+            None => (s, None),
+        };
+        let (s, pre) = match s.split_once('-') {
+            Some((s, pre)) => {
+                let pre = pre
+                    .split('.')
+                    .map(|id| {
+                        if !is_identifier(id) {
+                            None
+                        } else if id.bytes().all(|b| b.is_ascii_digit()) {
+                            parse_number(id).map(PreRelease::Numeric)
+                        } else {
+                            Some(PreRelease::Alphanumeric(id.to_owned()))
+                        }
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                (s, pre)
+            }
+            None => (s, Vec::new()),
+        };
+        let mut parts = s.split('.');
+        let major = parse_number(parts.next()?)?;
+        let minor = parts.next().map(parse_number);
+        let patch = parts.next().map(parse_number);
+        let (minor, patch) = match (minor, patch, parts.next()) {
+            (None, None, None) => (None, None),
+            (Some(Some(minor)), None, None) => (Some(minor), None),
+            (Some(Some(minor)), Some(Some(patch)), None) => (Some(minor), Some(patch)),
+            _ => return None,
+        };
+        // Pre-release and build metadata only make sense on complete versions.
+        if patch.is_none() && (!pre.is_empty() || build.is_some()) {
+            return None;
+        }
+        let version = Version {
+            major,
+            minor: minor.unwrap_or(0),
+            patch: patch.unwrap_or(0),
+            pre,
+            build,
+        };
+        Some((version, minor, patch))
+    }
+
+    fn precedence(&self) -> (u64, u64, u64, bool, &[PreRelease]) {
+        // A release has higher precedence than its pre-releases.
+        (
+            self.major,
+            self.minor,
+            self.patch,
+            self.pre.is_empty(),
+            &self.pre,
+        )
+    }
+
+    fn cmp_precedence(&self, other: &Version) -> Ordering {
+        self.precedence().cmp(&other.precedence())
+    }
+
+    /// The smallest version above all versions matching the partial version
+    /// `major[.minor[.patch]]`, or `None` if there is no such version because
+    /// the last given component is already the largest possible.
+    fn lowest_above(major: u64, minor: Option<u64>, patch: Option<u64>) -> Option<Version> {
+        Some(match (minor, patch) {
+            (None, _) => Version::lowest(major.checked_add(1)?, 0, 0),
+            (Some(minor), None) => Version::lowest(major, minor.checked_add(1)?, 0),
+            (Some(minor), Some(patch)) => Version::lowest(major, minor, patch.checked_add(1)?),
+        })
+    }
+
+    /// Check a single comparator such as `>=1.2`.
+    fn matches_comparator(&self, comparator: &str) -> anyhow::Result<bool> {
+        let invalid = || VersionError::InvalidConstraint(comparator.to_owned());
+        let (op, rest) = [">=", "<=", "==", "!=", ">", "<", "=", "^", "~"]
+            .iter()
+            .find_map(|op| Some((*op, comparator.strip_prefix(op)?)))
+            .unwrap_or(("=", comparator));
+        let rest = rest.trim();
+        let (bound, minor, patch) =
+            Self::parse_partial(rest.strip_prefix('v').unwrap_or(rest)).ok_or_else(invalid)?;
+        let Version { major, .. } = bound;
+        let next = Self::lowest_above(major, minor, patch);
+        let ge = |v: &Version| self.cmp_precedence(v) != Ordering::Less;
+        let lt = |v: &Version| self.cmp_precedence(v) == Ordering::Less;
+        // Versions are always below a missing upper bound, and never above it.
+        let ge_upper = |v: &Option<Version>| v.as_ref().is_some_and(ge);
+        let lt_upper = |v: &Option<Version>| !ge_upper(v);
+        let exact = || match patch {
+            Some(_) => self.cmp_precedence(&bound) == Ordering::Equal,
+            None => ge(&bound) && lt_upper(&next),
+        };
+        Ok(match op {
+            "=" | "==" => exact(),
+            "!=" => !exact(),
+            ">=" => ge(&bound),
+            "<" if bound.pre.is_empty() => lt(&Version::lowest(major, bound.minor, bound.patch)),
+            "<" => lt(&bound),
+            ">" => match patch {
+                Some(_) => self.cmp_precedence(&bound) == Ordering::Greater,
+                None => ge_upper(&next),
+            },
+            "<=" => match patch {
+                Some(_) => self.cmp_precedence(&bound) != Ordering::Greater,
+                None => lt_upper(&next),
+            },
+            "~" => {
+                let upper = Self::lowest_above(major, minor, None);
+                ge(&bound) && lt_upper(&upper)
+            }
+            "^" => {
+                let upper = match (major, minor, patch) {
+                    (0, Some(0), Some(_)) => Self::lowest_above(major, minor, patch),
+                    (0, Some(_), _) => Self::lowest_above(major, minor, None),
+                    _ => Self::lowest_above(major, None, None),
+                };
+                ge(&bound) && lt_upper(&upper)
+            }
+            _ => unreachable!("unknown operator"),
+        })
+    }
+
+    fn matches(&self, constraint: &str) -> anyhow::Result<bool> {
+        for comparator in constraint.split(',') {
+            let comparator = comparator.trim();
+            if comparator.is_empty() {
+                return Err(VersionError::InvalidConstraint(constraint.to_owned()).into());
+            }
+            if !self.matches_comparator(comparator)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (i, pre) in self.pre.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { '-' } else { '.' }, pre)?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
+        Ok(())
+    }
+}
+
+#[starlark_value(type = "version")]
+impl<'v> StarlarkValue<'v> for Version {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(version_methods)
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match other.downcast_ref::<Version>() {
+            Some(other) => Ok(self.cmp_precedence(other) == Ordering::Equal),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> crate::Result<Ordering> {
+        match other.downcast_ref::<Version>() {
+            Some(other) => Ok(self.cmp_precedence(other)),
+            None => ValueError::unsupported_with(self, "compare", other),
+        }
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        self.precedence().hash(hasher);
+        Ok(())
+    }
+}
+
+#[starlark_module]
+fn version_methods(builder: &mut MethodsBuilder) {
+    /// The major version number.
+    #[starlark(attribute)]
+    fn major(this: &Version) -> anyhow::Result<StarlarkInt> {
+        Ok(StarlarkInt::from(this.major))
+    }
+
+    /// The minor version number.
+    #[starlark(attribute)]
+    fn minor(this: &Version) -> anyhow::Result<StarlarkInt> {
+        Ok(StarlarkInt::from(this.minor))
+    }
+
+    /// The patch version number.
+    #[starlark(attribute)]
+    fn patch(this: &Version) -> anyhow::Result<StarlarkInt> {
+        Ok(StarlarkInt::from(this.patch))
+    }
+
+    /// The pre-release identifiers, like `"rc.1"`, or `None` for a release.
+    #[starlark(attribute)]
+    fn prerelease(this: &Version) -> anyhow::Result<Option<String>> {
+        if this.pre.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            this.pre
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>()
+                .join("."),
+        ))
+    }
+
+    /// The build metadata, or `None` if there is none.
+    #[starlark(attribute)]
+    fn build(this: &Version) -> anyhow::Result<Option<String>> {
+        Ok(this.build.clone())
+    }
+
+    /// Check the version against a constraint, a comma-separated list of comparators
+    /// which must all match.
+    ///
+    /// Comparators are `=`, `!=`, `<`, `<=`, `>`, `>=`, `~` and `^` followed by a version,
+    /// with the same meaning as in Cargo. A missing operator means `=`.
+    /// The version in a comparator may omit the minor and patch components:
+    /// `=1.2` matches any `1.2.x` and `>1` matches `2.0.0` and above.
+    /// Upper bounds exclude the pre-releases of the bound, so `<2` does not match `2.0.0-rc1`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// version("1.4.0").matches(">=1.2, <2")
+    /// not version("2.0.0-rc1").matches(">=1.2, <2")
+    /// version("1.2.7").matches("~1.2.3")
+    /// version("0.3.1").matches("^0.3")
+    /// # "#);
+    /// ```
+    fn matches(
+        this: &Version,
+        #[starlark(require = pos)] constraint: &str,
+    ) -> anyhow::Result<bool> {
+        this.matches(constraint)
+    }
+}
+
+pub(crate) fn version(globals: &mut GlobalsBuilder) {
+    #[starlark_module]
+    fn version_globals(globals: &mut GlobalsBuilder) {
+        /// Parse a [semantic version](https://semver.org) like `"1.2.3-rc.1+build.5"`.
+        /// A leading `v` is allowed.
+        ///
+        /// Versions compare by semantic version precedence, and have attributes
+        /// `major`, `minor`, `patch`, `prerelease` and `build`.
+        ///
+        /// ```
+        /// # starlark::assert::all_true(r#"
+        /// version("1.10.0") > version("1.9.0")
+        /// version("v1.2.3-rc.1").prerelease == "rc.1"
+        /// version("1.0.0-alpha") < version("1.0.0")
+        /// # "#);
+        /// ```
+        #[starlark(speculative_exec_safe)]
+        fn version(#[starlark(require = pos)] s: &str) -> anyhow::Result<Version> {
+            Version::parse(s)
+        }
+    }
+
+    version_globals(globals);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_version_parse() {
+        assert::pass(
+            r#"
+v = version("1.2.3-rc.1+build.5")
+assert_eq((1, 2, 3, "rc.1", "build.5"), (v.major, v.minor, v.patch, v.prerelease, v.build))
+assert_eq("1.2.3-rc.1+build.5", str(v))
+assert_eq("1.2.3", repr(version("v1.2.3")))
+assert_eq(None, version("1.2.3").prerelease)
+assert_eq("version", type(v))
+"#,
+        );
+        for bad in [
+            "1.2",
+            "1.2.3.4",
+            "01.2.3",
+            "1.2.x",
+            "1.2.3-",
+            "1.2.3-a..b",
+            "1.2.3+",
+        ] {
+            assert::fail(&format!("version('{}')", bad), "Invalid version");
+        }
+    }
+
+    #[test]
+    fn test_version_compare() {
+        assert::pass(
+            r#"
+assert_true(version("1.10.0") > version("1.9.0"))
+assert_true(version("1.0.0-alpha") < version("1.0.0-alpha.1"))
+assert_true(version("1.0.0-alpha.1") < version("1.0.0-alpha.beta"))
+assert_true(version("1.0.0-beta.2") < version("1.0.0-beta.11"))
+assert_true(version("1.0.0-rc.1") < version("1.0.0"))
+assert_eq(version("1.0.0+a"), version("1.0.0+b"))
+d = {version("1.0.0+a"): 1}
+d[version("1.0.0")] = 2
+assert_eq({version("1.0.0"): 2}, d)
+assert_eq(["0.9.0", "1.2.0", "1.10.0"], [str(v) for v in sorted([version("1.10.0"), version("0.9.0"), version("1.2.0")])])
+"#,
+        );
+        assert::fail("version('1.0.0') < '1.0.0'", "not supported");
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert::pass(
+            r#"
+def m(v, c):
+    return version(v).matches(c)
+assert_true(m("1.2.0", ">=1.2, <2"))
+assert_false(m("2.0.0", ">=1.2, <2"))
+assert_false(m("2.0.0-rc1", ">=1.2, <2"))
+assert_true(m("1.2.9", "=1.2"))
+assert_true(m("1.2.9", "1.2"))
+assert_false(m("1.3.0", "==1.2"))
+assert_true(m("1.3.0", "!=1.2"))
+assert_true(m("1.3.0", ">1.2"))
+assert_false(m("1.2.9", ">1.2"))
+assert_true(m("1.2.9", "<=1.2"))
+assert_true(m("1.2.4", "~1.2.3"))
+assert_false(m("1.3.0", "~1.2.3"))
+assert_true(m("1.9.0", "^1.2.3"))
+assert_false(m("2.0.0", "^1.2.3"))
+assert_false(m("0.4.0", "^0.3"))
+assert_false(m("0.0.4", "^0.0.3"))
+assert_true(m("1.0.0-rc.2", ">=1.0.0-rc.1"))
+assert_true(m("1.0.0-rc.2", "<1.0.0-rc.3"))
+assert_false(m("1.0.0-rc.2", "<1.0.0"))
+max = "18446744073709551615"
+assert_true(m(max + ".1.0", max))
+assert_true(m(max + ".1.0", "<=" + max))
+assert_false(m(max + ".1.0", ">" + max))
+assert_true(m(max + ".1.0", "~" + max))
+assert_true(m(max + ".1.0", "^" + max + ".0.1"))
+assert_true(m("1.2." + max, "~1.2"))
+assert_true(m("1." + max + ".3", "1." + max))
+assert_true(m("1." + max + ".3", "~1." + max))
+assert_true(m("1.2." + max, "<=1.2." + max))
+assert_false(m("1.2." + max, ">1.2." + max))
+assert_true(m("0.0." + max, "^0.0." + max))
+assert_true(m("0." + max + ".1", "^0." + max))
+"#,
+        );
+        assert::fail(
+            "version('1.0.0').matches('>=1.x')",
+            "Invalid version constraint `>=1.x`",
+        );
+        assert::fail(
+            "version('1.0.0').matches('>=1,')",
+            "Invalid version constraint `>=1,`",
+        );
+    }
+}
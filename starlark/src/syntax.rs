@@ -19,5 +19,9 @@
 
 pub use starlark_syntax::dialect::Dialect;
 pub use starlark_syntax::dialect::DialectTypes;
+pub use starlark_syntax::syntax::AstBinding;
+pub use starlark_syntax::syntax::AstBindingKind;
 pub use starlark_syntax::syntax::AstLoad;
 pub use starlark_syntax::syntax::AstModule;
+pub use starlark_syntax::syntax::AstNodeAt;
+pub use starlark_syntax::syntax::AstNodeKind;
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+mod allocation_failure;
 mod basic;
 mod bc;
 mod before_stmt;
@@ -28,9 +29,16 @@ mod freeze_access_value;
 mod fstring;
 mod go;
 mod interop;
+mod message_catalog;
+mod mutation_audit;
+mod native_call_interceptor;
 mod opt;
+mod progress;
 mod replace_binary;
 mod runtime;
+mod runtime_lints;
+mod strict;
+mod thaw;
 mod type_annot;
 mod uncategorized;
 pub(crate) mod util;
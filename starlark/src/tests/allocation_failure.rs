@@ -0,0 +1,83 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+const PROGRAM: &str = r#"
+def f(n):
+    xs = []
+    for i in range(n):
+        xs.append([i, str(i) * 20])
+    return xs
+ys = f(10)
+zs = [y[1] for y in ys]
+"#;
+
+/// Evaluate `program`, failing after `fail_after` allocations.
+fn eval(program: &str, fail_after: Option<usize>) -> Result<(), String> {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    if let Some(fail_after) = fail_after {
+        eval.fail_allocations_after(fail_after).unwrap();
+    }
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    match eval.eval_module(ast, &Globals::standard()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Smallest number of allowed allocations for which `run` succeeds.
+fn min_allocations(run: impl Fn(usize) -> Result<(), String>) -> usize {
+    (0..10000).find(|n| run(*n).is_ok()).unwrap()
+}
+
+#[test]
+fn test_fail_allocations_after() {
+    let total = min_allocations(|n| eval(PROGRAM, Some(n)));
+    assert!(total > 20, "{total}");
+    assert_eq!(Ok(()), eval(PROGRAM, None));
+
+    let err = eval(PROGRAM, Some(0)).unwrap_err();
+    assert!(
+        err.contains("Injected allocation failure: more than 0 values were allocated"),
+        "{err}"
+    );
+
+    // Failures are deterministic.
+    for n in 0..total {
+        let r = eval(PROGRAM, Some(n));
+        assert_eq!(r, eval(PROGRAM, Some(n)));
+        let e = r.unwrap_err();
+        assert!(e.contains(&format!("more than {n} values")), "{e}");
+    }
+}
+
+#[test]
+fn test_fail_allocations_in_comprehension() {
+    // A single statement allocating many values fails
+    // before the comprehension completes.
+    let program = "xs = [str(i) * 20 for i in range(100)]";
+    assert!(min_allocations(|n| eval(program, Some(n))) >= 100);
+    let err = eval(program, Some(10)).unwrap_err();
+    assert!(err.contains("more than 10 values"), "{err}");
+    assert!(err.contains("str(i) * 20"), "{err}");
+}
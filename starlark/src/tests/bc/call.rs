@@ -15,6 +15,11 @@
  * limitations under the License.
  */
 
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 use crate::tests::bc::golden::bc_golden_test;
 
 #[test]
@@ -35,3 +40,50 @@ def test(a, k):
 "#,
     );
 }
+
+/// Number of allocations made by calling `f()` defined in `program`.
+fn call_allocations(program: &str) -> usize {
+    // The smallest number of allowed allocations for which the call succeeds.
+    (0..1000)
+        .find(|n| {
+            let module = Module::new();
+            let mut eval = Evaluator::new(&module);
+            let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+            eval.eval_module(ast, &Globals::standard()).unwrap();
+            let f = module.get("f").unwrap();
+            eval.fail_allocations_after(*n).unwrap();
+            eval.eval_function(f, &[], &[]).is_ok()
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_method_call_does_not_allocate_bound_method() {
+    // `x.method(...)` calls the method directly, without allocating a bound method.
+    assert_eq!(
+        0,
+        call_allocations(
+            r#"
+xs = []
+d = {}
+def f():
+    for i in range(100):
+        xs.clear()
+        d.get(i)
+        "abc".startswith("a")
+"#
+        )
+    );
+    // Getting a method without calling it allocates a bound method.
+    assert_eq!(
+        100,
+        call_allocations(
+            r#"
+xs = []
+def f():
+    for i in range(100):
+        xs.clear
+"#
+        )
+    );
+}
@@ -64,3 +64,40 @@ fn test_call_maybe_known_method() {
 fn test_fstring() {
     bc_golden_test("expr_fstring", "def test(x): return f'test: {x}'");
 }
+
+#[test]
+fn test_dict_const_keys() {
+    bc_golden_test(
+        "expr_dict_const_keys",
+        "def test(x): return {'a': x, 'b': 1, 'c': [x]}",
+    );
+}
+
+#[test]
+fn test_dict_const_keys_eval() {
+    // Enough keys for the dict to have a hash index,
+    // like dict literals in generated files.
+    let keys = (0..100).map(|i| format!("'k{i}'")).collect::<Vec<_>>();
+    let dict = keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| format!("{k}: x + {i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    assert::pass(&format!(
+        r#"
+def test(x):
+    return {{{dict}}}
+
+d = test(1)
+assert_eq(100, len(d))
+assert_eq(1, d['k0'])
+assert_eq(100, d['k99'])
+assert_eq('k50', list(d.keys())[50])
+# Each evaluation creates a new dict.
+d['k0'] = 2
+assert_eq(1, test(1)['k0'])
+assert_eq(None, test(1).get('k100'))
+"#
+    ));
+}
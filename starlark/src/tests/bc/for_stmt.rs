@@ -37,3 +37,19 @@ fn test_for_continue() {
         "def test(x):\n  for i in x:\n    if i: continue\n    noop(i)",
     );
 }
+
+#[test]
+fn test_for_range() {
+    bc_golden_test(
+        "for_range",
+        "def test(x):\n  for i in range(len(x)):\n    noop(x[i])",
+    );
+}
+
+#[test]
+fn test_for_range_break_return() {
+    bc_golden_test(
+        "for_range_break_return",
+        "def test(x, y):\n  for i in range(1, x, 2):\n    for j in y:\n      if j: return i\n    if i: break",
+    );
+}
@@ -22,12 +22,16 @@ use starlark_syntax::golden_test_template::golden_test_template;
 
 use crate::assert::Assert;
 use crate::eval::compiler::def::FrozenDef;
+use crate::eval::OptLevel;
 use crate::syntax::Dialect;
 
-fn test_function_bytecode(program: &str) -> String {
+fn test_function_bytecode(program: &str, opt_level: Option<OptLevel>) -> String {
     let program = program.trim();
 
     let mut a = Assert::new();
+    if let Some(opt_level) = opt_level {
+        a.setup_eval(move |eval| eval.set_opt_level(opt_level));
+    }
     a.dialect(&Dialect {
         enable_f_strings: true,
         ..Dialect::Extended
@@ -49,13 +53,22 @@ fn test_function_bytecode(program: &str) -> String {
 }
 
 pub(crate) fn bc_golden_test(test_name: &str, program: &str) {
+    bc_golden_test_impl(test_name, None, program)
+}
+
+/// Like [`bc_golden_test`], but compile with given optimization level.
+pub(crate) fn bc_golden_test_opt_level(test_name: &str, opt_level: OptLevel, program: &str) {
+    bc_golden_test_impl(test_name, Some(opt_level), program)
+}
+
+fn bc_golden_test_impl(test_name: &str, opt_level: Option<OptLevel>, program: &str) {
     if mem::size_of::<usize>() != mem::size_of::<u64>() {
         // Bytecode addresses are different on 32-bit platforms.
         // TODO(nga): still run evaluation on 32-bit platforms, without comparison.
         return;
     }
 
-    let output = test_function_bytecode(program);
+    let output = test_function_bytecode(program, opt_level);
 
     golden_test_template(&format!("src/tests/bc/golden/{test_name}.golden"), &output);
 }
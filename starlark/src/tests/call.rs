@@ -102,6 +102,10 @@ fn test_repeated_parameters() {
     // Starlark requires both these types of errors are _static_ errors
     assert::fail("def f(x,x): pass", "duplicated parameter");
     assert::fail("def f(): pass\ndef g(): f(x=1,x=1)", "repeated named");
+    assert::fail(
+        "def f(): pass\ndef g(): f(x=1,y=2,x=3)",
+        "repeated named argument `x`, first used at assert.bzl:2:12-13",
+    );
 }
 
 #[test]
@@ -117,7 +121,10 @@ fn test_extra_args_native() {
     // In this module to use String functions as a test suite.
     assert::is_true(r#"("bonbon".find("on") == 1)"#);
     // Should fail because find declares #needle, so hide the parameter
-    assert::fail(r#"("bonbon".find(needle = "on") == 1)"#, "extra named");
+    assert::fail(
+        r#"("bonbon".find(needle = "on") == 1)"#,
+        "Positional-only parameter `needle` passed by name",
+    );
     assert::fail(r#""bonbon".find("on", 2, 3, 4)"#, "Wrong number of");
     assert::fail(r#""bonbon".find("on", needless="on")"#, "extra named");
     assert::fail(r#""bonbon".find()"#, "Wrong number of");
@@ -315,3 +322,51 @@ G_F_PTR = g([])
         frame_native_size,
     );
 }
+
+#[test]
+fn test_call_args_limits() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| {
+        eval.set_max_call_args(3);
+        eval.set_max_call_kwargs(2);
+    });
+    a.pass("def f(*args, **kwargs): pass\nf(1, 2, 3, x = 1, y = 2)");
+    a.pass("def f(*args, **kwargs): pass\nf(1, *[2, 3], **{'x': 1})");
+    a.fail(
+        "def f(*args, **kwargs): pass\nf(1, 2, 3, 4)",
+        "Too many positional arguments: 4, the limit is 3",
+    );
+    a.fail(
+        "def f(*args, **kwargs): pass\nf(1, *[2, 3, 4])",
+        "Too many positional arguments: 4, the limit is 3",
+    );
+    a.fail(
+        "def f(*args, **kwargs): pass\nf(x = 1, **{'y': 2, 'z': 3})",
+        "Too many named arguments: 3, the limit is 2",
+    );
+    a.fail("len(*[1, 2, 3, 4])", "Too many positional arguments");
+    a.fail(
+        "def g(x): return struct(a = x, b = x, c = x)\ng(1)",
+        "Too many named arguments",
+    );
+}
+
+#[test]
+fn test_call_args_limits_native_caller() {
+    let mut a = Assert::new();
+    a.setup_eval(|eval| eval.set_max_call_args(3));
+    // `partial` calls `f` from native code with the arguments combined.
+    a.pass("def f(*args): pass\npartial(f, 1, 2)(3)");
+    a.fail(
+        "def f(*args): pass\npartial(f, 1, 2)(3, 4)",
+        "Too many positional arguments: 4, the limit is 3",
+    );
+}
+
+#[test]
+fn test_many_extra_named_args() {
+    let names: Vec<String> = (0..1000).map(|i| format!("a{} = 1", i)).collect();
+    let program = format!("def f(): pass\nf({})", names.join(", "));
+    let err = assert::fail(&program, "`a8` `a9` and 990 more extra named parameter(s)");
+    assert!(!err.to_string().contains("`a999`"));
+}
@@ -20,6 +20,9 @@ mod attrs;
 mod docs;
 mod freeze;
 mod module;
+mod serialize;
+mod starlark_value;
+mod thaw;
 mod trace;
 mod unpack_value;
 mod unpack_value_attr;
@@ -24,6 +24,8 @@ use starlark_derive::AllocFrozenValue;
 use crate as starlark;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::AllocValue;
+use crate::values::FrozenHeap;
+use crate::values::Heap;
 
 #[derive(StarlarkTypeRepr, AllocValue, AllocFrozenValue)]
 enum AllocNoVariant {}
@@ -43,3 +45,42 @@ enum AllocTwoVariants {
 enum AllocWithLifetime<'v> {
     String(&'v str),
 }
+
+#[derive(StarlarkTypeRepr, AllocValue, AllocFrozenValue)]
+struct AllocStructFields {
+    name: String,
+    jobs: u32,
+    tags: Vec<String>,
+}
+
+#[derive(StarlarkTypeRepr, AllocValue, AllocFrozenValue)]
+struct AllocStructNoFields {}
+
+#[test]
+fn test_alloc_struct() {
+    let heap = Heap::new();
+    let value = heap.alloc(AllocStructFields {
+        name: "release".to_owned(),
+        jobs: 4,
+        tags: vec!["x".to_owned()],
+    });
+    assert_eq!(
+        r#"struct(name="release", jobs=4, tags=["x"])"#,
+        value.to_repr()
+    );
+    assert_eq!("struct()", heap.alloc(AllocStructNoFields {}).to_repr());
+}
+
+#[test]
+fn test_alloc_frozen_struct() {
+    let heap = FrozenHeap::new();
+    let value = heap.alloc(AllocStructFields {
+        name: "release".to_owned(),
+        jobs: 4,
+        tags: Vec::new(),
+    });
+    assert_eq!(
+        r#"struct(name="release", jobs=4, tags=[])"#,
+        value.to_value().to_repr()
+    );
+}
@@ -17,7 +17,14 @@
 
 mod basic;
 mod bounds;
+mod const_generics;
+mod containers;
 mod enums;
+mod field_path;
 mod identity;
+mod phantom;
+mod skip;
 mod validator;
+mod validator_freezer;
 mod validator_order;
+mod with;
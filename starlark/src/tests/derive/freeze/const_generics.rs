@@ -0,0 +1,50 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::Value;
+
+#[derive(Freeze)]
+struct Fixed<V, const N: usize>([V; N]);
+
+#[derive(Freeze)]
+struct Named<V, const N: usize, const DEFAULT: bool = false> {
+    values: [V; N],
+}
+
+#[test]
+fn test_freeze_const_generics() {
+    let heap = Heap::new();
+    let fixed = Fixed([heap.alloc(1), heap.alloc("x")]);
+    let named: Named<_, 2> = Named {
+        values: [heap.alloc(2), Value::new_none()],
+    };
+
+    let frozen_heap = FrozenHeap::new();
+    let freezer = Freezer::new(frozen_heap);
+    let fixed: Fixed<FrozenValue, 2> = fixed.freeze(&freezer).unwrap();
+    let named: Named<FrozenValue, 2> = named.freeze(&freezer).unwrap();
+    assert_eq!(Some(1), fixed.0[0].unpack_i32());
+    assert_eq!(Some("x"), fixed.0[1].to_value().unpack_str());
+    assert_eq!(Some(2), named.values[0].unpack_i32());
+    assert!(named.values[1].is_none());
+}
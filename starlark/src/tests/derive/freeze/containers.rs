@@ -0,0 +1,118 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+
+#[derive(Freeze)]
+struct TestContainers<V> {
+    map: HashMap<String, V>,
+    tree: BTreeMap<u32, V>,
+    set: HashSet<u64>,
+    deque: VecDeque<V>,
+    boxed: Box<[V]>,
+    #[freeze(identity)]
+    shared: Arc<Vec<u32>>,
+}
+
+#[test]
+fn test_containers() {
+    let heap = Heap::new();
+    let t = TestContainers {
+        map: HashMap::from([("a".to_owned(), heap.alloc(1))]),
+        tree: BTreeMap::from([(2, heap.alloc(2))]),
+        set: HashSet::from([3]),
+        deque: VecDeque::from([heap.alloc(4)]),
+        boxed: vec![heap.alloc(5)].into_boxed_slice(),
+        shared: Arc::new(vec![6]),
+    };
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t: TestContainers<FrozenValue> = t.freeze(&freezer).unwrap();
+    assert_eq!(Some(1), t.map["a"].unpack_i32());
+    assert_eq!(Some(2), t.tree[&2].unpack_i32());
+    assert!(t.set.contains(&3));
+    assert_eq!(Some(4), t.deque[0].unpack_i32());
+    assert_eq!(Some(5), t.boxed[0].unpack_i32());
+    assert_eq!(6, t.shared[0]);
+}
+
+#[cfg(feature = "smallvec")]
+#[test]
+fn test_smallvec() {
+    use smallvec::smallvec;
+    use smallvec::SmallVec;
+
+    let heap = Heap::new();
+    let small: SmallVec<[_; 2]> = smallvec![heap.alloc(7), heap.alloc(8)];
+    let freezer = Freezer::new(FrozenHeap::new());
+    let small = small.freeze(&freezer).unwrap();
+    assert_eq!(
+        vec![Some(7), Some(8)],
+        small.iter().map(|v| v.unpack_i32()).collect::<Vec<_>>()
+    );
+}
+
+/// Distinct keys which are equal after freezing.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Halved(u32);
+
+impl Freeze for Halved {
+    type Frozen = u32;
+
+    fn freeze(self, _freezer: &Freezer) -> anyhow::Result<u32> {
+        Ok(self.0 / 2)
+    }
+}
+
+fn assert_collision(err: anyhow::Error) {
+    let err = err.to_string();
+    assert!(err.ends_with("` collide after freezing"), "{}", err);
+}
+
+#[test]
+fn test_key_collision() {
+    let freezer = Freezer::new(FrozenHeap::new());
+    let err = HashMap::from([(Halved(2), ()), (Halved(3), ())])
+        .freeze(&freezer)
+        .unwrap_err();
+    assert_collision(err);
+    let err = BTreeMap::from([(Halved(2), ()), (Halved(3), ())])
+        .freeze(&freezer)
+        .unwrap_err();
+    assert_collision(err);
+    let err = HashSet::from([Halved(2), Halved(3)])
+        .freeze(&freezer)
+        .unwrap_err();
+    assert_collision(err);
+    assert_eq!(
+        2,
+        HashSet::from([Halved(2), Halved(4)])
+            .freeze(&freezer)
+            .unwrap()
+            .len()
+    );
+}
@@ -0,0 +1,95 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::error::Error;
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::FreezeError;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+
+struct Failing;
+
+impl Freeze for Failing {
+    type Frozen = Failing;
+
+    fn freeze(self, _freezer: &Freezer) -> anyhow::Result<Failing> {
+        Err(anyhow::anyhow!("failing"))
+    }
+}
+
+#[derive(Freeze)]
+struct Inner {
+    ok: u32,
+    value: Failing,
+}
+
+#[derive(Freeze)]
+enum Middle {
+    Unit,
+    Tuple(u32, Inner),
+}
+
+#[derive(Freeze)]
+struct Outer {
+    middle: Middle,
+}
+
+#[test]
+fn test_field_path() {
+    let freezer = Freezer::new(FrozenHeap::new());
+    let outer = Outer {
+        middle: Middle::Tuple(
+            1,
+            Inner {
+                ok: 2,
+                value: Failing,
+            },
+        ),
+    };
+    let err = outer.freeze(&freezer).err().unwrap();
+    let freeze_error = err.downcast_ref::<FreezeError>().unwrap();
+    assert_eq!(
+        &["Outer.middle", "Middle::Tuple.1", "Inner.value"],
+        freeze_error.path()
+    );
+    assert_eq!("failing", freeze_error.error().to_string());
+    assert_eq!(
+        "Error freezing `Outer.middle -> Middle::Tuple.1 -> Inner.value`",
+        err.to_string()
+    );
+    assert_eq!(
+        "Error freezing `Outer.middle -> Middle::Tuple.1 -> Inner.value`: failing",
+        format!("{:#}", err)
+    );
+    assert_eq!("failing", freeze_error.source().unwrap().to_string());
+}
+
+#[test]
+fn test_no_error() {
+    let freezer = Freezer::new(FrozenHeap::new());
+    assert!(matches!(
+        Outer {
+            middle: Middle::Unit
+        }
+        .freeze(&freezer),
+        Ok(Outer {
+            middle: Middle::Unit
+        })
+    ));
+}
@@ -0,0 +1,82 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#![allow(dead_code)]
+
+use std::marker::PhantomData;
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Value;
+
+/// Marker which does not implement `Freeze`.
+struct Marker;
+
+#[derive(Freeze)]
+struct Tagged<V, M> {
+    value: V,
+    marker: PhantomData<M>,
+}
+
+#[derive(Freeze)]
+struct TaggedLifetime<'v, V> {
+    value: V,
+    marker: PhantomData<&'v Marker>,
+}
+
+#[derive(Freeze)]
+enum TaggedEnum<V, M, S: Default> {
+    Value(V),
+    Marker(#[freeze(identity)] PhantomData<M>),
+    Skipped(#[freeze(skip)] S),
+}
+
+#[test]
+fn test_phantom_param() -> anyhow::Result<()> {
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t: Tagged<Value, Marker> = Tagged {
+        value: Value::new_none(),
+        marker: PhantomData,
+    };
+    let frozen: Tagged<FrozenValue, Marker> = t.freeze(&freezer)?;
+    assert!(frozen.value.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_phantom_lifetime() -> anyhow::Result<()> {
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t: TaggedLifetime<Value> = TaggedLifetime {
+        value: Value::new_none(),
+        marker: PhantomData,
+    };
+    let frozen: TaggedLifetime<'static, FrozenValue> = t.freeze(&freezer)?;
+    assert!(frozen.value.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_phantom_param_enum() -> anyhow::Result<()> {
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t: TaggedEnum<Value, Marker, String> = TaggedEnum::Skipped("x".to_owned());
+    let frozen: TaggedEnum<FrozenValue, Marker, String> = t.freeze(&freezer)?;
+    assert!(matches!(frozen, TaggedEnum::Skipped(s) if s.is_empty()));
+    Ok(())
+}
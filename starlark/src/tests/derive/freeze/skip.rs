@@ -0,0 +1,74 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+
+struct NonFreeze(u32);
+
+#[derive(Freeze)]
+struct TestStruct<V> {
+    value: V,
+    #[freeze(skip)]
+    cache: RefCell<Vec<V>>,
+    #[freeze(default = NonFreeze(17))]
+    scratch: NonFreeze,
+}
+
+#[derive(Freeze)]
+enum TestEnum<V> {
+    A(V, #[freeze(skip)] Option<V>),
+    B(#[freeze(default = 3)] u32),
+}
+
+#[test]
+fn test_struct() {
+    let heap = Heap::new();
+    let t = TestStruct {
+        value: heap.alloc(1),
+        cache: RefCell::new(vec![heap.alloc("x")]),
+        scratch: NonFreeze(5),
+    };
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t: TestStruct<FrozenValue> = t.freeze(&freezer).unwrap();
+    assert_eq!(Some(1), t.value.unpack_i32());
+    assert!(t.cache.borrow().is_empty());
+    assert_eq!(17, t.scratch.0);
+}
+
+#[test]
+fn test_enum() {
+    let heap = Heap::new();
+    let freezer = Freezer::new(FrozenHeap::new());
+    match TestEnum::A(heap.alloc(2), Some(heap.alloc(3)))
+        .freeze(&freezer)
+        .unwrap()
+    {
+        TestEnum::A(v, None) => assert_eq!(Some(2), v.unpack_i32()),
+        _ => panic!("expecting `A` with skipped field"),
+    }
+    match TestEnum::<FrozenValue>::B(5).freeze(&freezer).unwrap() {
+        TestEnum::B(x) => assert_eq!(3, x),
+        _ => panic!("expecting `B`"),
+    }
+}
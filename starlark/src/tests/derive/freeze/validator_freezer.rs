@@ -0,0 +1,61 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::values::list::ListRef;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+
+#[derive(Freeze)]
+#[freeze(validator = check_list, bounds = "V: Freeze<Frozen = FrozenValue>")]
+struct Test<V> {
+    field: V,
+}
+
+fn check_list(test: &Test<FrozenValue>, freezer: &Freezer) -> anyhow::Result<()> {
+    // Frozen values can be resolved in the validator.
+    let list =
+        ListRef::from_value(test.field.to_value()).ok_or_else(|| anyhow::anyhow!("not a list"))?;
+    // The freezer is the one used to freeze the value.
+    freezer.freeze(list[0])?;
+    Ok(())
+}
+
+#[test]
+fn test_ok() -> anyhow::Result<()> {
+    let heap = Heap::new();
+    let t = Test {
+        field: heap.alloc(vec![1]),
+    };
+    let freezer = Freezer::new(FrozenHeap::new());
+    t.freeze(&freezer)?;
+    Ok(())
+}
+
+#[test]
+fn test_fail() {
+    let heap = Heap::new();
+    let t = Test {
+        field: heap.alloc(1),
+    };
+    let freezer = Freezer::new(FrozenHeap::new());
+    let err = t.freeze(&freezer).err().unwrap();
+    assert_eq!("not a list", err.to_string());
+}
@@ -0,0 +1,88 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::FreezeError;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+
+/// Foreign type which does not implement `Freeze`.
+struct Foreign<V>(Vec<V>);
+
+fn freeze_foreign<V: Freeze>(
+    foreign: Foreign<V>,
+    freezer: &Freezer,
+) -> anyhow::Result<Foreign<V::Frozen>> {
+    Ok(Foreign(foreign.0.freeze(freezer)?))
+}
+
+fn freeze_name(name: String, _freezer: &Freezer) -> anyhow::Result<String> {
+    if name.is_empty() {
+        return Err(anyhow::anyhow!("empty name"));
+    }
+    Ok(name)
+}
+
+#[derive(Freeze)]
+struct TestStruct<V> {
+    #[freeze(with = "freeze_foreign")]
+    foreign: Foreign<V>,
+}
+
+#[derive(Freeze)]
+struct TestError {
+    #[freeze(with = "self::freeze_name")]
+    name: String,
+}
+
+#[test]
+fn test_with() {
+    let heap = Heap::new();
+    let t = TestStruct {
+        foreign: Foreign(vec![heap.alloc(1), heap.alloc(2)]),
+    };
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t: TestStruct<FrozenValue> = t.freeze(&freezer).unwrap();
+    assert_eq!(
+        vec![Some(1), Some(2)],
+        t.foreign
+            .0
+            .iter()
+            .map(|v| v.unpack_i32())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_with_error() {
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t = TestError {
+        name: "x".to_owned(),
+    };
+    assert_eq!("x", t.freeze(&freezer).unwrap().name);
+
+    let t = TestError {
+        name: String::new(),
+    };
+    let err = t.freeze(&freezer).err().unwrap();
+    let err = err.downcast_ref::<FreezeError>().unwrap();
+    assert_eq!(&["TestError.name"], err.path());
+    assert_eq!("empty name", err.error().to_string());
+}
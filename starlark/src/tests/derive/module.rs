@@ -15,11 +15,17 @@
  * limitations under the License.
  */
 
+mod async_fun;
 mod basic;
+mod coercion;
+mod declared_type;
 mod default_value;
 mod methods;
 mod named_positional;
 mod return_impl;
+mod return_option;
+mod signature_error;
 mod special_params;
 mod type_annotation;
+mod typed_error;
 mod unpack_value;
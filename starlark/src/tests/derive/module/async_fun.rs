@@ -0,0 +1,137 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::task::Context;
+use std::task::Poll;
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
+use crate::eval::AsyncExecutor;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::none::NoneType;
+
+/// Future which is pending once before completing, like a future waiting for I/O.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+async fn fetch(x: i32) -> anyhow::Result<i32> {
+    YieldOnce(false).await;
+    if x < 0 {
+        return Err(anyhow::anyhow!("Negative: {x}"));
+    }
+    Ok(x * 10)
+}
+
+#[starlark_module]
+fn functions(builder: &mut GlobalsBuilder) {
+    async fn async_fetch(x: i32) -> anyhow::Result<i32> {
+        let y = fetch(x).await?;
+        Ok(y + 1)
+    }
+
+    async fn async_fetch_option(x: i32) -> Option<i32> {
+        fetch(x).await.ok()
+    }
+
+    async fn async_with_eval(
+        #[starlark(require = named)] x: i32,
+        eval: &mut Evaluator,
+    ) -> anyhow::Result<NoneType> {
+        let y = fetch(x).await?;
+        eval.heap().alloc(y);
+        Ok(NoneType)
+    }
+}
+
+#[test]
+fn test_async_function() {
+    let mut a = Assert::new();
+    a.globals_add(functions);
+    a.eq("31", "async_fetch(3)");
+    a.fail("async_fetch(-1)", "Negative: -1");
+    a.eq("20", "async_fetch_option(2)");
+    a.eq("None", "async_fetch_option(-2)");
+    a.eq("None", "async_with_eval(x = 1)");
+    a.fail("async_with_eval(1)", "named-only");
+}
+
+#[test]
+fn test_async_function_signature() {
+    let mut a = Assert::new();
+    a.globals_add(functions);
+    a.fail("noop(async_fetch)(1, 2)", "async_fetch(x: int)");
+}
+
+struct CountingExecutor(AtomicUsize);
+
+impl AsyncExecutor for CountingExecutor {
+    fn block_on(&self, mut future: Pin<&mut (dyn Future<Output = ()> + '_)>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        while future.as_mut().poll(&mut cx).is_pending() {}
+    }
+}
+
+fn noop_waker() -> std::task::Waker {
+    struct Noop;
+    impl std::task::Wake for Noop {
+        fn wake(self: std::sync::Arc<Self>) {}
+    }
+    std::task::Waker::from(std::sync::Arc::new(Noop))
+}
+
+#[test]
+fn test_async_executor() {
+    let executor = CountingExecutor(AtomicUsize::new(0));
+    let globals = GlobalsBuilder::standard().with(functions).build();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_async_executor(&executor);
+    let ast = AstModule::parse(
+        "x.star",
+        "[async_fetch(1), async_fetch(2)]".to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    let res = eval.eval_module(ast, &globals).unwrap();
+    assert_eq!("[11, 21]", res.to_repr());
+    assert_eq!(2, executor.0.load(Ordering::SeqCst));
+}
@@ -0,0 +1,59 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+
+#[starlark_module]
+fn coercion_globals(globals: &mut GlobalsBuilder) {
+    fn double(x: i32) -> anyhow::Result<i32> {
+        Ok(x * 2)
+    }
+
+    fn maybe_double(#[starlark(default = 5)] x: i32, y: Option<i32>) -> anyhow::Result<i32> {
+        Ok(x * 2 + y.unwrap_or(0))
+    }
+}
+
+fn coercion_str_to_int(globals: &mut GlobalsBuilder) {
+    coercion_globals(globals);
+    globals.coercion(|v, heap| Some(heap.alloc(v.unpack_str()?.parse::<i32>().ok()?)));
+}
+
+#[test]
+fn test_coercion() {
+    let mut a = Assert::new();
+    a.disable_static_typechecking();
+    a.globals_add(coercion_str_to_int);
+    a.eq("6", "double(3)");
+    a.eq("6", "double('3')");
+    a.eq("21", "maybe_double('10', y = '1')");
+    a.eq("10", "maybe_double()");
+    a.eq("6", "def f(x): return double(x)\nf('3')");
+    a.fail("double('x')", "Type of parameter `x` doesn't match");
+}
+
+#[test]
+fn test_coercion_per_globals() {
+    let mut a = Assert::new();
+    a.disable_static_typechecking();
+    a.globals_add(coercion_globals);
+    a.fail("double('3')", "Type of parameter `x` doesn't match");
+}
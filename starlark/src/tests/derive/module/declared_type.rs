@@ -0,0 +1,92 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::values::list_or_tuple::UnpackListOrTuple;
+use crate::values::none::NoneOr;
+use crate::values::Value;
+
+/// Named type alias usable in declarations.
+type Names = UnpackListOrTuple<String>;
+
+#[starlark_module]
+fn declared_type_functions(globals: &mut GlobalsBuilder) {
+    fn count_names<'v>(
+        #[starlark(require = pos, ty = Names)] names: Value<'v>,
+    ) -> starlark::Result<i32> {
+        names.length()
+    }
+
+    #[starlark(return_ty = NoneOr<i32>)]
+    fn identity<'v>(#[starlark(require = pos)] x: Value<'v>) -> anyhow::Result<Value<'v>> {
+        Ok(x)
+    }
+}
+
+fn assert() -> Assert<'static> {
+    let mut a = Assert::new();
+    a.globals_add(declared_type_functions);
+    a
+}
+
+#[test]
+fn test_declared_type_pass() {
+    let a = assert();
+    a.eq("2", "count_names(['a', 'b'])");
+    a.eq("1", "count_names(('a',))");
+    a.eq("None", "identity(None)");
+    a.eq("1", "identity(1)");
+}
+
+#[test]
+fn test_declared_type_docs() {
+    let globals = GlobalsBuilder::new().with(declared_type_functions).build();
+    let docs = globals.documentation().render_as_code();
+    assert!(docs.contains("list[str] | tuple[str, ...]"), "{docs}");
+    assert!(docs.contains("None | int"), "{docs}");
+}
+
+#[test]
+fn test_declared_type_compile_time() {
+    assert().fail(
+        r#"
+def test():
+    count_names([1])
+"#,
+        "Expected type `list[str] | tuple[str, ...]` but got `list[int]`",
+    );
+}
+
+#[test]
+fn test_declared_type_runtime() {
+    let a = assert();
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    a.fail(
+        "count_names(noop([1]))",
+        "does not match the type annotation `list[str] | tuple[str, ...]` for argument `names`",
+    );
+    a.fail(
+        "identity(noop('x'))",
+        "does not match the type annotation `None | int` for return type",
+    );
+}
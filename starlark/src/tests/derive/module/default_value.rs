@@ -20,12 +20,31 @@ use starlark_derive::starlark_module;
 use crate as starlark;
 use crate::assert::Assert;
 use crate::environment::GlobalsBuilder;
+use crate::values::Value;
 
 #[starlark_module]
 fn default_value_functions(globals: &mut GlobalsBuilder) {
     fn foo(#[starlark(default = 75)] x: i32) -> anyhow::Result<i32> {
         Ok(x)
     }
+
+    fn empty_list_value<'v>(
+        #[starlark(default = vec![])] x: Value<'v>,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(x)
+    }
+
+    fn empty_dict_value<'v>(
+        #[starlark(default = SmallMap::new())] x: Value<'v>,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(x)
+    }
+
+    fn list_value<'v>(
+        #[starlark(default = vec!["a", "b"])] x: Value<'v>,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(x)
+    }
 }
 
 #[test]
@@ -35,3 +54,23 @@ fn test_default_value() {
     a.eq("74", "foo(74)");
     a.eq("75", "foo()");
 }
+
+#[test]
+fn test_default_value_allocated() {
+    let mut a = Assert::new();
+    a.globals_add(default_value_functions);
+    a.eq("[]", "empty_list_value()");
+    a.eq("{}", "empty_dict_value()");
+    a.eq("['a', 'b']", "list_value()");
+    // Defaults are allocated once on the frozen heap, so they cannot be modified.
+    a.fail("empty_list_value().append(1)", "Immutable");
+    a.pass("x = empty_list_value([]); x.append(1)");
+}
+
+#[test]
+fn test_default_value_allocated_signature() {
+    let mut a = Assert::new();
+    a.globals_add(default_value_functions);
+    a.fail("noop(empty_list_value)(1, 2)", "empty_list_value(x = [])");
+    a.fail("noop(empty_dict_value)(1, 2)", "empty_dict_value(x = {})");
+}
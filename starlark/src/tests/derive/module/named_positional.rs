@@ -50,6 +50,16 @@ fn named_positional_functions(globals: &mut GlobalsBuilder) {
     ) -> anyhow::Result<i32> {
         Ok(x + args.items.iter().sum::<i32>())
     }
+
+    fn pos_or_named_after_named_only(
+        #[starlark(require = pos)] a: i32,
+        b: i32,
+        #[starlark(require = named)] c: i32,
+        d: i32,
+        #[starlark(require = named, default = 0)] e: i32,
+    ) -> anyhow::Result<i32> {
+        Ok(a * 10000 + b * 1000 + c * 100 + d * 10 + e)
+    }
 }
 
 #[test]
@@ -57,7 +67,10 @@ fn test_positional_only() {
     let mut a = Assert::new();
     a.globals_add(named_positional_functions);
     a.eq("17", "positional(17)");
-    a.fail("noop(positional)(x=19)", "extra named parameter");
+    a.fail(
+        "noop(positional)(x=19)",
+        "Positional-only parameter `x` passed by name",
+    );
 }
 
 #[test]
@@ -94,3 +107,28 @@ fn test_named_after_args_explicitly_marked() {
         "Missing parameter",
     );
 }
+
+#[test]
+fn test_named_only_passed_positionally() {
+    let mut a = Assert::new();
+    a.globals_add(named_positional_functions);
+    a.fail(
+        "noop(named_only)(37)",
+        "it is named-only and must be passed as `x=...`",
+    );
+}
+
+#[test]
+fn test_params_after_named_only_are_named_only() {
+    let mut a = Assert::new();
+    a.globals_add(named_positional_functions);
+    a.eq(
+        "12345",
+        "pos_or_named_after_named_only(1, 2, c=3, d=4, e=5)",
+    );
+    a.eq("12340", "pos_or_named_after_named_only(1, b=2, d=4, c=3)");
+    a.fail(
+        "noop(pos_or_named_after_named_only)(1, 2, 3, 4)",
+        "pos_or_named_after_named_only(a: int, /, b: int, *, c: int, d: int, e: int = 0) -> int",
+    );
+}
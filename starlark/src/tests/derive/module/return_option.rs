@@ -0,0 +1,80 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::environment::MethodsBuilder;
+use crate::values::list::ListRef;
+use crate::values::Value;
+
+#[starlark_module]
+fn return_option_globals(globals: &mut GlobalsBuilder) {
+    fn find_index(xs: &ListRef, x: i32) -> Option<i32> {
+        for (i, y) in xs.iter().enumerate() {
+            if y.unpack_i32()? == x {
+                return Some(i as i32);
+            }
+        }
+        None
+    }
+
+    fn checked_half(x: i32) -> anyhow::Result<Option<i32>> {
+        if x < 0 {
+            return Err(anyhow::anyhow!("negative"));
+        }
+        Ok(if x % 2 == 0 { Some(x / 2) } else { None })
+    }
+}
+
+#[starlark_module]
+fn _test_return_option_methods(methods: &mut MethodsBuilder) {
+    fn first<'v>(this: &ListRef<'v>) -> Option<Value<'v>> {
+        this.content().first().copied()
+    }
+
+    #[starlark(attribute)]
+    fn maybe<'v>(this: Value<'v>) -> Option<Value<'v>> {
+        Some(this)
+    }
+}
+
+#[test]
+fn test_return_option() {
+    let mut a = Assert::new();
+    a.globals_add(return_option_globals);
+    a.eq("1", "find_index([3, 4, 5], 4)");
+    a.eq("None", "find_index([3, 4, 5], 6)");
+    a.eq("None", "find_index(['x', 4], 4)");
+    a.eq("2", "checked_half(4)");
+    a.eq("None", "checked_half(3)");
+    a.fail("checked_half(-1)", "negative");
+}
+
+#[test]
+fn test_return_option_signature() {
+    let mut a = Assert::new();
+    a.globals_add(return_option_globals);
+    a.pass(
+        r#"
+def test(xs: list[int]) -> None | int:
+    return find_index(xs, 1)
+test([1])
+"#,
+    );
+}
@@ -0,0 +1,68 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+
+#[starlark_module]
+fn signature_error_globals(globals: &mut GlobalsBuilder) {
+    fn repeat(s: &str, #[starlark(require = named)] times: i32) -> anyhow::Result<String> {
+        Ok(s.repeat(times as usize))
+    }
+}
+
+#[test]
+fn test_signature_error_highlights_param() {
+    let mut a = Assert::new();
+    a.globals_add(signature_error_globals);
+    a.eq("'xx'", "repeat('x', times = 2)");
+    let err = a.fail("repeat('x', times = 'a')", "parameter `times`");
+    let msg = err.to_string();
+    assert!(
+        msg.contains("Signature: repeat(s: str, *, times: int) -> str\n"),
+        "{msg}"
+    );
+    assert!(
+        msg.contains("\n                             ^^^^^^^^^^\n"),
+        "{msg}"
+    );
+}
+
+#[test]
+fn test_signature_error_missing_param() {
+    let mut a = Assert::new();
+    a.globals_add(signature_error_globals);
+    let err = a.fail("repeat('x')", "Missing parameter `times`");
+    assert!(
+        err.to_string()
+            .contains("Signature: repeat(s: str, *, times: int) -> str"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_signature_error_builtin_method() {
+    let a = Assert::new();
+    let err = a.fail("'a'.split(1)", "parameter `sep`");
+    assert!(
+        err.to_string().contains("Signature: string.split("),
+        "{err}"
+    );
+}
@@ -0,0 +1,87 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+
+use crate as starlark;
+use crate::assert::Assert;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+enum LookupError {
+    #[error("Key `{0}` not found")]
+    NotFound(String),
+}
+
+impl From<LookupError> for starlark::Error {
+    fn from(e: LookupError) -> Self {
+        starlark::Error::new_value(e)
+    }
+}
+
+fn find(key: &str) -> Result<i32, LookupError> {
+    match key {
+        "a" => Ok(1),
+        _ => Err(LookupError::NotFound(key.to_owned())),
+    }
+}
+
+#[starlark_module]
+fn functions(builder: &mut GlobalsBuilder) {
+    fn lookup(key: &str) -> Result<i32, LookupError> {
+        find(key)
+    }
+
+    fn lookup_std(key: &str) -> std::result::Result<i32, LookupError> {
+        find(key)
+    }
+}
+
+#[test]
+fn test_typed_error() {
+    let mut a = Assert::new();
+    a.globals_add(functions);
+    a.eq("1", "lookup('a')");
+    a.eq("1", "lookup_std('a')");
+    a.fail("lookup('b')", "Key `b` not found");
+    a.fail("lookup_std('b')", "Key `b` not found");
+    a.fail("noop(lookup)(1, 2)", "lookup(key: str)");
+}
+
+#[test]
+fn test_typed_error_downcast() {
+    let globals = GlobalsBuilder::standard().with(functions).build();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    let ast = AstModule::parse(
+        "x.star",
+        "def f(k):\n  return lookup(k)\nf('b')".to_owned(),
+        &Dialect::Standard,
+    )
+    .unwrap();
+    let err = eval.eval_module(ast, &globals).unwrap_err();
+    assert_eq!("value", err.kind().code());
+    assert_eq!(
+        Some(&LookupError::NotFound("b".to_owned())),
+        err.downcast_ref::<LookupError>()
+    );
+    assert_eq!(None, err.downcast_ref::<std::io::Error>().map(|e| e.kind()));
+}
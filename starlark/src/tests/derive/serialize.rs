@@ -0,0 +1,197 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use allocative::Allocative;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::Freeze;
+use starlark_derive::StarlarkSerialize;
+use starlark_derive::Trace;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::assert::Assert;
+use crate::coerce::Coerce;
+use crate::environment::GlobalsBuilder;
+use crate::starlark_complex_value;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[derive(
+    Debug,
+    derive_more::Display,
+    ProvidesStaticType,
+    Allocative,
+    StarlarkSerialize
+)]
+#[display(fmt = "Provider")]
+struct Provider {
+    name: String,
+    #[serialize(rename = "deps")]
+    dependencies: Vec<String>,
+    #[serialize(skip)]
+    #[allow(dead_code)]
+    cache: Vec<u32>,
+}
+
+#[starlark_value(type = "Provider")]
+impl<'v> StarlarkValue<'v> for Provider {}
+
+#[derive(StarlarkSerialize)]
+struct Pair<A, B> {
+    first: A,
+    second: B,
+}
+
+#[derive(StarlarkSerialize)]
+struct Meters(f64);
+
+#[derive(StarlarkSerialize)]
+struct Point(
+    i32,
+    #[serialize(skip)]
+    #[allow(dead_code)]
+    i32,
+    i32,
+);
+
+#[derive(StarlarkSerialize)]
+struct Empty;
+
+#[derive(StarlarkSerialize)]
+enum Shape {
+    Dot,
+    #[serialize(rename = "circle")]
+    Circle(f64),
+    Line(i32, i32),
+    Rect {
+        width: i32,
+        #[serialize(rename = "h")]
+        height: i32,
+        #[serialize(skip)]
+        #[allow(dead_code)]
+        area: i32,
+    },
+}
+
+/// Complex value with fields pointing to other values.
+#[derive(
+    Clone,
+    Debug,
+    Coerce,
+    derive_more::Display,
+    Trace,
+    Freeze,
+    ProvidesStaticType,
+    Allocative,
+    StarlarkSerialize
+)]
+#[display(fmt = "target({})", name)]
+#[repr(C)]
+struct TargetGen<V> {
+    name: String,
+    deps: V,
+    #[serialize(skip)]
+    #[allow(dead_code)]
+    attrs: Vec<V>,
+}
+
+starlark_complex_value!(Target);
+
+#[starlark_value(type = "target")]
+impl<'v, V: ValueLike<'v>> StarlarkValue<'v> for TargetGen<V> where Self: ProvidesStaticType<'v> {}
+
+#[starlark_module]
+fn target_functions(globals: &mut GlobalsBuilder) {
+    fn target<'v>(name: String, deps: Value<'v>) -> anyhow::Result<Target<'v>> {
+        Ok(TargetGen {
+            name,
+            deps,
+            attrs: vec![deps],
+        })
+    }
+}
+
+#[test]
+fn test_derive_serialize() {
+    let heap = Heap::new();
+    let provider = heap.alloc_simple(Provider {
+        name: "lib".to_owned(),
+        dependencies: vec!["a".to_owned(), "b".to_owned()],
+        cache: vec![1, 2],
+    });
+    assert_eq!(
+        r#"{"name":"lib","deps":["a","b"]}"#,
+        provider.to_json().unwrap()
+    );
+}
+
+#[test]
+fn test_derive_serialize_generic() {
+    let pair = Pair {
+        first: 1,
+        second: "x",
+    };
+    assert_eq!(
+        r#"{"first":1,"second":"x"}"#,
+        serde_json::to_string(&pair).unwrap()
+    );
+}
+
+#[test]
+fn test_derive_serialize_tuple_and_unit() {
+    assert_eq!("1.5", serde_json::to_string(&Meters(1.5)).unwrap());
+    assert_eq!("[1,3]", serde_json::to_string(&Point(1, 2, 3)).unwrap());
+    assert_eq!("null", serde_json::to_string(&Empty).unwrap());
+}
+
+#[test]
+fn test_derive_serialize_enum() {
+    assert_eq!(r#""Dot""#, serde_json::to_string(&Shape::Dot).unwrap());
+    assert_eq!(
+        r#"{"circle":2.0}"#,
+        serde_json::to_string(&Shape::Circle(2.0)).unwrap()
+    );
+    assert_eq!(
+        r#"{"Line":[1,2]}"#,
+        serde_json::to_string(&Shape::Line(1, 2)).unwrap()
+    );
+    assert_eq!(
+        r#"{"Rect":{"width":1,"h":2}}"#,
+        serde_json::to_string(&Shape::Rect {
+            width: 1,
+            height: 2,
+            area: 2
+        })
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_derive_serialize_complex_value() {
+    let mut a = Assert::new();
+    a.globals_add(target_functions);
+    a.eq(
+        r#"'{"name":"lib","deps":["a",{"b":1}]}'"#,
+        r#"json.encode(target("lib", ["a", {"b": 1}]))"#,
+    );
+    a.eq(
+        r#"'[{"name":"lib","deps":[]}]'"#,
+        r#"json.encode([target("lib", [])])"#,
+    );
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Tests for `#[derive(StarlarkValue)]` and `#[starlark_methods]`.
+
+use allocative::Allocative;
+use derive_more::Display;
+use starlark_derive::starlark_methods;
+use starlark_derive::NoSerialize;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::assert::Assert;
+use crate::docs::DocItem;
+use crate::docs::DocMember;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+
+/// Settings of a build.
+#[derive(
+    Debug,
+    Clone,
+    Display,
+    ProvidesStaticType,
+    NoSerialize,
+    Allocative,
+    StarlarkValue
+)]
+#[display(fmt = "config({})", name)]
+#[starlark(type = "config", methods)]
+struct Config {
+    /// Name of the configuration.
+    name: String,
+    jobs: i32,
+    #[starlark(skip)]
+    secret: String,
+}
+
+#[starlark_methods]
+impl Config {
+    /// Name with the given suffix.
+    fn with_suffix(&self, suffix: &str) -> anyhow::Result<String> {
+        Ok(format!("{}{}", self.name, suffix))
+    }
+
+    fn scaled(&self, #[starlark(require = named, default = 2)] factor: i32) -> anyhow::Result<i32> {
+        Ok(self.jobs * factor)
+    }
+
+    #[starlark(attribute)]
+    fn secret_len(&self) -> anyhow::Result<i32> {
+        Ok(self.secret.len() as i32)
+    }
+
+    fn new(name: &str) -> Config {
+        Config {
+            name: name.to_owned(),
+            jobs: 1,
+            secret: String::new(),
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Display,
+    ProvidesStaticType,
+    NoSerialize,
+    Allocative,
+    StarlarkValue
+)]
+#[display(fmt = "point")]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[test]
+fn test_derive_starlark_value() {
+    let mut a = Assert::new();
+    a.globals_add(|gb| {
+        gb.set(
+            "config",
+            Config {
+                name: "release".to_owned(),
+                jobs: 4,
+                secret: "abc".to_owned(),
+            },
+        );
+        gb.set("point", Point { x: 1, y: 2 });
+    });
+    a.eq("'config'", "type(config)");
+    a.eq("'release'", "config.name");
+    a.eq("'release-x'", "config.with_suffix('-x')");
+    a.eq("8", "config.scaled()");
+    a.eq("12", "config.scaled(factor = 3)");
+    a.eq("3", "config.secret_len");
+    a.is_true("not hasattr(config, 'secret')");
+    a.eq(
+        "['jobs', 'name', 'scaled', 'secret_len', 'with_suffix']",
+        "dir(config)",
+    );
+    a.eq("'Point'", "type(point)");
+    a.eq("3", "point.x + point.y");
+}
+
+#[test]
+fn test_derive_starlark_value_documentation() {
+    let heap = Heap::new();
+    let config = Config::new("x");
+    assert_eq!("x", config.name);
+    let Some(DocItem::Type(docs)) = heap.alloc(config).get_ref().documentation() else {
+        panic!("expected type documentation");
+    };
+    assert_eq!("Settings of a build.", docs.docs.unwrap().summary);
+    let Some(DocMember::Property(name)) = docs.members.get("name") else {
+        panic!("expected `name` property");
+    };
+    assert_eq!(
+        "Name of the configuration.",
+        name.docs.as_ref().unwrap().summary
+    );
+    assert_eq!("str", name.typ.to_string());
+    let Some(DocMember::Function(with_suffix)) = docs.members.get("with_suffix") else {
+        panic!("expected `with_suffix` method");
+    };
+    assert_eq!(
+        "Name with the given suffix.",
+        with_suffix.docs.as_ref().unwrap().summary
+    );
+    assert!(docs.members.get("secret").is_none());
+}
@@ -0,0 +1,73 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate as starlark;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::Thaw;
+use crate::values::Thawer;
+use crate::values::Value;
+
+#[derive(Freeze, Thaw)]
+struct TestStruct<V> {
+    values: Vec<V>,
+    #[freeze(identity)]
+    #[thaw(identity)]
+    name: String,
+}
+
+#[derive(Freeze, Thaw)]
+enum TestEnum<V> {
+    A(V),
+    B,
+}
+
+#[test]
+fn test_freeze_thaw() {
+    let heap = Heap::new();
+    let t = TestStruct {
+        values: vec![heap.alloc(vec![1]), heap.alloc("x")],
+        name: "t".to_owned(),
+    };
+    let e = TestEnum::A(heap.alloc(vec![2]));
+
+    let freezer = Freezer::new(FrozenHeap::new());
+    let t = t.freeze(&freezer).unwrap();
+    let e = e.freeze(&freezer).unwrap();
+
+    let heap = Heap::new();
+    let thawer = Thawer::new(&heap);
+    let t: TestStruct<Value> = t.thaw(&thawer).unwrap();
+    assert_eq!("t", t.name);
+    assert!(t.values[0].unpack_frozen().is_none());
+    assert_eq!("[1]", t.values[0].to_repr());
+    assert_eq!(Some("x"), t.values[1].unpack_str());
+    match e.thaw(&thawer).unwrap() {
+        TestEnum::A(v) => {
+            assert!(v.unpack_frozen().is_none());
+            assert_eq!("[2]", v.to_repr());
+        }
+        TestEnum::B => panic!("expecting `A`"),
+    }
+    assert!(matches!(
+        TestEnum::<FrozenValue>::B.thaw(&thawer).unwrap(),
+        TestEnum::B
+    ));
+}
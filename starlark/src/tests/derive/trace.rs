@@ -16,4 +16,5 @@
  */
 
 mod enums;
+mod ignore;
 mod statics;
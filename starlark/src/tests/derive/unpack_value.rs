@@ -16,11 +16,17 @@
  */
 
 use either::Either;
+use starlark_derive::starlark_module;
 
 use crate as starlark;
+use crate::assert::Assert;
 use crate::const_frozen_string;
+use crate::environment::GlobalsBuilder;
+use crate::values::dict::AllocDict;
+use crate::values::structs::AllocStruct;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::typing::StarlarkNever;
+use crate::values::Heap;
 use crate::values::UnpackValue;
 use crate::values::Value;
 
@@ -90,3 +96,84 @@ fn test_unpack_value() {
         WithLifetime::unpack_value(const_frozen_string!("def").to_value()).unwrap(),
     );
 }
+
+#[derive(StarlarkTypeRepr, UnpackValue, Eq, PartialEq, Debug)]
+struct StructFields<'v> {
+    name: &'v str,
+    #[unpack(default)]
+    jobs: u32,
+    #[unpack(rename = "type")]
+    ty: String,
+}
+
+#[test]
+fn test_unpack_struct() {
+    fn unpack(v: Value) -> Result<Option<StructFields>, String> {
+        StructFields::unpack_value(v).map_err(|e| e.to_string())
+    }
+
+    assert_eq!("struct(..)", StructFields::starlark_type_repr().to_string());
+
+    let heap = Heap::new();
+
+    let value = heap.alloc(AllocStruct([
+        ("name", heap.alloc("x")),
+        ("jobs", heap.alloc(2)),
+        ("type", heap.alloc("release")),
+    ]));
+    assert_eq!(
+        Ok(Some(StructFields {
+            name: "x",
+            jobs: 2,
+            ty: "release".to_owned(),
+        })),
+        unpack(value)
+    );
+
+    let value = heap.alloc(AllocDict([("name", "y"), ("type", "debug")]));
+    assert_eq!(
+        Ok(Some(StructFields {
+            name: "y",
+            jobs: 0,
+            ty: "debug".to_owned(),
+        })),
+        unpack(value)
+    );
+
+    assert_eq!(Ok(None), unpack(heap.alloc(1)));
+    assert_eq!(
+        Err("Cannot unpack `StructFields`: missing field `type`".to_owned()),
+        unpack(heap.alloc(AllocDict([("name", "y")])))
+    );
+    assert_eq!(
+        Err("Cannot unpack `StructFields`: unexpected field `extra`".to_owned()),
+        unpack(heap.alloc(AllocDict([
+            ("name", "y"),
+            ("type", "debug"),
+            ("extra", "z")
+        ])))
+    );
+    assert_eq!(
+        Err(
+            "Cannot unpack `StructFields`: field `name` expected `str`, got `int (repr: 1)`"
+                .to_owned()
+        ),
+        unpack(heap.alloc(AllocStruct([("name", 1), ("type", 2)])))
+    );
+}
+
+#[test]
+fn test_unpack_struct_in_module() {
+    #[starlark_module]
+    fn config_globals(builder: &mut GlobalsBuilder) {
+        fn jobs<'v>(config: StructFields<'v>) -> anyhow::Result<u32> {
+            Ok(config.jobs)
+        }
+    }
+
+    let mut a = Assert::new();
+    a.globals_add(config_globals);
+    a.eq("3", "jobs({'name': 'x', 'type': 't', 'jobs': 3})");
+    a.eq("0", "jobs(struct(name = 'x', type = 't'))");
+    a.fail("jobs({'name': 'x'})", "missing field `type`");
+}
@@ -150,3 +150,60 @@ def with_arguments(*args, **kwargs) -> int: pass
         assert_eq!(&expected, got.get(&name).unwrap());
     }
 }
+
+#[starlark_module]
+#[allow(unused_variables)] // Since this is for a test
+fn param_docs_globals(builder: &mut GlobalsBuilder) {
+    /// Add numbers.
+    ///
+    /// # Returns
+    ///
+    /// The sum.
+    fn add(
+        /// First number.
+        x: i32,
+        /// Second number.
+        ///
+        /// Defaults to one.
+        #[starlark(require = named, default = 1)]
+        y: i32,
+        /// Ignored.
+        #[starlark(kwargs)]
+        kwargs: SmallMap<String, Value>,
+    ) -> anyhow::Result<i32> {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn test_rustdoc_param_docs() {
+    let docs = GlobalsBuilder::new()
+        .with(param_docs_globals)
+        .build()
+        .documentation();
+    let Some(DocItem::Member(DocMember::Function(add))) = docs.members.get("add") else {
+        panic!("no `add` function");
+    };
+    assert_eq!("Add numbers.", add.docs.as_ref().unwrap().summary);
+    assert_eq!("The sum.", add.ret.docs.as_ref().unwrap().summary);
+    let docs: Vec<_> = add
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            DocParam::Arg { name, docs, .. } | DocParam::Kwargs { name, docs, .. } => Some((
+                name.as_str(),
+                docs.as_ref().map(|d| d.summary.as_str()),
+                docs.as_ref().and_then(|d| d.details.as_deref()),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            ("x", Some("First number."), None),
+            ("y", Some("Second number."), Some("Defaults to one.")),
+            ("**kwargs", Some("Ignored."), None),
+        ],
+        docs
+    );
+}
@@ -32,3 +32,112 @@ l.append(1)
 "#,
     );
 }
+
+#[test]
+fn test_for_range() {
+    assert::pass(
+        r#"
+def loop(args):
+    res = []
+    if len(args) == 1:
+        for i in range(args[0]):
+            res.append(i)
+    elif len(args) == 2:
+        for i in range(args[0], args[1]):
+            res.append(i)
+    else:
+        for i in range(args[0], args[1], args[2]):
+            res.append(i)
+    return res
+
+def compr(args):
+    if len(args) == 1:
+        return [i for i in range(args[0])]
+    elif len(args) == 2:
+        return [i for i in range(args[0], args[1])]
+    else:
+        return [i for i in range(args[0], args[1], args[2])]
+
+for args in [(0,), (5,), (-3,), (2, 7), (7, 2), (1, 10, 3), (10, 1, -3), (0, -5, -1), (3, 3, -1)]:
+    assert_eq(list(range(*args)), loop(args))
+    assert_eq(list(range(*args)), compr(args))
+
+assert_eq([2147483645, 2147483646], loop((2147483645, 2147483647)))
+assert_eq([2147483646], loop((2147483646, 2147483647, 1000)))
+assert_eq([-2147483647], loop((-2147483647, -2147483648, -1000)))
+"#,
+    );
+}
+
+#[test]
+fn test_for_range_break_continue() {
+    assert::pass(
+        r#"
+def test():
+    res = []
+    for i in range(10):
+        if i == 2:
+            continue
+        if i == 5:
+            break
+        for j in range(i):
+            if j == 1:
+                break
+            res.append((i, j))
+    return res
+
+assert_eq([(1, 0), (3, 0), (4, 0)], test())
+"#,
+    );
+}
+
+#[test]
+fn test_for_range_assign_non_local() {
+    assert::pass(
+        r#"
+def test():
+    x = [None]
+    res = []
+    for x[0] in range(3):
+        res.append(x[0])
+    def f():
+        return i
+    for i in range(2):
+        res.append(f())
+    return res
+
+assert_eq([0, 1, 2, 0, 1], test())
+"#,
+    );
+}
+
+#[test]
+fn test_for_range_errors() {
+    assert::fail(
+        "def test():\n  for i in range(1, 2, 0):\n    pass\ntest()",
+        "step) cannot be zero",
+    );
+    assert::fail_skip_typecheck(
+        "def test(x):\n  for i in range(x):\n    pass\ntest('a')",
+        "doesn't match",
+    );
+    assert::fail_skip_typecheck(
+        "def test(x):\n  for i in range(x):\n    pass\ntest(1 << 40)",
+        "too big",
+    );
+}
+
+#[test]
+fn test_for_range_shadowed() {
+    assert::pass(
+        r#"
+def range(x):
+    return ["a", "b"][:x]
+
+def test():
+    return [i for i in range(1)]
+
+assert_eq(["a"], test())
+"#,
+    );
+}
@@ -42,6 +42,7 @@ use crate::values::none::NoneType;
 use crate::values::types::exported_name::ExportedName;
 use crate::values::types::exported_name::MutableExportedName;
 use crate::values::Freeze;
+use crate::values::Heap;
 use crate::values::NoSerialize;
 use crate::values::StarlarkValue;
 use crate::values::Value;
@@ -256,3 +257,54 @@ fun
         .unwrap();
     assert_eq!(v.unpack_str(), Some("(8, \"hello\", 1)"))
 }
+
+#[test]
+fn test_eval_function_call_args_limits() {
+    let fun = assert::pass("def fun(*args, **kwargs): pass\nfun");
+    let env = Module::new();
+    let mut eval = Evaluator::new(&env);
+    eval.set_max_call_args(1);
+    eval.set_max_call_kwargs(1);
+    let one = Value::testing_new_int(1);
+    eval.eval_function(fun.value(), &[one], &[("x", one)])
+        .unwrap();
+    let err = eval
+        .eval_function(fun.value(), &[one, one], &[])
+        .unwrap_err();
+    assert!(err.to_string().contains("Too many positional arguments"));
+    let err = eval
+        .eval_function(fun.value(), &[], &[("x", one), ("y", one)])
+        .unwrap_err();
+    assert!(err.to_string().contains("Too many named arguments"));
+}
+
+#[test]
+fn test_iterate_from_rust() {
+    let heap = Heap::new();
+    let list = heap.alloc(vec!["a", "b"]);
+    let dict = assert::pass("{'x': 1, 'y': 2}");
+
+    let mut iter = list.iterate(&heap).unwrap();
+    assert_eq!(Some("a"), iter.next().and_then(|x| x.unpack_str()));
+    // List is locked while iterated.
+    assert!(list.set_at(heap.alloc(0), heap.alloc("c")).is_err());
+    drop(iter);
+    list.set_at(heap.alloc(0), heap.alloc("c")).unwrap();
+
+    let items: Vec<_> = list
+        .iterate(&heap)
+        .unwrap()
+        .map(|x| x.unpack_str().unwrap())
+        .collect();
+    assert_eq!(vec!["c", "b"], items);
+
+    let keys: Vec<_> = dict
+        .value()
+        .iterate(&heap)
+        .unwrap()
+        .map(|x| x.unpack_str().unwrap())
+        .collect();
+    assert_eq!(vec!["x", "y"], keys);
+
+    assert!(heap.alloc(1).iterate(&heap).is_err());
+}
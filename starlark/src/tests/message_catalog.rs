@@ -0,0 +1,123 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::Path;
+
+use crate::analysis::AstModuleLint;
+use crate::analysis::EvalMessage;
+use crate::assert;
+use crate::message::MessageCatalog;
+use crate::message::MessageTemplate;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+struct German;
+
+impl MessageCatalog for German {
+    fn template(&self, code: &str) -> Option<&str> {
+        match code {
+            "no-attr" => Some("Objekt vom Typ `{0}` hat kein Attribut `{1}`"),
+            "variable-not-found" => Some("Variable `{0}` nicht gefunden"),
+            "fail" => Some("Fehlschlag: {0}"),
+            "unused-load" => Some("Unbenutztes `load` von `{0}`"),
+            _ => None,
+        }
+    }
+}
+
+struct French;
+
+impl MessageCatalog for French {
+    fn template(&self, code: &str) -> Option<&str> {
+        match code {
+            "no-attr" => Some("L'objet de type `{0}` n'a pas d'attribut `{1}`"),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_error_message_template() {
+    let err = assert::fails("(1).foo", &["has no attribute"]);
+    assert_eq!(
+        MessageTemplate::new("no-attr", vec!["int".to_owned(), "foo".to_owned()]),
+        err.message_template()
+    );
+    assert_eq!(
+        "Objekt vom Typ `int` hat kein Attribut `foo`",
+        err.localized_message(&German)
+    );
+    assert_eq!(
+        "L'objet de type `int` n'a pas d'attribut `foo`",
+        err.localized_message(&French)
+    );
+    let display = err.localized(&German).to_string();
+    assert!(display.contains("Objekt vom Typ `int` hat kein Attribut `foo`"));
+    assert!(display.contains("(1).foo"), "{display}");
+}
+
+#[test]
+fn test_error_message_fallback() {
+    let err = assert::fails("fail('oops')", &["oops"]);
+    assert_eq!(
+        MessageTemplate::new("fail", vec!["oops".to_owned()]),
+        err.message_template()
+    );
+    assert_eq!("Fehlschlag: oops", err.localized_message(&German));
+    // No translation, keep the English message.
+    assert_eq!(
+        err.without_diagnostic().to_string(),
+        err.localized_message(&French)
+    );
+}
+
+#[test]
+fn test_eval_message_localized() {
+    let err = assert::fails("x", &["not found"]);
+    let message = EvalMessage::from_error_localized(Path::new("x.star"), &err, &German);
+    assert_eq!("Variable `x` nicht gefunden", message.description);
+    assert!(
+        message
+            .full_error_with_span
+            .unwrap()
+            .contains("Variable `x` nicht gefunden")
+    );
+}
+
+#[test]
+fn test_lint_localized() {
+    let lints = AstModule::parse(
+        "x.star",
+        "load('foo', 'bar')\n".to_owned(),
+        &Dialect::Extended,
+    )
+    .unwrap()
+    .lint(None);
+    let lint = lints
+        .iter()
+        .find(|l| l.short_name == "unused-load")
+        .unwrap();
+    assert_eq!(
+        &MessageTemplate::new("unused-load", vec!["bar".to_owned()]),
+        lint.message_template()
+    );
+    assert_eq!(
+        "Unbenutztes `load` von `bar`",
+        lint.localized_problem(&German)
+    );
+    assert_eq!(lint.problem, lint.localized_problem(&French));
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::assert::test_functions;
+use crate::environment::Globals;
+use crate::environment::GlobalsBuilder;
+use crate::environment::LibraryExtension;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[test]
+fn test_mutation_audit() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.enable_mutation_audit().unwrap();
+
+    let program = "\
+x = []
+d = {}
+def add(v):
+    d[v] = len(x)
+x.append(1)
+add('a')
+x = [1, 2]
+t = ([],)
+t[0].append(1)
+n = 1
+";
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard()).unwrap();
+    let mutations = eval
+        .module_mutations()
+        .unwrap()
+        .into_iter()
+        .map(|m| format!("{} {}", m.name, m.location))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec!["x a.star:5:1-12", "d a.star:4:5-18", "t a.star:9:1-15"],
+        mutations
+    );
+}
+
+#[test]
+fn test_mutation_audit_not_enabled() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    assert!(eval.module_mutations().is_err());
+}
+
+#[test]
+fn test_mutation_audit_rebind() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.enable_mutation_audit().unwrap();
+
+    let program = "\
+x = [1]
+y = 2
+x = [2]
+z = x if garbage_collect() == None else None
+z.append(3)
+x = {}
+";
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    let globals = GlobalsBuilder::standard().with(test_functions).build();
+    eval.eval_module(ast, &globals).unwrap();
+    let mutations = eval
+        .module_mutations()
+        .unwrap()
+        .into_iter()
+        .map(|m| format!("{} {}", m.name, m.location))
+        .collect::<Vec<_>>();
+    // Rebinding is not a mutation, also across garbage collection,
+    // but mutation through an alias is.
+    assert_eq!(vec!["x a.star:5:1-12", "z a.star:5:1-12"], mutations);
+}
+
+#[test]
+fn test_mutation_audit_sets_structs_records() {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.enable_mutation_audit().unwrap();
+
+    let program = "\
+s = set([1])
+st = struct(xs = [])
+R = record(xs = list)
+r = R(xs = [])
+s.add(2)
+st.xs.append(1)
+r.xs.append(1)
+s.discard(3)
+";
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    let globals = Globals::extended_by(&[
+        LibraryExtension::SetType,
+        LibraryExtension::StructType,
+        LibraryExtension::RecordType,
+    ]);
+    eval.eval_module(ast, &globals).unwrap();
+    let mutations = eval
+        .module_mutations()
+        .unwrap()
+        .into_iter()
+        .map(|m| format!("{} {}", m.name, m.location))
+        .collect::<Vec<_>>();
+    // Discarding a missing element does not change the set.
+    assert_eq!(
+        vec!["s a.star:5:1-9", "st a.star:6:1-16", "r a.star:7:1-15"],
+        mutations
+    );
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Arguments;
+use crate::eval::Evaluator;
+use crate::eval::NativeCallInterceptor;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::Value;
+
+#[derive(Default)]
+struct Recorder {
+    log: RefCell<Vec<String>>,
+}
+
+impl NativeCallInterceptor for Recorder {
+    fn before_call<'v>(
+        &self,
+        name: &str,
+        this: Option<Value<'v>>,
+        args: &Arguments<'v, '_>,
+    ) -> anyhow::Result<Option<Value<'v>>> {
+        let this = this.map_or_else(String::new, |this| format!("{}.", this));
+        self.log
+            .borrow_mut()
+            .push(format!("before {}{}/{}", this, name, args.0.pos.len()));
+        match name {
+            "repr" => Ok(Some(Value::testing_new_int(42))),
+            "hash" => Err(anyhow::anyhow!("denied by interceptor")),
+            _ => Ok(None),
+        }
+    }
+
+    fn after_call<'v>(
+        &self,
+        name: &str,
+        _duration: Duration,
+        result: Result<Value<'v>, &crate::Error>,
+    ) {
+        self.log.borrow_mut().push(format!(
+            "after {} {}",
+            name,
+            result.map_or_else(|e| format!("error: {}", e), |r| r.to_repr())
+        ));
+    }
+}
+
+fn eval_with_recorder(program: &str) -> (crate::Result<String>, Vec<String>) {
+    let recorder = Recorder::default();
+    let module = Module::new();
+    let result = {
+        let mut eval = Evaluator::new(&module);
+        eval.set_native_call_interceptor(&recorder);
+        let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+        eval.eval_module(ast, &Globals::standard())
+            .map(|v| v.to_repr())
+    };
+    (result, recorder.log.into_inner())
+}
+
+#[test]
+fn test_native_call_interceptor() {
+    let (result, log) = eval_with_recorder(
+        r#"
+def f(x):
+    x.append(3)
+    return str(x) + str(repr(x))
+f([1, 2])
+"#,
+    );
+    assert_eq!("\"[1, 2, 3]42\"", result.unwrap());
+    assert_eq!(
+        vec![
+            "before [1, 2].append/1",
+            "after append None",
+            "before str/1",
+            "after str \"[1, 2, 3]\"",
+            "before repr/1",
+            "before str/1",
+            "after str \"42\"",
+        ],
+        log
+    );
+}
+
+#[test]
+fn test_native_call_interceptor_error() {
+    let (result, log) = eval_with_recorder(
+        r#"
+def f(x):
+    return int(x)
+f("x")
+"#,
+    );
+    assert!(result.is_err());
+    assert_eq!(
+        1,
+        log.iter()
+            .filter(|l| l.starts_with("after int error:"))
+            .count()
+    );
+}
+
+#[test]
+fn test_native_call_interceptor_deny() {
+    let (result, log) = eval_with_recorder(
+        r#"
+def f(x):
+    return hash(x)
+f("x")
+"#,
+    );
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("denied by interceptor"));
+    assert_eq!(vec!["before hash/1"], log);
+}
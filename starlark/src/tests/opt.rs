@@ -22,6 +22,7 @@ mod def_inline;
 mod eq;
 mod if_rand;
 mod list_add;
+mod opt_level;
 mod speculative_exec;
 mod type_is;
 mod types;
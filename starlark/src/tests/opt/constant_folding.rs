@@ -41,3 +41,13 @@ fn test_fold_list_add_too_large() {
         );
     }
 }
+
+#[test]
+fn test_fold_slice_omitted_index() {
+    bc_golden_test("constant_folding_slice", "def test(): return 'abcd'[1:]");
+}
+
+#[test]
+fn test_fold_slice_non_const_index() {
+    crate::assert::eq("'c'", "def test(x): return 'abcd'[x:3:1]\ntest(2)");
+}
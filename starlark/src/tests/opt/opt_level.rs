@@ -0,0 +1,85 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for `OptLevel`.
+
+use crate::assert::Assert;
+use crate::eval::OptLevel;
+use crate::tests::bc::golden::bc_golden_test;
+use crate::tests::bc::golden::bc_golden_test_opt_level;
+
+const NESTED_IF: &str = r#"
+def test(x):
+    if x:
+        if x == 1:
+            y = 1
+        else:
+            y = 2
+    else:
+        y = 3
+    return y
+"#;
+
+const INLINE: &str = r#"
+def one():
+    return 1
+
+def test():
+    return one()
+"#;
+
+#[test]
+fn test_nested_if_none() {
+    bc_golden_test_opt_level("opt_level_nested_if_none", OptLevel::None, NESTED_IF);
+}
+
+#[test]
+fn test_nested_if_peephole() {
+    bc_golden_test_opt_level(
+        "opt_level_nested_if_peephole",
+        OptLevel::Peephole,
+        NESTED_IF,
+    );
+}
+
+#[test]
+fn test_nested_if_default() {
+    // Branches are only threaded when an opt level asks for it.
+    bc_golden_test("opt_level_nested_if_default", NESTED_IF);
+}
+
+#[test]
+fn test_inline_none() {
+    bc_golden_test_opt_level("opt_level_inline_none", OptLevel::None, INLINE);
+}
+
+#[test]
+fn test_inline_aggressive() {
+    bc_golden_test_opt_level("opt_level_inline_aggressive", OptLevel::Aggressive, INLINE);
+}
+
+#[test]
+fn test_same_results() {
+    for opt_level in [OptLevel::None, OptLevel::Peephole, OptLevel::Aggressive] {
+        let mut a = Assert::new();
+        a.setup_eval(move |eval| eval.set_opt_level(opt_level));
+        a.pass(&format!(
+            "{NESTED_IF}\nassert_eq([3, 1, 2], [test(0), test(1), test(2)])"
+        ));
+        a.pass(&format!("{INLINE}\nassert_eq(1, test())"));
+    }
+}
@@ -0,0 +1,103 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::EvalProgress;
+use crate::eval::Evaluator;
+use crate::eval::ProgressHandler;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+#[derive(Default)]
+struct Recorder {
+    log: RefCell<Vec<EvalProgress>>,
+    max_steps: Option<u64>,
+}
+
+impl ProgressHandler for Recorder {
+    fn progress(&self, progress: &EvalProgress) -> anyhow::Result<()> {
+        self.log.borrow_mut().push(progress.clone());
+        match self.max_steps {
+            Some(max_steps) if progress.steps >= max_steps => {
+                Err(anyhow::anyhow!("stuck after {} steps", progress.steps))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn eval_with_recorder(recorder: &Recorder, every: u64, program: &str) -> crate::Result<()> {
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_progress_handler(recorder, every)?;
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    eval.eval_module(ast, &Globals::standard())?;
+    Ok(())
+}
+
+#[test]
+fn test_progress() {
+    let recorder = Recorder::default();
+    eval_with_recorder(
+        &recorder,
+        10,
+        r#"
+def f():
+    for i in range(100):
+        x = i
+f()
+"#,
+    )
+    .unwrap();
+    let log = recorder.log.into_inner();
+    assert!(log.len() >= 10, "{:?}", log);
+    for (i, progress) in log.iter().enumerate() {
+        assert_eq!((i as u64 + 1) * 10, progress.steps);
+        assert_eq!("a.star", progress.location.filename());
+    }
+    let in_f = log.iter().find(|p| p.function == "f").unwrap();
+    assert_eq!(4, in_f.location.resolve_span().begin.line + 1);
+    assert_eq!(2, in_f.call_stack_count);
+}
+
+#[test]
+fn test_progress_stop() {
+    let recorder = Recorder {
+        max_steps: Some(1000),
+        ..Recorder::default()
+    };
+    let err = eval_with_recorder(
+        &recorder,
+        100,
+        r#"
+def f():
+    for i in range(1000000000):
+        x = i
+f()
+"#,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("stuck after 1000 steps"),
+        "{}",
+        err
+    );
+    assert_eq!(10, recorder.log.into_inner().len());
+}
@@ -0,0 +1,176 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+
+use allocative::Allocative;
+use derive_more::Display;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::assert::test_functions;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::eval::RuntimeLints;
+use crate::eval::SoftErrorHandler;
+use crate::starlark_simple_value;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::values::NoSerialize;
+use crate::values::StarlarkValue;
+
+/// Type with `len()` but without `bool()`.
+#[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+#[display(fmt = "empty")]
+struct Empty;
+
+starlark_simple_value!(Empty);
+
+#[starlark_value(type = "empty")]
+impl<'v> StarlarkValue<'v> for Empty {
+    fn length(&self) -> crate::Result<i32> {
+        Ok(0)
+    }
+}
+
+#[starlark_module]
+fn empty_globals(builder: &mut GlobalsBuilder) {
+    fn empty() -> anyhow::Result<Empty> {
+        Ok(Empty)
+    }
+}
+
+#[derive(Default)]
+struct CollectSoftErrors(RefCell<Vec<String>>);
+
+impl SoftErrorHandler for CollectSoftErrors {
+    fn soft_error(&self, category: &str, error: crate::Error) -> Result<(), crate::Error> {
+        let span = error.span().map(|s| s.resolve().to_string());
+        self.0
+            .borrow_mut()
+            .push(format!("{} {}", category, span.unwrap_or_default()));
+        Ok(())
+    }
+}
+
+fn eval(
+    program: &str,
+    checks: RuntimeLints,
+    handler: Option<&dyn SoftErrorHandler>,
+) -> crate::Result<()> {
+    let globals = GlobalsBuilder::standard()
+        .with(test_functions)
+        .with(empty_globals)
+        .build();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.set_runtime_lints(checks);
+    if let Some(handler) = handler {
+        eval.set_soft_error_handler(handler);
+    }
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended)?;
+    eval.eval_module(ast, &globals)?;
+    Ok(())
+}
+
+#[track_caller]
+fn fail(program: &str, checks: RuntimeLints, msg: &str) {
+    let err = eval(program, checks, None).unwrap_err().to_string();
+    assert!(err.contains(msg), "{:?} does not contain {:?}", err, msg);
+}
+
+const TRUTHINESS: RuntimeLints = RuntimeLints {
+    truthiness: true,
+    equality: false,
+};
+
+const EQUALITY: RuntimeLints = RuntimeLints {
+    truthiness: false,
+    equality: true,
+};
+
+#[test]
+fn test_runtime_lints_disabled() {
+    eval(
+        "x = 1 if empty() else 2\nassert_eq(False, 1 == '1')",
+        RuntimeLints::default(),
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_runtime_lint_truthiness() {
+    let msg = "Value of type `empty` has zero length but is truthy";
+    fail("if empty():\n    pass", TRUTHINESS, msg);
+    fail("def f(e):\n    return e or 1\nf(empty())", TRUTHINESS, msg);
+    fail("[x for x in [empty()] if x]", TRUTHINESS, msg);
+    eval(
+        "if [] or {} or 'x' or 1 or None or empty:\n    pass",
+        TRUTHINESS,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_runtime_lint_equality() {
+    let msg = "Comparing `int` with `string` is always false";
+    fail("def f(x):\n    return x == '1'\nf(1)", EQUALITY, msg);
+    fail("def f(x):\n    return x != '1'\nf(1)", EQUALITY, msg);
+    eval(
+        "\
+def eq(x, y):
+    return x == y
+assert_eq(True, eq(1, 1.0))
+assert_eq(False, eq(1, None))
+assert_eq(False, eq([], [1]))
+assert_eq(True, eq((1, 'a'), (1, 'a')))
+",
+        EQUALITY,
+        None,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_runtime_lints_soft_error_handler() {
+    let handler = CollectSoftErrors::default();
+    eval(
+        "\
+def eq(x, y):
+    return x == y
+assert_eq(False, eq(1, 'a'))
+if empty():
+    y = 1
+assert_eq(1, y)
+",
+        RuntimeLints::all(),
+        Some(&handler),
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            "runtime_lint_equality a.star:2:12-18",
+            "runtime_lint_truthiness a.star:4:1-6:1"
+        ],
+        handler.0.into_inner()
+    );
+}
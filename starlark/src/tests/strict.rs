@@ -0,0 +1,86 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::assert::Assert;
+use crate::syntax::Dialect;
+
+fn assert() -> Assert<'static> {
+    let mut a = Assert::new();
+    a.dialect(&Dialect {
+        strict: true,
+        ..Dialect::Extended
+    });
+    a
+}
+
+#[test]
+fn test_strict_pass() {
+    assert().pass(
+        r#"
+"""Docstring."""
+_private = 1
+public = _private
+def f(x):
+    return [y for y in x]
+assert_eq([1], f([1]))
+"#,
+    );
+}
+
+#[test]
+fn test_strict_shadows_builtin() {
+    assert().fail("len = 1", "Variable `len` shadows a builtin in strict mode");
+    assert().fail(
+        "def f(str):\n    return str\nf(1)",
+        "Variable `str` shadows a builtin in strict mode",
+    );
+    assert().fail(
+        "[list for list in [1]]",
+        "Variable `list` shadows a builtin in strict mode",
+    );
+}
+
+#[test]
+fn test_strict_unused_private() {
+    assert().fail(
+        "_x = 1\nx = 2",
+        "Private top-level variable `_x` is never used in strict mode",
+    );
+    assert().fail(
+        "def _f(): pass",
+        "Private top-level variable `_f` is never used in strict mode",
+    );
+    // Reading the variable from a function counts as a use.
+    assert().pass("_x = 1\ndef f(): return _x\nassert_eq(1, f())");
+}
+
+#[test]
+fn test_strict_load_first() {
+    let mut a = assert();
+    a.module("m", "x = 1");
+    a.pass("'''Doc.'''\nload('m', 'x')\ny = x");
+    a.fail(
+        "y = 1\nload('m', 'x')\nz = x",
+        "`load` must occur before other statements in strict mode",
+    );
+}
+
+#[test]
+fn test_not_strict() {
+    let a = Assert::new();
+    a.pass("len = 1\n_x = 2");
+}
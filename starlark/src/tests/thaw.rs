@@ -0,0 +1,164 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use allocative::Allocative;
+use derive_more::Display;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+use starlark_derive::Trace;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::assert;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::values::dict::DictRef;
+use crate::values::list::AllocList;
+use crate::values::list::ListRef;
+use crate::values::Demand;
+use crate::values::FrozenHeap;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::ThawHook;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[test]
+fn test_thaw_containers() {
+    let frozen = assert::pass(
+        r#"
+shared = [1]
+x = {"a": shared, "b": (shared, "s"), "c": struct(l = shared, i = 3), "d": (1, "t")}
+x
+"#,
+    );
+    let heap = Heap::new();
+    let x = heap.thaw(frozen.value().unpack_frozen().unwrap()).unwrap();
+    assert!(x.unpack_frozen().is_none());
+
+    let a = DictRef::from_value(x).unwrap().get_str("a").unwrap();
+    let b = DictRef::from_value(x).unwrap().get_str("b").unwrap();
+    let c = DictRef::from_value(x).unwrap().get_str("c").unwrap();
+    let d = DictRef::from_value(x).unwrap().get_str("d").unwrap();
+    assert!(a.unpack_frozen().is_none());
+    // Shared list is thawed once.
+    assert!(a.ptr_eq(b.at(heap.alloc(0), &heap).unwrap()));
+    assert!(a.ptr_eq(c.get_attr("l", &heap).unwrap().unwrap()));
+    // Tuple without mutable values is shared with the frozen heap.
+    assert!(d.unpack_frozen().is_some());
+
+    // Thawed list is mutable.
+    a.set_at(heap.alloc(0), heap.alloc(2)).unwrap();
+    assert_eq!("[2]", b.at(heap.alloc(0), &heap).unwrap().to_repr());
+    assert_eq!(
+        r#"{"a": [1], "b": ([1], "s"), "c": struct(l=[1], i=3), "d": (1, "t")}"#,
+        frozen.value().to_repr()
+    );
+}
+
+#[test]
+fn test_thaw_cycle() {
+    let frozen = assert::pass("x = []\nx.append(x)\nx");
+    let heap = Heap::new();
+    let x = heap.thaw(frozen.value().unpack_frozen().unwrap()).unwrap();
+    let content: Vec<Value> = ListRef::from_value(x).unwrap().iter().collect();
+    assert_eq!(1, content.len());
+    assert!(x.ptr_eq(content[0]));
+}
+
+#[test]
+fn test_thaw_cycle_through_tuple() {
+    let frozen = assert::pass("x = []\nt = (x, 1)\nx.append(t)\nt");
+    let heap = Heap::new();
+    let t = heap.thaw(frozen.value().unpack_frozen().unwrap()).unwrap();
+    let x = t.at(heap.alloc(0), &heap).unwrap();
+    assert!(x.unpack_frozen().is_none());
+    assert!(t.ptr_eq(x.at(heap.alloc(0), &heap).unwrap()));
+}
+
+#[test]
+fn test_thaw_cycle_through_struct_and_record() {
+    let frozen = assert::pass(
+        r#"
+R = record(l = list)
+x = []
+r = R(l = x)
+s = struct(r = r)
+x.append(s)
+r
+"#,
+    );
+    let heap = Heap::new();
+    let r = heap.thaw(frozen.value().unpack_frozen().unwrap()).unwrap();
+    assert!(r.unpack_frozen().is_none());
+    let x = r.get_attr("l", &heap).unwrap().unwrap();
+    let s = x.at(heap.alloc(0), &heap).unwrap();
+    assert!(r.ptr_eq(s.get_attr("r", &heap).unwrap().unwrap()));
+    x.set_at(heap.alloc(0), heap.alloc(1)).unwrap();
+    assert_eq!("record[R](l=[1])", r.to_repr());
+}
+
+#[test]
+fn test_thaw_set() {
+    let frozen = assert::pass("set([1, (2, 3)])");
+    let heap = Heap::new();
+    let x = heap.thaw(frozen.value().unpack_frozen().unwrap()).unwrap();
+    assert!(x.unpack_frozen().is_none());
+    let add = x.get_attr("add", &heap).unwrap().unwrap();
+    let module = Module::new();
+    let mut eval = Evaluator::new(&module);
+    eval.eval_function(add, &[heap.alloc(4)], &[]).unwrap();
+    assert_eq!("set([1, (2, 3), 4])", x.to_repr());
+    assert_eq!("frozenset([1, (2, 3)])", frozen.value().to_repr());
+}
+
+#[derive(Debug, Display, Trace, ProvidesStaticType, NoSerialize, Allocative)]
+#[display(fmt = "boxed({})", _0)]
+struct Boxed<'v>(Value<'v>);
+
+#[starlark_value(type = "boxed")]
+impl<'v> StarlarkValue<'v> for Boxed<'v> {}
+
+#[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+#[display(fmt = "boxed({})", _0)]
+struct FrozenBoxed(FrozenValue);
+
+#[starlark_value(type = "boxed")]
+impl<'v> StarlarkValue<'v> for FrozenBoxed {
+    type Canonical = Boxed<'v>;
+
+    fn provide(&'v self, demand: &mut Demand<'_, 'v>) {
+        demand.provide_value(ThawHook(|value, thawer| {
+            let boxed = value.downcast_frozen_ref::<FrozenBoxed>().unwrap();
+            let content = thawer.thaw(boxed.0)?;
+            Ok(thawer.heap().alloc_complex_no_freeze(Boxed(content)))
+        }));
+    }
+}
+
+#[test]
+fn test_thaw_hook() {
+    let frozen_heap = FrozenHeap::new();
+    let list = frozen_heap.alloc(AllocList([1, 2]));
+    let frozen = frozen_heap.alloc_simple(FrozenBoxed(list));
+    let heap = Heap::new();
+    let x = heap.thaw(frozen).unwrap();
+    let content = x.downcast_ref::<Boxed>().unwrap().0;
+    assert!(content.unpack_frozen().is_none());
+    assert_eq!("boxed([1, 2])", x.to_repr());
+}
@@ -646,6 +646,19 @@ fn test_getattr_did_you_mean_builtin() {
     );
 }
 
+#[test]
+fn test_getattr_function_did_you_mean() {
+    assert::fail(
+        "getattr([], 'appen')",
+        "Object of type `list` has no attribute `appen`, did you mean `append`?",
+    );
+    assert::fail(
+        "getattr(struct(grey=1), 'xyz')",
+        "Object of type `struct` has no attribute `xyz`",
+    );
+    assert::eq("getattr(struct(grey=1), 'gray', 2)", "2");
+}
+
 #[test]
 fn test_getattr_did_you_mean_custom() {
     assert::fail(
@@ -394,9 +394,15 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
         let AssignP { lhs, ty, rhs } = assign;
         match ty {
             None => self.assign(lhs, rhs),
-            Some(_ty) => {
-                // TODO(nga): do not ignore type.
-                self.assign(lhs, rhs)
+            Some(ty) => {
+                let ty = self.get_ty_expr(ty)?;
+                let rhs = self.expr(rhs)?;
+                // Declared type takes precedence over the inferred one.
+                let rhs = GlobalValue {
+                    value: rhs.value,
+                    ty,
+                };
+                self.assign_value(lhs, rhs)
             }
         }
     }
@@ -19,6 +19,10 @@
 
 use crate::typing::Ty;
 use crate::typing::TyBasic;
+use crate::values::type_repr::StarlarkTypeRepr;
+use crate::values::typing::type_compiled::compiled::TypeCompiled;
+use crate::values::Heap;
+use crate::values::Value;
 
 pub fn unpack_args_item_ty(ty: Ty) -> Ty {
     Ty::unions(
@@ -43,3 +47,20 @@ pub fn unpack_kwargs_value_ty(ty: Ty) -> Ty {
             .collect(),
     )
 }
+
+/// Check a parameter declared with `#[starlark(ty = T)]` matches that type.
+pub fn check_param_ty<'v, T: StarlarkTypeRepr>(
+    name: &str,
+    value: Value<'v>,
+    heap: &'v Heap,
+) -> crate::Result<()> {
+    TypeCompiled::from_ty(&T::starlark_type_repr(), heap).check_type(value, Some(name))
+}
+
+/// Check a return value declared with `#[starlark(return_ty = T)]` matches that type.
+pub fn check_return_ty<'v, T: StarlarkTypeRepr>(
+    value: Value<'v>,
+    heap: &'v Heap,
+) -> crate::Result<()> {
+    TypeCompiled::from_ty(&T::starlark_type_repr(), heap).check_type(value, None)
+}
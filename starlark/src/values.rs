@@ -38,6 +38,9 @@ pub use starlark_derive::AllocValue;
 pub use starlark_derive::Freeze;
 pub use starlark_derive::NoSerialize;
 pub use starlark_derive::StarlarkAttrs;
+pub use starlark_derive::StarlarkSerialize;
+pub use starlark_derive::StarlarkValue;
+pub use starlark_derive::Thaw;
 pub use starlark_derive::Trace;
 pub use starlark_derive::UnpackValue;
 
@@ -47,18 +50,30 @@ pub use crate::coerce::Coerce;
 pub use crate::values::alloc_value::AllocFrozenValue;
 pub use crate::values::alloc_value::AllocValue;
 pub use crate::values::demand::Demand;
+pub use crate::values::diff::ValueDiff;
+pub use crate::values::diff::ValueDiffOptions;
+pub use crate::values::diff::ValueDifference;
 pub use crate::values::error::ValueError;
 pub use crate::values::freeze::Freeze;
+pub use crate::values::freeze::FreezeError;
+pub use crate::values::freeze::FreezeValidator;
 pub use crate::values::frozen_ref::FrozenRef;
 pub use crate::values::frozen_ref::OwnedFrozenRef;
 pub use crate::values::iter::StarlarkIterator;
 pub use crate::values::layout::complex::ValueTypedComplex;
+pub use crate::values::layout::heap::escape::set_escape_detection;
+pub use crate::values::layout::heap::escape::EscapeGuard;
 pub use crate::values::layout::heap::heap_type::Freezer;
 pub use crate::values::layout::heap::heap_type::FrozenHeap;
 pub use crate::values::layout::heap::heap_type::FrozenHeapRef;
 pub use crate::values::layout::heap::heap_type::Heap;
 pub use crate::values::layout::heap::heap_type::Tracer;
+pub use crate::values::layout::heap::profile::stats::FreezeStats;
+pub use crate::values::layout::heap::profile::stats::HeapStats;
+pub use crate::values::layout::heap::profile::stats::HeapTypeStats;
 pub use crate::values::layout::identity::ValueIdentity;
+pub use crate::values::layout::identity::ValueIdentityMap;
+pub use crate::values::layout::identity::ValueIdentitySet;
 pub use crate::values::layout::static_string::constant_string;
 pub use crate::values::layout::static_string::StarlarkStrNRepr;
 pub use crate::values::layout::typed::string::FrozenStringValue;
@@ -72,6 +87,9 @@ pub use crate::values::layout::value::ValueLike;
 pub use crate::values::layout::value_lifetimeless::ValueLifetimeless;
 pub use crate::values::owned::OwnedFrozenValue;
 pub use crate::values::owned::OwnedFrozenValueTyped;
+pub use crate::values::thaw::Thaw;
+pub use crate::values::thaw::ThawHook;
+pub use crate::values::thaw::Thawer;
 pub use crate::values::trace::Trace;
 pub use crate::values::traits::ComplexValue;
 pub use crate::values::traits::StarlarkValue;
@@ -85,11 +103,13 @@ pub use crate::values::types::exported_name;
 pub use crate::values::types::float;
 pub use crate::values::types::function;
 pub use crate::values::types::int;
+pub use crate::values::types::label;
 pub use crate::values::types::list;
 pub use crate::values::types::list_or_tuple;
 pub use crate::values::types::none;
 pub use crate::values::types::range;
 pub use crate::values::types::record;
+pub use crate::values::types::sealed;
 pub use crate::values::types::starlark_value_as_type;
 pub use crate::values::types::string;
 pub use crate::values::types::structs;
@@ -105,7 +125,9 @@ pub use crate::values::value_of_unchecked::ValueOfUncheckedGeneric;
 
 mod alloc_value;
 mod comparison;
+mod content_hash;
 pub(crate) mod demand;
+mod diff;
 pub(crate) mod error;
 mod freeze;
 pub(crate) mod frozen_ref;
@@ -116,6 +138,7 @@ pub(crate) mod num;
 mod owned;
 pub(crate) mod recursive_repr_or_json_guard;
 mod stack_guard;
+mod thaw;
 pub(crate) mod starlark_type_id;
 mod trace;
 pub(crate) mod traits;
@@ -0,0 +1,305 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Stable hash of a value tree, see [`Value::content_hash`].
+
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+
+use crate::values::dict::DictRef;
+use crate::values::enumeration::EnumValue;
+use crate::values::float::StarlarkFloat;
+use crate::values::list::ListRef;
+use crate::values::record::Record;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::types::int_or_big::StarlarkIntRef;
+use crate::values::Value;
+use crate::values::ValueIdentity;
+use crate::values::ValueIdentitySet;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum ContentHashError {
+    #[error("Cannot compute content hash of value of type `{0}`")]
+    Unsupported(String),
+    #[error("Cannot compute content hash of a value containing a cycle")]
+    Cycle,
+}
+
+/// 128-bit FNV-1a, chosen because it is trivial to reimplement outside of Rust.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Fnv128 {
+        Fnv128(Self::OFFSET)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u128;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn tag(&mut self, tag: u8) {
+        self.bytes(&[tag]);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.bytes(&(len as u64).to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.bytes(s.as_bytes());
+    }
+}
+
+struct ContentHasher<'v> {
+    /// Containers currently being hashed, to detect cycles.
+    stack: ValueIdentitySet<'v>,
+}
+
+impl<'v> ContentHasher<'v> {
+    /// Hash of a value on its own, used for elements of unordered containers.
+    fn hash_alone(
+        &mut self,
+        f: impl FnOnce(&mut Self, &mut Fnv128) -> crate::Result<()>,
+    ) -> crate::Result<u128> {
+        let mut h = Fnv128::new();
+        f(self, &mut h)?;
+        Ok(h.0)
+    }
+
+    /// Hash the entries of a container whose equality ignores order,
+    /// by hashing entries separately and combining the sorted entry hashes.
+    fn unordered(
+        &mut self,
+        h: &mut Fnv128,
+        entries: impl ExactSizeIterator<Item = (Value<'v>, Value<'v>)>,
+    ) -> crate::Result<()> {
+        h.len(entries.len());
+        let mut hashes = Vec::with_capacity(entries.len());
+        for (k, v) in entries {
+            hashes.push(self.hash_alone(|this, h| {
+                this.value(h, k)?;
+                this.value(h, v)
+            })?);
+        }
+        hashes.sort_unstable();
+        for x in hashes {
+            h.bytes(&x.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    fn container(
+        &mut self,
+        h: &mut Fnv128,
+        x: Value<'v>,
+        f: impl FnOnce(&mut Self, &mut Fnv128) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let id: ValueIdentity<'v> = x.identity();
+        if !self.stack.insert(id) {
+            return Err(crate::Error::new_other(ContentHashError::Cycle));
+        }
+        let r = f(self, h);
+        self.stack.remove(&id);
+        r
+    }
+
+    fn value(&mut self, h: &mut Fnv128, x: Value<'v>) -> crate::Result<()> {
+        if x.is_none() {
+            h.tag(b'N');
+        } else if let Some(b) = x.unpack_bool() {
+            h.tag(b'B');
+            h.bytes(&[b as u8]);
+        } else if let Some(i) = StarlarkIntRef::unpack(x) {
+            h.tag(b'I');
+            h.str(&i.to_string());
+        } else if let Some(f) = x.downcast_ref::<StarlarkFloat>() {
+            // Integral floats compare equal to ints, so they must hash the same.
+            if f.0.is_finite() && f.0.fract() == 0.0 {
+                if let Some(i) = BigInt::from_f64(f.0) {
+                    h.tag(b'I');
+                    h.str(&i.to_string());
+                    return Ok(());
+                }
+            }
+            h.tag(b'F');
+            // All NaNs are equal for the purpose of hashing, as are zeros.
+            let bits = if f.0.is_nan() {
+                f64::NAN.to_bits()
+            } else if f.0 == 0.0 {
+                0
+            } else {
+                f.0.to_bits()
+            };
+            h.bytes(&bits.to_le_bytes());
+        } else if let Some(s) = x.unpack_str() {
+            h.tag(b'S');
+            h.str(s);
+        } else if let Some(xs) = ListRef::from_value(x) {
+            self.container(h, x, |this, h| {
+                h.tag(b'L');
+                h.len(xs.len());
+                xs.iter().try_for_each(|x| this.value(h, x))
+            })?;
+        } else if let Some(xs) = TupleRef::from_value(x) {
+            self.container(h, x, |this, h| {
+                h.tag(b'T');
+                h.len(xs.len());
+                xs.iter().try_for_each(|x| this.value(h, x))
+            })?;
+        } else if let Some(d) = DictRef::from_value(x) {
+            self.container(h, x, |this, h| {
+                h.tag(b'D');
+                this.unordered(h, d.iter())
+            })?;
+        } else if let Some(s) = StructRef::from_value(x) {
+            self.container(h, x, |this, h| {
+                h.tag(b's');
+                this.unordered(h, s.iter().map(|(k, v)| (k.to_value(), v)))
+            })?;
+        } else if let Some(r) = Record::from_value(x) {
+            self.container(h, x, |this, h| {
+                h.tag(b'R');
+                h.len(r.values.len());
+                for (k, v) in r.iter() {
+                    h.str(k);
+                    this.value(h, v)?;
+                }
+                Ok(())
+            })?;
+        } else if let Some(e) = EnumValue::from_value(x) {
+            h.tag(b'E');
+            h.bytes(&e.index.to_le_bytes());
+            self.value(h, e.value)?;
+        } else {
+            return Err(crate::Error::new_other(ContentHashError::Unsupported(
+                x.get_type().to_owned(),
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn content_hash(x: Value) -> crate::Result<u128> {
+    let mut hasher = ContentHasher {
+        stack: ValueIdentitySet::new(),
+    };
+    hasher.hash_alone(|this, h| this.value(h, x))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::values::Heap;
+    use crate::values::Value;
+
+    fn hash(code: &str) -> crate::Result<u128> {
+        let a = Assert::new();
+        let module = a.pass_module(&format!("x = {code}"));
+        let x = module.get("x").unwrap();
+        x.value().content_hash()
+    }
+
+    #[test]
+    fn test_stable() {
+        // These values are part of the documented contract, do not change.
+        let heap = Heap::new();
+        assert_eq!(
+            0xb881a5eb5ae8f368619d864d8106eeaf,
+            heap.alloc("abc").content_hash().unwrap()
+        );
+        assert_eq!(
+            hash("None").unwrap(),
+            Value::new_none().content_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_equal_values() {
+        assert_eq!(hash("{1: 2, 3: 4}").unwrap(), hash("{3: 4, 1: 2}").unwrap());
+        assert_eq!(
+            hash("struct(a = 1, b = [2])").unwrap(),
+            hash("struct(b = [2], a = 1)").unwrap()
+        );
+        assert_eq!(
+            hash("[1, (2, 'x')]").unwrap(),
+            hash("[1] + [(2, 'x')]").unwrap()
+        );
+        assert_eq!(hash("0.0").unwrap(), hash("-0.0").unwrap());
+        assert_eq!(hash("1").unwrap(), hash("1.0").unwrap());
+        assert_eq!(hash("0").unwrap(), hash("-0.0").unwrap());
+        assert_eq!(hash("{1: [2]}").unwrap(), hash("{1.0: [2.0]}").unwrap());
+        assert_eq!(hash("1 << 100").unwrap(), hash("float(1 << 100)").unwrap());
+        assert_eq!(
+            hash("1 << 100").unwrap(),
+            hash("1267650600228229401496703205376").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_different_values() {
+        let values = [
+            "None",
+            "True",
+            "False",
+            "0",
+            "1",
+            "1.5",
+            "''",
+            "'1'",
+            "[]",
+            "()",
+            "{}",
+            "[1]",
+            "(1,)",
+            "[[]]",
+            "[[], []]",
+            "['a', 'b']",
+            "['ab']",
+            "{1: 2}",
+            "{2: 1}",
+            "struct(a = 1)",
+            "struct(b = 1)",
+        ];
+        let hashes: Vec<u128> = values.iter().map(|v| hash(v).unwrap()).collect();
+        for i in 0..hashes.len() {
+            for j in 0..i {
+                assert_ne!(hashes[i], hashes[j], "{} vs {}", values[i], values[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(hash("len")
+            .unwrap_err()
+            .to_string()
+            .contains("Cannot compute content hash of value of type `function`"));
+        assert!(hash("[]\nx.append(x)")
+            .unwrap_err()
+            .to_string()
+            .contains("Cannot compute content hash of a value containing a cycle"));
+    }
+}
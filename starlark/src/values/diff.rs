@@ -0,0 +1,379 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Structural difference between two values.
+
+use std::fmt;
+use std::fmt::Display;
+
+use starlark_map::small_map::SmallMap;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// Limits used when computing and rendering a [`ValueDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ProvidesStaticType)]
+pub struct ValueDiffOptions {
+    /// Containers nested deeper than this are compared as a whole.
+    pub max_depth: usize,
+    /// Rendered values longer than this many characters are truncated.
+    pub max_width: usize,
+    /// Only this many differences are rendered, the rest are counted.
+    pub max_differences: usize,
+}
+
+impl Default for ValueDiffOptions {
+    fn default() -> Self {
+        ValueDiffOptions {
+            max_depth: 10,
+            max_width: 80,
+            max_differences: 10,
+        }
+    }
+}
+
+/// What differs at a given path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueDifference {
+    /// Both values are present, but are not equal.
+    Changed {
+        /// Repr of the expected value.
+        expected: String,
+        /// Repr of the actual value.
+        got: String,
+    },
+    /// Value is present only in the expected value.
+    Missing {
+        /// Repr of the expected value.
+        expected: String,
+    },
+    /// Value is present only in the actual value.
+    Unexpected {
+        /// Repr of the actual value.
+        got: String,
+    },
+}
+
+/// Differences between two values, as produced by [`ValueDiff::new`].
+///
+/// Lists, tuples, dicts and structs are compared element by element,
+/// so a difference deep inside a large value is reported with its path
+/// (like `[1].deps["x"]`) instead of printing both values in full.
+#[derive(Debug, Clone)]
+pub struct ValueDiff {
+    differences: Vec<(String, ValueDifference)>,
+    omitted: usize,
+}
+
+impl ValueDiff {
+    /// Compare `expected` with `got`.
+    pub fn new<'v>(
+        expected: Value<'v>,
+        got: Value<'v>,
+        options: &ValueDiffOptions,
+    ) -> crate::Result<ValueDiff> {
+        let mut diff = ValueDiff {
+            differences: Vec::new(),
+            omitted: 0,
+        };
+        DiffBuilder {
+            options,
+            diff: &mut diff,
+        }
+        .diff(&mut String::new(), expected, got, 0)?;
+        Ok(diff)
+    }
+
+    /// Values are equal.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Paths and differences, at most `max_differences` of them.
+    /// Path of the top-level value is an empty string.
+    pub fn differences(&self) -> &[(String, ValueDifference)] {
+        &self.differences
+    }
+
+    /// Number of differences not included in [`differences`](ValueDiff::differences).
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+}
+
+impl Display for ValueDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (path, difference)) in self.differences.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            let path = if path.is_empty() { "<value>" } else { path };
+            match difference {
+                ValueDifference::Changed { expected, got } => {
+                    write!(f, "  {path}: expected {expected}, got {got}")?
+                }
+                ValueDifference::Missing { expected } => {
+                    write!(f, "  {path}: missing, expected {expected}")?
+                }
+                ValueDifference::Unexpected { got } => write!(f, "  {path}: unexpected {got}")?,
+            }
+        }
+        if self.omitted != 0 {
+            write!(f, "\n  ... and {} more differences", self.omitted)?;
+        }
+        Ok(())
+    }
+}
+
+struct DiffBuilder<'a> {
+    options: &'a ValueDiffOptions,
+    diff: &'a mut ValueDiff,
+}
+
+impl<'a> DiffBuilder<'a> {
+    fn repr(&self, value: Value) -> String {
+        let repr = value.to_repr();
+        match repr.char_indices().nth(self.options.max_width) {
+            Some((i, _)) => format!("{}...", &repr[..i]),
+            None => repr,
+        }
+    }
+
+    fn add(&mut self, path: &str, difference: impl FnOnce(&Self) -> ValueDifference) {
+        if self.diff.differences.len() < self.options.max_differences {
+            let difference = difference(self);
+            self.diff.differences.push((path.to_owned(), difference));
+        } else {
+            self.diff.omitted += 1;
+        }
+    }
+
+    /// Append a path component, call `f`, then restore the path.
+    fn with_path<R>(
+        &mut self,
+        path: &mut String,
+        component: fmt::Arguments,
+        f: impl FnOnce(&mut Self, &mut String) -> R,
+    ) -> R {
+        let len = path.len();
+        fmt::Write::write_fmt(path, component).unwrap();
+        let r = f(self, path);
+        path.truncate(len);
+        r
+    }
+
+    fn diff<'v>(
+        &mut self,
+        path: &mut String,
+        expected: Value<'v>,
+        got: Value<'v>,
+        depth: usize,
+    ) -> crate::Result<()> {
+        if expected.equals(got)? {
+            return Ok(());
+        }
+        if depth < self.options.max_depth {
+            if let (Some(expected), Some(got)) =
+                (ListRef::from_value(expected), ListRef::from_value(got))
+            {
+                return self.diff_seq(path, expected.content(), got.content(), depth);
+            }
+            if let (Some(expected), Some(got)) =
+                (TupleRef::from_value(expected), TupleRef::from_value(got))
+            {
+                return self.diff_seq(path, expected.content(), got.content(), depth);
+            }
+            if let (Some(expected), Some(got)) =
+                (DictRef::from_value(expected), DictRef::from_value(got))
+            {
+                return self.diff_dict(path, &expected, &got, depth);
+            }
+            if let (Some(expected), Some(got)) =
+                (StructRef::from_value(expected), StructRef::from_value(got))
+            {
+                return self.diff_struct(path, expected, got, depth);
+            }
+        }
+        self.add(path, |s| ValueDifference::Changed {
+            expected: s.repr(expected),
+            got: s.repr(got),
+        });
+        Ok(())
+    }
+
+    fn diff_seq<'v>(
+        &mut self,
+        path: &mut String,
+        expected: &[Value<'v>],
+        got: &[Value<'v>],
+        depth: usize,
+    ) -> crate::Result<()> {
+        for i in 0..expected.len().max(got.len()) {
+            self.with_path(path, format_args!("[{i}]"), |s, path| {
+                match (expected.get(i), got.get(i)) {
+                    (Some(e), Some(g)) => return s.diff(path, *e, *g, depth + 1),
+                    (Some(e), None) => s.add(path, |s| ValueDifference::Missing {
+                        expected: s.repr(*e),
+                    }),
+                    (None, Some(g)) => {
+                        s.add(path, |s| ValueDifference::Unexpected { got: s.repr(*g) })
+                    }
+                    (None, None) => unreachable!("index is within one of the sequences"),
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    fn diff_dict<'v>(
+        &mut self,
+        path: &mut String,
+        expected: &DictRef<'v>,
+        got: &DictRef<'v>,
+        depth: usize,
+    ) -> crate::Result<()> {
+        for (k, e) in expected.iter() {
+            let key = k.to_repr();
+            self.with_path(path, format_args!("[{key}]"), |s, path| {
+                match got.get(k)? {
+                    Some(g) => return s.diff(path, e, g, depth + 1),
+                    None => s.add(path, |s| ValueDifference::Missing {
+                        expected: s.repr(e),
+                    }),
+                }
+                Ok(())
+            })?;
+        }
+        for (k, g) in got.iter() {
+            if expected.get(k)?.is_none() {
+                let key = k.to_repr();
+                self.with_path(path, format_args!("[{key}]"), |s, path| {
+                    s.add(path, |s| ValueDifference::Unexpected { got: s.repr(g) })
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn diff_struct<'v>(
+        &mut self,
+        path: &mut String,
+        expected: StructRef<'v>,
+        got: StructRef<'v>,
+        depth: usize,
+    ) -> crate::Result<()> {
+        let got_fields: SmallMap<&str, Value<'v>> =
+            got.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        for (k, e) in expected.iter() {
+            self.with_path(path, format_args!(".{}", k.as_str()), |s, path| {
+                match got_fields.get(k.as_str()) {
+                    Some(g) => return s.diff(path, e, *g, depth + 1),
+                    None => s.add(path, |s| ValueDifference::Missing {
+                        expected: s.repr(e),
+                    }),
+                }
+                Ok(())
+            })?;
+        }
+        for (k, g) in got.iter() {
+            if !expected.iter().any(|(e, _)| e.as_str() == k.as_str()) {
+                self.with_path(path, format_args!(".{}", k.as_str()), |s, path| {
+                    s.add(path, |s| ValueDifference::Unexpected { got: s.repr(g) })
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::values::diff::ValueDiff;
+    use crate::values::diff::ValueDiffOptions;
+
+    fn diff(expected: &str, got: &str, options: ValueDiffOptions) -> String {
+        let a = Assert::new();
+        let expected = a.pass(expected);
+        let got = a.pass(got);
+        ValueDiff::new(expected.value(), got.value(), &options)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_equal() {
+        let a = Assert::new();
+        let v = a.pass("[1, {'a': struct(b = 2)}]");
+        let diff = ValueDiff::new(v.value(), v.value(), &ValueDiffOptions::default()).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!("", diff.to_string());
+    }
+
+    #[test]
+    fn test_nested() {
+        assert_eq!(
+            "  [1][\"a\"].b: expected 2, got 3\n  [2]: unexpected \"x\"",
+            diff(
+                "[1, {'a': struct(b = 2, c = [])}]",
+                "[1, {'a': struct(b = 3, c = [])}, 'x']",
+                ValueDiffOptions::default()
+            )
+        );
+        assert_eq!(
+            "  [\"b\"]: missing, expected 2\n  [\"c\"]: unexpected 3",
+            diff(
+                "{'a': 1, 'b': 2}",
+                "{'a': 1, 'c': 3}",
+                ValueDiffOptions::default()
+            )
+        );
+        assert_eq!(
+            "  <value>: expected 1, got \"1\"",
+            diff("1", "'1'", ValueDiffOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let options = ValueDiffOptions {
+            max_depth: 1,
+            max_width: 5,
+            max_differences: 2,
+        };
+        assert_eq!(
+            "  [0]: expected [1, 2..., got [1, 3...\n  [1]: expected 1, got 2\n  ... and 1 more differences",
+            diff("[[1, 2, 3], 1, 1]", "[[1, 3, 3], 2, 2]", options)
+        );
+    }
+
+    #[test]
+    fn test_asserts_eq() {
+        let a = Assert::new();
+        a.fail(
+            "load('asserts.star', 'asserts'); asserts.eq({'a': [1, 2]}, {'a': [1, 3]})",
+            "assert_eq: values differ:\n  [\"a\"][1]: expected 2, got 3",
+        );
+        a.fail("assert_eq(1, 2)", "assert_eq: expected 1, got 2");
+    }
+}
@@ -19,6 +19,8 @@
 
 use thiserror::Error;
 
+use crate::errors::did_you_mean::did_you_mean;
+use crate::message::MessageTemplate;
 use crate::values::StarlarkValue;
 use crate::values::Value;
 
@@ -64,7 +66,9 @@ pub enum ValueError {
 
 impl From<ValueError> for crate::Error {
     fn from(e: ValueError) -> Self {
+        let message_template = e.message_template();
         crate::Error::new(crate::ErrorKind::Value(anyhow::Error::new(e)))
+            .with_message_template(message_template)
     }
 }
 
@@ -77,6 +81,53 @@ pub(crate) enum ControlError {
 }
 
 impl ValueError {
+    /// The message with the fields as arguments, in the order they are declared.
+    fn message_template(&self) -> MessageTemplate {
+        let (code, args) = match self {
+            ValueError::OperationNotSupported { op, typ } => {
+                ("operation-not-supported", vec![op.clone(), typ.clone()])
+            }
+            ValueError::OperationNotSupportedBinary { op, left, right } => (
+                "operation-not-supported-binary",
+                vec![op.clone(), left.clone(), right.clone()],
+            ),
+            ValueError::DivisionByZero => ("division-by-zero", Vec::new()),
+            ValueError::IntegerOverflow => ("integer-overflow", Vec::new()),
+            ValueError::NegativeShiftCount => ("negative-shift-count", Vec::new()),
+            ValueError::IncorrectParameterType => ("incorrect-parameter-type", Vec::new()),
+            ValueError::IncorrectParameterTypeNamed(x) => {
+                ("incorrect-parameter-type-named", vec![x.clone()])
+            }
+            ValueError::MissingThis => ("missing-this", Vec::new()),
+            ValueError::MissingRequired(x) => ("missing-required", vec![x.clone()]),
+            ValueError::IndexOutOfBound(i) => ("index-out-of-bound", vec![i.to_string()]),
+            ValueError::KeyNotFound(x) => ("key-not-found", vec![x.clone()]),
+            ValueError::CannotMutateImmutableValue => ("immutable", Vec::new()),
+            ValueError::MutationDuringIteration => ("mutation-during-iteration", Vec::new()),
+            ValueError::NoAttr(typ, attr) => ("no-attr", vec![typ.clone(), attr.clone()]),
+            ValueError::NoAttrDidYouMean(typ, attr, better) => (
+                "no-attr-did-you-mean",
+                vec![typ.clone(), attr.clone(), better.clone()],
+            ),
+        };
+        MessageTemplate::new(code, args)
+    }
+
+    /// Attribute `attribute` is missing on `x`, suggesting the closest attribute if any.
+    #[cold]
+    #[inline(never)]
+    pub(crate) fn no_attr(x: Value, attribute: &str) -> crate::Error {
+        match did_you_mean(attribute, x.dir_attr().iter().map(|s| s.as_str())) {
+            None => ValueError::NoAttr(x.get_type().to_owned(), attribute.to_owned()).into(),
+            Some(better) => ValueError::NoAttrDidYouMean(
+                x.get_type().to_owned(),
+                attribute.to_owned(),
+                better.to_owned(),
+            )
+            .into(),
+        }
+    }
+
     #[cold]
     pub(crate) fn unsupported_owned<T>(
         left: &str,
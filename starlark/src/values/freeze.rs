@@ -15,12 +15,26 @@
  * limitations under the License.
  */
 
+use std::any::type_name;
 use std::cell::OnceCell;
 use std::cell::RefCell;
 use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::BuildHasher;
+use std::hash::Hash;
 use std::marker;
 use std::marker::PhantomData;
 
+#[cfg(feature = "smallvec")]
+use smallvec::Array;
+#[cfg(feature = "smallvec")]
+use smallvec::SmallVec;
 use starlark_map::small_map::SmallMap;
 use starlark_map::small_set::SmallSet;
 use starlark_map::Hashed;
@@ -32,6 +46,65 @@ use crate::values::FrozenValue;
 use crate::values::StringValue;
 use crate::values::Value;
 
+#[derive(Debug, thiserror::Error)]
+enum FreezeContainerError {
+    #[error("Keys of `{0}` collide after freezing")]
+    KeyCollision(&'static str),
+}
+
+/// Error returned by `#[derive(Freeze)]` implementations when a field fails to freeze.
+///
+/// Records the path to the field, so a failure deep inside nested structures
+/// can be traced back to the field. Can be obtained from the error returned by
+/// [`Freeze::freeze`] with [`anyhow::Error::downcast_ref`].
+#[derive(Debug)]
+pub struct FreezeError {
+    path: Vec<&'static str>,
+    error: anyhow::Error,
+}
+
+impl FreezeError {
+    /// Fields leading to the failure, outermost first.
+    /// Each field is formatted as `Type.field`, or `Type::Variant.field` for enums.
+    pub fn path(&self) -> &[&'static str] {
+        &self.path
+    }
+
+    /// The error of the innermost field.
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+
+    /// Record that `error` happened when freezing `field`.
+    /// Used by the code generated by `#[derive(Freeze)]`.
+    #[doc(hidden)]
+    pub fn with_field(error: anyhow::Error, field: &'static str) -> anyhow::Error {
+        match error.downcast::<FreezeError>() {
+            Ok(mut e) => {
+                e.path.insert(0, field);
+                e.into()
+            }
+            Err(error) => FreezeError {
+                path: vec![field],
+                error,
+            }
+            .into(),
+        }
+    }
+}
+
+impl Display for FreezeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error freezing `{}`", self.path.join(" -> "))
+    }
+}
+
+impl std::error::Error for FreezeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
 /// Need to be implemented for non-simple `StarlarkValue`.
 ///
 /// This is called on freeze of the heap. Must produce a replacement object to place
@@ -41,8 +114,10 @@ use crate::values::Value;
 ///
 /// ```
 /// # struct AdditionalData;
+/// # use std::cell::RefCell;
 ///
 /// use starlark::values::Freeze;
+/// use starlark::values::Freezer;
 ///
 /// #[derive(Freeze)]
 /// struct MyType<V> {
@@ -50,8 +125,26 @@ use crate::values::Value;
 ///     // This field does not implement `Freeze`, but we can use it as is for freeze.
 ///     #[freeze(identity)]
 ///     data: AdditionalData,
+///     // This field is not frozen, frozen value gets `Default::default()`.
+///     #[freeze(skip)]
+///     cache: RefCell<Vec<V>>,
+///     // Same, but frozen value gets given expression.
+///     #[freeze(default = 10)]
+///     capacity: usize,
+///     // This field does not implement `Freeze`, it is frozen with given function.
+///     #[freeze(with = "freeze_data")]
+///     extra: AdditionalData,
+/// }
+///
+/// fn freeze_data(data: AdditionalData, _freezer: &Freezer) -> anyhow::Result<AdditionalData> {
+///     Ok(data)
 /// }
 /// ```
+///
+/// Type parameters used in the types of frozen fields (here `V`) get a `Freeze` bound,
+/// and are replaced with their `Frozen` type in the frozen type.
+/// Other type parameters, for example markers only used in `PhantomData`
+/// or `#[freeze(identity)]` fields, get no bound and are kept as is.
 pub trait Freeze {
     /// When type is frozen, it is frozen into this type.
     type Frozen;
@@ -65,6 +158,35 @@ pub trait Freeze {
     fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen>;
 }
 
+/// Function called on the frozen value by `#[derive(Freeze)]` with
+/// `#[freeze(validator = function)]`, returning an error fails the freeze.
+///
+/// Implemented for functions `fn(&T) -> anyhow::Result<()>`, and for functions
+/// `fn(&T, &Freezer) -> anyhow::Result<()>` which need the freezer,
+/// for example to look into frozen values. `A` distinguishes these signatures.
+pub trait FreezeValidator<T, A> {
+    /// Validate the frozen value.
+    fn validate(&self, frozen: &T, freezer: &Freezer) -> anyhow::Result<()>;
+}
+
+impl<T, F> FreezeValidator<T, fn(&T)> for F
+where
+    F: Fn(&T) -> anyhow::Result<()>,
+{
+    fn validate(&self, frozen: &T, _freezer: &Freezer) -> anyhow::Result<()> {
+        self(frozen)
+    }
+}
+
+impl<T, F> FreezeValidator<T, fn(&T, &Freezer)> for F
+where
+    F: Fn(&T, &Freezer) -> anyhow::Result<()>,
+{
+    fn validate(&self, frozen: &T, freezer: &Freezer) -> anyhow::Result<()> {
+        self(frozen, freezer)
+    }
+}
+
 impl Freeze for String {
     type Frozen = String;
 
@@ -197,6 +319,46 @@ where
     }
 }
 
+impl<T, const N: usize> Freeze for [T; N]
+where
+    T: Freeze,
+{
+    type Frozen = [T::Frozen; N];
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let frozen = Vec::from(self).into_try_map(|v| v.freeze(freezer))?;
+        match frozen.try_into() {
+            Ok(frozen) => Ok(frozen),
+            Err(_) => unreachable!("freezing preserves length"),
+        }
+    }
+}
+
+impl<T> Freeze for VecDeque<T>
+where
+    T: Freeze,
+{
+    type Frozen = VecDeque<T::Frozen>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        self.into_iter().map(|v| v.freeze(freezer)).collect()
+    }
+}
+
+#[cfg(feature = "smallvec")]
+impl<T, const N: usize> Freeze for SmallVec<[T; N]>
+where
+    T: Freeze,
+    [T; N]: Array<Item = T>,
+    [T::Frozen; N]: Array<Item = T::Frozen>,
+{
+    type Frozen = SmallVec<[T::Frozen; N]>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        self.into_iter().map(|v| v.freeze(freezer)).collect()
+    }
+}
+
 impl<T> Freeze for Option<T>
 where
     T: Freeze,
@@ -258,6 +420,91 @@ where
     }
 }
 
+impl<K, V, S> Freeze for HashMap<K, V, S>
+where
+    K: Freeze,
+    K::Frozen: Hash + Eq,
+    V: Freeze,
+    S: BuildHasher + Default,
+{
+    type Frozen = HashMap<K::Frozen, V::Frozen, S>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let len = self.len();
+        let frozen: Self::Frozen = self
+            .into_iter()
+            .map(|(k, v)| Ok((k.freeze(freezer)?, v.freeze(freezer)?)))
+            .collect::<anyhow::Result<_>>()?;
+        check_no_key_collision::<Self>(len, frozen.len())?;
+        Ok(frozen)
+    }
+}
+
+impl<T, S> Freeze for HashSet<T, S>
+where
+    T: Freeze,
+    T::Frozen: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Frozen = HashSet<T::Frozen, S>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let len = self.len();
+        let frozen: Self::Frozen = self
+            .into_iter()
+            .map(|v| v.freeze(freezer))
+            .collect::<anyhow::Result<_>>()?;
+        check_no_key_collision::<Self>(len, frozen.len())?;
+        Ok(frozen)
+    }
+}
+
+impl<K, V> Freeze for BTreeMap<K, V>
+where
+    K: Freeze,
+    K::Frozen: Ord,
+    V: Freeze,
+{
+    type Frozen = BTreeMap<K::Frozen, V::Frozen>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let len = self.len();
+        let frozen: Self::Frozen = self
+            .into_iter()
+            .map(|(k, v)| Ok((k.freeze(freezer)?, v.freeze(freezer)?)))
+            .collect::<anyhow::Result<_>>()?;
+        check_no_key_collision::<Self>(len, frozen.len())?;
+        Ok(frozen)
+    }
+}
+
+impl<T> Freeze for BTreeSet<T>
+where
+    T: Freeze,
+    T::Frozen: Ord,
+{
+    type Frozen = BTreeSet<T::Frozen>;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<Self::Frozen> {
+        let len = self.len();
+        let frozen: Self::Frozen = self
+            .into_iter()
+            .map(|v| v.freeze(freezer))
+            .collect::<anyhow::Result<_>>()?;
+        check_no_key_collision::<Self>(len, frozen.len())?;
+        Ok(frozen)
+    }
+}
+
+/// Distinct keys which became equal when frozen would silently drop entries.
+fn check_no_key_collision<T>(len: usize, frozen_len: usize) -> anyhow::Result<()> {
+    if len == frozen_len {
+        Ok(())
+    } else {
+        Err(FreezeContainerError::KeyCollision(type_name::<T>()).into())
+    }
+}
+
 impl<'v> Freeze for Value<'v> {
     type Frozen = FrozenValue;
 
@@ -16,11 +16,15 @@
  */
 
 use std::fmt::Debug;
+use std::iter::FusedIterator;
 
 use crate::values::Heap;
 use crate::values::Value;
 
-/// Iterator of starlark values.
+/// Iterator of starlark values, created with [`Value::iterate`].
+///
+/// While the iterator is alive, the iterated value (e.g. a list) cannot be mutated.
+/// Mutation is allowed again once the iterator is exhausted or dropped.
 #[derive(Debug)]
 pub struct StarlarkIterator<'v> {
     /// Iterator implementation. Typically an iterable itself.
@@ -74,6 +78,9 @@ impl<'v> Iterator for StarlarkIterator<'v> {
     }
 }
 
+// After exhaustion `value` is replaced with empty tuple, which yields nothing.
+impl<'v> FusedIterator for StarlarkIterator<'v> {}
+
 impl<'v> Drop for StarlarkIterator<'v> {
     #[inline]
     fn drop(&mut self) {
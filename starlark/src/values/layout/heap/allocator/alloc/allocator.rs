@@ -195,6 +195,12 @@ impl ArenaAllocator for ChunkAllocator {
         self.replace_chain(new_chain);
         self.current_ptr.set(current_ptr);
     }
+
+    fn reset(&mut self) {
+        // Chunks are returned to the thread-local cache and reused from there.
+        let (mut chain, _current_ptr) = self.take_chain();
+        chain.clear_with(&mut thread_local_release);
+    }
 }
 
 #[cfg(test)]
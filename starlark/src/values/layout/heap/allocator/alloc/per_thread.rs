@@ -70,19 +70,22 @@ thread_local! {
     static PER_THREAD_ALLOCATOR: RefCell<PerThreadChunkCache> = RefCell::new(PerThreadChunkCache::default());
 }
 
+/// Chunks stop growing after this size.
+///
+/// Frozen values cannot be moved once allocated, so the unused tail of the last chunk
+/// stays pinned for the lifetime of the frozen heap. Capping the chunk size bounds
+/// that slack for large heaps (e.g. modules kept in a cache) to a constant,
+/// instead of up to a half of the heap with unbounded doubling.
+const MAX_CHUNK_SIZE: AlignedSize = AlignedSize::new_bytes(1 << 20);
+
 fn next_chunk_size(chunk_count_in_bump: usize) -> AlignedSize {
     // Replicate `bumpalo` behavior: 512 in the first chunk, double each next,
-    // but not greater than 2G.
-    // TODO(nga): we should stop doubling after 1M or so.
-    let size = AlignedSize::new_bytes(
-        512u32
-            .checked_shl(chunk_count_in_bump.try_into().unwrap())
-            .unwrap() as usize,
-    );
-    if size.bytes() == 0 {
-        AlignedSize::new_bytes(1 << 31)
-    } else {
-        size
+    // but not greater than `MAX_CHUNK_SIZE`.
+    match 512u32.checked_shl(chunk_count_in_bump.try_into().unwrap_or(u32::MAX)) {
+        Some(size) if size != 0 && (size as usize) < MAX_CHUNK_SIZE.bytes() as usize => {
+            AlignedSize::new_bytes(size as usize)
+        }
+        _ => MAX_CHUNK_SIZE,
     }
 }
 
@@ -127,7 +130,9 @@ pub(crate) fn thread_local_release(chunk: ChunkPart) {
 mod tests {
     use crate::values::layout::aligned_size::AlignedSize;
     use crate::values::layout::heap::allocator::alloc::chunk_part::ChunkPart;
+    use crate::values::layout::heap::allocator::alloc::per_thread::next_chunk_size;
     use crate::values::layout::heap::allocator::alloc::per_thread::PerThreadChunkCache;
+    use crate::values::layout::heap::allocator::alloc::per_thread::MAX_CHUNK_SIZE;
     use crate::values::layout::heap::repr::AValueHeader;
 
     #[test]
@@ -148,4 +153,14 @@ mod tests {
         assert!(old_a_ptr == a.begin().as_ptr() || old_a_ptr == b.begin().as_ptr());
         assert!(old_b_ptr == a.begin().as_ptr() || old_b_ptr == b.begin().as_ptr());
     }
+
+    #[test]
+    fn test_next_chunk_size() {
+        assert_eq!(AlignedSize::new_bytes(512), next_chunk_size(0));
+        assert_eq!(AlignedSize::new_bytes(1024), next_chunk_size(1));
+        assert_eq!(MAX_CHUNK_SIZE, next_chunk_size(11));
+        assert_eq!(MAX_CHUNK_SIZE, next_chunk_size(12));
+        assert_eq!(MAX_CHUNK_SIZE, next_chunk_size(40));
+        assert_eq!(MAX_CHUNK_SIZE, next_chunk_size(usize::MAX));
+    }
 }
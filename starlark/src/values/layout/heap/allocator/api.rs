@@ -56,4 +56,7 @@ pub(crate) trait ArenaAllocator {
 
     /// No more allocation, reclaim memory if possible.
     fn finish(&mut self);
+
+    /// Forget all allocations, keeping memory for reuse if possible.
+    fn reset(&mut self);
 }
@@ -78,4 +78,8 @@ impl ArenaAllocator for Bump {
     }
 
     fn finish(&mut self) {}
+
+    fn reset(&mut self) {
+        Bump::reset(self)
+    }
 }
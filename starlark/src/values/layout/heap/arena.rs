@@ -51,9 +51,12 @@ use crate::values::layout::heap::call_enter_exit::CallEnter;
 use crate::values::layout::heap::call_enter_exit::CallExit;
 use crate::values::layout::heap::call_enter_exit::NeedsDrop;
 use crate::values::layout::heap::call_enter_exit::NoDrop;
+use crate::values::layout::heap::escape;
 use crate::values::layout::heap::heap_type::HeapKind;
 use crate::values::layout::heap::profile::alloc_counts::AllocCounts;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
+use crate::values::layout::heap::profile::stats::HeapStats;
+use crate::values::layout::heap::profile::stats::HeapStatsBuilder;
 use crate::values::layout::heap::repr::AValueForward;
 use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueOrForward;
@@ -217,6 +220,22 @@ impl<A: ArenaAllocator> Arena<A> {
         self.drop.remaining_capacity() + self.non_drop.remaining_capacity()
     }
 
+    /// Drop all the values, keeping the memory for new allocations.
+    pub(crate) fn reset(&mut self) {
+        self.drop_values();
+        self.drop.reset();
+        self.non_drop.reset();
+    }
+
+    fn drop_values(&mut self) {
+        escape::check(|addr| self.contains(addr));
+        self.for_each_drop_unordered(|x| {
+            // Safe to convert to *mut because we are the only owner
+            let value = x.payload_ptr();
+            x.0.drop_in_place(value);
+        });
+    }
+
     /// Don't forget to call this function to release memory.
     pub(crate) fn finish(&mut self) {
         self.drop.finish();
@@ -438,6 +457,17 @@ impl<A: ArenaAllocator> Arena<A> {
         }
     }
 
+    /// Is the address inside the memory of this arena.
+    fn contains(&self, addr: usize) -> bool {
+        [&self.drop, &self.non_drop].iter().any(|bump| {
+            // SAFETY: We're consuming the iterator immediately and not allocating from the arena during.
+            unsafe {
+                bump.iter_allocated_chunks_rev()
+                    .any(|chunk| chunk.as_ptr_range().contains(&(addr as *const _)))
+            }
+        })
+    }
+
     // Iterate over the values in the both bumps in any order
     fn for_each_unordered<'a>(&'a self, mut f: impl FnMut(&'a AValueHeader)) {
         for bump in [&self.drop, &self.non_drop] {
@@ -469,15 +499,21 @@ impl<A: ArenaAllocator> Arena<A> {
         }
         HeapSummary { summary }
     }
+
+    /// Per type statistics of the values in the arena.
+    pub(crate) fn stats(&self) -> HeapStats {
+        let mut stats = HeapStatsBuilder::default();
+        self.for_each_unordered(|x| {
+            let v = x.unpack();
+            stats.add(v.vtable().type_name, v.total_memory());
+        });
+        stats.build()
+    }
 }
 
 impl<A: ArenaAllocator> Drop for Arena<A> {
     fn drop(&mut self) {
-        self.for_each_drop_unordered(|x| {
-            // Safe to convert to *mut because we are the only owner
-            let value = x.payload_ptr();
-            x.0.drop_in_place(value);
-        });
+        self.drop_values();
     }
 }
 
@@ -0,0 +1,204 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Debug-mode detection of mutable values kept by host code after their heap is gone.
+
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::cell::RefCell;
+
+use crate::values::Value;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+    static ESCAPES: RefCell<Vec<Escape>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Value recorded with [`Value::debug_escape_guard`].
+struct Escape {
+    id: u64,
+    /// Address of the value in its heap arena.
+    addr: usize,
+    type_name: &'static str,
+    backtrace: Backtrace,
+}
+
+/// Enable or disable escape detection for the current thread.
+///
+/// When enabled, in debug builds, mutable values recorded with
+/// [`Value::debug_escape_guard`] are checked each time a heap drops its values
+/// (when the heap is dropped, reset or garbage collected):
+/// if a recorded value is still guarded, this panics with the Rust backtrace
+/// of the place which recorded it.
+///
+/// Recording captures a backtrace, so this is meant for tests, not for production.
+/// In release builds, this does nothing.
+pub fn set_escape_detection(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled));
+}
+
+/// Returned by [`Value::debug_escape_guard`]: the value is considered kept by host code
+/// until the guard is dropped.
+#[must_use]
+#[derive(Debug)]
+pub struct EscapeGuard {
+    id: Option<u64>,
+}
+
+impl Drop for EscapeGuard {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            ESCAPES.with(|escapes| escapes.borrow_mut().retain(|e| e.id != id));
+        }
+    }
+}
+
+pub(crate) fn record(value: Value) -> EscapeGuard {
+    if !cfg!(debug_assertions) || !ENABLED.with(|e| e.get()) {
+        return EscapeGuard { id: None };
+    }
+    if !value.0.is_unfrozen() {
+        // Frozen values live as long as their frozen heap is referenced.
+        return EscapeGuard { id: None };
+    }
+    let Some(ptr) = value.0.unpack_ptr() else {
+        return EscapeGuard { id: None };
+    };
+    let id = NEXT_ID.with(|n| {
+        let id = n.get();
+        n.set(id + 1);
+        id
+    });
+    ESCAPES.with(|escapes| {
+        escapes.borrow_mut().push(Escape {
+            id,
+            addr: ptr as *const _ as usize,
+            type_name: value.get_type(),
+            backtrace: Backtrace::force_capture(),
+        })
+    });
+    EscapeGuard { id: Some(id) }
+}
+
+/// Called before an arena drops its values: panic if a recorded value is in the arena.
+pub(crate) fn check(contains: impl Fn(usize) -> bool) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let escaped = ESCAPES.with(|escapes| {
+        let mut escapes = escapes.borrow_mut();
+        let i = escapes.iter().position(|e| contains(e.addr))?;
+        Some(escapes.remove(i))
+    });
+    if let Some(escape) = escaped {
+        if !std::thread::panicking() {
+            panic!(
+                "Mutable value of type `{}` is kept by host code after its heap dropped its values \
+                (the heap was dropped, reset or garbage collected), recorded at:\n{}",
+                escape.type_name, escape.backtrace
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+    use std::panic::AssertUnwindSafe;
+
+    use crate::values::layout::heap::escape::set_escape_detection;
+    use crate::values::FrozenHeap;
+    use crate::values::Heap;
+
+    fn panic_message(f: impl FnOnce()) -> Option<String> {
+        let err = panic::catch_unwind(AssertUnwindSafe(f)).err()?;
+        Some(err.downcast_ref::<String>().unwrap().clone())
+    }
+
+    #[test]
+    fn test_try_frozen() {
+        let heap = Heap::new();
+        let frozen_heap = FrozenHeap::new();
+        assert!(frozen_heap.alloc(vec![1]).to_value().try_frozen().is_ok());
+        assert!(heap.alloc(1).try_frozen().is_ok());
+        assert_eq!(
+            "Expected a frozen value but got a mutable value of type `list`",
+            heap.alloc(vec![1]).try_frozen().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_escape_detected() {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        set_escape_detection(true);
+        let heap = Heap::new();
+        let guard = heap.alloc(vec![1, 2]).debug_escape_guard();
+        let message = panic_message(|| drop(heap)).unwrap();
+        assert!(
+            message.starts_with("Mutable value of type `list` is kept by host code"),
+            "{message}"
+        );
+        assert!(message.contains("test_escape_detected"), "{message}");
+        drop(guard);
+        set_escape_detection(false);
+    }
+
+    #[test]
+    fn test_escape_detected_on_gc() {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        set_escape_detection(true);
+        let heap = Heap::new();
+        let guard = heap.alloc(vec![1, 2]).debug_escape_guard();
+        // SAFETY: the value is not used after garbage collection.
+        let message = panic_message(|| unsafe { heap.garbage_collect(|_| {}) }).unwrap();
+        assert!(message.contains("`list`"), "{message}");
+        drop(guard);
+        set_escape_detection(false);
+    }
+
+    #[test]
+    fn test_no_escape() {
+        set_escape_detection(true);
+        let heap = Heap::new();
+        let guard = heap.alloc(vec![1, 2]).debug_escape_guard();
+        drop(guard);
+        drop(heap);
+
+        // Frozen values are not tracked.
+        let frozen_heap = FrozenHeap::new();
+        let guard = frozen_heap
+            .alloc("x".repeat(100))
+            .to_value()
+            .debug_escape_guard();
+        drop(frozen_heap);
+        drop(guard);
+        set_escape_detection(false);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let heap = Heap::new();
+        let guard = heap.alloc(vec![1, 2]).debug_escape_guard();
+        drop(heap);
+        drop(guard);
+    }
+}
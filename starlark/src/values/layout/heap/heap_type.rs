@@ -74,7 +74,11 @@ use crate::values::layout::heap::call_enter_exit::NoDrop;
 use crate::values::layout::heap::fast_cell::FastCell;
 use crate::values::layout::heap::maybe_uninit_slice_util::maybe_uninit_write_from_exact_size_iter;
 use crate::values::layout::heap::profile::by_type::HeapSummary;
+use crate::values::layout::heap::profile::stats::FreezeStats;
+use crate::values::layout::heap::profile::stats::HeapStats;
+use crate::values::layout::heap::profile::stats::HeapStatsBuilder;
 use crate::values::layout::heap::repr::AValueRepr;
+use crate::values::layout::identity::ValueIdentity;
 use crate::values::layout::static_string::constant_string;
 use crate::values::layout::typed::string::StringValueLike;
 use crate::values::layout::value::FrozenValue;
@@ -92,12 +96,21 @@ use crate::values::FrozenValueOfUnchecked;
 use crate::values::FrozenValueTyped;
 use crate::values::StarlarkValue;
 use crate::values::StringValue;
+use crate::values::Thawer;
 use crate::values::Trace;
 use crate::values::UnpackValue;
 use crate::values::ValueOf;
 use crate::values::ValueOfUnchecked;
 use crate::values::ValueTyped;
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum HeapError {
+    #[error("Heap allocation limit of {0} bytes exceeded")]
+    AllocationLimitExceeded(usize),
+    #[error("Injected allocation failure: more than {0} values were allocated")]
+    AllocationFailureInjected(usize),
+}
+
 #[derive(Copy, Clone, Dupe)]
 pub(crate) enum HeapKind {
     Unfrozen,
@@ -109,6 +122,16 @@ pub(crate) enum HeapKind {
 pub struct Heap {
     /// Peak memory seen when a garbage collection takes place (may be lower than currently allocated)
     peak_allocated: Cell<usize>,
+    /// Any of the limits below is set, so allocations need to check them.
+    limited: Cell<bool>,
+    /// Limit on allocated bytes.
+    allocation_limit: Cell<Option<usize>>,
+    /// An allocation made the heap exceed `allocation_limit`.
+    allocation_limit_exceeded: Cell<bool>,
+    /// Number of allocations allowed before an injected failure, and how many are left.
+    allocations_left: Cell<Option<(usize, usize)>>,
+    /// An allocation was made after `allocations_left` reached zero.
+    allocation_failure_injected: Cell<bool>,
     arena: FastCell<Arena<Bump>>,
     str_interner: RefCell<StringValueInterner<'static>>,
 }
@@ -236,6 +259,14 @@ impl FrozenHeapRef {
             .as_ref()
             .map_or_else(HeapSummary::default, |a| a.arena.allocated_summary())
     }
+
+    /// Per type statistics of the values in this heap, reachable or not.
+    /// Doesn't include the heaps it keeps alive by reference.
+    pub fn stats(&self) -> HeapStats {
+        self.0
+            .as_ref()
+            .map_or_else(HeapStats::default, |a| a.arena.stats())
+    }
 }
 
 impl FrozenHeap {
@@ -247,6 +278,10 @@ impl FrozenHeap {
     /// After all values have been allocated, convert the [`FrozenHeap`] into a
     /// [`FrozenHeapRef`] which can be [`clone`](Clone::clone)d, shared between threads,
     /// and ensures the underlying values allocated on the [`FrozenHeap`] remain valid.
+    ///
+    /// The unused tail of the last arena chunk is released here, but values are not
+    /// copied into tighter buffers: frozen values are referenced by address, so they
+    /// cannot be moved once allocated.
     pub fn into_ref(self) -> FrozenHeapRef {
         let FrozenHeap {
             mut arena, refs, ..
@@ -316,8 +351,9 @@ impl FrozenHeap {
         self.alloc_str_impl(x, StarlarkStr::UNINIT_HASH)
     }
 
-    /// Intern string.
-    pub(crate) fn alloc_str_intern(&self, s: &str) -> FrozenStringValue {
+    /// Allocate a string on this heap, reusing a previously interned string if
+    /// an equal one was already interned on this heap.
+    pub fn alloc_str_intern(&self, s: &str) -> FrozenStringValue {
         if let Some(s) = constant_string(s) {
             s
         } else {
@@ -509,9 +545,20 @@ impl FrozenHeap {
     pub fn allocated_summary(&self) -> HeapSummary {
         self.arena.allocated_summary()
     }
+
+    /// Per type statistics of the values in this heap, reachable or not.
+    pub fn stats(&self) -> HeapStats {
+        self.arena.stats()
+    }
 }
 
 /// Used to `freeze` values by [`Freeze::freeze`](crate::values::Freeze::freeze).
+///
+/// A freezer only exists while a whole [`Module`](crate::environment::Module) is frozen:
+/// freezing a value overwrites it with a forward to the frozen copy,
+/// so values cannot be frozen while the evaluation which may still reference them continues.
+/// To freeze parts of a large program early, evaluate them as separate modules
+/// and `load` them.
 // A freezer is a pair of the FrozenHeap and a "magic" value,
 // which we happen to use for the slots (see `FrozenSlotsRef`)
 // but could be used for anything.
@@ -520,6 +567,39 @@ pub struct Freezer {
     pub(crate) heap: FrozenHeap,
     /// Defs frozen by this freezer.
     pub(crate) frozen_defs: RefCell<Vec<FrozenRef<'static, FrozenDef>>>,
+    /// Called after each heap value is frozen.
+    pub(crate) on_frozen: OnFrozenHooks,
+    /// Values frozen so far, when statistics are enabled.
+    frozen_stats: Option<RefCell<HeapStatsBuilder>>,
+    /// Number of values found already frozen.
+    deduplicated: Cell<usize>,
+}
+
+/// Callbacks invoked with the identity of each value before freezing
+/// and the value it was frozen to.
+#[derive(Default)]
+pub(crate) struct OnFrozenHooks {
+    hooks: RefCell<Vec<Box<dyn Fn(ValueIdentity<'static>, FrozenValue)>>>,
+}
+
+impl Debug for OnFrozenHooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnFrozenHooks")
+            .field("len", &self.hooks.borrow().len())
+            .finish()
+    }
+}
+
+impl OnFrozenHooks {
+    pub(crate) fn add(&self, hook: Box<dyn Fn(ValueIdentity<'static>, FrozenValue)>) {
+        self.hooks.borrow_mut().push(hook);
+    }
+
+    fn call(&self, identity: ValueIdentity<'static>, frozen: FrozenValue) {
+        for hook in self.hooks.borrow().iter() {
+            hook(identity, frozen);
+        }
+    }
 }
 
 impl Freezer {
@@ -527,9 +607,41 @@ impl Freezer {
         Freezer {
             heap,
             frozen_defs: RefCell::new(Vec::new()),
+            on_frozen: OnFrozenHooks::default(),
+            frozen_stats: None,
+            deduplicated: Cell::new(0),
         }
     }
 
+    /// Collect statistics of the values frozen from now on.
+    pub(crate) fn enable_stats(&mut self) {
+        self.frozen_stats = Some(RefCell::new(HeapStatsBuilder::default()));
+    }
+
+    /// Statistics of the values frozen so far by this freezer,
+    /// `None` unless statistics are enabled,
+    /// see [`Module::enable_freeze_stats`](crate::environment::Module::enable_freeze_stats).
+    pub fn stats(&self) -> Option<FreezeStats> {
+        let frozen_stats = self.frozen_stats.as_ref()?;
+        Some(FreezeStats {
+            frozen: frozen_stats.borrow().clone().build(),
+            deduplicated: self.deduplicated.get(),
+        })
+    }
+
+    /// Register a callback invoked each time a value allocated on the mutable heap
+    /// is frozen, with the identity the value had before freezing and its frozen copy.
+    ///
+    /// Can be used to build side tables mapping mutable values to their frozen
+    /// counterparts. The old value must not be accessed: it has already been
+    /// overwritten when the callback is invoked. Values frozen before the callback
+    /// is registered are not reported; use
+    /// [`Module::on_frozen`](crate::environment::Module::on_frozen)
+    /// to observe the whole module freeze.
+    pub fn on_frozen(&self, hook: impl Fn(ValueIdentity<'static>, FrozenValue) + 'static) {
+        self.on_frozen.add(Box::new(hook));
+    }
+
     pub(crate) fn into_ref(self) -> FrozenHeapRef {
         self.heap.into_ref()
     }
@@ -569,10 +681,24 @@ impl Freezer {
         }
 
         // Case 2: We have already been replaced with a forwarding, or need to freeze
+        let identity = value.identity().erase_lifetime();
         let value = value.0.unpack_ptr().unwrap();
         match value.unpack_overwrite() {
-            Either::Left(x) => Ok(unsafe { x.unpack_frozen_value() }),
-            Either::Right(v) => unsafe { v.heap_freeze(self) },
+            Either::Left(x) => {
+                self.deduplicated.set(self.deduplicated.get() + 1);
+                Ok(unsafe { x.unpack_frozen_value() })
+            }
+            Either::Right(v) => {
+                let frozen = unsafe { v.heap_freeze(self)? };
+                if let Some(frozen_stats) = &self.frozen_stats {
+                    let frozen_ref = frozen.to_value().get_ref();
+                    frozen_stats
+                        .borrow_mut()
+                        .add(frozen_ref.vtable().type_name, frozen_ref.total_memory());
+                }
+                self.on_frozen.call(identity, frozen);
+                Ok(frozen)
+            }
         }
     }
 
@@ -602,15 +728,141 @@ impl Heap {
         cmp::max(self.allocated_bytes(), self.peak_allocated.get())
     }
 
+    #[inline]
+    fn before_alloc(&self) {
+        if self.limited.get() {
+            self.check_allocation_limits();
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn check_allocation_limits(&self) {
+        if let Some(limit) = self.allocation_limit.get() {
+            if self.allocated_bytes() > limit {
+                self.allocation_limit_exceeded.set(true);
+            }
+        }
+        if let Some((allowed, left)) = self.allocations_left.get() {
+            match left.checked_sub(1) {
+                Some(left) => self.allocations_left.set(Some((allowed, left))),
+                None => self.allocation_failure_injected.set(true),
+            }
+        }
+    }
+
+    /// Limit the number of bytes allocated on this heap, or remove the limit with `None`.
+    ///
+    /// Allocations cannot fail, so exceeding the limit is only recorded,
+    /// see [`allocation_error`](Heap::allocation_error).
+    /// Setting a new limit forgets that the previous one was exceeded.
+    pub(crate) fn set_allocation_limit(&self, bytes: Option<usize>) {
+        self.allocation_limit.set(bytes);
+        self.allocation_limit_exceeded.set(false);
+        self.limited
+            .set(bytes.is_some() || self.allocations_left.get().is_some());
+    }
+
+    /// The limit set with [`set_allocation_limit`](Heap::set_allocation_limit).
+    pub(crate) fn allocation_limit(&self) -> Option<usize> {
+        self.allocation_limit.get()
+    }
+
+    /// An allocation exceeded the limit set with
+    /// [`set_allocation_limit`](Heap::set_allocation_limit).
+    pub(crate) fn allocation_limit_exceeded(&self) -> bool {
+        self.allocation_limit_exceeded.get()
+    }
+
+    /// Record a failure once more than `allocations` values are allocated on this heap.
+    ///
+    /// Allocations cannot fail, so the failure is only recorded,
+    /// see [`allocation_error`](Heap::allocation_error).
+    pub(crate) fn fail_allocations_after(&self, allocations: usize) {
+        self.allocations_left.set(Some((allocations, allocations)));
+        self.allocation_failure_injected.set(false);
+        self.limited.set(true);
+    }
+
+    /// An allocation exceeded the limit set with
+    /// [`set_allocation_limit`](Heap::set_allocation_limit)
+    /// or [`fail_allocations_after`](Heap::fail_allocations_after).
+    pub(crate) fn allocation_error(&self) -> Option<HeapError> {
+        if self.allocation_limit_exceeded.get() {
+            self.allocation_limit
+                .get()
+                .map(HeapError::AllocationLimitExceeded)
+        } else if self.allocation_failure_injected.get() {
+            self.allocations_left
+                .get()
+                .map(|(allowed, _)| HeapError::AllocationFailureInjected(allowed))
+        } else {
+            None
+        }
+    }
+
+    /// Fail if allocating `bytes` more would exceed the limit set with
+    /// [`set_allocation_limit`](Heap::set_allocation_limit), or if no more allocations
+    /// are allowed by [`fail_allocations_after`](Heap::fail_allocations_after).
+    ///
+    /// Called before operations whose result is not bounded by the size of their operands,
+    /// such as `[0] * n`, so they fail before the memory is allocated.
+    pub(crate) fn check_allocation(&self, bytes: usize) -> crate::Result<()> {
+        if !self.limited.get() {
+            return Ok(());
+        }
+        if let Some(limit) = self.allocation_limit.get() {
+            if self.allocated_bytes().saturating_add(bytes) > limit {
+                self.allocation_limit_exceeded.set(true);
+            }
+        }
+        if let Some((_, 0)) = self.allocations_left.get() {
+            self.allocation_failure_injected.set(true);
+        }
+        match self.allocation_error() {
+            Some(e) => Err(crate::Error::new_other(e)),
+            None => Ok(()),
+        }
+    }
+
     /// Number of bytes allocated by the heap but not yet filled.
     pub fn available_bytes(&self) -> usize {
         self.arena.borrow().available_bytes()
     }
 
+    /// Drop all the values allocated on this heap, keeping the memory
+    /// to be reused by subsequent allocations.
+    ///
+    /// This is cheaper than creating a new heap for each of many small evaluations.
+    /// Values are tied to the lifetime of a shared borrow of the heap,
+    /// so none of them can be used after the reset.
+    /// Allocation limits and injected allocation failures are removed.
+    pub fn reset(&mut self) {
+        self.peak_allocated.set(self.peak_allocated_bytes());
+        self.limited.set(false);
+        self.allocation_limit.set(None);
+        self.allocation_limit_exceeded.set(false);
+        self.allocations_left.set(None);
+        self.allocation_failure_injected.set(false);
+        self.str_interner.get_mut().clear();
+        // SAFETY: `&mut self` guarantees there are no values pointing into the arena.
+        unsafe {
+            (*self.arena.get_mut()).reset();
+        }
+    }
+
+    /// Copy a frozen value into this heap, as fresh mutable values.
+    ///
+    /// See [`Thawer`] for which values are copied.
+    pub fn thaw<'v>(&'v self, value: FrozenValue) -> anyhow::Result<Value<'v>> {
+        Thawer::new(self).thaw(value)
+    }
+
     fn alloc_raw<'v, 'v2: 'v2>(
         &'v self,
         x: AValueImpl<'v2, impl AValue<'v2, ExtraElem = ()>>,
     ) -> Value<'v> {
+        self.before_alloc();
         let arena = self.arena.borrow();
         let v: &AValueRepr<_> = arena.alloc(x);
 
@@ -636,6 +888,7 @@ impl Heap {
         hash: StarlarkHashValue,
         init: impl FnOnce(*mut u8),
     ) -> StringValue<'v> {
+        self.before_alloc();
         let arena = self.arena.borrow();
         let v = arena.alloc_str_init(len, hash, init);
 
@@ -727,6 +980,7 @@ impl Heap {
             return Value::new_empty_tuple();
         }
 
+        self.before_alloc();
         unsafe {
             let arena = self.arena.borrow();
             let (avalue, extra) = arena.alloc_extra(tuple_avalue(elems.len()));
@@ -747,6 +1001,7 @@ impl Heap {
                 return Value::new_empty_tuple();
             }
 
+            self.before_alloc();
             unsafe {
                 let arena = self.arena.borrow();
                 let (avalue, extra) = arena.alloc_extra(tuple_avalue(lower));
@@ -766,6 +1021,7 @@ impl Heap {
 
         let cap: u32 = cap.try_into().expect("capacity overflows u32::MAX");
 
+        self.before_alloc();
         unsafe {
             let (avalue, _) = self.arena.borrow().alloc_extra(array_avalue(cap));
             ValueTyped::new_repr(&*avalue)
@@ -917,6 +1173,14 @@ impl Heap {
         self.arena.borrow().allocated_summary()
     }
 
+    /// Per type statistics of the values in this heap.
+    ///
+    /// Includes values which are no longer reachable,
+    /// but were not yet removed by garbage collection.
+    pub fn stats(&self) -> HeapStats {
+        self.arena.borrow().stats()
+    }
+
     pub(crate) fn record_call_enter<'v>(&'v self, function: Value<'v>) {
         let time = ProfilerInstant::now();
         assert!(mem::needs_drop::<CallEnter<NeedsDrop>>());
@@ -1072,4 +1336,28 @@ assert_eq(x, "foox")
         "#,
         );
     }
+
+    #[test]
+    fn test_reset() {
+        let mut heap = Heap::new();
+        let mut allocated = Vec::new();
+        for _ in 0..3 {
+            for i in 0..1000 {
+                heap.alloc(vec![i]);
+                heap.alloc_str_intern("interned");
+            }
+            allocated.push(heap.allocated_bytes());
+            heap.reset();
+            // Retained memory is all available for new allocations.
+            assert_eq!(heap.allocated_bytes(), heap.available_bytes());
+
+            let s = heap.alloc_str_intern("interned");
+            assert_eq!("interned", s.as_str());
+            assert_eq!("[1, 2]", heap.alloc(vec![1, 2]).to_repr());
+            heap.reset();
+        }
+        // Once the retained chunk is big enough, the heap stops growing.
+        assert!(allocated[2] <= allocated[1]);
+        assert!(heap.peak_allocated_bytes() >= allocated[1]);
+    }
 }
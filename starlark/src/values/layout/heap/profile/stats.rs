@@ -0,0 +1,270 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use starlark_map::small_map::SmallMap;
+
+/// Statistics of the values stored in a heap, grouped by type.
+///
+/// Returned by `stats` on [`Heap`](crate::values::Heap),
+/// [`FrozenHeap`](crate::values::FrozenHeap) and
+/// [`FrozenHeapRef`](crate::values::FrozenHeapRef).
+/// Every value stored in the heap is counted, whether or not it is still reachable:
+/// for a mutable heap, values which became unreachable since the last
+/// garbage collection are included, and a frozen heap keeps (and counts)
+/// all the values allocated in it, even those no frozen value refers to.
+#[derive(Debug, Clone, Default, Allocative)]
+pub struct HeapStats {
+    /// Sorted by total bytes, largest first.
+    types: Vec<HeapTypeStats>,
+}
+
+/// Statistics of the values of one type.
+#[derive(Debug, Clone, Allocative)]
+pub struct HeapTypeStats {
+    type_name: &'static str,
+    count: usize,
+    bytes: usize,
+    /// Index `i` is the number of values with size in `(2^(i-1), 2^i]` bytes.
+    size_histogram: Vec<usize>,
+}
+
+impl HeapTypeStats {
+    fn new(type_name: &'static str) -> HeapTypeStats {
+        HeapTypeStats {
+            type_name,
+            count: 0,
+            bytes: 0,
+            size_histogram: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, bytes: usize) {
+        self.count += 1;
+        self.bytes += bytes;
+        let bucket = bytes.next_power_of_two().trailing_zeros() as usize;
+        if self.size_histogram.len() <= bucket {
+            self.size_histogram.resize(bucket + 1, 0);
+        }
+        self.size_histogram[bucket] += 1;
+    }
+
+    /// Name of the type, as returned by `type()`.
+    pub fn type_name(&self) -> &str {
+        self.type_name
+    }
+
+    /// Number of values.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Total size of the values, including memory owned by values outside of the heap
+    /// (as reported by [`Allocative`](allocative::Allocative)).
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Average size of a value in bytes.
+    pub fn average_bytes(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.bytes as f64 / self.count as f64
+        }
+    }
+
+    /// Histogram of value sizes: pairs of (upper bound in bytes, number of values),
+    /// where the buckets are powers of two, and empty buckets are skipped.
+    pub fn size_histogram(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.size_histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count != 0)
+            .map(|(i, count)| (1 << i, *count))
+    }
+}
+
+impl HeapStats {
+    /// Statistics for each type, sorted by total bytes, largest first.
+    pub fn types(&self) -> &[HeapTypeStats] {
+        &self.types
+    }
+
+    /// Statistics for the given type.
+    pub fn get(&self, type_name: &str) -> Option<&HeapTypeStats> {
+        self.types.iter().find(|t| t.type_name == type_name)
+    }
+
+    /// Total number of values.
+    pub fn total_count(&self) -> usize {
+        self.types.iter().map(|t| t.count).sum()
+    }
+
+    /// Total size of all the values.
+    pub fn total_bytes(&self) -> usize {
+        self.types.iter().map(|t| t.bytes).sum()
+    }
+}
+
+impl Display for HeapStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<24} {:>10} {:>12} {:>10}  sizes",
+            "type", "count", "bytes", "average"
+        )?;
+        for t in &self.types {
+            write!(
+                f,
+                "{:<24} {:>10} {:>12} {:>10.1} ",
+                t.type_name,
+                t.count,
+                t.bytes,
+                t.average_bytes()
+            )?;
+            for (size, count) in t.size_histogram() {
+                write!(f, " <={size}:{count}")?;
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "{:<24} {:>10} {:>12}",
+            "total",
+            self.total_count(),
+            self.total_bytes()
+        )
+    }
+}
+
+/// Statistics of freezing, returned by [`Freezer::stats`](crate::values::Freezer::stats)
+/// and [`FrozenModule::freeze_stats`](crate::environment::FrozenModule::freeze_stats).
+#[derive(Debug, Clone, Default, Allocative)]
+pub struct FreezeStats {
+    pub(crate) frozen: HeapStats,
+    pub(crate) deduplicated: usize,
+}
+
+impl FreezeStats {
+    /// Values copied from the mutable heap to the frozen heap, grouped by type.
+    /// Values allocated directly in the frozen heap are not included.
+    pub fn frozen(&self) -> &HeapStats {
+        &self.frozen
+    }
+
+    /// Number of times a value reachable from several places was found already frozen,
+    /// and was shared instead of being copied again.
+    pub fn deduplicated(&self) -> usize {
+        self.deduplicated
+    }
+}
+
+impl Display for FreezeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.frozen)?;
+        write!(f, "deduplicated {}", self.deduplicated)
+    }
+}
+
+/// Accumulate [`HeapStats`] value by value.
+#[derive(Default, Clone)]
+pub(crate) struct HeapStatsBuilder {
+    types: SmallMap<&'static str, HeapTypeStats>,
+}
+
+impl HeapStatsBuilder {
+    pub(crate) fn add(&mut self, type_name: &'static str, bytes: usize) {
+        self.types
+            .entry(type_name)
+            .or_insert_with(|| HeapTypeStats::new(type_name))
+            .add(bytes);
+    }
+
+    pub(crate) fn build(self) -> HeapStats {
+        let mut types: Vec<HeapTypeStats> = self.types.into_values().collect();
+        types.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.type_name.cmp(b.type_name))
+        });
+        HeapStats { types }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Module;
+    use crate::values::FrozenHeap;
+    use crate::values::FrozenHeapRef;
+    use crate::values::Heap;
+
+    #[test]
+    fn test_heap_stats() {
+        let heap = Heap::new();
+        heap.alloc_str("a long enough string not to be static");
+        heap.alloc_str("another long enough string not to be static");
+        heap.alloc(vec![1, 2, 3]);
+        let stats = heap.stats();
+        let strings = stats.get("string").unwrap();
+        assert_eq!(2, strings.count());
+        assert!(strings.average_bytes() > 0.0);
+        assert_eq!(2, strings.size_histogram().map(|(_, c)| c).sum::<usize>());
+        assert_eq!(1, stats.get("list").unwrap().count());
+        assert_eq!(
+            stats.total_count(),
+            stats.types().iter().map(|t| t.count()).sum::<usize>()
+        );
+        assert!(stats.to_string().contains("string"));
+    }
+
+    #[test]
+    fn test_frozen_heap_stats() {
+        let heap = FrozenHeap::new();
+        heap.alloc_str("a long enough string not to be static");
+        assert_eq!(1, heap.stats().get("string").unwrap().count());
+        let heap = heap.into_ref();
+        assert_eq!(1, heap.stats().get("string").unwrap().count());
+        assert_eq!(0, FrozenHeapRef::default().stats().total_count());
+    }
+
+    #[test]
+    fn test_freeze_stats() {
+        let module = Module::new();
+        module.enable_freeze_stats();
+        let list = module.heap().alloc(vec![1, 2]);
+        module.set("x", list);
+        module.set("y", module.heap().alloc((list, list)));
+        let module = module.freeze().unwrap();
+        let stats = module.freeze_stats().unwrap();
+        assert_eq!(1, stats.frozen().get("list").unwrap().count());
+        assert_eq!(1, stats.frozen().get("tuple").unwrap().count());
+        // Second and third references to the list.
+        assert_eq!(2, stats.deduplicated());
+        assert!(stats.to_string().ends_with("deduplicated 2"));
+    }
+
+    #[test]
+    fn test_freeze_stats_disabled() {
+        let module = Module::new();
+        module.set("x", module.heap().alloc(vec![1, 2]));
+        assert!(module.freeze().unwrap().freeze_stats().is_none());
+    }
+}
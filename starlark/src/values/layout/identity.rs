@@ -19,12 +19,17 @@ use std::marker::PhantomData;
 
 use allocative::Allocative;
 use dupe::Dupe;
+use starlark_map::small_map::SmallMap;
+use starlark_map::small_set::SmallSet;
 
 use crate::values::layout::pointer::RawPointer;
 use crate::values::Value;
 
 /// An opaque value representing the identity of a given Value. Two values have the same identity
 /// if and only if [`Value::ptr_eq`] would return [`true`] on them.
+///
+/// Identities of unfrozen values are only valid until the next garbage collection,
+/// so they should not be stored across evaluations.
 #[derive(Eq, PartialEq, Copy, Clone, Dupe, Hash, Debug, Allocative)]
 pub struct ValueIdentity<'v> {
     identity: RawPointer,
@@ -39,4 +44,53 @@ impl<'v> ValueIdentity<'v> {
             phantom: PhantomData,
         }
     }
+
+    /// Forget the lifetime, so the identity can be used as a key in a table
+    /// which outlives the heap borrow, like one filled by
+    /// [`Module::on_frozen`](crate::environment::Module::on_frozen).
+    ///
+    /// Once the value is garbage collected or frozen, its identity may be reused
+    /// by another value.
+    pub fn erase_lifetime(self) -> ValueIdentity<'static> {
+        ValueIdentity {
+            identity: self.identity,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// A set of values compared by identity, e.g. to track visited values during traversal.
+pub type ValueIdentitySet<'v> = SmallSet<ValueIdentity<'v>>;
+
+/// A map keyed by value identity.
+pub type ValueIdentityMap<'v, V> = SmallMap<ValueIdentity<'v>, V>;
+
+#[cfg(test)]
+mod tests {
+    use crate::values::FrozenHeap;
+    use crate::values::Heap;
+    use crate::values::ValueIdentityMap;
+    use crate::values::ValueIdentitySet;
+
+    #[test]
+    fn test_identity_set() {
+        let heap = Heap::new();
+        let a = heap.alloc(vec![1]);
+        let b = heap.alloc(vec![1]);
+        let mut visited = ValueIdentitySet::new();
+        assert!(visited.insert(a.identity()));
+        assert!(!visited.insert(a.identity()));
+        // Equal but not the same value.
+        assert!(visited.insert(b.identity()));
+        assert_eq!(2, visited.len());
+    }
+
+    #[test]
+    fn test_identity_map_frozen() {
+        let heap = FrozenHeap::new();
+        let a = heap.alloc("a");
+        let mut map = ValueIdentityMap::new();
+        map.insert(a.identity(), 1);
+        assert_eq!(Some(&1), map.get(&a.to_value().identity()));
+    }
 }
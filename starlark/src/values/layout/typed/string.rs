@@ -79,6 +79,18 @@ impl<'v> Borrow<str> for StringValue<'v> {
     }
 }
 
+impl AsRef<str> for FrozenStringValue {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'v> AsRef<str> for StringValue<'v> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
 impl<'v> Equivalent<FrozenStringValue> for StringValue<'v> {
     fn equivalent(&self, key: &FrozenStringValue) -> bool {
         *self == key.to_string_value()
@@ -209,6 +221,18 @@ impl<'v> PartialEq<FrozenStringValue> for StringValue<'v> {
     }
 }
 
+impl PartialEq<str> for FrozenStringValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'v> PartialEq<str> for StringValue<'v> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
 impl<'v> Hash for StringValue<'v> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.as_ref().hash(state)
@@ -248,6 +272,7 @@ impl Ord for FrozenStringValue {
 #[cfg(test)]
 mod tests {
     use crate::collections::Hashed;
+    use crate::collections::SmallMap;
     use crate::values::FrozenHeap;
     use crate::values::FrozenStringValue;
     use crate::values::FrozenValue;
@@ -273,4 +298,30 @@ mod tests {
         let fv: FrozenValue = heap.alloc_str("xyz").to_frozen_value();
         assert_eq!(expected, fv.get_hashed().unwrap().hash());
     }
+
+    #[test]
+    fn test_string_map_lookup_by_str() {
+        let heap = FrozenHeap::new();
+        let mut map: SmallMap<FrozenStringValue, u32> = SmallMap::new();
+        map.insert(heap.alloc_str_intern("abc"), 1);
+        map.insert(heap.alloc_str_intern("def"), 2);
+
+        assert_eq!(Some(&1), map.get("abc"));
+        assert_eq!(Some(&2), map.get_hashed(Hashed::new("def")));
+        assert_eq!(None, map.get("xyz"));
+
+        let heap = Heap::new();
+        let s = heap.alloc_str("def");
+        assert_eq!(Some(&2), map.get(&s));
+        assert_eq!(s, *"def");
+    }
+
+    #[test]
+    fn test_alloc_str_intern() {
+        let heap = FrozenHeap::new();
+        let a = heap.alloc_str_intern("some longer string");
+        let b = heap.alloc_str_intern("some longer string");
+        assert!(a.to_value().ptr_eq(b.to_value()));
+        assert_eq!(a, *"some longer string");
+    }
 }
@@ -67,6 +67,7 @@ use crate::sealed::Sealed;
 use crate::typing::Ty;
 use crate::values::bool::VALUE_FALSE_TRUE;
 use crate::values::demand::request_value_impl;
+use crate::values::dict::DictRef;
 use crate::values::dict::FrozenDictRef;
 use crate::values::enumeration::EnumType;
 use crate::values::enumeration::FrozenEnumValue;
@@ -77,6 +78,8 @@ use crate::values::int::PointerI32;
 use crate::values::iter::StarlarkIterator;
 use crate::values::layout::avalue::AValue;
 use crate::values::layout::avalue::AValueImpl;
+use crate::values::layout::heap::escape;
+use crate::values::layout::heap::escape::EscapeGuard;
 use crate::values::layout::heap::repr::AValueHeader;
 use crate::values::layout::heap::repr::AValueRepr;
 use crate::values::layout::pointer::FrozenPointer;
@@ -124,6 +127,8 @@ use crate::values::ValueIdentity;
 enum ValueValueError {
     #[error("Expected value of type `{0}` but got `{1}`")]
     WrongType(&'static str, String),
+    #[error("Expected a frozen value but got a mutable value of type `{0}`")]
+    NotFrozen(&'static str),
 }
 
 /// A Starlark value. The lifetime argument `'v` corresponds to the [`Heap`](crate::values::Heap) it is stored on.
@@ -327,6 +332,27 @@ impl<'v> Value<'v> {
         }
     }
 
+    /// Obtain the underlying [`FrozenValue`], or fail if the value is mutable.
+    ///
+    /// Host code which keeps values beyond the lifetime of the [`Heap`](crate::values::Heap)
+    /// must only keep frozen values (along with their
+    /// [`FrozenHeapRef`](crate::values::FrozenHeapRef)).
+    pub fn try_frozen(self) -> anyhow::Result<FrozenValue> {
+        self.unpack_frozen()
+            .ok_or_else(|| ValueValueError::NotFrozen(self.get_type()).into())
+    }
+
+    /// Record that host code keeps this value somewhere the heap does not know about,
+    /// until the returned guard is dropped.
+    ///
+    /// Does nothing unless escape detection is enabled with
+    /// [`set_escape_detection`](crate::values::set_escape_detection) in a debug build.
+    /// Then if the value is mutable, and its heap drops its values while the guard is alive,
+    /// this panics with the backtrace of this call.
+    pub fn debug_escape_guard(self) -> EscapeGuard {
+        escape::record(self)
+    }
+
     #[inline]
     unsafe fn unpack_frozen_unchecked(self) -> FrozenValue {
         debug_assert!(!self.0.is_unfrozen());
@@ -564,6 +590,40 @@ impl<'v> Value<'v> {
         self.get_ref().length()
     }
 
+    /// Convert to a dictionary for `f(**x)`, returning dictionaries unchanged.
+    pub fn to_kwargs(self, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        if DictRef::from_value(self).is_some() {
+            Ok(self)
+        } else {
+            self.get_ref().to_kwargs(heap)
+        }
+    }
+
+    /// Stable hash of the value tree, for example to use as a memoization or remote cache key.
+    ///
+    /// Unlike [`get_hash`](Value::get_hash), this hash is defined for lists and dictionaries,
+    /// and does not change between runs or versions of this crate. It is supported for
+    /// `None`, `bool`, `int`, `float`, `str`, `list`, `tuple`, `dict`, `struct`, records
+    /// and enum values, and is computed as 128-bit FNV-1a over an encoding
+    /// where each value is a one byte tag followed by its contents:
+    ///
+    /// * `N` for `None`; `B` and a byte `0` or `1` for `bool`
+    /// * `I` and the decimal representation for `int` (strings are a little-endian `u64`
+    ///   byte length followed by UTF-8 bytes)
+    /// * `F` and the little-endian IEEE bits for non-integral `float`, with all NaNs equal;
+    ///   integral floats are hashed like the equal `int`, so `1` and `1.0` hash the same
+    /// * `S` and the string for `str`
+    /// * `L` or `T`, the `u64` length, then the elements for `list` and `tuple`
+    /// * `D` or `s`, the `u64` length, then the sorted little-endian FNV-1a hashes of each
+    ///   key and value pair for `dict` and `struct`, so field order does not matter
+    /// * `R`, the `u64` field count, then each field name and value for records
+    /// * `E`, the little-endian `i32` index, then the value for enum values
+    ///
+    /// Other types and values containing cycles are an error.
+    pub fn content_hash(self) -> crate::Result<u128> {
+        crate::values::content_hash::content_hash(self)
+    }
+
     /// `other in x`.
     pub fn is_in(self, other: Value<'v>) -> crate::Result<bool> {
         self.get_ref().is_in(other)
@@ -646,6 +706,9 @@ impl<'v> Value<'v> {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
+        // Calls made by native code (e.g. `partial` or `eval_function`) come through here,
+        // so the limits are enforced for them too, not only for calls in bytecode.
+        eval.check_call_args(&args.0)?;
         eval.with_call_stack(self, location, |eval| {
             self.get_ref_full().invoke(args, eval)
         })
@@ -774,6 +837,31 @@ impl<'v> Value<'v> {
         serde_json::to_value(self).map_err(|e| anyhow::anyhow!(e))
     }
 
+    /// Convert the value to JSON with object keys sorted,
+    /// so the output does not depend on dict insertion order.
+    pub fn to_json_sorted(self) -> anyhow::Result<String> {
+        fn sort_keys(x: serde_json::Value) -> serde_json::Value {
+            match x {
+                serde_json::Value::Object(map) => {
+                    let mut entries: Vec<_> = map.into_iter().collect();
+                    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                    serde_json::Value::Object(
+                        entries
+                            .into_iter()
+                            .map(|(k, v)| (k, sort_keys(v)))
+                            .collect(),
+                    )
+                }
+                serde_json::Value::Array(xs) => {
+                    serde_json::Value::Array(xs.into_iter().map(sort_keys).collect())
+                }
+                x => x,
+            }
+        }
+
+        serde_json::to_string(&sort_keys(self.to_json_value()?)).map_err(|e| anyhow::anyhow!(e))
+    }
+
     /// Forwards to [`StarlarkValue::set_attr`].
     pub fn set_attr(self, attribute: &str, alloc_value: Value<'v>) -> crate::Result<()> {
         self.get_ref().set_attr(attribute, alloc_value)
@@ -790,6 +878,23 @@ impl<'v> Value<'v> {
     }
 
     /// Produce an iterable from a value.
+    ///
+    /// The value cannot be mutated while the returned iterator is alive,
+    /// attempts to mutate it fail with an error, same as in Starlark code.
+    ///
+    /// ```
+    /// use starlark::values::Heap;
+    ///
+    /// let heap = Heap::new();
+    /// let list = heap.alloc(vec![1, 2, 3]);
+    /// let mut sum = 0;
+    /// for x in list.iterate(&heap)? {
+    ///     sum += x.unpack_i32().unwrap();
+    ///     assert!(list.set_at(heap.alloc(0), x).is_err());
+    /// }
+    /// assert_eq!(6, sum);
+    /// # starlark::Result::Ok(())
+    /// ```
     #[inline]
     pub fn iterate(self, heap: &'v Heap) -> crate::Result<StarlarkIterator<'v>> {
         let iter = self.get_ref().iterate(self, heap)?;
@@ -1027,6 +1132,15 @@ impl FrozenValue {
         self.0.raw()
     }
 
+    /// Returns an identity for this [`FrozenValue`], derived from its pointer.
+    ///
+    /// Frozen values are never moved by the garbage collector,
+    /// so unlike [`Value::identity`] this identity is stable for as long as the heap is alive.
+    #[inline]
+    pub fn identity(self) -> ValueIdentity<'static> {
+        self.to_value().identity()
+    }
+
     /// Is a value a Starlark `None`.
     #[inline]
     pub fn is_none(self) -> bool {
@@ -1124,6 +1238,24 @@ impl FrozenValue {
         self.downcast_ref::<T>().map(|value| FrozenRef { value })
     }
 
+    /// Parameters of a frozen `def` or `lambda`, with frozen default values.
+    ///
+    /// Returns `None` if the value is not a Starlark-defined function.
+    pub fn def_parameters(self) -> Option<FrozenRef<'static, ParametersSpec<FrozenValue>>> {
+        Some(
+            self.downcast_frozen_ref::<FrozenDef>()?
+                .map(|def| &def.parameters),
+        )
+    }
+
+    /// Docstring of a frozen `def`, if the value is a function which has one.
+    pub fn def_docstring(self) -> Option<FrozenRef<'static, str>> {
+        self.downcast_frozen_ref::<FrozenDef>()?
+            .as_ref()
+            .def_info
+            .try_map_option(|info| info.docstring.as_deref())
+    }
+
     /// Downcast to string.
     #[inline]
     pub fn downcast_frozen_str(self) -> Option<FrozenRef<'static, str>> {
@@ -402,6 +402,11 @@ impl<'v> AValueDyn<'v> {
         (self.vtable.starlark_value.length)(self.value)
     }
 
+    #[inline]
+    pub(crate) fn to_kwargs(self, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        (self.vtable.starlark_value.to_kwargs)(self.value, heap)
+    }
+
     #[inline]
     pub(crate) fn iterate(self, me: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
         (self.vtable.starlark_value.iterate)(self.value, me, heap)
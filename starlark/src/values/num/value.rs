@@ -36,6 +36,12 @@ use crate::values::UnpackValue;
 enum NumError {
     #[error("float division by zero: {0} / {1}")]
     DivisionByZero(Num, Num),
+    #[error("zero cannot be raised to a negative power: pow({0}, {1})")]
+    PowZeroNegative(Num, Num),
+    #[error("pow({0}, {1}) result is not a real number")]
+    PowNotReal(Num, Num),
+    #[error("pow() with modulus requires all arguments to be integers")]
+    PowModulusNotInt,
 }
 
 /// [`NumRef`] represents a numerical value that can be unpacked from a [`Value`].
@@ -155,6 +161,29 @@ impl<'v> NumRef<'v> {
             StarlarkFloat::percent_impl(self.as_float(), other.as_float()).map(Num::Float)
         }
     }
+
+    /// `pow(self, exponent, modulus)` with Python semantics:
+    /// integer result for integer arguments and non-negative exponent, float result otherwise.
+    pub(crate) fn pow(self, exponent: NumRef, modulus: Option<NumRef>) -> anyhow::Result<Num> {
+        match (self, exponent, modulus) {
+            (NumRef::Int(a), NumRef::Int(b), Some(NumRef::Int(m))) => {
+                a.pow(b, Some(m)).map(Num::Int)
+            }
+            (_, _, Some(_)) => Err(NumError::PowModulusNotInt.into()),
+            (NumRef::Int(a), NumRef::Int(b), None) if b >= 0 => a.pow(b, None).map(Num::Int),
+            (a, b, None) => {
+                let (x, y) = (a.as_float(), b.as_float());
+                if x == 0.0 && y < 0.0 {
+                    return Err(NumError::PowZeroNegative(a.to_owned(), b.to_owned()).into());
+                }
+                let r = x.powf(y);
+                if r.is_nan() && !x.is_nan() && !y.is_nan() {
+                    return Err(NumError::PowNotReal(a.to_owned(), b.to_owned()).into());
+                }
+                Ok(Num::Float(r))
+            }
+        }
+    }
 }
 
 impl<'v> From<f64> for NumRef<'v> {
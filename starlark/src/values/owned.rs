@@ -25,12 +25,18 @@ use dupe::Dupe;
 use dupe::Dupe_;
 
 use crate::cast::transmute;
+use crate::eval::ParametersSpec;
 use crate::typing::Ty;
+use crate::values::dict::FrozenDictRef;
+use crate::values::list::FrozenListRef;
 use crate::values::none::NoneType;
+use crate::values::structs::FrozenStructRef;
+use crate::values::tuple::FrozenTupleRef;
 use crate::values::type_repr::StarlarkTypeRepr;
 use crate::values::AllocFrozenValue;
 use crate::values::FrozenHeap;
 use crate::values::FrozenHeapRef;
+use crate::values::FrozenRef;
 use crate::values::FrozenValue;
 use crate::values::FrozenValueTyped;
 use crate::values::OwnedFrozenRef;
@@ -138,6 +144,25 @@ impl OwnedFrozenValue {
         }
     }
 
+    /// Obtain a reference to `T` if `self` references `<T>`.
+    ///
+    /// The returned reference borrows `self`, so the owning heap is kept alive.
+    pub fn downcast_ref<T: StarlarkValue<'static>>(&self) -> Option<&T> {
+        self.value.downcast_frozen_ref::<T>().map(|r| r.as_ref())
+    }
+
+    /// `downcast_ref`, but return an error for human instead of `None`.
+    pub fn downcast_ref_anyhow<T: StarlarkValue<'static>>(&self) -> anyhow::Result<&T> {
+        match self.downcast_ref() {
+            Some(v) => Ok(v),
+            None => Err(OwnedError::WrongType(
+                T::TYPE,
+                self.value.to_value().to_string_for_type_error(),
+            )
+            .into()),
+        }
+    }
+
     /// `downcast`, but return an error for human instead of original value.
     pub fn downcast_anyhow<T: StarlarkValue<'static>>(
         self,
@@ -152,6 +177,77 @@ impl OwnedFrozenValue {
         }
     }
 
+    /// Parameters of a frozen `def` or `lambda`, with frozen default values,
+    /// e.g. to check a callback accepts the expected arguments before calling it.
+    ///
+    /// Returns `None` if the value is not a Starlark-defined function.
+    pub fn def_parameters(&self) -> Option<&ParametersSpec<FrozenValue>> {
+        self.value.def_parameters().map(FrozenRef::as_ref)
+    }
+
+    /// Docstring of a frozen `def`, if the value is a function which has one.
+    pub fn def_docstring(&self) -> Option<&str> {
+        self.value.def_docstring().map(FrozenRef::as_ref)
+    }
+
+    /// Value reachable from this value, kept alive by the same heap.
+    fn child(&self, value: FrozenValue) -> Self {
+        Self {
+            owner: self.owner.dupe(),
+            value,
+        }
+    }
+
+    /// Element at given index of a list or a tuple.
+    ///
+    /// Returns `None` if the value is not a list or a tuple, or the index is out of range.
+    pub fn index(&self, index: usize) -> Option<OwnedFrozenValue> {
+        let value = if let Some(list) = FrozenListRef::from_frozen_value(self.value) {
+            *list.get(index)?
+        } else {
+            *FrozenTupleRef::from_frozen_value(self.value)?
+                .content()
+                .get(index)?
+        };
+        Some(self.child(value))
+    }
+
+    /// Value of a dict by a string key.
+    ///
+    /// Returns `None` if the value is not a dict, or the key is not present.
+    pub fn dict_get_str(&self, key: &str) -> Option<OwnedFrozenValue> {
+        let value = FrozenDictRef::from_frozen_value(self.value)?.get_str(key)?;
+        Some(self.child(value))
+    }
+
+    /// Field of a `struct`.
+    ///
+    /// Returns `None` if the value is not a struct, or it has no such field.
+    pub fn struct_field(&self, name: &str) -> Option<OwnedFrozenValue> {
+        let (_, value) = FrozenStructRef::from_value(self.value)?
+            .iter()
+            .find(|(k, _)| k.as_str() == name)?;
+        Some(self.child(value))
+    }
+
+    /// Elements of a list or a tuple, or keys of a dict,
+    /// in the order a `for` loop would iterate over them.
+    ///
+    /// Returns `None` if the value is of another type.
+    pub fn iter(&self) -> Option<impl Iterator<Item = OwnedFrozenValue> + '_> {
+        let values: Box<dyn Iterator<Item = FrozenValue>> =
+            if let Some(list) = FrozenListRef::from_frozen_value(self.value) {
+                Box::new(list.iter().copied())
+            } else if let Some(tuple) = FrozenTupleRef::from_frozen_value(self.value) {
+                Box::new(tuple.iter())
+            } else if let Some(dict) = FrozenDictRef::from_frozen_value(self.value) {
+                Box::new(dict.iter().map(|(k, _)| k))
+            } else {
+                return None;
+            };
+        Some(values.map(|v| self.child(v)))
+    }
+
     /// Obtain the [`Value`] stored inside.
     pub fn value<'v>(&'v self) -> Value<'v> {
         Value::new_frozen(self.value)
@@ -218,6 +314,32 @@ pub struct OwnedFrozenValueTyped<T: StarlarkValue<'static>> {
     value: FrozenValueTyped<'static, T>,
 }
 
+impl<T: StarlarkValue<'static>> Display for OwnedFrozenValueTyped<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.value, f)
+    }
+}
+
+impl<T: StarlarkValue<'static>> StarlarkTypeRepr for OwnedFrozenValueTyped<T> {
+    type Canonical = <T as StarlarkTypeRepr>::Canonical;
+
+    fn starlark_type_repr() -> Ty {
+        T::starlark_type_repr()
+    }
+}
+
+impl<T: StarlarkValue<'static>> AllocFrozenValue for OwnedFrozenValueTyped<T> {
+    fn alloc_frozen_value(self, heap: &FrozenHeap) -> FrozenValue {
+        self.owned_frozen_value(heap)
+    }
+}
+
+impl<T: StarlarkValue<'static>> From<OwnedFrozenValueTyped<T>> for OwnedFrozenValue {
+    fn from(value: OwnedFrozenValueTyped<T>) -> Self {
+        value.into_owned_frozen_value()
+    }
+}
+
 impl<T: StarlarkValue<'static>> Deref for OwnedFrozenValueTyped<T> {
     type Target = T;
 
@@ -244,6 +366,18 @@ impl<T: StarlarkValue<'static>> OwnedFrozenValueTyped<T> {
         Self { owner, value }
     }
 
+    /// Create an [`OwnedFrozenValueTyped`] in a new heap.
+    pub fn alloc(x: T) -> Self
+    where
+        T: Send + Sync,
+    {
+        let heap = FrozenHeap::new();
+        let val = heap.alloc_simple(x);
+        // Safe because we just created the value on the heap,
+        // and `alloc_simple` always allocates a `T`.
+        unsafe { Self::new(heap.into_ref(), FrozenValueTyped::new_unchecked(val)) }
+    }
+
     /// Erase the type.
     ///
     /// This operation is unsafe because returned value is not bound by the heap lifetime.
@@ -267,6 +401,14 @@ impl<T: StarlarkValue<'static>> OwnedFrozenValueTyped<T> {
         }
     }
 
+    /// Erase the type.
+    pub fn into_owned_frozen_value(self) -> OwnedFrozenValue {
+        OwnedFrozenValue {
+            owner: self.owner,
+            value: self.value.to_frozen_value(),
+        }
+    }
+
     /// Convert to an owned ref.
     pub fn into_owned_frozen_ref(self) -> OwnedFrozenRef<T> {
         // SAFETY: Heap matches the value
@@ -359,3 +501,78 @@ impl<T: StarlarkValue<'static>> OwnedFrozenValueTyped<T> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Module;
+    use crate::values::dict::AllocDict;
+    use crate::values::float::StarlarkFloat;
+    use crate::values::none::NoneType;
+    use crate::values::string::StarlarkStr;
+    use crate::values::structs::AllocStruct;
+    use crate::values::OwnedFrozenValue;
+    use crate::values::OwnedFrozenValueTyped;
+
+    #[test]
+    fn test_downcast() {
+        let value = OwnedFrozenValue::alloc(NoneType);
+        assert!(value.downcast_ref::<NoneType>().is_some());
+        assert!(value.downcast_ref_anyhow::<NoneType>().is_ok());
+        let typed = value.downcast_anyhow::<NoneType>().unwrap();
+        assert_eq!("None", typed.to_string());
+        let value: OwnedFrozenValue = typed.into();
+        assert!(value.downcast_anyhow::<StarlarkStr>().is_err());
+    }
+
+    #[test]
+    fn test_downcast_wrong_type() {
+        let value = OwnedFrozenValue::alloc("test");
+        assert!(value.downcast_ref::<NoneType>().is_none());
+        let err = value.downcast_ref_anyhow::<NoneType>().unwrap_err();
+        assert_eq!(
+            "Expected value of type `NoneType` but got `string (repr: \"test\")`",
+            err.to_string()
+        );
+        assert!(value.downcast::<NoneType>().is_err());
+    }
+
+    #[test]
+    fn test_structural_access() {
+        let module = Module::new();
+        module.set(
+            "x",
+            module.heap().alloc(AllocStruct([(
+                "deps",
+                module.heap().alloc(AllocDict([("a", vec![1, 2])])),
+            )])),
+        );
+        let module = module.freeze().unwrap();
+        let x = module.get("x").unwrap();
+        let deps = x.struct_field("deps").unwrap();
+        assert!(x.struct_field("srcs").is_none());
+        assert_eq!(
+            vec!["a"],
+            deps.iter()
+                .unwrap()
+                .map(|k| k.unpack_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        );
+        let a = deps.dict_get_str("a").unwrap();
+        assert!(deps.dict_get_str("b").is_none());
+        assert_eq!(Some(2), a.index(1).unwrap().unpack_i32());
+        assert!(a.index(2).is_none());
+        assert_eq!(2, a.iter().unwrap().count());
+        assert!(a.struct_field("deps").is_none());
+        // Owner is kept alive after the module is dropped.
+        drop(module);
+        assert_eq!(Some(1), a.index(0).unwrap().unpack_i32());
+    }
+
+    #[test]
+    fn test_typed_alloc() {
+        let typed = OwnedFrozenValueTyped::alloc(StarlarkFloat(1.5));
+        assert_eq!(1.5, typed.as_ref().0);
+        let value = typed.into_owned_frozen_value();
+        assert_eq!("1.5", value.value().to_string());
+    }
+}
@@ -0,0 +1,307 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use starlark_map::small_map::SmallMap;
+use starlark_map::Hashed;
+use starlark_syntax::slice_vec_ext::SliceExt;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::values::dict::Dict;
+use crate::values::dict::DictMut;
+use crate::values::dict::FrozenDictRef;
+use crate::values::layout::pointer::RawPointer;
+use crate::values::list::value::ListData;
+use crate::values::list::FrozenListRef;
+use crate::values::record::instance::FrozenRecord;
+use crate::values::record::instance::Record;
+use crate::values::structs::value::Struct;
+use crate::values::structs::FrozenStructRef;
+use crate::values::tuple::FrozenTupleRef;
+use crate::values::types::set::thaw_frozen_set;
+use crate::values::FrozenStringValue;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StringValue;
+use crate::values::StringValueLike;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+/// Opposite of [`Freeze`](crate::values::Freeze):
+/// copy frozen data into a heap as fresh mutable values.
+///
+/// Can be implemented with `#[derive(Thaw)]`:
+///
+/// ```
+/// use starlark::values::FrozenValue;
+/// use starlark::values::Heap;
+/// use starlark::values::Thaw;
+/// use starlark::values::Thawer;
+///
+/// #[derive(Thaw)]
+/// struct MyType<V> {
+///     value: V,
+///     // This field does not implement `Thaw`, it is cloned.
+///     #[thaw(identity)]
+///     name: String,
+/// }
+///
+/// let frozen = MyType {
+///     value: FrozenValue::new_none(),
+///     name: "x".to_owned(),
+/// };
+/// let heap = Heap::new();
+/// let thawed: MyType<_> = frozen.thaw(&Thawer::new(&heap)).unwrap();
+/// assert!(thawed.value.is_none());
+/// ```
+pub trait Thaw<'v> {
+    /// When value is thawed, it is thawed into this type.
+    type Thawed;
+
+    /// Copy the value into the heap of the thawer.
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Self::Thawed>;
+}
+
+/// Copies a frozen value of a custom type with a [`Thawer`].
+///
+/// The thawer copies lists, dicts, sets, and tuples, structs and records containing them.
+/// Values of other types are shared with the frozen heap, which is only right for
+/// immutable values, so types holding values which should become mutable again
+/// provide this hook from [`StarlarkValue::provide`](crate::values::StarlarkValue::provide)
+/// of their frozen type, with [`Demand::provide_value`](crate::values::Demand::provide_value).
+/// The hook is called with the frozen value, and usually allocates a copy
+/// with the values it contains passed to [`Thawer::thaw`].
+#[derive(ProvidesStaticType, Clone, Copy)]
+pub struct ThawHook(pub for<'v> fn(FrozenValue, &Thawer<'v>) -> anyhow::Result<Value<'v>>);
+
+/// Copies frozen values into a heap.
+///
+/// Values reachable from several places (including cycles)
+/// are copied once per thawer, so the shape of the value graph is preserved.
+///
+/// Lists, dicts and sets are copied into new mutable values.
+/// Tuples, structs and records are copied only if they contain mutable values.
+/// Values of other types are copied with their [`ThawHook`] if they provide one,
+/// otherwise they are shared with the frozen heap.
+pub struct Thawer<'v> {
+    heap: &'v Heap,
+    thawed: RefCell<HashMap<RawPointer, Value<'v>>>,
+    /// Lists and dicts allocated empty, to be filled once nothing refers to them
+    /// from the stack, so cycles through tuples and other immutable values
+    /// always find the copy of the mutable value which breaks the cycle.
+    unfilled: RefCell<Vec<(FrozenValue, Value<'v>)>>,
+}
+
+impl<'v> Thawer<'v> {
+    /// Create a thawer copying values into given heap.
+    pub fn new(heap: &'v Heap) -> Thawer<'v> {
+        Thawer {
+            heap,
+            thawed: RefCell::new(HashMap::new()),
+            unfilled: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Heap where the values are thawed to.
+    pub fn heap(&self) -> &'v Heap {
+        self.heap
+    }
+
+    /// Thaw a nested value.
+    pub fn thaw(&self, value: FrozenValue) -> anyhow::Result<Value<'v>> {
+        let thawed = self.thaw_shallow(value)?;
+        loop {
+            let next = self.unfilled.borrow_mut().pop();
+            match next {
+                Some((frozen, thawed)) => self.fill(frozen, thawed)?,
+                None => return Ok(thawed),
+            }
+        }
+    }
+
+    /// Thaw a value, leaving the content of lists and dicts to [`fill`](Thawer::fill).
+    fn thaw_shallow(&self, value: FrozenValue) -> anyhow::Result<Value<'v>> {
+        if let Some(thawed) = self.thawed.borrow().get(&value.ptr_value()) {
+            return Ok(*thawed);
+        }
+
+        let thawed = if FrozenListRef::from_frozen_value(value).is_some() {
+            self.unfilled(value, self.heap.alloc_list(&[]))
+        } else if FrozenDictRef::from_frozen_value(value).is_some() {
+            self.unfilled(value, self.heap.alloc(Dict::default()))
+        } else if let Some(set) = thaw_frozen_set(value, self.heap) {
+            set
+        } else if let Some(tuple) = FrozenTupleRef::from_frozen_value(value) {
+            let content = tuple.content().try_map(|x| self.thaw_shallow(*x))?;
+            if Self::same(tuple.content(), &content) {
+                value.to_value()
+            } else {
+                self.heap.alloc_tuple(&content)
+            }
+        } else if let Some(s) = FrozenStructRef::from_value(value) {
+            let fields: SmallMap<StringValue<'v>, Value<'v>> = s
+                .iter()
+                .map(|(k, v)| Ok((k.to_string_value(), self.thaw_shallow(v)?)))
+                .collect::<anyhow::Result<_>>()?;
+            let frozen_fields: Vec<FrozenValue> = s.iter().map(|(_, v)| v).collect();
+            let thawed_fields: Vec<Value<'v>> = fields.values().copied().collect();
+            if Self::same(&frozen_fields, &thawed_fields) {
+                value.to_value()
+            } else {
+                self.heap.alloc(Struct::new(fields))
+            }
+        } else if let Some(record) = value.downcast_ref::<FrozenRecord>() {
+            let values = record.values.try_map(|x| self.thaw_shallow(*x))?;
+            if Self::same(&record.values, &values) {
+                value.to_value()
+            } else {
+                self.heap.alloc(Record {
+                    typ: record.typ.to_value(),
+                    values: values.into_boxed_slice(),
+                })
+            }
+        } else if let Some(ThawHook(hook)) = value.to_value().request_value::<ThawHook>() {
+            hook(value, self)?
+        } else {
+            value.to_value()
+        };
+        self.thawed.borrow_mut().insert(value.ptr_value(), thawed);
+        Ok(thawed)
+    }
+
+    /// Register the copy of a list or dict before thawing its content,
+    /// the content may refer back to it.
+    fn unfilled(&self, value: FrozenValue, thawed: Value<'v>) -> Value<'v> {
+        self.thawed.borrow_mut().insert(value.ptr_value(), thawed);
+        self.unfilled.borrow_mut().push((value, thawed));
+        thawed
+    }
+
+    /// Thawing did not change any value.
+    fn same(frozen: &[FrozenValue], thawed: &[Value<'v>]) -> bool {
+        frozen
+            .iter()
+            .zip(thawed)
+            .all(|(f, t)| f.to_value().ptr_eq(*t))
+    }
+
+    fn fill(&self, value: FrozenValue, thawed: Value<'v>) -> anyhow::Result<()> {
+        if let Some(list) = FrozenListRef::from_frozen_value(value) {
+            let content = list.try_map(|x| self.thaw_shallow(*x))?;
+            ListData::from_value_mut(thawed)?.extend(content, self.heap);
+        } else if let Some(dict) = FrozenDictRef::from_frozen_value(value) {
+            for (k, v) in dict.iter() {
+                // Keys are hashable, so they are immutable, and do not need thawing.
+                let k: Hashed<Value<'v>> =
+                    k.to_value().get_hashed().map_err(|e| e.into_anyhow())?;
+                let v = self.thaw_shallow(v)?;
+                DictMut::from_value(thawed)?.aref.insert_hashed(k, v);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'v> Thaw<'v> for FrozenValue {
+    type Thawed = Value<'v>;
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Value<'v>> {
+        thawer.thaw(*self)
+    }
+}
+
+impl<'v> Thaw<'v> for FrozenStringValue {
+    type Thawed = StringValue<'v>;
+
+    fn thaw(&self, _thawer: &Thawer<'v>) -> anyhow::Result<StringValue<'v>> {
+        // Strings are immutable.
+        Ok(self.to_string_value())
+    }
+}
+
+macro_rules! thaw_identity {
+    ($($t:ty),*) => {
+        $(
+            impl<'v> Thaw<'v> for $t {
+                type Thawed = $t;
+
+                fn thaw(&self, _thawer: &Thawer<'v>) -> anyhow::Result<$t> {
+                    Ok(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+thaw_identity!(String, i32, u32, i64, u64, usize, bool, ());
+
+impl<'v, T: Thaw<'v>> Thaw<'v> for Vec<T> {
+    type Thawed = Vec<T::Thawed>;
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Vec<T::Thawed>> {
+        self.try_map(|v| v.thaw(thawer))
+    }
+}
+
+impl<'v, T: Thaw<'v>> Thaw<'v> for Box<T> {
+    type Thawed = Box<T::Thawed>;
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Box<T::Thawed>> {
+        Ok(Box::new((**self).thaw(thawer)?))
+    }
+}
+
+impl<'v, T: Thaw<'v>> Thaw<'v> for Option<T> {
+    type Thawed = Option<T::Thawed>;
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Option<T::Thawed>> {
+        self.as_ref().map(|v| v.thaw(thawer)).transpose()
+    }
+}
+
+impl<'v, K: Thaw<'v>> Thaw<'v> for Hashed<K> {
+    type Thawed = Hashed<K::Thawed>;
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Hashed<K::Thawed>> {
+        // Hashable values are immutable, `thaw` does not change hash.
+        Ok(Hashed::new_unchecked(self.hash(), self.key().thaw(thawer)?))
+    }
+}
+
+impl<'v, K: Thaw<'v>, V: Thaw<'v>> Thaw<'v> for SmallMap<K, V> {
+    type Thawed = SmallMap<K::Thawed, V::Thawed>;
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Self::Thawed> {
+        let mut new = SmallMap::with_capacity(self.len());
+        for (key, value) in self.iter_hashed() {
+            let key = Hashed::new_unchecked(key.hash(), key.key().thaw(thawer)?);
+            new.insert_hashed_unique_unchecked(key, value.thaw(thawer)?);
+        }
+        Ok(new)
+    }
+}
+
+impl<'v, A: Thaw<'v>, B: Thaw<'v>> Thaw<'v> for (A, B) {
+    type Thawed = (A::Thawed, B::Thawed);
+
+    fn thaw(&self, thawer: &Thawer<'v>) -> anyhow::Result<Self::Thawed> {
+        Ok((self.0.thaw(thawer)?, self.1.thaw(thawer)?))
+    }
+}
@@ -563,6 +563,16 @@ pub trait StarlarkValue<'v>:
         ValueError::unsupported(self, "len()")
     }
 
+    /// Convert the value to a dictionary of keyword arguments,
+    /// so it can be passed as `f(**x)`.
+    ///
+    /// Only called for values which are not dictionaries,
+    /// and only when [`Dialect::enable_to_kwargs`](crate::syntax::Dialect::enable_to_kwargs)
+    /// is set. The returned value must be a dictionary with string keys.
+    fn to_kwargs(&self, _heap: &'v Heap) -> crate::Result<Value<'v>> {
+        ValueError::unsupported(self, "**")
+    }
+
     /// Attribute type, for the typechecker.
     ///
     /// If [`get_attr`](StarlarkValue::get_attr) is implemented,
@@ -31,11 +31,14 @@ pub(crate) mod inline_int;
 pub mod int;
 pub(crate) mod int_or_big;
 pub(crate) mod known_methods;
+pub mod label;
 pub mod list;
 pub mod list_or_tuple;
 pub mod none;
 pub mod range;
 pub mod record;
+pub mod sealed;
+pub(crate) mod set;
 pub mod starlark_value_as_type;
 pub mod string;
 pub mod structs;
@@ -26,6 +26,7 @@ pub(crate) mod unpack;
 pub(crate) mod value;
 
 pub use crate::values::dict::alloc::AllocDict;
+pub use crate::values::dict::alloc::AllocDictSortedUnique;
 pub use crate::values::dict::refs::DictMut;
 pub use crate::values::dict::refs::DictRef;
 pub use crate::values::dict::refs::FrozenDictRef;
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::cmp::Ordering;
 use std::iter;
 
 use starlark_map::small_map::SmallMap;
@@ -59,6 +60,111 @@ impl AllocDict<iter::Empty<(FrozenValue, FrozenValue)>> {
     pub const EMPTY: AllocDict<iter::Empty<(FrozenValue, FrozenValue)>> = AllocDict(iter::empty());
 }
 
+impl<D> AllocDict<D> {
+    /// Utility to allocate a dict from entries with unique keys in ascending order.
+    ///
+    /// Keys are only compared with the previous key, so this is cheaper than [`AllocDict`]
+    /// when building large dicts. The dict preserves the order of `entries`.
+    ///
+    /// Allocation fails if a key is not hashable, or if keys are not comparable
+    /// or not in strictly ascending order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use starlark::values::dict::AllocDict;
+    /// use starlark::values::FrozenHeap;
+    ///
+    /// let heap = FrozenHeap::new();
+    /// let entries = (0..100_000).map(|i| (i, i * 2));
+    /// let dict = AllocDict::from_sorted_unique(entries)
+    ///     .alloc_frozen(&heap)
+    ///     .unwrap();
+    /// # assert_eq!(dict.to_value().length().unwrap(), 100_000);
+    /// ```
+    pub fn from_sorted_unique<K, V>(entries: D) -> AllocDictSortedUnique<D>
+    where
+        D: IntoIterator<Item = (K, V)>,
+    {
+        AllocDictSortedUnique(entries)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum AllocDictError {
+    #[error("Dict keys are not sorted and unique: `{0}` is followed by `{1}`")]
+    NotSortedUnique(String, String),
+}
+
+/// Dict entries with unique sorted keys, created with [`AllocDict::from_sorted_unique`].
+pub struct AllocDictSortedUnique<D>(D);
+
+impl<D, K, V> AllocDictSortedUnique<D>
+where
+    D: IntoIterator<Item = (K, V)>,
+{
+    /// Allocate the dict on a heap.
+    pub fn alloc<'v>(self, heap: &'v Heap) -> crate::Result<Value<'v>>
+    where
+        K: AllocValue<'v>,
+        V: AllocValue<'v>,
+    {
+        let map = sorted_unique_map(
+            self.0
+                .into_iter()
+                .map(|(k, v)| (k.alloc_value(heap), v.alloc_value(heap))),
+        )?;
+        Ok(heap.alloc(Dict::new(map)))
+    }
+
+    /// Allocate the dict on a frozen heap.
+    pub fn alloc_frozen(self, heap: &FrozenHeap) -> crate::Result<FrozenValue>
+    where
+        K: AllocFrozenValue,
+        V: AllocFrozenValue,
+    {
+        let map = sorted_unique_map(
+            self.0
+                .into_iter()
+                .map(|(k, v)| (k.alloc_frozen_value(heap), v.alloc_frozen_value(heap))),
+        )?;
+        Ok(heap.alloc(FrozenDictData { content: map }))
+    }
+}
+
+impl<D, K, V> StarlarkTypeRepr for AllocDictSortedUnique<D>
+where
+    D: IntoIterator<Item = (K, V)>,
+    K: StarlarkTypeRepr,
+    V: StarlarkTypeRepr,
+{
+    type Canonical = DictType<K::Canonical, V::Canonical>;
+
+    fn starlark_type_repr() -> Ty {
+        DictType::<K, V>::starlark_type_repr()
+    }
+}
+
+fn sorted_unique_map<'v, V: ValueLike<'v>>(
+    entries: impl Iterator<Item = (V, V)>,
+) -> crate::Result<SmallMap<V, V>> {
+    let mut map = SmallMap::with_capacity(entries.size_hint().0);
+    let mut prev: Option<V> = None;
+    for (k, v) in entries {
+        if let Some(prev) = prev {
+            if prev.to_value().compare(k.to_value())? != Ordering::Less {
+                return Err(crate::Error::new_other(AllocDictError::NotSortedUnique(
+                    prev.to_value().to_repr(),
+                    k.to_value().to_repr(),
+                )));
+            }
+        }
+        prev = Some(k);
+        map.insert_hashed_unique_unchecked(k.get_hashed()?, v);
+    }
+    Ok(map)
+}
+
 impl<D, K, V> StarlarkTypeRepr for AllocDict<D>
 where
     D: IntoIterator<Item = (K, V)>,
@@ -109,3 +215,58 @@ where
         heap.alloc(FrozenDictData { content: map })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::values::dict::AllocDict;
+    use crate::values::FrozenHeap;
+    use crate::values::Heap;
+    use crate::values::OwnedFrozenValue;
+
+    #[test]
+    fn test_from_sorted_unique() {
+        let heap = Heap::new();
+        let entries = [("a", 1), ("b", 2), ("c", 3)];
+        let dict = AllocDict::from_sorted_unique(entries).alloc(&heap).unwrap();
+        assert!(dict.equals(heap.alloc(AllocDict(entries))).unwrap());
+        assert_eq!(
+            Some(2),
+            dict.at(heap.alloc("b"), &heap).unwrap().unpack_i32()
+        );
+
+        let frozen_heap = FrozenHeap::new();
+        let frozen = AllocDict::from_sorted_unique(entries)
+            .alloc_frozen(&frozen_heap)
+            .unwrap();
+        let frozen = unsafe { OwnedFrozenValue::new(frozen_heap.into_ref(), frozen) };
+        assert_eq!(r#"{"a": 1, "b": 2, "c": 3}"#, frozen.value().to_repr());
+    }
+
+    #[test]
+    fn test_from_sorted_unique_duplicate() {
+        let heap = Heap::new();
+        let err = AllocDict::from_sorted_unique([("a", 1), ("a", 2)])
+            .alloc(&heap)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Dict keys are not sorted and unique: `\"a\"` is followed by `\"a\"`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_from_sorted_unique_mixed_types() {
+        let heap = Heap::new();
+        let entries = [(heap.alloc(1), heap.alloc(1)), (heap.alloc("a"), heap.alloc(2))];
+        assert!(AllocDict::from_sorted_unique(entries).alloc(&heap).is_err());
+    }
+
+    #[test]
+    fn test_from_sorted_unique_unhashable() {
+        let heap = Heap::new();
+        let entries = [(heap.alloc(vec![1]), heap.alloc(1))];
+        assert!(AllocDict::from_sorted_unique(entries).alloc(&heap).is_err());
+    }
+}
@@ -26,6 +26,7 @@ use dupe::Dupe;
 use either::Either;
 
 use crate::coerce::coerce;
+use crate::eval::runtime::mutation_audit::note_mutation;
 use crate::typing::Ty;
 use crate::values::dict::value::DictGen;
 use crate::values::dict::value::FrozenDictData;
@@ -107,7 +108,10 @@ impl<'v> DictMut<'v> {
         match ptr {
             None => Err(error(x)),
             Some(ptr) => match ptr.0.try_borrow_mut() {
-                Ok(x) => Ok(DictMut { aref: x }),
+                Ok(x) => {
+                    note_mutation();
+                    Ok(DictMut { aref: x })
+                }
                 Err(_) => Err(ValueError::MutationDuringIteration.into()),
             },
         }
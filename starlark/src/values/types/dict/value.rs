@@ -43,6 +43,7 @@ use crate::collections::Hashed;
 use crate::collections::SmallMap;
 use crate::environment::Methods;
 use crate::environment::MethodsStatic;
+use crate::eval::runtime::mutation_audit::note_mutation;
 use crate::hint::unlikely;
 use crate::typing::Ty;
 use crate::values::comparison::equals_small_map;
@@ -365,6 +366,7 @@ impl<'v> DictLike<'v> for RefCell<Dict<'v>> {
     fn set_at(&self, index: Hashed<Value<'v>>, alloc_value: Value<'v>) -> crate::Result<()> {
         match self.try_borrow_mut() {
             Ok(mut xs) => {
+                note_mutation();
                 xs.content.insert_hashed(index, alloc_value);
                 Ok(())
             }
@@ -61,9 +61,25 @@ impl AllocFrozenValue for Ellipsis {
 #[cfg(test)]
 mod tests {
     use crate::assert;
+    use crate::assert::Assert;
+    use crate::syntax::Dialect;
 
     #[test]
     fn test_ellipsis() {
         assert::pass("...");
     }
+
+    #[test]
+    fn test_ellipsis_placeholder() {
+        let mut a = Assert::new();
+        a.dialect(&Dialect {
+            enable_ellipsis: true,
+            ..Dialect::Standard
+        });
+        a.eq("'Ellipsis'", "repr(...)");
+        a.pass("def stub(x):\n    ...\nstub(1)");
+
+        a.dialect(&Dialect::Standard);
+        a.fail("x = ...", "`...` is not allowed in this dialect");
+    }
 }
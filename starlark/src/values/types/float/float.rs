@@ -131,6 +131,44 @@ pub(crate) fn write_scientific<W: fmt::Write>(
     }
 }
 
+/// Stack buffer for the output of `{:e}`, so formatting a float does not allocate.
+/// The longest output is 24 bytes, e.g. `-2.2250738585072014e-308`.
+struct ExpBuf {
+    buf: [u8; 32],
+    len: usize,
+}
+
+impl ExpBuf {
+    fn new(f: f64) -> ExpBuf {
+        let mut buf = ExpBuf {
+            buf: [0; 32],
+            len: 0,
+        };
+        write!(buf, "{:e}", f).expect("`{:e}` output fits in the buffer");
+        buf
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole `&str` are written, so the buffer is valid UTF-8.
+        std::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl fmt::Write for ExpBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Write a float in the shortest form which round-trips back to the same float,
+/// switching to scientific notation for large and small exponents
+/// (like `%g` in starlark-go and `repr` in Python).
 pub(crate) fn write_compact<W: fmt::Write>(
     output: &mut W,
     f: f64,
@@ -139,16 +177,21 @@ pub(crate) fn write_compact<W: fmt::Write>(
     if !f.is_finite() {
         write_non_finite(output, f)
     } else {
-        let abs = f.abs();
-        let exponent = if f == 0.0 {
-            0
-        } else {
-            abs.log10().floor() as i32
-        };
-
-        if exponent.abs() >= WRITE_PRECISION as i32 {
-            // use scientific notation if exponent is outside of our precision (but strip 0s)
-            write_scientific(output, f, exponent_char, true)
+        // Rust `{:e}` produces the shortest mantissa which round-trips,
+        // and exponent computed exactly (unlike `log10`).
+        let shortest = ExpBuf::new(f);
+        let (mantissa, exponent) = shortest
+            .as_str()
+            .split_once('e')
+            .expect("`{:e}` output always has an exponent");
+        let exponent: i32 = exponent
+            .parse()
+            .expect("`{:e}` output exponent is an integer");
+
+        if exponent < -4 || exponent >= WRITE_PRECISION as i32 {
+            output.write_str(mantissa)?;
+            output.write_char(exponent_char)?;
+            output.write_fmt(format_args!("{:+03}", exponent))
         } else if f.fract() == 0.0 {
             // make sure there's a fractional part even if the number doesn't have it
             output.write_fmt(format_args!("{:.1}", f))
@@ -159,6 +202,148 @@ pub(crate) fn write_compact<W: fmt::Write>(
     }
 }
 
+/// Write a float in hexadecimal notation, like Python `float.hex()`.
+pub(crate) fn write_hex<W: fmt::Write>(output: &mut W, f: f64) -> fmt::Result {
+    if f.is_nan() {
+        return output.write_str("nan");
+    }
+    if f.is_sign_negative() {
+        output.write_char('-')?;
+    }
+    if f.is_infinite() {
+        return output.write_str("inf");
+    }
+    let bits = f.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    let mantissa = bits & ((1 << 52) - 1);
+    if exponent == 0 && mantissa == 0 {
+        output.write_str("0x0.0p+0")
+    } else if exponent == 0 {
+        // Subnormal.
+        output.write_fmt(format_args!("0x0.{:013x}p-1022", mantissa))
+    } else {
+        output.write_fmt(format_args!("0x1.{:013x}p{:+}", mantissa, exponent - 1023))
+    }
+}
+
+/// Parse a float in hexadecimal notation, like Python `float.fromhex()`.
+///
+/// Returns `None` if the string is not a valid hexadecimal float.
+/// The result is correctly rounded (to nearest, ties to even).
+pub(crate) fn parse_hex(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let lower = s.to_ascii_lowercase();
+    if lower == "inf" || lower == "infinity" {
+        return Some(if negative {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        });
+    }
+    if lower == "nan" {
+        return Some(f64::NAN);
+    }
+    let s = lower.strip_prefix("0x").unwrap_or(&lower);
+    let (digits, exp) = match s.split_once('p') {
+        Some((digits, exp)) => (digits, Some(exp)),
+        None => (s, None),
+    };
+
+    // Value is `mantissa * 2^exponent`, with `sticky` set if any nonzero digits
+    // did not fit into `mantissa`.
+    let mut mantissa: u64 = 0;
+    let mut exponent: i64 = 0;
+    let mut sticky = false;
+    let mut seen_digit = false;
+    let mut seen_point = false;
+    for c in digits.chars() {
+        if c == '.' {
+            if seen_point {
+                return None;
+            }
+            seen_point = true;
+            continue;
+        }
+        let d = c.to_digit(16)? as u64;
+        seen_digit = true;
+        if mantissa < (1 << 60) {
+            mantissa = mantissa * 16 + d;
+            if seen_point {
+                exponent -= 4;
+            }
+        } else {
+            sticky |= d != 0;
+            if !seen_point {
+                exponent += 4;
+            }
+        }
+    }
+    if !seen_digit {
+        return None;
+    }
+    if let Some(exp) = exp {
+        let (exp_negative, exp) = match exp.as_bytes().first() {
+            Some(b'-') => (true, &exp[1..]),
+            Some(b'+') => (false, &exp[1..]),
+            _ => (false, exp),
+        };
+        if exp.is_empty() || !exp.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        // Saturate: anything this large overflows or underflows anyway.
+        let exp: i64 = exp.parse().unwrap_or(1 << 40).min(1 << 40);
+        exponent += if exp_negative { -exp } else { exp };
+    }
+
+    let sign = if negative { -1.0 } else { 1.0 };
+    if mantissa == 0 {
+        return Some(sign * 0.0);
+    }
+
+    // Normalize, so the top bit of the mantissa is bit 63.
+    let shift = mantissa.leading_zeros();
+    let mantissa = (mantissa << shift) as u128;
+    exponent -= shift as i64;
+    // Exponent of the leading bit.
+    let top = exponent + 63;
+    if top > 1023 {
+        return Some(sign * f64::INFINITY);
+    }
+    // Number of significant bits we can keep: 53 for normal numbers, fewer for subnormal.
+    let keep = if top >= -1022 { 53 } else { top + 1075 };
+    let drop = 64 - keep;
+    if drop >= 128 {
+        return Some(sign * 0.0);
+    }
+    let mut kept = mantissa >> drop;
+    let rem = mantissa & ((1 << drop) - 1);
+    let half = 1 << (drop - 1);
+    if rem > half || (rem == half && (sticky || kept & 1 == 1)) {
+        kept += 1;
+    }
+    let bits = if top >= -1022 {
+        let (kept, top) = if kept == 1 << 53 {
+            (kept >> 1, top + 1)
+        } else {
+            (kept, top)
+        };
+        if top > 1023 {
+            return Some(sign * f64::INFINITY);
+        }
+        (((top + 1023) as u64) << 52) | (kept as u64 & ((1 << 52) - 1))
+    } else {
+        // Subnormal, if rounding up produced `1 << 52` it is the smallest normal number,
+        // which has the same bit representation.
+        kept as u64
+    };
+    Some(sign * f64::from_bits(bits))
+}
+
 /// Runtime representation of Starlark `float` type.
 #[derive(Clone, Dupe, Copy, Debug, ProvidesStaticType, Serialize, Allocative)]
 #[serde(transparent)]
@@ -404,6 +589,73 @@ mod tests {
         assert_eq!(compact(1.23e45), "1.23e+45");
         assert_eq!(compact(-3.14e-145), "-3.14e-145");
         assert_eq!(compact(1e300), "1e+300");
+        assert_eq!(compact(1.2345678e10), "1.2345678e+10");
+        assert_eq!(compact(123456.7), "123456.7");
+        assert_eq!(compact(0.0001), "0.0001");
+        assert_eq!(compact(0.00001234), "1.234e-05");
+        assert_eq!(compact(1e-300), "1e-300");
+        assert_eq!(compact(f64::MAX), "1.7976931348623157e+308");
+        assert_eq!(compact(-f64::MIN_POSITIVE), "-2.2250738585072014e-308");
+        assert_eq!(compact(-5e-324), "-5e-324");
+    }
+
+    fn hex(f: f64) -> String {
+        let mut buf = String::new();
+        write_hex(&mut buf, f).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_write_hex() {
+        assert_eq!(hex(f64::NAN), "nan");
+        assert_eq!(hex(f64::INFINITY), "inf");
+        assert_eq!(hex(f64::NEG_INFINITY), "-inf");
+        assert_eq!(hex(0.0), "0x0.0p+0");
+        assert_eq!(hex(-0.0), "-0x0.0p+0");
+        assert_eq!(hex(1.0), "0x1.0000000000000p+0");
+        assert_eq!(hex(1.5), "0x1.8000000000000p+0");
+        assert_eq!(hex(-0.1), "-0x1.999999999999ap-4");
+        assert_eq!(hex(5e-324), "0x0.0000000000001p-1022");
+        assert_eq!(hex(f64::MAX), "0x1.fffffffffffffp+1023");
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("0x1.8p1"), Some(3.0));
+        assert_eq!(parse_hex("-0X1P-3"), Some(-0.125));
+        assert_eq!(parse_hex(" 1.8 "), Some(1.5));
+        assert_eq!(parse_hex("0x.8"), Some(0.5));
+        assert_eq!(parse_hex("inf"), Some(f64::INFINITY));
+        assert_eq!(parse_hex("-Infinity"), Some(f64::NEG_INFINITY));
+        assert!(parse_hex("nan").unwrap().is_nan());
+        assert_eq!(parse_hex("0x1p1024"), Some(f64::INFINITY));
+        assert_eq!(parse_hex("0x1p-1075"), Some(0.0));
+        assert_eq!(parse_hex("0x1.8p-1074"), Some(1e-323));
+        assert_eq!(parse_hex("0x1.00000000000008p0"), Some(1.0));
+        assert_eq!(
+            parse_hex("0x1.00000000000008000001p0"),
+            Some(1.0 + f64::EPSILON)
+        );
+        assert_eq!(parse_hex("0x1.fffffffffffff8p0"), Some(2.0));
+        assert_eq!(parse_hex(""), None);
+        assert_eq!(parse_hex("0x"), None);
+        assert_eq!(parse_hex("0x1.2.3"), None);
+        assert_eq!(parse_hex("0x1p"), None);
+        assert_eq!(parse_hex("0xg"), None);
+
+        for f in [
+            0.1,
+            -1.5,
+            std::f64::consts::PI,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            5e-324,
+            1e-310,
+            -0.0,
+        ] {
+            let parsed = parse_hex(&hex(f)).unwrap();
+            assert_eq!(f.to_bits(), parsed.to_bits(), "{}", f);
+        }
     }
 
     #[test]
@@ -17,7 +17,7 @@
 
 //! Function types, including native functions and `object.member` functions.
 
-use std::collections::HashMap;
+use std::fmt;
 
 use allocative::Allocative;
 use derivative::Derivative;
@@ -35,6 +35,7 @@ use crate::docs::DocMember;
 use crate::docs::DocProperty;
 use crate::docs::DocString;
 use crate::docs::DocStringKind;
+use crate::eval::runtime::arguments::FunctionError as ArgumentsError;
 use crate::eval::Arguments;
 use crate::eval::Evaluator;
 use crate::eval::ParametersParser;
@@ -154,6 +155,156 @@ where
     }
 }
 
+/// Error in the arguments of a call to a native function defined with
+/// [`#[starlark_module]`](macro@crate::starlark_module), raised before the function body runs.
+///
+/// The caller, which knows the function documentation, replaces it with an error
+/// which includes the signature, see [`add_native_signature`].
+struct NativeArgumentsError {
+    error: anyhow::Error,
+    kind: fn(anyhow::Error) -> crate::ErrorKind,
+    param: Option<String>,
+}
+
+impl fmt::Debug for NativeArgumentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Display for NativeArgumentsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for NativeArgumentsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Error in the arguments of a call, followed by the signature of the function
+/// with the failing parameter underlined.
+struct NativeArgumentsErrorWithSignature {
+    error: anyhow::Error,
+    signature: String,
+    highlight: Option<(usize, usize)>,
+}
+
+impl fmt::Debug for NativeArgumentsErrorWithSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for NativeArgumentsErrorWithSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const PREFIX: &str = "Signature: ";
+        fmt::Display::fmt(&self.error, f)?;
+        write!(f, "\n{}{}", PREFIX, self.signature)?;
+        if let Some((start, end)) = self.highlight {
+            write!(
+                f,
+                "\n{}{}",
+                " ".repeat(PREFIX.len() + start),
+                "^".repeat(end - start)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for NativeArgumentsErrorWithSignature {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl NativeArgumentsError {
+    fn wrap(error: crate::Error, param: Option<String>) -> crate::Error {
+        let (error, kind): (_, fn(anyhow::Error) -> crate::ErrorKind) = match error.into_kind() {
+            crate::ErrorKind::Function(e) if e.is::<NativeArgumentsError>() => {
+                return crate::Error::new(crate::ErrorKind::Function(e));
+            }
+            crate::ErrorKind::Function(e) => {
+                let param = param.or_else(|| match e.downcast_ref::<ArgumentsError>() {
+                    Some(
+                        ArgumentsError::MissingParameter { name, .. }
+                        | ArgumentsError::MissingNamedOnlyParameter { name, .. },
+                    ) => Some(name.clone()),
+                    _ => None,
+                });
+                let error = NativeArgumentsError {
+                    error: e,
+                    kind: crate::ErrorKind::Function,
+                    param,
+                };
+                return crate::Error::new(crate::ErrorKind::Function(anyhow::Error::new(error)));
+            }
+            crate::ErrorKind::Value(e) => (e, crate::ErrorKind::Value),
+            crate::ErrorKind::Other(e) => (e, crate::ErrorKind::Other),
+            kind => return crate::Error::new(kind),
+        };
+        crate::Error::new(crate::ErrorKind::Function(anyhow::Error::new(
+            NativeArgumentsError { error, kind, param },
+        )))
+    }
+}
+
+/// Used by generated code: mark an error unpacking the parameter `param` of a native function.
+#[doc(hidden)]
+#[cold]
+pub fn native_parameter_error(param: &str, error: crate::Error) -> crate::Error {
+    NativeArgumentsError::wrap(error, Some(param.to_owned()))
+}
+
+/// Used by generated code: mark an error collecting the arguments of a native function.
+#[doc(hidden)]
+#[cold]
+pub fn native_arguments_error(error: crate::Error) -> crate::Error {
+    NativeArgumentsError::wrap(error, None)
+}
+
+/// Add the signature of the function to an error in its arguments.
+/// Other errors are returned unchanged.
+#[cold]
+pub(crate) fn add_native_signature(
+    error: crate::Error,
+    name: &str,
+    this: Option<Value>,
+    raw_docs: Option<&NativeCallableRawDocs>,
+) -> crate::Error {
+    match error.kind() {
+        crate::ErrorKind::Function(e) if e.is::<NativeArgumentsError>() => {}
+        _ => return error,
+    }
+    let crate::ErrorKind::Function(e) = error.into_kind() else {
+        unreachable!("checked above")
+    };
+    let NativeArgumentsError { error, kind, param } = match e.downcast() {
+        Ok(e) => e,
+        Err(e) => return crate::Error::new(crate::ErrorKind::Function(e)),
+    };
+    let Some(raw_docs) = raw_docs else {
+        return crate::Error::new(kind(error));
+    };
+    let name = match this {
+        Some(this) => format!("{}.{}", this.get_type(), name),
+        None => name.to_owned(),
+    };
+    let (signature, highlight) = raw_docs
+        .documentation()
+        .render_signature_line(&name, param.as_deref());
+    crate::Error::new(kind(anyhow::Error::new(
+        NativeArgumentsErrorWithSignature {
+            error,
+            signature,
+            highlight,
+        },
+    )))
+}
+
 /// A native function that can be evaluated.
 pub trait NativeAttr:
     for<'v> Fn(Value<'v>, &'v Heap) -> crate::Result<Value<'v>> + Send + Sync + 'static
@@ -172,6 +323,8 @@ pub struct NativeCallableRawDocs {
     pub rust_docstring: Option<&'static str>,
     pub signature: ParametersSpec<FrozenValue>,
     pub parameter_types: Vec<Ty>,
+    /// Doc comments on parameters, by parameter name.
+    pub parameter_docs: Vec<(&'static str, &'static str)>,
     pub return_type: Ty,
     pub as_type: Option<Ty>,
 }
@@ -181,8 +334,18 @@ impl NativeCallableRawDocs {
     pub fn documentation(&self) -> DocFunction {
         DocFunction::from_docstring(
             DocStringKind::Rust,
-            self.signature
-                .documentation(self.parameter_types.clone(), HashMap::new()),
+            self.signature.documentation(
+                self.parameter_types.clone(),
+                self.parameter_docs
+                    .iter()
+                    .map(|(name, docs)| {
+                        (
+                            (*name).to_owned(),
+                            DocString::from_docstring(DocStringKind::Rust, docs),
+                        )
+                    })
+                    .collect(),
+            ),
             self.return_type.clone(),
             self.rust_docstring,
             self.as_type.clone(),
@@ -274,7 +437,9 @@ impl<'v> StarlarkValue<'v> for NativeFunction {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
-        self.function.invoke(eval, args).map_err(Into::into)
+        eval.invoke_native(&self.name, None, args, self.raw_docs.as_ref(), |eval| {
+            self.function.invoke(eval, args)
+        })
     }
 
     fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
@@ -467,9 +632,14 @@ where
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
-        self.method
-            .function
-            .invoke(eval, self.this.to_value(), args)
+        let this = self.this.to_value();
+        eval.invoke_native(
+            &self.method.as_ref().name,
+            Some(this),
+            args,
+            Some(&self.method.as_ref().raw_docs),
+            |eval| self.method.function.invoke(eval, this, args),
+        )
     }
 
     fn documentation(&self) -> Option<DocItem> {
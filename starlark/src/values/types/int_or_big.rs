@@ -32,6 +32,7 @@ use dupe::Dupe;
 use num_bigint::BigInt;
 use num_bigint::Sign;
 use num_traits::FromPrimitive;
+use num_traits::One;
 use num_traits::Signed;
 use num_traits::ToPrimitive;
 use num_traits::Zero;
@@ -62,6 +63,14 @@ enum StarlarkIntError {
     LeftShiftNegative,
     #[error("Negative right shift")]
     RightShiftNegative,
+    #[error("Negative exponent with modulus: pow({0}, {1}, {2})")]
+    PowNegativeExponent(StarlarkInt, StarlarkInt, StarlarkInt),
+    #[error("Negative exponent for integer result: pow({0}, {1})")]
+    PowNegativeExponentInt(StarlarkInt, StarlarkInt),
+    #[error("Modulo by zero: pow({0}, {1}, {2})")]
+    PowModuloByZero(StarlarkInt, StarlarkInt, StarlarkInt),
+    #[error("Integer overflow computing pow")]
+    PowOverflow,
 }
 
 #[derive(
@@ -403,6 +412,81 @@ impl<'v> StarlarkIntRef<'v> {
             StarlarkIntRef::Big(i) => StarlarkInt::from(i.get().abs()),
         }
     }
+
+    /// Number of bits required to represent the absolute value, like Python `int.bit_length()`.
+    pub(crate) fn bit_length(self) -> u64 {
+        match self {
+            StarlarkIntRef::Small(i) => {
+                64 - (i.to_i32() as i64).unsigned_abs().leading_zeros() as u64
+            }
+            StarlarkIntRef::Big(i) => i.get().bits(),
+        }
+    }
+
+    /// Format with the given radix and prefix, like Python `bin`, `oct` and `hex`.
+    pub(crate) fn to_str_radix_prefixed(self, radix: u32, prefix: &str) -> String {
+        let big = self.to_big();
+        let sign = if big.is_negative() { "-" } else { "" };
+        format!("{}{}{}", sign, prefix, big.magnitude().to_str_radix(radix))
+    }
+
+    /// `pow(self, exponent)` for non-negative `exponent`,
+    /// or `pow(self, exponent, modulus)` if `modulus` is given.
+    pub(crate) fn pow(
+        self,
+        exponent: StarlarkIntRef,
+        modulus: Option<StarlarkIntRef>,
+    ) -> anyhow::Result<StarlarkInt> {
+        if let Some(modulus) = modulus {
+            if exponent.is_negative() {
+                return Err(StarlarkIntError::PowNegativeExponent(
+                    self.to_owned(),
+                    exponent.to_owned(),
+                    modulus.to_owned(),
+                )
+                .into());
+            }
+            if modulus.is_zero() {
+                return Err(StarlarkIntError::PowModuloByZero(
+                    self.to_owned(),
+                    exponent.to_owned(),
+                    modulus.to_owned(),
+                )
+                .into());
+            }
+            let modulus = modulus.to_big();
+            // `modpow` returns a result with the sign of `modulus`, like Python.
+            let r = self.to_big().modpow(&exponent.to_big(), &modulus);
+            return Ok(StarlarkInt::from(r));
+        }
+
+        if exponent.is_negative() {
+            return Err(
+                StarlarkIntError::PowNegativeExponentInt(self.to_owned(), exponent.to_owned())
+                    .into(),
+            );
+        }
+        let base = self.to_big();
+        if base.is_zero() || base.magnitude().is_one() {
+            // `0`, `1` or `-1` raised to any power do not grow.
+            let exponent_odd = exponent.to_big().bit(0);
+            return Ok(StarlarkInt::from(if exponent.is_zero() {
+                BigInt::one()
+            } else if base.is_negative() && !exponent_odd {
+                -base
+            } else {
+                base
+            }));
+        }
+        // Limit the size of the BigInt to avoid accidentally consuming
+        // too much memory, same as for left shift.
+        match exponent.to_u64() {
+            Some(e) if e.saturating_mul(self.bit_length() - 1) <= 100_000 => {
+                Ok(StarlarkInt::from(base.pow(e as u32)))
+            }
+            _ => Err(StarlarkIntError::PowOverflow.into()),
+        }
+    }
 }
 
 impl<'v> StarlarkTypeRepr for StarlarkIntRef<'v> {
@@ -684,6 +768,18 @@ mod tests {
             .to_string()
     }
 
+    #[test]
+    fn test_pow_negative_exponent() {
+        assert_eq!(
+            "Negative exponent for integer result: pow(2, -1)",
+            int("2")
+                .as_ref()
+                .pow(int("-1").as_ref(), None)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
     #[test]
     fn test_floor_div_big() {
         assert_eq!(
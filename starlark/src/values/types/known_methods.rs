@@ -55,7 +55,14 @@ impl KnownMethod {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> crate::Result<Value<'v>> {
-        self.imp.invoke(eval, this, args)
+        let imp = self.imp;
+        eval.invoke_native(
+            &self.method.as_ref().name,
+            Some(this),
+            args,
+            Some(&self.method.as_ref().raw_docs),
+            |eval| imp.invoke(eval, this, args),
+        )
     }
 }
 
@@ -0,0 +1,531 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Build-system target labels like `//pkg:name` or `@repo//pkg:name`,
+//! and target patterns like `//pkg/...`.
+//!
+//! Labels are parsed into a canonical form which is shared between clones and
+//! stores its hash, so cloning and hashing a [`Label`] are constant time, and
+//! comparing unequal labels for equality is usually constant time.
+//!
+//! ```
+//! use starlark::values::label::Label;
+//! use starlark::values::label::LabelPattern;
+//!
+//! let label = Label::parse("//foo/bar").unwrap();
+//! assert_eq!("//foo/bar:bar", label.as_str());
+//! assert_eq!("foo/bar", label.package());
+//! assert_eq!("bar", label.name());
+//! assert_eq!(label, Label::parse("//foo/bar:bar").unwrap());
+//!
+//! let other = label.relative(":baz").unwrap();
+//! assert_eq!("//foo/bar:baz", other.as_str());
+//! assert!(LabelPattern::parse("//foo/...").unwrap().matches(&other));
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+use starlark_map::StarlarkHasher;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+#[derive(Debug, thiserror::Error)]
+enum LabelError {
+    #[error("Invalid label `{0}`: {1}")]
+    InvalidLabel(String, &'static str),
+    #[error("Invalid target pattern `{0}`: {1}")]
+    InvalidPattern(String, &'static str),
+}
+
+fn is_valid_repo(repo: &str) -> bool {
+    let repo = repo.strip_prefix('@').unwrap_or(repo);
+    !repo.is_empty()
+        && repo
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"_.-+~".contains(&b))
+}
+
+/// Check the `/`-separated components of a package or target name.
+fn is_valid_path(path: &str, allow_empty: bool) -> bool {
+    if path.is_empty() {
+        return allow_empty;
+    }
+    path.split('/')
+        .all(|c| !c.is_empty() && c != "." && c != "..")
+        && !path
+            .bytes()
+            .any(|b| b.is_ascii_control() || b == b':' || b == b'@')
+}
+
+/// A parsed target label in canonical form: `@repo//package:name`,
+/// or `//package:name` for the main repository.
+#[derive(Clone, Dupe, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct Label {
+    /// Canonical form.
+    #[allocative(skip)]
+    s: Arc<str>,
+    /// Offset of the package in `s`, after the `//`.
+    package_start: u32,
+    /// Offset of the name in `s`, after the `:`.
+    name_start: u32,
+    hash: u64,
+}
+
+starlark_simple_value!(Label);
+
+impl Label {
+    /// The result of calling `type()` on a label.
+    pub const TYPE: &'static str = "Label";
+
+    fn new(repo: &str, package: &str, name: &str) -> Label {
+        let s = if repo.is_empty() {
+            format!("//{}:{}", package, name)
+        } else {
+            format!("@{}//{}:{}", repo, package, name)
+        };
+        let package_start = (s.len() - name.len() - 1 - package.len()) as u32;
+        let name_start = (s.len() - name.len()) as u32;
+        let mut hasher = StarlarkHasher::new();
+        s.hash(&mut hasher);
+        Label {
+            hash: hasher.finish(),
+            s: s.into(),
+            package_start,
+            name_start,
+        }
+    }
+
+    /// Parse an absolute label: `//pkg:name`, `//pkg` (short for `//pkg:pkg`),
+    /// `@repo//pkg:name` or `@repo` (short for `@repo//:repo`).
+    pub fn parse(s: &str) -> anyhow::Result<Label> {
+        Self::parse_impl(s, None)
+    }
+
+    /// Parse a label relative to this one: `:name` and `name` refer to targets
+    /// in the package of this label, and `//pkg:name` refers to the repository of this label.
+    pub fn relative(&self, s: &str) -> anyhow::Result<Label> {
+        Self::parse_impl(s, Some(self))
+    }
+
+    fn parse_impl(s: &str, base: Option<&Label>) -> anyhow::Result<Label> {
+        let err = |msg| LabelError::InvalidLabel(s.to_owned(), msg);
+        let (repo, rest) = match s.strip_prefix('@') {
+            Some(rest) => match rest.split_once("//") {
+                Some((repo, rest)) => (repo, Some(rest)),
+                // `@repo` is `@repo//:repo`.
+                None if is_valid_repo(rest) => {
+                    let name = rest.trim_start_matches('@');
+                    return Ok(Label::new(rest, "", name));
+                }
+                None => return Err(err("expected `//` after the repository name").into()),
+            },
+            None => match s.strip_prefix("//") {
+                Some(rest) => (base.map_or("", |b| b.repo()), Some(rest)),
+                None => ("", None),
+            },
+        };
+        if !repo.is_empty() && !is_valid_repo(repo) {
+            return Err(err("invalid repository name").into());
+        }
+        let (repo, package, name) = match rest {
+            Some(rest) => match rest.split_once(':') {
+                Some((package, name)) => (repo, package, name),
+                None => match rest.rsplit('/').next() {
+                    Some(name) if !name.is_empty() => (repo, rest, name),
+                    _ => return Err(err("expected a target name").into()),
+                },
+            },
+            None => {
+                let Some(base) = base else {
+                    return Err(err("absolute label must start with `//` or `@`").into());
+                };
+                let name = match s.strip_prefix(':') {
+                    Some(name) => name,
+                    None if !s.contains(':') => s,
+                    None => return Err(err("relative label must start with `:`").into()),
+                };
+                (base.repo(), base.package(), name)
+            }
+        };
+        if !is_valid_path(package, true) {
+            return Err(err("invalid package name").into());
+        }
+        if !is_valid_path(name, false) {
+            return Err(err("invalid target name").into());
+        }
+        Ok(Label::new(repo, package, name))
+    }
+
+    /// The canonical form of the label.
+    pub fn as_str(&self) -> &str {
+        &self.s
+    }
+
+    /// The repository name, empty for the main repository.
+    pub fn repo(&self) -> &str {
+        if self.package_start == 2 {
+            ""
+        } else {
+            &self.s[1..self.package_start as usize - 2]
+        }
+    }
+
+    /// The package, like `foo/bar`, empty for the root package.
+    pub fn package(&self) -> &str {
+        &self.s[self.package_start as usize..self.name_start as usize - 1]
+    }
+
+    /// The target name.
+    pub fn name(&self) -> &str {
+        &self.s[self.name_start as usize..]
+    }
+}
+
+impl PartialEq for Label {
+    fn eq(&self, other: &Label) -> bool {
+        self.hash == other.hash && self.s == other.s
+    }
+}
+
+impl Eq for Label {}
+
+impl Hash for Label {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+
+impl PartialOrd for Label {
+    fn partial_cmp(&self, other: &Label) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label {
+    fn cmp(&self, other: &Label) -> Ordering {
+        self.s.cmp(&other.s)
+    }
+}
+
+impl fmt::Debug for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Label({:?})", self.as_str())
+    }
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[starlark_value(type = Label::TYPE)]
+impl<'v> StarlarkValue<'v> for Label {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(label_methods)
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        Ok(other.downcast_ref::<Label>() == Some(self))
+    }
+
+    fn compare(&self, other: Value<'v>) -> crate::Result<Ordering> {
+        match other.downcast_ref::<Label>() {
+            Some(other) => Ok(self.cmp(other)),
+            None => ValueError::unsupported_with(self, "compare", other),
+        }
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        self.hash.hash(hasher);
+        Ok(())
+    }
+}
+
+/// What a [`LabelPattern`] matches within its package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternKind {
+    /// A single target.
+    Target(Label),
+    /// All targets in the package, `:all` or `:*`.
+    Package,
+    /// All targets in the package and its subpackages, `/...`.
+    Recursive,
+}
+
+/// A target pattern: a label, `//pkg:all` (also `:*` and `:all-targets`)
+/// for all targets in a package, or `//pkg/...` for all targets in a package
+/// and its subpackages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelPattern {
+    repo: String,
+    package: String,
+    kind: PatternKind,
+}
+
+impl LabelPattern {
+    /// Parse an absolute target pattern.
+    pub fn parse(s: &str) -> anyhow::Result<LabelPattern> {
+        let err = |msg| LabelError::InvalidPattern(s.to_owned(), msg);
+        let target = || -> anyhow::Result<LabelPattern> {
+            let label = Label::parse(s)?;
+            Ok(LabelPattern {
+                repo: label.repo().to_owned(),
+                package: label.package().to_owned(),
+                kind: PatternKind::Target(label),
+            })
+        };
+        let (repo, rest) = match s.strip_prefix('@') {
+            Some(rest) => rest
+                .split_once("//")
+                .ok_or_else(|| err("expected `//` after the repository name"))?,
+            None => (
+                "",
+                s.strip_prefix("//")
+                    .ok_or_else(|| err("pattern must start with `//` or `@`"))?,
+            ),
+        };
+        if !repo.is_empty() && !is_valid_repo(repo) {
+            return Err(err("invalid repository name").into());
+        }
+        let (package, kind) = match rest.split_once(':') {
+            Some((package, "all" | "*" | "all-targets")) => (package, PatternKind::Package),
+            Some(_) => return target(),
+            None if rest == "..." => ("", PatternKind::Recursive),
+            None => match rest.strip_suffix("/...") {
+                Some(package) => (package, PatternKind::Recursive),
+                None => return target(),
+            },
+        };
+        if !is_valid_path(package, true) {
+            return Err(err("invalid package name").into());
+        }
+        Ok(LabelPattern {
+            repo: repo.to_owned(),
+            package: package.to_owned(),
+            kind,
+        })
+    }
+
+    /// Does the pattern match the label.
+    pub fn matches(&self, label: &Label) -> bool {
+        if label.repo() != self.repo {
+            return false;
+        }
+        match &self.kind {
+            PatternKind::Target(target) => target == label,
+            PatternKind::Package => label.package() == self.package,
+            PatternKind::Recursive => {
+                self.package.is_empty()
+                    || label
+                        .package()
+                        .strip_prefix(self.package.as_str())
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            }
+        }
+    }
+}
+
+#[starlark_module]
+fn label_methods(builder: &mut MethodsBuilder) {
+    /// The repository name, `""` for the main repository.
+    #[starlark(attribute)]
+    fn repo(this: &Label) -> anyhow::Result<String> {
+        Ok(this.repo().to_owned())
+    }
+
+    /// The package, like `"foo/bar"`, `""` for the root package.
+    #[starlark(attribute)]
+    fn package(this: &Label) -> anyhow::Result<String> {
+        Ok(this.package().to_owned())
+    }
+
+    /// The target name.
+    #[starlark(attribute)]
+    fn name(this: &Label) -> anyhow::Result<String> {
+        Ok(this.name().to_owned())
+    }
+
+    /// Resolve a label relative to this one.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// str(Label("@r//foo:bar").relative(":baz")) == "@r//foo:baz"
+    /// str(Label("@r//foo:bar").relative("//x")) == "@r//x:x"
+    /// # "#);
+    /// ```
+    fn relative(this: &Label, #[starlark(require = pos)] label: &str) -> anyhow::Result<Label> {
+        this.relative(label)
+    }
+
+    /// Does the label match a target pattern like `//foo/...`, `//foo:all` or `//foo:bar`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// Label("//foo/bar:baz").matches("//foo/...")
+    /// not Label("//foobar:baz").matches("//foo/...")
+    /// Label("//foo:baz").matches("//foo:all")
+    /// # "#);
+    /// ```
+    fn matches(this: &Label, #[starlark(require = pos)] pattern: &str) -> anyhow::Result<bool> {
+        Ok(LabelPattern::parse(pattern)?.matches(this))
+    }
+}
+
+#[starlark_module]
+pub(crate) fn register_label(globals: &mut GlobalsBuilder) {
+    /// Parse an absolute target label like `"//foo:bar"`, `"//foo"` or `"@repo//foo:bar"`
+    /// into its canonical form.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// str(Label("//foo")) == "//foo:foo"
+    /// Label("//foo") == Label("//foo:foo")
+    /// Label("@repo//a/b:c").package == "a/b"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn Label(#[starlark(require = pos)] label: &str) -> anyhow::Result<Label> {
+        Label::parse(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dupe::Dupe;
+
+    use crate::assert;
+    use crate::values::label::Label;
+    use crate::values::label::LabelPattern;
+
+    #[test]
+    fn test_parse() {
+        for (input, canonical, repo, package, name) in [
+            ("//foo:bar", "//foo:bar", "", "foo", "bar"),
+            ("//foo/bar", "//foo/bar:bar", "", "foo/bar", "bar"),
+            ("//:all", "//:all", "", "", "all"),
+            ("@r//a/b:c/d.txt", "@r//a/b:c/d.txt", "r", "a/b", "c/d.txt"),
+            ("@r", "@r//:r", "r", "", "r"),
+            ("@@r.1//x", "@@r.1//x:x", "@r.1", "x", "x"),
+            ("@//a", "//a:a", "", "a", "a"),
+        ] {
+            let label = Label::parse(input).unwrap();
+            assert_eq!(canonical, label.as_str(), "{input}");
+            assert_eq!(
+                (repo, package, name),
+                (label.repo(), label.package(), label.name()),
+                "{input}"
+            );
+        }
+        for input in [
+            "", "foo", ":foo", "//", "//foo:", "//foo/", "//a//b", "//a/../b", "@a b//x", "@r/x",
+            "//a:b:c",
+        ] {
+            assert!(Label::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_relative() {
+        let base = Label::parse("@r//foo:bar").unwrap();
+        for (input, expected) in [
+            (":baz", "@r//foo:baz"),
+            ("baz/qux", "@r//foo:baz/qux"),
+            ("//x:y", "@r//x:y"),
+            ("@s//x", "@s//x:x"),
+        ] {
+            assert_eq!(expected, base.relative(input).unwrap().as_str());
+        }
+        assert!(base.relative("x:y").is_err());
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = Label::parse("//foo").unwrap();
+        let b = Label::parse("//foo:foo").unwrap();
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(a.as_str(), a.dupe().as_str()));
+        assert_ne!(a, Label::parse("@r//foo").unwrap());
+    }
+
+    #[test]
+    fn test_pattern() {
+        let label = Label::parse("//foo/bar:baz").unwrap();
+        for (pattern, expected) in [
+            ("//...", true),
+            ("//foo/...", true),
+            ("//foo/bar/...", true),
+            ("//fo/...", false),
+            ("//foo/bar:all", true),
+            ("//foo/bar:*", true),
+            ("//foo:all", false),
+            ("//foo/bar:baz", true),
+            ("//foo/bar:qux", false),
+            ("@r//...", false),
+        ] {
+            assert_eq!(
+                expected,
+                LabelPattern::parse(pattern).unwrap().matches(&label),
+                "{pattern}"
+            );
+        }
+        for pattern in ["foo/...", "//foo/..", "//a/../...", "@a b//..."] {
+            assert!(LabelPattern::parse(pattern).is_err(), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn test_starlark() {
+        assert::pass(
+            r#"
+l = Label("@r//foo")
+assert_eq(("r", "foo", "foo"), (l.repo, l.package, l.name))
+assert_eq("@r//foo:foo", str(l))
+assert_eq("Label", type(l))
+assert_eq([Label("//a:b"), Label("//a:c")], sorted([Label("//a:c"), Label("//a:b")]))
+assert_eq({Label("//x"): 1}, {Label("//x:x"): 1})
+assert_true(l.relative(":bar").matches("@r//foo:all"))
+"#,
+        );
+        assert::fail("Label(':foo')", "Invalid label `:foo`");
+        assert::fail(
+            "Label('//foo').matches('foo')",
+            "Invalid target pattern `foo`",
+        );
+    }
+}
@@ -46,6 +46,34 @@ impl AllocList<iter::Empty<FrozenValue>> {
     pub const EMPTY: AllocList<iter::Empty<FrozenValue>> = AllocList(iter::empty());
 }
 
+impl<T> AllocList<Vec<T>> {
+    /// Create an empty list builder with room for `capacity` elements.
+    ///
+    /// Elements are added with [`push`](AllocList::push), and the list is allocated
+    /// once with exact size when the builder is allocated on a [`Heap`] or a [`FrozenHeap`].
+    ///
+    /// ```
+    /// use starlark::values::list::AllocList;
+    /// use starlark::values::FrozenHeap;
+    ///
+    /// let mut list = AllocList::with_capacity(100_000);
+    /// for i in 0..100_000 {
+    ///     list.push(i);
+    /// }
+    /// let heap = FrozenHeap::new();
+    /// let list = heap.alloc(list);
+    /// # assert_eq!(list.to_value().length().unwrap(), 100_000);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        AllocList(Vec::with_capacity(capacity))
+    }
+
+    /// Append an element.
+    pub fn push(&mut self, item: T) {
+        self.0.push(item);
+    }
+}
+
 impl<L> StarlarkTypeRepr for AllocList<L>
 where
     L: IntoIterator,
@@ -24,6 +24,7 @@ use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::mem;
 use std::slice;
 
 use allocative::Allocative;
@@ -40,6 +41,7 @@ use crate::any::ProvidesStaticType;
 use crate::coerce::coerce;
 use crate::environment::Methods;
 use crate::environment::MethodsStatic;
+use crate::eval::runtime::mutation_audit::note_mutation;
 use crate::hint::likely;
 use crate::hint::unlikely;
 use crate::private::Private;
@@ -160,6 +162,7 @@ impl<'v> ListData<'v> {
         if unlikely(self.content.get().as_ref().iter_count_is_non_zero()) {
             return Err(ValueError::MutationDuringIteration.into());
         }
+        note_mutation();
         Ok(())
     }
 
@@ -550,7 +553,15 @@ where
             Ok(None) => return None,
             Err(e) => return Some(Err(e)),
         };
-        let mut result = Vec::with_capacity(self.0.content().len() * cmp::max(0, l) as usize);
+        let len = self
+            .0
+            .content()
+            .len()
+            .saturating_mul(cmp::max(0, l) as usize);
+        if let Err(e) = heap.check_allocation(len.saturating_mul(mem::size_of::<Value>())) {
+            return Some(Err(e));
+        }
+        let mut result = Vec::with_capacity(len);
         for _ in 0..l {
             result.extend(self.0.content().iter());
         }
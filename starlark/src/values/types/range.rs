@@ -69,6 +69,11 @@ impl Range {
         Range { start, stop, step }
     }
 
+    /// Start, stop and step of the range as integers.
+    pub(crate) fn start_stop_step(&self) -> (i32, i32, i32) {
+        (self.start, self.stop, self.step.get())
+    }
+
     fn equals_range(&self, other: &Range) -> crate::Result<bool> {
         let self_length = self.length()?;
         let other_length = other.length()?;
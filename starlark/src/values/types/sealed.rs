@@ -0,0 +1,162 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Opaque tokens wrapping frozen values, to pass host data through Starlark code.
+//!
+//! A host can [`seal`](Seal::seal) a frozen value into a [`SealedValue`], allocate it
+//! on a heap and hand it to another evaluation. Starlark code can store and pass the token
+//! around, but cannot see or call anything on the wrapped value. Only the same [`Seal`]
+//! can [`unseal`](Seal::unseal) the token, and the token keeps the heap of the wrapped value alive,
+//! so the unsealed value is always valid.
+//!
+//! ```
+//! use starlark::environment::Module;
+//! use starlark::values::sealed::Seal;
+//! use starlark::values::OwnedFrozenValue;
+//!
+//! let seal = Seal::new("handle");
+//! let token = seal.seal(OwnedFrozenValue::alloc(17));
+//!
+//! let module = Module::new();
+//! let value = module.heap().alloc(token);
+//! assert_eq!("<sealed handle>", value.to_str());
+//! assert_eq!(Some(17), seal.unseal(value).and_then(|v| v.unpack_i32()));
+//! assert!(Seal::new("handle").unseal(value).is_none());
+//! ```
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use allocative::Allocative;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::starlark_simple_value;
+use crate::values::OwnedFrozenValue;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+
+/// A capability to create and open [`SealedValue`] tokens.
+///
+/// Each `Seal` is distinct: tokens sealed by one seal cannot be unsealed by another,
+/// even if both have the same name.
+#[derive(Debug, Allocative)]
+pub struct Seal {
+    id: u64,
+    name: &'static str,
+}
+
+impl Seal {
+    /// Create a new seal. The name is shown in the representation of tokens.
+    pub fn new(name: &'static str) -> Seal {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Seal {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name,
+        }
+    }
+
+    /// Wrap a value into an opaque token.
+    pub fn seal(&self, value: OwnedFrozenValue) -> SealedValue {
+        SealedValue {
+            seal_id: self.id,
+            name: self.name,
+            value,
+        }
+    }
+
+    /// Get the value from a token created by this seal,
+    /// or `None` if `token` is not a token, or was sealed by a different seal.
+    pub fn unseal(&self, token: Value) -> Option<OwnedFrozenValue> {
+        let token = SealedValue::from_value(token)?;
+        if token.seal_id == self.id {
+            Some(token.value.clone())
+        } else {
+            None
+        }
+    }
+}
+
+/// Opaque token created by [`Seal::seal`].
+#[derive(Debug, Clone, ProvidesStaticType, NoSerialize, Allocative)]
+pub struct SealedValue {
+    seal_id: u64,
+    name: &'static str,
+    value: OwnedFrozenValue,
+}
+
+starlark_simple_value!(SealedValue);
+
+impl Display for SealedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<sealed {}>", self.name)
+    }
+}
+
+#[starlark_value(type = "sealed")]
+impl<'v> StarlarkValue<'v> for SealedValue {
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        Ok(match SealedValue::from_value(other) {
+            Some(other) => {
+                self.seal_id == other.seal_id && self.value.value().ptr_eq(other.value.value())
+            }
+            None => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert::Assert;
+    use crate::environment::GlobalsBuilder;
+    use crate::values::list::ListRef;
+    use crate::values::sealed::Seal;
+    use crate::values::OwnedFrozenValue;
+
+    #[test]
+    fn test_opaque_in_starlark() {
+        let seal = Seal::new("handle");
+        let token = seal.seal(OwnedFrozenValue::alloc("secret"));
+        let other = seal.seal(OwnedFrozenValue::alloc("secret"));
+        let mut a = Assert::new();
+        a.globals_add(|g: &mut GlobalsBuilder| {
+            g.set("token", token);
+            g.set("other", other);
+        });
+        a.eq("'sealed'", "type(token)");
+        a.eq("'<sealed handle>'", "repr(token)");
+        a.is_true("token == token");
+        a.is_true("token != other");
+        a.is_true("[token] == [token]");
+        a.fail("token.value", "has no attribute");
+        a.fail("{token: 1}", "not hashable");
+
+        // The token survives being stored in and returned from another module.
+        let module = a.pass_module("tokens = [token, token]");
+        let tokens = module.get("tokens").unwrap();
+        let first = ListRef::from_value(tokens.value()).unwrap()[0];
+        assert_eq!(
+            Some("secret"),
+            seal.unseal(first).as_ref().and_then(|v| v.unpack_str())
+        );
+        assert!(Seal::new("handle").unseal(first).is_none());
+    }
+}
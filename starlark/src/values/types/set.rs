@@ -0,0 +1,668 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set` type, enabled with
+//! [`LibraryExtension::SetType`](crate::environment::LibraryExtension::SetType).
+//!
+//! Sets are mutable, keep their elements in insertion order,
+//! and become `frozenset` when their module is frozen.
+//! There is no literal syntax, sets are created with `set([...])`.
+
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::mem;
+use std::ops::Deref;
+
+use allocative::Allocative;
+use display_container::fmt_container;
+use serde::Serialize;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::Trace;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::coerce::coerce;
+use crate::collections::SmallSet;
+use crate::collections::StarlarkHasher;
+use crate::environment::GlobalsBuilder;
+use crate::environment::Methods;
+use crate::environment::MethodsBuilder;
+use crate::environment::MethodsStatic;
+use crate::eval::runtime::mutation_audit::note_mutation;
+use crate::values::dict::refcell::unleak_borrow;
+use crate::values::none::NoneType;
+use crate::values::tuple::UnpackTuple;
+use crate::values::Freeze;
+use crate::values::Freezer;
+use crate::values::FrozenValue;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+/// Mutable set, the result of `set()`.
+#[derive(Debug, Default, Trace, ProvidesStaticType, Allocative)]
+pub(crate) struct MutableSet<'v>(RefCell<SmallSet<Value<'v>>>);
+
+/// Set after freezing, of type `frozenset`.
+#[derive(Debug, ProvidesStaticType, Allocative)]
+pub(crate) struct FrozenSet(SmallSet<FrozenValue>);
+
+/// Content of a `set` or a `frozenset`.
+pub(crate) enum SetRef<'v> {
+    Mutable(Ref<'v, SmallSet<Value<'v>>>),
+    Frozen(&'v SmallSet<Value<'v>>),
+}
+
+impl<'v> Deref for SetRef<'v> {
+    type Target = SmallSet<Value<'v>>;
+
+    fn deref(&self) -> &SmallSet<Value<'v>> {
+        match self {
+            SetRef::Mutable(xs) => xs,
+            SetRef::Frozen(xs) => xs,
+        }
+    }
+}
+
+impl<'v> SetRef<'v> {
+    pub(crate) fn from_value(x: Value<'v>) -> Option<SetRef<'v>> {
+        if let Some(set) = x.downcast_ref::<MutableSet<'v>>() {
+            Some(SetRef::Mutable(set.0.borrow()))
+        } else {
+            x.downcast_ref::<FrozenSet>()
+                .map(|set| SetRef::Frozen(coerce(&set.0)))
+        }
+    }
+}
+
+fn set_mut<'v>(x: Value<'v>) -> anyhow::Result<RefMut<'v, SmallSet<Value<'v>>>> {
+    match x.downcast_ref::<MutableSet<'v>>() {
+        Some(set) => {
+            let set = set
+                .0
+                .try_borrow_mut()
+                .map_err(|_| ValueError::MutationDuringIteration)?;
+            note_mutation();
+            Ok(set)
+        }
+        None => Err(ValueError::CannotMutateImmutableValue.into()),
+    }
+}
+
+fn alloc_set<'v>(heap: &'v Heap, content: SmallSet<Value<'v>>) -> Value<'v> {
+    heap.alloc_complex(MutableSet(RefCell::new(content)))
+}
+
+/// Copy of a mutable set, or `None` if the value is not a mutable set.
+pub(crate) fn copy_mutable_set<'v>(x: Value<'v>, heap: &'v Heap) -> Option<Value<'v>> {
+    let set = x.downcast_ref::<MutableSet<'v>>()?;
+    let content = set.0.borrow().clone();
+    Some(alloc_set(heap, content))
+}
+
+/// Copy of a frozen set as a mutable set.
+pub(crate) fn thaw_frozen_set<'v>(x: FrozenValue, heap: &'v Heap) -> Option<Value<'v>> {
+    let set = x.downcast_ref::<FrozenSet>()?;
+    // Elements are hashable, so they are immutable, and are shared.
+    Some(alloc_set(heap, coerce(&set.0).clone()))
+}
+
+/// Elements of an iterable, as a set.
+fn collect<'v>(x: Value<'v>, heap: &'v Heap) -> crate::Result<SmallSet<Value<'v>>> {
+    if let Some(set) = SetRef::from_value(x) {
+        return Ok((*set).clone());
+    }
+    let mut res = SmallSet::new();
+    for x in x.iterate(heap)? {
+        res.insert_hashed(x.get_hashed()?);
+    }
+    Ok(res)
+}
+
+fn equals<'v>(this: &SmallSet<Value<'v>>, other: Value<'v>) -> crate::Result<bool> {
+    match SetRef::from_value(other) {
+        None => Ok(false),
+        Some(other) => {
+            if this.len() != other.len() {
+                return Ok(false);
+            }
+            for x in other.iter() {
+                if !this.contains_hashed(x.get_hashed()?.as_ref()) {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn is_in<'v>(this: &SmallSet<Value<'v>>, x: Value<'v>) -> crate::Result<bool> {
+    Ok(this.contains_hashed(x.get_hashed()?.as_ref()))
+}
+
+/// Implementation of the binary operators `|`, `&`, `-` and `^`, which only accept sets.
+fn binary_op<'v, S: StarlarkValue<'v>>(
+    this: &S,
+    xs: &SmallSet<Value<'v>>,
+    op: &str,
+    other: Value<'v>,
+    heap: &'v Heap,
+) -> crate::Result<Value<'v>> {
+    let Some(ys) = SetRef::from_value(other) else {
+        return ValueError::unsupported_with(this, op, other);
+    };
+    let mut res = SmallSet::new();
+    match op {
+        "|" => {
+            res = xs.clone();
+            for y in ys.iter_hashed() {
+                res.insert_hashed(y.copied());
+            }
+        }
+        "&" => {
+            for x in xs.iter_hashed() {
+                if ys.contains_hashed(x) {
+                    res.insert_hashed(x.copied());
+                }
+            }
+        }
+        "-" => {
+            for x in xs.iter_hashed() {
+                if !ys.contains_hashed(x) {
+                    res.insert_hashed(x.copied());
+                }
+            }
+        }
+        "^" => {
+            for x in xs.iter_hashed() {
+                if !ys.contains_hashed(x) {
+                    res.insert_hashed(x.copied());
+                }
+            }
+            for y in ys.iter_hashed() {
+                if !xs.contains_hashed(y) {
+                    res.insert_hashed(y.copied());
+                }
+            }
+        }
+        _ => unreachable!("unknown set operator `{op}`"),
+    }
+    Ok(alloc_set(heap, res))
+}
+
+fn display_set(name: &str, xs: &SmallSet<Value>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt_container(f, &format!("{name}(["), "])", xs.iter())
+}
+
+impl<'v> Display for MutableSet<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_set("set", &self.0.borrow(), f)
+    }
+}
+
+impl Display for FrozenSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display_set("frozenset", coerce(&self.0), f)
+    }
+}
+
+impl<'v> Serialize for MutableSet<'v> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.borrow().iter())
+    }
+}
+
+impl Serialize for FrozenSet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+impl<'v> Freeze for MutableSet<'v> {
+    type Frozen = FrozenSet;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<FrozenSet> {
+        Ok(FrozenSet(self.0.freeze(freezer)?))
+    }
+}
+
+fn set_methods_static() -> Option<&'static Methods> {
+    static RES: MethodsStatic = MethodsStatic::new();
+    RES.methods(set_methods)
+}
+
+#[starlark_value(type = "set")]
+impl<'v> StarlarkValue<'v> for MutableSet<'v> {
+    fn get_methods() -> Option<&'static Methods> {
+        set_methods_static()
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.borrow().is_empty()
+    }
+
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.0.borrow().len() as i32)
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        equals(&self.0.borrow(), other)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> crate::Result<bool> {
+        is_in(&self.0.borrow(), other)
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> crate::Result<Value<'v>> {
+        mem::forget(self.0.borrow());
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        let rem = self.0.try_borrow_unguarded().unwrap().len() - index;
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, _heap: &'v Heap) -> Option<Value<'v>> {
+        // SAFETY: the set is borrowed by `iterate`.
+        self.0
+            .try_borrow_unguarded()
+            .unwrap()
+            .get_index(index)
+            .copied()
+    }
+
+    unsafe fn iter_stop(&self) {
+        unleak_borrow(&self.0);
+    }
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, &self.0.borrow(), "|", other, heap)
+    }
+
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, &self.0.borrow(), "&", other, heap)
+    }
+
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, &self.0.borrow(), "^", other, heap)
+    }
+
+    fn sub(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, &self.0.borrow(), "-", other, heap)
+    }
+}
+
+#[starlark_value(type = "frozenset")]
+impl<'v> StarlarkValue<'v> for FrozenSet {
+    type Canonical = FrozenSet;
+
+    fn get_methods() -> Option<&'static Methods> {
+        set_methods_static()
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn length(&self) -> crate::Result<i32> {
+        Ok(self.0.len() as i32)
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        equals(coerce(&self.0), other)
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        // Equality ignores the order of elements, so the hash must too.
+        let hash = self
+            .0
+            .iter_hashed()
+            .fold(0u32, |acc, x| acc.wrapping_add(x.hash().get()));
+        hash.hash(hasher);
+        Ok(())
+    }
+
+    fn is_in(&self, other: Value<'v>) -> crate::Result<bool> {
+        is_in(coerce(&self.0), other)
+    }
+
+    unsafe fn iterate(&self, me: Value<'v>, _heap: &'v Heap) -> crate::Result<Value<'v>> {
+        Ok(me)
+    }
+
+    unsafe fn iter_size_hint(&self, index: usize) -> (usize, Option<usize>) {
+        let rem = self.0.len() - index;
+        (rem, Some(rem))
+    }
+
+    unsafe fn iter_next(&self, index: usize, _heap: &'v Heap) -> Option<Value<'v>> {
+        self.0.get_index(index).map(|x| x.to_value())
+    }
+
+    unsafe fn iter_stop(&self) {}
+
+    fn bit_or(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, coerce(&self.0), "|", other, heap)
+    }
+
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, coerce(&self.0), "&", other, heap)
+    }
+
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, coerce(&self.0), "^", other, heap)
+    }
+
+    fn sub(&self, other: Value<'v>, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        binary_op(self, coerce(&self.0), "-", other, heap)
+    }
+}
+
+/// Methods shared by `set` and `frozenset`.
+/// Methods which mutate the set fail on a `frozenset`.
+#[starlark_module]
+fn set_methods(builder: &mut MethodsBuilder) {
+    /// Add an element to the set, does nothing if it is already present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.add(3)
+    /// x.add(1)
+    /// x == set([1, 2, 3])
+    /// # "#);
+    /// ```
+    fn add<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] x: Value<'v>,
+    ) -> starlark::Result<NoneType> {
+        let x = x.get_hashed()?;
+        set_mut(this)?.insert_hashed(x);
+        Ok(NoneType)
+    }
+
+    /// Remove an element from the set, fails if it is not present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.remove(1)
+    /// x == set([2])
+    /// # "#);
+    /// ```
+    fn remove<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] x: Value<'v>,
+    ) -> starlark::Result<NoneType> {
+        let hashed = x.get_hashed()?;
+        if set_mut(this)?.remove_hashed(hashed.as_ref()) {
+            Ok(NoneType)
+        } else {
+            Err(crate::Error::new_other(ValueError::KeyNotFound(
+                x.to_repr(),
+            )))
+        }
+    }
+
+    /// Remove an element from the set if it is present.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.discard(1)
+    /// x.discard(3)
+    /// x == set([2])
+    /// # "#);
+    /// ```
+    fn discard<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] x: Value<'v>,
+    ) -> starlark::Result<NoneType> {
+        let x = x.get_hashed()?;
+        set_mut(this)?.remove_hashed(x.as_ref());
+        Ok(NoneType)
+    }
+
+    /// Remove all the elements of the set.
+    fn clear(this: Value) -> anyhow::Result<NoneType> {
+        set_mut(this)?.clear();
+        Ok(NoneType)
+    }
+
+    /// Return a new set with the elements of the set and of all the given iterables.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).union([2, 3], (4,)) == set([1, 2, 3, 4])
+    /// # "#);
+    /// ```
+    fn union<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> starlark::Result<Value<'v>> {
+        let mut res = collect(this, heap)?;
+        for other in others.items {
+            for x in other.iterate(heap)? {
+                res.insert_hashed(x.get_hashed()?);
+            }
+        }
+        Ok(alloc_set(heap, res))
+    }
+
+    /// Return a new set with the elements of the set which are in all the given iterables.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3]).intersection([2, 3, 4], (3, 2)) == set([2, 3])
+    /// # "#);
+    /// ```
+    fn intersection<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> starlark::Result<Value<'v>> {
+        let mut res = collect(this, heap)?;
+        for other in others.items {
+            let other = collect(other, heap)?;
+            let mut kept = SmallSet::new();
+            for x in res.into_iter_hashed() {
+                if other.contains_hashed(x.as_ref()) {
+                    kept.insert_hashed(x);
+                }
+            }
+            res = kept;
+        }
+        Ok(alloc_set(heap, res))
+    }
+
+    /// Return a new set with the elements of the set which are in none of the given iterables.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2, 3]).difference([2], (3, 4)) == set([1])
+    /// # "#);
+    /// ```
+    fn difference<'v>(
+        this: Value<'v>,
+        #[starlark(args)] others: UnpackTuple<Value<'v>>,
+        heap: &'v Heap,
+    ) -> starlark::Result<Value<'v>> {
+        let mut res = collect(this, heap)?;
+        for other in others.items {
+            for x in other.iterate(heap)? {
+                res.remove_hashed(x.get_hashed()?.as_ref());
+            }
+        }
+        Ok(alloc_set(heap, res))
+    }
+
+    /// Check if all the elements of the set are in the given iterable.
+    fn issubset<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> starlark::Result<bool> {
+        let other = collect(other, heap)?;
+        let this = collect(this, heap)?;
+        Ok(this.iter_hashed().all(|x| other.contains_hashed(x)))
+    }
+
+    /// Check if all the elements of the given iterable are in the set.
+    fn issuperset<'v>(
+        this: Value<'v>,
+        #[starlark(require = pos)] other: Value<'v>,
+        heap: &'v Heap,
+    ) -> starlark::Result<bool> {
+        let this = collect(this, heap)?;
+        for x in other.iterate(heap)? {
+            if !this.contains_hashed(x.get_hashed()?.as_ref()) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[starlark_module]
+pub(crate) fn register_set(globals: &mut GlobalsBuilder) {
+    /// Create a mutable set with the elements of an iterable, in iteration order.
+    /// Sets become `frozenset` when their module is frozen.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set() == set([])
+    /// list(set([3, 1, 3, 2])) == [3, 1, 2]
+    /// set([1, 2]) | set([2, 3]) == set([1, 2, 3])
+    /// set([1, 2]) & set([2, 3]) == set([2])
+    /// set([1, 2]) - set([2, 3]) == set([1])
+    /// # "#);
+    /// ```
+    fn set<'v>(
+        #[starlark(require = pos)] iterable: Option<Value<'v>>,
+        heap: &'v Heap,
+    ) -> starlark::Result<Value<'v>> {
+        let content = match iterable {
+            Some(iterable) => collect(iterable, heap)?,
+            None => SmallSet::new(),
+        };
+        Ok(alloc_set(heap, content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+
+    #[test]
+    fn test_set() {
+        assert::all_true(
+            r#"
+type(set()) == "set"
+not set()
+len(set([1, 2, 1])) == 2
+1 in set([1, 2])
+3 not in set([1, 2])
+set([1, 2]) == set([2, 1])
+set([1, 2]) != [1, 2]
+set([1, 2]) != set([1])
+str(set([1, "a"])) == 'set([1, "a"])'
+set([1, 2]) ^ set([2, 3]) == set([1, 3])
+list(set([3, 2]) | set([1, 2])) == [3, 2, 1]
+set("ab".elems()).issubset(["a", "b", "c"])
+set([1, 2]).issuperset(set([2]))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_set_mutation() {
+        assert::is_true(
+            r#"
+x = set()
+x.add(1)
+x.add(2)
+x.remove(1)
+x.add(3)
+y = x
+y.clear()
+y.add(4)
+x == set([4])
+"#,
+        );
+        assert::fail("set([1]).remove(2)", "Key `2` was not found");
+        assert::fail("set([[1]])", "not hashable");
+        assert::fail("set([1]) | [1]", "Operation `|` not supported");
+        assert::fail(
+            r#"
+x = set([1, 2])
+for i in x:
+    x.add(3)
+"#,
+            "mutate an iterable for an iterator",
+        );
+    }
+
+    #[test]
+    fn test_frozenset() {
+        let mut a = Assert::new();
+        a.module("m", "x = set([1, 2])");
+        a.pass(
+            r#"
+load("m", "x")
+assert_eq(type(x), "frozenset")
+assert_eq(str(x), "frozenset([1, 2])")
+assert_eq(x, set([2, 1]))
+assert_eq(type(x | set([3])), "set")
+assert_eq(x.union([3]), set([1, 2, 3]))
+"#,
+        );
+        a.module("n", "y = set([2, 1])");
+        a.pass(
+            r#"
+load("m", "x")
+load("n", "y")
+d = {x: 1}
+assert_eq(d[y], 1)
+assert_eq(len(set([x, y])), 1)
+"#,
+        );
+        a.fail("{set([1]): 1}", "not hashable");
+        a.fail(
+            r#"
+load("m", "x")
+x.add(3)
+"#,
+            "Immutable",
+        );
+    }
+}
@@ -18,6 +18,7 @@
 //! The string type. All strings must be valid UTF8.
 
 mod alloc_unpack;
+pub(crate) mod casefold;
 pub(crate) mod dot_format;
 pub(crate) mod intern;
 pub(crate) mod interpolation;
@@ -77,6 +77,10 @@ impl<'v> StringValueInterner<'v> {
             }
         }
     }
+
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+    }
 }
 
 #[cfg(test)]
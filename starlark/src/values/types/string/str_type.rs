@@ -344,7 +344,11 @@ impl<'v> StarlarkValue<'v> for StarlarkStr {
             Ok(None) => return None,
             Err(e) => return Some(Err(e)),
         };
-        let mut result = String::with_capacity(self.len() * cmp::max(0, l) as usize);
+        let len = self.len().saturating_mul(cmp::max(0, l) as usize);
+        if let Err(e) = heap.check_allocation(len) {
+            return Some(Err(e));
+        }
+        let mut result = String::with_capacity(len);
         for _i in 0..l {
             result.push_str(self)
         }
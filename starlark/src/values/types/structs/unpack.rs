@@ -0,0 +1,106 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Runtime support for `#[derive(UnpackValue)]` on Rust structs.
+
+use starlark_map::small_map::SmallMap;
+
+use crate::typing::Ty;
+use crate::values::dict::DictRef;
+use crate::values::structs::StructRef;
+use crate::values::UnpackValue;
+use crate::values::Value;
+
+#[derive(Debug, thiserror::Error)]
+enum UnpackStructError {
+    #[error("Cannot unpack `{0}`: dict key must be a string, got `{1}`")]
+    NonStringKey(&'static str, String),
+    #[error("Cannot unpack `{0}`: missing field `{1}`")]
+    Missing(&'static str, String),
+    #[error("Cannot unpack `{0}`: unexpected field `{1}`")]
+    Unexpected(&'static str, String),
+    #[error("Cannot unpack `{0}`: field `{1}` expected `{2}`, got `{3}`")]
+    WrongType(&'static str, String, Ty, String),
+}
+
+/// Fields of a struct or a dict with string keys, being unpacked into a Rust struct.
+#[doc(hidden)]
+pub struct UnpackStructFields<'v> {
+    type_name: &'static str,
+    fields: SmallMap<&'v str, Value<'v>>,
+}
+
+impl<'v> UnpackStructFields<'v> {
+    /// Collect the fields, or return `None` if the value is neither a struct nor a dict.
+    pub fn new(type_name: &'static str, value: Value<'v>) -> crate::Result<Option<Self>> {
+        let fields = if let Some(s) = StructRef::from_value(value) {
+            s.iter().map(|(k, v)| (k.as_str(), v)).collect()
+        } else if let Some(dict) = DictRef::from_value(value) {
+            let mut fields = SmallMap::with_capacity(dict.len());
+            for (k, v) in dict.iter() {
+                let Some(k) = k.unpack_str() else {
+                    return Err(crate::Error::new_value(UnpackStructError::NonStringKey(
+                        type_name,
+                        k.to_string_for_type_error(),
+                    )));
+                };
+                fields.insert(k, v);
+            }
+            fields
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(UnpackStructFields { type_name, fields }))
+    }
+
+    /// Unpack the field if it is present.
+    pub fn field<T: UnpackValue<'v>>(&mut self, name: &str) -> crate::Result<Option<T>> {
+        let Some(value) = self.fields.remove(name) else {
+            return Ok(None);
+        };
+        match T::unpack_value(value)? {
+            Some(x) => Ok(Some(x)),
+            None => Err(crate::Error::new_value(UnpackStructError::WrongType(
+                self.type_name,
+                name.to_owned(),
+                T::starlark_type_repr(),
+                value.to_string_for_type_error(),
+            ))),
+        }
+    }
+
+    /// Unpack the field, which must be present.
+    pub fn required<T: UnpackValue<'v>>(&mut self, name: &str) -> crate::Result<T> {
+        match self.field(name)? {
+            Some(x) => Ok(x),
+            None => Err(crate::Error::new_value(UnpackStructError::Missing(
+                self.type_name,
+                name.to_owned(),
+            ))),
+        }
+    }
+
+    /// Check there are no fields left which do not correspond to Rust struct fields.
+    pub fn finish(self) -> crate::Result<()> {
+        match self.fields.into_keys().next() {
+            Some(name) => Err(crate::Error::new_value(UnpackStructError::Unexpected(
+                self.type_name,
+                name.to_owned(),
+            ))),
+            None => Ok(()),
+        }
+    }
+}
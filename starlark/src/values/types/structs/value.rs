@@ -43,6 +43,7 @@ use crate::typing::Ty;
 use crate::typing::TyStruct;
 use crate::values::comparison::compare_small_map;
 use crate::values::comparison::equals_small_map;
+use crate::values::dict::Dict;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
 use crate::values::structs::unordered_hasher::UnorderedHasher;
 use crate::values::FrozenStringValue;
@@ -175,6 +176,14 @@ where
         Ok(())
     }
 
+    fn to_kwargs(&self, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        let mut kwargs = SmallMap::with_capacity(self.fields.len());
+        for (k, v) in self.iter() {
+            kwargs.insert_hashed(k.to_value().get_hashed()?, v.to_value());
+        }
+        Ok(heap.alloc(Dict::new(kwargs)))
+    }
+
     fn dir_attr(&self) -> Vec<String> {
         self.fields.keys().map(|x| x.as_str().to_owned()).collect()
     }
@@ -213,6 +222,8 @@ impl<'v, V: ValueLike<'v>> Serialize for StructGen<'v, V> {
 mod tests {
 
     use crate::assert;
+    use crate::assert::Assert;
+    use crate::syntax::Dialect;
 
     #[test]
     fn test_repr() {
@@ -265,6 +276,41 @@ json.encode(struct(foo = [struct(bar = "some")])) == '{"foo":[{"bar":"some"}]}'
         );
     }
 
+    #[test]
+    fn test_to_kwargs() {
+        let mut a = Assert::new();
+        a.dialect(&Dialect {
+            enable_to_kwargs: true,
+            ..Dialect::Extended
+        });
+        a.pass(
+            r#"
+def f(a, b = 2, **kwargs):
+    return (a, b, kwargs)
+
+s = struct(a = 1, c = 3)
+assert_eq((1, 2, {"c": 3}), f(**s))
+assert_eq((1, 5, {}), f(b = 5, **struct(a = 1)))
+assert_eq((1, 2, {}), f(**{"a": 1}))
+assert_eq(s, struct(**s))
+assert_eq(struct(a = 1, c = 3), struct(**{"a": 1, "c": 3}))
+"#,
+        );
+        a.fail("def f(**kwargs): pass\nf(**[1])", "`**` not supported");
+        a.fail(
+            "def f(a): pass\nf(a = 1, **struct(a = 2))",
+            "occurs more than once",
+        );
+    }
+
+    #[test]
+    fn test_to_kwargs_disabled() {
+        assert::fail(
+            "def f(**kwargs): pass\nf(**struct(a = 1))",
+            "is not a dictionary",
+        );
+    }
+
     #[test]
     fn test_comparison_bug() {
         // TODO(nga): this should be false, because `a < b`,
@@ -15,11 +15,13 @@
  * limitations under the License.
  */
 
+use std::cmp;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::mem;
 use std::slice;
 
 use allocative::Allocative;
@@ -244,7 +246,11 @@ where
             Ok(None) => return None,
             Err(e) => return Some(Err(e)),
         };
-        let mut result = Vec::new();
+        let len = self.content().len().saturating_mul(cmp::max(0, l) as usize);
+        if let Err(e) = heap.check_allocation(len.saturating_mul(mem::size_of::<Value>())) {
+            return Some(Err(e));
+        }
+        let mut result = Vec::with_capacity(len);
         for _i in 0..l {
             result.extend(self.content().iter().map(|e| e.to_value()));
         }
@@ -89,7 +89,13 @@ impl UnboundValue {
             self.to_frozen_value().to_value(),
             Some(span),
             |eval| match self {
-                UnboundValue::Method(_, m) => m.invoke(eval, this, args),
+                UnboundValue::Method(method, m) => eval.invoke_native(
+                    &method.as_ref().name,
+                    Some(this),
+                    args,
+                    Some(&method.as_ref().raw_docs),
+                    |eval| m.invoke(eval, this, args),
+                ),
                 UnboundValue::Attr(_, a) => {
                     NativeAttribute::invoke_method_impl(&**a, this, args, eval)
                 }
@@ -215,14 +215,7 @@ fn type_compiled_methods(methods: &mut MethodsBuilder) {
 
     /// Error if the value does not match this type.
     fn check_matches<'v>(this: Value<'v>, value: Value<'v>) -> anyhow::Result<NoneType> {
-        if !this.get_ref().type_matches_value(value) {
-            return Err(TypingError::ValueDoesNotMatchType(
-                value.to_repr(),
-                value.get_type(),
-                TypeCompiled(this).to_string(),
-            )
-            .into());
-        }
+        TypeCompiled(this).check(value)?;
         Ok(NoneType)
     }
 }
@@ -288,6 +281,21 @@ impl<'v, V: ValueLike<'v>> TypeCompiled<V> {
         self.0.to_value().get_ref().type_matches_value(value)
     }
 
+    /// Check if given value matches this type, with an error describing
+    /// the mismatch otherwise. Useful to validate values like rule attributes.
+    pub fn check(&self, value: Value<'v>) -> anyhow::Result<()> {
+        if self.matches(value) {
+            Ok(())
+        } else {
+            Err(TypingError::ValueDoesNotMatchType(
+                value.to_repr(),
+                value.get_type(),
+                self.to_string(),
+            )
+            .into())
+        }
+    }
+
     /// Get the typechecker type for this runtime type.
     pub fn as_ty(&self) -> &'v Ty {
         self.downcast().unwrap().as_ty_dyn()
@@ -18,8 +18,10 @@
 #![cfg(test)]
 
 use crate::assert;
+use crate::values::list::AllocList;
 use crate::values::typing::type_compiled::compiled::TypeCompiled;
 use crate::values::Heap;
+use crate::values::Value;
 
 #[test]
 fn test_types() {
@@ -184,6 +186,21 @@ fn test_type_compiled_display() {
     t("None", "None");
 }
 
+#[test]
+fn test_type_compiled_check() {
+    let heap = Heap::new();
+    let ty = assert::pass("list[str] | None");
+    let ty = unsafe { ty.unchecked_frozen_value() }.to_value();
+    let ty = TypeCompiled::new(ty, &heap).unwrap();
+    ty.check(Value::new_none()).unwrap();
+    ty.check(heap.alloc(AllocList(["a", "b"]))).unwrap();
+    let err = ty.check(heap.alloc(AllocList([1]))).unwrap_err();
+    assert_eq!(
+        "Value of type `list` does not match type `None | list[str]`: [1]",
+        err.to_string()
+    );
+}
+
 #[test]
 fn test_type_compiled_starlark_api() {
     assert::eq("\"int\"", "repr(eval_type(int))");
@@ -64,6 +64,8 @@ use starlark_lsp::server::StringLiteralResult;
 use self::label::Label;
 use crate::eval::ContextMode;
 use crate::eval::EvalResult;
+use crate::load_trace::LoadTraceEvent;
+use crate::load_trace::LoadTracer;
 
 #[derive(Debug, thiserror::Error)]
 enum ContextError {
@@ -142,6 +144,7 @@ pub(crate) fn main(
     prelude: &[PathBuf],
     dialect: Dialect,
     globals: Globals,
+    trace_loads: bool,
 ) -> anyhow::Result<()> {
     if !lsp {
         return Err(anyhow::anyhow!("Bazel mode only supports `--lsp`"));
@@ -158,6 +161,9 @@ pub(crate) fn main(
     )?;
 
     ctx.mode = ContextMode::Check;
+    if trace_loads {
+        ctx.load_tracer = LoadTracer::stderr();
+    }
     starlark_lsp::server::stdio_server(ctx)?;
 
     Ok(())
@@ -174,6 +180,8 @@ pub(crate) struct BazelContext {
     pub(crate) globals: Globals,
     pub(crate) builtin_docs: HashMap<LspUrl, String>,
     pub(crate) builtin_symbols: HashMap<String, LspUrl>,
+    /// Receives the steps of resolving `load()` labels.
+    pub(crate) load_tracer: LoadTracer,
 }
 
 impl BazelContext {
@@ -262,6 +270,7 @@ impl BazelContext {
             }),
             external_output_base: output_base
                 .map(|output_base| PathBuf::from(output_base).join("external")),
+            load_tracer: LoadTracer::default(),
         })
     }
 
@@ -403,6 +412,46 @@ impl BazelContext {
             .map(|external_output_base| external_output_base.join(repository_name))
     }
 
+    fn trace_root(&self, reason: &'static str, root: Option<&Path>) {
+        self.load_tracer.trace(LoadTraceEvent::Root { reason, root });
+    }
+
+    /// Check whether a candidate file for a label exists.
+    fn check_candidate(&self, path: &Path) -> bool {
+        let exists = path.exists();
+        self.load_tracer.trace(LoadTraceEvent::Candidate { path, exists });
+        exists
+    }
+
+    /// Resolve a label in a `load()` statement to a file.
+    fn resolve_label(
+        &self,
+        path: &str,
+        current_file: &LspUrl,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<LspUrl> {
+        let label = Label::parse(path)?;
+
+        let folder = self.resolve_folder(&label, current_file, workspace_root)?;
+
+        // Try the presumed filename first, and check if it exists.
+        let presumed_path = folder.join(label.name);
+        if self.check_candidate(&presumed_path) {
+            return Ok(Url::from_file_path(presumed_path).unwrap().try_into()?);
+        }
+
+        // If the presumed filename doesn't exist, try to find a build file from the build system
+        // and use that instead.
+        for build_file_name in Self::BUILD_FILE_NAMES {
+            let path = folder.join(build_file_name);
+            if self.check_candidate(&path) {
+                return Ok(Url::from_file_path(path).unwrap().try_into()?);
+            }
+        }
+
+        Err(ResolveLoadError::TargetNotFound(path.to_owned()).into())
+    }
+
     /// Finds the directory that is the root of a package, given a label
     fn resolve_folder(
         &self,
@@ -427,24 +476,34 @@ impl BazelContext {
                 if let Some((_, remote_repository_root)) =
                     self.get_repository_for_path(current_file)
                 {
+                    self.trace_root(
+                        "repository of the current file",
+                        Some(remote_repository_root),
+                    );
                     Some(Cow::Borrowed(remote_repository_root))
                 } else {
+                    self.trace_root("workspace root", workspace_root);
                     workspace_root.map(Cow::Borrowed)
                 }
             }
             // No repository in the load path, and we don't have build system information, or
             // an `LspUrl` we can't use to check the root. Use the workspace root.
-            (None, _) => workspace_root.map(Cow::Borrowed),
+            (None, _) => {
+                self.trace_root("workspace root", workspace_root);
+                workspace_root.map(Cow::Borrowed)
+            }
             // We have a repository name and build system information. Check if the repository
             // name refers to the workspace, and if so, use the workspace root. If not, check
             // if it refers to a known remote repository, and if so, use that root.
             // Otherwise, fail with an error.
             (Some(repository), _) => {
                 if matches!(self.workspace_name.as_ref(), Some(name) if name == &repository.name) {
+                    self.trace_root("repository is the workspace", workspace_root);
                     workspace_root.map(Cow::Borrowed)
                 } else if let Some(remote_repository_root) =
                     self.get_repository_path(&repository.name).map(Cow::Owned)
                 {
+                    self.trace_root("external repository", Some(&remote_repository_root));
                     Some(remote_repository_root)
                 } else {
                     return Err(ResolveLoadError::UnknownRepository(
@@ -469,7 +528,13 @@ impl BazelContext {
                 LspUrl::File(current_file_path) => {
                     let current_file_dir = current_file_path.parent();
                     match current_file_dir {
-                        Some(current_file_dir) => Ok(current_file_dir.to_owned()),
+                        Some(current_file_dir) => {
+                            self.trace_root(
+                                "no package, directory of the current file",
+                                Some(current_file_dir),
+                            );
+                            Ok(current_file_dir.to_owned())
+                        }
                         None => Err(ResolveLoadError::MissingCurrentFilePath(label.clone()).into()),
                     }
                 }
@@ -656,26 +721,13 @@ impl LspContext for BazelContext {
         current_file: &LspUrl,
         workspace_root: Option<&std::path::Path>,
     ) -> anyhow::Result<LspUrl> {
-        let label = Label::parse(path)?;
-
-        let folder = self.resolve_folder(&label, current_file, workspace_root)?;
-
-        // Try the presumed filename first, and check if it exists.
-        let presumed_path = folder.join(label.name);
-        if presumed_path.exists() {
-            return Ok(Url::from_file_path(presumed_path).unwrap().try_into()?);
-        }
-
-        // If the presumed filename doesn't exist, try to find a build file from the build system
-        // and use that instead.
-        for build_file_name in Self::BUILD_FILE_NAMES {
-            let path = folder.join(build_file_name);
-            if path.exists() {
-                return Ok(Url::from_file_path(path).unwrap().try_into()?);
-            }
-        }
-
-        Err(ResolveLoadError::TargetNotFound(path.to_owned()).into())
+        self.load_tracer.trace(LoadTraceEvent::Resolve {
+            path,
+            from: current_file,
+        });
+        let result = self.resolve_label(path, current_file, workspace_root);
+        self.load_tracer.trace_result(path, &result);
+        result
     }
 
     fn render_as_load(
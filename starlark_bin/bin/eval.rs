@@ -22,12 +22,14 @@ use std::io;
 use std::iter;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 use itertools::Either;
 use lsp_types::Url;
 use starlark::analysis::AstModuleLint;
 use starlark::docs::get_registered_starlark_docs;
 use starlark::docs::render_docs_as_code;
+use starlark::docs::stub::STUB_EXTENSION;
 use starlark::docs::Doc;
 use starlark::docs::DocItem;
 use starlark::docs::DocModule;
@@ -36,8 +38,11 @@ use starlark::environment::Globals;
 use starlark::environment::Module;
 use starlark::errors::EvalMessage;
 use starlark::eval::Evaluator;
+use starlark::eval::ProfileData;
+use starlark::eval::ProfileMode;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
+use starlark::values::Value;
 use starlark::StarlarkResultExt;
 use starlark_lsp::error::eval_message_to_lsp_diagnostic;
 use starlark_lsp::server::LspContext;
@@ -45,7 +50,12 @@ use starlark_lsp::server::LspEvalResult;
 use starlark_lsp::server::LspUrl;
 use starlark_lsp::server::StringLiteralResult;
 
+use crate::load_trace::LoadTraceEvent;
+use crate::load_trace::LoadTracer;
+use crate::output::JsonRecord;
 use crate::suppression::GlobLintSuppression;
+use crate::var::vars_module;
+use crate::var::VarDefinition;
 
 #[derive(Debug)]
 pub(crate) enum ContextMode {
@@ -63,6 +73,21 @@ enum ContextError {
     WrongScheme(String, LspUrl),
 }
 
+/// Arguments of [`Context::new`].
+pub(crate) struct ContextOptions<'a> {
+    pub(crate) mode: ContextMode,
+    pub(crate) print_non_none: bool,
+    /// Files evaluated first, their public symbols are available to the evaluated code.
+    pub(crate) prelude: &'a [PathBuf],
+    /// Variables defined with `--var`.
+    pub(crate) vars: &'a [VarDefinition],
+    /// Keep a module to evaluate expressions in, for the interactive mode.
+    pub(crate) module: bool,
+    pub(crate) dialect: Dialect,
+    pub(crate) globals: Globals,
+    pub(crate) suppression_rules: Vec<GlobLintSuppression>,
+}
+
 #[derive(Debug)]
 pub(crate) struct Context {
     pub(crate) mode: ContextMode,
@@ -74,6 +99,14 @@ pub(crate) struct Context {
     pub(crate) builtin_docs: HashMap<LspUrl, String>,
     pub(crate) builtin_symbols: HashMap<String, LspUrl>,
     pub(crate) suppression_rules: Vec<GlobLintSuppression>,
+    /// If set, every evaluated file is profiled in this mode.
+    pub(crate) profile_mode: Option<ProfileMode>,
+    /// Profiles collected so far, one per evaluated file.
+    pub(crate) profiles: Mutex<Vec<ProfileData>>,
+    /// Print results of expressions as JSON records.
+    pub(crate) json_output: bool,
+    /// Receives the steps of resolving `load()` paths.
+    pub(crate) load_tracer: LoadTracer,
 }
 
 /// The outcome of evaluating (checking, parsing or running) given starlark code.
@@ -97,17 +130,23 @@ enum ResolveLoadError {
     WrongScheme(String, LspUrl),
 }
 
+fn is_stub(path: &Path) -> bool {
+    path.extension() == Some(STUB_EXTENSION.as_ref())
+}
+
 impl Context {
-    pub(crate) fn new(
-        mode: ContextMode,
-        print_non_none: bool,
-        prelude: &[PathBuf],
-        module: bool,
-        dialect: Dialect,
-        globals: Globals,
-        suppression_rules: Vec<GlobLintSuppression>,
-    ) -> anyhow::Result<Self> {
-        let prelude: Vec<_> = prelude
+    pub(crate) fn new(options: ContextOptions) -> anyhow::Result<Self> {
+        let ContextOptions {
+            mode,
+            print_non_none,
+            prelude,
+            vars,
+            module,
+            dialect,
+            globals,
+            suppression_rules,
+        } = options;
+        let mut prelude: Vec<_> = prelude
             .iter()
             .map(|x| {
                 let env = Module::new();
@@ -119,6 +158,9 @@ impl Context {
                 env.freeze()
             })
             .collect::<anyhow::Result<_>>()?;
+        if !vars.is_empty() {
+            prelude.push(vars_module(vars, &dialect)?);
+        }
 
         let module = if module {
             Some(Self::new_module(&prelude))
@@ -147,6 +189,10 @@ impl Context {
             builtin_docs,
             builtin_symbols,
             suppression_rules,
+            profile_mode: None,
+            profiles: Mutex::new(Vec::new()),
+            json_output: false,
+            load_tracer: LoadTracer::default(),
         })
     }
 
@@ -173,6 +219,8 @@ impl Context {
         let mut warnings = Either::Left(iter::empty());
         let mut errors = Either::Left(iter::empty());
         let final_ast = match self.mode {
+            // Interface stubs only declare signatures, there is nothing to lint or run.
+            _ if is_stub(Path::new(file)) => Some(ast),
             ContextMode::Check => {
                 warnings = Either::Right(self.check(file, &ast));
                 Some(ast)
@@ -233,9 +281,15 @@ impl Context {
         filename: &str,
         content: String,
     ) -> EvalResult<impl Iterator<Item = EvalMessage>> {
+        // Interface stubs use types and `...` whatever the dialect of the real modules.
+        let dialect = if is_stub(Path::new(filename)) {
+            &Dialect::Extended
+        } else {
+            &self.dialect
+        };
         Self::err(
             filename,
-            AstModule::parse(filename, content, &self.dialect)
+            AstModule::parse(filename, content, dialect)
                 .map(|module| self.go(filename, module))
                 .map_err(Into::into),
         )
@@ -254,20 +308,109 @@ impl Context {
         eval.enable_terminal_breakpoint_console();
         Self::err(
             file,
-            eval.eval_module(ast, &self.globals)
-                .map(|v| {
-                    if self.print_non_none && !v.is_none() {
+            self.eval_module(&mut eval, ast).and_then(|v| {
+                if self.print_non_none && !v.is_none() {
+                    if self.json_output {
+                        JsonRecord::Value {
+                            path: file,
+                            value: v.to_repr(),
+                            json: v.to_json_value().ok(),
+                        }
+                        .print()
+                        .map_err(starlark::Error::new_other)?;
+                    } else {
                         println!("{}", v);
                     }
-                    EvalResult {
-                        messages: iter::empty(),
-                        ast: None,
-                    }
+                }
+                Ok(EvalResult {
+                    messages: iter::empty(),
+                    ast: None,
                 })
-                .map_err(Into::into),
+            }),
         )
     }
 
+    /// Evaluate the module, collecting a profile if profiling is enabled.
+    fn eval_module<'v>(
+        &self,
+        eval: &mut Evaluator<'v, '_, '_>,
+        ast: AstModule,
+    ) -> starlark::Result<Value<'v>> {
+        let Some(mode) = &self.profile_mode else {
+            return eval.eval_module(ast, &self.globals);
+        };
+        eval.enable_profile(mode)?;
+        let res = eval.eval_module(ast, &self.globals);
+        // A failed evaluation is still profiled, up to the failure.
+        match eval.gen_profile() {
+            Ok(profile) => self.profiles.lock().unwrap().push(profile),
+            Err(e) if res.is_ok() => return Err(e),
+            Err(_) => {}
+        }
+        res
+    }
+
+    /// Merge the collected profiles and write them to `path`.
+    pub(crate) fn write_profile(&self, path: &Path) -> anyhow::Result<()> {
+        let profiles = self.profiles.lock().unwrap();
+        let profile = match (profiles.as_slice(), &self.profile_mode) {
+            // Nothing was evaluated, e.g. all files failed to parse.
+            ([], Some(mode)) => ProfileData::empty(mode),
+            _ => ProfileData::merge(profiles.iter()),
+        };
+        profile
+            .and_then(|profile| profile.write(path))
+            .into_anyhow_result()
+    }
+
+    fn resolve_load_path(&self, path: &str, current_file: &LspUrl) -> anyhow::Result<LspUrl> {
+        let path = PathBuf::from(path);
+        match current_file {
+            LspUrl::File(current_file_path) => {
+                let current_file_dir = current_file_path.parent();
+                let absolute_path = match (current_file_dir, path.is_absolute()) {
+                    (_, true) => {
+                        self.load_tracer.trace(LoadTraceEvent::Root {
+                            reason: "absolute path",
+                            root: None,
+                        });
+                        Ok(path)
+                    }
+                    (Some(current_file_dir), false) => {
+                        self.load_tracer.trace(LoadTraceEvent::Root {
+                            reason: "directory of the current file",
+                            root: Some(current_file_dir),
+                        });
+                        Ok(current_file_dir.join(&path))
+                    }
+                    (None, false) => Err(ResolveLoadError::MissingCurrentFilePath(path)),
+                }?;
+                let absolute_path = self.stub_fallback(absolute_path);
+                Ok(Url::from_file_path(absolute_path).unwrap().try_into()?)
+            }
+            _ => Err(
+                ResolveLoadError::WrongScheme("file://".to_owned(), current_file.clone()).into(),
+            ),
+        }
+    }
+
+    /// If a loaded module does not exist, but its interface stub does, resolve to the stub,
+    /// so modules shipped without source still get completions and definitions.
+    fn stub_fallback(&self, path: PathBuf) -> PathBuf {
+        if is_stub(&path) || path.exists() {
+            return path;
+        }
+        let stub = path.with_extension(STUB_EXTENSION);
+        if !stub.exists() {
+            return path;
+        }
+        self.load_tracer.trace(LoadTraceEvent::Candidate {
+            path: &stub,
+            exists: true,
+        });
+        stub
+    }
+
     fn is_suppressed(&self, file: &str, issue: &str) -> bool {
         self.suppression_rules
             .iter()
@@ -319,21 +462,13 @@ impl LspContext for Context {
         current_file: &LspUrl,
         _workspace_root: Option<&Path>,
     ) -> anyhow::Result<LspUrl> {
-        let path = PathBuf::from(path);
-        match current_file {
-            LspUrl::File(current_file_path) => {
-                let current_file_dir = current_file_path.parent();
-                let absolute_path = match (current_file_dir, path.is_absolute()) {
-                    (_, true) => Ok(path),
-                    (Some(current_file_dir), false) => Ok(current_file_dir.join(&path)),
-                    (None, false) => Err(ResolveLoadError::MissingCurrentFilePath(path)),
-                }?;
-                Ok(Url::from_file_path(absolute_path).unwrap().try_into()?)
-            }
-            _ => Err(
-                ResolveLoadError::WrongScheme("file://".to_owned(), current_file.clone()).into(),
-            ),
-        }
+        self.load_tracer.trace(LoadTraceEvent::Resolve {
+            path,
+            from: current_file,
+        });
+        let result = self.resolve_load_path(path, current_file);
+        self.load_tracer.trace_result(path, &result);
+        result
     }
 
     fn resolve_string_literal(
@@ -387,3 +522,65 @@ impl LspContext for Context {
         DocModule::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use starlark::environment::Globals;
+    use starlark::eval::ProfileMode;
+    use starlark::syntax::Dialect;
+
+    use crate::eval::Context;
+    use crate::eval::ContextMode;
+    use crate::eval::ContextOptions;
+
+    /// Run each of `programs` with statement profiling and return the written profile.
+    fn profile(programs: &[&str]) -> String {
+        let mut ctx = Context::new(ContextOptions {
+            mode: ContextMode::Run,
+            print_non_none: false,
+            prelude: &[],
+            vars: &[],
+            module: false,
+            dialect: Dialect::Extended,
+            globals: Globals::standard(),
+            suppression_rules: Vec::new(),
+        })
+        .unwrap();
+        ctx.profile_mode = Some(ProfileMode::Statement);
+        for program in programs {
+            ctx.expression((*program).to_owned())
+                .messages
+                .for_each(drop);
+        }
+        let path = std::env::temp_dir().join(format!(
+            "starlark_profile_{}_{}.csv",
+            std::process::id(),
+            programs.len()
+        ));
+        ctx.write_profile(&path).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        csv
+    }
+
+    #[test]
+    fn test_profile_failed_eval() {
+        let csv = profile(&["x = 1\nfail('oops')"]);
+        assert!(csv.contains("expression"));
+    }
+
+    #[test]
+    fn test_profile_nothing_evaluated() {
+        let csv = profile(&[]);
+        assert!(csv.starts_with("File,Span,Duration(s),Count\n"));
+        assert!(!csv.contains("expression"));
+    }
+
+    #[test]
+    fn test_profile_parse_error() {
+        let csv = profile(&["def", "1 +"]);
+        assert!(!csv.contains("expression"));
+    }
+}
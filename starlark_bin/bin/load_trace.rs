@@ -0,0 +1,200 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Logging of how `load()` paths are resolved, enabled with `--trace-loads`.
+
+use std::fmt;
+use std::fmt::Display;
+use std::path::Path;
+
+use starlark_lsp::server::LspUrl;
+
+/// One step in resolving a `load()` path.
+pub(crate) enum LoadTraceEvent<'a> {
+    /// Start resolving `path` loaded from `from`.
+    Resolve { path: &'a str, from: &'a LspUrl },
+    /// Root which the path is resolved against, and why it was chosen.
+    Root {
+        reason: &'static str,
+        root: Option<&'a Path>,
+    },
+    /// A candidate file was checked.
+    Candidate { path: &'a Path, exists: bool },
+    /// The path was resolved.
+    Resolved { path: &'a str, url: &'a LspUrl },
+    /// The path could not be resolved.
+    Failed {
+        path: &'a str,
+        error: &'a anyhow::Error,
+    },
+}
+
+impl Display for LoadTraceEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadTraceEvent::Resolve { path, from } => write!(f, "resolving `{path}` from {from}"),
+            LoadTraceEvent::Root { reason, root } => match root {
+                Some(root) => write!(f, "  root {} ({reason})", root.display()),
+                None => write!(f, "  no root ({reason})"),
+            },
+            LoadTraceEvent::Candidate { path, exists } => write!(
+                f,
+                "  candidate {}: {}",
+                path.display(),
+                if *exists { "exists" } else { "not found" }
+            ),
+            LoadTraceEvent::Resolved { path, url } => write!(f, "resolved `{path}` to {url}"),
+            LoadTraceEvent::Failed { path, error } => {
+                write!(f, "failed to resolve `{path}`: {error:#}")
+            }
+        }
+    }
+}
+
+/// Callback receiving the steps of `load()` resolution. Does nothing by default.
+#[derive(Default)]
+pub(crate) struct LoadTracer(Option<Box<dyn Fn(&LoadTraceEvent) + Send + Sync>>);
+
+impl fmt::Debug for LoadTracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LoadTracer")
+            .field(&self.0.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl LoadTracer {
+    pub(crate) fn new(callback: impl Fn(&LoadTraceEvent) + Send + Sync + 'static) -> Self {
+        LoadTracer(Some(Box::new(callback)))
+    }
+
+    /// Print the steps to stderr, which works with the LSP server using stdout.
+    pub(crate) fn stderr() -> Self {
+        Self::new(|event| eprintln!("load: {event}"))
+    }
+
+    pub(crate) fn trace(&self, event: LoadTraceEvent) {
+        if let Some(callback) = &self.0 {
+            callback(&event);
+        }
+    }
+
+    /// Trace the result of resolving `path`.
+    pub(crate) fn trace_result(&self, path: &str, result: &anyhow::Result<LspUrl>) {
+        match result {
+            Ok(url) => self.trace(LoadTraceEvent::Resolved { path, url }),
+            Err(error) => self.trace(LoadTraceEvent::Failed { path, error }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use starlark::environment::Globals;
+    use starlark::syntax::Dialect;
+    use starlark_lsp::server::LspContext;
+    use starlark_lsp::server::LspUrl;
+
+    use crate::eval::Context;
+    use crate::eval::ContextMode;
+    use crate::eval::ContextOptions;
+    use crate::load_trace::LoadTracer;
+
+    fn context() -> Context {
+        Context::new(ContextOptions {
+            mode: ContextMode::Check,
+            print_non_none: false,
+            prelude: &[],
+            vars: &[],
+            module: false,
+            dialect: Dialect::Extended,
+            globals: Globals::standard(),
+            suppression_rules: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    fn resolve(path: &str) -> Vec<String> {
+        resolve_from(path, PathBuf::from("/ws/pkg/main.star")).0
+    }
+
+    fn resolve_from(path: &str, current_file: PathBuf) -> (Vec<String>, anyhow::Result<LspUrl>) {
+        let mut ctx = context();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_copy = events.clone();
+        ctx.load_tracer =
+            LoadTracer::new(move |event| events_copy.lock().unwrap().push(event.to_string()));
+        let current_file = LspUrl::File(current_file);
+        let result = ctx.resolve_load(path, &current_file, None);
+        let events = events.lock().unwrap();
+        (events.clone(), result)
+    }
+
+    #[test]
+    fn test_trace_relative() {
+        assert_eq!(
+            vec![
+                "resolving `lib.star` from file:///ws/pkg/main.star",
+                "  root /ws/pkg (directory of the current file)",
+                "resolved `lib.star` to file:///ws/pkg/lib.star",
+            ],
+            resolve("lib.star")
+        );
+    }
+
+    #[test]
+    fn test_trace_absolute() {
+        assert_eq!(
+            vec![
+                "resolving `/lib/lib.star` from file:///ws/pkg/main.star",
+                "  no root (absolute path)",
+                "resolved `/lib/lib.star` to file:///lib/lib.star",
+            ],
+            resolve("/lib/lib.star")
+        );
+    }
+
+    #[test]
+    fn test_trace_stub() {
+        let dir = std::env::temp_dir().join(format!("starlark_stub_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let stub = dir.join("lib.star-api");
+        let source = "def f(x: int, y = ...) -> str:\n    ...\n";
+        fs::write(&stub, source).unwrap();
+        let (events, url) = resolve_from("lib.star", dir.join("main.star"));
+        let url = url.unwrap();
+        let missing = resolve_from("main.star", dir.join("main.star")).1.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(LspUrl::File(stub.clone()), url);
+        assert_eq!(format!("  candidate {}: exists", stub.display()), events[2]);
+        assert_eq!(LspUrl::File(dir.join("main.star")), missing);
+
+        let ctx = Context {
+            dialect: Dialect::Standard,
+            ..context()
+        };
+        let result = ctx.parse_file_with_contents(&url, source.to_owned());
+        assert!(result.diagnostics.is_empty());
+        assert!(result.ast.is_some());
+    }
+}
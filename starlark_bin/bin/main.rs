@@ -22,8 +22,10 @@
 use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Display;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Context as _;
 use clap::builder::StringValueParser;
@@ -34,6 +36,10 @@ use dupe::Dupe;
 use eval::Context;
 use itertools::Either;
 use itertools::Itertools;
+use output::doc_records;
+use output::duration_ms;
+use output::JsonRecord;
+use serde::Serialize;
 use starlark::analysis::LintMessage;
 use starlark::docs::get_registered_starlark_docs;
 use starlark::docs::markdown::render_doc_item;
@@ -43,17 +49,24 @@ use starlark::docs::DocItem;
 use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
+use starlark::eval::ProfileMode;
 use starlark::read_line::ReadLine;
 use starlark::syntax::Dialect;
 use suppression::GlobLintSuppression;
+use var::VarDefinition;
 use walkdir::WalkDir;
 
 use crate::eval::ContextMode;
+use crate::eval::ContextOptions;
+use crate::load_trace::LoadTracer;
 
 mod bazel;
 mod dap;
 mod eval;
+mod load_trace;
+mod output;
 mod suppression;
+mod var;
 
 #[derive(Debug, Parser)]
 #[command(name = "starlark", about = "Evaluate Starlark code", version)]
@@ -65,6 +78,7 @@ struct Args {
             "dap",
             "check",
             "json",
+            "output",
             "docs",
             "evaluate",
             "files",
@@ -80,6 +94,7 @@ struct Args {
             "lsp",
             "check",
             "json",
+            "output",
             "docs",
             "extension",
             "prelude",
@@ -99,10 +114,19 @@ struct Args {
     #[arg(
         long = "json",
         help = "Show output as JSON lines.",
-        conflicts_with_all = &["lsp", "dap"],
+        conflicts_with_all = &["lsp", "dap", "output"],
     )]
     json: bool,
 
+    #[arg(
+        long = "output",
+        help = "Output format. With `json`, diagnostics, results, timings and summary \
+are printed as JSON lines, each tagged with a `type` field.",
+        default_value = "text",
+        conflicts_with_all = &["lsp", "dap"],
+    )]
+    output: ArgsOutput,
+
     #[arg(
         long = "docs",
         help = "Generate documentation output.",
@@ -130,6 +154,16 @@ struct Args {
     )]
     evaluate: Vec<String>,
 
+    #[arg(
+        long = "var",
+        value_name = "NAME=VALUE",
+        help = "Define a variable for the evaluated code, e.g. `--var x='[1, 2, 3]'`. \
+The value is a Starlark literal or JSON.",
+        conflicts_with_all = &["lsp", "dap"],
+        value_parser = StringValueParser::new().try_map(VarDefinition::try_parse)
+    )]
+    var: Vec<VarDefinition>,
+
     #[arg(
         long = "dialect",
         help = "Dialect to use for features and globals.",
@@ -151,6 +185,13 @@ struct Args {
     )]
     bazel: bool,
 
+    #[arg(
+        long = "trace-loads",
+        help = "Log to stderr how each `load()` path is resolved: the roots chosen, \
+the candidate files checked and the final path."
+    )]
+    trace_loads: bool,
+
     #[arg(
         long = "suppression",
         help = "Specify lint rules to suppress. You may specify an optional glob pattern to \
@@ -159,6 +200,25 @@ suppress rules for files matching the pattern, in the format of `[<glob>:]<rule>
         value_parser = StringValueParser::new().try_map(GlobLintSuppression::try_parse)
     )]
     suppression: Vec<GlobLintSuppression>,
+
+    #[arg(
+        long = "profile",
+        help = "Profile the evaluated files. The output format is selected by the extension of \
+`--profile-out`: `.csv` gives a per-statement (time) or per-function (heap) summary, \
+anything else gives stacks for `flamegraph.pl`. Coverage is always a list of covered spans.",
+        requires = "profile_out",
+        conflicts_with_all = &["lsp", "dap", "check", "docs"],
+    )]
+    profile: Option<ArgsProfile>,
+
+    #[arg(
+        long = "profile-out",
+        id = "profile_out",
+        value_name = "PATH",
+        help = "File to write the profile to.",
+        requires = "profile"
+    )]
+    profile_out: Option<PathBuf>,
 }
 
 #[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
@@ -168,6 +228,33 @@ enum ArgsDoc {
     Code,
 }
 
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsOutput {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum ArgsProfile {
+    Time,
+    Heap,
+    Coverage,
+}
+
+impl ArgsProfile {
+    /// Pick the profile mode producing the format suggested by the output file extension.
+    fn mode(self, out: &Path) -> ProfileMode {
+        let csv = out.extension() == Some(OsStr::new("csv"));
+        match self {
+            ArgsProfile::Time if csv => ProfileMode::Statement,
+            ArgsProfile::Time => ProfileMode::TimeFlame,
+            ArgsProfile::Heap if csv => ProfileMode::HeapSummaryAllocated,
+            ArgsProfile::Heap => ProfileMode::HeapFlameAllocated,
+            ArgsProfile::Coverage => ProfileMode::Coverage,
+        }
+    }
+}
+
 #[derive(ValueEnum, Copy, Clone, Dupe, Debug, PartialEq, Eq)]
 enum ArgsDialect {
     Standard,
@@ -195,11 +282,25 @@ fn expand_dirs(extension: &str, xs: Vec<PathBuf>) -> impl Iterator<Item = PathBu
     })
 }
 
-#[derive(Default)]
+/// How messages are printed.
+#[derive(Copy, Clone, Dupe, Debug, PartialEq, Eq)]
+enum Output {
+    Text,
+    /// `--json`: only lint messages, without `type` tag.
+    LintJson,
+    /// `--output=json`.
+    Json,
+}
+
+#[derive(Default, Serialize)]
 struct Stats {
+    #[serde(rename = "files")]
     file: usize,
+    #[serde(rename = "errors")]
     error: usize,
+    #[serde(rename = "warnings")]
     warning: usize,
+    #[serde(rename = "advices")]
     advice: usize,
     disabled: usize,
 }
@@ -226,20 +327,30 @@ impl Stats {
             EvalSeverity::Disabled => self.disabled += 1,
         }
     }
+
+    fn merge(&mut self, other: &Stats) {
+        self.file += other.file;
+        self.error += other.error;
+        self.warning += other.warning;
+        self.advice += other.advice;
+        self.disabled += other.disabled;
+    }
 }
 
 fn drain(
     xs: impl Iterator<Item = EvalMessage>,
-    json: bool,
+    output: Output,
     stats: &mut Stats,
 ) -> anyhow::Result<()> {
     for x in xs {
         stats.increment(x.severity);
-        if json {
+        if output == Output::LintJson {
             println!(
                 "{}",
                 serde_json::to_string(&LintMessage::new(x)).context("serializing lint to JSON")?
             );
+        } else if output == Output::Json {
+            JsonRecord::Diagnostic(LintMessage::new(x)).print()?;
         } else if let Some(error) = x.full_error_with_span {
             let mut error = error.to_owned();
             if !error.is_empty() && !error.ends_with('\n') {
@@ -253,13 +364,36 @@ fn drain(
     Ok(())
 }
 
+/// Drain messages of one file or expression, evaluation of which started at `start`.
+fn drain_file(
+    path: &str,
+    xs: impl Iterator<Item = EvalMessage>,
+    start: Instant,
+    output: Output,
+    stats: &mut Stats,
+) -> anyhow::Result<()> {
+    let mut file_stats = Stats::default();
+    file_stats.increment_file();
+    drain(xs, output, &mut file_stats)?;
+    if output == Output::Json {
+        JsonRecord::File {
+            path,
+            duration_ms: duration_ms(start.elapsed()),
+            stats: &file_stats,
+        }
+        .print()?;
+    }
+    stats.merge(&file_stats);
+    Ok(())
+}
+
 fn interactive(ctx: &Context) -> anyhow::Result<()> {
     let mut rl = ReadLine::new("STARLARK_RUST_HISTFILE")?;
     loop {
         match rl.read_line("$> ")? {
             Some(line) => {
                 let mut stats = Stats::default();
-                drain(ctx.expression(line).messages, false, &mut stats)?;
+                drain(ctx.expression(line).messages, Output::Text, &mut stats)?;
             }
             // User pressed EOF - disconnected terminal, or similar
             None => return Ok(()),
@@ -282,6 +416,11 @@ fn main() -> anyhow::Result<()> {
 
     let args = argfile::expand_args(argfile::parse_fromfile, argfile::PREFIX)?;
     let args: Args = Args::parse_from(args);
+    let output = match (args.json, args.output) {
+        (true, _) => Output::LintJson,
+        (false, ArgsOutput::Text) => Output::Text,
+        (false, ArgsOutput::Json) => Output::Json,
+    };
 
     let (dialect, globals) = match args.dialect {
         ArgsDialect::Standard => (Dialect::Standard, Globals::standard()),
@@ -310,23 +449,33 @@ fn main() -> anyhow::Result<()> {
                 &prelude,
                 dialect,
                 globals,
+                args.trace_loads,
             )?;
             return Ok(());
         }
 
-        let mut ctx = Context::new(
-            if args.check {
+        let mut ctx = Context::new(ContextOptions {
+            mode: if args.check {
                 ContextMode::Check
             } else {
                 ContextMode::Run
             },
             print_non_none,
-            &prelude,
-            is_interactive,
+            prelude: &prelude,
+            vars: &args.var,
+            module: is_interactive,
             dialect,
             globals,
-            args.suppression,
-        )?;
+            suppression_rules: args.suppression,
+        })?;
+
+        if let (Some(profile), Some(out)) = (args.profile, &args.profile_out) {
+            ctx.profile_mode = Some(profile.mode(out));
+        }
+        ctx.json_output = output == Output::Json;
+        if args.trace_loads {
+            ctx.load_tracer = LoadTracer::stderr();
+        }
 
         if args.lsp {
             ctx.mode = ContextMode::Check;
@@ -339,6 +488,11 @@ fn main() -> anyhow::Result<()> {
             ));
 
             match docs {
+                _ if output == Output::Json => {
+                    for x in doc_records(docs, &builtin)? {
+                        x.print()?;
+                    }
+                }
                 ArgsDoc::Markdown | ArgsDoc::Lsp => {
                     println!(
                         "{}",
@@ -357,22 +511,47 @@ fn main() -> anyhow::Result<()> {
         } else if is_interactive {
             interactive(&ctx)?;
         } else {
+            let start = Instant::now();
             let mut stats = Stats::default();
             for e in args.evaluate.clone() {
-                stats.increment_file();
-                drain(ctx.expression(e).messages, args.json, &mut stats)?;
+                let file_start = Instant::now();
+                let messages = ctx.expression(e).messages;
+                drain_file("expression", messages, file_start, output, &mut stats)?;
             }
 
             for file in expand_dirs(ext, args.files.clone()) {
-                stats.increment_file();
-                drain(ctx.file(&file).messages, args.json, &mut stats)?;
+                let file_start = Instant::now();
+                let messages = ctx.file(&file).messages;
+                drain_file(
+                    &file.to_string_lossy(),
+                    messages,
+                    file_start,
+                    output,
+                    &mut stats,
+                )?;
+            }
+
+            if let Some(out) = &args.profile_out {
+                ctx.write_profile(out)?;
+                if output == Output::Json {
+                    JsonRecord::Profile {
+                        path: &out.to_string_lossy(),
+                    }
+                    .print()?;
+                }
             }
 
-            if !args.json {
-                println!("{}", stats);
-                if stats.error > 0 {
-                    return Err(anyhow::anyhow!("Failed with {} errors", stats.error));
+            match output {
+                Output::LintJson => return Ok(()),
+                Output::Text => println!("{}", stats),
+                Output::Json => JsonRecord::Summary {
+                    duration_ms: duration_ms(start.elapsed()),
+                    stats: &stats,
                 }
+                .print()?,
+            }
+            if stats.error > 0 {
+                return Err(anyhow::anyhow!("Failed with {} errors", stats.error));
             }
         }
     }
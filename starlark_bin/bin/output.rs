@@ -0,0 +1,150 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Records printed with `--output=json`.
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+use serde::Serialize;
+use starlark::analysis::LintMessage;
+use starlark::docs::markdown::render_doc_item;
+use starlark::docs::render_docs_as_code;
+use starlark::docs::Doc;
+
+use crate::ArgsDoc;
+use crate::Stats;
+
+/// Output line for `--output=json`, tagged with `type`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum JsonRecord<'a> {
+    /// Error, lint or warning, with path and position.
+    Diagnostic(LintMessage),
+    /// Non-`None` result of an evaluated expression.
+    Value {
+        path: &'a str,
+        /// Repr of the value.
+        value: String,
+        /// The value as JSON, if it can be converted.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        json: Option<serde_json::Value>,
+    },
+    /// A file or an expression was processed.
+    File {
+        path: &'a str,
+        duration_ms: f64,
+        #[serde(flatten)]
+        stats: &'a Stats,
+    },
+    /// Profile was written.
+    Profile { path: &'a str },
+    /// Documentation of a builtin, rendered as markdown.
+    Doc { name: &'a str, markdown: String },
+    /// Documentation of all builtins, rendered as Starlark code.
+    DocCode { code: String },
+    /// Last record, totals over all the files.
+    Summary {
+        duration_ms: f64,
+        #[serde(flatten)]
+        stats: &'a Stats,
+    },
+}
+
+impl<'a> JsonRecord<'a> {
+    /// Print as a single line to stdout.
+    pub(crate) fn print(&self) -> anyhow::Result<()> {
+        println!(
+            "{}",
+            serde_json::to_string(self).context("serializing output to JSON")?
+        );
+        Ok(())
+    }
+}
+
+/// Records for `--docs` with `--output=json`.
+pub(crate) fn doc_records(docs: ArgsDoc, builtin: &[Doc]) -> anyhow::Result<Vec<JsonRecord<'_>>> {
+    match docs {
+        ArgsDoc::Markdown => Ok(builtin
+            .iter()
+            .map(|x| JsonRecord::Doc {
+                name: &x.id.name,
+                markdown: render_doc_item(&x.id.name, &x.item),
+            })
+            .collect()),
+        ArgsDoc::Code => Ok(vec![JsonRecord::DocCode {
+            code: render_docs_as_code(builtin),
+        }]),
+        ArgsDoc::Lsp => Err(anyhow::anyhow!(
+            "`--docs=lsp` does not support `--output=json`"
+        )),
+    }
+}
+
+pub(crate) fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::docs::DocItem;
+    use starlark::environment::Globals;
+
+    use super::*;
+
+    fn builtin() -> Vec<Doc> {
+        vec![Doc::named_item(
+            "globals".to_owned(),
+            DocItem::Module(Globals::standard().documentation()),
+        )]
+    }
+
+    fn to_json(records: &[JsonRecord]) -> Vec<serde_json::Value> {
+        records
+            .iter()
+            .map(|x| serde_json::to_value(x).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_doc_records_markdown() {
+        let builtin = builtin();
+        let records = to_json(&doc_records(ArgsDoc::Markdown, &builtin).unwrap());
+        assert_eq!(1, records.len());
+        assert_eq!("doc", records[0]["type"]);
+        assert_eq!("globals", records[0]["name"]);
+        assert!(records[0]["markdown"].as_str().unwrap().contains("def len"));
+    }
+
+    #[test]
+    fn test_doc_records_code() {
+        let builtin = builtin();
+        let records = to_json(&doc_records(ArgsDoc::Code, &builtin).unwrap());
+        assert_eq!(1, records.len());
+        assert_eq!("doc_code", records[0]["type"]);
+        assert!(records[0]["code"].as_str().unwrap().contains("def len"));
+    }
+
+    #[test]
+    fn test_doc_records_lsp() {
+        let err = doc_records(ArgsDoc::Lsp, &builtin()).err().unwrap();
+        assert_eq!(
+            "`--docs=lsp` does not support `--output=json`",
+            err.to_string()
+        );
+    }
+}
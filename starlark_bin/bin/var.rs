@@ -0,0 +1,188 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use starlark::values::Value;
+use starlark::StarlarkResultExt;
+use starlark_syntax::syntax::ast::AstExpr;
+use starlark_syntax::syntax::ast::AstLiteral;
+use starlark_syntax::syntax::ast::Expr;
+use starlark_syntax::syntax::ast::Stmt;
+use starlark_syntax::syntax::module::AstModuleFields;
+
+#[derive(Debug, thiserror::Error)]
+enum VarError {
+    #[error("Expecting `NAME=VALUE`, got `{0}`")]
+    NoEquals(String),
+    #[error("Variable name `{0}` is not an identifier")]
+    NotIdentifier(String),
+    #[error("Value of `{0}` is not a literal or JSON: `{1}`")]
+    NotLiteral(String, String),
+}
+
+/// Variable defined with `--var NAME=VALUE`.
+#[derive(Debug, Clone)]
+pub struct VarDefinition {
+    name: String,
+    /// Starlark literal or JSON.
+    value: String,
+}
+
+impl VarDefinition {
+    pub fn try_parse(input: impl AsRef<str>) -> anyhow::Result<Self> {
+        let input = input.as_ref();
+        let (name, value) = input
+            .split_once('=')
+            .ok_or_else(|| VarError::NoEquals(input.to_owned()))?;
+        let name = name.trim();
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(VarError::NotIdentifier(name.to_owned()).into());
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        })
+    }
+
+    /// Parse the value as a Starlark literal, or, if it is not one,
+    /// as JSON (which has `true`, `false` and `null`).
+    fn eval<'v>(&self, module: &'v Module, dialect: &Dialect) -> anyhow::Result<Value<'v>> {
+        let ast = AstModule::parse(&format!("--var {}", self.name), self.value.clone(), dialect)
+            .ok()
+            .filter(is_literal_module);
+        match ast {
+            Some(ast) => {
+                // Only literals are evaluated, so no user code runs.
+                Evaluator::new(module)
+                    .eval_module(ast, &Globals::standard())
+                    .into_anyhow_result()
+            }
+            None => match serde_json::from_str::<serde_json::Value>(&self.value) {
+                Ok(json) => Ok(module.heap().alloc(json)),
+                Err(_) => Err(VarError::NotLiteral(self.name.clone(), self.value.clone()).into()),
+            },
+        }
+    }
+}
+
+/// The module is a single literal expression.
+fn is_literal_module(ast: &AstModule) -> bool {
+    match &ast.statement().node {
+        Stmt::Expression(x) => is_literal(x),
+        Stmt::Statements(xs) => match xs.as_slice() {
+            [x] => matches!(&x.node, Stmt::Expression(x) if is_literal(x)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// A number, a string, `True`, `False`, `None`, or a list, tuple or dict of literals.
+fn is_literal(x: &AstExpr) -> bool {
+    match &x.node {
+        Expr::Literal(AstLiteral::Int(_) | AstLiteral::Float(_) | AstLiteral::String(_)) => true,
+        Expr::Identifier(x) => matches!(x.node.ident.as_str(), "True" | "False" | "None"),
+        Expr::Minus(x) | Expr::Plus(x) => matches!(
+            &x.node,
+            Expr::Literal(AstLiteral::Int(_) | AstLiteral::Float(_))
+        ),
+        Expr::List(xs) | Expr::Tuple(xs) => xs.iter().all(is_literal),
+        Expr::Dict(xs) => xs.iter().all(|(k, v)| is_literal(k) && is_literal(v)),
+        _ => false,
+    }
+}
+
+/// Module defining the variables, to be imported into evaluated modules like a prelude.
+pub fn vars_module(vars: &[VarDefinition], dialect: &Dialect) -> anyhow::Result<FrozenModule> {
+    let module = Module::new();
+    for var in vars {
+        let value = var.eval(&module, dialect)?;
+        module.set(&var.name, value);
+    }
+    module.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(vars: &[&str]) -> anyhow::Result<FrozenModule> {
+        let vars = vars
+            .iter()
+            .map(VarDefinition::try_parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        vars_module(&vars, &Dialect::Extended)
+    }
+
+    fn repr(module: &FrozenModule, name: &str) -> String {
+        module.get(name).unwrap().value().to_repr()
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert!(VarDefinition::try_parse("x=1").is_ok());
+        assert!(VarDefinition::try_parse("x").is_err());
+        assert!(VarDefinition::try_parse("1x=1").is_err());
+        assert!(VarDefinition::try_parse("x.y=1").is_err());
+    }
+
+    #[test]
+    fn test_starlark_and_json() {
+        let module = eval(&[
+            "x=[1,2,3]",
+            "s='a=b'",
+            r#"j={"a": true, "b": null}"#,
+            "t=(1, 2)",
+        ])
+        .unwrap();
+        assert_eq!("[1, 2, 3]", repr(&module, "x"));
+        assert_eq!("\"a=b\"", repr(&module, "s"));
+        assert_eq!("{\"a\": True, \"b\": None}", repr(&module, "j"));
+        assert_eq!("(1, 2)", repr(&module, "t"));
+    }
+
+    #[test]
+    fn test_negative_and_nested() {
+        let module = eval(&["x=-1", r#"d={"a": (1.5, None), 2: [True]}"#]).unwrap();
+        assert_eq!("-1", repr(&module, "x"));
+        assert_eq!("{\"a\": (1.5, None), 2: [True]}", repr(&module, "d"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(eval(&["x=[1,"]).is_err());
+        assert!(eval(&["x=undefined"]).is_err());
+    }
+
+    #[test]
+    fn test_not_literal() {
+        for value in ["fail('boom')", "len([1])", "1 + 2", "[x for x in [1]]"] {
+            let err = eval(&[&format!("x={value}")]).unwrap_err();
+            assert_eq!(
+                format!("Value of `x` is not a literal or JSON: `{value}`"),
+                err.to_string()
+            );
+        }
+    }
+}
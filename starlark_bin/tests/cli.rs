@@ -0,0 +1,94 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests running the `starlark` binary.
+
+use std::process::Command;
+use std::process::Output;
+
+fn starlark(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_starlark"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn test_evaluate_with_var() {
+    let output = starlark(&["-e", "len(x) + 1", "--var", "x=[1, 2, 3]"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        "4\n1 files, 0 errors, 0 warnings, 0 advices, 0 disabled\n",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn test_var_json() {
+    let output = starlark(&["-e", "x['a']", "--var", r#"x={"a": true}"#]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).starts_with("True\n"));
+}
+
+#[test]
+fn test_var_not_literal() {
+    let output = starlark(&["-e", "x", "--var", "x=fail('boom')"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Value of `x` is not a literal or JSON: `fail('boom')`"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn test_output_json() {
+    let output = starlark(&["--output=json", "-e", "1 + 1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let records = stdout(&output)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let types = records
+        .iter()
+        .map(|x| x["type"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["value", "file", "summary"], types);
+    assert_eq!(2, records[0]["json"]);
+}
+
+#[test]
+fn test_docs_json() {
+    let output = starlark(&["--docs=markdown", "--output=json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    for line in stdout(&output).lines() {
+        let record = serde_json::from_str::<serde_json::Value>(line).unwrap();
+        assert_eq!("doc", record["type"]);
+    }
+
+    let output = starlark(&["--docs=lsp", "--output=json"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("`--docs=lsp` does not support `--output=json`"));
+}
@@ -21,6 +21,7 @@ use syn::DeriveInput;
 use syn::Fields;
 
 use crate::util::DataEnumUtil;
+use crate::util::DataStructUtil;
 use crate::util::DeriveInputUtil;
 use crate::v_lifetime::find_v_lifetime;
 
@@ -51,11 +52,9 @@ fn derive_alloc_value_impl(
     which_trait: WhichTrait,
 ) -> syn::Result<proc_macro2::TokenStream> {
     let derive_input = DeriveInputUtil::new(&derive_input)?;
-    let DeriveInputUtil::Enum(en) = derive_input else {
-        return Err(syn::Error::new(
-            derive_input.span(),
-            "`AllocValue` can only be derived for enums",
-        ));
+    let body = match derive_input {
+        DeriveInputUtil::Enum(en) => alloc_value_body(en, which_trait)?,
+        DeriveInputUtil::Struct(st) => alloc_struct_body(st, which_trait)?,
     };
     let (_impl_generics, type_generics, where_clause) = derive_input.generics.split_for_impl();
 
@@ -72,8 +71,6 @@ fn derive_alloc_value_impl(
 
     let type_name = &derive_input.ident;
 
-    let body = alloc_value_body(en, which_trait)?;
-
     let item_impl: syn::ItemImpl = match which_trait {
         WhichTrait::AllocValue => {
             syn::parse_quote_spanned! {
@@ -139,3 +136,38 @@ fn alloc_value_body(en: DataEnumUtil, which_trait: WhichTrait) -> syn::Result<sy
         }
     })
 }
+
+/// Struct is allocated as a Starlark `struct` with an attribute per field.
+fn alloc_struct_body(st: DataStructUtil, which_trait: WhichTrait) -> syn::Result<syn::Expr> {
+    let Fields::Named(fields) = &st.fields else {
+        return Err(syn::Error::new(
+            st.derive_input.span(),
+            "`AllocValue` can only be derived for enums or structs with named fields",
+        ));
+    };
+    let names: Vec<&syn::Ident> = fields
+        .named
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap())
+        .collect();
+    let keys: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+    let len = names.len();
+    match which_trait {
+        WhichTrait::AllocValue => Ok(syn::parse_quote_spanned! {
+            st.derive_input.span() => {
+                let fields: [(&str, starlark::values::Value<'v>); #len] = [
+                    #((#keys, starlark::values::AllocValue::alloc_value(self.#names, heap)),)*
+                ];
+                heap.alloc(starlark::values::structs::AllocStruct(fields))
+            }
+        }),
+        WhichTrait::AllocFrozenValue => Ok(syn::parse_quote_spanned! {
+            st.derive_input.span() => {
+                let fields: [(&str, starlark::values::FrozenValue); #len] = [
+                    #((#keys, starlark::values::AllocFrozenValue::alloc_frozen_value(self.#names, heap)),)*
+                ];
+                heap.alloc(starlark::values::structs::AllocStruct(fields))
+            }
+        }),
+    }
+}
@@ -15,107 +15,93 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+use std::collections::HashSet;
+
 use proc_macro2::Span;
 use quote::quote;
-use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
+use syn::visit_mut::VisitMut;
 use syn::DeriveInput;
 
 use crate::util::DeriveInputUtil;
-use crate::util::GenericsUtil;
-
-fn punctuated_try_map<A, B, P: Clone>(
-    punctuated: &Punctuated<A, P>,
-    f: impl Fn(&A) -> syn::Result<B>,
-) -> syn::Result<Punctuated<B, P>> {
-    let mut new_punctuated = Punctuated::new();
-    for pair in punctuated.pairs() {
-        new_punctuated.push_value(f(pair.value())?);
-        if let Some(p) = pair.punct() {
-            new_punctuated.push_punct((*p).clone());
-        }
-    }
-    Ok(new_punctuated)
+
+/// Rewrites types and bounds mentioning the generic parameters of the derived type
+/// into the same types and bounds of its `StaticType`:
+/// lifetime parameters become `'static`, and type parameters `T` become `T::StaticType`
+/// (or stay `T` if `T: 'static`).
+struct StaticProjection<'a> {
+    lifetimes: &'a HashSet<syn::Ident>,
+    /// Type parameter name to its static projection.
+    type_params: &'a HashMap<syn::Ident, syn::Type>,
+    /// First error, reported after the visit.
+    error: Option<syn::Error>,
 }
 
-fn generic_argument_replace_lifetimes_with_static(
-    generic_argument: &syn::GenericArgument,
-) -> syn::Result<syn::GenericArgument> {
-    match generic_argument {
-        syn::GenericArgument::Lifetime(lifetime) => {
-            Ok(syn::parse_quote_spanned! { lifetime.span() => 'static })
+impl<'a> StaticProjection<'a> {
+    fn error(&mut self, error: syn::Error) {
+        if self.error.is_none() {
+            self.error = Some(error);
         }
-        a @ syn::GenericArgument::Const(..) => Ok(a.clone()),
-        c => Err(syn::Error::new_spanned(c, "unsupported generic argument")),
     }
-}
 
-fn path_arguments_replace_lifetimes_with_static(
-    path_arguments: &syn::PathArguments,
-) -> syn::Result<syn::PathArguments> {
-    Ok(match path_arguments {
-        syn::PathArguments::None => syn::PathArguments::None,
-        syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-            colon2_token,
-            lt_token,
-            args,
-            gt_token,
-        }) => syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {
-            colon2_token: *colon2_token,
-            lt_token: *lt_token,
-            args: punctuated_try_map(args, generic_argument_replace_lifetimes_with_static)?,
-            gt_token: *gt_token,
-        }),
-        syn::PathArguments::Parenthesized(_) => {
-            return Err(syn::Error::new_spanned(
-                path_arguments,
-                "unsupported path arguments",
-            ));
+    fn project<T>(&mut self, item: &T, visit: impl FnOnce(&mut Self, &mut T)) -> syn::Result<T>
+    where
+        T: Clone,
+    {
+        let mut item = item.clone();
+        visit(self, &mut item);
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(item),
         }
-    })
-}
-
-fn path_segment_replace_lifetimes_with_static(
-    path_segment: &syn::PathSegment,
-) -> syn::Result<syn::PathSegment> {
-    Ok(syn::PathSegment {
-        ident: path_segment.ident.clone(),
-        arguments: path_arguments_replace_lifetimes_with_static(&path_segment.arguments)?,
-    })
-}
+    }
 
-fn path_replace_lifetimes_with_static(path: &syn::Path) -> syn::Result<syn::Path> {
-    Ok(syn::Path {
-        leading_colon: path.leading_colon,
-        segments: punctuated_try_map(&path.segments, path_segment_replace_lifetimes_with_static)?,
-    })
+    /// Type parameter which starts the path, if any.
+    fn type_param_prefix(&self, path: &syn::Path) -> Option<&syn::Ident> {
+        let first = path.segments.first()?;
+        if path.leading_colon.is_some() || !first.arguments.is_none() {
+            return None;
+        }
+        self.type_params.get_key_value(&first.ident).map(|(k, _)| k)
+    }
 }
 
-/// Replace `X<'a>` with `X<'static>`.
-fn type_param_bound_replace_lifetimes_with_static(
-    bound: &syn::TypeParamBound,
-) -> syn::Result<syn::TypeParamBound> {
-    match bound {
-        syn::TypeParamBound::Lifetime(lifetime) => {
-            Ok(syn::parse_quote_spanned! { lifetime.span() =>
-                'static
-            })
+impl<'a> VisitMut for StaticProjection<'a> {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut syn::Lifetime) {
+        if self.lifetimes.contains(&lifetime.ident) {
+            *lifetime = syn::Lifetime::new("'static", lifetime.span());
         }
-        syn::TypeParamBound::Trait(trait_bound) => {
-            if trait_bound.lifetimes.is_some() {
-                return Err(syn::Error::new_spanned(
-                    trait_bound,
-                    "trait bounds with generic lifetimes are not supported",
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        match ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) => {
+                if let Some(param) = self.type_param_prefix(path) {
+                    if path.segments.len() == 1 {
+                        *ty = self.type_params[param].clone();
+                    } else {
+                        let message = format!(
+                            "cannot derive `ProvidesStaticType`: associated type of type parameter \
+                            `{param}` cannot be projected to `'static`, write it as \
+                            `<{param} as Trait>::Assoc` or implement `ProvidesStaticType` manually"
+                        );
+                        self.error(syn::Error::new_spanned(&*path, message));
+                    }
+                    return;
+                }
+            }
+            syn::Type::Macro(mac) => {
+                self.error(syn::Error::new_spanned(
+                    &*mac,
+                    "cannot derive `ProvidesStaticType`: macros in types of generic bounds \
+                    are not supported, implement `ProvidesStaticType` manually",
                 ));
+                return;
             }
-            Ok(syn::TypeParamBound::Trait(syn::TraitBound {
-                paren_token: trait_bound.paren_token,
-                modifier: trait_bound.modifier,
-                lifetimes: trait_bound.lifetimes.clone(),
-                path: path_replace_lifetimes_with_static(&trait_bound.path)?,
-            }))
+            _ => {}
         }
-        _ => Ok(bound.clone()),
+        syn::visit_mut::visit_type_mut(self, ty);
     }
 }
 
@@ -128,117 +114,149 @@ pub(crate) fn derive_provides_static_type(
     }
 }
 
-/// Single lifetime parameter for `ProvidesStaticType`
-fn pst_lifetime<'a>(generics: &'a syn::Generics) -> syn::Result<syn::Lifetime> {
-    let generics = GenericsUtil::new(generics);
-    let lifetime = generics
-        .assert_at_most_one_lifetime_param()?
-        .map(|p| p.lifetime.clone());
-    Ok(match lifetime {
-        Some(lifetime) => lifetime,
-        None => syn::parse_quote_spanned! { Span::call_site() => 'pst },
+fn has_static_lifetime_bound(param: &syn::TypeParam) -> bool {
+    param.bounds.iter().any(|bound| {
+        if let syn::TypeParamBound::Lifetime(lifetime) = bound {
+            lifetime.ident == "static"
+        } else {
+            false
+        }
     })
 }
 
+fn is_maybe_sized(bound: &syn::TypeParamBound) -> bool {
+    matches!(
+        bound,
+        syn::TypeParamBound::Trait(syn::TraitBound {
+            modifier: syn::TraitBoundModifier::Maybe(_),
+            ..
+        })
+    )
+}
+
 fn derive_provides_static_type_impl(input: proc_macro::TokenStream) -> syn::Result<syn::ItemImpl> {
     let input: DeriveInput = syn::parse(input)?;
     let input = DeriveInputUtil::new(&input)?;
 
     let span = input.ident.span();
-
     let name = &input.ident;
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
-
-    let lifetime = pst_lifetime(&input.generics)?;
-
-    let mut lifetimes: Vec<syn::Lifetime> = Vec::new();
-    let mut static_lifetimes: Vec<syn::Lifetime> = Vec::new();
-    let mut type_param_names = Vec::new();
-    let mut type_param_bounds = Vec::new();
-    let mut type_param_static_type_bounds = Vec::new();
-    let mut static_type_params = Vec::new();
-    let mut const_params = Vec::new();
-    let mut const_param_names = Vec::new();
-    for param in &input.generics.params {
-        match param {
-            syn::GenericParam::Lifetime(param) => {
-                lifetimes.push(param.lifetime.clone());
-                static_lifetimes.push(syn::parse_quote_spanned! { param.span() => 'static });
+
+    let lifetimes: Vec<syn::Lifetime> = input
+        .generics
+        .lifetimes()
+        .map(|p| p.lifetime.clone())
+        .collect();
+    let lifetime_idents: HashSet<syn::Ident> = lifetimes.iter().map(|l| l.ident.clone()).collect();
+    let mut type_params: HashMap<syn::Ident, syn::Type> = HashMap::new();
+    for param in input.generics.type_params() {
+        let param_name = &param.ident;
+        let projection = if has_static_lifetime_bound(param) {
+            syn::parse_quote_spanned! { param.span() => #param_name }
+        } else {
+            syn::parse_quote_spanned! { param.span() => #param_name :: StaticType }
+        };
+        type_params.insert(param_name.clone(), projection);
+    }
+    let mut projection = StaticProjection {
+        lifetimes: &lifetime_idents,
+        type_params: &type_params,
+        error: None,
+    };
+
+    // With a single lifetime parameter, `ProvidesStaticType` is implemented for that lifetime,
+    // otherwise for a fresh lifetime all the lifetime parameters outlive.
+    let (lifetime, fresh_lifetime) = match lifetimes.as_slice() {
+        [lifetime] => (lifetime.clone(), false),
+        _ => (syn::Lifetime::new("'pst", Span::call_site()), true),
+    };
+
+    let mut generics = input.generics.clone();
+    let mut static_predicates: Vec<syn::WherePredicate> = Vec::new();
+    for param in generics.type_params_mut() {
+        param.default = None;
+        param.eq_token = None;
+        let param_name = &param.ident;
+        let maybe_sized = param.bounds.iter().any(is_maybe_sized);
+        let mut static_bounds = Vec::new();
+        for bound in &param.bounds {
+            if !is_maybe_sized(bound) {
+                static_bounds
+                    .push(projection.project(bound, |p, b| p.visit_type_param_bound_mut(b))?);
             }
-            syn::GenericParam::Type(param) => {
-                let has_static_lifetime_bound = param.bounds.iter().any(|bound| {
-                    if let syn::TypeParamBound::Lifetime(lifetime) = bound {
-                        lifetime.ident == "static"
-                    } else {
-                        false
-                    }
+        }
+        if !maybe_sized {
+            static_bounds.push(syn::parse_quote_spanned! { param.span() => Sized });
+        }
+        let static_type = &type_params[param_name];
+        if !static_bounds.is_empty() {
+            static_predicates.push(syn::parse_quote_spanned! { param.span() =>
+                #static_type : #(#static_bounds)+*
+            });
+        }
+        if !has_static_lifetime_bound(param) {
+            param
+                .bounds
+                .push(syn::parse_quote_spanned! { param.span() =>
+                    starlark::any::ProvidesStaticType<#lifetime>
                 });
-
-                let param_name = &param.ident;
-                let param_bounds = param.bounds.iter();
-                if has_static_lifetime_bound {
-                    type_param_bounds.push(quote! {
-                        #param_name : #(#param_bounds+)* Sized
-                    });
-                    let param_bounds = param
-                        .bounds
-                        .iter()
-                        .map(type_param_bound_replace_lifetimes_with_static)
-                        .collect::<syn::Result<Vec<_>>>()?;
-                    type_param_static_type_bounds.push(quote! {
-                        #param_name : #(#param_bounds+)* Sized
-                    });
-                    static_type_params.push(quote! { #param_name});
-                } else {
-                    type_param_bounds.push(quote! {
-                        #param_name : #(#param_bounds+)* starlark::any::ProvidesStaticType<#lifetime> + Sized
-                    });
-                    let param_bounds = param
+        }
+    }
+    if let Some(where_clause) = &input.generics.where_clause {
+        for predicate in &where_clause.predicates {
+            match predicate {
+                syn::WherePredicate::Type(predicate) => {
+                    let bounds = predicate
                         .bounds
                         .iter()
-                        .map(type_param_bound_replace_lifetimes_with_static)
+                        .filter(|b| !is_maybe_sized(b))
+                        .map(|b| projection.project(b, |p, b| p.visit_type_param_bound_mut(b)))
                         .collect::<syn::Result<Vec<_>>>()?;
-                    type_param_static_type_bounds.push(quote! {
-                        #param_name :: StaticType : #(#param_bounds+)* Sized
+                    if bounds.is_empty() {
+                        continue;
+                    }
+                    let lifetimes = &predicate.lifetimes;
+                    let bounded_ty =
+                        projection.project(&predicate.bounded_ty, |p, t| p.visit_type_mut(t))?;
+                    static_predicates.push(syn::parse_quote_spanned! { predicate.span() =>
+                        #lifetimes #bounded_ty : #(#bounds)+*
                     });
-                    static_type_params.push(quote! { #param_name :: StaticType });
                 }
-                type_param_names.push(param.ident.clone());
-            }
-            syn::GenericParam::Const(params) => {
-                const_params.push(params.clone());
-                const_param_names.push(params.ident.clone());
+                // Lifetimes of the static type are all `'static`.
+                syn::WherePredicate::Lifetime(_) => {}
+                p => {
+                    return Err(syn::Error::new_spanned(
+                        p,
+                        "cannot derive `ProvidesStaticType`: unsupported where predicate, \
+                        implement `ProvidesStaticType` manually",
+                    ));
+                }
             }
         }
     }
-
-    Ok(if input.generics.lt_token.is_none() {
-        syn::parse_quote_spanned! { span =>
-            unsafe impl<#lifetime> #impl_generics starlark::any::ProvidesStaticType<#lifetime> for #name #ty_generics #where_clause {
-                type StaticType = #name #ty_generics;
-            }
+    if fresh_lifetime {
+        let where_clause = generics.make_where_clause();
+        for l in &lifetimes {
+            where_clause
+                .predicates
+                .push(syn::parse_quote_spanned! { l.span() => #l : #lifetime });
         }
-    } else {
-        syn::parse_quote_spanned! { span =>
-            unsafe impl <
-                #lifetime,
-                #(#type_param_bounds,)*
-                #(#const_params,)*
-                    > starlark::any::ProvidesStaticType<#lifetime>
-            for #name <
-                #(#lifetimes,)*
-                #(#type_param_names,)*
-                #(#const_param_names,)*
-                    > #where_clause
-            where
-                #(#type_param_static_type_bounds,)*
-            {
-                type StaticType = #name <
-                    #(#static_lifetimes,)*
-                    #(#static_type_params,)*
-                    #(#const_param_names,)*
-                        >;
-            }
+        generics
+            .params
+            .insert(0, syn::parse_quote_spanned! { span => #lifetime });
+    }
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(static_predicates);
+
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let self_ty: syn::Type = syn::parse_quote_spanned! { span => #name #ty_generics };
+    let static_type = projection.project(&self_ty, |p, t| p.visit_type_mut(t))?;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    Ok(syn::parse_quote_spanned! { span =>
+        unsafe impl #impl_generics starlark::any::ProvidesStaticType<#lifetime> for #self_ty #where_clause {
+            type StaticType = #static_type;
         }
     })
 }
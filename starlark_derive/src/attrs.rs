@@ -38,14 +38,15 @@ pub fn derive_attrs(input: TokenStream) -> TokenStream {
         .into()
 }
 
-struct Field {
-    ident: Ident,
+pub(crate) struct Field {
+    pub(crate) ident: Ident,
     starlark_args: Vec<Ident>,
-    ty: Type,
+    pub(crate) ty: Type,
 }
 
 impl Field {
-    fn name(&self) -> String {
+    /// Attribute name, without `r#` prefix.
+    pub(crate) fn name(&self) -> String {
         let value = self.ident.to_string();
         value.strip_prefix("r#").unwrap_or(&value).to_owned()
     }
@@ -93,6 +94,12 @@ impl Field {
 }
 
 fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStream> {
+    let expose_fields = exposed_fields(data)?;
+    Ok(expand_attrs_impl(&name, &expose_fields))
+}
+
+/// Fields exported as attributes: all fields not marked with `#[starlark(skip)]`.
+pub(crate) fn exposed_fields(data: Data) -> Result<Vec<Field>> {
     let fields: Vec<_> = match data {
         Data::Struct(s) => Ok(s.fields.iter().cloned().collect()),
         Data::Enum(e) => Err(Error::new(
@@ -130,7 +137,11 @@ fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStre
         })
         .filter(|f| f.as_ref().map(|f| !f.skip()).unwrap_or(true))
         .collect::<Result<_>>()?;
+    Ok(expose_fields)
+}
 
+/// Generate `attrs_{has,get,dir}_attr` methods for the given fields.
+pub(crate) fn expand_attrs_impl(name: &Ident, expose_fields: &[Field]) -> proc_macro2::TokenStream {
     let has_attr_items = expose_fields.iter().map(|f| f.has_attr_match_item());
     let has_attr = quote! {
         pub(crate) fn attrs_has_attr(&self, attr: &str) -> bool {
@@ -160,7 +171,7 @@ fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStre
         }
     };
 
-    let expanded = quote! {
+    quote! {
         // Unfortunately, we can't actually implement the direct methods for
         // `StarlarkValue`, because then we would have conflicting
         // implementations. However, we can implement wrappers in another
@@ -170,9 +181,7 @@ fn expand_attrs_derive(data: Data, name: Ident) -> Result<proc_macro2::TokenStre
             #get_attr
             #dir_attr
         }
-    };
-
-    Ok(expanded)
+    }
 }
 
 fn field_attr<'a, I: ?Sized>(field: &'a syn::Field, path: &I) -> Option<&'a Attribute>
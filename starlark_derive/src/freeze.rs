@@ -15,6 +15,8 @@
  * limitations under the License.
  */
 
+use std::collections::HashSet;
+
 use proc_macro2::Ident;
 use proc_macro2::TokenStream;
 use quote::quote;
@@ -22,18 +24,25 @@ use quote::quote_spanned;
 use syn::parse::ParseStream;
 use syn::parse_macro_input;
 use syn::spanned::Spanned;
+use syn::visit;
+use syn::visit::Visit;
 use syn::Attribute;
+use syn::Data;
 use syn::DeriveInput;
-use syn::Error;
+use syn::Field;
 use syn::GenericParam;
 use syn::LitStr;
 use syn::Token;
+use syn::Type;
 use syn::WherePredicate;
 
 use crate::util::DeriveInputUtil;
+use crate::util::StructOrEnumVariant;
 
 struct Input<'a> {
     input: &'a DeriveInput,
+    /// Type parameters which are frozen, see [`frozen_type_params`].
+    frozen_params: HashSet<Ident>,
 }
 
 impl<'a> Input<'a> {
@@ -59,7 +68,7 @@ impl<'a> Input<'a> {
         }
         for param in &self.input.generics.params {
             match param {
-                GenericParam::Type(t) => {
+                GenericParam::Type(t) if self.frozen_params.contains(&t.ident) => {
                     let name = &t.ident;
                     let bounds = t.bounds.iter();
                     impl_params.push(quote_spanned! {
@@ -75,13 +84,25 @@ impl<'a> Input<'a> {
                         <#name as starlark::values::Freeze>::Frozen
                     });
                 }
+                GenericParam::Type(t) => {
+                    // Not frozen: the frozen type has the same parameter.
+                    let name = &t.ident;
+                    let bounds = &t.bounds;
+                    impl_params.push(quote_spanned! { span=> #name: #bounds });
+                    input_params.push(quote_spanned! { span=> #name });
+                    output_params.push(quote_spanned! { span=> #name });
+                }
                 GenericParam::Lifetime(lt) => {
                     impl_params.push(quote_spanned! { span=> #lt });
                     input_params.push(quote_spanned! { span=> #lt });
                     output_params.push(quote_spanned! { span=> 'static });
                 }
-                GenericParam::Const(_) => {
-                    return Err(Error::new_spanned(param, "const generics not supported"));
+                GenericParam::Const(c) => {
+                    let name = &c.ident;
+                    let ty = &c.ty;
+                    impl_params.push(quote_spanned! { span=> const #name: #ty });
+                    input_params.push(quote_spanned! { span=> #name });
+                    output_params.push(quote_spanned! { span=> #name });
                 }
             }
         }
@@ -95,7 +116,10 @@ impl<'a> Input<'a> {
 
 fn derive_freeze_impl(input: DeriveInput) -> syn::Result<syn::ItemImpl> {
     let span = input.span();
-    let input = Input { input: &input };
+    let input = Input {
+        frozen_params: frozen_type_params(&input)?,
+        input: &input,
+    };
 
     let name = &input.input.ident;
 
@@ -106,7 +130,7 @@ fn derive_freeze_impl(input: DeriveInput) -> syn::Result<syn::ItemImpl> {
     let validate_body = match opts.validator {
         Some(validator) => quote_spanned! {
             span=>
-            #validator(&frozen)?;
+            starlark::values::FreezeValidator::validate(&#validator, &frozen, freezer)?;
         },
         None => quote_spanned! { span=> },
     };
@@ -183,47 +207,164 @@ fn extract_options(attrs: &[Attribute]) -> syn::Result<FreezeDeriveOptions> {
     Ok(opts)
 }
 
-/// Parse attribute `#[freeze(identity)]`.
-///
-/// Currently it fails on any attribute argument other than `id`.
-fn is_identity(attrs: &[Attribute]) -> syn::Result<bool> {
+/// How a field is frozen.
+enum FreezeField {
+    /// Field is frozen with `Freeze::freeze`.
+    Freeze,
+    /// `#[freeze(identity)]`: field is moved to the frozen value as is.
+    Identity,
+    /// `#[freeze(skip)]` or `#[freeze(default = expr)]`: field is dropped,
+    /// and the frozen field is `Default::default()` or given expression.
+    Skip(Option<syn::Expr>),
+    /// `#[freeze(with = "path")]`: field is frozen with given function.
+    With(syn::Path),
+    /// Field of type `PhantomData` without options: frozen field is a new `PhantomData`,
+    /// and its type parameters get no `Freeze` bound, like serde does.
+    Phantom,
+}
+
+/// Parse field attributes `#[freeze(identity)]`, `#[freeze(skip)]`,
+/// `#[freeze(default = expr)]` and `#[freeze(with = "path")]`.
+fn extract_field_options(attrs: &[Attribute]) -> syn::Result<FreezeField> {
     syn::custom_keyword!(identity);
+    syn::custom_keyword!(skip);
+    syn::custom_keyword!(default);
+    syn::custom_keyword!(with);
+
+    let mut field = FreezeField::Freeze;
 
     for attr in attrs.iter() {
         if !attr.path().is_ident("freeze") {
             continue;
         }
 
-        let ignore = attr.parse_args_with(|input: ParseStream| {
-            let ignore = input.parse::<Option<identity>>()?.is_some();
-            Ok(ignore)
+        attr.parse_args_with(|input: ParseStream| {
+            if !matches!(field, FreezeField::Freeze) {
+                return Err(input.error("only one `freeze` option can be set on a field"));
+            }
+            if input.parse::<identity>().is_ok() {
+                field = FreezeField::Identity;
+            } else if input.parse::<skip>().is_ok() {
+                field = FreezeField::Skip(None);
+            } else if input.parse::<default>().is_ok() {
+                input.parse::<Token![=]>()?;
+                field = FreezeField::Skip(Some(input.parse()?));
+            } else if input.parse::<with>().is_ok() {
+                input.parse::<Token![=]>()?;
+                field = FreezeField::With(input.parse::<LitStr>()?.parse()?);
+            } else {
+                return Err(input.lookahead1().error());
+            }
+            Ok(())
         })?;
+    }
 
-        if !ignore {
-            continue;
+    Ok(field)
+}
+
+/// How a field is frozen: given by its attributes, or special for `PhantomData`.
+fn field_freeze(field: &Field) -> syn::Result<FreezeField> {
+    let freeze = extract_field_options(&field.attrs)?;
+    if let (FreezeField::Freeze, Type::Path(ty)) = (&freeze, &field.ty) {
+        if ty.qself.is_none()
+            && ty
+                .path
+                .segments
+                .last()
+                .is_some_and(|s| s.ident == "PhantomData")
+        {
+            return Ok(FreezeField::Phantom);
         }
+    }
+    Ok(freeze)
+}
+
+/// Type parameters which appear in the types of fields frozen with `Freeze::freeze`
+/// or `#[freeze(with = "path")]`, like serde infers bounds from field types.
+///
+/// Other type parameters, like parameters only used in `PhantomData`,
+/// `#[freeze(identity)]` or skipped fields, get no `Freeze` bound,
+/// and are the same in the frozen type.
+fn frozen_type_params(input: &DeriveInput) -> syn::Result<HashSet<Ident>> {
+    struct VisitParams<'a> {
+        params: Vec<&'a Ident>,
+        frozen: HashSet<Ident>,
+    }
 
-        return Ok(true);
+    impl<'a, 'ast> Visit<'ast> for VisitParams<'a> {
+        fn visit_path(&mut self, path: &'ast syn::Path) {
+            if path.leading_colon.is_none() {
+                if let Some(first) = path.segments.first() {
+                    if let Some(param) = self.params.iter().find(|p| **p == &first.ident) {
+                        self.frozen.insert((*param).clone());
+                    }
+                }
+            }
+            visit::visit_path(self, path);
+        }
     }
 
-    Ok(false)
+    let mut visit = VisitParams {
+        params: input.generics.type_params().map(|t| &t.ident).collect(),
+        frozen: HashSet::new(),
+    };
+    let fields: Vec<&Field> = match &input.data {
+        Data::Struct(s) => s.fields.iter().collect(),
+        Data::Enum(e) => e.variants.iter().flat_map(|v| &v.fields).collect(),
+        Data::Union(_) => Vec::new(),
+    };
+    for field in fields {
+        match field_freeze(field)? {
+            FreezeField::Freeze | FreezeField::With(_) => visit.visit_type(&field.ty),
+            FreezeField::Identity | FreezeField::Skip(_) | FreezeField::Phantom => {}
+        }
+    }
+    Ok(visit.frozen)
 }
 
 fn freeze_impl(derive_input: &DeriveInput) -> syn::Result<syn::Expr> {
     let derive_input = DeriveInputUtil::new(derive_input)?;
+    let type_name = &derive_input.ident;
     derive_input.match_self(|struct_or_enum_variant, fields| {
+        let owner = match struct_or_enum_variant {
+            StructOrEnumVariant::Struct(_) => type_name.to_string(),
+            StructOrEnumVariant::EnumVariant(variant) => {
+                format!("{}::{}", type_name, variant.ident)
+            }
+        };
         let fields: Vec<syn::Expr> = fields
             .iter()
-            .map(|(ident, f)| {
+            .enumerate()
+            .map(|(i, (ident, f))| {
                 let span = ident.span();
-                if is_identity(&f.attrs)? {
-                    Ok(syn::parse_quote_spanned! { span=>
+                let field = match &f.ident {
+                    Some(name) => format!("{}.{}", owner, name),
+                    None => format!("{}.{}", owner, i),
+                };
+                match field_freeze(f)? {
+                    FreezeField::Freeze => Ok(syn::parse_quote_spanned! { span=>
+                        starlark::values::Freeze::freeze(#ident, freezer).map_err(|e| {
+                            starlark::values::FreezeError::with_field(e, #field)
+                        })?
+                    }),
+                    FreezeField::With(with) => Ok(syn::parse_quote_spanned! { span=>
+                        #with(#ident, freezer).map_err(|e| {
+                            starlark::values::FreezeError::with_field(e, #field)
+                        })?
+                    }),
+                    FreezeField::Identity => Ok(syn::parse_quote_spanned! { span=>
                         #ident
-                    })
-                } else {
-                    Ok(syn::parse_quote_spanned! { span=>
-                        starlark::values::Freeze::freeze(#ident, freezer)?
-                    })
+                    }),
+                    // Skipped field is dropped with the rest of unfrozen value.
+                    FreezeField::Skip(None) => Ok(syn::parse_quote_spanned! { span=>
+                        std::default::Default::default()
+                    }),
+                    FreezeField::Skip(Some(default)) => Ok(syn::parse_quote_spanned! { span=>
+                        #default
+                    }),
+                    FreezeField::Phantom => Ok(syn::parse_quote_spanned! { span=>
+                        std::marker::PhantomData
+                    }),
                 }
             })
             .collect::<syn::Result<_>>()?;
@@ -31,8 +31,10 @@ mod docs;
 mod freeze;
 mod module;
 mod serde;
+mod starlark_methods;
 mod starlark_type_repr;
 mod starlark_value;
+mod thaw;
 mod trace;
 mod unpack_value;
 mod util;
@@ -62,10 +64,19 @@ mod vtable;
 /// parameter name:
 ///
 /// * `#[starlark(default = "a default")]` - provide a deafult for the parameter if it is omitted.
+///   For a `Value` parameter, the default can be any expression implementing `AllocFrozenValue`,
+///   including empty containers `vec![]` and `SmallMap::new()`. It is evaluated once,
+///   when the module is built, and allocated on the frozen heap, so like in Starlark `def f(x = [])`,
+///   the default is shared between calls, but unlike in Starlark, it cannot be mutated.
 /// * `#[starlark(require = pos)]` - require the parameter to be passed by position, not named.
+///   Positional-only parameters must come first, like parameters before `/` in Python.
 /// * `#[starlark(require = named)]` - require the parameter to be passed by name, not by position.
+///   Like parameters after `*` in Python, all following parameters are named-only too.
 /// * `#[starlark(args)]` - treat the argument as `*args` in Starlark, receiving all additional positional arguments as a tuple.
 /// * `#[starlark(kwargs)]` - treat the argument as `**kwargs` in Starlark, receiving all additional named arguments as a dictionary.
+/// * `#[starlark(ty = T)]` - on a `Value` parameter, declare its Starlark type as that of the Rust type `T`
+///   (e.g. `UnpackList<String>` for `list[str]`, or a type alias), used for documentation and by the typechecker.
+///   In debug builds the argument is also checked against this type at runtime.
 ///
 /// There are a number of attributes that can be applied to the entire function by writing attributes
 /// before the `fn` of the function:
@@ -73,6 +84,8 @@ mod vtable;
 /// * `#[starlark(attribute_type = "foo")]` - if the function has `.type` applied, return this string. Usually used on
 ///   constructor functions so that `ctor.type` can be used in Starlark code.
 /// * `#[starlark(return_type = "foo")]` - the return type of the function used for documention.
+/// * `#[starlark(return_ty = T)]` - declare the Starlark return type as that of the Rust type `T`,
+///   for documentation and the typechecker. In debug builds the returned value is checked against it.
 /// * `#[starlark(speculative_exec_safe)]` - the function
 ///   is considered safe to execute speculatively: the function should have
 ///   no global side effects, should not panic, and should finish in reasonable time.
@@ -84,6 +97,23 @@ mod vtable;
 /// Multiple attributes can be specified either separately `#[starlark(require = named)] #[starlark(default = "")]` or
 /// separated with a comman `#[starlark(require = named, default = "")]`.
 ///
+/// Doc comments on functions become their documentation, with `# Arguments` and `# Returns`
+/// sections describing parameters and the return value. Parameters can also be documented
+/// with doc comments written directly on them.
+///
+/// Functions return `anyhow::Result<T>` or `starlark::Result<T>`, where `T` is allocated with `AllocValue`.
+/// Functions can also return `Result<T, E>` with their own error type `E` implementing
+/// `Into<starlark::Error>` (attributes cannot). Such an error is preserved when the evaluation fails,
+/// and can be recovered with `starlark::Error::downcast_ref::<E>()`.
+/// Infallible functions may instead return `Option<T>` directly, in which case `None` is returned
+/// to Starlark as `None`. A fallible function returning `anyhow::Result<Option<T>>` behaves the same way
+/// for `Ok(None)`, and `Err` is still reported as an error.
+///
+/// Functions can be declared `async fn`, and `.await` futures, for example to do I/O.
+/// When such a function is called, the evaluator is suspended until the future completes,
+/// running it with the executor installed with `Evaluator::set_async_executor`.
+/// Attributes cannot be `async`.
+///
 /// There are two special arguments, distinguished by their type, which provides access to interpreter state:
 ///
 /// * `heap: &'v Heap` gives access to the Starlark heap, for allocating things.
@@ -152,36 +182,155 @@ pub fn derive_freeze(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     freeze::derive_freeze(input)
 }
 
+/// Derive the `Thaw` trait.
+#[proc_macro_derive(Thaw, attributes(thaw))]
+pub fn derive_thaw(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    thaw::derive_thaw(input)
+}
+
 /// Derive the `NoSerialize` trait for serde.
 #[proc_macro_derive(NoSerialize)]
 pub fn derive_no_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     serde::derive_no_serialize(input)
 }
 
+/// Derive the `Serialize` trait for serde from the fields of a struct or an enum,
+/// so the value can be converted to JSON (for example with `json.encode`).
+///
+/// The output is the same as with `#[derive(serde::Serialize)]`: structs with named fields
+/// are serialized as maps, tuple structs as arrays (or as the field for a single field),
+/// and enum variants are tagged with their names.
+/// For complex values generic over `V: ValueLike`, fields of type `V` are serialized
+/// as the values they point to.
+///
+/// Fields can be annotated with `#[serialize(skip)]` to leave them out,
+/// and fields and enum variants with `#[serialize(rename = "name")]` to use a different name.
+/// Unlike `#[derive(serde::Serialize)]`, this does not require a dependency on `serde`.
+#[proc_macro_derive(StarlarkSerialize, attributes(serialize))]
+pub fn derive_starlark_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    serde::derive_starlark_serialize(input)
+}
+
 /// Derive the `StarlarkTypeRepr` trait.
+///
+/// Can be derived for enums with single field variants (type is a union of field types),
+/// or for structs with named fields (type is `struct`).
 #[proc_macro_derive(StarlarkTypeRepr)]
 pub fn derive_starlark_type_repr(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     starlark_type_repr::derive_starlark_type_repr(input)
 }
 
 /// Derive the `UnpackValue` trait.
-#[proc_macro_derive(UnpackValue)]
+///
+/// For enums with single field variants, the first variant which unpacks is used.
+/// Structs with named fields are unpacked from a Starlark `struct`,
+/// or from a dict with string keys, with an entry per field.
+/// Their type stays `struct`, so dicts are only accepted when evaluated.
+/// Entries without a corresponding field are an error.
+///
+/// Fields can be annotated with `#[unpack(default)]` to use `Default::default()`
+/// when the entry is missing, or with `#[unpack(rename = "name")]` to use a different key:
+///
+/// ```ignore
+/// #[derive(StarlarkTypeRepr, UnpackValue)]
+/// struct Config {
+///     name: String,
+///     #[unpack(default)]
+///     jobs: u32,
+///     #[unpack(rename = "type")]
+///     ty: String,
+/// }
+/// ```
+#[proc_macro_derive(UnpackValue, attributes(unpack))]
 pub fn derive_unpack_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     unpack_value::derive_unpack_value(input)
 }
 
 /// Derive the `AllocValue` trait.
+///
+/// For enums with single field variants, the value of the variant is allocated.
+/// Structs with named fields are allocated as Starlark `struct` values,
+/// with an attribute per field:
+///
+/// ```ignore
+/// #[derive(StarlarkTypeRepr, AllocValue)]
+/// struct Config {
+///     name: String,
+///     jobs: u32,
+/// }
+///
+/// let heap = Heap::new();
+/// let config = heap.alloc(Config {
+///     name: "release".to_owned(),
+///     jobs: 4,
+/// });
+/// assert_eq!("struct(name=\"release\", jobs=4)", config.to_repr());
+/// ```
 #[proc_macro_derive(AllocValue)]
 pub fn derive_alloc_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     alloc_value::derive_alloc_value(input)
 }
 
 /// Derive the `AllocFrozenValue` trait.
+///
+/// Supports the same types as [`AllocValue`](macro@AllocValue).
 #[proc_macro_derive(AllocFrozenValue)]
 pub fn derive_alloc_frozen_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     alloc_value::derive_alloc_frozen_value(input)
 }
 
+/// Derive the `StarlarkValue` trait for a simple struct (without lifetimes or type parameters),
+/// so it can be allocated on a heap and passed to Starlark.
+///
+/// All fields not marked with `#[starlark(skip)]` are attributes,
+/// like with [`StarlarkAttrs`](macro@StarlarkAttrs).
+/// The struct can be annotated with:
+///
+/// * `#[starlark(type = "name")]` - the Starlark type name, by default the name of the struct.
+/// * `#[starlark(methods)]` - use the methods defined in an `impl` block
+///   annotated with [`#[starlark_methods]`](macro@starlark_methods).
+///
+/// Documentation of the type is generated from doc comments of the struct, its fields and methods.
+/// The struct must also implement `Debug`, `Display`, `ProvidesStaticType`, `Allocative`
+/// and `Serialize` (or derive `NoSerialize`), and fields must be `Clone` and `AllocValue`.
+///
+/// ```ignore
+/// /// Settings of a build.
+/// #[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative, StarlarkValue)]
+/// #[display(fmt = "config({})", name)]
+/// #[starlark(type = "config", methods)]
+/// struct Config {
+///     /// Name of the configuration.
+///     name: String,
+///     #[starlark(skip)]
+///     secret: String,
+/// }
+///
+/// #[starlark_methods]
+/// impl Config {
+///     /// Name with the given suffix.
+///     fn with_suffix(&self, suffix: &str) -> anyhow::Result<String> {
+///         Ok(format!("{}{}", self.name, suffix))
+///     }
+/// }
+/// ```
+#[proc_macro_derive(StarlarkValue, attributes(starlark))]
+pub fn derive_starlark_value(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    starlark_value::derive_starlark_value_struct(input)
+}
+
+/// Expose the methods of an inherent `impl` block to Starlark,
+/// for types deriving [`StarlarkValue`](macro@StarlarkValue) with `#[starlark(methods)]`.
+///
+/// Each function taking `&self` becomes a Starlark method, other functions are left as is.
+/// Parameters and functions accept the same `#[starlark(...)]` annotations
+/// as in [`#[starlark_module]`](macro@starlark_module),
+/// e.g. `#[starlark(require = named)]` or `#[starlark(attribute)]`.
+#[proc_macro_attribute]
+pub fn starlark_methods(attr: TokenStream, input: TokenStream) -> TokenStream {
+    starlark_methods::starlark_methods(attr, input)
+}
+
 /// Derive accessor methods that are designed to be used from {has,get,dir}_attr
 /// in an `impl StarlarkValue` block. All fields in the struct that are not
 /// marked with #[starlark(skip)] are exported to Starlark code as attributes.
@@ -233,8 +382,13 @@ pub fn starlark_value(
     starlark_value::derive_starlark_value(attr, input)
 }
 
-/// Derive the `ProvidesStaticType` trait. Requires the type has no type arguments, no constant arguments,
-/// and at most one lifetime argument.
+/// Derive the `ProvidesStaticType` trait.
+///
+/// `StaticType` is the same type with all the lifetime arguments replaced with `'static`,
+/// and type arguments `T` replaced with `T::StaticType` (or kept as is if `T: 'static`).
+/// Bounds of type parameters and where clauses are rewritten the same way.
+/// Bounds mentioning associated types like `T::Assoc` are not supported,
+/// use `<T as Trait>::Assoc` instead.
 #[proc_macro_derive(ProvidesStaticType)]
 pub fn derive_provides_static_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     any_lifetime::derive_provides_static_type(input)
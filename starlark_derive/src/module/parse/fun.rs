@@ -21,6 +21,7 @@ use syn::parse::ParseStream;
 use syn::spanned::Spanned;
 use syn::visit::Visit;
 use syn::Attribute;
+use syn::Block;
 use syn::Expr;
 use syn::FnArg;
 use syn::GenericArgument;
@@ -47,6 +48,7 @@ use crate::module::typ::StarAttr;
 use crate::module::typ::StarFun;
 use crate::module::typ::StarFunSource;
 use crate::module::typ::StarStmt;
+use crate::module::util::is_type_name;
 use crate::util::GenericsUtil;
 
 #[derive(Default)]
@@ -55,6 +57,8 @@ struct FnAttrs {
     as_type: Option<syn::Path>,
     starlark_ty_custom_function: Option<Expr>,
     special_builtin_function: Option<Expr>,
+    /// `#[starlark(return_ty = T)]`.
+    return_ty: Option<Type>,
     speculative_exec_safe: bool,
     docstring: Option<String>,
     /// Rest attributes
@@ -64,11 +68,15 @@ struct FnAttrs {
 #[derive(Default)]
 struct FnParamAttrs {
     default: Option<Expr>,
+    /// `#[starlark(ty = T)]`.
+    ty: Option<Type>,
     this: bool,
     pos_only: bool,
     named_only: bool,
     args: bool,
     kwargs: bool,
+    /// Doc comment on the parameter.
+    docstring: Option<String>,
     unused_attrs: Vec<Attribute>,
 }
 
@@ -95,6 +103,10 @@ fn parse_starlark_fn_param_attr(
                 parser.parse::<Token![=]>()?;
                 param_attrs.default = Some(parser.parse::<Expr>()?);
                 continue;
+            } else if ident == "ty" {
+                parser.parse::<Token![=]>()?;
+                param_attrs.ty = Some(parser.parse::<Type>()?);
+                continue;
             } else if ident == "this" {
                 param_attrs.this = true;
                 continue;
@@ -122,6 +134,7 @@ fn parse_starlark_fn_param_attr(
                 `#[starlark(default = expr)]`, \
                 `#[starlark(require = pos)]`, \
                 `#[starlark(require = named)]`, \
+                `#[starlark(ty = T)]`, \
                 `#[starlark(this)]` attribute",
             ));
         }
@@ -130,12 +143,21 @@ fn parse_starlark_fn_param_attr(
     tokens.parse_args_with(parse)
 }
 
-/// Parse fn param attributes: parse `#[starlark(...)]` and take others as is.
+/// Parse fn param attributes: parse `#[starlark(...)]` and doc comments, and take others as is.
 fn parse_fn_param_attrs(attrs: Vec<Attribute>) -> syn::Result<FnParamAttrs> {
     let mut param_attrs = FnParamAttrs::default();
     for attr in attrs {
         if attr.path().is_ident("starlark") {
             parse_starlark_fn_param_attr(&attr, &mut param_attrs)?;
+        } else if let Some(ds) = is_attribute_docstring(&attr) {
+            // Rust does not allow doc comments on parameters, so they are not passed through.
+            match &mut param_attrs.docstring {
+                None => param_attrs.docstring = Some(ds),
+                Some(docstring) => {
+                    docstring.push('\n');
+                    docstring.push_str(&ds);
+                }
+            }
         } else {
             param_attrs.unused_attrs.push(attr);
         }
@@ -173,6 +195,10 @@ fn parse_starlark_fn_attr(tokens: &Attribute, attrs: &mut FnAttrs) -> syn::Resul
                 parser.parse::<Token![=]>()?;
                 attrs.starlark_ty_custom_function = Some(parser.parse::<Expr>()?);
                 continue;
+            } else if ident == "return_ty" {
+                parser.parse::<Token![=]>()?;
+                attrs.return_ty = Some(parser.parse::<Type>()?);
+                continue;
             } else if ident == "special_builtin_function" {
                 parser.parse::<Token![=]>()?;
                 attrs.special_builtin_function = Some(parser.parse::<Expr>()?);
@@ -183,6 +209,7 @@ fn parse_starlark_fn_attr(tokens: &Attribute, attrs: &mut FnAttrs) -> syn::Resul
                 "Expecting \
                     `#[starlark(as_type = ImplStarlarkValue)]`, \
                     `#[starlark(ty_custom_function = MyTy)]`, \
+                    `#[starlark(return_ty = T)]`, \
                     `#[starlark(attribute)]`, \
                     `#[starlark(speculative_exec_safe)]` attribute",
             ));
@@ -255,11 +282,35 @@ fn is_anyhow_or_starlark_result(t: &Type) -> bool {
     matches!(t, GenericArgument::Type(_))
 }
 
+/// Is the type `Result<T, E>` with an explicit error type, like `Result<T, MyError>`
+/// or `std::result::Result<T, MyError>`.
+fn is_result_with_error(t: &Type) -> bool {
+    let path = match t {
+        Type::Path(p) if p.qself.is_none() => p,
+        _ => return false,
+    };
+    let result = match path.path.segments.last() {
+        Some(s) if s.ident == "Result" => s,
+        _ => return false,
+    };
+    match &result.arguments {
+        PathArguments::AngleBracketed(args) => {
+            args.args.len() == 2
+                && args
+                    .args
+                    .iter()
+                    .all(|a| matches!(a, GenericArgument::Type(_)))
+        }
+        _ => false,
+    }
+}
+
 // Add a function to the `GlobalsModule` named `globals_builder`.
 pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<StarStmt> {
     parse_visibility(&func.vis)?;
 
     let sig_span = func.sig.span();
+    let is_async = func.sig.asyncness.is_some();
 
     let FnAttrs {
         is_attribute,
@@ -268,21 +319,30 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
         docstring,
         starlark_ty_custom_function,
         special_builtin_function,
+        return_ty,
         attrs,
     } = parse_fn_attrs(func.span(), func.attrs)?;
 
     let has_v = parse_fn_generics(&func.sig.generics)?;
 
-    let return_type = parse_fn_output(&func.sig.output, func.sig.span(), has_v)?;
+    let (return_type, body) = parse_fn_output(
+        &func.sig.output,
+        func.sig.span(),
+        has_v,
+        is_async,
+        *func.block,
+    )?;
 
     let mut eval = None;
     let mut heap = None;
 
-    let mut seen_star_args = false;
+    // Like in Python, parameters after `*args` or after a named-only parameter
+    // are named-only.
+    let mut seen_star = false;
     let mut args = Vec::new();
     for (i, arg) in func.sig.inputs.into_iter().enumerate() {
         let span = arg.span();
-        let parsed_arg = parse_arg(arg, has_v, seen_star_args, module_kind, i)?;
+        let parsed_arg = parse_arg(arg, has_v, seen_star, module_kind, i)?;
         match parsed_arg {
             StarArgOrSpecial::Heap(special) => {
                 if heap.is_some() {
@@ -297,8 +357,11 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
                 eval = Some(special);
             }
             StarArgOrSpecial::StarArg(arg) => {
-                if arg.pass_style == StarArgPassStyle::Args {
-                    seen_star_args = true;
+                if matches!(
+                    arg.pass_style,
+                    StarArgPassStyle::Args | StarArgPassStyle::NamedOnly
+                ) {
+                    seen_star = true;
                 }
                 args.push(arg);
             }
@@ -313,6 +376,10 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
     }
 
     if is_attribute {
+        if is_async {
+            return Err(syn::Error::new(sig_span, "Attributes cannot be `async`"));
+        }
+
         if eval.is_some() {
             return Err(syn::Error::new(
                 sig_span,
@@ -320,6 +387,13 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
             ));
         }
 
+        if is_result_with_error(&return_type) && !is_anyhow_or_starlark_result(&return_type) {
+            return Err(syn::Error::new(
+                sig_span,
+                "Attributes must return `anyhow::Result<...>`, `starlark::Result<...>` or `Option<...>`",
+            ));
+        }
+
         if args.len() != 1 {
             return Err(syn::Error::new(
                 sig_span,
@@ -345,6 +419,12 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
                 "Attribute function cannot types are not implemented",
             ));
         }
+        if return_ty.is_some() {
+            return Err(syn::Error::new(
+                sig_span,
+                "`return_ty` is not implemented for attributes",
+            ));
+        }
         Ok(StarStmt::Attr(StarAttr {
             name: func.sig.ident,
             arg: arg.ty,
@@ -352,7 +432,7 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
             attrs,
             return_type,
             speculative_exec_safe,
-            body: *func.block,
+            body,
             docstring,
         }))
     } else {
@@ -381,10 +461,12 @@ pub(crate) fn parse_fun(func: ItemFn, module_kind: ModuleKind) -> syn::Result<St
             heap,
             eval,
             return_type,
+            return_ty,
             starlark_ty_custom_function,
             special_builtin_function,
             speculative_exec_safe,
-            body: *func.block,
+            is_async,
+            body,
             source,
             docstring,
         };
@@ -485,17 +567,63 @@ fn check_lifetimes_in_type(ty: &Type, has_v: bool) -> syn::Result<()> {
     visit.result
 }
 
-fn parse_fn_output(return_type: &ReturnType, span: Span, has_v: bool) -> syn::Result<Type> {
+/// Check if given type is `Option<T>`.
+fn is_option(t: &Type) -> bool {
+    let path = match t {
+        Type::Path(p) => p,
+        _ => return false,
+    };
+    if path.qself.is_some() {
+        return false;
+    }
+    match path.path.segments.last() {
+        Some(s) if s.ident == "Option" => {
+            matches!(&s.arguments, PathArguments::AngleBracketed(args) if args.args.len() == 1)
+        }
+        _ => false,
+    }
+}
+
+/// Parse the return type, returning it together with the function body.
+///
+/// Infallible functions returning `Option<T>` are rewritten to return `::anyhow::Result<Option<T>>`,
+/// so `None` becomes Starlark `None` like in any other return of `Option`.
+fn parse_fn_output(
+    return_type: &ReturnType,
+    span: Span,
+    has_v: bool,
+    is_async: bool,
+    body: Block,
+) -> syn::Result<(Type, Block)> {
     check_lifetimes_in_return_type(return_type, has_v)?;
     match return_type {
         ReturnType::Default => Err(syn::Error::new(span, "Function must have a return type")),
-        ReturnType::Type(_, x) => match is_anyhow_or_starlark_result(x) {
-            true => Ok((**x).clone()),
-            false => Err(syn::Error::new(
-                return_type.span(),
-                "Function return type must be either `anyhow::Result<...>` or `starlark::Result<...>`",
-            )),
-        },
+        ReturnType::Type(_, x) if is_anyhow_or_starlark_result(x) || is_result_with_error(x) => {
+            Ok(((**x).clone(), body))
+        }
+        // In `async` functions, an inner `async` block does the same as the closure below.
+        ReturnType::Type(_, x) if is_option(x) && is_async => Ok((
+            syn::parse_quote_spanned! { x.span()=> ::anyhow::Result<#x> },
+            syn::parse_quote_spanned! { body.span()=>
+                {
+                    Ok(async move #body.await)
+                }
+            },
+        )),
+        ReturnType::Type(_, x) if is_option(x) => Ok((
+            syn::parse_quote_spanned! { x.span()=> ::anyhow::Result<#x> },
+            // The closure keeps `return` and `?` in the body working on `Option`.
+            syn::parse_quote_spanned! { body.span()=>
+                {
+                    #[allow(clippy::redundant_closure_call)]
+                    Ok((move || #body)())
+                }
+            },
+        )),
+        ReturnType::Type(..) => Err(syn::Error::new(
+            return_type.span(),
+            "Function return type must be either `anyhow::Result<...>`, `starlark::Result<...>`, `Result<..., E>` or `Option<...>`",
+        )),
     }
 }
 
@@ -560,7 +688,7 @@ fn is_heap(ident: &Ident, ty: &Type) -> syn::Result<Option<SpecialParam>> {
 fn parse_arg(
     x: FnArg,
     has_v: bool,
-    seen_star_args: bool,
+    seen_star: bool,
     module_kind: ModuleKind,
     param_index: usize,
 ) -> syn::Result<StarArgOrSpecial> {
@@ -635,7 +763,7 @@ fn parse_arg(
                 this,
                 param_attrs.args,
                 param_attrs.kwargs,
-                seen_star_args,
+                seen_star,
                 param_attrs.pos_only,
                 param_attrs.named_only,
                 arguments,
@@ -646,7 +774,8 @@ fn parse_arg(
                 (false, _, _, true, true, _, false) => {
                     return Err(syn::Error::new(
                         span,
-                        "Positional-only arguments cannot follow *args",
+                        "Positional-only parameters cannot follow `*args` \
+                        or named-only parameters",
                     ));
                 }
                 (false, false, false, true, false, _, false) => StarArgPassStyle::NamedOnly,
@@ -673,6 +802,21 @@ fn parse_arg(
                     ));
                 }
             };
+            if param_attrs.ty.is_some() {
+                let plain = matches!(
+                    pass_style,
+                    StarArgPassStyle::PosOnly
+                        | StarArgPassStyle::PosOrNamed
+                        | StarArgPassStyle::NamedOnly
+                );
+                if !plain || !is_type_name(&ty, "Value") {
+                    return Err(syn::Error::new(
+                        span,
+                        "`#[starlark(ty = T)]` can only be used on `Value` parameters",
+                    ));
+                }
+            }
+
             Ok(StarArgOrSpecial::StarArg(StarArg {
                 span,
                 attrs: param_attrs.unused_attrs,
@@ -680,7 +824,9 @@ fn parse_arg(
                 name: ident.ident,
                 pass_style,
                 ty: *ty,
+                starlark_ty: param_attrs.ty,
                 default: param_attrs.default,
+                docstring: param_attrs.docstring,
                 source: StarArgSource::Unknown,
             }))
         }
@@ -220,6 +220,9 @@ pub(crate) fn render_starlark_type(typ: &syn::Type) -> syn::Expr {
 }
 
 pub(crate) fn render_starlark_return_type(fun: &StarFun) -> syn::Expr {
+    if let Some(return_ty) = &fun.return_ty {
+        return render_starlark_type(return_ty);
+    }
     let struct_name = fun.struct_name();
     syn::parse_quote! {
         #struct_name::return_type_starlark_type_repr()
@@ -124,6 +124,31 @@ impl StarFun {
         }
     }
 
+    /// Async executor function parameter and call argument, for `async` functions.
+    fn async_executor_param_arg(
+        &self,
+    ) -> (
+        Option<TokenStream>,
+        Option<TokenStream>,
+        Option<TokenStream>,
+    ) {
+        if self.is_async {
+            (
+                Some(quote! {
+                    __async_executor: &dyn starlark::eval::AsyncExecutor,
+                }),
+                Some(quote! {
+                    &dyn starlark::eval::AsyncExecutor,
+                }),
+                Some(quote! {
+                    __async_executor,
+                }),
+            )
+        } else {
+            (None, None, None)
+        }
+    }
+
     /// `this` param if needed and call argument.
     fn this_param_arg(
         &self,
@@ -148,7 +173,7 @@ impl StarFun {
         let Bindings { prepare, bindings } = render_binding(self);
         let binding_params: Vec<_> = bindings.iter().map(|b| b.render_param()).collect();
         let binding_param_types: Vec<_> = bindings.iter().map(|b| b.render_param_type()).collect();
-        let binding_args: Vec<_> = bindings.iter().map(|b| b.render_arg()).collect();
+        let binding_args: Vec<_> = bindings.iter().map(|b| b.render_checked_arg()).collect();
         (binding_params, binding_param_types, prepare, binding_args)
     }
 
@@ -251,7 +276,12 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<syn::Stmt> {
     let (this_param, this_param_type, this_arg) = x.this_param_arg();
     let (eval_param, eval_param_type, eval_arg) = x.eval_param_arg();
     let (heap_param, heap_param_type, heap_arg) = x.heap_param_arg();
+    let (async_executor_param, async_executor_param_type, async_executor_arg) =
+        x.async_executor_param_arg();
     let (binding_params, binding_param_types, prepare, binding_args) = x.binding_params_arg();
+    let binding_vars: Vec<Ident> = (0..binding_args.len())
+        .map(|i| format_ident!("__binding_{}", i))
+        .collect();
 
     let trait_name = x.trait_name();
     let (struct_fields, struct_fields_init) = x.struct_fields()?;
@@ -260,13 +290,38 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<syn::Stmt> {
 
     let builder_set = x.builder_set(&documentation_var, struct_fields_init)?;
 
+    // Check the declared return type in debug builds.
+    let check_return_ty = x.return_ty.as_ref().map(|return_ty| {
+        quote! {
+            if cfg!(debug_assertions) {
+                starlark::typing::macro_support::check_return_ty::<#return_ty>(v, eval.heap())?;
+            }
+        }
+    });
+
+    // The executor is obtained before the call, because the evaluator may be passed to the function.
+    let get_async_executor = x.is_async.then(|| {
+        quote! {
+            let __async_executor = eval.async_executor();
+        }
+    });
+
     let StarFun {
         attrs,
         return_type,
         body,
+        is_async,
         ..
     } = x;
 
+    let body = if is_async {
+        quote! {
+            __async_executor.run(async move #body)
+        }
+    } else {
+        quote! { #body }
+    };
+
     Ok(syn::parse_quote! {
         {
             struct #struct_name {
@@ -283,6 +338,7 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<syn::Stmt> {
                     #( #binding_params, )*
                     #eval_param
                     #heap_param
+                    #async_executor_param
                 ) -> #return_type {
                     #body
                 }
@@ -300,6 +356,7 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<syn::Stmt> {
                             #( #binding_param_types, )*
                             #eval_param_type
                             #heap_param_type
+                            #async_executor_param_type
                         ) -> std::result::Result<T, E>,
                     ) -> starlark::typing::Ty {
                         <T as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
@@ -316,9 +373,23 @@ pub(crate) fn render_fun(x: StarFun) -> syn::Result<syn::Stmt> {
                     #this_param
                     parameters: &starlark::eval::Arguments<'v, '_>,
                 ) -> starlark::Result<starlark::values::Value<'v>> {
-                    #prepare
-                    match Self::invoke_impl(#this_arg #( #binding_args, )* #eval_arg #heap_arg) {
-                        Ok(v) => Ok(eval.heap().alloc(v)),
+                    // Errors in arguments are collected separately from errors in the body,
+                    // so the caller can report them with the function signature.
+                    #[allow(clippy::redundant_closure_call)]
+                    let ( #( #binding_vars, )* ) = match (|| -> starlark::Result<_> {
+                        #prepare
+                        Ok(( #( #binding_args, )* ))
+                    })() {
+                        Ok(bound) => bound,
+                        Err(e) => return Err(starlark::values::function::native_arguments_error(e)),
+                    };
+                    #get_async_executor
+                    match Self::invoke_impl(#this_arg #( #binding_vars, )* #eval_arg #heap_arg #async_executor_arg) {
+                        Ok(v) => {
+                            let v = eval.heap().alloc(v);
+                            #check_return_ty
+                            Ok(v)
+                        }
                         // The `.into()` is an `anyhow -> anyhow` conversion if the return type is `anyhow`
                         #[allow(clippy::useless_conversion)]
                         Err(e) => Err(e.into()),
@@ -386,10 +457,20 @@ fn render_binding(x: &StarFun) -> Bindings {
         }
         StarFunSource::Positional { required, optional } => {
             let bind_args = x.args.iter().map(render_binding_arg).collect();
+            let name_str = x.name_str();
+            let param_names: Vec<String> = x
+                .args
+                .iter()
+                .filter(|a| a.pass_style != StarArgPassStyle::This)
+                .map(|a| ident_string(&a.name))
+                .collect();
+            let no_named_args = quote! {
+                parameters.no_named_args_for(#name_str, &[#(#param_names),*])?;
+            };
             if optional == 0 {
                 Bindings {
                     prepare: quote! {
-                        parameters.no_named_args()?;
+                        #no_named_args
                         let __required: [_; #required] = parameters.positional(eval.heap())?;
                     },
                     bindings: bind_args,
@@ -397,7 +478,7 @@ fn render_binding(x: &StarFun) -> Bindings {
             } else {
                 Bindings {
                     prepare: quote! {
-                        parameters.no_named_args()?;
+                        #no_named_args
                         let (__required, __optional): ([_; #required], [_; #optional]) = parameters.optional(eval.heap())?;
                     },
                     bindings: bind_args,
@@ -433,8 +514,16 @@ impl BindingArg {
         }
     }
 
-    fn render_arg(&self) -> syn::Expr {
-        self.expr.clone()
+    /// Argument expression, with an error tagged with the parameter name.
+    fn render_checked_arg(&self) -> syn::Expr {
+        let expr = &self.expr;
+        let name_str = ident_string(&self.name);
+        syn::parse_quote! {
+            match (|| -> starlark::Result<_> { Ok(#expr) })() {
+                Ok(v) => v,
+                Err(e) => return Err(starlark::values::function::native_parameter_error(#name_str, e)),
+            }
+        }
     }
 }
 
@@ -460,7 +549,7 @@ fn render_binding_arg(arg: &StarArg) -> BindingArg {
             "Can't have Option argument with a default, for `{}`",
             name_str
         );
-        syn::parse_quote! { starlark::eval::Arguments::check_optional(#name_str, #source)? }
+        syn::parse_quote! { starlark::eval::Arguments::check_optional_coerce(#name_str, #source, eval)? }
     } else if !arg.is_value() && arg.default.is_some() {
         let default = arg
             .default
@@ -472,12 +561,30 @@ fn render_binding_arg(arg: &StarArg) -> BindingArg {
                 #[allow(clippy::manual_unwrap_or)]
                 #[allow(clippy::unnecessary_lazy_evaluations)]
                 #[allow(clippy::redundant_closure)]
-                let x = starlark::eval::Arguments::check_optional(#name_str, #source)?.unwrap_or_else(|| #default);
+                let x = starlark::eval::Arguments::check_optional_coerce(#name_str, #source, eval)?.unwrap_or_else(|| #default);
                 x
             }
         }
     } else {
-        syn::parse_quote! { starlark::eval::Arguments::check_required(#name_str, #source)? }
+        syn::parse_quote! { starlark::eval::Arguments::check_required_coerce(#name_str, #source, eval)? }
+    };
+
+    // Check the declared type in debug builds.
+    let next = match &arg.starlark_ty {
+        Some(starlark_ty) => syn::parse_quote! {
+            {
+                let x: starlark::values::Value<'v> = #next;
+                if cfg!(debug_assertions) {
+                    starlark::typing::macro_support::check_param_ty::<#starlark_ty>(
+                        #name_str,
+                        x,
+                        eval.heap(),
+                    )?;
+                }
+                x
+            }
+        },
+        None => next,
     };
 
     BindingArg {
@@ -559,7 +666,9 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
                 StarArgPassStyle::PosOnly
                 | StarArgPassStyle::PosOrNamed
                 | StarArgPassStyle::NamedOnly => {
-                    let typ_str = render_starlark_type(arg.without_option());
+                    let typ_str = render_starlark_type(
+                        arg.starlark_ty.as_ref().unwrap_or(arg.without_option()),
+                    );
                     vec![syn::parse_quote! { #typ_str }]
                 }
                 StarArgPassStyle::Arguments => {
@@ -573,6 +682,21 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
         })
         .collect();
 
+    let parameter_docs: Vec<TokenStream> = x
+        .args
+        .iter()
+        .filter_map(|arg| {
+            let docstring = arg.docstring.as_ref()?;
+            // Names used in the signature.
+            let name_str = match arg.pass_style {
+                StarArgPassStyle::Args => "*args".to_owned(),
+                StarArgPassStyle::Kwargs => "**kwargs".to_owned(),
+                _ => ident_string(&arg.name),
+            };
+            Some(quote! { (#name_str, #docstring) })
+        })
+        .collect();
+
     let return_type_str = render_starlark_return_type(x);
     let var_name = format_ident!("__documentation");
     let as_type = x.as_type_expr();
@@ -583,6 +707,7 @@ fn render_documentation(x: &StarFun) -> syn::Result<(Ident, TokenStream)> {
                 rust_docstring: #docs,
                 signature: #documentation_signature,
                 parameter_types,
+                parameter_docs: std::vec![#(#parameter_docs),*],
                 return_type: #return_type_str,
                 as_type: #as_type,
             }
@@ -631,12 +756,19 @@ fn render_signature_args(
                 if last_param_style > CurrentParamStyle::PosOnly {
                     return Err(syn::Error::new(
                         arg.span,
-                        "Positional-only parameter after non-positional-only",
+                        "Positional-only parameter after non-positional-only, \
+                        mark preceding parameters with `#[starlark(require = pos)]`",
                     ));
                 }
                 last_param_style = CurrentParamStyle::PosOnly;
             }
             StarArgPassStyle::PosOrNamed => {
+                if last_param_style >= CurrentParamStyle::NamedOnly {
+                    return Err(syn::Error::new(
+                        arg.span,
+                        "Positional parameter cannot follow `args`, named-only parameters or `kwargs`",
+                    ));
+                }
                 if last_param_style == CurrentParamStyle::PosOnly {
                     sig_args.extend(quote! {
                         #signature_var.no_more_positional_only_args();
@@ -645,6 +777,12 @@ fn render_signature_args(
                 last_param_style = CurrentParamStyle::PosOrNamed;
             }
             StarArgPassStyle::NamedOnly => {
+                if last_param_style == CurrentParamStyle::NoMore {
+                    return Err(syn::Error::new(
+                        arg.span,
+                        "Named-only parameter cannot follow `kwargs`",
+                    ));
+                }
                 if last_param_style < CurrentParamStyle::NamedOnly {
                     sig_args.extend(quote! {
                         #signature_var.no_more_positional_args();
@@ -683,12 +821,16 @@ fn render_signature_arg(
     } else if arg.is_option() {
         Ok(quote! { #signature_var.optional(#name_str);})
     } else if let Some(default) = &arg.default {
-        // For things that are type Value, we put them on the frozen heap.
+        // For things that are type Value, we put them on the frozen heap,
+        // so the default is evaluated once, and cannot be mutated by the function.
         // For things that aren't type value, use optional and then next_opt/unwrap
         // to avoid the to/from value conversion.
         if arg.is_value() {
+            // Empty containers like `vec![]` need a type to be allocated.
+            let frozen = render_default_as_frozen_value(default)
+                .unwrap_or_else(|| quote! { globals_builder.alloc(#default) });
             Ok(quote! {
-                #signature_var.defaulted(#name_str, globals_builder.alloc(#default));
+                #signature_var.defaulted(#name_str, #frozen);
             })
         } else if purpose == Purpose::Documentation
             && render_default_as_frozen_value(default).is_some()
@@ -730,7 +872,10 @@ fn render_default_as_frozen_value(default: &Expr) -> Option<TokenStream> {
     ) {
         // Make sure we don't splice in `x` again, or we double quote the string
         Some(quote! { globals_builder.alloc(#default) })
-    } else if x == "UnpackListOrTuple :: default()" || x == "UnpackList :: default()" {
+    } else if matches!(
+        x.as_str(),
+        "UnpackListOrTuple :: default()" | "UnpackList :: default()" | "vec! []" | "Vec :: new()"
+    ) {
         Some(quote! { globals_builder.alloc(starlark::values::list::AllocList::EMPTY) })
     } else if x == "SmallMap :: new()" {
         Some(quote! { globals_builder.alloc(starlark::values::dict::AllocDict::EMPTY) })
@@ -75,9 +75,13 @@ pub(crate) struct StarFun {
     pub eval: Option<SpecialParam>,
     /// `anyhow::Result<T>`.
     pub return_type: Type,
+    /// Starlark return type declared with `#[starlark(return_ty = T)]`.
+    pub return_ty: Option<Type>,
     pub starlark_ty_custom_function: Option<Expr>,
     pub special_builtin_function: Option<Expr>,
     pub speculative_exec_safe: bool,
+    /// Declared as `async fn`, the body is run with the evaluator's async executor.
+    pub is_async: bool,
     pub body: Block,
     pub source: StarFunSource,
     pub docstring: Option<String>,
@@ -143,7 +147,11 @@ pub(crate) struct StarArg {
     pub pass_style: StarArgPassStyle,
     pub name: Ident,
     pub ty: Type,
+    /// Starlark type declared with `#[starlark(ty = T)]`.
+    pub starlark_ty: Option<Type>,
     pub default: Option<Expr>,
+    /// Doc comment on the parameter.
+    pub docstring: Option<String>,
     pub source: StarArgSource,
 }
 
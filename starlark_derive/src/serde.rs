@@ -17,11 +17,21 @@
 
 use proc_macro2::Span;
 use quote::quote;
+use quote::quote_spanned;
+use syn::parse::ParseStream;
 use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Attribute;
+use syn::Data;
 use syn::DeriveInput;
+use syn::Error;
+use syn::Field;
+use syn::Fields;
 use syn::GenericParam;
 use syn::Lifetime;
 use syn::LifetimeParam;
+use syn::LitStr;
+use syn::Token;
 
 pub fn derive_no_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -57,3 +67,252 @@ pub fn derive_no_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenS
 
     gen.into()
 }
+
+#[derive(Default)]
+struct SerializeFieldOptions {
+    skip: bool,
+    rename: Option<LitStr>,
+}
+
+/// Parse `#[serialize(skip)]` and `#[serialize(rename = "name")]` field and variant annotations.
+fn extract_field_options(attrs: &[Attribute]) -> syn::Result<SerializeFieldOptions> {
+    syn::custom_keyword!(skip);
+    syn::custom_keyword!(rename);
+
+    let mut opts = SerializeFieldOptions::default();
+
+    for attr in attrs.iter() {
+        if !attr.path().is_ident("serialize") {
+            continue;
+        }
+
+        attr.parse_args_with(|input: ParseStream| {
+            loop {
+                if input.parse::<skip>().is_ok() {
+                    if opts.skip {
+                        return Err(input.error("`skip` was set twice"));
+                    }
+                    opts.skip = true;
+                } else if input.parse::<rename>().is_ok() {
+                    if opts.rename.is_some() {
+                        return Err(input.error("`rename` was set twice"));
+                    }
+                    input.parse::<Token![=]>()?;
+                    opts.rename = Some(input.parse()?);
+                } else {
+                    return Err(input.lookahead1().error());
+                }
+
+                if input.parse::<Option<Token![,]>>()?.is_none() {
+                    break;
+                }
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(opts)
+}
+
+/// Fields which are not skipped, with the expressions to get references to them.
+fn serialized_fields<'a>(
+    fields: &'a Fields,
+    access: impl Fn(usize, &'a Field) -> proc_macro2::TokenStream,
+) -> syn::Result<Vec<(&'a Field, SerializeFieldOptions, proc_macro2::TokenStream)>> {
+    let mut serialized = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let opts = extract_field_options(&field.attrs)?;
+        if opts.skip {
+            continue;
+        }
+        if let (None, Some(rename)) = (&field.ident, &opts.rename) {
+            return Err(Error::new_spanned(
+                rename,
+                "`rename` is only supported on named fields",
+            ));
+        }
+        serialized.push((field, opts, access(i, field)));
+    }
+    Ok(serialized)
+}
+
+/// Name of the field or the variant in the serialized output.
+fn serialized_name(ident: &syn::Ident, opts: &SerializeFieldOptions) -> LitStr {
+    match &opts.rename {
+        Some(rename) => rename.clone(),
+        None => LitStr::new(&ident.to_string(), ident.span()),
+    }
+}
+
+/// Serialize a struct or an enum variant with the given fields, like `#[derive(serde::Serialize)]` does.
+/// `variant` is the variant index and name for enums.
+fn serialize_fields(
+    name: &str,
+    variant: Option<(u32, &LitStr)>,
+    fields: &Fields,
+    access: impl Fn(usize, &Field) -> proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let serde = quote! { starlark::__derive_refs::serde };
+    let variant_args = match variant {
+        Some((index, variant)) => quote! { , #index, #variant },
+        None => quote! {},
+    };
+    let serialized = serialized_fields(fields, access)?;
+    let (method, field_trait) = match (fields, variant) {
+        (Fields::Unit, None) => {
+            return Ok(quote! { #serde::Serializer::serialize_unit_struct(serializer, #name) });
+        }
+        (Fields::Unit, Some(_)) => {
+            return Ok(quote! {
+                #serde::Serializer::serialize_unit_variant(serializer, #name #variant_args)
+            });
+        }
+        (Fields::Unnamed(unnamed), _) if unnamed.unnamed.len() == 1 && serialized.len() == 1 => {
+            let value = &serialized[0].2;
+            let method = match variant {
+                None => quote! { serialize_newtype_struct },
+                Some(_) => quote! { serialize_newtype_variant },
+            };
+            return Ok(quote! {
+                #serde::Serializer::#method(serializer, #name #variant_args, #value)
+            });
+        }
+        (Fields::Unnamed(_), None) => (
+            quote! { serialize_tuple_struct },
+            quote! { SerializeTupleStruct },
+        ),
+        (Fields::Unnamed(_), Some(_)) => (
+            quote! { serialize_tuple_variant },
+            quote! { SerializeTupleVariant },
+        ),
+        (Fields::Named(_), None) => (quote! { serialize_struct }, quote! { SerializeStruct }),
+        (Fields::Named(_), Some(_)) => (
+            quote! { serialize_struct_variant },
+            quote! { SerializeStructVariant },
+        ),
+    };
+
+    let mut entries = Vec::new();
+    for (field, opts, value) in &serialized {
+        let key = field.ident.as_ref().map(|ident| {
+            let key = serialized_name(ident, opts);
+            quote! { #key, }
+        });
+        entries.push(quote_spanned! { field.span()=>
+            #serde::#field_trait::serialize_field(&mut s, #key #value)?;
+        });
+    }
+    let len = serialized.len();
+    Ok(quote! {
+        {
+            let mut s = #serde::Serializer::#method(serializer, #name #variant_args, #len)?;
+            #(#entries)*
+            #serde::#field_trait::end(s)
+        }
+    })
+}
+
+fn derive_starlark_serialize_impl(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let span = input.span();
+    let name = &input.ident;
+    let name_str = name.to_string();
+
+    let body = match &input.data {
+        Data::Struct(data) => serialize_fields(&name_str, None, &data.fields, |i, field| {
+            match &field.ident {
+                Some(ident) => quote! { &self.#ident },
+                None => {
+                    let index = syn::Index::from(i);
+                    quote! { &self.#index }
+                }
+            }
+        })?,
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for (index, variant) in data.variants.iter().enumerate() {
+                let opts = extract_field_options(&variant.attrs)?;
+                if opts.skip {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "`skip` is not supported on enum variants",
+                    ));
+                }
+                let variant_name = serialized_name(&variant.ident, &opts);
+                let binding = |i: usize| syn::Ident::new(&format!("__f{i}"), Span::call_site());
+                let serialize = serialize_fields(
+                    &name_str,
+                    Some((index.try_into().unwrap(), &variant_name)),
+                    &variant.fields,
+                    |i, _| {
+                        let binding = binding(i);
+                        quote! { #binding }
+                    },
+                )?;
+                let ident = &variant.ident;
+                let pattern = match &variant.fields {
+                    Fields::Unit => quote! { Self::#ident },
+                    Fields::Unnamed(fields) => {
+                        let bindings = (0..fields.unnamed.len()).map(binding);
+                        quote! { Self::#ident(#(#bindings),*) }
+                    }
+                    Fields::Named(fields) => {
+                        let bindings = fields.named.iter().enumerate().map(|(i, f)| {
+                            let ident = f.ident.as_ref().unwrap();
+                            let binding = binding(i);
+                            quote! { #ident: #binding }
+                        });
+                        quote! { Self::#ident { #(#bindings),* } }
+                    }
+                };
+                arms.push(quote_spanned! { variant.span()=>
+                    #[allow(unused_variables)]
+                    #pattern => #serialize,
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                span,
+                "`StarlarkSerialize` cannot be derived for unions",
+            ));
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    for param in &input.generics.params {
+        if let GenericParam::Type(t) = param {
+            let name = &t.ident;
+            generics
+                .make_where_clause()
+                .predicates
+                .push(syn::parse_quote! { #name: starlark::__derive_refs::serde::Serialize });
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote_spanned! { span=>
+        impl #impl_generics starlark::__derive_refs::serde::Serialize for #name #ty_generics #where_clause {
+            fn serialize<__StarlarkSerializeS>(&self, serializer: __StarlarkSerializeS) -> std::result::Result<__StarlarkSerializeS::Ok, __StarlarkSerializeS::Error>
+            where
+                __StarlarkSerializeS: starlark::__derive_refs::serde::Serializer,
+            {
+                #body
+            }
+        }
+    })
+}
+
+pub fn derive_starlark_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match derive_starlark_serialize_impl(input) {
+        Ok(gen) => gen.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
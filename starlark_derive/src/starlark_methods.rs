@@ -0,0 +1,152 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use proc_macro::TokenStream;
+use quote::quote_spanned;
+use syn::spanned::Spanned;
+
+pub(crate) fn starlark_methods(attr: TokenStream, input: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "`#[starlark_methods]` does not accept arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let input = syn::parse_macro_input!(input as syn::ItemImpl);
+    match starlark_methods_impl(input) {
+        Ok(gen) => gen.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn is_starlark_attr(attr: &syn::Attribute) -> bool {
+    attr.path().is_ident("starlark")
+}
+
+/// `Some(lifetime)` if the method takes `&self`, `None` if it has no receiver.
+fn ref_self_receiver(method: &syn::ImplItemFn) -> syn::Result<Option<Option<syn::Lifetime>>> {
+    match method.sig.receiver() {
+        None => Ok(None),
+        Some(syn::Receiver {
+            reference: Some((_, lifetime)),
+            mutability: None,
+            colon_token: None,
+            ..
+        }) => Ok(Some(lifetime.clone())),
+        Some(receiver) => Err(syn::Error::new_spanned(
+            receiver,
+            "Starlark methods must take `&self`",
+        )),
+    }
+}
+
+/// Starlark function which calls the method with `this` as `self`.
+fn method_wrapper(
+    self_ty: &syn::Type,
+    method: &syn::ImplItemFn,
+    this_lifetime: Option<syn::Lifetime>,
+) -> syn::Result<syn::ItemFn> {
+    let span = method.sig.span();
+    let name = &method.sig.ident;
+    let generics = &method.sig.generics;
+    let output = &method.sig.output;
+    let attrs = method
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc") || is_starlark_attr(a));
+
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    for input in method.sig.inputs.iter().skip(1) {
+        let syn::FnArg::Typed(param) = input else {
+            unreachable!("receiver can only be the first parameter");
+        };
+        let syn::Pat::Ident(ident) = &*param.pat else {
+            return Err(syn::Error::new_spanned(
+                &param.pat,
+                "Starlark method parameters must be identifiers",
+            ));
+        };
+        args.push(ident.ident.clone());
+        params.push(param.clone());
+    }
+
+    // Body is spanned at the call site, so lints do not point at the user's method.
+    let body: syn::Block = syn::parse_quote! {
+        {
+            <#self_ty>::#name(this, #(#args),*)
+        }
+    };
+    Ok(syn::parse_quote_spanned! { span =>
+        #(#attrs)*
+        fn #name #generics(this: & #this_lifetime #self_ty, #(#params),*) #output #body
+    })
+}
+
+fn starlark_methods_impl(mut input: syn::ItemImpl) -> syn::Result<proc_macro2::TokenStream> {
+    let span = input.span();
+    if let Some((_, path, _)) = &input.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "`#[starlark_methods]` must be applied to an inherent `impl` block",
+        ));
+    }
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`#[starlark_methods]` does not support generic `impl` blocks",
+        ));
+    }
+
+    let self_ty = &input.self_ty;
+    let mut wrappers = Vec::new();
+    for item in &input.items {
+        if let syn::ImplItem::Fn(method) = item {
+            if let Some(this_lifetime) = ref_self_receiver(method)? {
+                wrappers.push(method_wrapper(self_ty, method, this_lifetime)?);
+            }
+        }
+    }
+
+    // `#[starlark]` annotations are only meaningful in the generated module.
+    for item in &mut input.items {
+        if let syn::ImplItem::Fn(method) = item {
+            method.attrs.retain(|a| !is_starlark_attr(a));
+            for param in &mut method.sig.inputs {
+                if let syn::FnArg::Typed(param) = param {
+                    param.attrs.retain(|a| !is_starlark_attr(a));
+                }
+            }
+        }
+    }
+
+    let self_ty = &input.self_ty;
+    Ok(quote_spanned! { span =>
+        #input
+
+        impl starlark::__derive_refs::DerivedMethods for #self_ty {
+            fn methods(builder: &mut starlark::environment::MethodsBuilder) {
+                #[starlark::starlark_module]
+                fn methods(builder: &mut starlark::environment::MethodsBuilder) {
+                    #(#wrappers)*
+                }
+                methods(builder)
+            }
+        }
+    })
+}
@@ -103,6 +103,14 @@ fn derive_starlark_type_repr_impl(
 ) -> syn::Result<proc_macro2::TokenStream> {
     let span = input.ident.span();
 
+    if let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(_),
+        ..
+    }) = &input.data
+    {
+        return Ok(derive_struct_type_repr(&input));
+    }
+
     let input = StarlarkTypeReprInput::parse(input, "StarlarkTypeRepr")?;
 
     let ident = &input.ident;
@@ -120,3 +128,20 @@ fn derive_starlark_type_repr_impl(
     };
     Ok(quote::quote_spanned! { span => #trait_impl })
 }
+
+/// Structs with named fields are represented as Starlark `struct`,
+/// matching `#[derive(AllocValue)]`.
+fn derive_struct_type_repr(input: &syn::DeriveInput) -> proc_macro2::TokenStream {
+    let span = input.ident.span();
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    quote::quote_spanned! { span =>
+        impl #impl_generics starlark::values::type_repr::StarlarkTypeRepr for #ident #type_generics #where_clause {
+            type Canonical = <starlark::values::structs::StructRef<'static> as starlark::values::type_repr::StarlarkTypeRepr>::Canonical;
+
+            fn starlark_type_repr() -> starlark::typing::Ty {
+                <starlark::values::structs::StructRef<'static> as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr()
+            }
+        }
+    }
+}
@@ -541,3 +541,191 @@ fn derive_starlark_value_impl(
         #input
     })
 }
+
+/// Options of `#[derive(StarlarkValue)]`, from `#[starlark(type = "name", methods)]` on the struct.
+#[derive(Default)]
+struct DeriveStarlarkValueOptions {
+    typ: Option<syn::LitStr>,
+    methods: bool,
+}
+
+fn derive_starlark_value_options(
+    attrs: &[syn::Attribute],
+) -> syn::Result<DeriveStarlarkValueOptions> {
+    syn::custom_keyword!(methods);
+
+    let mut opts = DeriveStarlarkValueOptions::default();
+
+    for attr in attrs.iter() {
+        if !attr.path().is_ident("starlark") {
+            continue;
+        }
+
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            loop {
+                if input.parse::<syn::Token![type]>().is_ok() {
+                    if opts.typ.is_some() {
+                        return Err(input.error("`type` was set twice"));
+                    }
+                    input.parse::<syn::Token![=]>()?;
+                    opts.typ = Some(input.parse()?);
+                } else if input.parse::<methods>().is_ok() {
+                    if opts.methods {
+                        return Err(input.error("`methods` was set twice"));
+                    }
+                    opts.methods = true;
+                } else {
+                    return Err(input.lookahead1().error());
+                }
+
+                if input.parse::<Option<syn::Token![,]>>()?.is_none() {
+                    break;
+                }
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(opts)
+}
+
+fn doc_attrs_string(attrs: &[syn::Attribute]) -> String {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if let syn::Meta::NameValue(syn::MetaNameValue {
+            path,
+            value:
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }),
+            ..
+        }) = &attr.meta
+        {
+            if path.is_ident("doc") {
+                lines.push(s.value());
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+pub(crate) fn derive_starlark_value_struct(
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    match derive_starlark_value_struct_impl(input) {
+        Ok(gen) => gen.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn derive_starlark_value_struct_impl(
+    input: syn::DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let span = input.ident.span();
+    let syn::Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            span,
+            "`StarlarkValue` can only be derived for structs",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`StarlarkValue` can only be derived for types without generic parameters, \
+            use `#[starlark_value]` on an `impl StarlarkValue` instead",
+        ));
+    }
+
+    let opts = derive_starlark_value_options(&input.attrs)?;
+    let ident = &input.ident;
+    let typ = opts
+        .typ
+        .unwrap_or_else(|| syn::LitStr::new(&ident.to_string(), span));
+
+    let fields = crate::attrs::exposed_fields(input.data.clone())?;
+    let attrs_impl = crate::attrs::expand_attrs_impl(ident, &fields);
+
+    let type_docs = doc_attrs_string(&input.attrs);
+    let field_docs = fields.iter().map(|field| {
+        let name = field.name();
+        let ty = &field.ty;
+        let docs = data
+            .fields
+            .iter()
+            .find(|f| f.ident.as_ref() == Some(&field.ident))
+            .map(|f| doc_attrs_string(&f.attrs))
+            .unwrap_or_default();
+        quote_spanned! { field.ident.span() =>
+            docs.members.insert(
+                #name.to_owned(),
+                starlark::docs::DocMember::Property(starlark::docs::DocProperty {
+                    docs: starlark::docs::DocString::from_docstring(starlark::docs::DocStringKind::Rust, #docs),
+                    typ: <#ty as starlark::values::type_repr::StarlarkTypeRepr>::starlark_type_repr(),
+                }),
+            );
+        }
+    });
+
+    let get_methods: Option<syn::ImplItem> = if opts.methods {
+        Some(syn::parse_quote_spanned! { span =>
+            fn get_methods() -> Option<&'static starlark::environment::Methods> {
+                static RES: starlark::environment::MethodsStatic = starlark::environment::MethodsStatic::new();
+                RES.methods(<#ident as starlark::__derive_refs::DerivedMethods>::methods)
+            }
+        })
+    } else {
+        None
+    };
+
+    let impl_starlark_value: syn::ItemImpl = syn::parse_quote_spanned! { span =>
+        impl<'v> starlark::values::StarlarkValue<'v> for #ident {
+            fn has_attr(&self, attr: &str, _heap: &'v starlark::values::Heap) -> bool {
+                self.attrs_has_attr(attr)
+            }
+
+            fn get_attr(&self, attr: &str, heap: &'v starlark::values::Heap) -> Option<starlark::values::Value<'v>> {
+                self.attrs_get_attr(attr, heap)
+            }
+
+            fn dir_attr(&self) -> Vec<String> {
+                self.attrs_dir_attr()
+            }
+
+            #get_methods
+
+            fn documentation(&self) -> Option<starlark::docs::DocItem> {
+                let ty = <Self as starlark::values::StarlarkValue>::get_type_starlark_repr();
+                let mut docs = match <Self as starlark::values::StarlarkValue>::get_methods() {
+                    Some(methods) => methods.documentation(ty),
+                    None => starlark::docs::DocType {
+                        docs: None,
+                        members: Default::default(),
+                        ty,
+                    },
+                };
+                docs.docs = starlark::docs::DocString::from_docstring(starlark::docs::DocStringKind::Rust, #type_docs);
+                #(#field_docs)*
+                Some(starlark::docs::DocItem::Type(docs))
+            }
+        }
+    };
+    let impl_starlark_value = derive_starlark_value_impl(
+        StarlarkValueAttrs {
+            typ: syn::parse_quote_spanned! { span => #typ },
+            unpack_value: false,
+            starlark_type_repr: false,
+        },
+        impl_starlark_value,
+    )?;
+
+    Ok(quote_spanned! { span =>
+        #attrs_impl
+
+        starlark::starlark_simple_value!(#ident);
+
+        #impl_starlark_value
+    })
+}
@@ -0,0 +1,142 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use quote::quote_spanned;
+use syn::parse::ParseStream;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Attribute;
+use syn::DeriveInput;
+use syn::GenericParam;
+
+use crate::util::DeriveInputUtil;
+
+/// Generics of the impl, of the frozen type and of the thawed type.
+fn format_impl_generics(input: &DeriveInput) -> (TokenStream, TokenStream, TokenStream) {
+    let span = input.span();
+    let mut impl_params = vec![quote_spanned! { span=> 'thaw }];
+    let mut input_params = Vec::new();
+    let mut output_params = Vec::new();
+    for param in &input.generics.params {
+        match param {
+            GenericParam::Type(t) => {
+                let name = &t.ident;
+                let bounds = t.bounds.iter();
+                impl_params.push(quote_spanned! {
+                    span=>
+                    #name: #(#bounds +)* starlark::values::Thaw<'thaw>
+                });
+                input_params.push(quote_spanned! { span=> #name });
+                output_params.push(quote_spanned! {
+                    span=>
+                    <#name as starlark::values::Thaw<'thaw>>::Thawed
+                });
+            }
+            GenericParam::Lifetime(_) => {
+                // Frozen value has all lifetimes `'static`.
+                input_params.push(quote_spanned! { span=> 'static });
+                output_params.push(quote_spanned! { span=> 'thaw });
+            }
+            GenericParam::Const(c) => {
+                let name = &c.ident;
+                let ty = &c.ty;
+                impl_params.push(quote_spanned! { span=> const #name: #ty });
+                input_params.push(quote_spanned! { span=> #name });
+                output_params.push(quote_spanned! { span=> #name });
+            }
+        }
+    }
+    let angle_brackets = |tokens: Vec<TokenStream>| {
+        if tokens.is_empty() {
+            quote_spanned! { span=> }
+        } else {
+            quote_spanned! { span=> < #(#tokens,)* > }
+        }
+    };
+    (
+        angle_brackets(impl_params),
+        angle_brackets(input_params),
+        angle_brackets(output_params),
+    )
+}
+
+fn derive_thaw_impl(input: DeriveInput) -> syn::Result<syn::ItemImpl> {
+    let span = input.span();
+    let name = &input.ident;
+    let (impl_params, input_params, output_params) = format_impl_generics(&input);
+    let body = thaw_impl(&input)?;
+
+    Ok(syn::parse_quote_spanned! {
+        span=>
+        impl #impl_params starlark::values::Thaw<'thaw> for #name #input_params {
+            type Thawed = #name #output_params;
+            #[allow(unused_variables)]
+            fn thaw(&self, thawer: &starlark::values::Thawer<'thaw>) -> anyhow::Result<Self::Thawed> {
+                std::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+/// Parse attribute `#[thaw(identity)]`.
+fn is_identity(attrs: &[Attribute]) -> syn::Result<bool> {
+    syn::custom_keyword!(identity);
+
+    for attr in attrs.iter() {
+        if !attr.path().is_ident("thaw") {
+            continue;
+        }
+
+        attr.parse_args_with(|input: ParseStream| input.parse::<identity>())?;
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+fn thaw_impl(derive_input: &DeriveInput) -> syn::Result<syn::Expr> {
+    let derive_input = DeriveInputUtil::new(derive_input)?;
+    derive_input.match_self(|struct_or_enum_variant, fields| {
+        let fields: Vec<syn::Expr> = fields
+            .iter()
+            .map(|(ident, f)| {
+                let span = ident.span();
+                if is_identity(&f.attrs)? {
+                    Ok(syn::parse_quote_spanned! { span=>
+                        std::clone::Clone::clone(#ident)
+                    })
+                } else {
+                    Ok(syn::parse_quote_spanned! { span=>
+                        starlark::values::Thaw::thaw(#ident, thawer)?
+                    })
+                }
+            })
+            .collect::<syn::Result<_>>()?;
+        struct_or_enum_variant.construct(fields)
+    })
+}
+
+pub fn derive_thaw(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match derive_thaw_impl(input) {
+        Ok(input) => quote! { #input }.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
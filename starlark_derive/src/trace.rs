@@ -84,17 +84,19 @@ pub fn derive_trace(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// Parse attribute `#[trace(unsafe_ignore)]`.
 ///
 /// Currently it fails on any attribute argument other than `unsafe_ignore`.
-fn is_ignore(attrs: &[Attribute]) -> bool {
+fn is_ignore(attrs: &[Attribute]) -> syn::Result<bool> {
     syn::custom_keyword!(unsafe_ignore);
 
-    attrs.iter().any(|a| {
-        a.path().is_ident("trace")
-            && a.parse_args_with(|input: ParseStream| {
-                let ignore = input.parse::<Option<unsafe_ignore>>()?.is_some();
-                Ok(ignore)
-            })
-            .unwrap()
-    })
+    for attr in attrs.iter() {
+        if !attr.path().is_ident("trace") {
+            continue;
+        }
+
+        attr.parse_args_with(|input: ParseStream| input.parse::<unsafe_ignore>())?;
+        return Ok(true);
+    }
+
+    Ok(false)
 }
 
 fn trace_impl(derive_input: &DeriveInput, generics: &Generics) -> syn::Result<syn::Expr> {
@@ -106,7 +108,7 @@ fn trace_impl(derive_input: &DeriveInput, generics: &Generics) -> syn::Result<sy
         .collect();
 
     derive_input.for_each_field(|name, field| {
-        if is_ignore(&field.attrs) {
+        if is_ignore(&field.attrs)? {
             Ok(quote! {})
         } else if is_static(&field.ty, &generic_types) {
             Ok(quote_spanned! {
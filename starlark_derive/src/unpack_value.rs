@@ -15,7 +15,9 @@
  * limitations under the License.
  */
 
+use syn::parse::ParseStream;
 use syn::spanned::Spanned;
+use syn::Token;
 
 use crate::starlark_type_repr::StarlarkTypeReprInput;
 use crate::v_lifetime::find_v_lifetime;
@@ -31,6 +33,14 @@ pub(crate) fn derive_unpack_value(input: proc_macro::TokenStream) -> proc_macro:
 fn derive_unpack_value_impl(input: syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let span = input.ident.span();
 
+    if let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(fields),
+        ..
+    }) = &input.data
+    {
+        return derive_unpack_struct(&input, fields);
+    }
+
     let input = StarlarkTypeReprInput::parse(input, "UnpackValue")?;
 
     let ident = input.ident;
@@ -87,3 +97,112 @@ fn derive_unpack_value_impl(input: syn::DeriveInput) -> syn::Result<proc_macro2:
     };
     Ok(quote::quote_spanned! { span => #trait_impl })
 }
+
+#[derive(Default)]
+struct UnpackFieldOptions {
+    default: bool,
+    rename: Option<syn::LitStr>,
+}
+
+/// Parse `#[unpack(default)]` and `#[unpack(rename = "name")]` field annotations.
+fn extract_field_options(attrs: &[syn::Attribute]) -> syn::Result<UnpackFieldOptions> {
+    syn::custom_keyword!(default);
+    syn::custom_keyword!(rename);
+
+    let mut opts = UnpackFieldOptions::default();
+
+    for attr in attrs.iter() {
+        if !attr.path().is_ident("unpack") {
+            continue;
+        }
+
+        attr.parse_args_with(|input: ParseStream| {
+            loop {
+                if input.parse::<default>().is_ok() {
+                    if opts.default {
+                        return Err(input.error("`default` was set twice"));
+                    }
+                    opts.default = true;
+                } else if input.parse::<rename>().is_ok() {
+                    if opts.rename.is_some() {
+                        return Err(input.error("`rename` was set twice"));
+                    }
+                    input.parse::<Token![=]>()?;
+                    opts.rename = Some(input.parse()?);
+                } else {
+                    return Err(input.lookahead1().error());
+                }
+
+                if input.parse::<Option<Token![,]>>()?.is_none() {
+                    break;
+                }
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(opts)
+}
+
+/// Struct is unpacked from a Starlark `struct` or a dict with string keys,
+/// with an entry per field.
+fn derive_unpack_struct(
+    input: &syn::DeriveInput,
+    fields: &syn::FieldsNamed,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let span = input.ident.span();
+    let ident = &input.ident;
+    let lifetime = find_v_lifetime(&input.generics)?;
+
+    let (_impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut generics = input.generics.clone();
+    if lifetime.is_none() {
+        generics
+            .params
+            .push(syn::parse_quote_spanned! { span => 'v });
+    }
+
+    let (impl_generics, _type_generics, _where_clause) = generics.split_for_impl();
+
+    let mut field_inits: Vec<syn::FieldValue> = Vec::new();
+    for field in &fields.named {
+        let opts = extract_field_options(&field.attrs)?;
+        let name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let key = match opts.rename {
+            Some(rename) => rename,
+            None => syn::LitStr::new(&name.to_string(), name.span()),
+        };
+        field_inits.push(if opts.default {
+            syn::parse_quote_spanned! { field.span() =>
+                #name: fields.field::<#ty>(#key)?.unwrap_or_default()
+            }
+        } else {
+            syn::parse_quote_spanned! { field.span() =>
+                #name: fields.required::<#ty>(#key)?
+            }
+        });
+    }
+
+    let ident_str = ident.to_string();
+    let trait_impl: syn::ItemImpl = syn::parse_quote_spanned! { span =>
+        #[allow(clippy::all)]
+        impl #impl_generics starlark::values::UnpackValue<'v> for #ident #type_generics #where_clause {
+            type Error = starlark::Error;
+
+            fn unpack_value_impl(value: starlark::values::Value<'v>) -> std::result::Result<std::option::Option<Self>, Self::Error> {
+                let Some(mut fields) = starlark::__derive_refs::UnpackStructFields::new(#ident_str, value)? else {
+                    return std::result::Result::Ok(None);
+                };
+                let result = #ident {
+                    #(#field_inits,)*
+                };
+                fields.finish()?;
+                std::result::Result::Ok(Some(result))
+            }
+        }
+    };
+    Ok(quote::quote_spanned! { span => #trait_impl })
+}
@@ -37,10 +37,12 @@ use std::ops::DerefMut;
 use std::ops::Sub;
 use std::ptr;
 use std::sync::Arc;
+use std::sync::OnceLock;
 
 use allocative::Allocative;
 use dupe::Dupe;
 use once_cell::sync::Lazy;
+use starlark_map::StarlarkHasher;
 
 use crate::fast_string;
 
@@ -251,6 +253,8 @@ struct CodeMapData {
     source: String,
     /// Byte positions of line beginnings.
     lines: Vec<Pos>,
+    /// [`CodeMap::hash_source`] of `source`, computed on first use.
+    content_hash: OnceLock<u64>,
 }
 
 /// "Codemap" for `.rs` files.
@@ -319,6 +323,7 @@ impl CodeMap {
             filename,
             source,
             lines,
+            content_hash: OnceLock::new(),
         })))
     }
 
@@ -409,6 +414,29 @@ impl CodeMap {
         }
     }
 
+    /// Hash of the file contents.
+    ///
+    /// Spans in errors and diagnostics keep the [`CodeMap`] they were produced from,
+    /// so a long-lived host can compare this hash with [`CodeMap::hash_source`]
+    /// of the current file contents to detect that a location refers to an outdated revision.
+    pub fn content_hash(&self) -> u64 {
+        match &self.0 {
+            CodeMapImpl::Real(data) => *data
+                .content_hash
+                .get_or_init(|| Self::hash_source(&data.source)),
+            CodeMapImpl::Native(_) => Self::hash_source(NativeCodeMap::SOURCE),
+        }
+    }
+
+    /// Hash of file contents as returned by [`content_hash`](CodeMap::content_hash).
+    /// The hash does not depend on the process, but it may differ between platforms,
+    /// so it should not be persisted or compared across machines.
+    pub fn hash_source(source: &str) -> u64 {
+        let mut hasher = StarlarkHasher::new();
+        hasher.write(source.as_bytes());
+        hasher.finish()
+    }
+
     /// Gets the source text of a Span.
     ///
     /// Panics if `span` is not entirely within this file.
@@ -571,6 +599,11 @@ impl<'a> FileSpanRef<'a> {
     pub fn source_span(self) -> &'a str {
         self.file.source_span(self.span)
     }
+
+    /// Hash of the contents of the file, see [`CodeMap::content_hash`].
+    pub fn content_hash(&self) -> u64 {
+        self.file.content_hash()
+    }
 }
 
 impl FileSpan {
@@ -604,6 +637,17 @@ impl FileSpan {
         self.as_ref().resolve_span()
     }
 
+    /// Hash of the contents of the file, see [`CodeMap::content_hash`].
+    pub fn content_hash(&self) -> u64 {
+        self.file.content_hash()
+    }
+
+    /// This span was produced from a different revision of the file than `source`,
+    /// so its lines and columns may point to the wrong place in `source`.
+    pub fn is_stale(&self, source: &str) -> bool {
+        self.file.content_hash() != CodeMap::hash_source(source)
+    }
+
     /// Resolve the span to lines and columns.
     pub fn resolve(&self) -> ResolvedFileSpan {
         ResolvedFileSpan {
@@ -853,6 +897,16 @@ mod tests {
         assert_eq!(span.to_string(), "1:1-3:33");
     }
 
+    #[test]
+    fn test_content_hash() {
+        let source = "x = 1\n";
+        let span = FileSpan::new("a.star".to_owned(), source.to_owned());
+        assert_eq!(CodeMap::hash_source(source), span.content_hash());
+        assert_eq!(span.content_hash(), span.as_ref().content_hash());
+        assert!(!span.is_stale(source));
+        assert!(span.is_stale("\nx = 1\n"));
+    }
+
     #[test]
     fn test_native_code_map() {
         static NATIVE_CODEMAP: NativeCodeMap = NativeCodeMap::new("test.rs", 100, 200);
@@ -22,6 +22,7 @@ use crate::call_stack::CallStack;
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
 use crate::codemap::Span;
+use crate::message::MessageTemplate;
 use crate::span_display::span_display;
 
 /// A value of type `T`, together with some diagnostic information.
@@ -49,6 +50,7 @@ impl<T> WithDiagnostic<T> {
             diagnostic: Diagnostic {
                 span: Some(codemap.file_span(span)),
                 call_stack: CallStack::default(),
+                message_template: None,
             },
         }))
     }
@@ -99,6 +101,14 @@ impl<T> WithDiagnostic<T> {
             self.0.diagnostic.call_stack = call_stack();
         }
     }
+
+    pub fn message_template(&self) -> Option<&MessageTemplate> {
+        self.0.diagnostic.message_template.as_ref()
+    }
+
+    pub fn set_message_template(&mut self, message_template: MessageTemplate) {
+        self.0.diagnostic.message_template = Some(message_template);
+    }
 }
 
 impl<T: StdError> fmt::Display for WithDiagnostic<T> {
@@ -119,7 +129,8 @@ impl<T: Into<crate::Error>> From<WithDiagnostic<T>> for crate::Error {
     fn from(e: WithDiagnostic<T>) -> Self {
         let diagnostic = e.0.diagnostic;
         let mut e: crate::Error = e.0.t.into();
-        e.0.0.diagnostic = diagnostic;
+        e.0.0.diagnostic.span = diagnostic.span;
+        e.0.0.diagnostic.call_stack = diagnostic.call_stack;
         e
     }
 }
@@ -132,6 +143,9 @@ struct Diagnostic {
 
     /// Call stack where the error originated.
     call_stack: CallStack,
+
+    /// The message as a template for translation, if the error provides one.
+    message_template: Option<MessageTemplate>,
 }
 
 impl Diagnostic {
@@ -160,12 +174,22 @@ pub(crate) fn diagnostic_display<T: fmt::Debug + fmt::Display>(
     color: bool,
     f: &mut dyn fmt::Write,
     with_context: bool,
+) -> fmt::Result {
+    diagnostic_display_message(d, &d.inner().to_string(), color, f, with_context)
+}
+
+/// Like [`diagnostic_display`], but showing `annotation_label` as the message.
+pub(crate) fn diagnostic_display_message<T: fmt::Debug + fmt::Display>(
+    d: &WithDiagnostic<T>,
+    annotation_label: &str,
+    color: bool,
+    f: &mut dyn fmt::Write,
+    with_context: bool,
 ) -> fmt::Result {
     write!(f, "{}", d.call_stack())?;
-    let annotation_label = format!("{}", d.inner());
     // I set color to false here to make the comparison easier with tests (coloring
     // adds in pretty strange unicode chars).
-    let display_list = d.0.diagnostic.get_display_list(&annotation_label, color);
+    let display_list = d.0.diagnostic.get_display_list(annotation_label, color);
     writeln!(f, "{}", display_list)?;
     // Print out the `Caused by:` trace (if exists) and rust backtrace (if enabled).
     // The trace printed comes from an [`anyhow::Error`] that is not a [`Diagnostic`].
@@ -59,6 +59,26 @@ pub struct Dialect {
     /// Are `f"{expression}"` strings supported?
     /// Disabled in all dialects by default.
     pub enable_f_strings: bool,
+    /// Can `f(**x)` be called with `x` other than a dictionary, e.g. a `struct`,
+    /// if the type of `x` knows how to convert itself to keyword arguments?
+    /// Disabled in all dialects by default.
+    pub enable_to_kwargs: bool,
+    /// Is `...` allowed as an expression, evaluating to the `Ellipsis` placeholder value,
+    /// e.g. in code generation templates or interface stub files?
+    /// It is always allowed when types are enabled.
+    /// Only enabled in [`Extended`](Dialect::Extended).
+    pub enable_ellipsis: bool,
+    /// Turn common mistakes into compile-time errors: assignments shadowing builtins,
+    /// private top-level variables which are never used,
+    /// and `load` statements after other statements.
+    /// Disabled in all dialects by default.
+    pub strict: bool,
+    /// Are duplicate keys allowed in dict literals, with the last value winning,
+    /// for legacy code which relies on it?
+    /// Otherwise duplicate constant keys are a compile-time error,
+    /// and other duplicate keys are a runtime error.
+    /// Disabled in all dialects by default.
+    pub enable_duplicate_dict_keys: bool,
     /// Like `#[non_exhaustive]`, but allows struct expression.
     ///
     /// [Explanation](https://github.com/rust-lang/rust-clippy/issues/6559).
@@ -87,6 +107,10 @@ impl Dialect {
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
         enable_f_strings: false,
+        enable_to_kwargs: false,
+        enable_ellipsis: false,
+        strict: false,
+        enable_duplicate_dict_keys: false,
         _non_exhaustive: (),
     };
 
@@ -100,6 +124,10 @@ impl Dialect {
         enable_load_reexport: true,
         enable_top_level_stmt: true,
         enable_f_strings: false,
+        enable_to_kwargs: false,
+        enable_ellipsis: true,
+        strict: false,
+        enable_duplicate_dict_keys: false,
         _non_exhaustive: (),
     };
 }
@@ -22,7 +22,10 @@ use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
 use crate::codemap::Span;
 use crate::diagnostic::diagnostic_display;
+use crate::diagnostic::diagnostic_display_message;
 use crate::diagnostic::WithDiagnostic;
+use crate::message::MessageCatalog;
+use crate::message::MessageTemplate;
 
 /// An error produced by starlark.
 ///
@@ -68,6 +71,18 @@ impl Error {
         self.0.into_inner()
     }
 
+    /// The underlying error, if it is of type `T`.
+    ///
+    /// Errors returned by native functions, for example a `Result<T, MyError>` returned
+    /// from a `#[starlark_module]` function, are preserved when the evaluation fails,
+    /// so the caller of `eval_module` can recover `MyError`.
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.kind().as_anyhow().downcast_ref()
+    }
+
     pub fn has_diagnostic(&self) -> bool {
         self.0.span().is_some() || !self.0.call_stack().is_empty()
     }
@@ -120,6 +135,53 @@ impl Error {
         self.0.set_call_stack(call_stack);
     }
 
+    /// Record the template this error's message is rendered from, so it can be translated.
+    pub fn with_message_template(mut self, message_template: MessageTemplate) -> Self {
+        self.0.set_message_template(message_template);
+        self
+    }
+
+    /// The message of this error as a template for translation.
+    ///
+    /// Errors without a specific template use the [`code`](ErrorKind::code) of their kind,
+    /// with the whole message as the only argument.
+    pub fn message_template(&self) -> MessageTemplate {
+        match self.0.message_template() {
+            Some(message_template) => message_template.clone(),
+            None => MessageTemplate::new(
+                self.kind().code(),
+                vec![self.kind().as_anyhow().to_string()],
+            ),
+        }
+    }
+
+    /// The message of this error translated by `catalog`, without the diagnostic information.
+    /// Falls back to the English message if the catalog has no template for it.
+    pub fn localized_message(&self, catalog: &dyn MessageCatalog) -> String {
+        self.message_template()
+            .localize(catalog)
+            .unwrap_or_else(|| self.without_diagnostic().to_string())
+    }
+
+    /// Format this error like `Display` does, with the message translated by `catalog`.
+    pub fn localized<'a>(&'a self, catalog: &'a dyn MessageCatalog) -> impl fmt::Display + 'a {
+        struct Localized<'a>(&'a Error, &'a dyn MessageCatalog);
+
+        impl fmt::Display for Localized<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let message = self.0.localized_message(self.1);
+                if self.0.has_diagnostic() {
+                    let with_context = f.alternate() && self.0.kind().source().is_some();
+                    diagnostic_display_message(&self.0.0, &message, false, f, with_context)
+                } else {
+                    f.write_str(&message)
+                }
+            }
+        }
+
+        Localized(self, catalog)
+    }
+
     /// Print an error to the stderr stream. If the error has diagnostic information it will use
     /// color-codes when printing.
     ///
@@ -188,6 +250,34 @@ pub enum ErrorKind {
 }
 
 impl ErrorKind {
+    /// Stable kebab-case identifier of the kind, e.g. `value`, used as the
+    /// [`MessageTemplate`] code of errors which do not provide a more specific one.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Fail(_) => "fail",
+            Self::StackOverflow(_) => "stack-overflow",
+            Self::Value(_) => "value",
+            Self::Function(_) => "function",
+            Self::Scope(_) => "scope",
+            Self::Lexer(_) => "lexer",
+            Self::Internal(_) => "internal",
+            Self::Other(_) => "other",
+        }
+    }
+
+    fn as_anyhow(&self) -> &anyhow::Error {
+        match self {
+            Self::Fail(e)
+            | Self::StackOverflow(e)
+            | Self::Value(e)
+            | Self::Function(e)
+            | Self::Scope(e)
+            | Self::Lexer(e)
+            | Self::Internal(e)
+            | Self::Other(e) => e,
+        }
+    }
+
     /// The source of the error, akin to `[std::error::Error::source]`
     pub fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
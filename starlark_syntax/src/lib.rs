@@ -45,6 +45,7 @@ pub mod golden_test_template;
 pub mod lexer;
 #[cfg(test)]
 mod lexer_tests;
+pub mod message;
 pub mod slice_vec_ext;
 pub mod span_display;
 pub mod syntax;
@@ -0,0 +1,112 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Translation of diagnostic messages.
+
+/// A diagnostic message as a stable code plus the arguments substituted into it,
+/// so a [`MessageCatalog`] can translate it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTemplate {
+    /// Stable kebab-case identifier of the template, e.g. `no-attr` or `unused-load`.
+    pub code: &'static str,
+    /// Arguments of the message, in the order of the `{0}`, `{1}`, ... placeholders.
+    pub args: Vec<String>,
+}
+
+impl MessageTemplate {
+    /// Create a template with the given code and arguments.
+    pub fn new(code: &'static str, args: Vec<String>) -> Self {
+        Self { code, args }
+    }
+
+    /// Render the message with the template `catalog` provides for [`code`](MessageTemplate::code),
+    /// or `None` if the catalog has no translation for it.
+    pub fn localize(&self, catalog: &dyn MessageCatalog) -> Option<String> {
+        Some(self.render(catalog.template(self.code)?))
+    }
+
+    /// Substitute the arguments for the `{0}`, `{1}`, ... placeholders of `template`.
+    /// Placeholders without a matching argument are kept verbatim.
+    pub fn render(&self, template: &str) -> String {
+        let mut res = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            res.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let arg = rest.find('}').and_then(|end| {
+                let arg = self.args.get(rest[1..end].parse::<usize>().ok()?)?;
+                Some((arg, end))
+            });
+            match arg {
+                Some((arg, end)) => {
+                    res.push_str(arg);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    res.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        res.push_str(rest);
+        res
+    }
+}
+
+/// Provider of translated diagnostic message templates.
+///
+/// The catalog is passed explicitly wherever messages are rendered, e.g. to
+/// [`Error::localized`](crate::Error::localized), so different evaluations
+/// can use different languages.
+///
+/// ```
+/// use starlark_syntax::message::MessageCatalog;
+/// use starlark_syntax::message::MessageTemplate;
+///
+/// struct German;
+///
+/// impl MessageCatalog for German {
+///     fn template(&self, code: &str) -> Option<&str> {
+///         match code {
+///             "no-attr" => Some("Objekt vom Typ `{0}` hat kein Attribut `{1}`"),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let message = MessageTemplate::new("no-attr", vec!["int".to_owned(), "foo".to_owned()]);
+/// assert_eq!(
+///     Some("Objekt vom Typ `int` hat kein Attribut `foo`"),
+///     message.localize(&German).as_deref()
+/// );
+/// ```
+pub trait MessageCatalog {
+    /// Template for messages with the given [`code`](MessageTemplate::code),
+    /// using `{0}`, `{1}`, ... for the arguments, or `None` to keep the English message.
+    fn template(&self, code: &str) -> Option<&str>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::MessageTemplate;
+
+    #[test]
+    fn test_render() {
+        let message = MessageTemplate::new("test", vec!["a".to_owned(), "{1}".to_owned()]);
+        assert_eq!("[{1}] a {2} {x} {", message.render("[{1}] {0} {2} {x} {"));
+    }
+}
@@ -18,6 +18,10 @@
 //! The AST of Starlark as [`AstModule`], along with a [`parse`](AstModule::parse) function.
 
 pub use module::AstModule;
+pub use node_at::AstBinding;
+pub use node_at::AstBindingKind;
+pub use node_at::AstNodeAt;
+pub use node_at::AstNodeKind;
 pub use parser::AstLoad;
 
 pub use crate::dialect::Dialect;
@@ -30,6 +34,7 @@ mod grammar_tests;
 pub mod grammar_util;
 mod lint_suppressions;
 pub mod module;
+mod node_at;
 pub mod parser;
 pub mod payload_map;
 pub mod state;
@@ -321,12 +321,23 @@ fn test_ellipsis() {
         "ellipsis",
         &Dialect {
             enable_types: DialectTypes::Disable,
+            enable_ellipsis: false,
             ..Dialect::Extended
         },
         &["x = ..."],
     );
 
     assert_eq!(parse("x = ..."), "x = ...\n");
+    assert_eq!(
+        parse_with_dialect(
+            "def f(x):\n  ...",
+            &Dialect {
+                enable_ellipsis: true,
+                ..Dialect::Standard
+            }
+        ),
+        "def f(x):\n  ...\n"
+    );
 }
 
 #[test]
@@ -135,6 +135,10 @@ pub trait AstModuleFields: Sized {
     fn dialect(&self) -> &Dialect;
 
     fn into_parts(self) -> (CodeMap, AstStmt, Dialect, bool);
+
+    /// Replace the top-level statement, keeping the rest of the module.
+    /// The new statement is not validated against the dialect.
+    fn map_statement(self, f: impl FnOnce(AstStmt) -> AstStmt) -> Self;
 }
 
 impl AstModuleFields for AstModule {
@@ -153,6 +157,13 @@ impl AstModuleFields for AstModule {
     fn into_parts(self) -> (CodeMap, AstStmt, Dialect, bool) {
         (self.codemap, self.statement, self.dialect, self.typecheck)
     }
+
+    fn map_statement(self, f: impl FnOnce(AstStmt) -> AstStmt) -> Self {
+        AstModule {
+            statement: f(self.statement),
+            ..self
+        }
+    }
 }
 
 impl AstModule {
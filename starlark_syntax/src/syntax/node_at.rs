@@ -0,0 +1,465 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Find the syntax node and the variables in scope at a position.
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::codemap::Pos;
+use crate::codemap::Span;
+use crate::syntax::ast::ArgumentP;
+use crate::syntax::ast::AstAssignIdent;
+use crate::syntax::ast::AstAssignTarget;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Clause;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::ForClause;
+use crate::syntax::ast::Stmt;
+use crate::syntax::uniplate::Visit;
+use crate::syntax::AstModule;
+
+/// What kind of syntax is at a position, as returned by [`AstModule::node_at`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AstNodeKind {
+    /// The position is not inside any statement, e.g. on a blank line at the top level.
+    Module,
+    /// A statement, with the position not inside any of its expressions.
+    Statement,
+    /// An expression which is not described by a more specific kind.
+    Expression,
+    /// A literal, e.g. `"x"` or `1`.
+    Literal,
+    /// A variable read, e.g. `x` in `f(x)`.
+    Identifier(String),
+    /// A variable binding, e.g. `x` in `x = 1`, a function name, a parameter,
+    /// a loop variable or a symbol bound by `load`.
+    Binding(String),
+    /// An attribute name, e.g. `y` in `x.y`.
+    Attribute(String),
+    /// A named argument, e.g. `y` in `f(y = 1)`.
+    ArgumentName(String),
+}
+
+/// How a variable in scope was bound.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Hash)]
+pub enum AstBindingKind {
+    /// Assigned at the top level of the module, or a top-level function.
+    Module,
+    /// Bound by a `load` statement.
+    Load,
+    /// A parameter of an enclosing function or lambda.
+    Parameter,
+    /// Assigned inside an enclosing function.
+    Local,
+    /// Bound by a `for` clause of an enclosing comprehension.
+    Comprehension,
+}
+
+/// A variable in scope at a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AstBinding {
+    /// Name of the variable.
+    pub name: String,
+    /// Where the variable is (first) bound in its scope.
+    pub span: FileSpan,
+    /// How the variable was bound.
+    pub kind: AstBindingKind,
+}
+
+/// The innermost syntax node at a position, and the variables in scope there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AstNodeAt {
+    /// What kind of node is at the position.
+    pub kind: AstNodeKind,
+    /// Span of that node.
+    pub span: FileSpan,
+    /// Variables in scope, innermost scope first. Where a name is bound in several
+    /// enclosing scopes, only the innermost binding is listed.
+    ///
+    /// Builtins and globals provided by the host are not included.
+    pub bindings: Vec<AstBinding>,
+}
+
+struct Finder<'a> {
+    codemap: &'a CodeMap,
+    pos: Pos,
+    kind: AstNodeKind,
+    span: Span,
+    /// Scopes entered so far, outermost first.
+    scopes: Vec<Vec<AstBinding>>,
+}
+
+impl<'a> Finder<'a> {
+    fn found(&mut self, kind: AstNodeKind, span: Span) {
+        self.kind = kind;
+        self.span = span;
+    }
+
+    fn binding(&self, x: &AstAssignIdent, kind: AstBindingKind) -> AstBinding {
+        AstBinding {
+            name: x.ident.clone(),
+            span: self.codemap.file_span(x.span),
+            kind,
+        }
+    }
+
+    fn push_scope(&mut self, bindings: Vec<AstBinding>) {
+        self.scopes.push(bindings);
+    }
+
+    fn param_bindings(&self, params: &[AstParameter]) -> Vec<AstBinding> {
+        params
+            .iter()
+            .filter_map(|p| p.ident())
+            .map(|x| self.binding(x, AstBindingKind::Parameter))
+            .collect()
+    }
+
+    /// Bindings made by statements in a scope, not descending into nested functions.
+    fn stmt_bindings(&self, x: &AstStmt, kind: AstBindingKind, res: &mut Vec<AstBinding>) {
+        match &**x {
+            Stmt::Assign(x) => x.lhs.visit_lvalue(|x| res.push(self.binding(x, kind))),
+            Stmt::AssignModify(lhs, _, _) => lhs.visit_lvalue(|x| res.push(self.binding(x, kind))),
+            Stmt::For(x) => x.var.visit_lvalue(|x| res.push(self.binding(x, kind))),
+            Stmt::Def(x) => {
+                res.push(self.binding(&x.name, kind));
+                return;
+            }
+            Stmt::Load(x) => {
+                for arg in &x.args {
+                    res.push(self.binding(&arg.local, AstBindingKind::Load));
+                }
+            }
+            _ => {}
+        }
+        x.visit_stmt(|x| self.stmt_bindings(x, kind, res));
+    }
+
+    /// Check whether the position is in a bound identifier.
+    fn lvalue(&mut self, x: &AstAssignTarget) {
+        x.visit_lvalue(|x| {
+            if x.span.contains(self.pos) {
+                self.found(AstNodeKind::Binding(x.ident.clone()), x.span);
+            }
+        });
+    }
+
+    fn params(&mut self, params: &[AstParameter]) {
+        for p in params {
+            if let Some(x) = p.ident() {
+                if x.span.contains(self.pos) {
+                    self.found(AstNodeKind::Binding(x.ident.clone()), x.span);
+                }
+            }
+        }
+    }
+
+    fn stmt(&mut self, x: &AstStmt) {
+        if !x.span.contains(self.pos) {
+            return;
+        }
+        match &**x {
+            Stmt::Statements(_) => {}
+            _ => self.found(AstNodeKind::Statement, x.span),
+        }
+        match &**x {
+            Stmt::Assign(assign) => self.lvalue(&assign.lhs),
+            Stmt::AssignModify(lhs, _, _) => self.lvalue(lhs),
+            Stmt::For(x) => self.lvalue(&x.var),
+            Stmt::Def(def) => {
+                if def.name.span.contains(self.pos) {
+                    self.found(AstNodeKind::Binding(def.name.ident.clone()), def.name.span);
+                }
+                // Default values and types are evaluated in the enclosing scope.
+                for p in &def.params {
+                    p.visit_expr(|x| self.expr(x));
+                }
+                if let Some(x) = &def.return_type {
+                    self.expr(&x.expr);
+                }
+                if def.body.span.contains(self.pos)
+                    || def
+                        .params
+                        .iter()
+                        .any(|p| p.ident().is_some_and(|x| x.span.contains(self.pos)))
+                {
+                    let mut bindings = self.param_bindings(&def.params);
+                    self.stmt_bindings(&def.body, AstBindingKind::Local, &mut bindings);
+                    self.push_scope(bindings);
+                    self.params(&def.params);
+                    self.stmt(&def.body);
+                }
+                return;
+            }
+            Stmt::Load(load) => {
+                if load.module.span.contains(self.pos) {
+                    self.found(AstNodeKind::Literal, load.module.span);
+                }
+                for arg in &load.args {
+                    if arg.local.span.contains(self.pos) {
+                        self.found(
+                            AstNodeKind::Binding(arg.local.ident.clone()),
+                            arg.local.span,
+                        );
+                    } else if arg.their.span.contains(self.pos) {
+                        self.found(AstNodeKind::Literal, arg.their.span);
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+        x.visit_children(|x| match x {
+            Visit::Stmt(x) => self.stmt(x),
+            Visit::Expr(x) => self.expr(x),
+        });
+    }
+
+    fn comprehension(&mut self, for_: &ForClause, clauses: &[Clause], body: &[&AstExpr]) {
+        // The first iterable is evaluated in the enclosing scope.
+        self.expr(&for_.over);
+        if for_.over.span.contains(self.pos) {
+            return;
+        }
+        let mut bindings = Vec::new();
+        let mut vars = vec![&for_.var];
+        vars.extend(clauses.iter().filter_map(|c| match c {
+            Clause::For(x) => Some(&x.var),
+            Clause::If(_) => None,
+        }));
+        for var in &vars {
+            var.visit_lvalue(|x| bindings.push(self.binding(x, AstBindingKind::Comprehension)));
+        }
+        self.push_scope(bindings);
+        for var in vars {
+            self.lvalue(var);
+        }
+        for clause in clauses {
+            match clause {
+                Clause::For(x) => self.expr(&x.over),
+                Clause::If(x) => self.expr(x),
+            }
+        }
+        for x in body {
+            self.expr(x);
+        }
+    }
+
+    fn expr(&mut self, x: &AstExpr) {
+        if !x.span.contains(self.pos) {
+            return;
+        }
+        match &**x {
+            Expr::Identifier(ident) => {
+                self.found(AstNodeKind::Identifier(ident.ident.clone()), x.span);
+                return;
+            }
+            Expr::Literal(_) => {
+                self.found(AstNodeKind::Literal, x.span);
+                return;
+            }
+            _ => self.found(AstNodeKind::Expression, x.span),
+        }
+        match &**x {
+            Expr::Dot(_, attr) if attr.span.contains(self.pos) => {
+                self.found(AstNodeKind::Attribute(attr.node.clone()), attr.span);
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    if let ArgumentP::Named(name, _) = &arg.node {
+                        if name.span.contains(self.pos) {
+                            self.found(AstNodeKind::ArgumentName(name.node.clone()), name.span);
+                        }
+                    }
+                }
+            }
+            Expr::Lambda(lambda) => {
+                for p in &lambda.params {
+                    p.visit_expr(|x| self.expr(x));
+                }
+                let bindings = self.param_bindings(&lambda.params);
+                self.push_scope(bindings);
+                self.params(&lambda.params);
+                self.expr(&lambda.body);
+                return;
+            }
+            Expr::ListComprehension(body, for_, clauses) => {
+                self.comprehension(for_, clauses, &[body]);
+                return;
+            }
+            Expr::DictComprehension(body, for_, clauses) => {
+                self.comprehension(for_, clauses, &[&body.0, &body.1]);
+                return;
+            }
+            _ => {}
+        }
+        x.visit_expr(|x| self.expr(x));
+    }
+
+    fn bindings(self) -> Vec<AstBinding> {
+        let mut res: Vec<AstBinding> = Vec::new();
+        for scope in self.scopes.into_iter().rev() {
+            for b in scope {
+                if !res.iter().any(|r| r.name == b.name) {
+                    res.push(b);
+                }
+            }
+        }
+        res
+    }
+}
+
+impl AstModule {
+    /// Find the innermost syntax node at a byte offset, together with the variables
+    /// bound in the module which are in scope there.
+    ///
+    /// Returns `None` if the position is outside the module source.
+    ///
+    /// ```
+    /// use starlark_syntax::codemap::Pos;
+    /// use starlark_syntax::syntax::AstModule;
+    /// use starlark_syntax::syntax::AstNodeKind;
+    /// use starlark_syntax::syntax::Dialect;
+    ///
+    /// let source = "x = 1\ndef f(y):\n    return x + y\n";
+    /// let ast = AstModule::parse("a.star", source.to_owned(), &Dialect::Standard).unwrap();
+    /// let node = ast
+    ///     .node_at(Pos::new(source.rfind('y').unwrap() as u32))
+    ///     .unwrap();
+    /// assert_eq!(AstNodeKind::Identifier("y".to_owned()), node.kind);
+    /// assert_eq!(
+    ///     vec!["y", "x", "f"],
+    ///     node.bindings
+    ///         .iter()
+    ///         .map(|b| b.name.as_str())
+    ///         .collect::<Vec<_>>()
+    /// );
+    /// ```
+    pub fn node_at(&self, pos: Pos) -> Option<AstNodeAt> {
+        if pos.get() as usize > self.codemap.source().len() {
+            return None;
+        }
+        let mut module = Vec::new();
+        let mut finder = Finder {
+            codemap: &self.codemap,
+            pos,
+            kind: AstNodeKind::Module,
+            span: self.codemap.full_span(),
+            scopes: Vec::new(),
+        };
+        finder.stmt_bindings(&self.statement, AstBindingKind::Module, &mut module);
+        finder.push_scope(module);
+        finder.stmt(&self.statement);
+        let kind = finder.kind.clone();
+        let span = self.codemap.file_span(finder.span);
+        Some(AstNodeAt {
+            kind,
+            span,
+            bindings: finder.bindings(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax::Dialect;
+
+    fn node_at(source: &str) -> (AstNodeKind, String, Vec<(String, AstBindingKind)>) {
+        let pos = source.find('^').unwrap();
+        let source = source.replacen('^', "", 1);
+        let ast = AstModule::parse("a.star", source, &Dialect::Extended).unwrap();
+        let node = ast.node_at(Pos::new(pos as u32)).unwrap();
+        (
+            node.kind,
+            node.span.source_span().to_owned(),
+            node.bindings
+                .into_iter()
+                .map(|b| (b.name, b.kind))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_node_kinds() {
+        let src = "load('m.star', 'a')\nx = a.^foo(key = 1)";
+        assert_eq!(AstNodeKind::Attribute("foo".to_owned()), node_at(src).0);
+        assert_eq!(
+            (
+                AstNodeKind::ArgumentName("key".to_owned()),
+                "key".to_owned()
+            ),
+            {
+                let (k, s, _) = node_at("f(^key = 1)");
+                (k, s)
+            }
+        );
+        assert_eq!(
+            AstNodeKind::Binding("a".to_owned()),
+            node_at("load('m.star', '^a')").0
+        );
+        assert_eq!(AstNodeKind::Literal, node_at("load('^m.star', 'a')").0);
+        assert_eq!(
+            AstNodeKind::Binding("y".to_owned()),
+            node_at("x, ^y = 1, 2").0
+        );
+        assert_eq!(AstNodeKind::Statement, node_at("^pass").0);
+        assert_eq!(AstNodeKind::Module, node_at("x = 1\n^\ny = 2").0);
+    }
+
+    #[test]
+    fn test_scopes() {
+        let (kind, span, bindings) = node_at(
+            r#"
+load("m.star", "l")
+x = 1
+def f(x, *args):
+    y = 2
+    return [z for z in args if ^z]
+"#,
+        );
+        assert_eq!(AstNodeKind::Identifier("z".to_owned()), kind);
+        assert_eq!("z", span);
+        assert_eq!(
+            vec![
+                ("z".to_owned(), AstBindingKind::Comprehension),
+                ("x".to_owned(), AstBindingKind::Parameter),
+                ("args".to_owned(), AstBindingKind::Parameter),
+                ("y".to_owned(), AstBindingKind::Local),
+                ("l".to_owned(), AstBindingKind::Load),
+                ("f".to_owned(), AstBindingKind::Module),
+            ],
+            bindings
+        );
+    }
+
+    #[test]
+    fn test_default_in_enclosing_scope() {
+        let (_, _, bindings) = node_at("def f(x = ^y):\n    z = 1\n");
+        assert_eq!(vec![("f".to_owned(), AstBindingKind::Module)], bindings);
+    }
+
+    #[test]
+    fn test_outside() {
+        let ast = AstModule::parse("a.star", "x".to_owned(), &Dialect::Standard).unwrap();
+        assert!(ast.node_at(Pos::new(2)).is_none());
+    }
+}
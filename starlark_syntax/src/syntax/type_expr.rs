@@ -15,6 +15,10 @@
  * limitations under the License.
  */
 
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
 use crate::codemap::CodeMap;
 use crate::codemap::Spanned;
 use crate::diagnostic::WithDiagnostic;
@@ -25,6 +29,7 @@ use crate::syntax::ast::AstLiteral;
 use crate::syntax::ast::AstPayload;
 use crate::syntax::ast::BinOp;
 use crate::syntax::ast::ExprP;
+use crate::syntax::ast::TypeExprP;
 
 #[derive(Debug, thiserror::Error)]
 pub enum TypeExprUnpackError {
@@ -74,6 +79,7 @@ pub enum TypeExprUnpackP<'a, P: AstPayload> {
     ),
     /// List argument in `typing.Callable[[int], str]`.
     List(Vec<Spanned<TypeExprUnpackP<'a, P>>>),
+    /// `int | str | None`, flattened into a single union.
     Union(Vec<Spanned<TypeExprUnpackP<'a, P>>>),
     Tuple(Vec<Spanned<TypeExprUnpackP<'a, P>>>),
     Literal(Spanned<&'a str>),
@@ -236,11 +242,17 @@ impl<'a, P: AstPayload> TypeExprUnpackP<'a, P> {
             ExprP::Plus(..) => err("plus"),
             ExprP::BitNot(..) => err("bit not"),
             ExprP::Op(a, BinOp::BitOr, b) => {
-                let a = TypeExprUnpackP::unpack(a, codemap)?;
-                let b = TypeExprUnpackP::unpack(b, codemap)?;
+                let mut xs = Vec::new();
+                for x in [a, b] {
+                    let x = TypeExprUnpackP::unpack(x, codemap)?;
+                    match x.node {
+                        TypeExprUnpackP::Union(ys) => xs.extend(ys),
+                        _ => xs.push(x),
+                    }
+                }
                 Ok(Spanned {
                     span,
-                    node: TypeExprUnpackP::Union(vec![a, b]),
+                    node: TypeExprUnpackP::Union(xs),
                 })
             }
             ExprP::Op(..) => err("bin op except `|`"),
@@ -269,3 +281,116 @@ impl<'a, P: AstPayload> TypeExprUnpackP<'a, P> {
         }
     }
 }
+
+impl<P: AstPayload> TypeExprP<P> {
+    /// Unpack the type expression into a structured form.
+    pub fn unpack<'a>(
+        &'a self,
+        codemap: &CodeMap,
+    ) -> Result<Spanned<TypeExprUnpackP<'a, P>>, WithDiagnostic<TypeExprUnpackError>> {
+        TypeExprUnpackP::unpack(&self.expr, codemap)
+    }
+}
+
+impl<'a, P: AstPayload> Display for TypePathP<'a, P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first.node.ident)?;
+        for x in &self.rem {
+            write!(f, ".{}", x.node)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the items separated by `sep`.
+fn fmt_items<'a, P: AstPayload>(
+    f: &mut Formatter<'_>,
+    xs: &[Spanned<TypeExprUnpackP<'a, P>>],
+    sep: &str,
+) -> fmt::Result {
+    for (i, x) in xs.iter().enumerate() {
+        if i != 0 {
+            f.write_str(sep)?;
+        }
+        write!(f, "{}", x.node)?;
+    }
+    Ok(())
+}
+
+/// Canonical formatting of a type, e.g. `dict[str, int | None]`.
+impl<'a, P: AstPayload> Display for TypeExprUnpackP<'a, P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExprUnpackP::Ellipsis => f.write_str("..."),
+            TypeExprUnpackP::Path(path) => write!(f, "{path}"),
+            TypeExprUnpackP::Index(a, i) => write!(f, "{}[{}]", a.node.ident, i.node),
+            TypeExprUnpackP::Index2(a, i0, i1) => {
+                write!(f, "{}[{}, {}]", a.node, i0.node, i1.node)
+            }
+            TypeExprUnpackP::List(xs) => {
+                f.write_str("[")?;
+                fmt_items(f, xs, ", ")?;
+                f.write_str("]")
+            }
+            TypeExprUnpackP::Union(xs) => fmt_items(f, xs, " | "),
+            TypeExprUnpackP::Tuple(xs) => {
+                f.write_str("(")?;
+                fmt_items(f, xs, ", ")?;
+                if xs.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            TypeExprUnpackP::Literal(x) => write!(f, "{:?}", x.node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::ast::AstNoPayload;
+    use crate::syntax::ast::StmtP;
+    use crate::syntax::type_expr::TypeExprUnpackP;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    /// Parse `x: <ty> = None` and pass the unpacked type to `f`.
+    fn with_unpacked<R>(ty: &str, f: impl FnOnce(&TypeExprUnpackP<AstNoPayload>) -> R) -> R {
+        let module =
+            AstModule::parse("x.star", format!("x: {ty} = None"), &Dialect::Extended).unwrap();
+        let StmtP::Assign(assign) = &module.statement.node else {
+            panic!("expecting assignment: {}", module.statement.node);
+        };
+        let ty = assign.ty.as_ref().unwrap().unpack(&module.codemap).unwrap();
+        f(&ty.node)
+    }
+
+    #[test]
+    fn test_display() {
+        for ty in [
+            "int",
+            "typing.Any",
+            "list[int]",
+            "dict[str, MyStruct]",
+            "int | None",
+            "list[str | int] | None",
+            "typing.Callable[[int, str], bool]",
+            "tuple[int, ...]",
+            "(int, str)",
+            "(int,)",
+        ] {
+            assert_eq!(ty, with_unpacked(ty, |t| t.to_string()));
+        }
+        assert_eq!("int | str", with_unpacked("[int, str]", |t| t.to_string()));
+    }
+
+    #[test]
+    fn test_union_flattened() {
+        with_unpacked("(int | str) | (bool | None)", |ty| {
+            let TypeExprUnpackP::Union(xs) = ty else {
+                panic!("expecting union: {ty}");
+            };
+            assert_eq!(4, xs.len());
+        });
+    }
+}
@@ -17,11 +17,12 @@
 
 //! AST for parsed starlark files.
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use thiserror::Error;
 
 use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
 use crate::eval_exception::EvalException;
 use crate::syntax::ast::Argument;
 use crate::syntax::ast::AstArgument;
@@ -45,6 +46,8 @@ enum ValidateError {
     ReturnOutsideDef,
     #[error("`load` must only occur at the top of a module")]
     LoadNotTop,
+    #[error("`load` must occur before other statements in strict mode")]
+    LoadAfterStatement,
     #[error("`if` cannot be used outside `def` in this dialect")]
     NoTopLevelIf,
     #[error("`for` cannot be used outside `def` in this dialect")]
@@ -69,8 +72,8 @@ enum ArgumentDefinitionOrderError {
     PositionalThenNonPositional,
     #[error("named argument after *args or **kwargs")]
     NamedArgumentAfterStars,
-    #[error("repeated named argument")]
-    RepeatedNamed,
+    #[error("repeated named argument `{0}`, first used at {1}")]
+    RepeatedNamed(String, FileSpan),
     #[error("Args array after another args or kwargs")]
     ArgsArrayAfterArgsOrKwargs,
     #[error("Multiple kwargs dictionary in arguments")]
@@ -100,7 +103,7 @@ impl Expr {
         };
 
         let mut stage = ArgsStage::Positional;
-        let mut named_args = HashSet::new();
+        let mut named_args = HashMap::new();
         for arg in &args {
             match &arg.node {
                 Argument::Positional(_) => {
@@ -117,9 +120,15 @@ impl Expr {
                             arg.span,
                             ArgumentDefinitionOrderError::NamedArgumentAfterStars,
                         );
-                    } else if !named_args.insert(&n.node) {
+                    } else if let Some(first) = named_args.insert(&n.node, n.span) {
                         // Check the names are distinct
-                        return err(n.span, ArgumentDefinitionOrderError::RepeatedNamed);
+                        return err(
+                            n.span,
+                            ArgumentDefinitionOrderError::RepeatedNamed(
+                                n.node.clone(),
+                                codemap.file_span(first),
+                            ),
+                        );
                     } else {
                         stage = ArgsStage::Named;
                     }
@@ -206,7 +215,7 @@ impl Stmt {
 
         fn expr(expr: &AstExpr, dialect: &Dialect, codemap: &CodeMap) -> Result<(), EvalException> {
             if let Expr::Literal(AstLiteral::Ellipsis) = &expr.node {
-                if dialect.enable_types == DialectTypes::Disable {
+                if !dialect.enable_ellipsis && dialect.enable_types == DialectTypes::Disable {
                     return Err(EvalException::new_anyhow(
                         ValidateError::Ellipsis.into(),
                         expr.span,
@@ -217,8 +226,37 @@ impl Stmt {
             Ok(())
         }
 
+        // In strict mode loads must come first, after an optional docstring.
+        fn load_first(codemap: &CodeMap, stmt: &AstStmt) -> Result<(), EvalException> {
+            let stmts = match &stmt.node {
+                Stmt::Statements(stmts) => stmts.as_slice(),
+                _ => std::slice::from_ref(stmt),
+            };
+            let mut seen_other = false;
+            for (i, stmt) in stmts.iter().enumerate() {
+                match &stmt.node {
+                    Stmt::Load(..) if seen_other => {
+                        return Err(EvalException::new_anyhow(
+                            ValidateError::LoadAfterStatement.into(),
+                            stmt.span,
+                            codemap,
+                        ));
+                    }
+                    Stmt::Load(..) => {}
+                    Stmt::Expression(x)
+                        if i == 0 && matches!(x.node, Expr::Literal(AstLiteral::String(_))) => {}
+                    _ => seen_other = true,
+                }
+            }
+            Ok(())
+        }
+
         f(codemap, dialect, stmt, true, false, false)?;
 
+        if dialect.strict {
+            load_first(codemap, stmt)?;
+        }
+
         stmt.visit_expr_result(|x| expr(x, dialect, codemap))?;
 
         Ok(())